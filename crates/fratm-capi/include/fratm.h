@@ -0,0 +1,44 @@
+/* FratmScript compiler, C interface. Generated by build.rs: do not edit. */
+
+#ifndef FRATM_H
+#define FRATM_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Compiles `source` with the options in `options_json` and returns the
+// result as a JSON string, to be released with `fratm_string_free`.
+//
+// `options_json` may be NULL for the default options. Invalid UTF-8 or
+// JSON gives a `{"success":false,"error":{"message":...}}` result; the
+// function never returns NULL.
+//
+// # Safety
+//
+// `source` must be a valid NUL-terminated string, and `options_json`
+// either NULL or a valid NUL-terminated string.
+char *fratm_compile(const char *source, const char *options_json);
+
+// Releases a string returned by this library. NULL is ignored.
+//
+// # Safety
+//
+// `string` must come from a function of this library and not have been
+// released already.
+void fratm_string_free(char *string);
+
+// The compiler version, such as `0.1.0`. The string is static: don't
+// release it.
+const char *fratm_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FRATM_H */
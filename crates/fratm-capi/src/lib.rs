@@ -0,0 +1,213 @@
+//! C ABI for embedding FratmScript
+//!
+//! Builds `libfratm` (shared and static) for C, C++, Swift or any host with
+//! a C FFI. The header, `include/fratm.h`, is generated from this file:
+//! after changing the exported functions, update it with
+//! `FRATM_BLESS=1 cargo test -p fratm-capi`.
+//!
+//! Options and results are JSON strings, so new options don't change the
+//! ABI:
+//!
+//! ```c
+//! #include "fratm.h"
+//!
+//! char *result = fratm_compile("stamm a dì(42)", "{\"target\": \"es2015\"}");
+//! // {"code":"console.log(42);\n","sourceMap":null,"success":true,"warnings":[]}
+//! fratm_string_free(result);
+//! ```
+//!
+//! Options are those of the playground (`sourceMap`, `filename`,
+//! `outputFilename`, `minify`, `optimize`, `target`, `moduleFormat`, `locale`, `style`,
+//! `banner`, `footer`, `useStrict`, `comments`, `assertions`, and `limits`
+//! for untrusted sources), all optional. An unknown option is an error.
+//! A source that doesn't compile gives `{"success":false,"error":{...}}`,
+//! the error being the serialized `Diagnostic` (code, message, span,
+//! labels, notes, suggestion).
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+
+use fratm_core::errors::Locale;
+use fratm_core::limits::Limits;
+use fratm_core::{Assertions, CodegenStyle, CompileOptions, ModuleFormat, Target};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Options accepted as JSON, mirroring `CompileOptions`
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+struct JsonOptions {
+    source_map: bool,
+    filename: Option<String>,
+    output_filename: Option<String>,
+    minify: bool,
+    optimize: bool,
+    target: Target,
+    module_format: ModuleFormat,
+    locale: Locale,
+    style: CodegenStyle,
+    banner: Option<String>,
+    footer: Option<String>,
+    use_strict: bool,
+    comments: bool,
+    assertions: Assertions,
+    limits: Limits,
+}
+
+impl JsonOptions {
+    fn build(&self) -> CompileOptions {
+        let mut builder = CompileOptions::builder()
+            .source_map(self.source_map)
+            .minify(self.minify)
+            .optimize(self.optimize)
+            .target(self.target)
+            .module_format(self.module_format)
+            .locale(self.locale)
+            .style(self.style)
+            .use_strict(self.use_strict)
+            .comments(self.comments)
+            .assertions(self.assertions)
+            .limits(self.limits);
+        if let Some(filename) = &self.filename {
+            builder = builder.filename(filename);
+        }
+        if let Some(output_filename) = &self.output_filename {
+            builder = builder.output_filename(output_filename);
+        }
+        if let Some(banner) = &self.banner {
+            builder = builder.banner(banner);
+        }
+        if let Some(footer) = &self.footer {
+            builder = builder.footer(footer);
+        }
+        builder.build()
+    }
+}
+
+/// Compiles `source` with the options in `options_json` and returns the
+/// result as a JSON string, to be released with `fratm_string_free`.
+///
+/// `options_json` may be NULL for the default options. Invalid UTF-8 or
+/// JSON gives a `{"success":false,"error":{"message":...}}` result; the
+/// function never returns NULL.
+///
+/// # Safety
+///
+/// `source` must be a valid NUL-terminated string, and `options_json`
+/// either NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fratm_compile(source: *const c_char, options_json: *const c_char) -> *mut c_char {
+    let source = if source.is_null() { None } else { CStr::from_ptr(source).to_str().ok() };
+    let options = if options_json.is_null() { Some("{}") } else { CStr::from_ptr(options_json).to_str().ok() };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| compile(source, options)))
+        .unwrap_or_else(|_| failure("internal compiler error"));
+    into_c_string(result.to_string())
+}
+
+/// Releases a string returned by this library. NULL is ignored.
+///
+/// # Safety
+///
+/// `string` must come from a function of this library and not have been
+/// released already.
+#[no_mangle]
+pub unsafe extern "C" fn fratm_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// The compiler version, such as `0.1.0`. The string is static: don't
+/// release it.
+#[no_mangle]
+pub extern "C" fn fratm_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+fn compile(source: Option<&str>, options: Option<&str>) -> Value {
+    let Some(source) = source else { return failure("source is NULL or not valid UTF-8") };
+    let Some(options) = options else { return failure("options are not valid UTF-8") };
+    let options: JsonOptions = match serde_json::from_str(options) {
+        Ok(options) => options,
+        Err(e) => return failure(&format!("invalid options: {}", e)),
+    };
+    match fratm_core::compile(source, options.build()) {
+        Ok(result) => json!({
+            "success": true,
+            "code": result.code,
+            "sourceMap": result.source_map.map(|map| map.to_json()),
+            "warnings": result.warnings,
+        }),
+        Err(e) => {
+            let mut error = serde_json::to_value(e.diagnostic()).unwrap_or_else(|_| json!({}));
+            error["message"] = e.localized(options.locale).into();
+            json!({ "success": false, "error": error })
+        }
+    }
+}
+
+fn failure(message: &str) -> Value {
+    json!({ "success": false, "error": { "message": message } })
+}
+
+fn into_c_string(string: String) -> *mut c_char {
+    // JSON escapes control characters, so there are no NULs to reject
+    CString::new(string).unwrap_or_default().into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(source: &str, options: Option<&str>) -> Value {
+        let source = CString::new(source).unwrap();
+        let options = options.map(|options| CString::new(options).unwrap());
+        unsafe {
+            let result = fratm_compile(source.as_ptr(), options.as_ref().map_or(std::ptr::null(), |options| options.as_ptr()));
+            let value = serde_json::from_str(CStr::from_ptr(result).to_str().unwrap()).unwrap();
+            fratm_string_free(result);
+            value
+        }
+    }
+
+    #[test]
+    fn test_compile() {
+        let result = call("mann for chist è x = 2 ** 3", Some(r#"{"target": "es2015", "moduleFormat": "commonjs", "sourceMap": true}"#));
+        assert_eq!(result["success"], true);
+        assert_eq!(result["code"], "const x = Math.pow(2, 3);\nexports.x = x;\n");
+        assert!(result["sourceMap"].is_string());
+
+        let result = call("stamm a dì(1)", None);
+        assert_eq!(result["code"], "console.log(1);\n");
+    }
+
+    #[test]
+    fn test_header() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/fratm.h"));
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/include/fratm.h");
+        if std::env::var_os("FRATM_BLESS").is_some() {
+            std::fs::write(path, generated).unwrap();
+        }
+        assert!(std::fs::read_to_string(path).unwrap() == generated, "include/fratm.h is out of date: run FRATM_BLESS=1 cargo test -p fratm-capi");
+    }
+
+    #[test]
+    fn test_errors() {
+        let result = call("chist x = 1", Some(r#"{"locale": "en"}"#));
+        assert_eq!(result["success"], false);
+        assert_eq!(result["error"]["span"]["column"], 7);
+        assert!(result["error"]["message"].as_str().unwrap().contains("must be followed by 'è'"));
+
+        let result = call("", Some(r#"{"target": "es5"}"#));
+        assert!(result["error"]["message"].as_str().unwrap().starts_with("invalid options"));
+        let result = call("", Some(r#"{"limit": {"maxSourceBytes": 10}}"#));
+        assert!(result["error"]["message"].as_str().unwrap().contains("unknown field `limit`"));
+
+        let result = call("chist è x = 42", Some(r#"{"limits": {"maxSourceBytes": 10}}"#));
+        assert_eq!(result["error"]["code"], "too-large");
+
+        let result = unsafe { fratm_compile(std::ptr::null(), std::ptr::null()) };
+        assert!(!result.is_null());
+        unsafe { fratm_string_free(result) };
+    }
+}
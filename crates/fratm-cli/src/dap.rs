@@ -0,0 +1,448 @@
+//! `fratm dap`: a Debug Adapter Protocol server for `.fratm` programs
+//!
+//! Editors start it and speak DAP on stdin/stdout. `launch { program,
+//! args?, cwd?, stopOnEntry? }` compiles the program with a source map and
+//! starts it under `node --inspect-brk`; the adapter then drives Node's
+//! inspector, moving breakpoints from `.fratm` lines to the JavaScript
+//! through the map, and stack frames back. What the program prints comes
+//! as `output` events, its stack traces pointing at the `.fratm` file.
+//!
+//! Requests: initialize, launch, setBreakpoints, setExceptionBreakpoints,
+//! configurationDone, threads, stackTrace, scopes, variables, evaluate,
+//! continue, next, stepIn, stepOut, pause, terminate and disconnect. Only
+//! the launched file takes breakpoints, and `evaluate` compiles what it is
+//! given as FratmScript when it can.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use fratm_core::errors::Locale;
+use fratm_core::render::{render_diagnostic, RenderOptions};
+use fratm_core::sourcemap::{Mapping, SourceMap};
+use fratm_core::{compile, CompileOptions};
+use serde_json::{json, Value};
+
+use crate::inspector::Inspector;
+
+/// The program runs on one thread, as DAP sees it
+const THREAD: u64 = 1;
+
+enum Message {
+    Request(Value),
+    Inspector(String, Value),
+    Output(&'static str, String),
+    /// Node ran the program and waits for the debugger to let go
+    Detach,
+    /// Node exited, and all it printed was sent
+    Finished,
+    /// The editor hung up
+    Closed,
+}
+
+/// The launched program
+struct Program {
+    /// The `.fratm` file, in full
+    path: PathBuf,
+    /// The compiled file, and its URL for the inspector
+    js_path: PathBuf,
+    url: String,
+    /// Its id in the inspector, once Node parsed it
+    script: Option<String>,
+    map: SourceMap,
+    mappings: Vec<Mapping>,
+    child: Child,
+    inspector: Arc<Inspector>,
+    /// Inspector ids of the breakpoints set
+    breakpoints: Vec<String>,
+}
+
+impl Program {
+    /// Where a breakpoint on `line` (1-based) stops: the first line from
+    /// there with code, and the start of its JavaScript (0-based).
+    fn position(&self, line: usize) -> Option<(usize, usize, usize)> {
+        self.mappings.iter().filter(|m| m.src_line + 1 >= line).min_by_key(|m| (m.src_line, m.gen_line, m.gen_col)).map(|m| (m.src_line + 1, m.gen_line, m.gen_col))
+    }
+}
+
+struct Session {
+    messages: mpsc::Sender<Message>,
+    locale: Locale,
+    seq: u64,
+    program: Option<Program>,
+    stop_on_entry: bool,
+    /// Whether Node stopped on its first line yet
+    started: bool,
+    stepping: bool,
+    /// Call frames of the pause, as the inspector gave them
+    frames: Vec<Value>,
+    /// Inspector objects behind the `variablesReference`s handed out this pause
+    handles: Vec<String>,
+}
+
+pub fn serve(locale: Locale) {
+    let (sender, receiver) = mpsc::channel();
+    let requests = sender.clone();
+    thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        while let Some(request) = read_message(&mut stdin) {
+            if requests.send(Message::Request(request)).is_err() { return; }
+        }
+        let _ = requests.send(Message::Closed);
+    });
+
+    let mut session = Session { messages: sender, locale, seq: 0, program: None, stop_on_entry: false, started: false, stepping: false, frames: Vec::new(), handles: Vec::new() };
+    for message in receiver {
+        if !session.handle(message) { break; }
+    }
+    session.stop();
+}
+
+/// One `Content-Length`-framed message
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 { return None; }
+        match line.trim_end().split_once(':') {
+            Some((name, value)) if name.eq_ignore_ascii_case("content-length") => length = value.trim().parse().ok(),
+            None if line.trim_end().is_empty() && length.is_some() => break,
+            _ => {}
+        }
+    }
+    let mut body = vec![0; length?];
+    reader.read_exact(&mut body).ok()?;
+    serde_json::from_slice(&body).ok()
+}
+
+impl Session {
+    /// Handles one message; false once the editor is done
+    fn handle(&mut self, message: Message) -> bool {
+        match message {
+            Message::Request(request) => return self.request(&request),
+            Message::Inspector(method, params) => match method.as_str() {
+                "Debugger.paused" => self.paused(params),
+                "Debugger.scriptParsed" => if let Some(program) = self.program.as_mut().filter(|program| params["url"] == program.url.as_str()) {
+                    program.script = params["scriptId"].as_str().map(String::from);
+                },
+                "Debugger.resumed" => { self.frames.clear(); self.handles.clear(); }
+                _ => {}
+            },
+            Message::Output(category, output) => self.event("output", json!({ "category": category, "output": output })),
+            Message::Detach => if let Some(program) = &self.program { program.inspector.close(); },
+            Message::Finished => self.finish(),
+            Message::Closed => return false,
+        }
+        true
+    }
+
+    fn request(&mut self, request: &Value) -> bool {
+        let command = request["command"].as_str().unwrap_or_default();
+        let args = &request["arguments"];
+        let result = match command {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsEvaluateForHovers": true,
+                "supportsTerminateRequest": true,
+                "exceptionBreakpointFilters": [
+                    { "filter": "uncaught", "label": "Uncaught Exceptions", "default": false },
+                    { "filter": "all", "label": "All Exceptions", "default": false },
+                ],
+            })),
+            "launch" => self.launch(args),
+            "setBreakpoints" => self.set_breakpoints(args),
+            "setExceptionBreakpoints" => {
+                let filters = args["filters"].as_array().cloned().unwrap_or_default();
+                let state = ["all", "uncaught"].into_iter().find(|filter| filters.iter().any(|f| f == filter)).unwrap_or("none");
+                self.call("Debugger.setPauseOnExceptions", json!({ "state": state }))
+            }
+            "configurationDone" => self.call("Runtime.runIfWaitingForDebugger", json!({})),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD, "name": "main" }] })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => self.scopes(args["frameId"].as_u64().unwrap_or_default() as usize),
+            "variables" => self.variables(args["variablesReference"].as_u64().unwrap_or_default() as usize),
+            "evaluate" => self.evaluate(args),
+            "continue" => self.resume("Debugger.resume", false).map(|_| json!({ "allThreadsContinued": true })),
+            "next" => self.resume("Debugger.stepOver", true),
+            "stepIn" => self.resume("Debugger.stepInto", true),
+            "stepOut" => self.resume("Debugger.stepOut", true),
+            "pause" => self.call("Debugger.pause", json!({})),
+            "terminate" => {
+                // Node's pipes close, and Finished reports the exit
+                if let Some(program) = &mut self.program { let _ = program.child.kill(); }
+                Ok(json!({}))
+            }
+            "disconnect" => Ok(json!({})),
+            _ => Err(format!("unsupported request: {}", command)),
+        };
+        let ok = result.is_ok();
+        self.respond(request, result);
+        if command == "launch" && ok {
+            // Now the editor sends its breakpoints, then configurationDone
+            self.event("initialized", json!({}));
+        }
+        command != "disconnect"
+    }
+
+    fn launch(&mut self, args: &Value) -> Result<Value, String> {
+        if self.program.is_some() { return Err("a program is already running".to_string()); }
+        let program = args["program"].as_str().ok_or("launch needs a \"program\"")?;
+        let cwd = args["cwd"].as_str().map(PathBuf::from);
+        let path = cwd.as_deref().unwrap_or(Path::new("")).join(program);
+        let path = fs::canonicalize(&path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        let source = fs::read_to_string(&path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        let options = crate::project_options(&path).source_map(true).filename(path.display().to_string()).locale(self.locale).build();
+        let result = match compile(&source, options) {
+            Ok(result) => result,
+            Err(error) => {
+                self.event("output", json!({ "category": "stderr", "output": render_diagnostic(&source, error.diagnostic(), RenderOptions { color: false, locale: self.locale }) }));
+                return Err(format!("{} doesn't compile", path.display()));
+            }
+        };
+        let map = result.source_map.ok_or("no source map")?;
+        // Node only runs `import`/`export` from a .mjs file
+        let js_path = std::env::temp_dir().join(format!("fratm_dap_{}.{}", std::process::id(), if result.is_module { "mjs" } else { "js" }));
+        fs::write(&js_path, &result.code).map_err(|e| format!("cannot write {}: {}", js_path.display(), e))?;
+
+        let mut command = Command::new("node");
+        command.arg("--inspect-brk=127.0.0.1:0").arg(&js_path).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        command.args(args["args"].as_array().into_iter().flatten().filter_map(Value::as_str));
+        if let Some(cwd) = &cwd { command.current_dir(cwd); }
+        let mut child = command.spawn().map_err(|e| format!("cannot start Node.js: {}", e))?;
+        let url = format!("file://{}", js_path.display());
+        let inspector = match self.attach(&mut child, &js_path, &url, &path, &map) {
+            Ok(inspector) => inspector,
+            Err(e) => { let _ = child.kill(); let _ = fs::remove_file(&js_path); return Err(e); }
+        };
+
+        self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+        self.started = false;
+        let mappings = map.decode_mappings().unwrap_or_default();
+        self.program = Some(Program { path, js_path, url, script: None, map, mappings, child, inspector, breakpoints: Vec::new() });
+        Ok(json!({}))
+    }
+
+    /// Connects to the inspector of `child`, and forwards its output
+    fn attach(&self, child: &mut Child, js_path: &Path, url: &str, path: &Path, map: &SourceMap) -> Result<Arc<Inspector>, String> {
+        let mut stderr = BufReader::new(child.stderr.take().ok_or("no stderr")?);
+        // Node names its socket before running anything
+        let socket = loop {
+            let mut line = String::new();
+            if stderr.read_line(&mut line).unwrap_or(0) == 0 { return Err("Node.js didn't start its inspector".to_string()); }
+            if let Some(socket) = line.trim().strip_prefix("Debugger listening on ") { break socket.to_string(); }
+        };
+
+        let stdout = child.stdout.take().ok_or("no stdout")?;
+        let messages = self.messages.clone();
+        let printing = thread::spawn(move || forward_lines(BufReader::new(stdout), |line| { let _ = messages.send(Message::Output("stdout", line)); }));
+        let messages = self.messages.clone();
+        let (js_path, fratm_path, map) = (if url.ends_with(".mjs") { url.to_string() } else { js_path.display().to_string() }, path.display().to_string(), map.clone());
+        thread::spawn(move || {
+            forward_lines(stderr, |line| match line.trim_end() {
+                "Waiting for the debugger to disconnect..." => { let _ = messages.send(Message::Detach); }
+                "Debugger attached." => {}
+                text if text.starts_with("For help, see: ") || text.starts_with("Debugger ending on ") => {}
+                _ => { let _ = messages.send(Message::Output("stderr", crate::remap_stack_trace(&line, &js_path, &fratm_path, &map))); }
+            });
+            let _ = printing.join();
+            let _ = messages.send(Message::Finished);
+        });
+
+        let messages = self.messages.clone();
+        let inspector = Inspector::connect(&socket, move |method, params| { let _ = messages.send(Message::Inspector(method, params)); })
+            .map_err(|e| format!("cannot reach the inspector: {}", e))?;
+        inspector.call("Runtime.enable", json!({}))?;
+        inspector.call("Debugger.enable", json!({}))?;
+        // Stepping goes through the program, not through Node
+        let _ = inspector.call("Debugger.setBlackboxPatterns", json!({ "patterns": ["^node:"] }));
+        Ok(inspector)
+    }
+
+    fn set_breakpoints(&mut self, args: &Value) -> Result<Value, String> {
+        let program = self.program.as_mut().ok_or("no program launched")?;
+        let lines = args["breakpoints"].as_array().into_iter().flatten().filter_map(|breakpoint| breakpoint["line"].as_u64()).map(|line| line as usize);
+        let same_file = args["source"]["path"].as_str().and_then(|path| fs::canonicalize(path).ok()).is_some_and(|path| path == program.path);
+        if !same_file {
+            let breakpoints: Vec<_> = lines.map(|line| json!({ "verified": false, "line": line, "message": "Only the launched file can stop" })).collect();
+            return Ok(json!({ "breakpoints": breakpoints }));
+        }
+        for id in program.breakpoints.drain(..) {
+            let _ = program.inspector.call("Debugger.removeBreakpoint", json!({ "breakpointId": id }));
+        }
+        let mut breakpoints = Vec::new();
+        for line in lines {
+            let Some((line, gen_line, gen_col)) = program.position(line) else {
+                breakpoints.push(json!({ "verified": false, "line": line, "message": "No code from this line on" }));
+                continue;
+            };
+            match program.inspector.call("Debugger.setBreakpointByUrl", json!({ "url": program.url, "lineNumber": gen_line, "columnNumber": gen_col })) {
+                Ok(result) => {
+                    program.breakpoints.extend(result["breakpointId"].as_str().map(String::from));
+                    breakpoints.push(json!({ "verified": true, "line": line }));
+                }
+                Err(e) => breakpoints.push(json!({ "verified": false, "line": line, "message": e })),
+            }
+        }
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    fn paused(&mut self, params: Value) {
+        self.frames = params["callFrames"].as_array().cloned().unwrap_or_default();
+        self.handles.clear();
+        let hit = params["hitBreakpoints"].as_array().is_some_and(|hit| !hit.is_empty());
+        let reason = if !self.started {
+            // --inspect-brk stops on the first line, which may have a breakpoint too
+            self.started = true;
+            match (self.stop_on_entry, hit) {
+                (true, _) => "entry",
+                (false, true) => "breakpoint",
+                (false, false) => { let _ = self.call("Debugger.resume", json!({})); return; }
+            }
+        } else if matches!(params["reason"].as_str(), Some("exception" | "promiseRejection")) {
+            "exception"
+        } else if self.stepping {
+            "step"
+        } else {
+            "breakpoint"
+        };
+        self.stepping = false;
+        self.event("stopped", json!({ "reason": reason, "threadId": THREAD, "allThreadsStopped": true }));
+    }
+
+    fn stack_trace(&mut self) -> Result<Value, String> {
+        let program = self.program.as_ref().ok_or("no program launched")?;
+        let name = program.path.file_name().map(|name| name.to_string_lossy().into_owned());
+        // Frames of Node itself or of imported files have no .fratm line
+        let frames: Vec<_> = self.frames.iter().enumerate().filter_map(|(id, frame)| {
+            let location = &frame["location"];
+            if location["scriptId"].as_str() != program.script.as_deref() { return None; }
+            let (line, column, _) = program.map.lookup(location["lineNumber"].as_u64()? as usize, location["columnNumber"].as_u64().unwrap_or(0) as usize)?;
+            let function = frame["functionName"].as_str().filter(|name| !name.is_empty()).unwrap_or("(program)");
+            Some(json!({ "id": id, "name": function, "source": { "name": name, "path": program.path }, "line": line + 1, "column": column + 1 }))
+        }).collect();
+        Ok(json!({ "stackFrames": frames, "totalFrames": frames.len() }))
+    }
+
+    fn scopes(&mut self, frame: usize) -> Result<Value, String> {
+        let chain = self.frames.get(frame).ok_or("not paused there")?["scopeChain"].as_array().cloned().unwrap_or_default();
+        let scopes: Vec<_> = chain.iter().map(|scope| {
+            let kind = scope["type"].as_str().unwrap_or("scope");
+            let name = kind[..1].to_uppercase() + &kind[1..];
+            let reference = scope["object"]["objectId"].as_str().map_or(0, |id| self.reference(id));
+            json!({ "name": name, "variablesReference": reference, "expensive": kind == "global" })
+        }).collect();
+        Ok(json!({ "scopes": scopes }))
+    }
+
+    fn variables(&mut self, reference: usize) -> Result<Value, String> {
+        let object = reference.checked_sub(1).and_then(|i| self.handles.get(i)).cloned().ok_or("no such variable")?;
+        let result = self.call("Runtime.getProperties", json!({ "objectId": object, "ownProperties": true }))?;
+        // Getters and setters have no value to show
+        let variables: Vec<_> = result["result"].as_array().into_iter().flatten().filter(|property| property["value"].is_object()).map(|property| {
+            let (value, reference) = self.describe(&property["value"]);
+            json!({ "name": property["name"], "value": value, "variablesReference": reference })
+        }).collect();
+        Ok(json!({ "variables": variables }))
+    }
+
+    fn evaluate(&mut self, args: &Value) -> Result<Value, String> {
+        let expression = javascript(args["expression"].as_str().unwrap_or_default());
+        let frame = args["frameId"].as_u64().and_then(|id| self.frames.get(id as usize)).map(|frame| frame["callFrameId"].clone());
+        let result = match frame {
+            Some(frame) => self.call("Debugger.evaluateOnCallFrame", json!({ "callFrameId": frame, "expression": expression }))?,
+            None => self.call("Runtime.evaluate", json!({ "expression": expression }))?,
+        };
+        if let Some(details) = result.get("exceptionDetails") {
+            return Err(result["result"]["description"].as_str().or(details["text"].as_str()).unwrap_or("exception").to_string());
+        }
+        let (value, reference) = self.describe(&result["result"]);
+        Ok(json!({ "result": value, "variablesReference": reference }))
+    }
+
+    fn resume(&mut self, method: &str, stepping: bool) -> Result<Value, String> {
+        self.stepping = stepping;
+        self.call(method, json!({}))
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value, String> {
+        self.program.as_ref().ok_or("no program launched")?.inspector.call(method, params)
+    }
+
+    /// How to show an inspector value, and the reference to expand it with
+    fn describe(&mut self, value: &Value) -> (String, usize) {
+        let reference = value["objectId"].as_str().map_or(0, |id| self.reference(id));
+        let text = match value["type"].as_str() {
+            Some("string") => value["value"].to_string(),
+            Some("undefined") => "undefined".to_string(),
+            _ => value["description"].as_str().map_or_else(|| value["value"].to_string(), String::from),
+        };
+        (text, reference)
+    }
+
+    fn reference(&mut self, object: &str) -> usize {
+        self.handles.push(object.to_string());
+        self.handles.len()
+    }
+
+    /// Reports that Node exited
+    fn finish(&mut self) {
+        let Some(mut program) = self.program.take() else { return };
+        program.inspector.close();
+        let code = program.child.wait().ok().and_then(|status| status.code()).unwrap_or(1);
+        let _ = fs::remove_file(&program.js_path);
+        self.event("exited", json!({ "exitCode": code }));
+        self.event("terminated", json!({}));
+    }
+
+    /// Ends the program without telling the editor, which is leaving
+    fn stop(&mut self) {
+        if let Some(mut program) = self.program.take() {
+            let _ = program.child.kill();
+            program.inspector.close();
+            let _ = program.child.wait();
+            let _ = fs::remove_file(&program.js_path);
+        }
+    }
+
+    fn respond(&mut self, request: &Value, result: Result<Value, String>) {
+        let mut response = json!({ "type": "response", "request_seq": request["seq"], "command": request["command"], "success": result.is_ok() });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = json!(message),
+        }
+        self.send(response);
+    }
+
+    fn event(&mut self, event: &str, body: Value) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+
+    fn send(&mut self, mut message: Value) {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        let body = message.to_string();
+        let mut stdout = io::stdout().lock();
+        let _ = write!(stdout, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+        let _ = stdout.flush();
+    }
+}
+
+/// Calls `send` with every line of `reader`, newline included
+fn forward_lines(mut reader: impl BufRead, mut send: impl FnMut(String)) {
+    let mut line = String::new();
+    while reader.read_line(&mut line).unwrap_or(0) > 0 {
+        send(std::mem::take(&mut line));
+    }
+}
+
+/// The JavaScript of an expression typed in the editor: FratmScript if it
+/// compiles, as it is otherwise
+fn javascript(expression: &str) -> String {
+    match compile(expression, CompileOptions::default()) {
+        Ok(result) => result.code.trim_end().trim_end_matches(';').to_string(),
+        Err(_) => expression.to_string(),
+    }
+}
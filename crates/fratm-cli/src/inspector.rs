@@ -0,0 +1,129 @@
+//! Client for the inspector protocol of `node --inspect`
+//!
+//! Just enough WebSocket for the loopback socket Node opens: text frames,
+//! ping and close, no extensions. [`Inspector::call`] sends a command and
+//! waits for its answer, while the events go to the callback given to
+//! [`Inspector::connect`], from the thread reading the socket.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use serde_json::{json, Value};
+
+/// Handshake key. Servers only echo it back hashed, so it needn't be random
+const KEY: &str = "ZnJhdG1zY3JpcHQgZGFwIQ==";
+/// Mask of the frames we send. Masking guards proxies, and there's none
+/// between us and Node
+const MASK: [u8; 4] = [0x66, 0x72, 0x61, 0x74];
+
+/// Answers still awaited, by command id; `None` once the socket closed
+type Pending = Mutex<Option<HashMap<u64, mpsc::Sender<Result<Value, String>>>>>;
+
+pub struct Inspector {
+    socket: Mutex<TcpStream>,
+    next_id: AtomicU64,
+    pending: Pending,
+}
+
+impl Inspector {
+    /// Connects to the `ws://host:port/id` Node printed, calling
+    /// `on_event(method, params)` for every event.
+    pub fn connect(url: &str, mut on_event: impl FnMut(String, Value) + Send + 'static) -> io::Result<Arc<Self>> {
+        let address = url.strip_prefix("ws://").ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("not a ws:// URL: {}", url)))?;
+        let (host, path) = address.split_once('/').unwrap_or((address, ""));
+        let mut socket = TcpStream::connect(host)?;
+        write!(socket, "GET /{} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n", path, host, KEY)?;
+        let mut reader = BufReader::new(socket.try_clone()?);
+        let mut status = String::new();
+        reader.read_line(&mut status)?;
+        if status.split_whitespace().nth(1) != Some("101") {
+            return Err(io::Error::new(io::ErrorKind::ConnectionRefused, format!("handshake refused: {}", status.trim())));
+        }
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 || header == "\r\n" { break; }
+        }
+
+        let inspector = Arc::new(Self { socket: Mutex::new(socket), next_id: AtomicU64::new(1), pending: Mutex::new(Some(HashMap::new())) });
+        let this = Arc::clone(&inspector);
+        thread::spawn(move || {
+            let mut message = Vec::new();
+            while let Ok((fin, opcode, payload)) = read_frame(&mut reader) {
+                match opcode {
+                    0x0..=0x2 => message.extend(payload),
+                    0x8 => break,
+                    0x9 => { let _ = this.send_frame(0xA, &payload); continue; }
+                    _ => continue,
+                }
+                if !fin { continue; }
+                let Ok(message) = serde_json::from_slice::<Value>(&std::mem::take(&mut message)) else { continue };
+                match message["id"].as_u64() {
+                    Some(id) => {
+                        let sender = this.pending.lock().unwrap().as_mut().and_then(|pending| pending.remove(&id));
+                        let result = match message.get("error") {
+                            Some(error) => Err(error["message"].as_str().unwrap_or("inspector error").to_string()),
+                            None => Ok(message["result"].clone()),
+                        };
+                        if let Some(sender) = sender { let _ = sender.send(result); }
+                    }
+                    None => on_event(message["method"].as_str().unwrap_or_default().to_string(), message["params"].clone()),
+                }
+            }
+            // Wake up whoever still waits
+            *this.pending.lock().unwrap() = None;
+        });
+        Ok(inspector)
+    }
+
+    /// Sends `method` and waits for its result
+    pub fn call(&self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel();
+        match self.pending.lock().unwrap().as_mut() {
+            Some(pending) => pending.insert(id, sender),
+            None => return Err("the inspector is gone".to_string()),
+        };
+        let message = json!({ "id": id, "method": method, "params": params }).to_string();
+        self.send_frame(0x1, message.as_bytes()).map_err(|e| e.to_string())?;
+        receiver.recv().unwrap_or_else(|_| Err("the inspector is gone".to_string()))
+    }
+
+    /// Hangs up, which lets a Node waiting for its debugger exit
+    pub fn close(&self) {
+        let _ = self.socket.lock().unwrap().shutdown(Shutdown::Both);
+    }
+
+    fn send_frame(&self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            len if len < 126 => frame.push(0x80 | len as u8),
+            len if len <= 0xffff => { frame.push(0x80 | 126); frame.extend((len as u16).to_be_bytes()); }
+            len => { frame.push(0x80 | 127); frame.extend((len as u64).to_be_bytes()); }
+        }
+        frame.extend(MASK);
+        frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ MASK[i % 4]));
+        self.socket.lock().unwrap().write_all(&frame)
+    }
+}
+
+/// Whether the frame is the last of its message, its opcode and payload
+fn read_frame(reader: &mut impl Read) -> io::Result<(bool, u8, Vec<u8>)> {
+    let mut head = [0; 2];
+    reader.read_exact(&mut head)?;
+    let len = match head[1] & 0x7f {
+        126 => { let mut len = [0; 2]; reader.read_exact(&mut len)?; u16::from_be_bytes(len) as usize }
+        127 => { let mut len = [0; 8]; reader.read_exact(&mut len)?; u64::from_be_bytes(len) as usize }
+        len => len as usize,
+    };
+    let mask = if head[1] & 0x80 != 0 { let mut mask = [0; 4]; reader.read_exact(&mut mask)?; Some(mask) } else { None };
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+    if let Some(mask) = mask {
+        payload.iter_mut().enumerate().for_each(|(i, byte)| *byte ^= mask[i % 4]);
+    }
+    Ok((head[0] & 0x80 != 0, head[0] & 0x0f, payload))
+}
@@ -0,0 +1,69 @@
+// Node.js loader for .fratm files, written by `fratm emit-loader`.
+//
+//   node --import ./fratm-loader.mjs app.js
+//
+// Imports of .fratm files are compiled on the fly by one `fratm serve`
+// process, with the options of the nearest fratm.json (keep its
+// moduleFormat to the default, esm). Add
+// --enable-source-maps to get .fratm positions in stack traces.
+// Set FRATM_BIN to use another fratm binary.
+import { spawn } from 'node:child_process'
+import { readFile } from 'node:fs/promises'
+import { register } from 'node:module'
+import { createInterface } from 'node:readline'
+import { fileURLToPath } from 'node:url'
+import { isMainThread } from 'node:worker_threads'
+
+const FRATM_BIN = process.env.FRATM_BIN ?? __FRATM_BIN__
+
+// Loaded with --import: install the hooks below, which Node runs off the main thread
+if (isMainThread) {
+  register(import.meta.url)
+}
+
+let server = null
+let nextId = 1
+const pending = new Map()
+
+function startServer() {
+  const child = spawn(FRATM_BIN, ['serve'], { stdio: ['pipe', 'pipe', 'inherit'] })
+  child.on('error', (error) => {
+    for (const { reject } of pending.values()) reject(error)
+    pending.clear()
+    server = null
+  })
+  createInterface({ input: child.stdout }).on('line', (line) => {
+    const response = JSON.parse(line)
+    const request = pending.get(response.id)
+    if (!request) return
+    pending.delete(response.id)
+    if (response.error) {
+      const { message, data } = response.error
+      const where = data ? `${request.filename}:${data.span.line}:${data.span.column}` : request.filename
+      request.reject(new SyntaxError(`${where}: ${message}`))
+    } else {
+      request.resolve(response.result)
+    }
+  })
+  // The server stops when its stdin closes, that is when the program exits
+  return child
+}
+
+function compile(source, filename) {
+  server ??= startServer()
+  const id = nextId++
+  return new Promise((resolve, reject) => {
+    pending.set(id, { resolve, reject, filename })
+    server.stdin.write(JSON.stringify({ jsonrpc: '2.0', id, method: 'compile', params: { source, filename, sourceMap: true } }) + '\n')
+  })
+}
+
+export async function load(url, context, nextLoad) {
+  if (!url.endsWith('.fratm')) {
+    return nextLoad(url, context)
+  }
+  const filename = fileURLToPath(url)
+  const { code, sourceMap } = await compile(await readFile(filename, 'utf8'), filename)
+  const map = sourceMap ? `\n//# sourceMappingURL=data:application/json;base64,${Buffer.from(sourceMap).toString('base64')}` : ''
+  return { format: 'module', source: code + map, shortCircuit: true }
+}
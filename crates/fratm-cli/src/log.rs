@@ -0,0 +1,74 @@
+//! What the CLI says besides its output
+//!
+//! `-v` adds the options used, the modules read and how long each step
+//! took, `-vv` the details, and `--quiet` drops the lines that only say
+//! all went well. Diagnostics are printed whatever the level. Everything
+//! here goes to stderr, so stdout stays the output of the command.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Instant;
+
+use colored::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+    Debug,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        2 => Verbosity::Verbose,
+        _ => Verbosity::Debug,
+    }
+}
+
+/// Whether to leave out the lines that only say all went well
+pub fn quiet() -> bool {
+    verbosity() == Verbosity::Quiet
+}
+
+/// Logs with `-v`
+macro_rules! verbose {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Verbosity::Verbose, format_args!($($arg)*)) };
+}
+
+/// Logs with `-vv`
+macro_rules! debug {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Verbosity::Debug, format_args!($($arg)*)) };
+}
+
+pub(crate) use {debug, verbose};
+
+pub fn write(level: Verbosity, message: fmt::Arguments) {
+    if verbosity() >= level {
+        eprintln!("{}", format!("[fratm] {}", message).dimmed());
+    }
+}
+
+/// Logs how long `step` took when dropped, with `-v`
+pub struct Timer {
+    step: &'static str,
+    start: Instant,
+}
+
+pub fn timer(step: &'static str) -> Timer {
+    Timer { step, start: Instant::now() }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        write(Verbosity::Verbose, format_args!("{} took {:.2?}", self.step, self.start.elapsed()));
+    }
+}
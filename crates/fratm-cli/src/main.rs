@@ -1,12 +1,27 @@
 //! FratmScript CLI - JavaScript, but the way it should be 🤌
 
-use clap::{Parser as ClapParser, Subcommand};
+use clap::{ArgAction, Args, Parser as ClapParser, Subcommand, ValueEnum};
 use colored::*;
-use fratm_core::{compile, CompileOptions, errors};
+use fratm_core::{compile, generate_to, Assertions, CodegenStyle, CompileOptions, CompileOptionsBuilder, Decorators, ModuleFormat, Target, Trace, errors};
+use fratm_core::coverage::Counts;
+use fratm_core::diff::ChangeKind;
+use fratm_core::errors::{Locale, Severity};
+use fratm_core::lexer::{Lexer, Span};
+use fratm_core::parser::{Parser, ParserOptions, Program, Statement};
+use fratm_core::lexer::keywords::KeywordTable;
+use fratm_core::render::{render_diagnostic, RenderOptions};
+use fratm_core::sourcemap::SourceMap;
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::{self, BufRead, Write};
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use serde::Deserialize;
+
+mod dap;
+mod inspector;
+mod log;
+mod serve;
 
 #[derive(ClapParser)]
 #[command(name = "fratm")]
@@ -16,6 +31,37 @@ use std::process::Command;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Language of error messages: nap (default) or en. Falls back to FRATM_LANG
+    #[arg(long, global = true, value_name = "LANG")]
+    lang: Option<Locale>,
+    /// Plain output without the paesano phrases (e.g. for CI)
+    #[arg(long, global = true)]
+    serious: bool,
+    /// Say what is going on: options, modules, timings (-vv: more details)
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+    /// Print only what the command outputs and its diagnostics, without the success lines
+    #[arg(short, long, global = true)]
+    quiet: bool,
+}
+
+/// Exit statuses, by what failed: the contract CI scripts gate on. All
+/// went well with 0.
+mod status {
+    /// The code doesn't compile, fails a check, or has more warnings than `--max-warnings`
+    pub const CODE: i32 = 1;
+    /// A file can't be read or written, or a settings file (fratm.json, .env,
+    /// a dialect) is invalid. Clap exits with 2 on a bad command line too
+    pub const IO: i32 = 2;
+    /// The program `fratm run` ran failed, or Node.js couldn't start
+    pub const RUNTIME: i32 = 3;
+}
+
+/// Global flags shared by the commands
+#[derive(Clone, Copy)]
+struct Settings {
+    locale: Locale,
+    serious: bool,
 }
 
 #[derive(Subcommand)]
@@ -25,95 +71,923 @@ enum Commands {
         file: PathBuf,
         #[arg(long)]
         sourcemap: bool,
+        /// Define a name for `#si` directives (overrides fratm.json)
+        #[arg(short = 'D', value_name = "NAME[=VALUE]")]
+        define: Vec<String>,
+        /// `.env` file with the values of `amb("NAME")` (overrides fratm.json)
+        #[arg(long, value_name = "FILE")]
+        env_file: Option<PathBuf>,
+        #[command(flatten)]
+        trace: TraceFlags,
+        #[command(flatten)]
+        inspect: InspectFlags,
     },
     /// Compile a .fratm file to JavaScript
     Build {
+        /// The file, or with --out-dir a directory of them
         file: PathBuf,
-        #[arg(short, long)]
+        #[arg(short, long, conflicts_with = "out_dir")]
         output: Option<PathBuf>,
+        /// Write the JavaScript here, in the directories of the sources; a file brings the .fratm files it imports along
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+        /// Delete the .js and .js.map files earlier builds wrote in --out-dir that this one didn't
+        #[arg(long, requires = "out_dir")]
+        clean: bool,
         #[arg(long)]
         sourcemap: bool,
+        /// File whose text goes at the top of the output as a comment (overrides fratm.json)
+        #[arg(long, value_name = "FILE")]
+        banner_file: Option<PathBuf>,
+        #[command(flatten)]
+        code: CodeFlags,
     },
     /// Interactive REPL
     Repl,
+    /// Tutorial: small exercises, checked as you write them
+    Learn {
+        /// Where the exercises done are kept
+        #[arg(long, value_name = "FILE", default_value = "fratm-learn.json")]
+        progress: PathBuf,
+        /// Show the exercises and which are done
+        #[arg(long)]
+        list: bool,
+        /// Start over from the first exercise
+        #[arg(long)]
+        reset: bool,
+    },
     /// Show tokens (debug)
-    Tokens { file: PathBuf },
+    Tokens {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value = "text")]
+        format: TokensFormat,
+        /// Echo the source with every token marked, to see where tokens start and end
+        #[arg(long, conflicts_with = "format")]
+        spans: bool,
+    },
     /// Show AST (debug)
-    Ast { file: PathBuf },
+    Ast {
+        file: PathBuf,
+        /// Only list functions, classes and top-level variables
+        #[arg(long, conflicts_with = "format")]
+        outline: bool,
+        #[arg(long, value_enum, default_value = "json")]
+        format: AstFormat,
+    },
+    /// Keep the compiler running and answer JSON-RPC requests, one per line
+    Serve {
+        /// Listen on this Unix socket instead of stdin/stdout
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+    /// Debug adapter (DAP) on stdin/stdout, for editors to step through .fratm programs
+    Dap,
+    /// Write a Node.js loader that compiles imported .fratm files on the fly
+    EmitLoader {
+        #[arg(short, long, default_value = "fratm-loader.mjs")]
+        output: PathBuf,
+    },
+    /// Compare the structure of two .fratm files, ignoring layout and comments
+    Diff { old: PathBuf, new: PathBuf },
+    /// Print a hash of each file's structure, the same whatever its layout and comments
+    Fingerprint {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Bring old .fratm files up to the current syntax (keyword spelling, `pe ogni`)
+    Fix {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Show the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+        /// Dialect pack the files are written in, if fratm.json has since changed it
+        #[arg(long, value_name = "FILE")]
+        from: Option<PathBuf>,
+    },
+    /// Check .fratm files for errors and warnings without compiling them
+    Check {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Also check values against their type annotations
+        #[arg(long)]
+        types: bool,
+        /// Report type mismatches as errors (implies --types)
+        #[arg(long)]
+        strict_types: bool,
+        /// Fail when the files have more warnings than this, together
+        #[arg(long, value_name = "N")]
+        max_warnings: Option<usize>,
+        /// Report up to this many syntax errors per file, instead of stopping at the first
+        #[arg(long, value_name = "N")]
+        max_errors: Option<usize>,
+        /// Apply the fixes that surely mend an error or warning, showing what changed
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Report code that compiles but is probably a mistake, with every rule of the linter
+    Lint {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Apply the fixes that surely mend an error or warning, showing what changed
+        #[arg(long)]
+        fix: bool,
+        /// Fail when the files have more warnings than this, together
+        #[arg(long, value_name = "N")]
+        max_warnings: Option<usize>,
+    },
+    /// Coverage of programs built with `--coverage`
+    #[command(subcommand)]
+    Coverage(CoverageCommand),
+    /// Print a syntax highlighting grammar for editors
+    Grammar {
+        #[arg(long, value_enum, default_value = "tmlanguage")]
+        format: GrammarFormat,
+        /// Dialect pack to highlight, as in fratm.json: `{ "name": ..., "words": { ... } }`
+        #[arg(long, value_name = "FILE")]
+        dialect: Option<PathBuf>,
+    },
+    /// Print the keywords with their JavaScript and what they do
+    Translate {
+        /// Only the keywords whose words, JavaScript or description contain this
+        term: Option<String>,
+        #[arg(long, value_enum, default_value = "text")]
+        format: TranslateFormat,
+        /// Dialect pack to show the words of, as in fratm.json
+        #[arg(long, value_name = "FILE")]
+        dialect: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CoverageCommand {
+    /// Show which .fratm lines and branches ran, from the counts a covered program wrote
+    Report {
+        /// Counts written on exit (FRATM_COVERAGE_FILE)
+        #[arg(default_value = "fratm-coverage.json")]
+        counts: PathBuf,
+        #[arg(long, value_enum, default_value = "text")]
+        format: CoverageFormat,
+    },
+}
+
+/// Flags of `build` that change the generated code
+#[derive(Args)]
+struct CodeFlags {
+    /// Define a name for `#si` directives, `1` without a value (overrides fratm.json)
+    #[arg(short = 'D', value_name = "NAME[=VALUE]")]
+    define: Vec<String>,
+    /// Shorten local variable names (the source map keeps the originals)
+    #[arg(long)]
+    minify: bool,
+    /// Fold string concatenations into single strings or template literals
+    #[arg(long)]
+    optimize: bool,
+    /// Leave out `giura che` assertions
+    #[arg(long)]
+    release: bool,
+    /// Compile `giura che` to console.assert, which logs instead of throwing
+    #[arg(long, conflicts_with = "release")]
+    console_assert: bool,
+    /// Where the output runs (overrides the moduleFormat of fratm.json)
+    #[arg(long, value_enum)]
+    target: Option<OutputTarget>,
+    /// Global object a browser script exports to and imports from [default: Fratm]
+    #[arg(long, value_name = "NAME")]
+    global: Option<String>,
+    /// `.env` file with the values of `amb("NAME")` (overrides fratm.json)
+    #[arg(long, value_name = "FILE")]
+    env_file: Option<PathBuf>,
+    /// Count the statements and branches that run, for `fratm coverage report` (implies --sourcemap)
+    #[arg(long)]
+    coverage: bool,
+    /// Write each line of the source as a comment above the JavaScript it compiles to
+    #[arg(long)]
+    annotate: bool,
+    /// Fail when the code has more warnings than this
+    #[arg(long, value_name = "N")]
+    max_warnings: Option<usize>,
+    /// Report up to this many syntax errors, instead of stopping at the first
+    #[arg(long, value_name = "N")]
+    max_errors: Option<usize>,
+    #[command(flatten)]
+    trace: TraceFlags,
+}
+
+/// Flags of `run` and `build` that log function calls
+#[derive(Args)]
+struct TraceFlags {
+    /// Log the calls and returns of functions: all but those marked `// nun traccià`, or only those marked `// traccia` (overrides fratm.json)
+    #[arg(long, value_enum, value_name = "WHICH", num_args = 0..=1, require_equals = true, default_missing_value = "all")]
+    trace: Option<TraceMode>,
+    /// Function called as `hook("enter", name, arguments)` and `hook("exit", name, value)` instead of console.log
+    #[arg(long, value_name = "NAME")]
+    trace_hook: Option<String>,
+}
+
+/// Flags of `run` that let a debugger attach to Node
+#[derive(Args)]
+struct InspectFlags {
+    /// Let a debugger attach (Node's --inspect); the source map goes inline, so `fermete` stops in the .fratm file
+    #[arg(long, value_name = "[HOST:]PORT", num_args = 0..=1, require_equals = true, default_missing_value = "")]
+    inspect: Option<String>,
+    /// Like --inspect, but wait for the debugger before the first line
+    #[arg(long, value_name = "[HOST:]PORT", num_args = 0..=1, require_equals = true, default_missing_value = "", conflicts_with = "inspect")]
+    inspect_brk: Option<String>,
+}
+
+impl InspectFlags {
+    /// The flag to hand Node, if any
+    fn node_arg(&self) -> Option<String> {
+        let (flag, address) = match (&self.inspect, &self.inspect_brk) {
+            (Some(address), _) => ("--inspect", address),
+            (_, Some(address)) => ("--inspect-brk", address),
+            _ => return None,
+        };
+        Some(if address.is_empty() { flag.to_string() } else { format!("{}={}", flag, address) })
+    }
+}
+
+impl TraceFlags {
+    fn apply(&self, mut options: CompileOptionsBuilder) -> CompileOptionsBuilder {
+        match self.trace {
+            Some(TraceMode::All) => options = options.trace(Trace::All),
+            Some(TraceMode::Annotated) => options = options.trace(Trace::Annotated),
+            None => {}
+        }
+        if let Some(hook) = &self.trace_hook { options = options.trace_hook(hook); }
+        options
+    }
+}
+
+impl CodeFlags {
+    fn apply(&self, mut options: CompileOptionsBuilder) -> CompileOptionsBuilder {
+        options = define(options, &self.define);
+        options = env_file(options, self.env_file.as_deref());
+        if self.minify { options = options.minify(true); }
+        if self.optimize { options = options.optimize(true); }
+        if self.release { options = options.assertions(Assertions::Strip); }
+        if self.console_assert { options = options.assertions(Assertions::Console); }
+        match self.target {
+            Some(OutputTarget::Node) => options = options.module_format(ModuleFormat::Esm),
+            Some(OutputTarget::BrowserScript) => options = options.module_format(ModuleFormat::Script),
+            None => {}
+        }
+        if let Some(global) = &self.global { options = options.global_name(global); }
+        if self.coverage { options = options.coverage(true); }
+        if self.annotate { options = options.annotate(true); }
+        options = options.parser_options(parser_options(self.max_errors));
+        self.trace.apply(options)
+    }
+}
+
+/// Stops parsing at the first syntax error, or at `--max-errors`
+fn parser_options(max_errors: Option<usize>) -> ParserOptions {
+    let max_errors = max_errors.unwrap_or(1);
+    ParserOptions { recovery: max_errors > 1, max_errors, ..Default::default() }
+}
+
+/// Adds `-D NAME=VALUE` defines to `options`; `-D NAME` is `NAME=1`
+fn define(mut options: CompileOptionsBuilder, defines: &[String]) -> CompileOptionsBuilder {
+    for define in defines {
+        let (name, value) = define.split_once('=').unwrap_or((define, "1"));
+        options = options.define(name, value);
+    }
+    options
+}
+
+/// Adds the variables of the `.env` file at `path` to `options`. Exits if it can't be read.
+fn env_file(mut options: CompileOptionsBuilder, path: Option<&Path>) -> CompileOptionsBuilder {
+    let Some(path) = path else { return options };
+    let env = fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|text| fratm_core::env::parse_env_file(&text));
+    match env {
+        Ok(env) => for (name, value) in env { options = options.env(name, value); },
+        Err(e) => { eprintln!("{} {}: {}", "Error: cannot read env file:".red().bold(), path.display(), e); std::process::exit(status::IO); }
+    }
+    options
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputTarget {
+    /// An ES module, for Node.js or a bundler
+    Node,
+    /// A file for a plain `<script>` tag: the code in a function, exports on a global object
+    BrowserScript,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TraceMode {
+    /// Every function, except those marked `// nun traccià`
+    All,
+    /// Only the functions marked `// traccia`
+    Annotated,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TokensFormat {
+    /// One token per line, with its text and position
+    Text,
+    /// JSON list of `{ kind, span, literal }`
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum AstFormat {
+    /// JSON with a `type`, `id`, `start` and `end` on every node
+    Json,
+    /// Indented outline, one node per line
+    Tree,
+    /// S-expressions without spans, for diffs
+    Sexp,
+    /// Lossless tree with every token, space and comment, even with errors
+    Syntax,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CoverageFormat {
+    /// A summary per file, with the lines that never ran
+    Text,
+    /// lcov tracefile, for coverage services and editors
+    Lcov,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TranslateFormat {
+    /// One keyword per line, in columns
+    Text,
+    /// JSON list of `{ phrase, javascript, description, example }`
+    Json,
+    /// Markdown table, for docs
+    Markdown,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GrammarFormat {
+    /// TextMate JSON, for VS Code and most editors
+    Tmlanguage,
+    /// tree-sitter grammar.js
+    TreeSitter,
 }
 
 fn main() {
     let cli = Cli::parse();
+    log::set_verbosity(match (cli.quiet, cli.verbose) {
+        (true, _) => log::Verbosity::Quiet,
+        (false, 0) => log::Verbosity::Normal,
+        (false, 1) => log::Verbosity::Verbose,
+        (false, _) => log::Verbosity::Debug,
+    });
+    let settings = Settings { locale: cli.lang.unwrap_or_else(Locale::from_env), serious: cli.serious };
     match cli.command {
-        Commands::Run { file, sourcemap } => run_file(&file, sourcemap),
-        Commands::Build { file, output, sourcemap } => build_file(&file, output, sourcemap),
-        Commands::Repl => run_repl(),
-        Commands::Tokens { file } => show_tokens(&file),
-        Commands::Ast { file } => show_ast(&file),
+        Commands::Run { file, sourcemap, define, env_file, trace, inspect } => run_file(&file, sourcemap, &define, env_file.as_deref(), &trace, &inspect, settings),
+        Commands::Build { file, out_dir: Some(out_dir), clean, sourcemap, banner_file, code, .. } => build_tree(&file, &out_dir, clean, sourcemap || code.coverage, banner_file, &code, settings),
+        Commands::Build { file, output, sourcemap, banner_file, code, .. } => build_file(&file, output, sourcemap || code.coverage, banner_file, &code, None, settings),
+        Commands::Repl => run_repl(settings.locale),
+        Commands::Learn { progress, list, reset } => learn(&progress, list, reset, settings.locale),
+        Commands::Tokens { file, spans: true, .. } => show_token_spans(&file),
+        Commands::Tokens { file, format, spans: false } => show_tokens(&file, format),
+        Commands::Ast { file, outline: false, format } => show_ast(&file, format, settings.locale),
+        Commands::Ast { file, outline: true, .. } => show_outline(&file),
+        Commands::Serve { socket: None } => serve::serve_stdio(),
+        Commands::Serve { socket: Some(path) } => {
+            if let Err(e) = serve::serve_socket(&path) {
+                eprintln!("{} {}: {}", "Error: cannot serve on".red().bold(), path.display(), e);
+                std::process::exit(status::IO);
+            }
+        }
+        Commands::Dap => dap::serve(settings.locale),
+        Commands::EmitLoader { output } => emit_loader(&output),
+        Commands::Diff { old, new } => diff_files(&old, &new, settings),
+        Commands::Fingerprint { files } => fingerprint_files(&files, settings),
+        Commands::Fix { files, dry_run, from } => fix_files(&files, dry_run, from.as_deref(), settings),
+        Commands::Check { files, types, strict_types, max_warnings, max_errors, fix } => {
+            check_files(&files, CheckFlags { types: types || strict_types, strict: strict_types, lint: false, fix, max_warnings, max_errors }, settings)
+        }
+        Commands::Lint { files, fix, max_warnings } => check_files(&files, CheckFlags { types: false, strict: false, lint: true, fix, max_warnings, max_errors: None }, settings),
+        Commands::Coverage(CoverageCommand::Report { counts, format }) => coverage_report(&counts, format),
+        Commands::Grammar { format, dialect } => show_grammar(format, dialect.as_deref()),
+        Commands::Translate { term, format, dialect } => show_keywords(term.as_deref(), format, dialect.as_deref()),
     }
 }
 
-fn run_file(path: &PathBuf, sourcemap: bool) {
-    let source = match fs::read_to_string(path) {
-        Ok(s) => s,
-        Err(e) => { eprintln!("{} {}", "Error: file not found:".red().bold(), e); std::process::exit(1); }
+fn run_file(path: &Path, sourcemap: bool, defines: &[String], env: Option<&Path>, trace: &TraceFlags, inspect: &InspectFlags, settings: Settings) {
+    let source = read_source(path);
+    let inspect = inspect.node_arg();
+    // The debugger finds the map inline, and resolves its source against the temp file: name it in full
+    let sourcemap = sourcemap || inspect.is_some();
+    let filename = match inspect {
+        Some(_) => fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+        None => path.to_path_buf(),
     };
 
-    let options = CompileOptions { source_map: sourcemap, filename: Some(path.display().to_string()), minify: false };
+    // Always build the map: it is needed to translate Node stack traces back to .fratm positions
+    let options = trace.apply(env_file(define(project_options(path), defines), env)).source_map(true).filename(filename.display().to_string()).locale(settings.locale).build();
+    log::verbose!("options: {}", describe_options(&options));
+    if let Ok((program, _)) = fratm_core::parse_with_options(&source, &options) {
+        check_imports(path, &source, &program, &options.dialect, settings);
+    }
 
-    match compile(&source, options) {
+    let compiling = log::timer("compiling");
+    let compiled = compile(&source, options);
+    drop(compiling);
+    match compiled {
         Ok(result) => {
-            let temp_path = std::env::temp_dir().join("fratm_temp.js");
+            for warning in &result.warnings { eprintln!("{} {}", "⚠ Warning:".yellow(), warning); }
+            // Node only runs `import`/`export` from a .mjs file
+            let temp_path = std::env::temp_dir().join(if result.is_module { "fratm_temp.mjs" } else { "fratm_temp.js" });
             let mut output = result.code;
-            if sourcemap { if let Some(sm) = &result.source_map { output.push_str("\n"); output.push_str(&sm.to_data_url()); } }
-            if let Err(e) = fs::write(&temp_path, &output) { eprintln!("{} {}", "Error: cannot write file:".red().bold(), e); std::process::exit(1); }
+            if sourcemap { if let Some(sm) = &result.source_map { output.push('\n'); output.push_str(&sm.to_data_url()); } }
+            if let Err(e) = fs::write(&temp_path, &output) { eprintln!("{} {}", "Error: cannot write file:".red().bold(), e); std::process::exit(status::IO); }
+            // ES module stack traces show the file as a URL
+            let js_path = if result.is_module { format!("file://{}", temp_path.display()) } else { temp_path.display().to_string() };
+            let remap = |stderr: &str| match &result.source_map {
+                Some(sm) => remap_stack_trace(stderr, &js_path, &path.display().to_string(), sm),
+                None => stderr.to_string(),
+            };
+            if let Some(inspect) = inspect {
+                run_inspected(&temp_path, &inspect, remap);
+                return;
+            }
+            log::debug!("running node {}", temp_path.display());
+            let running = log::timer("running");
             let cmd_output = Command::new("node").arg(&temp_path).output();
+            drop(running);
             match cmd_output {
                 Ok(out) => {
                     io::stdout().write_all(&out.stdout).unwrap();
-                    io::stderr().write_all(&out.stderr).unwrap();
-                    if !out.status.success() { std::process::exit(out.status.code().unwrap_or(1)); }
+                    let stderr = remap(&String::from_utf8_lossy(&out.stderr));
+                    io::stderr().write_all(stderr.as_bytes()).unwrap();
+                    if !out.status.success() { std::process::exit(status::RUNTIME); }
                 }
-                Err(e) => { eprintln!("{} {}", "Error: Node.js failed:".red().bold(), e); std::process::exit(1); }
+                Err(e) => { eprintln!("{} {}", "Error: Node.js failed:".red().bold(), e); std::process::exit(status::RUNTIME); }
             }
         }
-        Err(e) => { print_error(&source, &e); std::process::exit(1); }
+        Err(e) => { print_error(&source, &e, settings); std::process::exit(status::CODE); }
     }
 }
 
-fn build_file(path: &PathBuf, output: Option<PathBuf>, sourcemap: bool) {
-    let source = match fs::read_to_string(path) {
-        Ok(s) => s,
-        Err(e) => { eprintln!("{} {}", "Error: file not found:".red().bold(), e); std::process::exit(1); }
+/// Runs `node --inspect` on `script` with the terminal attached, streaming
+/// its stderr through `remap` and pointing out the DevTools URL as Node
+/// announces the debugger.
+fn run_inspected(script: &Path, inspect: &str, remap: impl Fn(&str) -> String) {
+    log::debug!("running node {} {}", inspect, script.display());
+    let child = Command::new("node").arg(inspect).arg(script).stderr(Stdio::piped()).spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => { eprintln!("{} {}", "Error: Node.js failed:".red().bold(), e); std::process::exit(status::RUNTIME); }
     };
+    if let Some(stderr) = child.stderr.take() {
+        for line in io::BufReader::new(stderr).lines().map_while(Result::ok) {
+            eprintln!("{}", remap(&line));
+            if let Some(url) = devtools_url(&line) {
+                eprintln!("{} {}", "🔍 Open in Chrome:".cyan().bold(), url);
+                eprintln!("   {}", "(or chrome://inspect, which finds it on its own)".dimmed());
+            }
+        }
+    }
+    match child.wait() {
+        Ok(exit) if !exit.success() => std::process::exit(status::RUNTIME),
+        Ok(_) => {}
+        Err(e) => { eprintln!("{} {}", "Error: Node.js failed:".red().bold(), e); std::process::exit(status::RUNTIME); }
+    }
+}
 
-    let options = CompileOptions { source_map: sourcemap, filename: Some(path.display().to_string()), minify: false };
+/// The DevTools URL for the `Debugger listening on ws://...` line of Node
+fn devtools_url(line: &str) -> Option<String> {
+    let socket = line.strip_prefix("Debugger listening on ws://")?.trim();
+    Some(format!("devtools://devtools/bundled/js_app.html?experiments=true&v8only=true&ws={}", socket))
+}
 
-    match compile(&source, options) {
-        Ok(result) => {
-            let out_path = output.unwrap_or_else(|| { let mut p = path.clone(); p.set_extension("js"); p });
-            let mut output_content = result.code;
-            if sourcemap {
-                if let Some(sm) = &result.source_map {
-                    let map_path = out_path.with_extension("js.map");
-                    if let Err(e) = fs::write(&map_path, sm.to_json_pretty()) {
-                        eprintln!("{} {}", "Warning: cannot write source map:".yellow(), e);
-                    } else {
-                        output_content.push_str(&format!("\n//# sourceMappingURL={}", map_path.file_name().unwrap().to_string_lossy()));
-                        println!("  {} {}", "Source map:".dimmed(), map_path.display());
-                    }
-                }
+/// Where `build --out-dir` writes: the JavaScript of a .fratm file under
+/// `root` goes to the same place under `out_dir`. Both are absolute.
+struct Layout {
+    root: PathBuf,
+    out_dir: PathBuf,
+}
+
+impl Layout {
+    fn output(&self, source: &Path) -> Option<PathBuf> {
+        Some(self.out_dir.join(source.strip_prefix(&self.root).ok()?).with_extension("js"))
+    }
+
+    /// Points the relative imports of `program` from `out_path` to where
+    /// they are: the JavaScript of the .fratm files built alongside, the
+    /// other files where they were. Exits on a .fratm file outside the root.
+    fn rewrite_imports(&self, path: &Path, out_path: &Path, program: &Program, options: &mut CompileOptions) {
+        let (dir, out_dir) = (path.parent().unwrap_or(Path::new("")), out_path.parent().unwrap_or(Path::new("")));
+        for stmt in &program.statements {
+            let Statement::Import { source, .. } = stmt else { continue };
+            if !source.starts_with("./") && !source.starts_with("../") { continue; }
+            let target = normalize(&dir.join(source));
+            let target = if fratm_core::modules::is_local(source) {
+                self.output(&target).unwrap_or_else(|| {
+                    eprintln!("{} {} imports {}, outside {}", "Error:".red().bold(), path.display(), source, self.root.display());
+                    std::process::exit(status::CODE);
+                })
+            } else {
+                target
+            };
+            let specifier = relative_path(&target, out_dir);
+            let specifier = if specifier.starts_with("../") { specifier } else { format!("./{}", specifier) };
+            log::debug!("{}: import {} as {}", path.display(), source, specifier);
+            options.module_aliases.insert(source.clone(), specifier);
+        }
+    }
+}
+
+/// `path` without `.` and `..`, which may not exist yet
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => { normal.pop(); }
+            component => normal.push(component),
+        }
+    }
+    normal
+}
+
+/// Builds the .fratm files of the directory `input`, or the file `input`
+/// and those it imports, into `out_dir`, keeping their directories.
+fn build_tree(input: &Path, out_dir: &Path, clean: bool, sourcemap: bool, banner_file: Option<PathBuf>, code: &CodeFlags, settings: Settings) {
+    let absolute = |path: &Path| fs::canonicalize(path).unwrap_or_else(|e| {
+        eprintln!("{} {}: {}", "Error: file not found:".red().bold(), path.display(), e);
+        std::process::exit(status::IO);
+    });
+    if let Err(e) = fs::create_dir_all(out_dir) {
+        eprintln!("{} {}: {}", "Error: cannot create directory:".red().bold(), out_dir.display(), e);
+        std::process::exit(status::IO);
+    }
+    let (input, out_dir) = (absolute(input), absolute(out_dir));
+    let (root, files) = if input.is_dir() {
+        let mut files = Vec::new();
+        find_sources(&input, &out_dir, &mut files);
+        files.sort();
+        (input, files)
+    } else {
+        let files = import_graph(&input);
+        // The closest directory holding them all
+        let mut root = input.parent().unwrap_or(Path::new("/")).to_path_buf();
+        while !files.iter().all(|file| file.starts_with(&root)) { root.pop(); }
+        (root, files)
+    };
+    log::verbose!("building {} file(s) of {} into {}", files.len(), root.display(), out_dir.display());
+
+    let layout = Layout { root, out_dir };
+    let (previous, mut dirs) = read_manifest(&layout.out_dir);
+    let mut written = Vec::new();
+    for file in &files {
+        let out_path = layout.output(file).expect("found under the root");
+        if let Some(dir) = out_path.parent() {
+            // Recorded so that --clean only removes the directories it made
+            dirs.extend(dir.ancestors().take_while(|dir| *dir != layout.out_dir && !dir.exists()).map(Path::to_path_buf));
+            if let Err(e) = fs::create_dir_all(dir) {
+                eprintln!("{} {}: {}", "Error: cannot create directory:".red().bold(), dir.display(), e);
+                std::process::exit(status::IO);
             }
-            if let Err(e) = fs::write(&out_path, &output_content) { eprintln!("{} {}", "Error: cannot write file:".red().bold(), e); std::process::exit(1); }
-            println!("{} {} → {}", errors::success_message().green().bold(), path.display(), out_path.display());
         }
-        Err(e) => { print_error(&source, &e); std::process::exit(1); }
+        build_file(file, Some(out_path.clone()), sourcemap, banner_file.clone(), code, Some(&layout), settings);
+        if sourcemap { written.push(out_path.with_extension("js.map")); }
+        written.push(out_path);
+    }
+    dirs.sort();
+    dirs.dedup();
+    let stale: Vec<PathBuf> = previous.into_iter().filter(|path| !written.contains(path) && path.exists()).collect();
+    if clean {
+        let removed = remove_stale(&stale, &mut dirs);
+        if removed > 0 && !log::quiet() { println!("{}", format!("Removed {} stale file(s)", removed).dimmed()); }
+    } else {
+        // Still this tool's to clean later
+        written.extend(stale);
+        dirs.retain(|dir| dir.exists());
+    }
+    write_manifest(&layout.out_dir, &written, &dirs);
+}
+
+/// What `build --out-dir` wrote in its output directory, so that --clean
+/// deletes only that: a path relative to it per line, the directories it
+/// created ending in `/`
+const MANIFEST: &str = ".fratm-build";
+
+/// The files and directories of the manifest in `out_dir`, as absolute
+/// paths. Entries going out of it, or into `node_modules` or a hidden
+/// directory, are left out.
+fn read_manifest(out_dir: &Path) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let (mut files, mut dirs) = (Vec::new(), Vec::new());
+    let Ok(manifest) = fs::read_to_string(out_dir.join(MANIFEST)) else { return (files, dirs) };
+    for line in manifest.lines() {
+        let (entry, is_dir) = line.strip_suffix('/').map_or((line, false), |entry| (entry, true));
+        let safe = !entry.is_empty() && Path::new(entry).components().all(|component| matches!(component,
+            Component::Normal(name) if !name.to_string_lossy().starts_with('.') && name != "node_modules"));
+        if !safe { continue; }
+        if is_dir { dirs.push(out_dir.join(entry)); } else { files.push(out_dir.join(entry)); }
+    }
+    (files, dirs)
+}
+
+fn write_manifest(out_dir: &Path, files: &[PathBuf], dirs: &[PathBuf]) {
+    let mut manifest = String::new();
+    for (path, suffix) in files.iter().map(|file| (file, "")).chain(dirs.iter().map(|dir| (dir, "/"))) {
+        manifest.push_str(&format!("{}{}\n", relative_path(path, out_dir), suffix));
+    }
+    if let Err(e) = fs::write(out_dir.join(MANIFEST), manifest) {
+        eprintln!("{} {}: {}", "Warning: cannot write build manifest:".yellow(), out_dir.display(), e);
+    }
+}
+
+/// The .fratm files under `dir`, leaving out `out_dir`, hidden directories and `node_modules`
+fn find_sources(dir: &Path, out_dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_dir() {
+            if path != out_dir && !name.starts_with('.') && name != "node_modules" { find_sources(&path, out_dir, files); }
+        } else if path.extension().is_some_and(|extension| extension == "fratm") {
+            files.push(path);
+        }
+    }
+}
+
+/// `entry` and the .fratm files it imports, directly or not, that exist
+fn import_graph(entry: &Path) -> Vec<PathBuf> {
+    let dialect = project_options(entry).build().dialect;
+    let mut files = vec![entry.to_path_buf()];
+    let mut next = 0;
+    while let Some(file) = files.get(next).cloned() {
+        next += 1;
+        let Ok(source) = fs::read_to_string(&file) else { continue };
+        let (program, _) = Parser::streaming(Lexer::new(&source).with_keywords(&dialect)).parse_recovering();
+        for stmt in &program.statements {
+            let Statement::Import { source, .. } = stmt else { continue };
+            if !fratm_core::modules::is_local(source) { continue; }
+            let Ok(module) = fs::canonicalize(file.parent().unwrap_or(Path::new("")).join(source)) else { continue };
+            if !files.contains(&module) { files.push(module); }
+        }
+    }
+    files
+}
+
+/// Deletes the `stale` outputs of earlier builds, then those of `dirs`,
+/// the directories builds created, left empty. Returns how many files went.
+fn remove_stale(stale: &[PathBuf], dirs: &mut Vec<PathBuf>) -> usize {
+    let mut removed = 0;
+    for path in stale {
+        log::verbose!("removing {}", path.display());
+        if fs::remove_file(path).is_ok() { removed += 1; }
+    }
+    // The deepest first, each only if empty
+    dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    dirs.retain(|dir| fs::remove_dir(dir).is_err() && dir.exists());
+    removed
+}
+
+fn build_file(path: &Path, output: Option<PathBuf>, sourcemap: bool, banner_file: Option<PathBuf>, code: &CodeFlags, layout: Option<&Layout>, settings: Settings) {
+    let source = read_source(path);
+
+    let out_path = output.unwrap_or_else(|| path.with_extension("js"));
+    // The map sits next to the output, so "sources" must be relative to that directory
+    let map_source = relative_path(path, out_path.parent().unwrap_or(Path::new("")));
+    let mut options = code.apply(project_options(path).source_map(sourcemap).filename(map_source).locale(settings.locale));
+    if let Some(banner_file) = banner_file {
+        match fs::read_to_string(&banner_file) {
+            Ok(banner) => options = options.banner(banner),
+            Err(e) => { eprintln!("{} {}: {}", "Error: cannot read banner:".red().bold(), banner_file.display(), e); std::process::exit(status::IO); }
+        }
+    }
+    if let Some(name) = out_path.file_name() {
+        options = options.output_filename(name.to_string_lossy());
+    }
+    let mut options = options.build();
+    log::verbose!("options: {}", describe_options(&options));
+
+    let parsing = log::timer("parsing");
+    let (program, comments) = match fratm_core::parse_with_options(&source, &options) {
+        Ok(parsed) => parsed,
+        Err(errors) => {
+            for error in errors { print_error(&source, &error.into(), settings); }
+            std::process::exit(status::CODE);
+        }
+    };
+    drop(parsing);
+    if let Some(layout) = layout { layout.rewrite_imports(path, &out_path, &program, &mut options); }
+    check_imports(path, &source, &program, &options.dialect, settings);
+    let warnings = fratm_core::linter::compile_warnings(&program, settings.locale);
+    for warning in &warnings {
+        eprint!("{}", render_diagnostic(&source, warning, RenderOptions { color: colored::control::SHOULD_COLORIZE.should_colorize(), locale: settings.locale }));
+    }
+    // Nothing is written for a build that fails
+    check_max_warnings(warnings.len(), code.max_warnings);
+
+    // Stream the code straight into the file instead of building it in memory
+    let generating = log::timer("generating");
+    let written = fs::File::create(&out_path).and_then(|file| {
+        let mut out = io::BufWriter::new(file);
+        // Annotating takes the source, which only compile has
+        let source_map = if options.annotate {
+            let result = compile(&source, options.clone()).expect("parsed above");
+            out.write_all(result.code.as_bytes())?;
+            result.source_map
+        } else {
+            generate_to(&program, comments, &options, &mut out)?
+        };
+        if let Some(sm) = source_map {
+            let map_path = out_path.with_extension("js.map");
+            if let Err(e) = fs::write(&map_path, sm.with_content(&source).to_json_pretty()) {
+                eprintln!("{} {}", "Warning: cannot write source map:".yellow(), e);
+            } else {
+                write!(out, "\n//# sourceMappingURL={}", map_path.file_name().unwrap().to_string_lossy())?;
+                if !log::quiet() { println!("  {} {}", "Source map:".dimmed(), map_path.display()); }
+            }
+        }
+        out.flush()
+    });
+    if let Err(e) = written { eprintln!("{} {}", "Error: cannot write file:".red().bold(), e); std::process::exit(status::IO); }
+    drop(generating);
+    if log::quiet() { return; }
+    let message = if settings.serious { "Compiled" } else { errors::success_message() };
+    println!("{} {} → {}", message.green().bold(), path.display(), out_path.display());
+}
+
+/// The options that matter for `-v`, on one line. Only the names of the
+/// environment variables: their values may be secrets.
+fn describe_options(options: &CompileOptions) -> String {
+    let mut parts = vec![
+        format!("target {:?}", options.target),
+        format!("modules {:?}", options.module_format),
+        format!("assertions {:?}", options.assertions),
+    ];
+    let flags = [
+        (options.source_map, "source map"), (options.minify, "minify"), (options.optimize, "optimize"), (options.use_strict, "use strict"),
+        (options.comments, "comments"), (options.annotate, "annotate"), (options.coverage, "coverage"), (options.interpolation, "interpolation"),
+    ];
+    parts.extend(flags.iter().filter(|(on, _)| *on).map(|(_, name)| name.to_string()));
+    if options.trace != Trace::Off { parts.push(format!("trace {:?}", options.trace)); }
+    parts.push(format!("dialect {}", options.dialect.name()));
+    if !options.defines.is_empty() {
+        parts.push(format!("defines {}", options.defines.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join(" ")));
+    }
+    if !options.env.is_empty() { parts.push(format!("env {}", options.env.keys().cloned().collect::<Vec<_>>().join(" "))); }
+    if !options.module_aliases.is_empty() { parts.push(format!("{} module alias(es)", options.module_aliases.len())); }
+    parts.join(", ")
+}
+
+/// Project settings, read from the nearest `fratm.json` up from the compiled file
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+struct Config {
+    banner: Option<String>,
+    /// Read relative to the directory of `fratm.json`
+    banner_file: Option<PathBuf>,
+    footer: Option<String>,
+    use_strict: bool,
+    comments: bool,
+    target: Option<Target>,
+    module_format: Option<ModuleFormat>,
+    /// Global object of a `"script"`
+    global_name: Option<String>,
+    style: Option<CodegenStyle>,
+    assertions: Option<Assertions>,
+    /// `"legacy"` compiles `@decoratore` to plain calls
+    decorators: Option<Decorators>,
+    /// `"Ciao {nome}!"` puts `nome` in the string
+    interpolation: bool,
+    /// A dialect pack: `{ "name": ..., "words": { neapolitan: dialect } }`
+    dialect: Option<KeywordTable>,
+    /// Module aliases: `{ "pizzeria": "@acme/pizzeria" }`
+    module_aliases: BTreeMap<String, String>,
+    /// Check that the npm packages imported are installed
+    check_packages: bool,
+    /// Names for `#si` directives: `{ "DEBUG": "1" }`
+    defines: BTreeMap<String, String>,
+    /// Values of `amb("NAME")`: `{ "API_URL": "..." }`
+    env: BTreeMap<String, String>,
+    /// A `.env` file with more of them, read relative to the directory of `fratm.json`
+    env_file: Option<PathBuf>,
+    /// `"all"` or `"annotated"` functions log their calls and returns
+    trace: Option<Trace>,
+    /// Function the traced ones call instead of console.log
+    trace_hook: Option<String>,
+}
+
+/// The nearest `fratm.json` up from `source`. With one, the file is part of
+/// a project, and its imports of other .fratm files are checked.
+fn project_config(source: &Path) -> Option<PathBuf> {
+    let dir = fs::canonicalize(source).ok().and_then(|p| p.parent().map(Path::to_path_buf)).unwrap_or_default();
+    dir.ancestors().map(|d| d.join("fratm.json")).find(|p| p.is_file())
+}
+
+/// The project's `fratm.json` and its settings. Exits if it is invalid.
+fn project(source: &Path) -> Option<(PathBuf, Config)> {
+    let config_path = project_config(source)?;
+    let text = fs::read_to_string(&config_path).unwrap_or_else(|e| invalid_config(&config_path, e.to_string()));
+    let config = serde_json::from_str(&text).unwrap_or_else(|e| invalid_config(&config_path, e.to_string()));
+    Some((config_path, config))
+}
+
+fn invalid_config(config_path: &Path, message: String) -> ! {
+    eprintln!("{} {}: {}", "Error: invalid config".red().bold(), config_path.display(), message);
+    std::process::exit(status::IO);
+}
+
+/// In a project, checks that the .fratm files `program` imports exist and
+/// export what it imports, and with `checkPackages` that the npm packages
+/// it imports are installed. Exits if they aren't.
+fn check_imports(path: &Path, source: &str, program: &Program, dialect: &KeywordTable, settings: Settings) {
+    let Some((_, config)) = project(path) else { return };
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut errors = fratm_core::modules::check_imports(program, |module| {
+        // Parsed with recovery: its own errors are reported when it is compiled
+        let Ok(module_source) = fs::read_to_string(dir.join(module)) else {
+            log::verbose!("import {}: not found", module);
+            return None;
+        };
+        let (program, _) = Parser::streaming(Lexer::new(&module_source).with_keywords(dialect)).parse_recovering();
+        let exports = fratm_core::modules::exports(&program);
+        log::verbose!("import {}: exports {}", module, exports.iter().map(|name| name.to_string()).collect::<Vec<_>>().join(", "));
+        Some(exports)
+    });
+    if config.check_packages {
+        let dependencies = package_dependencies(path);
+        errors.extend(fratm_core::modules::check_packages(program, &config.module_aliases, |package| {
+            let declared = dependencies.iter().any(|name| name == package);
+            let found = declared || is_installed(path, package);
+            log::debug!("package {}: {}", package, if declared { "in package.json" } else if found { "in node_modules" } else { "missing" });
+            found
+        }));
+        errors.sort_by_key(|error| error.span.start);
+    }
+    if errors.is_empty() { return; }
+    let options = RenderOptions { color: colored::control::SHOULD_COLORIZE.should_colorize(), locale: settings.locale };
+    for error in &errors {
+        eprint!("\n{}", render_diagnostic(source, error, options));
+    }
+    if !settings.serious {
+        eprintln!("\n{}", errors::random_encouragement().dimmed());
+    }
+    std::process::exit(status::CODE);
+}
+
+/// Every package the nearest `package.json` up from `source` depends on,
+/// dev, peer and optional dependencies included
+fn package_dependencies(source: &Path) -> Vec<String> {
+    let dir = fs::canonicalize(source).ok().and_then(|p| p.parent().map(Path::to_path_buf)).unwrap_or_default();
+    let Some(manifest) = dir.ancestors().map(|d| d.join("package.json")).find(|p| p.is_file()) else { return Vec::new() };
+    let Ok(manifest) = fs::read_to_string(manifest).map(|text| serde_json::from_str::<serde_json::Value>(&text).unwrap_or_default()) else {
+        return Vec::new();
+    };
+    ["dependencies", "devDependencies", "peerDependencies", "optionalDependencies"].iter()
+        .filter_map(|field| manifest.get(field)?.as_object())
+        .flat_map(|dependencies| dependencies.keys().cloned())
+        .collect()
+}
+
+/// Whether `package` is in a `node_modules` up from `source`, as Node looks for it
+fn is_installed(source: &Path, package: &str) -> bool {
+    let dir = fs::canonicalize(source).ok().and_then(|p| p.parent().map(Path::to_path_buf)).unwrap_or_default();
+    dir.ancestors().any(|d| d.join("node_modules").join(package).is_dir())
+}
+
+/// Compile options from the project's `fratm.json`, or the defaults if there is none
+fn project_options(source: &Path) -> CompileOptionsBuilder {
+    let Some((config_path, config)) = project(source) else {
+        log::verbose!("no fratm.json: default options");
+        return CompileOptions::builder();
+    };
+    log::verbose!("config: {}", config_path.display());
+
+    let mut options = CompileOptions::builder().use_strict(config.use_strict).comments(config.comments).interpolation(config.interpolation);
+    if let Some(banner_file) = &config.banner_file {
+        let banner_path = config_path.parent().unwrap_or(Path::new("")).join(banner_file);
+        let banner = fs::read_to_string(&banner_path).unwrap_or_else(|e| invalid_config(&config_path, format!("{}: {}", banner_path.display(), e)));
+        options = options.banner(banner);
     }
+    if let Some(banner) = config.banner { options = options.banner(banner); }
+    if let Some(footer) = config.footer { options = options.footer(footer); }
+    if let Some(target) = config.target { options = options.target(target); }
+    if let Some(module_format) = config.module_format { options = options.module_format(module_format); }
+    if let Some(global_name) = config.global_name { options = options.global_name(global_name); }
+    if let Some(style) = config.style { options = options.style(style); }
+    if let Some(assertions) = config.assertions { options = options.assertions(assertions); }
+    if let Some(decorators) = config.decorators { options = options.decorators(decorators); }
+    if let Some(trace) = config.trace { options = options.trace(trace); }
+    if let Some(hook) = config.trace_hook { options = options.trace_hook(hook); }
+    if let Some(dialect) = config.dialect { options = options.dialect(dialect); }
+    for (alias, specifier) in config.module_aliases { options = options.module_alias(alias, specifier); }
+    for (name, value) in config.defines { options = options.define(name, value); }
+    if let Some(env_file) = &config.env_file {
+        let env_path = config_path.parent().unwrap_or(Path::new("")).join(env_file);
+        let text = fs::read_to_string(&env_path).unwrap_or_else(|e| invalid_config(&config_path, format!("{}: {}", env_path.display(), e)));
+        let env = fratm_core::env::parse_env_file(&text).unwrap_or_else(|e| invalid_config(&config_path, format!("{}: {}", env_path.display(), e)));
+        for (name, value) in env { options = options.env(name, value); }
+    }
+    for (name, value) in config.env { options = options.env(name, value); }
+    options
 }
 
-fn run_repl() {
+fn run_repl(locale: Locale) {
     println!("{}", "🤌 FratmScript REPL - Write JavaScript the way it should be".cyan().bold());
-    println!("{}", format!("   Version {} - Type 'exit' to quit\n", fratm_core::version()).dimmed());
+    println!("{}", format!("   Version {} - Type 'exit' to quit\n", fratm_core::version_with_dialect(&KeywordTable::default())).dimmed());
     let stdin = io::stdin();
     let mut accumulated = String::new();
     loop {
@@ -126,12 +1000,12 @@ fn run_repl() {
         if trimmed == "esci" || trimmed == "exit" { println!("{}", "Goodbye! 👋".cyan()); break; }
         if trimmed.is_empty() { continue; }
         accumulated.push_str(&line);
-        match compile(&accumulated, Default::default()) {
+        match compile(&accumulated, CompileOptions::builder().locale(locale).build()) {
             Ok(result) => {
                 println!("{}", "─".repeat(40).dimmed());
                 println!("{}", result.code.trim().blue());
                 println!("{}", "─".repeat(40).dimmed());
-                let temp_path = std::env::temp_dir().join("fratm_repl.js");
+                let temp_path = std::env::temp_dir().join(if result.is_module { "fratm_repl.mjs" } else { "fratm_repl.js" });
                 if fs::write(&temp_path, &result.code).is_ok() {
                     if let Ok(output) = Command::new("node").arg(&temp_path).output() {
                         let stdout = String::from_utf8_lossy(&output.stdout);
@@ -143,7 +1017,7 @@ fn run_repl() {
                 accumulated.clear();
             }
             Err(e) => {
-                let msg = format!("{}", e);
+                let msg = e.localized(locale);
                 if !msg.contains("'}'") && !msg.contains("')'") {
                     println!("{} {}", "✗".red().bold(), msg.red());
                     accumulated.clear();
@@ -153,38 +1027,629 @@ fn run_repl() {
     }
 }
 
-fn show_tokens(path: &PathBuf) {
-    let source = match fs::read_to_string(path) { Ok(s) => s, Err(e) => { eprintln!("{} {}", "Error: file not found:".red().bold(), e); std::process::exit(1); } };
+/// The tutorial: the first exercise not done, until there are none left.
+/// Each answer is read like in the REPL, and what is done is saved at once.
+fn learn(progress_path: &Path, list: bool, reset: bool, locale: Locale) {
+    use fratm_core::learn::{Mistake, Progress, EXERCISES};
+
+    let save = |progress: &Progress| {
+        let written = serde_json::to_string_pretty(progress).map_err(|e| e.to_string()).and_then(|json| fs::write(progress_path, json).map_err(|e| e.to_string()));
+        if let Err(e) = written { eprintln!("{} {}: {}", "Warning: cannot save progress:".yellow(), progress_path.display(), e); }
+    };
+    let mut progress = if reset || !progress_path.exists() {
+        Progress::default()
+    } else {
+        let text = fs::read_to_string(progress_path).map_err(|e| e.to_string());
+        match text.and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string())) {
+            Ok(progress) => progress,
+            Err(e) => { eprintln!("{} {}: {}", "Error: invalid progress file".red().bold(), progress_path.display(), e); std::process::exit(status::IO); }
+        }
+    };
+    if reset { save(&progress); }
+    if list {
+        for (i, exercise) in EXERCISES.iter().enumerate() {
+            let mark = if progress.is_done(exercise) { "✓".green() } else { "·".dimmed() };
+            println!("{} {:>2}. {}", mark, i + 1, exercise.title);
+        }
+        return;
+    }
+
+    println!("{}", "🤌 FratmScript tutorial".cyan().bold());
+    println!("{}", "   Type 'hint' for a hint, 'clear' to start the answer over, 'skip' to move on, 'exit' to stop\n".dimmed());
+    let stdin = io::stdin();
+    let mut skipped = Vec::new();
+    while let Some(exercise) = EXERCISES.iter().find(|exercise| !progress.is_done(exercise) && !skipped.contains(&exercise.id)) {
+        let number = EXERCISES.iter().position(|other| other.id == exercise.id).unwrap_or(0) + 1;
+        println!("{} {}", format!("Exercise {}/{}:", number, EXERCISES.len()).yellow().bold(), exercise.title.bold());
+        println!("  {}", exercise.task);
+        let mut accumulated = String::new();
+        loop {
+            print!("{}", if accumulated.is_empty() { "learn> " } else { "  ...> " }.green());
+            io::stdout().flush().unwrap();
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).map_or(true, |read| read == 0) { return; }
+            match line.trim() {
+                "exit" | "esci" => { println!("{}", "Progress saved. Goodbye! 👋".cyan()); return; }
+                "hint" => { println!("  💡 {}", exercise.hint.cyan()); continue; }
+                "clear" => { accumulated.clear(); continue; }
+                "skip" => { skipped.push(exercise.id); break; }
+                "" => continue,
+                _ => {}
+            }
+            accumulated.push_str(&line);
+            match exercise.check_localized(&accumulated, locale) {
+                Ok(code) => {
+                    println!("{} {}", "✓".green().bold(), "Right! It compiles to:".green());
+                    println!("{}", code.trim().blue());
+                    println!();
+                    progress.complete(exercise);
+                    save(&progress);
+                    break;
+                }
+                // An unclosed brace or parenthesis: the answer goes on on the next line
+                Err(Mistake::Compile(message)) if message.contains("'}'") || message.contains("')'") => {}
+                Err(Mistake::Compile(message)) => {
+                    println!("{} {}", "✗".red().bold(), message.red());
+                    println!("{}", "  Try again, or type 'hint'".dimmed());
+                    accumulated.clear();
+                }
+                // What is there is kept: the next lines may add what is missing
+                Err(Mistake::Missing(message)) => println!("{} {}", "…".yellow().bold(), message.yellow()),
+            }
+        }
+    }
+    if progress.next().is_none() {
+        println!("{}", "🎉 Every exercise done: you speak FratmScript!".green().bold());
+    } else {
+        println!("{}", "Skipped exercises are left for next time: run `fratm learn` again.".dimmed());
+    }
+}
+
+fn show_tokens(path: &Path, format: TokensFormat) {
+    let source = read_source(path);
     let mut lexer = fratm_core::lexer::Lexer::new(&source);
     let tokens = lexer.tokenize();
+    if let TokensFormat::Json = format {
+        println!("{}", serde_json::to_string_pretty(&tokens).unwrap_or_default());
+        return;
+    }
     println!("{}", "Tokens:".cyan().bold());
-    for token in tokens { println!("  {:20} @ {}:{}", format!("{:?}", token.kind).yellow(), token.span.line.to_string().dimmed(), token.span.column.to_string().dimmed()); }
+    for token in tokens {
+        let span = token.span;
+        println!("  {:20} {:24} {:>11} @ {}", format!("{:?}", token.kind).yellow(), format!("{:?}", token.literal),
+            format!("{}..{}", span.start, span.end).dimmed(), format!("{}:{}-{}:{}", span.line, span.column, span.end_line, span.end_column).dimmed());
+    }
+}
+
+/// Prints the source with each token in brackets, alternating colors, so
+/// multi-word keywords and odd splits stand out.
+fn show_token_spans(path: &Path) {
+    let source = read_source(path);
+    let tokens = fratm_core::lexer::Lexer::new(&source).tokenize();
+    let mut out = String::new();
+    let mut end = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        let span = token.span;
+        if span.start < end || span.end > source.len() { continue; }
+        // Whitespace and comments between tokens
+        out.push_str(&source[end..span.start].dimmed().to_string());
+        let text = &source[span.start..span.end];
+        let marked = match token.kind {
+            fratm_core::lexer::TokenKind::Newline => format!("[⏎]{}", text),
+            fratm_core::lexer::TokenKind::Eof => "[EOF]".to_string(),
+            _ => format!("[{}]", text),
+        };
+        let marked = match token.kind {
+            fratm_core::lexer::TokenKind::Invalid(_) => marked.red().bold(),
+            _ if i % 2 == 0 => marked.yellow(),
+            _ => marked.cyan(),
+        };
+        out.push_str(&marked.to_string());
+        end = span.end;
+    }
+    println!("{}", out);
 }
 
-fn show_ast(path: &PathBuf) {
-    let source = match fs::read_to_string(path) { Ok(s) => s, Err(e) => { eprintln!("{} {}", "Error: file not found:".red().bold(), e); std::process::exit(1); } };
+fn show_ast(path: &Path, format: AstFormat, locale: Locale) {
+    let source = read_source(path);
+    if let AstFormat::Syntax = format {
+        print!("{}", fratm_core::syntax::parse(&source).dump());
+        return;
+    }
     let mut lexer = fratm_core::lexer::Lexer::new(&source);
     let tokens = lexer.tokenize();
-    let mut parser = fratm_core::parser::Parser::new(tokens);
+    let mut parser = fratm_core::parser::Parser::new(tokens).with_locale(locale);
     match parser.parse() {
-        Ok(program) => { println!("{}", "AST:".cyan().bold()); println!("{}", serde_json::to_string_pretty(&program).unwrap_or_default()); }
+        Ok(program) => match format {
+            AstFormat::Json => println!("{}", serde_json::to_string_pretty(&program.to_json()).unwrap_or_default()),
+            AstFormat::Tree => print!("{}", program.to_tree()),
+            AstFormat::Sexp => print!("{}", program.to_sexp()),
+            AstFormat::Syntax => unreachable!("printed above"),
+        },
         Err(errors) => { for e in errors { println!("{} {}", "✗".red().bold(), e.message.red()); } }
     }
 }
 
-fn print_error(source: &str, error: &fratm_core::errors::CompileError) {
-    let lines: Vec<&str> = source.lines().collect();
-    eprintln!("\n{} {}", "✗ Error:".red().bold(), error);
-    if let Some(line_num) = error.line() {
-        if line_num > 0 && line_num <= lines.len() {
-            let line = lines[line_num - 1];
-            eprintln!("  {} │ {}", line_num.to_string().dimmed(), line);
-            if let Some(col) = error.column() {
-                let pointer = " ".repeat(col.saturating_sub(1)) + "^";
-                eprintln!("  {} │ {}", " ".repeat(line_num.to_string().len()), pointer.red());
+fn show_outline(path: &Path) {
+    let source = read_source(path);
+    let (program, _) = fratm_core::parse_recovering(&source);
+    fn print(symbols: &[fratm_core::semantic::DocumentSymbol], depth: usize) {
+        for symbol in symbols {
+            let kind = format!("{:?}", symbol.kind).to_lowercase();
+            println!("{}{} {} {}", "  ".repeat(depth), kind.dimmed(), symbol.name.bold(), format!("{}:{}", symbol.span.line, symbol.span.column).dimmed());
+            print(&symbol.children, depth + 1);
+        }
+    }
+    print(&fratm_core::semantic::document_symbols(&program), 0);
+}
+
+/// Writes the Node.js loader, pointing it at this `fratm` binary
+fn emit_loader(output: &Path) {
+    let bin = std::env::current_exe().map(|path| path.display().to_string()).unwrap_or_else(|_| "fratm".to_string());
+    let loader = include_str!("loader.mjs").replace("__FRATM_BIN__", &serde_json::to_string(&bin).unwrap_or_default());
+    if let Err(e) = fs::write(output, loader) {
+        eprintln!("{} {}", "Error: cannot write file:".red().bold(), e);
+        std::process::exit(status::IO);
+    }
+    if log::quiet() { return; }
+    println!("{} {}", "Loader written:".green().bold(), output.display());
+    println!("  {}", format!("node --import ./{} app.js", output.display()).dimmed());
+}
+
+/// Prints the structural changes from `old_path` to `new_path`, and exits
+/// with 1 if there are any, like `diff`.
+/// One `fingerprint  path` line per file; files that don't parse are
+/// reported and make the exit status 1.
+fn fingerprint_files(paths: &[PathBuf], settings: Settings) {
+    let mut failed = false;
+    for path in paths {
+        let source = read_source(path);
+        let options = project_options(path).locale(settings.locale).build();
+        match fratm_core::parse_with_options(&source, &options) {
+            Ok((program, _)) => println!("{}  {}", fratm_core::fingerprint::fingerprint_program(&program), path.display()),
+            Err(mut errors) => { print_error(&source, &errors.remove(0).into(), settings); failed = true; }
+        }
+    }
+    if failed { std::process::exit(status::CODE); }
+}
+
+fn diff_files(old_path: &Path, new_path: &Path, settings: Settings) {
+    let parse = |path: &Path| {
+        let source = read_source(path);
+        let options = project_options(path).locale(settings.locale).build();
+        match fratm_core::parse_with_options(&source, &options) {
+            Ok((program, _)) => (source, program),
+            Err(mut errors) => { print_error(&source, &errors.remove(0).into(), settings); std::process::exit(2); }
+        }
+    };
+    let (old_source, old) = parse(old_path);
+    let (new_source, new) = parse(new_path);
+    let changes = fratm_core::diff::diff(&old, &new);
+    if changes.is_empty() {
+        if !log::quiet() { println!("{}", "No structural differences".dimmed()); }
+        return;
+    }
+    let first_line = |source: &str, span: Span| source[span.start..span.end].lines().next().unwrap_or("").to_string();
+    for change in &changes {
+        let (source, span, path) = match change.new {
+            Some(span) => (&new_source, span, new_path),
+            None => (&old_source, change.old.unwrap_or_default(), old_path),
+        };
+        let sign = match change.kind {
+            ChangeKind::Added => "+".green().bold(),
+            ChangeKind::Removed => "-".red().bold(),
+            ChangeKind::Changed => "~".yellow().bold(),
+        };
+        println!("{} {} {}", sign, change.label.bold(), format!("{}:{}", path.display(), span.line).dimmed());
+        println!("    {}", first_line(source, span).dimmed());
+    }
+    std::process::exit(1);
+}
+
+/// Maps the counts of every covered file in `counts_path` to its .fratm
+/// lines, through the source map `build --coverage` wrote next to it.
+fn coverage_report(counts_path: &Path, format: CoverageFormat) {
+    let text = fs::read_to_string(counts_path).unwrap_or_else(|e| {
+        eprintln!("{} {}: {}", "Error: cannot read coverage counts:".red().bold(), counts_path.display(), e);
+        std::process::exit(status::IO);
+    });
+    let counts: BTreeMap<String, Counts> = serde_json::from_str(&text).unwrap_or_else(|e| {
+        eprintln!("{} {}: {}", "Error: invalid coverage counts:".red().bold(), counts_path.display(), e);
+        std::process::exit(status::IO);
+    });
+    let mut files = Vec::new();
+    for (file, counts) in &counts {
+        // ES modules register under their URL
+        let js_path = PathBuf::from(file.strip_prefix("file://").unwrap_or(file));
+        let map_path = js_path.with_extension("js.map");
+        let read = || -> Result<(String, SourceMap), String> {
+            let code = fs::read_to_string(&js_path).map_err(|e| e.to_string())?;
+            let map = fs::read_to_string(&map_path).map_err(|e| format!("{}: {}", map_path.display(), e))?;
+            Ok((code, SourceMap::parse(&map).map_err(|e| e.to_string())?))
+        };
+        match read() {
+            Ok((code, map)) => {
+                let mut coverage = fratm_core::coverage::map_counts(&code, &map, counts);
+                // Sources are relative to the map
+                coverage.source = map_path.parent().unwrap_or(Path::new("")).join(&coverage.source).display().to_string();
+                files.push(coverage);
+            }
+            Err(e) => eprintln!("{} {}: {}", "⚠ Warning: skipped".yellow(), js_path.display(), e),
+        }
+    }
+    match format {
+        CoverageFormat::Lcov => print!("{}", fratm_core::coverage::lcov(&files)),
+        CoverageFormat::Text => {
+            let percent = |(hit, found): (usize, usize)| {
+                let percent = if found == 0 { 100.0 } else { hit as f64 * 100.0 / found as f64 };
+                let text = format!("{:.1}% ({}/{})", percent, hit, found);
+                if percent >= 80.0 { text.green() } else if percent >= 50.0 { text.yellow() } else { text.red() }
+            };
+            for file in &files {
+                println!("{}  lines {}  branches {}", file.source.bold(), percent(file.lines_hit()), percent(file.branches_hit()));
+                let missed: Vec<String> = file.lines.iter().filter(|(_, &count)| count == 0).map(|(line, _)| line.to_string()).collect();
+                if !missed.is_empty() { println!("  {} {}", "never run:".dimmed(), missed.join(", ")); }
+            }
+        }
+    }
+}
+
+/// Rewrites each file with the fixes of [`fratm_core::fix::upgrade`], or
+/// with `dry_run` prints the lines that would change.
+fn fix_files(paths: &[PathBuf], dry_run: bool, from: Option<&Path>, settings: Settings) {
+    let from = from.map(read_dialect);
+    let mut changed = 0;
+    for path in paths {
+        let source = read_source(path);
+        let to = project_options(path).build().dialect;
+        let fixes = match fratm_core::fix::upgrade(&source, from.as_ref().unwrap_or(&to), &to) {
+            Ok(fixes) => fixes,
+            Err(e) => { print_error(&source, &e, settings); std::process::exit(status::CODE); }
+        };
+        if fixes.is_empty() { continue; }
+        changed += 1;
+        let fixed = fratm_core::fix::apply(&source, &fixes);
+        if !dry_run {
+            if let Err(e) = fs::write(path, &fixed) {
+                eprintln!("{} {}: {}", "Error: cannot write file:".red().bold(), path.display(), e);
+                std::process::exit(status::IO);
             }
+            if !log::quiet() { println!("{} {} ({} fix{})", "Fixed".green().bold(), path.display(), fixes.len(), if fixes.len() == 1 { "" } else { "es" }); }
+            continue;
         }
+        print_diff(path, &source, &fixed);
+    }
+    match (changed, dry_run) {
+        _ if log::quiet() => {}
+        (0, _) => println!("{}", "Nothing to fix".dimmed()),
+        (n, true) => println!("{}", format!("{} file(s) would change", n).dimmed()),
+        (n, false) => println!("{}", format!("{} file(s) fixed", n).dimmed()),
+    }
+}
+
+/// Prints the lines `new` changed from `old`. Each changed line is shown
+/// on its own, unless lines were added or removed in between.
+fn print_diff(path: &Path, old: &str, new: &str) {
+    let (old, new): (Vec<&str>, Vec<&str>) = (old.lines().collect(), new.lines().collect());
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+    println!("{}", format!("--- {}", path.display()).bold());
+    if old.len() == new.len() {
+        for (number, (old, new)) in old.iter().zip(new).enumerate().filter(|(_, (old, new))| old != new) {
+            println!("{}", format!("@@ {} @@", prefix + number + 1).cyan());
+            println!("{}", format!("-{}", old).red());
+            println!("{}", format!("+{}", new).green());
+        }
+        return;
+    }
+    println!("{}", format!("@@ {} @@", prefix + 1).cyan());
+    for line in old { println!("{}", format!("-{}", line).red()); }
+    for line in new { println!("{}", format!("+{}", line).green()); }
+}
+
+/// Exits if there are more than `max` warnings
+fn check_max_warnings(warnings: usize, max: Option<usize>) {
+    let Some(max) = max.filter(|&max| warnings > max) else { return };
+    eprintln!("{} {} warning(s), more than --max-warnings {}", "Error:".red().bold(), warnings, max);
+    std::process::exit(status::CODE);
+}
+
+/// What `check` and `lint` report, and whether they fix it
+struct CheckFlags {
+    /// Also the type checker's warnings, as errors if `strict`
+    types: bool,
+    strict: bool,
+    /// Every rule of the linter, not only the warnings of `build`
+    lint: bool,
+    fix: bool,
+    max_warnings: Option<usize>,
+    max_errors: Option<usize>,
+}
+
+/// Rounds of fixes per file: a fix can uncover another, like a second missing `}`
+const FIX_ROUNDS: usize = 10;
+
+/// Prints the errors and warnings of each file, after applying their fixes
+/// with `--fix`, and exits with 1 if there are errors, or more warnings
+/// than `--max-warnings`.
+fn check_files(paths: &[PathBuf], flags: CheckFlags, settings: Settings) {
+    let options = RenderOptions { color: colored::control::SHOULD_COLORIZE.should_colorize(), locale: settings.locale };
+    let (mut errors, mut warnings) = (0, 0);
+    let _checking = log::timer("checking");
+    for path in paths {
+        log::debug!("checking {}", path.display());
+        let original = read_source(path);
+        let parse_options = project_options(path).locale(settings.locale).parser_options(parser_options(flags.max_errors)).build();
+        let diagnose = |source: &str| {
+            let program = match fratm_core::parse_with_options(source, &parse_options) {
+                Ok((program, _)) => program,
+                Err(parse_errors) => return parse_errors,
+            };
+            let mut diagnostics = if flags.lint { fratm_core::linter::lint_source(source, &program, settings.locale) } else { fratm_core::linter::compile_warnings(&program, settings.locale) };
+            if flags.types {
+                diagnostics.extend(fratm_core::types::check(&program).into_iter().map(|mut d| {
+                    if flags.strict { d.severity = Severity::Error; }
+                    d
+                }));
+            }
+            diagnostics
+        };
+        let (mut source, mut diagnostics) = (original.clone(), diagnose(&original));
+        for _ in 0..FIX_ROUNDS {
+            let fixes = fratm_core::fix::fixes_of(&diagnostics);
+            if !flags.fix || fixes.is_empty() { break; }
+            log::verbose!("{}: applying {} fix(es)", path.display(), fixes.len());
+            source = fratm_core::fix::apply(&source, &fixes);
+            diagnostics = diagnose(&source);
+        }
+        if source != original {
+            if let Err(e) = fs::write(path, &source) {
+                eprintln!("{} {}: {}", "Error: cannot write file:".red().bold(), path.display(), e);
+                std::process::exit(status::IO);
+            }
+            print_diff(path, &original, &source);
+        }
+        for diagnostic in &diagnostics {
+            match diagnostic.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+            eprint!("{}", render_diagnostic(&source, diagnostic, options));
+        }
+    }
+    if !log::quiet() || errors > 0 {
+        println!("{}", format!("{} error(s), {} warning(s) in {} file(s)", errors, warnings, paths.len()).dimmed());
+    }
+    if errors > 0 {
+        std::process::exit(status::CODE);
+    }
+    check_max_warnings(warnings, flags.max_warnings);
+}
+
+/// Reads a dialect pack, `{ "name": ..., "words": { neapolitan: dialect } }`.
+fn read_dialect(path: &Path) -> KeywordTable {
+    let fail = |message: String| -> ! {
+        eprintln!("{} {}: {}", "Error: invalid dialect".red().bold(), path.display(), message);
+        std::process::exit(status::IO);
+    };
+    let text = fs::read_to_string(path).unwrap_or_else(|e| fail(e.to_string()));
+    serde_json::from_str(&text).unwrap_or_else(|e| fail(e.to_string()))
+}
+
+fn show_grammar(format: GrammarFormat, dialect: Option<&Path>) {
+    let table = dialect.map(read_dialect).unwrap_or_default();
+    match format {
+        GrammarFormat::Tmlanguage => print!("{}", fratm_core::grammar::tm_language(&table)),
+        GrammarFormat::TreeSitter => print!("{}", fratm_core::grammar::tree_sitter(&table)),
+    }
+}
+
+/// The keyword cheat sheet, built from the table the lexer uses
+fn show_keywords(term: Option<&str>, format: TranslateFormat, dialect: Option<&Path>) {
+    let table = dialect.map(read_dialect).unwrap_or_default();
+    let mut entries = fratm_core::lexer::keywords::cheat_sheet(&table);
+    if let Some(term) = term {
+        entries.retain(|entry| entry.matches(term));
+        if entries.is_empty() {
+            eprintln!("{} '{}'", "No keyword matches".yellow(), term);
+            std::process::exit(1);
+        }
+    }
+    match format {
+        TranslateFormat::Json => println!("{}", serde_json::to_string_pretty(&entries).unwrap_or_default()),
+        TranslateFormat::Markdown => print!("{}", fratm_core::lexer::keywords::cheat_sheet_markdown(&entries)),
+        TranslateFormat::Text => {
+            let width = |text: &str| text.chars().count();
+            let phrase_width = entries.iter().map(|entry| width(&entry.phrase)).max().unwrap_or(0);
+            let js_width = entries.iter().map(|entry| width(entry.javascript)).max().unwrap_or(0);
+            for entry in &entries {
+                let phrase = format!("{}{}", entry.phrase, " ".repeat(phrase_width - width(&entry.phrase)));
+                println!("{}  {:js_width$}  {}", phrase.cyan().bold(), entry.javascript, entry.description);
+                println!("{}  {}", " ".repeat(phrase_width + js_width + 4), entry.example.dimmed());
+            }
+        }
+    }
+}
+
+/// Reads a source file, see [`decode_source`]
+fn read_source(path: &Path) -> String {
+    match fs::read(path) {
+        Ok(bytes) => decode_source(&bytes, path),
+        Err(e) => { eprintln!("{} {}", "Error: file not found:".red().bold(), e); std::process::exit(status::IO); }
+    }
+}
+
+/// The text of the source file at `path`. UTF-8, with or without a BOM, is
+/// read as is, as is UTF-16 with a BOM; anything else is taken for
+/// Windows-1252 (a superset of Latin-1), with a warning since the guess may be wrong.
+fn decode_source(bytes: &[u8], path: &Path) -> String {
+    let (encoding, source) = match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => match std::str::from_utf8(rest) {
+            Ok(source) => return source.to_string(),
+            Err(_) => ("Windows-1252", decode_windows_1252(rest)),
+        },
+        [0xFF, 0xFE, rest @ ..] => ("UTF-16LE", decode_utf16(rest, u16::from_le_bytes)),
+        [0xFE, 0xFF, rest @ ..] => ("UTF-16BE", decode_utf16(rest, u16::from_be_bytes)),
+        bytes => match std::str::from_utf8(bytes) {
+            Ok(source) => return source.to_string(),
+            Err(e) => {
+                let line = bytes[..e.valid_up_to()].iter().filter(|&&b| b == b'\n').count() + 1;
+                let encoding = if bytes.iter().any(|b| (0x80..0xA0).contains(b)) { "Windows-1252" } else { "Latin-1" };
+                eprintln!("{} {} is not UTF-8 (line {}), reading it as {}. Save it as UTF-8 to be sure accents come out right.",
+                    "⚠ Warning:".yellow().bold(), path.display(), line, encoding);
+                return decode_windows_1252(bytes);
+            }
+        },
+    };
+    eprintln!("{} {} is {}, converted to UTF-8", "⚠ Warning:".yellow().bold(), path.display(), encoding);
+    source
+}
+
+/// Windows-1252 is Latin-1 except for 0x80-0x9F, which hold `€`, curly quotes and a few letters
+fn decode_windows_1252(bytes: &[u8]) -> String {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+        '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+    ];
+    bytes.iter().map(|&b| if (0x80..0xA0).contains(&b) { HIGH[b as usize - 0x80] } else { b as char }).collect()
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    String::from_utf16_lossy(&bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]])).collect::<Vec<_>>())
+}
+
+/// Rewrite `js_path:line:col` locations in Node output to the original .fratm positions.
+fn remap_stack_trace(text: &str, js_path: &str, fratm_path: &str, map: &SourceMap) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find(js_path) {
+        result.push_str(&rest[..idx]);
+        let after = &rest[idx + js_path.len()..];
+        let (line, after_line) = take_position(after);
+        let (col, after_col) = if line.is_some() { take_position(after_line) } else { (None, after_line) };
+        // Node positions are 1-indexed, source map positions 0-indexed.
+        // A bare line (no column) resolves through the last mapping on that line
+        let gen_col = col.map_or(usize::MAX, |c| c.saturating_sub(1));
+        match line.and_then(|l| map.lookup(l.saturating_sub(1), gen_col)) {
+            Some((src_line, src_col, _)) if col.is_some() => {
+                result.push_str(&format!("{}:{}:{}", fratm_path, src_line + 1, src_col + 1));
+                rest = after_col;
+            }
+            Some((src_line, _, _)) => {
+                result.push_str(&format!("{}:{}", fratm_path, src_line + 1));
+                rest = after_line;
+            }
+            None => {
+                result.push_str(js_path);
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Parse a `:<number>` prefix, returning the number and the remaining text.
+fn take_position(text: &str) -> (Option<usize>, &str) {
+    let Some(digits) = text.strip_prefix(':') else { return (None, text) };
+    let len = digits.bytes().take_while(|b| b.is_ascii_digit()).count();
+    match digits[..len].parse() {
+        Ok(n) => (Some(n), &digits[len..]),
+        Err(_) => (None, text),
+    }
+}
+
+/// Path of `target` as seen from `base_dir`, using forward slashes as source maps expect.
+fn relative_path(target: &Path, base_dir: &Path) -> String {
+    let absolute = |p: &Path| fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+    let target = absolute(target);
+    let base = absolute(if base_dir.as_os_str().is_empty() { Path::new(".") } else { base_dir });
+    let target_parts: Vec<Component> = target.components().collect();
+    let base_parts: Vec<Component> = base.components().collect();
+    let common = target_parts.iter().zip(&base_parts).take_while(|(a, b)| a == b).count();
+    let mut parts: Vec<String> = vec!["..".to_string(); base_parts.len() - common];
+    parts.extend(target_parts[common..].iter().map(|c| c.as_os_str().to_string_lossy().into_owned()));
+    parts.join("/")
+}
+
+fn print_error(source: &str, error: &fratm_core::errors::CompileError, settings: Settings) {
+    let options = RenderOptions { color: colored::control::SHOULD_COLORIZE.should_colorize(), locale: settings.locale };
+    eprint!("\n{}", render_diagnostic(source, error.diagnostic(), options));
+    if !settings.serious {
+        eprintln!("\n{}", errors::random_encouragement().dimmed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8]) -> String {
+        decode_source(bytes, Path::new("pizza.fratm"))
+    }
+
+    #[test]
+    fn test_decode_utf8() {
+        assert_eq!(decode("tien x = \"è\"".as_bytes()), "tien x = \"è\"");
+        assert_eq!(decode(b"\xEF\xBB\xBFtien x = \"\xC3\xA8\""), "tien x = \"è\"");
+    }
+
+    #[test]
+    fn test_decode_utf16() {
+        let utf16 = |unit: fn(u16) -> [u8; 2], bom: [u8; 2]| bom.into_iter().chain("x = 'è'".encode_utf16().flat_map(unit)).collect::<Vec<_>>();
+        assert_eq!(decode(&utf16(u16::to_le_bytes, [0xFF, 0xFE])), "x = 'è'");
+        assert_eq!(decode(&utf16(u16::to_be_bytes, [0xFE, 0xFF])), "x = 'è'");
+    }
+
+    #[test]
+    fn test_decode_latin1() {
+        assert_eq!(decode(b"stamm a d\xEC(\"caff\xE8\")"), "stamm a dì(\"caffè\")");
+        // 0x80-0x9F are Windows-1252, not control characters
+        assert_eq!(decode(b"\x93pizza\x94 \x80"), "“pizza” €");
+    }
+
+    /// An empty directory of its own under the system's temporary one
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fratm_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(relative_path(Path::new("/progetto/src/pizze/margherita.fratm"), Path::new("/progetto/src")), "pizze/margherita.fratm");
+        assert_eq!(relative_path(Path::new("/progetto/src/main.fratm"), Path::new("/progetto/dist/js")), "../../src/main.fratm");
+        // Paths that exist are resolved first, `..` included
+        let dir = temp_dir("relative");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join("dist")).unwrap();
+        fs::write(dir.join("src/main.fratm"), "").unwrap();
+        assert_eq!(relative_path(&dir.join("dist/../src/main.fratm"), &dir.join("dist")), "../src/main.fratm");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_manifest() {
+        let out_dir = temp_dir("manifest");
+        fs::create_dir_all(out_dir.join("pizze")).unwrap();
+        let files = vec![out_dir.join("main.js"), out_dir.join("pizze/margherita.js")];
+        for file in files.iter().chain([&out_dir.join("mio.js")]) { fs::write(file, "").unwrap(); }
+        let dirs = vec![out_dir.join("pizze")];
+        write_manifest(&out_dir, &files, &dirs);
+        assert_eq!(fs::read_to_string(out_dir.join(MANIFEST)).unwrap(), "main.js\npizze/margherita.js\npizze/\n");
+        assert_eq!(read_manifest(&out_dir), (files, dirs));
+
+        // --clean deletes what the manifest lists and nothing else
+        let (stale, mut dirs) = read_manifest(&out_dir);
+        assert_eq!(remove_stale(&stale, &mut dirs), 2);
+        assert!(dirs.is_empty() && !out_dir.join("pizze").exists());
+        assert!(out_dir.join("mio.js").exists());
+
+        // Entries out of the directory or into hidden ones are left out
+        fs::write(out_dir.join(MANIFEST), "../fuori.js\n.git/config\nnode_modules/x.js\nmain.js\n").unwrap();
+        assert_eq!(read_manifest(&out_dir), (vec![out_dir.join("main.js")], vec![]));
+        fs::remove_dir_all(out_dir).unwrap();
     }
-    if let Some(suggestion) = errors::get_suggestion(error) { eprintln!("\n{}", suggestion.yellow()); }
-    eprintln!("\n{}", errors::random_encouragement().dimmed());
 }
@@ -0,0 +1,222 @@
+//! `fratm serve`: the compiler as a long-running JSON-RPC 2.0 server
+//!
+//! Build-tool plugins start it once and send it one request per line,
+//! instead of spawning `fratm` for every file. Results are cached by
+//! request, so a file that didn't change is answered without compiling
+//! it again, and every client of a socket shares the cache.
+//!
+//! Methods, all taking named params:
+//!
+//! - `compile { source, filename?, sourceMap? }` → `{ code, sourceMap, warnings }`,
+//!   with the options of the `fratm.json` next to `filename`
+//! - `check { source }` → `{ diagnostics }`: syntax errors, or lint warnings
+//! - `format { source, indentWidth?, useTabs? }` → `{ code }`
+//! - `tokenize { source }` → the token list
+//! - `shutdown` → `null`, then the server stops reading
+//!
+//! A source that doesn't compile is an error response with code `-32000`
+//! and the diagnostic as `data`.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use fratm_core::errors::{CompileError, Locale};
+use fratm_core::formatter::FormatOptions;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Entries kept before the cache starts over
+const CACHE_LIMIT: usize = 1000;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const COMPILE_ERROR: i64 = -32000;
+
+type Cache = Arc<Mutex<HashMap<String, Result<Value, RpcError>>>>;
+
+#[derive(Clone)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), data: None }
+    }
+}
+
+impl From<CompileError> for RpcError {
+    fn from(error: CompileError) -> Self {
+        Self { code: COMPILE_ERROR, message: error.message.clone(), data: serde_json::to_value(&*error).ok() }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompileParams {
+    source: String,
+    filename: Option<String>,
+    #[serde(default)]
+    source_map: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FormatParams {
+    source: String,
+    indent_width: Option<usize>,
+    use_tabs: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct SourceParams {
+    source: String,
+}
+
+/// Serves requests on stdin/stdout until `shutdown` or the end of input.
+pub fn serve_stdio() {
+    let cache = Cache::default();
+    let stdin = io::stdin();
+    serve(stdin.lock(), io::stdout().lock(), &cache);
+}
+
+/// Serves every client of the Unix socket at `path`, one thread each.
+#[cfg(unix)]
+pub fn serve_socket(path: &Path) -> io::Result<()> {
+    use std::os::unix::net::UnixListener;
+
+    // A socket file left over by a server that didn't shut down cleanly
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    let cache = Cache::default();
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let cache = cache.clone();
+        std::thread::spawn(move || {
+            if let Ok(reader) = stream.try_clone() {
+                serve(BufReader::new(reader), stream, &cache);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn serve_socket(_path: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "sockets are only supported on Unix, use stdio"))
+}
+
+fn serve(input: impl BufRead, mut output: impl Write, cache: &Cache) {
+    for line in input.lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (response, stop) = handle(&line, cache);
+        if let Some(response) = response {
+            if writeln!(output, "{}", response).and_then(|_| output.flush()).is_err() {
+                break;
+            }
+        }
+        if stop {
+            break;
+        }
+    }
+}
+
+/// The response to one request line, if it needs one, and whether to stop
+fn handle(line: &str, cache: &Cache) -> (Option<Value>, bool) {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return (Some(response(Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string())))), false),
+    };
+    let id = request.get("id").cloned();
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return (Some(response(id.unwrap_or(Value::Null), Err(RpcError::new(INVALID_REQUEST, "missing method")))), false);
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    let stop = method == "shutdown";
+    let result = if stop { Ok(Value::Null) } else { cached(method, params, cache) };
+    // Notifications (no id) get no response
+    (id.map(|id| response(id, result)), stop)
+}
+
+fn cached(method: &str, params: Value, cache: &Cache) -> Result<Value, RpcError> {
+    let key = format!("{}:{}", method, params);
+    if let Some(result) = cache.lock().ok().and_then(|cache| cache.get(&key).cloned()) {
+        crate::log::debug!("{}: cached", method);
+        return result;
+    }
+    crate::log::debug!("{}: not cached", method);
+    let result = call(method, params);
+    if let Ok(mut cache) = cache.lock() {
+        if cache.len() >= CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert(key, result.clone());
+    }
+    result
+}
+
+fn call(method: &str, params: Value) -> Result<Value, RpcError> {
+    fn parse<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+        serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+    }
+    match method {
+        "compile" => {
+            let params: CompileParams = parse(params)?;
+            let mut options = match &params.filename {
+                Some(filename) => crate::project_options(Path::new(filename)).filename(filename),
+                None => fratm_core::CompileOptions::builder(),
+            };
+            options = options.source_map(params.source_map);
+            let result = fratm_core::compile(&params.source, options.build())?;
+            Ok(json!({
+                "code": result.code,
+                "sourceMap": result.source_map.map(|map| map.to_json()),
+                "warnings": result.warnings,
+            }))
+        }
+        "check" => {
+            let params: SourceParams = parse(params)?;
+            let (program, errors) = fratm_core::parse_recovering(&params.source);
+            let diagnostics = if errors.is_empty() { fratm_core::linter::lint_source(&params.source, &program, Locale::default()) } else { errors };
+            Ok(json!({ "diagnostics": diagnostics }))
+        }
+        "format" => {
+            let params: FormatParams = parse(params)?;
+            let defaults = FormatOptions::default();
+            let options = FormatOptions {
+                indent_width: params.indent_width.unwrap_or(defaults.indent_width),
+                use_tabs: params.use_tabs.unwrap_or(defaults.use_tabs),
+            };
+            Ok(json!({ "code": fratm_core::formatter::format(&params.source, &options)? }))
+        }
+        "tokenize" => {
+            let params: SourceParams = parse(params)?;
+            serde_json::to_value(fratm_core::lexer::Lexer::new(&params.source).tokenize()).map_err(|e| RpcError::new(COMPILE_ERROR, e.to_string()))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method: {}", method))),
+    }
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => {
+            let mut error_object = json!({ "code": error.code, "message": error.message });
+            if let Some(data) = error.data {
+                error_object["data"] = data;
+            }
+            json!({ "jsonrpc": "2.0", "id": id, "error": error_object })
+        }
+    }
+}
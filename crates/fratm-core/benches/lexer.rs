@@ -0,0 +1,34 @@
+//! Lexer and parser throughput on the examples, repeated to a sizeable file.
+//! See `phases.rs` for every phase on synthetic programs.
+//!
+//! Run with `cargo bench -p fratm-core`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use fratm_core::lexer::Lexer;
+use fratm_core::parser::Parser;
+
+const EXAMPLES: &[&str] = &[
+    include_str!("../../../examples/01_salutatore.fratm"),
+    include_str!("../../../examples/02_variabili_matematica.fratm"),
+    include_str!("../../../examples/03_condizionali.fratm"),
+    include_str!("../../../examples/04_loop.fratm"),
+    include_str!("../../../examples/06_funzioni.fratm"),
+    include_str!("../../../examples/08_classi.fratm"),
+];
+
+fn source() -> String {
+    EXAMPLES.join("\n").repeat(50)
+}
+
+fn lexer(c: &mut Criterion) {
+    let source = source();
+    let mut group = c.benchmark_group("lexer");
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    group.bench_function("tokenize", |b| b.iter(|| Lexer::new(black_box(&source)).tokenize()));
+    group.bench_function("parse", |b| b.iter(|| Parser::streaming(Lexer::new(black_box(&source))).parse_recovering()));
+    group.bench_function("parse_arena", |b| b.iter(|| Parser::streaming(Lexer::new(black_box(&source))).parse_arena()));
+    group.finish();
+}
+
+criterion_group!(benches, lexer);
+criterion_main!(benches);
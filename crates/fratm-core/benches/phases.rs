@@ -0,0 +1,93 @@
+//! Each compiler phase on its own, on synthetic programs of growing size and
+//! on a deeply nested expression, so a change to one phase can be measured.
+//!
+//! Run with `cargo bench -p fratm-core --bench phases`, or add a filter such
+//! as `phases/parse/large`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use fratm_core::codegen::CodeGen;
+use fratm_core::lexer::Lexer;
+use fratm_core::parser::{Parser, DEFAULT_MAX_DEPTH};
+use fratm_core::CompileOptions;
+
+/// A bit of everything: declarations, functions, a class, loops, objects,
+/// strings with escapes, calls and member chains. `i` keeps names apart.
+fn unit(i: usize) -> String {
+    format!(
+        r#"// Unit {i}
+chist è menu{i} = ["Margherita", "Marinara", "Diavola \"piccante\""]
+tien totale{i} = 0
+
+facc prezzo{i}(pizza, quantità) {{
+    si (pizza === "Margherita") {{
+        piglie 5 * quantità
+    }} sinnò si (pizza === "Marinara" o quantità > 10) {{
+        piglie 4.5 * quantità - 1
+    }}
+    piglie (6 + quantità) * 2 ** 2 % 7
+}}
+
+na famiglie Pizzeria{i} {{
+    facc costruttore(nome) {{
+        stu cos.nome = nome
+        stu cos.ordini = []
+    }}
+
+    facc ordina(pizza) {{
+        stu cos.ordini.push({{ pizza: pizza, quando: Date.now(), pagato: sfòls }})
+        piglie stu cos.ordini.length
+    }}
+}}
+
+pe (tien i = 0; i < menu{i}.length; i = i + 1) {{
+    totale{i} = totale{i} + prezzo{i}(menu{i}[i], i + 1)
+}}
+
+mentre che (totale{i} > 100 e manco (totale{i} === 0)) {{
+    totale{i} = totale{i} / 2
+}}
+
+chist è pizzeria{i} = nu bell Pizzeria{i}("Da Gennaro")
+chist è conto{i} = (n) => n > 50 ? "caro" : "buono"
+stamm a dì(pizzeria{i}.nome + ": " + conto{i}(totale{i}))
+"#
+    )
+}
+
+fn program(units: usize) -> String {
+    (0..units).map(unit).collect::<Vec<_>>().join("\n")
+}
+
+/// `1 + (1 + (1 + ...))`, the worst case for a recursive descent parser,
+/// `depth` levels deep. The statement takes two levels of the parser limit.
+fn nested(depth: usize) -> String {
+    format!("chist è x = {}1{}\n", "(1 + ".repeat(depth), ")".repeat(depth))
+}
+
+fn inputs() -> Vec<(&'static str, String)> {
+    vec![("small", program(1)), ("medium", program(20)), ("large", program(400)), ("nested", nested(DEFAULT_MAX_DEPTH - 2))]
+}
+
+fn phases(c: &mut Criterion) {
+    let mut group = c.benchmark_group("phases");
+    for (name, source) in inputs() {
+        let (program, errors) = Parser::streaming(Lexer::new(&source)).parse_recovering();
+        assert!(errors.is_empty(), "{} doesn't parse: {}", name, errors[0].message);
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(BenchmarkId::new("lex", name), &source, |b, source| b.iter(|| Lexer::new(black_box(source)).tokenize()));
+        group.bench_with_input(BenchmarkId::new("parse", name), &source, |b, source| {
+            b.iter(|| Parser::streaming(Lexer::new(black_box(source))).parse_recovering())
+        });
+        group.bench_with_input(BenchmarkId::new("codegen", name), &program, |b, program| b.iter(|| CodeGen::new(false).generate(black_box(program))));
+        group.bench_with_input(BenchmarkId::new("codegen_source_map", name), &program, |b, program| {
+            b.iter(|| CodeGen::new(true).generate(black_box(program)))
+        });
+        group.bench_with_input(BenchmarkId::new("compile", name), &source, |b, source| {
+            b.iter(|| fratm_core::compile(black_box(source), CompileOptions::builder().source_map(true).build()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, phases);
+criterion_main!(benches);
@@ -0,0 +1,15 @@
+#![no_main]
+
+use fratm_core::formatter::{format, FormatOptions};
+use libfuzzer_sys::fuzz_target;
+
+// Formatted code must parse, and formatting it again must change nothing
+fuzz_target!(|source: &str| {
+    let options = FormatOptions::default();
+    if let Ok(formatted) = format(source, &options) {
+        match format(&formatted, &options) {
+            Ok(again) => assert_eq!(again, formatted, "formatting is not stable for {:?}", source),
+            Err(e) => panic!("formatted code doesn't parse: {}\n{}", e.message, formatted),
+        }
+    }
+});
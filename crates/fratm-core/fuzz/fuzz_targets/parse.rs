@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    let _ = fratm_core::parse_recovering(source);
+    if fratm_core::parse_source(source).is_ok() {
+        let _ = fratm_core::compile(source, Default::default());
+    }
+});
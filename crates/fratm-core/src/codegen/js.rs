@@ -0,0 +1,144 @@
+//! The JavaScript tree [`CodeGen`](super::CodeGen) prints
+//!
+//! [`Lowering`](super::lower::Lowering) builds it from the FratmScript AST
+//! and makes every choice that depends on the options: module format,
+//! target, assertions, reserved words. Printing it is then only layout:
+//! indentation, semicolons, quotes, parentheses, comments and mappings.
+//!
+//! `span` is the FratmScript code a node comes from. Nodes the compiler
+//! makes up, such as `exports.x = x` or `Math.pow`, have none and get no
+//! mapping.
+
+use std::borrow::Cow;
+
+use crate::lexer::Span;
+use crate::parser::Name;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    /// `const x = 1` or `let x`
+    Var { is_const: bool, name: String, value: Option<Expr> },
+    Function { is_async: bool, name: String, params: Vec<String>, body: Block },
+    Return(Option<Expr>),
+    If { condition: Expr, then: Branch, otherwise: Option<Branch> },
+    While { condition: Expr, body: Block },
+    /// `init` is a `Var` or an `Expression`, written without its `;`
+    For { init: Option<Box<Stmt>>, condition: Option<Expr>, update: Option<Expr>, body: Block },
+    Break,
+    Continue,
+    Debugger,
+    /// A lone `;`
+    Empty,
+    Try { body: Block, param: Option<String>, handler: Block },
+    Throw(Expr),
+    /// `@decorator class name extends superclass { methods }`, then `then` on
+    /// the next lines (legacy decorators, applied by hand)
+    Class { name: String, decorators: Vec<Expr>, superclass: Option<Expr>, methods: Vec<Method>, then: Vec<Stmt> },
+    /// `import { a, b as c } from "m"`, as (imported, local) pairs
+    Import { names: Vec<(String, String)>, source: String },
+    /// `const { a, b: c } = require("m")`, as (imported, local) pairs
+    Require { names: Vec<(String, String)>, source: String },
+    /// `const { a, b: c } = value`, as (property, local) pairs
+    Destructure { names: Vec<(String, String)>, value: Expr },
+    /// A declaration, after `export` if `keyword`, then `then` on the next line
+    /// (`exports.x = x`, or `export { $x as x }` for a renamed one)
+    Export { keyword: bool, declaration: Box<Stmt>, then: Option<Box<Stmt>> },
+    ExportDefault(Expr),
+    /// `export { a, $b as b }`, as (local, exported) pairs
+    ExportNames(Vec<(String, String)>),
+    Expression(Expr),
+    Block(Block),
+}
+
+/// Statements in braces. Comments of the source before `end` go inside.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub statements: Vec<Stmt>,
+    pub end: Option<usize>,
+}
+
+/// The body of an `if` or `else`: a block, or one statement on the same
+/// line (`else if`, `if (!x) throw ...`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Branch {
+    Block(Block),
+    Statement(Box<Stmt>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Method {
+    pub decorators: Vec<Expr>,
+    pub is_async: bool,
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    /// A variable; `original` is its source name when minifying renamed it
+    Identifier { name: String, original: Option<Name> },
+    /// Written as is: `true`, `this`, `console`, a property name
+    Raw(Cow<'static, str>),
+    Number(f64),
+    String(String),
+    /// `` `a${x}b` ``: one more text part than expressions
+    Template { quasis: Vec<String>, expressions: Vec<Expr> },
+    Array(Vec<Expr>),
+    Object(Vec<(String, Expr)>),
+    Binary { left: Box<Expr>, operator: &'static str, right: Box<Expr> },
+    /// `-x`, `!x`, and the word operators `await`, `typeof` and `delete`
+    Unary { operator: &'static str, operand: Box<Expr> },
+    Assign { target: Box<Expr>, value: Box<Expr> },
+    Call { callee: Box<Expr>, arguments: Vec<Expr> },
+    /// `object.property`, or `object?.property` if `optional`
+    Member { object: Box<Expr>, property: Box<Expr>, optional: bool },
+    /// `object[index]`, or `object?.[index]` if `optional`
+    Index { object: Box<Expr>, index: Box<Expr>, optional: bool },
+    New { callee: Box<Expr>, arguments: Vec<Expr> },
+    Arrow { params: Vec<String>, body: ArrowBody },
+    Conditional { condition: Box<Expr>, consequent: Box<Expr>, alternate: Box<Expr> },
+    /// Parentheses the meaning needs, whatever the printer adds
+    Paren(Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArrowBody {
+    Expression(Box<Expr>),
+    Block(Block),
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: impl Into<Option<Span>>) -> Self {
+        Self { kind, span: span.into() }
+    }
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: impl Into<Option<Span>>) -> Self {
+        Self { kind, span: span.into() }
+    }
+
+    /// Made-up text with no source position, e.g. `exports`
+    pub fn raw(text: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ExprKind::Raw(text.into()), None)
+    }
+
+    /// `object.property`, made up
+    pub fn member(object: Expr, property: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ExprKind::Member { object: Box::new(object), property: Box::new(Self::raw(property)), optional: false }, None)
+    }
+}
@@ -0,0 +1,730 @@
+//! FratmScript AST to the JavaScript tree of [`js`](super::js)
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::js::{self, Branch, Expr, ExprKind, Stmt, StmtKind};
+use super::{is_reserved_word, js_name};
+use crate::coverage::{Counters, COUNTERS};
+use crate::env;
+use crate::errors::assertion_failed;
+use crate::lexer::keywords::{class_name, console_method};
+use crate::lexer::Span;
+use crate::modules::resolve_alias;
+use crate::parser::*;
+use crate::{Assertions, Decorators, Jsx, ModuleFormat, Target, Trace};
+
+/// The options that change what the JavaScript says, as opposed to how it
+/// is laid out
+pub(super) struct Lowering<'a> {
+    pub target: Target,
+    pub module_format: ModuleFormat,
+    /// Global object of a [`ModuleFormat::Script`]
+    pub global_name: &'a str,
+    pub assertions: Assertions,
+    /// Fold string concatenations (see [`Lowering::concatenation`])
+    pub optimize: bool,
+    pub module_aliases: &'a BTreeMap<String, String>,
+    /// Names for `#si`, which choose the branches to compile
+    pub defines: &'a BTreeMap<String, String>,
+    /// Values of `amb("NAME")`
+    pub env: &'a BTreeMap<String, String>,
+    /// Original names of the identifiers minifying renamed, by span start
+    pub original_names: &'a HashMap<usize, Name>,
+    /// What JSX elements compile to
+    pub jsx: Option<&'a Jsx>,
+    /// Helpers of the automatic JSX runtime used so far, to import
+    pub jsx_imports: RefCell<BTreeSet<&'static str>>,
+    pub decorators: Decorators,
+    /// Counters to put in for coverage, if on
+    pub coverage: Option<&'a Counters>,
+    pub trace: Trace,
+    pub trace_hook: Option<&'a str>,
+    /// Lines of the functions marked `// traccia` (true) or `// nun traccià`
+    pub trace_lines: &'a BTreeMap<usize, bool>,
+    /// Name of the traced function whose body is being lowered, for its returns
+    pub traced: RefCell<Option<String>>,
+    /// Whether a function is traced, so the output needs [`Lowering::trace_exit`]
+    pub trace_used: Cell<bool>,
+}
+
+/// Helper a traced function returns through
+const TRACE_EXIT: &str = "__fratm_exit";
+
+impl Lowering<'_> {
+    /// `statements` as compiled: a `#si` gives way to the statements of the
+    /// branch the defines choose, in the same scope.
+    pub fn statements(&self, statements: &[Statement]) -> Vec<Stmt> {
+        let mut lowered = Vec::with_capacity(statements.len());
+        for stmt in statements {
+            match stmt {
+                Statement::Conditional { condition, then_branch, else_branch, .. } => {
+                    let branch = if condition.holds(self.defines) { Some(then_branch) } else { else_branch.as_ref() };
+                    if let Some(branch) = branch { lowered.extend(self.statements(branch)); }
+                }
+                stmt => {
+                    // Declarations always run: only what they contain counts
+                    let declaration = matches!(stmt, Statement::FunctionDecl { .. } | Statement::ClassDecl { .. } | Statement::Import { .. })
+                        || matches!(stmt, Statement::Export { declaration: Some(declaration), .. } if matches!(**declaration, Statement::FunctionDecl { .. } | Statement::ClassDecl { .. }));
+                    // Counters are numbered in source order, before what the statement contains
+                    let counted = self.coverage.filter(|_| !declaration).map(|counters| counter(counters.statement(), stmt.span()));
+                    let Some(lowered_stmt) = self.statement(stmt) else { continue };
+                    lowered.extend(counted);
+                    lowered.push(lowered_stmt);
+                }
+            }
+        }
+        lowered
+    }
+
+    /// `None` for a statement left out of the output, such as a stripped assertion
+    pub fn statement(&self, stmt: &Statement) -> Option<Stmt> {
+        let kind = match stmt {
+            Statement::VariableDecl { name, value, is_const, .. } => {
+                StmtKind::Var { is_const: *is_const, name: js_name(name).into_owned(), value: value.as_ref().map(|value| self.expression(value)) }
+            }
+            Statement::FunctionDecl { name, params, body, is_async, span, .. } => StmtKind::Function {
+                is_async: *is_async,
+                name: js_name(name).into_owned(),
+                params: params_of(params),
+                body: self.function_body(name.to_string(), params, body, *span),
+            },
+            Statement::Return { value, .. } => {
+                let value = value.as_ref().map(|value| self.expression(value));
+                match &*self.traced.borrow() {
+                    Some(name) => StmtKind::Return(Some(call(Expr::raw(TRACE_EXIT), std::iter::once(string(name)).chain(value).collect()))),
+                    None => StmtKind::Return(value),
+                }
+            }
+            Statement::If { condition, then_branch, else_branch, span } => {
+                let condition = self.expression(condition);
+                // Both branches count at the line of the `si`, the one without code too
+                let then_taken = self.coverage.map(|counters| counter(counters.branch(), *span));
+                // Comments after the last statement may belong to the else branch
+                let end = if else_branch.is_some() { None } else { Some(span.end) };
+                let mut then = self.block(then_branch, end);
+                let else_taken = self.coverage.map(|counters| counter(counters.branch(), *span));
+                let mut otherwise = else_branch.as_ref().map(|else_body| match else_body.as_slice() {
+                    [stmt @ Statement::If { .. }] => Branch::Statement(Box::new(self.statement(stmt).expect("an if is never stripped"))),
+                    _ => Branch::Block(self.block(else_body, Some(span.end))),
+                });
+                if let (Some(then_taken), Some(taken)) = (then_taken, else_taken) {
+                    then.statements.insert(0, then_taken);
+                    otherwise = Some(match otherwise {
+                        Some(Branch::Block(mut block)) => {
+                            block.statements.insert(0, taken);
+                            Branch::Block(block)
+                        }
+                        Some(Branch::Statement(stmt)) => Branch::Block(js::Block { statements: vec![taken, *stmt], end: None }),
+                        None => Branch::Block(js::Block { statements: vec![taken], end: None }),
+                    });
+                }
+                StmtKind::If { condition, then: Branch::Block(then), otherwise }
+            }
+            Statement::While { condition, body, span } => StmtKind::While { condition: self.expression(condition), body: self.block(body, Some(span.end)) },
+            Statement::For { init, condition, update, body, span } => StmtKind::For {
+                init: init.as_ref().and_then(|init| self.statement(init)).map(Box::new),
+                condition: condition.as_ref().map(|condition| self.expression(condition)),
+                update: update.as_ref().map(|update| self.expression(update)),
+                body: self.block(body, Some(span.end)),
+            },
+            Statement::Break { .. } => StmtKind::Break,
+            Statement::Continue { .. } => StmtKind::Continue,
+            Statement::Debugger { .. } => StmtKind::Debugger,
+            Statement::Assert { condition, message, span } => {
+                let message = match message {
+                    Some(message) => self.expression(message),
+                    None => Expr::new(ExprKind::String(assertion_failed(span.line)), None),
+                };
+                match self.assertions {
+                    Assertions::Strip => return None,
+                    Assertions::Console => {
+                        let callee = Expr::member(Expr::raw("console"), "assert");
+                        StmtKind::Expression(call(callee, vec![self.expression(condition), message]))
+                    }
+                    Assertions::Throw => {
+                        let failed = unary("!", Expr::new(ExprKind::Paren(Box::new(self.expression(condition))), None));
+                        let error = Expr::new(ExprKind::New { callee: Box::new(Expr::raw("Error")), arguments: vec![message] }, None);
+                        StmtKind::If { condition: failed, then: Branch::Statement(Box::new(Stmt::new(StmtKind::Throw(error), None))), otherwise: None }
+                    }
+                }
+            }
+            // Only reachable with a recovered program: keep the output valid JavaScript
+            Statement::Error { .. } => StmtKind::Empty,
+            Statement::TryCatch { try_body, catch_param, catch_type, catch_body, span } => {
+                let param = catch_param.as_ref().map(|param| js_name(param).into_owned());
+                let mut handler = self.block(catch_body, Some(span.end));
+                // `(err è uno T)`: `if (!(err instanceof T)) throw err;` first
+                if let (Some(param), Some(catch_type)) = (&param, catch_type) {
+                    let error = || Expr::new(ExprKind::Identifier { name: param.clone(), original: None }, None);
+                    let other = unary("!", binary(error(), "instanceof", self.class_reference(catch_type)));
+                    let rethrow = Stmt::new(StmtKind::Throw(error()), None);
+                    handler.statements.insert(0, Stmt::new(StmtKind::If { condition: other, then: Branch::Statement(Box::new(rethrow)), otherwise: None }, None));
+                }
+                StmtKind::Try { body: self.block(try_body, None), param, handler }
+            }
+            Statement::Throw { value, .. } => StmtKind::Throw(self.expression(value)),
+            Statement::ClassDecl { name, decorators, superclass, methods, .. } => {
+                let name = js_name(name).into_owned();
+                let superclass = superclass.as_ref().map(|superclass| self.class_reference(superclass));
+                let legacy = self.decorators == Decorators::Legacy;
+                let mut then = Vec::new();
+                let methods = methods.iter().filter_map(|method| match method {
+                    Statement::FunctionDecl { name: method_name, params, body, is_async, decorators, span, .. } => {
+                        // Translate "costruttore" to JavaScript "constructor"
+                        let method_name = if &**method_name == "costruttore" { "constructor".to_string() } else { method_name.to_string() };
+                        let mut decorators = self.decorators(decorators);
+                        if legacy {
+                            let method = Expr::member(Expr::member(Expr::raw(name.clone()), "prototype"), method_name.clone());
+                            then.extend(decorate(method, std::mem::take(&mut decorators), "method", &method_name));
+                        }
+                        let explicit = calls_parent(body);
+                        let mut body = self.function_body(format!("{}.{}", name, method_name), params, body, *span);
+                        // Without a `pate(...)` the parent is built with the same arguments
+                        // before the constructor runs
+                        if superclass.is_some() && method_name == "constructor" && !explicit {
+                            body.statements.insert(0, Stmt::new(StmtKind::Expression(Expr::raw("super(...arguments)")), None));
+                        }
+                        Some(js::Method {
+                            is_async: *is_async,
+                            decorators,
+                            body,
+                            name: method_name,
+                            params: params_of(params),
+                            span: *span,
+                        })
+                    }
+                    _ => None,
+                }).collect();
+                let mut decorators = self.decorators(decorators);
+                if legacy {
+                    // Method decorators run first, as in the TC39 proposal
+                    then.extend(decorate(Expr::raw(name.clone()), std::mem::take(&mut decorators), "class", &name));
+                }
+                StmtKind::Class { name, decorators, superclass, methods, then }
+            }
+            Statement::Import { specifiers, source, .. } => {
+                // `import { class as $class }`: reserved words are fine as imported names
+                let names = specifiers.iter().map(|s| (s.local.to_string(), js_name(&s.local).into_owned())).collect();
+                self.import(names, resolve_alias(source, self.module_aliases).into_owned())
+            }
+            Statement::Export { declaration, default_value, .. } => match (default_value, declaration) {
+                (Some(value), _) => {
+                    let value = self.expression(value);
+                    match self.module_format {
+                        ModuleFormat::Esm => StmtKind::ExportDefault(value),
+                        ModuleFormat::CommonJs | ModuleFormat::Script => StmtKind::Expression(assign(Expr::member(Expr::raw("exports"), "default"), value)),
+                    }
+                }
+                (None, Some(declaration)) => {
+                    let name = match declaration.as_ref() {
+                        Statement::VariableDecl { name, .. } | Statement::FunctionDecl { name, .. } | Statement::ClassDecl { name, .. } => Some(name),
+                        _ => None,
+                    };
+                    // A reserved word is declared as `$name` but still exported as `name`
+                    let escaped = name.filter(|name| is_reserved_word(name));
+                    let then = match (self.module_format, name) {
+                        (ModuleFormat::CommonJs | ModuleFormat::Script, Some(name)) => {
+                            let variable = Expr::new(ExprKind::Identifier { name: js_name(name).into_owned(), original: None }, None);
+                            Some(StmtKind::Expression(assign(Expr::member(Expr::raw("exports"), name.to_string()), variable)))
+                        }
+                        (ModuleFormat::Esm, Some(name)) if escaped.is_some() => Some(StmtKind::ExportNames(vec![(js_name(name).into_owned(), name.to_string())])),
+                        _ => None,
+                    };
+                    StmtKind::Export {
+                        keyword: self.module_format == ModuleFormat::Esm && escaped.is_none(),
+                        declaration: Box::new(self.statement(declaration)?),
+                        then: then.map(|kind| Box::new(Stmt::new(kind, None))),
+                    }
+                }
+                (None, None) => StmtKind::Empty,
+            },
+            Statement::Expression { expression, .. } => StmtKind::Expression(self.expression(expression)),
+            Statement::Block { statements, span } => StmtKind::Block(self.block(statements, Some(span.end))),
+            // Spliced into its list by `statements`
+            Statement::Conditional { .. } => return None,
+        };
+        Some(Stmt::new(kind, stmt.span()))
+    }
+
+    /// `names` from `source`, as (imported, local) pairs. A script takes
+    /// them from its global object, where the script exporting them put them.
+    pub fn import(&self, names: Vec<(String, String)>, source: String) -> StmtKind {
+        match self.module_format {
+            ModuleFormat::Esm => StmtKind::Import { names, source },
+            ModuleFormat::CommonJs => StmtKind::Require { names, source },
+            ModuleFormat::Script => StmtKind::Destructure { names, value: Expr::member(Expr::raw("globalThis"), self.global_name.to_string()) },
+        }
+    }
+
+    /// The body of the function `name`. A traced one logs its call first,
+    /// returns through [`TRACE_EXIT`], and logs the return at the end when
+    /// it can get there.
+    fn function_body(&self, name: String, params: &[Name], body: &[Statement], span: Span) -> js::Block {
+        let traced = match (self.trace, self.trace_lines.get(&span.line)) {
+            (Trace::Off, _) => false,
+            (_, Some(&on)) => on,
+            (trace, None) => trace == Trace::All,
+        };
+        let outer = self.traced.replace(traced.then(|| name.clone()));
+        let mut block = self.block(body, Some(span.end));
+        self.traced.replace(outer);
+        if !traced {
+            return block;
+        }
+        self.trace_used.set(true);
+        let arguments = params_of(params).into_iter().map(Expr::raw);
+        let enter = match self.trace_hook {
+            Some(hook) => call(Expr::raw(hook.to_string()), vec![string("enter"), string(&name), Expr::new(ExprKind::Array(arguments.collect()), None)]),
+            None => call(Expr::member(Expr::raw("console"), "log"), std::iter::once(string(&format!("→ {}", name))).chain(arguments).collect()),
+        };
+        block.statements.insert(0, Stmt::new(StmtKind::Expression(enter), None));
+        if !matches!(body.last(), Some(Statement::Return { .. } | Statement::Throw { .. })) {
+            block.statements.push(Stmt::new(StmtKind::Expression(call(Expr::raw(TRACE_EXIT), vec![string(&name)])), None));
+        }
+        block
+    }
+
+    /// `function __fratm_exit(name, value)`, which logs a return and gives
+    /// back its value
+    pub fn trace_exit(&self) -> Stmt {
+        let (name, value) = (Expr::raw("name"), Expr::raw("value"));
+        let log = match self.trace_hook {
+            Some(hook) => call(Expr::raw(hook.to_string()), vec![string("exit"), name, value]),
+            None => call(Expr::member(Expr::raw("console"), "log"), vec![binary(string("← "), "+", name), value]),
+        };
+        let body = vec![Stmt::new(StmtKind::Expression(log), None), Stmt::new(StmtKind::Return(Some(Expr::raw("value"))), None)];
+        let params = vec!["name".to_string(), "value".to_string()];
+        Stmt::new(StmtKind::Function { is_async: false, name: TRACE_EXIT.to_string(), params, body: js::Block { statements: body, end: None } }, None)
+    }
+
+    /// The coverage collector, first in the file: the counters, registered
+    /// under the file's name, and for Node.js a hook writing them all out on exit
+    pub fn coverage_collector(&self, (statements, branches): (usize, usize)) -> Vec<Stmt> {
+        let global = |name: &'static str| Expr::member(Expr::raw("globalThis"), name);
+        let zeros = |n: usize| call(Expr::member(Expr::new(ExprKind::New { callee: Box::new(Expr::raw("Array")), arguments: vec![number(n)] }, None), "fill"), vec![number(0)]);
+        let (file, write) = match self.module_format {
+            ModuleFormat::Esm => ("import.meta.url", Some(Expr::raw("__fratm_writeFileSync"))),
+            ModuleFormat::CommonJs => ("__filename", Some(Expr::member(call(Expr::raw("require"), vec![string("fs")]), "writeFileSync"))),
+            ModuleFormat::Script => ("document.currentScript.src", None),
+        };
+        let mut collector = Vec::new();
+        if self.module_format == ModuleFormat::Esm {
+            collector.push(StmtKind::Import { names: vec![("writeFileSync".to_string(), "__fratm_writeFileSync".to_string())], source: "node:fs".to_string() });
+        }
+        let counts = Expr::new(ExprKind::Object(vec![("s".to_string(), zeros(statements)), ("b".to_string(), zeros(branches))]), None);
+        collector.push(StmtKind::Var { is_const: true, name: COUNTERS.to_string(), value: Some(counts) });
+        let files = || global("__fratm_coverage");
+        collector.push(StmtKind::Expression(assign(files(), binary(files(), "||", Expr::new(ExprKind::Object(Vec::new()), None)))));
+        let slot = Expr::new(ExprKind::Index { object: Box::new(files()), index: Box::new(Expr::raw(file)), optional: false }, None);
+        collector.push(StmtKind::Expression(assign(slot, Expr::raw(COUNTERS))));
+        if let Some(write) = write {
+            let path = binary(Expr::member(Expr::member(Expr::raw("process"), "env"), "FRATM_COVERAGE_FILE"), "||", string("fratm-coverage.json"));
+            let dump = call(write, vec![path, call(Expr::member(Expr::raw("JSON"), "stringify"), vec![files()])]);
+            let on_exit = Expr::new(ExprKind::Arrow { params: Vec::new(), body: js::ArrowBody::Expression(Box::new(dump)) }, None);
+            // Every covered file registers, but the first one writes for all
+            let hook = vec![
+                Stmt::new(StmtKind::Expression(assign(global("__fratm_coverage_dump"), Expr::raw("true"))), None),
+                Stmt::new(StmtKind::Expression(call(Expr::member(Expr::raw("process"), "on"), vec![string("exit"), on_exit])), None),
+            ];
+            collector.push(StmtKind::If { condition: unary("!", global("__fratm_coverage_dump")), then: Branch::Block(js::Block { statements: hook, end: None }), otherwise: None });
+        }
+        collector.into_iter().map(|kind| Stmt::new(kind, None)).collect()
+    }
+
+    fn block(&self, statements: &[Statement], end: Option<usize>) -> js::Block {
+        js::Block { statements: self.statements(statements), end }
+    }
+
+    /// A class after `figlio 'e` or `è uno`, where `Errore` is `Error`
+    fn class_reference(&self, expr: &Expression) -> Expr {
+        match expr {
+            Expression::Identifier { name, span } if class_name(name) != &**name => {
+                Expr::new(ExprKind::Identifier { name: class_name(name).to_string(), original: None }, *span)
+            }
+            expr => self.expression(expr),
+        }
+    }
+
+    fn expression(&self, expr: &Expression) -> Expr {
+        let span = expr.span();
+        let kind = match expr {
+            Expression::Identifier { name, .. } => {
+                ExprKind::Identifier { name: js_name(name).into_owned(), original: self.original_names.get(&span.start).cloned() }
+            }
+            Expression::Number { value, .. } => ExprKind::Number(*value),
+            Expression::String { value, .. } => ExprKind::String(value.clone()),
+            Expression::Boolean { value, .. } => ExprKind::Raw(if *value { "true" } else { "false" }.into()),
+            Expression::Null { .. } => ExprKind::Raw("null".into()),
+            Expression::Undefined { .. } | Expression::Error { .. } => ExprKind::Raw("undefined".into()),
+            Expression::This { .. } => ExprKind::Raw("this".into()),
+            Expression::SuperCall { arguments, .. } => ExprKind::Call { callee: Box::new(Expr::raw("super")), arguments: self.expressions(arguments) },
+            Expression::Array { elements, .. } => ExprKind::Array(self.expressions(elements)),
+            Expression::Object { properties, .. } => {
+                ExprKind::Object(properties.iter().map(|(key, value)| (key.to_string(), self.expression(value))).collect())
+            }
+            Expression::Binary { left, operator: BinaryOp::Power, right, .. } if self.target == Target::Es2015 => {
+                // `**` is ES2016
+                ExprKind::Call { callee: Box::new(Expr::raw("Math.pow")), arguments: vec![self.expression(left), self.expression(right)] }
+            }
+            Expression::Binary { left, operator: BinaryOp::Nullish, right, .. } => {
+                let mut left = self.expression(left);
+                short_circuit(&mut left);
+                ExprKind::Binary { left: Box::new(left), operator: "??", right: Box::new(self.expression(right)) }
+            }
+            Expression::Binary { left, operator, right, .. } => match self.concatenation(expr) {
+                Some(folded) => folded,
+                None => ExprKind::Binary { left: Box::new(self.expression(left)), operator: operator.to_js(), right: Box::new(self.expression(right)) },
+            },
+            Expression::Unary { operator, operand, .. } => ExprKind::Unary { operator: operator.to_js(), operand: Box::new(self.expression(operand)) },
+            Expression::Await { argument, .. } => ExprKind::Unary { operator: "await", operand: Box::new(self.expression(argument)) },
+            Expression::TypeOf { operand, .. } => ExprKind::Unary { operator: "typeof", operand: Box::new(self.expression(operand)) },
+            Expression::Delete { operand, .. } => ExprKind::Unary { operator: "delete", operand: Box::new(self.expression(operand)) },
+            Expression::Assignment { target, value, .. } => ExprKind::Assign { target: Box::new(self.expression(target)), value: Box::new(self.expression(value)) },
+            Expression::Call { callee, arguments, .. } => match env::variable(expr) {
+                Some((name, _)) => self.env.get(name).map_or(ExprKind::Raw("undefined".into()), |value| ExprKind::String(value.clone())),
+                None => ExprKind::Call { callee: Box::new(self.expression(callee)), arguments: self.expressions(arguments) },
+            },
+            Expression::Member { object, property, computed: true, .. } => {
+                ExprKind::Index { object: Box::new(self.expression(object)), index: Box::new(self.expression(property)), optional: false }
+            }
+            Expression::Member { object, property, .. } => {
+                let property = match property.as_ref() {
+                    // A property name, so `obj.class` stays as written
+                    Expression::Identifier { name, span } => {
+                        let name = match object.as_ref() {
+                            Expression::Console { .. } => console_method(name),
+                            _ => name,
+                        };
+                        Expr::new(ExprKind::Raw(Cow::Owned(name.to_string())), *span)
+                    }
+                    property => self.expression(property),
+                };
+                ExprKind::Member { object: Box::new(self.expression(object)), property: Box::new(property), optional: false }
+            }
+            Expression::New { callee, arguments, .. } => ExprKind::New { callee: Box::new(self.expression(callee)), arguments: self.expressions(arguments) },
+            Expression::ArrowFunction { params, body, .. } => {
+                // The returns of an arrow function are its own, never traced
+                let outer = self.traced.replace(None);
+                let body = match body {
+                    ArrowBody::Expression(body) => js::ArrowBody::Expression(Box::new(self.expression(body))),
+                    ArrowBody::Block(statements) => js::ArrowBody::Block(self.block(statements, Some(span.end))),
+                };
+                self.traced.replace(outer);
+                ExprKind::Arrow { params: params_of(params), body }
+            }
+            Expression::Ternary { condition, consequent, alternate, .. } => ExprKind::Conditional {
+                condition: Box::new(self.expression(condition)),
+                consequent: Box::new(self.expression(consequent)),
+                alternate: Box::new(self.expression(alternate)),
+            },
+            Expression::Console { .. } => ExprKind::Raw("console".into()),
+            Expression::Promise { helper, .. } => ExprKind::Raw(helper.to_js().into()),
+            Expression::ConsoleLog { arguments, .. } => self.console_call("log", arguments),
+            Expression::ConsoleWarn { arguments, .. } => self.console_call("warn", arguments),
+            Expression::ConsoleError { arguments, .. } => self.console_call("error", arguments),
+            Expression::JsxElement { name, attributes, children, .. } => self.jsx_element(name.as_deref(), attributes, children),
+            Expression::Template { quasis, expressions, .. } => ExprKind::Template { quasis: quasis.clone(), expressions: self.expressions(expressions) },
+            Expression::Match { subject, arms, .. } => self.match_expression(subject, arms),
+        };
+        Expr::new(kind, span)
+    }
+
+    /// `vir buono`. Matching a variable with arms that bind nothing is a
+    /// chain of `?:`; anything else goes through an arrow called with the
+    /// subject, so it is evaluated once and the bindings get a scope.
+    fn match_expression(&self, subject: &Expression, arms: &[MatchArm]) -> ExprKind {
+        let binds = arms.iter().any(|arm| !arm.pattern.bindings().is_empty());
+        if let (Expression::Identifier { .. }, false) = (subject, binds) {
+            let value = self.expression(subject);
+            let mut chain = Expr::raw("undefined");
+            for arm in arms.iter().rev() {
+                let mut tests = Vec::new();
+                pattern_tests(&arm.pattern, &value, &mut tests, &mut Vec::new());
+                tests.extend(arm.guard.as_ref().map(|guard| self.expression(guard)));
+                chain = match conjunction(tests) {
+                    Some(condition) => Expr::new(ExprKind::Conditional {
+                        condition: Box::new(condition),
+                        consequent: Box::new(self.expression(&arm.body)),
+                        alternate: Box::new(chain),
+                    }, None),
+                    None => self.expression(&arm.body),
+                };
+            }
+            return chain.kind;
+        }
+        let value = Expr::new(ExprKind::Identifier { name: MATCH_VALUE.to_string(), original: None }, None);
+        let mut statements = Vec::new();
+        for arm in arms {
+            let (mut tests, mut bindings) = (Vec::new(), Vec::new());
+            pattern_tests(&arm.pattern, &value, &mut tests, &mut bindings);
+            let mut body: Vec<Stmt> = bindings.into_iter()
+                .map(|(name, value)| Stmt::new(StmtKind::Var { is_const: true, name, value: Some(value) }, None))
+                .collect();
+            let result = Stmt::new(StmtKind::Return(Some(self.expression(&arm.body))), arm.body.span());
+            body.push(match &arm.guard {
+                Some(guard) => Stmt::new(StmtKind::If {
+                    condition: self.expression(guard),
+                    then: Branch::Statement(Box::new(result)),
+                    otherwise: None,
+                }, None),
+                None => result,
+            });
+            match conjunction(tests) {
+                Some(condition) => statements.push(Stmt::new(StmtKind::If {
+                    condition,
+                    then: Branch::Block(js::Block { statements: body, end: None }),
+                    otherwise: None,
+                }, arm.span)),
+                // Matches anything: a block only to scope what it binds
+                None if body.len() > 1 => statements.push(Stmt::new(StmtKind::Block(js::Block { statements: body, end: None }), arm.span)),
+                None => statements.extend(body),
+            }
+        }
+        let arrow = Expr::new(ExprKind::Arrow {
+            params: vec![MATCH_VALUE.to_string()],
+            body: js::ArrowBody::Block(js::Block { statements, end: None }),
+        }, None);
+        ExprKind::Call { callee: Box::new(Expr::new(ExprKind::Paren(Box::new(arrow)), None)), arguments: vec![self.expression(subject)] }
+    }
+
+    /// With `optimize`, a chain of `+` with a string literal in it as one
+    /// string, or as a template literal when other values come in between:
+    /// `"Uè " + nome + "!"` is `\`Uè ${nome}!\``. `None` if there is nothing
+    /// to fold.
+    fn concatenation(&self, expr: &Expression) -> Option<ExprKind> {
+        if !self.optimize {
+            return None;
+        }
+        // The chain leans left, `((a + b) + c) + d`: `spine[i]` adds up all
+        // but the last `i` operands
+        let (mut spine, mut operands, mut node) = (Vec::new(), Vec::new(), expr);
+        while let Expression::Binary { left, operator: BinaryOp::Add, right, .. } = node {
+            spine.push(node);
+            operands.push(right.as_ref());
+            node = left;
+        }
+        operands.push(node);
+        operands.reverse();
+        let first_string = operands.iter().position(|operand| matches!(operand, Expression::String { .. }))?;
+        // Until the first string, `+` may add numbers: that part stays as it is
+        let (mut quasis, mut expressions) = (vec![String::new()], Vec::new());
+        let start = if first_string >= 2 {
+            expressions.push(self.expression(spine[operands.len() - first_string]));
+            quasis.push(String::new());
+            first_string
+        } else {
+            0
+        };
+        for operand in &operands[start..] {
+            match operand {
+                Expression::String { value, .. } => quasis.last_mut().unwrap().push_str(value),
+                Expression::Number { value, .. } if *value == value.floor() && value.abs() < 1e15 => {
+                    quasis.last_mut().unwrap().push_str(&(*value as i64).to_string())
+                }
+                operand => {
+                    expressions.push(self.expression(operand));
+                    quasis.push(String::new());
+                }
+            }
+        }
+        Some(match expressions.is_empty() {
+            true => ExprKind::String(quasis.remove(0)),
+            false => ExprKind::Template { quasis, expressions },
+        })
+    }
+
+    /// `<tag a={x}>...</tag>` as a call to the JSX runtime
+    fn jsx_element(&self, name: Option<&str>, attributes: &[(Name, Option<Expression>)], children: &[JsxChild]) -> ExprKind {
+        let classic = Jsx::default();
+        let tag = |fragment: Expr| match name {
+            None => fragment,
+            Some(name) if jsx_component(name).is_some() => Expr::raw(name.to_string()),
+            Some(name) => Expr::new(ExprKind::String(name.to_string()), None),
+        };
+        let mut props: Vec<(String, Expr)> = attributes.iter().map(|(key, value)| {
+            (key.to_string(), value.as_ref().map_or_else(|| Expr::raw("true"), |value| self.expression(value)))
+        }).collect();
+        let mut children: Vec<Expr> = children.iter().filter_map(|child| match child {
+            JsxChild::Text(text) => jsx_text(text).map(|text| Expr::new(ExprKind::String(text), None)),
+            JsxChild::Expression(child) => Some(self.expression(child)),
+        }).collect();
+        match self.jsx.unwrap_or(&classic) {
+            Jsx::Classic { factory, fragment } => {
+                let props = if props.is_empty() { Expr::raw("null") } else { Expr::new(ExprKind::Object(props), None) };
+                let mut arguments = vec![tag(Expr::raw(fragment.clone())), props];
+                arguments.append(&mut children);
+                ExprKind::Call { callee: Box::new(Expr::raw(factory.clone())), arguments }
+            }
+            Jsx::Automatic { .. } => {
+                // `key` goes apart, after the props
+                let key = props.iter().position(|(name, _)| name == "key").map(|i| props.remove(i).1);
+                let helper = if children.len() > 1 { "jsxs" } else { "jsx" };
+                match children.len() {
+                    0 => {}
+                    1 => props.push(("children".to_string(), children.remove(0))),
+                    _ => props.push(("children".to_string(), Expr::new(ExprKind::Array(children), None))),
+                }
+                let mut imports = self.jsx_imports.borrow_mut();
+                imports.insert(helper);
+                if name.is_none() { imports.insert("Fragment"); }
+                let mut arguments = vec![tag(Expr::raw("_Fragment")), Expr::new(ExprKind::Object(props), None)];
+                arguments.extend(key);
+                ExprKind::Call { callee: Box::new(Expr::raw(format!("_{}", helper))), arguments }
+            }
+        }
+    }
+
+    /// Decorators other than `a.b` or `a.b(c)` need parentheses after `@`
+    fn decorators(&self, decorators: &[Expression]) -> Vec<Expr> {
+        fn is_chain(expr: &Expr) -> bool {
+            match &expr.kind {
+                ExprKind::Identifier { .. } | ExprKind::Raw(_) => true,
+                ExprKind::Member { object, .. } => is_chain(object),
+                _ => false,
+            }
+        }
+        decorators.iter().map(|decorator| {
+            let decorator = self.expression(decorator);
+            match &decorator.kind {
+                ExprKind::Call { callee, .. } if is_chain(callee) => decorator,
+                // Printed in parentheses anyway
+                ExprKind::Binary { .. } | ExprKind::Paren(_) => decorator,
+                _ if is_chain(&decorator) => decorator,
+                _ => Expr::new(ExprKind::Paren(Box::new(decorator)), None),
+            }
+        }).collect()
+    }
+
+    fn expressions(&self, expressions: &[Expression]) -> Vec<Expr> {
+        expressions.iter().map(|expr| self.expression(expr)).collect()
+    }
+
+    fn console_call(&self, method: &'static str, arguments: &[Expression]) -> ExprKind {
+        ExprKind::Call { callee: Box::new(Expr::member(Expr::raw("console"), method)), arguments: self.expressions(arguments) }
+    }
+}
+
+/// JSX text as a string, the way React reads it: lines are trimmed, and
+/// those left with something on them are joined by a space. `None` if
+/// nothing is left, as between tags on separate lines.
+fn jsx_text(text: &str) -> Option<String> {
+    let lines: Vec<&str> = text.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)).collect();
+    let last_with_text = lines.iter().rposition(|line| !line.trim().is_empty())?;
+    let mut joined = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let line = if i > 0 { line.trim_start() } else { line };
+        let line = if i + 1 < lines.len() { line.trim_end() } else { line };
+        if line.is_empty() { continue; }
+        joined.push_str(line);
+        if i != last_with_text { joined.push(' '); }
+    }
+    Some(joined)
+}
+
+/// `target = decorator(target, { kind, name }) || target;` for each of
+/// `decorators`, the innermost (last) first
+fn decorate(target: Expr, decorators: Vec<Expr>, kind: &str, name: &str) -> Vec<Stmt> {
+    decorators.into_iter().rev().map(|decorator| {
+        let context = Expr::new(ExprKind::Object(vec![
+            ("kind".to_string(), Expr::new(ExprKind::String(kind.to_string()), None)),
+            ("name".to_string(), Expr::new(ExprKind::String(name.to_string()), None)),
+        ]), None);
+        let decorated = call(decorator, vec![target.clone(), context]);
+        let value = Expr::new(ExprKind::Binary { left: Box::new(decorated), operator: "||", right: Box::new(target.clone()) }, None);
+        Stmt::new(StmtKind::Expression(assign(target.clone(), value)), None)
+    }).collect()
+}
+
+/// The parameter holding the subject of a `vir buono`: FratmScript names
+/// can't start with `$`, and reserved words get one (see [`js_name`]) only
+/// in front of a whole word, so nothing in the source can be called this
+const MATCH_VALUE: &str = "$valore";
+
+/// What `value` must pass to match `pattern`, and the names it binds with
+/// where their values are in it
+fn pattern_tests(pattern: &Pattern, value: &Expr, tests: &mut Vec<Expr>, bindings: &mut Vec<(String, Expr)>) {
+    match pattern {
+        Pattern::Wildcard { .. } => {}
+        Pattern::Binding { name, .. } => bindings.push((js_name(name).into_owned(), value.clone())),
+        Pattern::Literal { value: literal, .. } => {
+            let literal = match literal {
+                Literal::Number(number) => Expr::new(ExprKind::Number(*number), None),
+                Literal::String(string) => Expr::new(ExprKind::String(string.clone()), None),
+                Literal::Boolean(boolean) => Expr::raw(if *boolean { "true" } else { "false" }),
+                Literal::Null => Expr::raw("null"),
+                Literal::Undefined => Expr::raw("undefined"),
+            };
+            tests.push(binary(value.clone(), "===", literal));
+        }
+        Pattern::Array { elements, .. } => {
+            tests.push(call(Expr::member(Expr::raw("Array"), "isArray"), vec![value.clone()]));
+            tests.push(binary(Expr::member(value.clone(), "length"), "===", Expr::new(ExprKind::Number(elements.len() as f64), None)));
+            for (index, element) in elements.iter().enumerate() {
+                let index = Expr::new(ExprKind::Number(index as f64), None);
+                let element_value = Expr::new(ExprKind::Index { object: Box::new(value.clone()), index: Box::new(index), optional: false }, None);
+                pattern_tests(element, &element_value, tests, bindings);
+            }
+        }
+        Pattern::Object { properties, .. } => {
+            tests.push(binary(unary("typeof", value.clone()), "===", Expr::new(ExprKind::String("object".into()), None)));
+            tests.push(binary(value.clone(), "!==", Expr::raw("null")));
+            for (key, property) in properties {
+                tests.push(binary(Expr::new(ExprKind::String(key.to_string()), None), "in", value.clone()));
+                pattern_tests(property, &Expr::member(value.clone(), key.to_string()), tests, bindings);
+            }
+        }
+    }
+}
+
+/// Makes the property accesses along the chain of `expr` optional, so
+/// `a.b.c()` becomes `a?.b?.c()`
+fn short_circuit(expr: &mut Expr) {
+    match &mut expr.kind {
+        ExprKind::Member { object, optional, .. } | ExprKind::Index { object, optional, .. } => {
+            *optional = true;
+            short_circuit(object);
+        }
+        ExprKind::Call { callee, .. } => short_circuit(callee),
+        _ => {}
+    }
+}
+
+/// `tests` joined with `&&`, or `None` if there are none
+fn conjunction(tests: Vec<Expr>) -> Option<Expr> {
+    tests.into_iter().reduce(|left, right| binary(left, "&&", right))
+}
+
+fn binary(left: Expr, operator: &'static str, right: Expr) -> Expr {
+    Expr::new(ExprKind::Binary { left: Box::new(left), operator, right: Box::new(right) }, None)
+}
+
+/// `__fratm_cov.s[0]++`, mapped to the code it counts
+fn counter(increment: String, span: Span) -> Stmt {
+    Stmt::new(StmtKind::Expression(Expr::raw(increment)), span)
+}
+
+fn number(value: usize) -> Expr {
+    Expr::new(ExprKind::Number(value as f64), None)
+}
+
+fn string(value: &str) -> Expr {
+    Expr::new(ExprKind::String(value.to_string()), None)
+}
+
+fn params_of(params: &[Name]) -> Vec<String> {
+    params.iter().map(|param| js_name(param).into_owned()).collect()
+}
+
+fn call(callee: Expr, arguments: Vec<Expr>) -> Expr {
+    Expr::new(ExprKind::Call { callee: Box::new(callee), arguments }, None)
+}
+
+fn assign(target: Expr, value: Expr) -> Expr {
+    Expr::new(ExprKind::Assign { target: Box::new(target), value: Box::new(value) }, None)
+}
+
+fn unary(operator: &'static str, operand: Expr) -> Expr {
+    Expr::new(ExprKind::Unary { operator, operand: Box::new(operand) }, None)
+}
@@ -0,0 +1,435 @@
+//! Short names for local variables, used when minifying
+//!
+//! [`mangle`] renames everything declared inside a function or a block
+//! (variables, parameters, functions, classes, `catch` parameters) to
+//! `a`, `b`, … Top-level declarations, imports and exports keep their
+//! names since other scripts and modules can see them, and so do names
+//! that are never declared (`console`, `Math`, other globals).
+//!
+//! A nested scope picks up numbering where its parent stopped, so a short
+//! name never shadows an outer one, and no short name is handed out if it
+//! is also a preserved name.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::parser::{jsx_component, ArrowBody, Expression, JsxChild, Name, Pattern, Program, Statement};
+
+/// A renamed copy of a program.
+pub struct Mangled {
+    pub program: Program,
+    /// Original name of every renamed identifier reference, by span start
+    pub original_names: HashMap<usize, Name>,
+}
+
+/// Words a short name must never be, even where the program doesn't use them.
+const RESERVED: &[&str] = &[
+    "do", "if", "in", "of", "for", "let", "new", "try", "var", "case", "else", "enum", "eval",
+    "null", "this", "true", "void", "with", "await", "break", "catch", "class", "const", "false",
+    "super", "throw", "while", "yield", "async", "delete", "export", "import", "public", "return",
+    "static", "switch", "typeof", "default", "extends", "finally", "package", "private", "continue",
+    "debugger", "function", "arguments", "interface", "protected", "implements", "instanceof",
+    "undefined", "NaN", "Infinity",
+];
+
+/// Renames the local names of `program`.
+pub fn mangle(program: &Program) -> Mangled {
+    let mut program = program.clone();
+
+    // First pass: find the names that must stay as they are
+    let mut mangler = Mangler::new(false, RESERVED.iter().map(|word| Name::from(*word)).collect());
+    mangler.program(&mut program);
+
+    let mut mangler = Mangler::new(true, mangler.reserved);
+    mangler.program(&mut program);
+    Mangled { program, original_names: mangler.original_names }
+}
+
+struct Scope {
+    names: HashMap<Name, Name>,
+    /// Index of the next short name to try
+    next: usize,
+}
+
+struct Mangler {
+    /// Whether to hand out short names, or only to collect `reserved`
+    renaming: bool,
+    reserved: HashSet<Name>,
+    scopes: Vec<Scope>,
+    original_names: HashMap<usize, Name>,
+}
+
+impl Mangler {
+    fn new(renaming: bool, reserved: HashSet<Name>) -> Self {
+        Self { renaming, reserved, scopes: Vec::new(), original_names: HashMap::new() }
+    }
+
+    fn program(&mut self, program: &mut Program) {
+        // Top-level names are visible outside the file: keep them
+        for stmt in &program.statements {
+            for name in declared_names(stmt) {
+                self.reserved.insert(name.clone());
+            }
+            if let Statement::Import { specifiers, .. } = stmt {
+                self.reserved.extend(specifiers.iter().map(|spec| spec.local.clone()));
+            }
+        }
+        for stmt in &mut program.statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn push_scope(&mut self) {
+        let next = self.scopes.last().map_or(0, |scope| scope.next);
+        self.scopes.push(Scope { names: HashMap::new(), next });
+    }
+
+    fn declare(&mut self, name: &Name) {
+        let short = if self.renaming { self.short_name() } else { name.clone() };
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.names.insert(name.clone(), short);
+        }
+    }
+
+    fn short_name(&mut self) -> Name {
+        let Some(scope) = self.scopes.last_mut() else { unreachable!("declare outside a scope") };
+        loop {
+            let name: Name = short_name(scope.next).into();
+            scope.next += 1;
+            if !self.reserved.contains(&name) {
+                return name;
+            }
+        }
+    }
+
+    fn resolve(&self, name: &Name) -> Option<&Name> {
+        self.scopes.iter().rev().find_map(|scope| scope.names.get(name))
+    }
+
+    /// Replaces a declared `name` with its short name
+    fn rename(&self, name: &mut Name) {
+        if let Some(short) = self.resolve(name) {
+            *name = short.clone();
+        }
+    }
+
+    /// Walks a block in its own scope, declaring its names up front since
+    /// functions and closures may use them before their declaration
+    fn block(&mut self, statements: &mut [Statement]) {
+        self.push_scope();
+        self.statements(statements);
+        self.scopes.pop();
+    }
+
+    fn statements(&mut self, statements: &mut [Statement]) {
+        for stmt in statements.iter() {
+            for name in declared_names(stmt) {
+                self.declare(name);
+            }
+        }
+        for stmt in statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn function(&mut self, params: &mut [Name], body: &mut [Statement]) {
+        self.push_scope();
+        for param in params.iter_mut() {
+            self.declare(param);
+            self.rename(param);
+        }
+        self.statements(body);
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, stmt: &mut Statement) {
+        match stmt {
+            Statement::VariableDecl { name, value, .. } => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+                self.rename(name);
+            }
+            Statement::FunctionDecl { name, params, body, .. } => {
+                self.rename(name);
+                self.function(params, body);
+            }
+            Statement::ClassDecl { name, decorators, superclass, methods, .. } => {
+                self.rename(name);
+                for decorator in decorators {
+                    self.expression(decorator);
+                }
+                if let Some(superclass) = superclass {
+                    self.expression(superclass);
+                }
+                for method in methods {
+                    // Method names are properties, only their insides are renamed
+                    if let Statement::FunctionDecl { params, body, decorators, .. } = method {
+                        for decorator in decorators {
+                            self.expression(decorator);
+                        }
+                        self.function(params, body);
+                    }
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                self.expression(condition);
+                self.block(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.block(else_branch);
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                self.expression(condition);
+                self.block(body);
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                self.push_scope();
+                if let Some(init) = init {
+                    for name in declared_names(init) {
+                        self.declare(name);
+                    }
+                    self.statement(init);
+                }
+                if let Some(condition) = condition {
+                    self.expression(condition);
+                }
+                if let Some(update) = update {
+                    self.expression(update);
+                }
+                self.block(body);
+                self.scopes.pop();
+            }
+            Statement::TryCatch { try_body, catch_param, catch_type, catch_body, .. } => {
+                self.block(try_body);
+                if let Some(catch_type) = catch_type {
+                    self.expression(catch_type);
+                }
+                self.push_scope();
+                if let Some(param) = catch_param {
+                    self.declare(param);
+                    self.rename(param);
+                }
+                self.statements(catch_body);
+                self.scopes.pop();
+            }
+            Statement::Throw { value, .. } | Statement::Expression { expression: value, .. } => self.expression(value),
+            Statement::Assert { condition, message, .. } => {
+                self.expression(condition);
+                if let Some(message) = message {
+                    self.expression(message);
+                }
+            }
+            Statement::Export { declaration, default_value, .. } => {
+                if let Some(declaration) = declaration {
+                    self.statement(declaration);
+                }
+                if let Some(value) = default_value {
+                    self.expression(value);
+                }
+            }
+            Statement::Block { statements, .. } => self.block(statements),
+            // The branches end up in the enclosing block, so in its scope
+            Statement::Conditional { then_branch, else_branch, .. } => {
+                for stmt in then_branch.iter_mut().chain(else_branch.iter_mut().flatten()) {
+                    self.statement(stmt);
+                }
+            }
+            Statement::Break { .. } | Statement::Continue { .. } | Statement::Debugger { .. }
+            | Statement::Import { .. } | Statement::Error { .. } => {}
+        }
+    }
+
+    fn expression(&mut self, expr: &mut Expression) {
+        match expr {
+            Expression::Identifier { name, span } => match self.resolve(name) {
+                Some(short) if self.renaming => {
+                    let original = std::mem::replace(name, short.clone());
+                    self.original_names.insert(span.start, original);
+                }
+                Some(_) => {}
+                None => {
+                    self.reserved.insert(name.clone());
+                }
+            },
+            Expression::Array { elements: items, .. }
+            | Expression::Template { expressions: items, .. }
+            | Expression::ConsoleLog { arguments: items, .. }
+            | Expression::SuperCall { arguments: items, .. }
+            | Expression::ConsoleWarn { arguments: items, .. }
+            | Expression::ConsoleError { arguments: items, .. } => {
+                for item in items {
+                    self.expression(item);
+                }
+            }
+            Expression::Object { properties, .. } => {
+                for (_, value) in properties {
+                    self.expression(value);
+                }
+            }
+            Expression::Binary { left, right, .. } | Expression::Assignment { target: left, value: right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::Unary { operand, .. } | Expression::Await { argument: operand, .. }
+            | Expression::TypeOf { operand, .. } | Expression::Delete { operand, .. } => self.expression(operand),
+            Expression::Call { callee, arguments, .. } | Expression::New { callee, arguments, .. } => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            Expression::Member { object, property, computed, .. } => {
+                self.expression(object);
+                // `a.b` names a property, not a variable
+                if *computed {
+                    self.expression(property);
+                }
+            }
+            Expression::ArrowFunction { params, body, .. } => match body {
+                ArrowBody::Block(body) => self.function(params, body),
+                ArrowBody::Expression(body) => {
+                    self.push_scope();
+                    for param in params.iter_mut() {
+                        self.declare(param);
+                        self.rename(param);
+                    }
+                    self.expression(body);
+                    self.scopes.pop();
+                }
+            },
+            Expression::Ternary { condition, consequent, alternate, .. } => {
+                self.expression(condition);
+                self.expression(consequent);
+                self.expression(alternate);
+            }
+            Expression::Match { subject, arms, .. } => {
+                self.expression(subject);
+                for arm in arms {
+                    self.push_scope();
+                    self.pattern(&mut arm.pattern);
+                    if let Some(guard) = &mut arm.guard {
+                        self.expression(guard);
+                    }
+                    self.expression(&mut arm.body);
+                    self.scopes.pop();
+                }
+            }
+            Expression::JsxElement { name, attributes, children, .. } => {
+                if let Some(tag) = name {
+                    if let Some(component) = jsx_component(tag).map(Name::from) {
+                        match self.resolve(&component) {
+                            Some(short) if self.renaming => *tag = Name::from(format!("{}{}", short, &tag[component.len()..])),
+                            Some(_) => {}
+                            None => { self.reserved.insert(component); }
+                        }
+                    }
+                }
+                for value in attributes.iter_mut().filter_map(|(_, value)| value.as_mut()) {
+                    self.expression(value);
+                }
+                for child in children {
+                    if let JsxChild::Expression(child) = child {
+                        self.expression(child);
+                    }
+                }
+            }
+            Expression::Number { .. } | Expression::String { .. } | Expression::Boolean { .. }
+            | Expression::Null { .. } | Expression::Undefined { .. } | Expression::This { .. }
+            | Expression::Console { .. } | Expression::Promise { .. } | Expression::Error { .. } => {}
+        }
+    }
+
+    /// Declares and renames the names a pattern binds. Object keys stay.
+    fn pattern(&mut self, pattern: &mut Pattern) {
+        match pattern {
+            Pattern::Binding { name, .. } => {
+                self.declare(name);
+                self.rename(name);
+            }
+            Pattern::Array { elements, .. } => {
+                for element in elements {
+                    self.pattern(element);
+                }
+            }
+            Pattern::Object { properties, .. } => {
+                for (_, value) in properties {
+                    self.pattern(value);
+                }
+            }
+            Pattern::Wildcard { .. } | Pattern::Literal { .. } => {}
+        }
+    }
+}
+
+/// Names `stmt` declares in the scope it appears in
+fn declared_names(stmt: &Statement) -> Vec<&Name> {
+    match stmt {
+        Statement::VariableDecl { name, .. } | Statement::FunctionDecl { name, .. } | Statement::ClassDecl { name, .. } => vec![name],
+        Statement::Export { declaration: Some(declaration), .. } => declared_names(declaration),
+        Statement::Conditional { then_branch, else_branch, .. } => then_branch.iter().chain(else_branch.iter().flatten()).flat_map(declared_names).collect(),
+        _ => vec![],
+    }
+}
+
+/// `a`…`z`, `A`…`Z`, then `aa`, `ba`, … (digits allowed after the first letter)
+fn short_name(mut index: usize) -> String {
+    const FIRST: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const REST: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    let mut name = String::from(FIRST[index % FIRST.len()] as char);
+    index /= FIRST.len();
+    while index > 0 {
+        index -= 1;
+        name.push(REST[index % REST.len()] as char);
+        index /= REST.len();
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mangled(source: &str) -> String {
+        let program = crate::parse_source(source).unwrap();
+        let mut codegen = crate::codegen::CodeGen::new(false);
+        codegen.generate(&mangle(&program).program)
+    }
+
+    #[test]
+    fn test_locals_are_renamed() {
+        let js = mangled("facc somma(primo, secondo) {\n  tien totale = primo + secondo\n  piglie totale\n}");
+        assert!(js.contains("function somma(a, b) {"), "{}", js);
+        assert!(js.contains("let c = (a + b);"), "{}", js);
+        assert!(js.contains("return c;"), "{}", js);
+    }
+
+    #[test]
+    fn test_globals_and_properties_are_kept() {
+        let js = mangled("tien a = 0\nfacc f(lista) {\n  tien x = lista.lunghezza\n  a = Math.max(x, a)\n}");
+        assert!(js.contains("let a = 0;"), "{}", js);
+        // `a` is the top-level name, so the locals start from `b`
+        assert!(js.contains("function f(b) {"), "{}", js);
+        assert!(js.contains("let c = b.lunghezza;"), "{}", js);
+        assert!(js.contains("a = Math.max(c, a);"), "{}", js);
+    }
+
+    #[test]
+    fn test_nested_scopes_do_not_shadow() {
+        let js = mangled("facc f(x) {\n  piglie (y) => x + y\n}");
+        assert!(js.contains("function f(a) {"), "{}", js);
+        assert!(js.contains("return (b) => (a + b);"), "{}", js);
+    }
+
+    #[test]
+    fn test_short_names() {
+        assert_eq!(short_name(0), "a");
+        assert_eq!(short_name(51), "Z");
+        assert_eq!(short_name(52), "aa");
+        assert_eq!(short_name(53), "ba");
+    }
+}
@@ -1,58 +1,406 @@
 //! Code Generator for FratmScript
+//!
+//! Each statement is first lowered to a small JavaScript tree (see [`js`]),
+//! which holds every decision about what to generate, then printed. [`CodeGen`]
+//! writes into any [`fmt::Write`] sink one statement at a time, so a big
+//! bundle can go straight to a file (see [`IoWriter`]) instead of being built
+//! up in memory first.
 
-use crate::parser::*;
+mod js;
+mod lower;
+mod mangle;
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::{fmt, io};
+
+use crate::coverage::Counters;
+use crate::lexer::{Comment, Span};
+use crate::parser::{Name, Program};
 use crate::sourcemap::{SourceMap, SourceMapBuilder};
+use crate::{Assertions, CodegenStyle, Decorators, Jsx, ModuleFormat, Quote, Target, Trace};
+use js::{Branch, Expr, ExprKind, Stmt, StmtKind};
 
-pub struct CodeGen {
+pub struct CodeGen<W = String> {
     indent: usize,
-    output: String,
+    output: W,
+    /// First write error; once set, nothing more is written
+    result: fmt::Result,
     source_map_enabled: bool,
     source_map_builder: SourceMapBuilder,
     current_line: usize,
     current_col: usize,
+    /// Generated position of the last mapping, to avoid duplicate segments
+    last_mapped: Option<(usize, usize)>,
+    target: Target,
+    module_format: ModuleFormat,
+    /// Global object of a [`ModuleFormat::Script`]
+    global_name: String,
+    style: CodegenStyle,
+    /// One level of indentation, from `style`
+    indent_unit: String,
+    banner: Option<String>,
+    footer: Option<String>,
+    use_strict: bool,
+    /// Source comments to re-emit, in order, and the first one not written yet
+    comments: Vec<Comment>,
+    next_comment: usize,
+    /// Lines of the source to write as comments above their code, and the
+    /// last one written
+    annotations: Vec<String>,
+    annotated_line: usize,
+    assertions: Assertions,
+    /// Rename locals to short names (see [`mangle`])
+    minify: bool,
+    /// Original names of the identifiers `minify` renamed, by span start
+    original_names: HashMap<usize, Name>,
+    optimize: bool,
+    module_aliases: BTreeMap<String, String>,
+    defines: BTreeMap<String, String>,
+    env: BTreeMap<String, String>,
+    jsx: Option<Jsx>,
+    decorators: Decorators,
+    /// Whether an `import` or `export` was written
+    is_module: bool,
+    /// Coverage counters handed out, if coverage is on
+    coverage: Option<Counters>,
+    trace: Trace,
+    trace_hook: Option<String>,
+    /// Lines of the functions marked `// traccia` (true) or `// nun traccià`
+    trace_lines: BTreeMap<usize, bool>,
 }
 
 impl CodeGen {
     pub fn new(source_map: bool) -> Self {
+        Self::with_output(String::new(), source_map)
+    }
+
+    /// Generates the whole program as a string.
+    pub fn generate(&mut self, program: &Program) -> String {
+        // Writing to a String cannot fail
+        let _ = self.write(program);
+        std::mem::take(&mut self.output)
+    }
+}
+
+impl<W: fmt::Write> CodeGen<W> {
+    /// A generator that writes into `output`.
+    pub fn with_output(output: W, source_map: bool) -> Self {
         Self {
             indent: 0,
-            output: String::new(),
+            output,
+            result: Ok(()),
             source_map_enabled: source_map,
             source_map_builder: SourceMapBuilder::new(),
             current_line: 0,
             current_col: 0,
+            last_mapped: None,
+            target: Target::default(),
+            module_format: ModuleFormat::default(),
+            global_name: "Fratm".to_string(),
+            style: CodegenStyle::default(),
+            indent_unit: "  ".to_string(),
+            banner: None,
+            footer: None,
+            use_strict: false,
+            comments: Vec::new(),
+            next_comment: 0,
+            annotations: Vec::new(),
+            annotated_line: 0,
+            assertions: Assertions::default(),
+            minify: false,
+            original_names: HashMap::new(),
+            optimize: false,
+            module_aliases: BTreeMap::new(),
+            defines: BTreeMap::new(),
+            env: BTreeMap::new(),
+            jsx: None,
+            decorators: Decorators::default(),
+            is_module: false,
+            coverage: None,
+            trace: Trace::Off,
+            trace_hook: None,
+            trace_lines: BTreeMap::new(),
         }
     }
 
-    pub fn generate(&mut self, program: &Program) -> String {
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn with_module_format(mut self, module_format: ModuleFormat) -> Self {
+        self.module_format = module_format;
+        self
+    }
+
+    /// Global object a [`ModuleFormat::Script`] exports to, `Fratm` if `None`
+    pub fn with_global_name(mut self, name: Option<String>) -> Self {
+        if let Some(name) = name { self.global_name = name; }
+        self
+    }
+
+    /// Text before and after the code; lines that aren't comments become `//` comments
+    pub fn with_banner(mut self, banner: Option<String>, footer: Option<String>) -> Self {
+        self.banner = banner;
+        self.footer = footer;
+        self
+    }
+
+    /// Emit `"use strict";` first in CommonJS and script output
+    pub fn with_use_strict(mut self, use_strict: bool) -> Self {
+        self.use_strict = use_strict;
+        self
+    }
+
+    pub fn with_assertions(mut self, assertions: Assertions) -> Self {
+        self.assertions = assertions;
+        self
+    }
+
+    /// Renames local variables and parameters to short names. The source map
+    /// keeps their original names, so debuggers can still show them.
+    pub fn with_minify(mut self, minify: bool) -> Self {
+        self.minify = minify;
+        self
+    }
+
+    /// Folds string concatenations: `"a" + "b"` is `"ab"`, and `"Uè " + nome`
+    /// is `` `Uè ${nome}` ``.
+    pub fn with_optimize(mut self, optimize: bool) -> Self {
+        self.optimize = optimize;
+        self
+    }
+
+    /// Module names imported as other specifiers (see [`crate::modules::resolve_alias`])
+    pub fn with_module_aliases(mut self, module_aliases: BTreeMap<String, String>) -> Self {
+        self.module_aliases = module_aliases;
+        self
+    }
+
+    /// Names for `#si` directives, which choose the branches to compile
+    pub fn with_defines(mut self, defines: BTreeMap<String, String>) -> Self {
+        self.defines = defines;
+        self
+    }
+
+    /// Logs the calls and returns of functions; `comments` mark the ones on
+    /// the next line with `// traccia` or `// nun traccià`
+    pub fn with_trace(mut self, trace: Trace, hook: Option<String>, comments: &[Comment]) -> Self {
+        self.trace = trace;
+        self.trace_hook = hook;
+        self.trace_lines = comments.iter().filter_map(|comment| {
+            let on = match comment.text.strip_prefix("//")?.trim() {
+                "traccia" => true,
+                "nun traccià" | "nun traccia" => false,
+                _ => return None,
+            };
+            Some((comment.span.line + 1, on))
+        }).collect();
+        self
+    }
+
+    /// Counts the statements and branches that run (see [`crate::coverage`])
+    pub fn with_coverage(mut self, coverage: bool) -> Self {
+        self.coverage = coverage.then(Counters::default);
+        self
+    }
+
+    /// Values of `amb("NAME")`; one missing compiles to `undefined`
+    pub fn with_env(mut self, env: BTreeMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// What JSX elements compile to; without it they can't be parsed anyway
+    pub fn with_jsx(mut self, jsx: Option<Jsx>) -> Self {
+        self.jsx = jsx;
+        self
+    }
+
+    pub fn with_decorators(mut self, decorators: Decorators) -> Self {
+        self.decorators = decorators;
+        self
+    }
+
+    /// Whether the code written so far is an ES module: it has an `import`
+    /// or an `export`, so Node has to load it as one (`.mjs`)
+    pub fn is_module(&self) -> bool {
+        self.is_module
+    }
+
+    /// Comments captured by the lexer, written next to the statements around them
+    pub fn with_comments(mut self, comments: Vec<Comment>) -> Self {
+        self.comments = comments;
+        self.next_comment = 0;
+        self
+    }
+
+    /// Writes the lines of `source` as comments above the code they compile to
+    pub fn with_annotations(mut self, source: &str) -> Self {
+        self.annotations = source.lines().map(str::to_string).collect();
+        self.annotated_line = 0;
+        self
+    }
+
+    pub fn with_style(mut self, style: CodegenStyle) -> Self {
+        self.indent_unit = if style.use_tabs { "\t".to_string() } else { " ".repeat(style.indent_width) };
+        self.style = style;
+        self
+    }
+
+    fn lowering(&self) -> lower::Lowering<'_> {
+        lower::Lowering {
+            target: self.target,
+            module_format: self.module_format,
+            global_name: &self.global_name,
+            assertions: self.assertions,
+            optimize: self.optimize,
+            module_aliases: &self.module_aliases,
+            defines: &self.defines,
+            env: &self.env,
+            original_names: &self.original_names,
+            jsx: self.jsx.as_ref(),
+            jsx_imports: Default::default(),
+            decorators: self.decorators,
+            coverage: self.coverage.as_ref(),
+            trace: self.trace,
+            trace_hook: self.trace_hook.as_deref(),
+            trace_lines: &self.trace_lines,
+            traced: Default::default(),
+            trace_used: Default::default(),
+        }
+    }
+
+    /// Writes the program to the output, stopping at the first write error.
+    pub fn write(&mut self, program: &Program) -> fmt::Result {
+        let mangled;
+        let program = if self.minify {
+            mangled = mangle::mangle(program);
+            self.original_names = mangled.original_names;
+            &mangled.program
+        } else {
+            program
+        };
+        if let Some(banner) = self.banner.take() {
+            self.emit_comment(&banner);
+            self.banner = Some(banner);
+        }
+        // A script runs in a function, so that only its exports end up global
+        let script = self.module_format == ModuleFormat::Script;
+        if script {
+            self.emit("(function (exports) {\n");
+            self.indent += 1;
+        }
+        if self.use_strict && matches!(self.module_format, ModuleFormat::CommonJs | ModuleFormat::Script) {
+            self.write_indent();
+            self.emit_string("use strict");
+            self.end_statement();
+            self.emit("\n");
+        }
+        // The automatic JSX runtime is imported first, and only if an element
+        // needs it, the coverage collector needs the number of counters, and
+        // the tracing helper is only there for a traced function: all take
+        // lowering the whole program up front
+        let mut lowered = Vec::new();
+        let jsx_source = match &self.jsx {
+            Some(Jsx::Automatic { import_source }) => Some(format!("{}/jsx-runtime", import_source)),
+            _ => None,
+        };
+        if jsx_source.is_some() || self.coverage.is_some() || self.trace != Trace::Off {
+            let lowering = self.lowering();
+            lowered = program.statements.iter().map(|stmt| lowering.statements(std::slice::from_ref(stmt))).collect();
+            let names: Vec<(String, String)> = lowering.jsx_imports.take().into_iter().map(|name| (name.to_string(), format!("_{}", name))).collect();
+            let mut header = Vec::new();
+            if let Some(source) = jsx_source.filter(|_| !names.is_empty()) {
+                header.push(Stmt::new(lowering.import(names, source), None));
+            }
+            if let Some(counters) = &self.coverage {
+                header.extend(lowering.coverage_collector(counters.len()));
+            }
+            if lowering.trace_used.get() {
+                header.push(lowering.trace_exit());
+            }
+            for stmt in header {
+                self.gen_statement(&stmt, true);
+                self.emit("\n");
+            }
+        }
+        let mut lowered = lowered.into_iter();
         for stmt in &program.statements {
-            self.gen_statement(stmt);
+            let statements = lowered.next().unwrap_or_else(|| self.lowering().statements(std::slice::from_ref(stmt)));
+            for stmt in statements {
+                self.gen_statement(&stmt, true);
+                self.emit("\n");
+            }
+            self.result?;
+        }
+        self.flush_comments_before(usize::MAX);
+        if script {
+            self.indent -= 1;
+            let global = format!("globalThis.{}", self.global_name);
+            self.emit(&format!("}})({} = {} || {{}})", global, global));
+            self.end_statement();
+            self.emit("\n");
+        }
+        if let Some(footer) = self.footer.take() {
+            self.emit_comment(&footer);
+            self.footer = Some(footer);
+        }
+        self.result
+    }
+
+    /// Emits `text` line by line, as `//` comments unless it is already commented
+    fn emit_comment(&mut self, text: &str) {
+        let trimmed = text.trim_start();
+        if trimmed.starts_with("//") || trimmed.starts_with("/*") {
+            self.emit(text.trim_end());
+            self.emit("\n");
+            return;
+        }
+        for line in text.trim_end().lines() {
+            self.emit(if line.is_empty() { "//" } else { "// " });
+            self.emit(line);
             self.emit("\n");
         }
-        self.output.clone()
+    }
+
+    /// Gives the output back, e.g. to flush it.
+    pub fn into_output(self) -> W {
+        self.output
     }
 
     pub fn get_source_map(&self) -> SourceMap {
         self.source_map_builder.clone().build(None)
     }
 
+    /// Whether to record mappings; constant false without the `sourcemap`
+    /// feature, so the mapping code is left out of the build
+    fn mapping(&self) -> bool {
+        cfg!(feature = "sourcemap") && self.source_map_enabled
+    }
+
     fn emit(&mut self, s: &str) {
         for c in s.chars() {
             if c == '\n' {
                 self.current_line += 1;
                 self.current_col = 0;
-                if self.source_map_enabled {
+                if self.mapping() {
                     self.source_map_builder.new_line();
                 }
             } else {
                 self.current_col += 1;
             }
         }
-        self.output.push_str(s);
+        if self.result.is_ok() {
+            self.result = self.output.write_str(s);
+        }
     }
 
     fn add_mapping(&mut self, src_line: usize, src_col: usize) {
-        if self.source_map_enabled {
+        let position = (self.current_line, self.current_col);
+        if self.mapping() && self.last_mapped != Some(position) {
+            self.last_mapped = Some(position);
             self.source_map_builder.add_mapping(
                 self.current_line,
                 self.current_col,
@@ -62,331 +410,333 @@ impl CodeGen {
         }
     }
 
-    fn gen_statement(&mut self, stmt: &Statement) {
-        match stmt {
-            Statement::VariableDecl { name, value, is_const, span, .. } => {
-                self.write_indent();
-                self.add_mapping(span.line, span.column);
-                self.emit(if *is_const { "const " } else { "let " });
-                self.emit(name);
-                if let Some(val) = value {
-                    self.emit(" = ");
-                    self.gen_expression(val);
-                }
-                self.emit(";");
-            }
+    /// Maps a renamed identifier back to its original name
+    fn add_named_mapping(&mut self, span: Span, name: &str) {
+        if self.mapping() {
+            // Replaces the mapping of a statement starting here, if any
+            self.last_mapped = Some((self.current_line, self.current_col));
+            self.source_map_builder.add_named_mapping(
+                self.current_line,
+                self.current_col,
+                span.line.saturating_sub(1),
+                span.column.saturating_sub(1),
+                name,
+            );
+        }
+    }
 
-            Statement::FunctionDecl { name, params, body, is_async, span, .. } => {
-                self.write_indent();
-                self.add_mapping(span.line, span.column);
+    fn gen_statement(&mut self, stmt: &Stmt, indent: bool) {
+        if let Some(span) = stmt.span { self.flush_comments_before(span.start); }
+        if let (Some(span), true) = (stmt.span, indent) {
+            // A statement with a body only shows its first line: the body's statements show theirs
+            let header = matches!(stmt.kind, StmtKind::Function { .. } | StmtKind::If { .. } | StmtKind::While { .. } | StmtKind::For { .. }
+                | StmtKind::Try { .. } | StmtKind::Class { .. } | StmtKind::Export { .. } | StmtKind::Block(_));
+            self.annotate(span, header);
+        }
+        if indent { self.write_indent(); }
+        self.gen_statement_inner(stmt);
+        if let Some(span) = stmt.span { self.trailing_comment(span); }
+    }
+
+    /// `{`, the statements indented, then comments left before `end` and `}`
+    fn gen_block(&mut self, block: &js::Block) {
+        self.emit("{\n");
+        self.indent += 1;
+        for s in &block.statements {
+            self.gen_statement(s, true);
+            self.emit("\n");
+        }
+        if let Some(end) = block.end { self.flush_comments_before(end); }
+        self.indent -= 1;
+        self.write_indent();
+        self.emit("}");
+    }
+
+    fn gen_branch(&mut self, branch: &Branch) {
+        match branch {
+            Branch::Block(block) => self.gen_block(block),
+            Branch::Statement(stmt) => self.gen_statement(stmt, false),
+        }
+    }
+
+    fn gen_statement_inner(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            // No mapping: there is nothing of the source here
+            StmtKind::Empty => { self.emit(";"); return; }
+            StmtKind::Expression(expression) if !self.style.semicolons && continues_previous_line(expression) => self.emit(";"),
+            _ => {}
+        }
+        self.map(stmt.span);
+        match &stmt.kind {
+            StmtKind::Var { .. } => {
+                self.gen_declaration(stmt);
+                self.end_statement();
+            }
+            StmtKind::Function { is_async, name, params, body } => {
                 if *is_async { self.emit("async "); }
                 self.emit("function ");
                 self.emit(name);
                 self.emit("(");
-                self.emit(&params.join(", "));
-                self.emit(") {\n");
-                self.indent += 1;
-                for s in body { self.gen_statement(s); self.emit("\n"); }
-                self.indent -= 1;
-                self.write_indent();
-                self.emit("}");
+                self.emit_params(params);
+                self.emit(") ");
+                self.gen_block(body);
             }
-
-            Statement::Return { value, span, .. } => {
-                self.write_indent();
-                self.add_mapping(span.line, span.column);
+            StmtKind::Return(value) => {
                 self.emit("return");
                 if let Some(val) = value { self.emit(" "); self.gen_expression(val); }
-                self.emit(";");
+                self.end_statement();
             }
-
-            Statement::If { condition, then_branch, else_branch, span, .. } => {
-                self.write_indent();
-                self.add_mapping(span.line, span.column);
+            StmtKind::If { condition, then, otherwise } => {
                 self.emit("if (");
                 self.gen_expression(condition);
-                self.emit(") {\n");
-                self.indent += 1;
-                for s in then_branch { self.gen_statement(s); self.emit("\n"); }
-                self.indent -= 1;
-                self.write_indent();
-                self.emit("}");
-                if let Some(else_body) = else_branch {
+                self.emit(") ");
+                self.gen_branch(then);
+                if let Some(otherwise) = otherwise {
                     self.emit(" else ");
-                    if else_body.len() == 1 {
-                        if let Statement::If { .. } = &else_body[0] {
-                            self.gen_statement(&else_body[0]);
-                            return;
-                        }
-                    }
-                    self.emit("{\n");
-                    self.indent += 1;
-                    for s in else_body { self.gen_statement(s); self.emit("\n"); }
-                    self.indent -= 1;
-                    self.write_indent();
-                    self.emit("}");
+                    self.gen_branch(otherwise);
                 }
             }
-
-            Statement::While { condition, body, span, .. } => {
-                self.write_indent();
-                self.add_mapping(span.line, span.column);
+            StmtKind::While { condition, body } => {
                 self.emit("while (");
                 self.gen_expression(condition);
-                self.emit(") {\n");
-                self.indent += 1;
-                for s in body { self.gen_statement(s); self.emit("\n"); }
-                self.indent -= 1;
-                self.write_indent();
-                self.emit("}");
+                self.emit(") ");
+                self.gen_block(body);
             }
-
-            Statement::For { init, condition, update, body, span, .. } => {
-                self.write_indent();
-                self.add_mapping(span.line, span.column);
+            StmtKind::For { init, condition, update, body } => {
                 self.emit("for (");
-                if let Some(i) = init {
-                    match i.as_ref() {
-                        Statement::VariableDecl { name, value, is_const, .. } => {
-                            self.emit(if *is_const { "const " } else { "let " });
-                            self.emit(name);
-                            if let Some(val) = value { self.emit(" = "); self.gen_expression(val); }
-                        }
-                        Statement::Expression { expression, .. } => self.gen_expression(expression),
-                        _ => {}
-                    }
-                }
+                if let Some(init) = init { self.gen_declaration(init); }
                 self.emit("; ");
                 if let Some(c) = condition { self.gen_expression(c); }
                 self.emit("; ");
                 if let Some(u) = update { self.gen_expression(u); }
-                self.emit(") {\n");
-                self.indent += 1;
-                for s in body { self.gen_statement(s); self.emit("\n"); }
-                self.indent -= 1;
-                self.write_indent();
-                self.emit("}");
+                self.emit(") ");
+                self.gen_block(body);
             }
-
-            Statement::Break { .. } => { self.write_indent(); self.emit("break;"); }
-            Statement::Continue { .. } => { self.write_indent(); self.emit("continue;"); }
-            Statement::Debugger { .. } => { self.write_indent(); self.emit("debugger;"); }
-
-            Statement::TryCatch { try_body, catch_param, catch_body, span, .. } => {
-                self.write_indent();
-                self.add_mapping(span.line, span.column);
-                self.emit("try {\n");
-                self.indent += 1;
-                for s in try_body { self.gen_statement(s); self.emit("\n"); }
-                self.indent -= 1;
-                self.write_indent();
-                self.emit("} catch");
-                if let Some(param) = catch_param { self.emit(" ("); self.emit(param); self.emit(")"); }
-                self.emit(" {\n");
-                self.indent += 1;
-                for s in catch_body { self.gen_statement(s); self.emit("\n"); }
-                self.indent -= 1;
-                self.write_indent();
-                self.emit("}");
+            StmtKind::Break => { self.emit("break"); self.end_statement(); }
+            StmtKind::Continue => { self.emit("continue"); self.end_statement(); }
+            StmtKind::Debugger => { self.emit("debugger"); self.end_statement(); }
+            StmtKind::Empty => {}
+            StmtKind::Try { body, param, handler } => {
+                self.emit("try ");
+                self.gen_block(body);
+                self.emit(" catch");
+                if let Some(param) = param { self.emit(" ("); self.emit(param); self.emit(")"); }
+                self.emit(" ");
+                self.gen_block(handler);
             }
-
-            Statement::Throw { value, .. } => {
-                self.write_indent();
+            StmtKind::Throw(value) => {
                 self.emit("throw ");
                 self.gen_expression(value);
-                self.emit(";");
+                self.end_statement();
             }
-
-            Statement::ClassDecl { name, methods, span, .. } => {
-                self.write_indent();
-                self.add_mapping(span.line, span.column);
+            StmtKind::Class { name, decorators, superclass, methods, then } => {
+                self.emit_decorators(decorators);
                 self.emit("class ");
                 self.emit(name);
+                if let Some(superclass) = superclass {
+                    self.emit(" extends ");
+                    self.gen_expression(superclass);
+                }
                 self.emit(" {\n");
                 self.indent += 1;
                 for method in methods {
-                    if let Statement::FunctionDecl { name: method_name, params, body, is_async, .. } = method {
-                        self.write_indent();
-                        if *is_async { self.emit("async "); }
-                        // Translate "costruttore" to JavaScript "constructor"
-                        let js_method_name = if method_name == "costruttore" { "constructor" } else { method_name };
-                        self.emit(js_method_name);
-                        self.emit("(");
-                        self.emit(&params.join(", "));
-                        self.emit(") {\n");
-                        self.indent += 1;
-                        for s in body { self.gen_statement(s); self.emit("\n"); }
-                        self.indent -= 1;
-                        self.write_indent();
-                        self.emit("}\n");
-                    }
+                    self.flush_comments_before(method.span.start);
+                    self.annotate(method.span, true);
+                    self.write_indent();
+                    self.add_mapping(method.span.line, method.span.column);
+                    self.emit_decorators(&method.decorators);
+                    if method.is_async { self.emit("async "); }
+                    self.emit(&method.name);
+                    self.emit("(");
+                    self.emit_params(&method.params);
+                    self.emit(") ");
+                    self.gen_block(&method.body);
+                    self.trailing_comment(method.span);
+                    self.emit("\n");
                 }
+                if let Some(span) = stmt.span { self.flush_comments_before(span.end); }
                 self.indent -= 1;
                 self.write_indent();
                 self.emit("}");
+                for stmt in then {
+                    self.emit("\n");
+                    self.gen_statement(stmt, true);
+                }
             }
-
-            Statement::Import { specifiers, source, .. } => {
-                self.write_indent();
+            StmtKind::Import { names, source } => {
+                self.is_module = true;
                 self.emit("import { ");
-                let names: Vec<&str> = specifiers.iter().map(|s| s.local.as_str()).collect();
-                self.emit(&names.join(", "));
-                self.emit(" } from \"");
-                self.emit(source);
-                self.emit("\";");
+                self.emit_names(names, " as ");
+                self.emit(" } from ");
+                self.emit_string(source);
+                self.end_statement();
             }
-
-            Statement::Export { declaration, default_value, .. } => {
-                self.write_indent();
-                if let Some(val) = default_value {
-                    self.emit("export default ");
-                    self.gen_expression(val);
-                    self.emit(";");
-                } else if let Some(decl) = declaration {
+            StmtKind::Require { names, source } => {
+                self.emit("const { ");
+                self.emit_names(names, ": ");
+                self.emit(" } = require(");
+                self.emit_string(source);
+                self.emit(")");
+                self.end_statement();
+            }
+            StmtKind::Destructure { names, value } => {
+                self.emit("const { ");
+                self.emit_names(names, ": ");
+                self.emit(" } = ");
+                self.gen_expression(value);
+                self.end_statement();
+            }
+            StmtKind::Export { keyword, declaration, then } => {
+                if *keyword {
+                    self.is_module = true;
                     self.emit("export ");
-                    let saved = self.indent;
-                    self.indent = 0;
-                    self.gen_statement(decl);
-                    self.indent = saved;
+                }
+                self.gen_statement(declaration, false);
+                if let Some(then) = then {
+                    self.emit("\n");
+                    self.gen_statement(then, true);
                 }
             }
-
-            Statement::Expression { expression, .. } => {
-                self.write_indent();
-                self.gen_expression(expression);
-                self.emit(";");
+            StmtKind::ExportDefault(value) => {
+                self.is_module = true;
+                self.emit("export default ");
+                self.gen_expression(value);
+                self.end_statement();
             }
-
-            Statement::Block { statements, .. } => {
-                self.write_indent();
-                self.emit("{\n");
-                self.indent += 1;
-                for s in statements { self.gen_statement(s); self.emit("\n"); }
-                self.indent -= 1;
-                self.write_indent();
-                self.emit("}");
+            StmtKind::ExportNames(names) => {
+                self.is_module = true;
+                self.emit("export { ");
+                self.emit_names(names, " as ");
+                self.emit(" }");
+                self.end_statement();
             }
+            StmtKind::Expression(expression) => {
+                self.gen_expression(expression);
+                self.end_statement();
+            }
+            StmtKind::Block(block) => self.gen_block(block),
         }
     }
 
-    fn gen_expression(&mut self, expr: &Expression) {
-        match expr {
-            Expression::Identifier { name, span, .. } => {
-                self.add_mapping(span.line, span.column);
+    /// A `Var` without its `;`, or an expression: what `for (` starts with
+    fn gen_declaration(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Var { is_const, name, value } => {
+                self.emit(if *is_const { "const " } else { "let " });
                 self.emit(name);
+                if let Some(val) = value { self.emit(" = "); self.gen_expression(val); }
             }
-            Expression::Number { value, .. } => {
+            StmtKind::Expression(expression) => self.gen_expression(expression),
+            _ => {}
+        }
+    }
+
+    fn gen_expression(&mut self, expr: &Expr) {
+        match (&expr.kind, expr.span) {
+            (ExprKind::Identifier { original: Some(original), .. }, Some(span)) => self.add_named_mapping(span, original),
+            (_, span) => self.map(span),
+        }
+        match &expr.kind {
+            ExprKind::Identifier { name, .. } => self.emit(name),
+            ExprKind::Raw(text) => self.emit(text),
+            ExprKind::Number(value) => {
                 if *value == value.floor() && value.abs() < 1e15 {
                     self.emit(&(*value as i64).to_string());
                 } else {
                     self.emit(&value.to_string());
                 }
             }
-            Expression::String { value, .. } => {
-                self.emit("\"");
-                for c in value.chars() {
-                    match c {
-                        '"' => self.emit("\\\""),
-                        '\\' => self.emit("\\\\"),
-                        '\n' => self.emit("\\n"),
-                        '\r' => self.emit("\\r"),
-                        '\t' => self.emit("\\t"),
-                        _ => self.emit(&c.to_string()),
-                    }
-                }
-                self.emit("\"");
-            }
-            Expression::Boolean { value, .. } => self.emit(if *value { "true" } else { "false" }),
-            Expression::Null { .. } => self.emit("null"),
-            Expression::Undefined { .. } => self.emit("undefined"),
-            Expression::This { .. } => self.emit("this"),
-            Expression::Array { elements, .. } => {
+            ExprKind::String(value) => self.emit_string(value),
+            ExprKind::Template { quasis, expressions } => self.emit_template(quasis, expressions),
+            ExprKind::Array(elements) => {
                 self.emit("[");
-                for (i, elem) in elements.iter().enumerate() {
-                    if i > 0 { self.emit(", "); }
-                    self.gen_expression(elem);
-                }
+                self.gen_list(elements);
                 self.emit("]");
             }
-            Expression::Object { properties, .. } => {
+            ExprKind::Object(properties) if properties.is_empty() => self.emit("{}"),
+            ExprKind::Object(properties) => {
                 self.emit("{ ");
                 for (i, (key, value)) in properties.iter().enumerate() {
                     if i > 0 { self.emit(", "); }
-                    self.emit(key);
+                    // JSX props such as `aria-label` need quotes
+                    if key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$') { self.emit(key); } else { self.emit_string(key); }
                     self.emit(": ");
                     self.gen_expression(value);
                 }
                 self.emit(" }");
             }
-            Expression::Binary { left, operator, right, .. } => {
+            ExprKind::Binary { left, operator, right } => {
                 self.emit("(");
-                self.gen_expression(left);
+                // `-2 ** 2` is a syntax error in JavaScript
+                if *operator == "**" && matches!(left.kind, ExprKind::Unary { .. }) {
+                    self.emit("(");
+                    self.gen_expression(left);
+                    self.emit(")");
+                } else {
+                    self.gen_expression(left);
+                }
                 self.emit(" ");
-                self.emit(operator.to_js());
+                self.emit(operator);
                 self.emit(" ");
                 self.gen_expression(right);
                 self.emit(")");
             }
-            Expression::Unary { operator, operand, .. } => {
-                self.emit(operator.to_js());
-                self.gen_expression(operand);
+            ExprKind::Unary { operator, operand } => {
+                self.emit(operator);
+                // `- -a` written `--a` would be a decrement
+                if operator.starts_with(char::is_alphabetic) {
+                    self.emit(" ");
+                    self.gen_expression(operand);
+                } else if *operator == "-" && matches!(operand.kind, ExprKind::Unary { operator: "-", .. }) {
+                    self.emit("(");
+                    self.gen_expression(operand);
+                    self.emit(")");
+                } else {
+                    self.gen_expression(operand);
+                }
             }
-            Expression::Assignment { target, value, .. } => {
+            ExprKind::Assign { target, value } => {
                 self.gen_expression(target);
                 self.emit(" = ");
                 self.gen_expression(value);
             }
-            Expression::Call { callee, arguments, .. } => {
+            ExprKind::Call { callee, arguments } => {
                 self.gen_expression(callee);
+                // V8 puts the call of `(...)(x)` at its `(`, which would
+                // otherwise map to the end of the callee
+                if matches!(callee.kind, ExprKind::Paren(_)) { self.map(expr.span); }
                 self.emit("(");
-                for (i, arg) in arguments.iter().enumerate() {
-                    if i > 0 { self.emit(", "); }
-                    self.gen_expression(arg);
-                }
+                self.gen_list(arguments);
                 self.emit(")");
             }
-            Expression::Member { object, property, computed, .. } => {
+            ExprKind::Member { object, property, optional } => {
                 self.gen_expression(object);
-                if *computed {
-                    self.emit("[");
-                    self.gen_expression(property);
-                    self.emit("]");
-                } else {
-                    self.emit(".");
-                    self.gen_expression(property);
-                }
+                self.emit(if *optional { "?." } else { "." });
+                self.gen_expression(property);
+            }
+            ExprKind::Index { object, index, optional } => {
+                self.gen_expression(object);
+                self.emit(if *optional { "?.[" } else { "[" });
+                self.gen_expression(index);
+                self.emit("]");
             }
-            Expression::New { callee, arguments, .. } => {
+            ExprKind::New { callee, arguments } => {
                 self.emit("new ");
                 self.gen_expression(callee);
                 self.emit("(");
-                for (i, arg) in arguments.iter().enumerate() {
-                    if i > 0 { self.emit(", "); }
-                    self.gen_expression(arg);
-                }
+                self.gen_list(arguments);
                 self.emit(")");
             }
-            Expression::ArrowFunction { params, body, .. } => {
+            ExprKind::Arrow { params, body } => {
                 self.emit("(");
-                self.emit(&params.join(", "));
+                self.emit_params(params);
                 self.emit(") => ");
                 match body {
-                    ArrowBody::Expression(e) => self.gen_expression(e),
-                    ArrowBody::Block(stmts) => {
-                        self.emit("{\n");
-                        self.indent += 1;
-                        for s in stmts { self.gen_statement(s); self.emit("\n"); }
-                        self.indent -= 1;
-                        self.write_indent();
-                        self.emit("}");
-                    }
+                    js::ArrowBody::Expression(e) => self.gen_expression(e),
+                    js::ArrowBody::Block(block) => self.gen_block(block),
                 }
             }
-            Expression::Await { argument, .. } => {
-                self.emit("await ");
-                self.gen_expression(argument);
-            }
-            Expression::Ternary { condition, consequent, alternate, .. } => {
+            ExprKind::Conditional { condition, consequent, alternate } => {
                 self.emit("(");
                 self.gen_expression(condition);
                 self.emit(" ? ");
@@ -395,46 +745,475 @@ impl CodeGen {
                 self.gen_expression(alternate);
                 self.emit(")");
             }
-            Expression::ConsoleLog { arguments, .. } => {
-                self.emit("console.log(");
-                for (i, arg) in arguments.iter().enumerate() {
-                    if i > 0 { self.emit(", "); }
-                    self.gen_expression(arg);
-                }
-                self.emit(")");
-            }
-            Expression::ConsoleWarn { arguments, .. } => {
-                self.emit("console.warn(");
-                for (i, arg) in arguments.iter().enumerate() {
-                    if i > 0 { self.emit(", "); }
-                    self.gen_expression(arg);
-                }
-                self.emit(")");
-            }
-            Expression::ConsoleError { arguments, .. } => {
-                self.emit("console.error(");
-                for (i, arg) in arguments.iter().enumerate() {
-                    if i > 0 { self.emit(", "); }
-                    self.gen_expression(arg);
-                }
+            ExprKind::Paren(inner) => {
+                self.emit("(");
+                self.gen_expression(inner);
                 self.emit(")");
             }
-            Expression::TypeOf { operand, .. } => {
-                self.emit("typeof ");
-                self.gen_expression(operand);
-            }
-            Expression::Delete { operand, .. } => {
-                self.emit("delete ");
-                self.gen_expression(operand);
-            }
         }
     }
 
+    fn gen_list(&mut self, expressions: &[Expr]) {
+        for (i, expr) in expressions.iter().enumerate() {
+            if i > 0 { self.emit(", "); }
+            self.gen_expression(expr);
+        }
+    }
+
+    fn map(&mut self, span: Option<Span>) {
+        if let Some(span) = span { self.add_mapping(span.line, span.column); }
+    }
+
     fn write_indent(&mut self) {
-        for _ in 0..self.indent { self.emit("  "); }
+        let indent = self.indent_unit.repeat(self.indent);
+        self.emit(&indent);
+    }
+
+    /// Writes every pending comment that starts before `offset` on its own line.
+    fn flush_comments_before(&mut self, offset: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.span.start >= offset { break; }
+            let text = comment.text.clone();
+            self.next_comment += 1;
+            self.write_indent();
+            self.emit(&text);
+            self.emit("\n");
+        }
+    }
+
+    /// Writes the source lines of `span` not written yet as comments, only the
+    /// first with `header`. Blank lines are left out.
+    fn annotate(&mut self, span: Span, header: bool) {
+        let last = if header { span.line } else { span.end_line };
+        for line in span.line.max(self.annotated_line + 1)..=last.min(self.annotations.len()) {
+            let text = self.annotations[line - 1].trim().to_string();
+            if text.is_empty() { continue; }
+            self.write_indent();
+            self.emit("// ");
+            self.emit(&text);
+            self.emit("\n");
+        }
+        self.annotated_line = self.annotated_line.max(last);
+    }
+
+    /// Keeps a comment that sits on the same line right after a statement.
+    fn trailing_comment(&mut self, span: Span) {
+        if let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.span.line == span.end_line && comment.span.start >= span.end {
+                let text = comment.text.clone();
+                self.next_comment += 1;
+                self.emit(" ");
+                self.emit(&text);
+            }
+        }
+    }
+
+    fn end_statement(&mut self) {
+        if self.style.semicolons { self.emit(";"); }
+    }
+
+    /// `@a @b(c) `, before a class or a method
+    fn emit_decorators(&mut self, decorators: &[Expr]) {
+        for decorator in decorators {
+            self.emit("@");
+            self.gen_expression(decorator);
+            self.emit(" ");
+        }
+    }
+
+    fn emit_params(&mut self, params: &[String]) {
+        self.emit(&params.join(", "));
+    }
+
+    /// `a, b as c`: (first, second) pairs, `separator` between the two when they differ
+    fn emit_names(&mut self, names: &[(String, String)], separator: &str) {
+        for (i, (first, second)) in names.iter().enumerate() {
+            if i > 0 { self.emit(", "); }
+            self.emit(first);
+            if first != second { self.emit(separator); self.emit(second); }
+        }
+    }
+
+    /// Emits `value` as a string literal, in the configured quotes unless
+    /// the other ones need fewer escapes (`'say "hi"'`)
+    fn emit_string(&mut self, value: &str) {
+        let (preferred, other) = match self.style.quote { Quote::Double => ('"', '\''), Quote::Single => ('\'', '"') };
+        let count = |quote: char| value.chars().filter(|&c| c == quote).count();
+        let quote = if count(preferred) > count(other) { other } else { preferred };
+        let mut literal = String::with_capacity(value.len() + 2);
+        literal.push(quote);
+        escape(&mut literal, value, quote);
+        literal.push(quote);
+        self.emit(&literal);
+    }
+
+    fn emit_template(&mut self, quasis: &[String], expressions: &[Expr]) {
+        let mut text = String::from("`");
+        escape(&mut text, &quasis[0], '`');
+        self.emit(&text);
+        for (expression, quasi) in expressions.iter().zip(&quasis[1..]) {
+            self.emit("${");
+            self.gen_expression(expression);
+            let mut text = String::from("}");
+            escape(&mut text, quasi, '`');
+            self.emit(&text);
+        }
+        self.emit("`");
+    }
+}
+
+/// Appends `value` to `literal`, escaped for a string between `quote`s
+fn escape(literal: &mut String, value: &str, quote: char) {
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c if c == quote => { literal.push('\\'); literal.push(c); }
+            // `${` would start an expression in a template
+            '$' if quote == '`' && chars.peek() == Some(&'{') => literal.push_str("\\$"),
+            // Line terminators for JavaScript engines older than ES2019
+            '\u{2028}' => literal.push_str("\\u2028"),
+            '\u{2029}' => literal.push_str("\\u2029"),
+            // `\x00` rather than `\0`, which a following digit would turn into an octal escape
+            c if c.is_control() && (c as u32) < 0x100 => literal.push_str(&format!("\\x{:02x}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+}
+
+/// Whether `expression` starts with a character that would join it to the
+/// previous line when there is no `;` to end it
+fn continues_previous_line(expression: &Expr) -> bool {
+    match &expression.kind {
+        ExprKind::Binary { .. } | ExprKind::Conditional { .. } | ExprKind::Arrow { .. } | ExprKind::Array(_) | ExprKind::Paren(_) => true,
+        // A tagged template otherwise
+        ExprKind::Template { .. } => true,
+        ExprKind::Unary { operator, .. } => *operator == "-",
+        ExprKind::Assign { target: inner, .. }
+        | ExprKind::Call { callee: inner, .. }
+        | ExprKind::Member { object: inner, .. }
+        | ExprKind::Index { object: inner, .. } => continues_previous_line(inner),
+        _ => false,
     }
 }
 
+/// Words JavaScript doesn't allow as variable names (in strict mode and modules)
+const RESERVED_WORDS: &[&str] = &[
+    "await", "break", "case", "catch", "class", "const", "continue", "debugger", "default", "delete",
+    "do", "else", "enum", "export", "extends", "false", "finally", "for", "function", "if",
+    "implements", "import", "in", "instanceof", "interface", "let", "new", "null", "package",
+    "private", "protected", "public", "return", "static", "super", "switch", "this", "throw",
+    "true", "try", "typeof", "var", "void", "while", "with", "yield",
+];
+
+/// Whether `name` can't be a JavaScript variable, e.g. `class` or `delete`.
+/// FratmScript allows them, so the generated code renames them (see [`js_name`]).
+pub fn is_reserved_word(name: &str) -> bool {
+    RESERVED_WORDS.contains(&name)
+}
+
+/// `name` as written in the JavaScript output: reserved words get a `$`
+/// in front, which can't clash since FratmScript names never contain one.
+pub fn js_name(name: &str) -> Cow<'_, str> {
+    if is_reserved_word(name) { Cow::Owned(format!("${}", name)) } else { Cow::Borrowed(name) }
+}
+
 impl Default for CodeGen {
     fn default() -> Self { Self::new(false) }
 }
+
+/// Adapts an [`io::Write`] (a file, stdout, ...) into the [`fmt::Write`]
+/// sink [`CodeGen`] writes to, keeping the I/O error that `fmt::Error` can't carry.
+pub struct IoWriter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> IoWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, error: None }
+    }
+
+    /// The wrapped writer, or the error that stopped the writing.
+    pub fn finish(self) -> io::Result<W> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.inner),
+        }
+    }
+}
+
+impl<W: io::Write> fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Expression, Statement};
+    use crate::{compile, CompileOptions};
+
+    /// Finds the generated (line, col) of `needle` and looks it up in the map.
+    #[cfg(feature = "sourcemap")]
+    fn original_position(source: &str, needle: &str) -> Option<(usize, usize)> {
+        let options = CompileOptions { source_map: true, ..Default::default() };
+        let result = compile(source, options).unwrap();
+        let map = result.source_map.unwrap();
+        let (line, text) = result.code.lines().enumerate().find(|(_, l)| l.contains(needle))?;
+        let col = text[..text.find(needle)?].chars().count();
+        map.lookup(line, col).map(|(l, c, _)| (l, c))
+    }
+
+    #[test]
+    #[cfg(feature = "sourcemap")]
+    fn test_expression_mappings() {
+        let source = "chist è x = 42\nstamm a dì(1 + x)";
+        assert_eq!(original_position(source, "42"), Some((0, 12)));
+        assert_eq!(original_position(source, "console"), Some((1, 0)));
+        assert_eq!(original_position(source, "x)"), Some((1, 15)));
+    }
+
+    #[test]
+    #[cfg(feature = "sourcemap")]
+    fn test_nested_statement_mappings() {
+        let source = "facc f() {\n    iett nu bell Error(\"boh\")\n}";
+        assert_eq!(original_position(source, "throw"), Some((1, 4)));
+        assert_eq!(original_position(source, "new"), Some((1, 9)));
+        assert_eq!(original_position(source, "Error"), Some((1, 17)));
+    }
+
+    #[test]
+    fn test_commonjs_modules() {
+        let source = "chiamm { leggi } da \"fs\"\nmann for facc saluta() {}\nmann for predefinit 42";
+        let options = CompileOptions::builder().module_format(ModuleFormat::CommonJs).build();
+        let code = compile(source, options).unwrap().code;
+        assert!(code.contains("const { leggi } = require(\"fs\");"));
+        assert!(code.contains("function saluta() {\n}\nexports.saluta = saluta;"));
+        assert!(code.contains("exports.default = 42;"));
+        assert!(!code.contains("export "));
+    }
+
+    #[test]
+    fn test_browser_script() {
+        let source = "// 'a pizza\nmann for facc pizza() {}\nstamm a dì(pizza())";
+        let options = CompileOptions::builder().module_format(ModuleFormat::Script).use_strict(true).comments(true).build();
+        let code = compile(source, options).unwrap().code;
+        assert_eq!(code, "(function (exports) {\n  \"use strict\";\n  // 'a pizza\n  function pizza() {\n  }\n  exports.pizza = pizza;\n  console.log(pizza());\n})(globalThis.Fratm = globalThis.Fratm || {});\n");
+        let error = compile("chiamm { join } da \"path\"", CompileOptions::builder().module_format(ModuleFormat::Script).build()).unwrap_err();
+        assert_eq!(error.code, "node-builtin");
+    }
+
+    #[test]
+    fn test_trace() {
+        let source = "facc f(x) {\n  si (x) { piglie (y) => { piglie y } }\n}\nna famiglie P {\n  // nun traccià\n  facc g() {}\n}";
+        let code = compile(source, CompileOptions::builder().trace(Trace::All).build()).unwrap().code;
+        assert!(code.starts_with("function __fratm_exit(name, value) {\n  console.log((\"← \" + name), value);\n  return value;\n}\n"));
+        // Arrow functions return on their own; falling off the end logs too
+        assert!(code.contains("console.log(\"→ f\", x);\n  if (x) {\n    return __fratm_exit(\"f\", (y) => {\n      return y;\n    });\n  }\n  __fratm_exit(\"f\");\n}"));
+        assert!(code.contains("g() {\n  }"));
+        let hooked = compile("facc f(x) {}", CompileOptions::builder().trace(Trace::All).trace_hook("spia").build()).unwrap().code;
+        assert!(hooked.contains("spia(\"exit\", name, value);") && hooked.contains("spia(\"enter\", \"f\", [x]);"));
+        // Off ignores the comments, and leaves no helper
+        assert!(!compile("// traccia\nfacc f(x) {}", Default::default()).unwrap().code.contains("__fratm_exit"));
+    }
+
+    #[test]
+    fn test_annotate() {
+        let source = "tien x = 1\n\nfacc f(a) {\n  chist è ob = {\n    k: a\n  }\n}";
+        let code = compile(source, CompileOptions::builder().annotate(true).build()).unwrap().code;
+        assert_eq!(code, "// tien x = 1\nlet x = 1;\n// facc f(a) {\nfunction f(a) {\n  // chist è ob = {\n  // k: a\n  // }\n  const ob = { k: a };\n}\n");
+        // Without the source there is nothing to annotate with
+        let program = crate::parse_source(source).unwrap();
+        assert!(!crate::generate(&program, &CompileOptions::builder().annotate(true).build()).code.contains("//"));
+    }
+
+    #[test]
+    fn test_module_aliases() {
+        let options = CompileOptions::builder().module_alias("pizzeria", "@acme/pizzeria").module_format(ModuleFormat::CommonJs).build();
+        let code = compile("chiamm { forno } da \"pizzeria/forno\"\nchiamm { express } da \"express\"", options).unwrap().code;
+        assert!(code.contains("const { forno } = require(\"@acme/pizzeria/forno\");"));
+        assert!(code.contains("const { express } = require(\"express\");"));
+    }
+
+    #[test]
+    fn test_conditional_compilation() {
+        let source = "#si DEBUG {\n    chist è log = (x) => stamm a dì(x)\n} sinnò {\n    chist è log = (x) => boh\n}\nfacc f() {\n    #si MODE == \"prova\" { log(1) }\n}";
+        let code = compile(source, Default::default()).unwrap().code;
+        assert_eq!(code, "const log = (x) => undefined;\nfunction f() {\n}\n");
+        let options = CompileOptions::builder().define("DEBUG", "1").define("MODE", "prova").build();
+        let code = compile(source, options).unwrap().code;
+        assert_eq!(code, "const log = (x) => console.log(x);\nfunction f() {\n  log(1);\n}\n");
+        // Off is off, whatever the spelling
+        let options = CompileOptions::builder().define("DEBUG", "sfòls").build();
+        assert!(compile(source, options).unwrap().code.contains("undefined"));
+    }
+
+    #[test]
+    fn test_optimize_folds_concatenation() {
+        let optimize = |source: &str| compile(source, CompileOptions::builder().optimize(true).build()).unwrap().code;
+        assert_eq!(optimize("chist è a = \"Uè\" + \", \" + \"guagliò\" + 1"), "const a = \"Uè, guagliò1\";\n");
+        assert_eq!(optimize("chist è a = \"Uè \" + nome + \"!\""), "const a = `Uè ${nome}!`;\n");
+        // `1 + 2` adds before the string joins in
+        assert_eq!(optimize("chist è a = 1 + x + \"`${}\" + (y + 1)"), "const a = `${(1 + x)}\\`\\${}${(y + 1)}`;\n");
+        assert_eq!(optimize("chist è a = x + 1"), "const a = (x + 1);\n");
+        assert_eq!(compile("chist è a = \"a\" + \"b\"", Default::default()).unwrap().code, "const a = (\"a\" + \"b\");\n");
+    }
+
+    #[test]
+    fn test_es2015_lowers_exponent() {
+        let options = CompileOptions::builder().target(Target::Es2015).build();
+        let code = compile("chist è x = 2 ** 3", options).unwrap().code;
+        assert!(code.contains("Math.pow(2, 3)"));
+        assert!(compile("chist è x = 2 ** 3", Default::default()).unwrap().code.contains("(2 ** 3)"));
+    }
+
+    #[test]
+    fn test_else_if_in_block() {
+        let code = compile("facc f(x) {\n  si (x) { piglie 1 } sinnò si (manco x) { piglie 2 }\n}", Default::default()).unwrap().code;
+        assert_eq!(code, "function f(x) {\n  if (x) {\n    return 1;\n  } else if (!x) {\n    return 2;\n  }\n}\n");
+    }
+
+    #[test]
+    fn test_style() {
+        let style = CodegenStyle { use_tabs: true, quote: Quote::Single, semicolons: false, ..Default::default() };
+        let options = CompileOptions::builder().style(style).build();
+        let code = compile("facc f(a) {\n  piglie \"l'ammore\" + a\n}\n(1 + 2) * 3\n-f(1)", options).unwrap().code;
+        assert_eq!(code, "function f(a) {\n\treturn (\"l'ammore\" + a)\n}\n;((1 + 2) * 3)\n;-f(1)\n");
+
+        let style = CodegenStyle { indent_width: 4, ..Default::default() };
+        let code = compile("si (overo) { stamm a dì(1) }", CompileOptions::builder().style(style).build()).unwrap().code;
+        assert!(code.contains("\n    console.log(1);\n"));
+    }
+
+    #[test]
+    #[cfg(feature = "sourcemap")]
+    fn test_banner_footer_and_use_strict() {
+        let options = CompileOptions::builder()
+            .banner("Copyright 2024\n\nMIT")
+            .footer("/* fine */")
+            .use_strict(true)
+            .module_format(ModuleFormat::CommonJs)
+            .source_map(true)
+            .build();
+        let result = compile("tien x = 1", options).unwrap();
+        assert_eq!(result.code, "// Copyright 2024\n//\n// MIT\n\"use strict\";\nlet x = 1;\n/* fine */\n");
+        // Mappings start after the banner
+        assert_eq!(result.source_map.unwrap().lookup(4, 0).map(|(l, c, _)| (l, c)), Some((0, 0)));
+
+        // ES modules are strict already
+        let code = compile("tien x = 1", CompileOptions::builder().use_strict(true).build()).unwrap().code;
+        assert_eq!(code, "let x = 1;\n");
+    }
+
+    #[test]
+    fn test_comments_passthrough() {
+        let source = "// Copyright Gennaro\n\nfacc f() { // saluta\n    stamm a dì(1) // uno\n    /* fine */\n}\n\nna famiglie C {\n    // costruttore\n    facc costruttore() {}\n}\n// fine file";
+        let code = compile(source, CompileOptions::builder().comments(true).build()).unwrap().code;
+        assert_eq!(code, "// Copyright Gennaro\nfunction f() {\n  // saluta\n  console.log(1); // uno\n  /* fine */\n}\nclass C {\n  // costruttore\n  constructor() {\n  }\n}\n// fine file\n");
+        assert!(!compile(source, Default::default()).unwrap().code.contains("//"));
+    }
+
+    #[test]
+    #[cfg(feature = "sourcemap")]
+    fn test_minify_keeps_original_names() {
+        let source = "facc doppio(numero) {\n  piglie numero * 2\n}";
+        let result = compile(source, CompileOptions::builder().minify(true).source_map(true).build()).unwrap();
+        assert!(result.code.contains("function doppio(a) {\n  return (a * 2);"), "{}", result.code);
+
+        // `a` on line 2 maps back to `numero`, so a debugger can show it
+        let map = result.source_map.unwrap();
+        assert_eq!(map.names, vec!["numero"]);
+        let mapping = map.find_mapping(1, 10).unwrap();
+        assert_eq!((mapping.src_line, mapping.src_col, mapping.name), (1, 9, Some(0)));
+    }
+
+    #[test]
+    fn test_reserved_words_are_escaped() {
+        let source = "facc delete(class) {\n  piglie class.new\n}\nmann for chist è new = { class: delete(1) }";
+        let result = compile(source, Default::default()).unwrap();
+        assert!(result.code.contains("function $delete($class) {\n  return $class.new;"), "{}", result.code);
+        assert!(result.code.contains("const $new = { class: $delete(1) };\nexport { $new as new };"), "{}", result.code);
+        assert_eq!(result.warnings.len(), 3);
+        let english = compile(source, CompileOptions::builder().locale(crate::errors::Locale::English).build()).unwrap();
+        assert!(english.warnings[0].ends_with("'delete' is a JavaScript reserved word: in the JavaScript it becomes '$delete'"), "{:?}", english.warnings);
+
+        let options = CompileOptions::builder().module_format(ModuleFormat::CommonJs).build();
+        assert!(compile(source, options).unwrap().code.contains("exports.new = $new;"));
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let code = compile("stamm a dì(\"say \\\"hi\\\"\", 'it\\'s', \"\u{2028}\\t\u{7}1\")", Default::default()).unwrap().code;
+        assert_eq!(code, "console.log('say \"hi\"', \"it's\", \"\\u2028\\t\\x071\");\n");
+    }
+
+    #[test]
+    fn test_assertions() {
+        let source = "giura che (x > 0)\ngiura che (y) sinnò \"y manca\"\nstamm a dì(x)";
+        let code = compile(source, Default::default()).unwrap().code;
+        assert!(code.starts_with("if (!((x > 0))) throw new Error(\"giura che: 'a condizione nun è overa (riga 1)\");\n"), "{}", code);
+        assert!(code.contains("if (!(y)) throw new Error(\"y manca\");\n"), "{}", code);
+
+        let code = compile(source, CompileOptions::builder().assertions(Assertions::Strip).build()).unwrap().code;
+        assert_eq!(code, "console.log(x);\n");
+    }
+
+    #[test]
+    fn test_streams_to_writer() {
+        let program = crate::parse_source("tien x = 1\nstamm a dì(x)").unwrap();
+        let mut out = Vec::new();
+        crate::generate_to(&program, Vec::new(), &Default::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), crate::generate(&program, &Default::default()).code);
+
+        // A full disk shows up as the I/O error, not a panic or a silent truncation
+        let full: &mut [u8] = &mut [0; 8];
+        let error = crate::generate_to(&program, Vec::new(), &Default::default(), full).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::WriteZero);
+    }
+
+    /// Parses the generated `const x = ...;` with a real JavaScript parser
+    /// and returns the value of the string literal
+    fn parse_js_string(js: &str) -> String {
+        use oxc_ast::ast::{Expression as JsExpression, Statement as JsStatement};
+        let allocator = oxc_allocator::Allocator::default();
+        let parsed = oxc_parser::Parser::new(&allocator, js, oxc_span::SourceType::mjs()).parse();
+        assert!(parsed.errors.is_empty(), "{:?} in {}", parsed.errors, js);
+        let JsStatement::VariableDeclaration(decl) = &parsed.program.body[0] else { panic!("{}", js) };
+        let Some(JsExpression::StringLiteral(literal)) = &decl.declarations[0].init else { panic!("{}", js) };
+        literal.value.to_string()
+    }
+
+    fn string_round_trip(value: &str, quote: Quote) -> String {
+        let span = Span::default();
+        let program = Program { statements: vec![Statement::VariableDecl {
+            name: "x".into(),
+            annotation: None,
+            value: Some(Expression::String { value: value.to_string(), span }),
+            is_const: true,
+            span,
+        }] };
+        let style = CodegenStyle { quote, ..Default::default() };
+        parse_js_string(&CodeGen::new(false).with_style(style).generate(&program))
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_strings_round_trip(value in "(\\PC|[\"'\\\\\n\r\t\u{0}-\u{1f}\u{7f}-\u{9f}\u{2028}\u{2029}0-9])*") {
+            proptest::prop_assert_eq!(string_round_trip(&value, Quote::Double), value.clone());
+            proptest::prop_assert_eq!(string_round_trip(&value, Quote::Single), value);
+        }
+
+        #[test]
+        fn test_any_string_round_trips(value in proptest::prelude::any::<String>()) {
+            proptest::prop_assert_eq!(string_round_trip(&value, Quote::Double), value);
+        }
+    }
+}
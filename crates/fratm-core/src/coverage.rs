@@ -0,0 +1,227 @@
+//! Code coverage
+//!
+//! With [`CompileOptions::coverage`](crate::CompileOptions::coverage) the
+//! compiler puts a counter before every statement, `__fratm_cov.s[3]++`,
+//! and at the start of both branches of every `si`, `__fratm_cov.b[0]++`
+//! (an `if` without `else` gets one). A collector at the top of the file
+//! keeps the counts in `globalThis.__fratm_coverage`, by file, and under
+//! Node.js writes them to `fratm-coverage.json` (or `$FRATM_COVERAGE_FILE`)
+//! on exit.
+//!
+//! The counters carry the position of their statement in the source map,
+//! so [`map_counts`] turns the counts of a file back into `.fratm` lines,
+//! for [`lcov`] or a summary:
+//!
+//! ```rust
+//! use fratm_core::coverage::{map_counts, Counts};
+//! use fratm_core::{compile, CompileOptions};
+//!
+//! let source = "tien x = 1\nsi (x > 1) {\n  stamm a dì(x)\n}";
+//! let options = CompileOptions::builder().coverage(true).source_map(true).build();
+//! let result = compile(source, options).unwrap();
+//! assert!(result.code.contains("__fratm_cov.s[0]++;\nlet x = 1;"));
+//!
+//! // What running it gives: the branch without else is taken
+//! # #[cfg(feature = "sourcemap")] {
+//! let counts = Counts { s: vec![1, 1, 0], b: vec![0, 1] };
+//! let file = map_counts(&result.code, result.source_map.as_ref().unwrap(), &counts);
+//! assert_eq!(file.lines.into_iter().collect::<Vec<_>>(), [(1, 1), (2, 1), (3, 0)]);
+//! assert_eq!(file.branches.iter().map(|b| (b.line, b.count)).collect::<Vec<_>>(), [(2, 0), (2, 1)]);
+//! # }
+//! ```
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::sourcemap::SourceMap;
+
+/// Name of the counters object in the generated code
+pub const COUNTERS: &str = "__fratm_cov";
+
+/// Counts a covered program dumps for one file, by counter: `s` for
+/// statements, `b` for branches.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Counts {
+    pub s: Vec<u64>,
+    pub b: Vec<u64>,
+}
+
+/// Coverage of one `.fratm` file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileCoverage {
+    /// The file, as the source map names it
+    pub source: String,
+    /// Times the statements of a line ran, by line (1-based). A line with
+    /// several statements counts its most run one.
+    pub lines: BTreeMap<usize, u64>,
+    /// The branches of every `si`, in order
+    pub branches: Vec<BranchCount>,
+}
+
+/// Times one branch of an `si` was taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BranchCount {
+    /// Line of the `si` (1-based)
+    pub line: usize,
+    pub count: u64,
+}
+
+impl FileCoverage {
+    /// Lines that ran at least once, and lines with a statement
+    pub fn lines_hit(&self) -> (usize, usize) {
+        (self.lines.values().filter(|&&count| count > 0).count(), self.lines.len())
+    }
+
+    /// Branches taken at least once, and branches
+    pub fn branches_hit(&self) -> (usize, usize) {
+        (self.branches.iter().filter(|branch| branch.count > 0).count(), self.branches.len())
+    }
+}
+
+/// The counters the compiler has handed out so far
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    statements: Cell<usize>,
+    branches: Cell<usize>,
+}
+
+impl Counters {
+    /// `__fratm_cov.s[n]++` for the next statement counter
+    pub fn statement(&self) -> String {
+        format!("{}.s[{}]++", COUNTERS, next(&self.statements))
+    }
+
+    /// `__fratm_cov.b[n]++` for the next branch counter
+    pub fn branch(&self) -> String {
+        format!("{}.b[{}]++", COUNTERS, next(&self.branches))
+    }
+
+    /// Statement and branch counters handed out
+    pub fn len(&self) -> (usize, usize) {
+        (self.statements.get(), self.branches.get())
+    }
+}
+
+fn next(counter: &Cell<usize>) -> usize {
+    let n = counter.get();
+    counter.set(n + 1);
+    n
+}
+
+/// Maps the `counts` of the compiled `code` to the lines of its source,
+/// through the source map of `code`. Counters the map has no position for
+/// are left out.
+pub fn map_counts(code: &str, map: &SourceMap, counts: &Counts) -> FileCoverage {
+    let mappings = map.decode_mappings().unwrap_or_default();
+    let source_line = |line: usize, column: usize| {
+        mappings.iter().filter(|m| m.gen_line == line && m.gen_col <= column).max_by_key(|m| m.gen_col).map(|m| m.src_line + 1)
+    };
+    let mut file = FileCoverage { source: map.sources.first().cloned().unwrap_or_default(), ..Default::default() };
+    for (line, text) in code.lines().enumerate() {
+        let mut rest = text;
+        while let Some(at) = rest.find(COUNTERS) {
+            let after = &rest[at + COUNTERS.len()..];
+            // Columns in source maps count characters
+            let column = text[..text.len() - rest.len() + at].chars().count();
+            rest = after;
+            let Some((kind, index)) = counter(after) else { continue };
+            let Some(src_line) = source_line(line, column) else { continue };
+            match kind {
+                's' => {
+                    let count = counts.s.get(index).copied().unwrap_or(0);
+                    let hits = file.lines.entry(src_line).or_insert(0);
+                    *hits = (*hits).max(count);
+                }
+                _ => file.branches.push(BranchCount { line: src_line, count: counts.b.get(index).copied().unwrap_or(0) }),
+            }
+        }
+    }
+    file
+}
+
+/// `('s', 3)` for the `.s[3]++` after a [`COUNTERS`]
+fn counter(text: &str) -> Option<(char, usize)> {
+    let kind = text.strip_prefix('.')?.chars().next().filter(|kind| matches!(kind, 's' | 'b'))?;
+    let digits = text[2..].strip_prefix('[')?;
+    let end = digits.find("]++")?;
+    Some((kind, digits[..end].parse().ok()?))
+}
+
+/// `files` in the lcov tracefile format, which coverage services and
+/// editors read.
+///
+/// ```rust
+/// use fratm_core::coverage::{lcov, BranchCount, FileCoverage};
+///
+/// let file = FileCoverage {
+///     source: "pizza.fratm".to_string(),
+///     lines: [(1, 2), (2, 0)].into(),
+///     branches: vec![BranchCount { line: 1, count: 2 }, BranchCount { line: 1, count: 0 }],
+/// };
+/// assert_eq!(lcov(&[file]), "TN:\nSF:pizza.fratm\nDA:1,2\nDA:2,0\nLF:2\nLH:1\nBRDA:1,0,0,2\nBRDA:1,0,1,-\nBRF:2\nBRH:1\nend_of_record\n");
+/// ```
+pub fn lcov(files: &[FileCoverage]) -> String {
+    let mut out = String::new();
+    for file in files {
+        let _ = writeln!(out, "TN:\nSF:{}", file.source);
+        for (line, count) in &file.lines {
+            let _ = writeln!(out, "DA:{},{}", line, count);
+        }
+        let (hit, found) = file.lines_hit();
+        let _ = writeln!(out, "LF:{}\nLH:{}", found, hit);
+        // Branches are numbered within their block, the line of their `si`
+        let mut block = (0, 0);
+        for branch in &file.branches {
+            block = if block.0 == branch.line { (block.0, block.1 + 1) } else { (branch.line, 0) };
+            let taken = if branch.count > 0 { branch.count.to_string() } else { "-".to_string() };
+            let _ = writeln!(out, "BRDA:{},0,{},{}", branch.line, block.1, taken);
+        }
+        let (hit, found) = file.branches_hit();
+        let _ = writeln!(out, "BRF:{}\nBRH:{}\nend_of_record", found, hit);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compile, CompileOptions, ModuleFormat};
+
+    fn covered(source: &str, format: ModuleFormat) -> String {
+        compile(source, CompileOptions::builder().coverage(true).module_format(format).build()).unwrap().code
+    }
+
+    #[test]
+    fn test_counters() {
+        let code = covered("facc f(x) {\n  si (x) { piglie 1 } sinnò si (x == 0) { piglie 2 }\n}\nf(1)", ModuleFormat::Esm);
+        // Declarations always run, so only the call and what f does count
+        assert!(code.contains("function f(x) {\n  __fratm_cov.s[0]++;\n  if (x) {\n    __fratm_cov.b[0]++;\n    __fratm_cov.s[1]++;\n    return 1;"));
+        assert!(code.contains("} else {\n    __fratm_cov.b[1]++;\n    if ((x == 0)) {\n      __fratm_cov.b[2]++;\n      __fratm_cov.s[2]++;"));
+        assert!(code.contains("} else {\n      __fratm_cov.b[3]++;\n    }"));
+        assert!(code.contains("__fratm_cov.s[3]++;\nf(1);"));
+        assert!(code.contains("{ s: new Array(4).fill(0), b: new Array(4).fill(0) }"));
+    }
+
+    #[test]
+    fn test_collector() {
+        let esm = covered("stamm a dì(1)", ModuleFormat::Esm);
+        assert!(esm.starts_with("import { writeFileSync as __fratm_writeFileSync } from \"node:fs\";\n"));
+        assert!(esm.contains("globalThis.__fratm_coverage[import.meta.url] = __fratm_cov;"));
+        assert!(covered("stamm a dì(1)", ModuleFormat::CommonJs).contains("require(\"fs\").writeFileSync((process.env.FRATM_COVERAGE_FILE"));
+        assert!(!covered("stamm a dì(1)", ModuleFormat::Script).contains("process.on"));
+        assert!(compile("stamm a dì(1)", CompileOptions::builder().coverage(true).build()).unwrap().is_module);
+    }
+
+    #[test]
+    fn test_counter() {
+        assert_eq!(counter(".s[12]++;"), Some(('s', 12)));
+        assert_eq!(counter(".b[0]++"), Some(('b', 0)));
+        assert_eq!([counter(" = {"), counter(".s[x]++"), counter(".q[1]++")], [None; 3]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Structural diff of two programs
+//!
+//! [`diff`] compares two parsed programs statement by statement, so
+//! whitespace, comments and formatting never show up as changes:
+//!
+//! - declarations (`chist è`, `tien`, `facc`, `na famiglie`, exported or
+//!   not) are matched by name, wherever they are in the file, and reported
+//!   as added, removed or changed; the methods of a changed class are
+//!   compared the same way
+//! - other top-level statements are matched in order, and reported as
+//!   added or removed
+//!
+//! ```rust
+//! use fratm_core::diff::{diff, ChangeKind};
+//!
+//! let old = fratm_core::parse_source("tien x = 1\nfacc f() { piglie x }").unwrap();
+//! let new = fratm_core::parse_source("facc f() {\n  piglie x + 1\n}\n\ntien   x = 1 // same").unwrap();
+//! let changes = diff(&old, &new);
+//! assert_eq!(changes.len(), 1);
+//! assert_eq!((changes[0].kind, changes[0].label.as_str()), (ChangeKind::Changed, "facc f"));
+//! ```
+
+use serde::Serialize;
+
+use crate::lexer::Span;
+use crate::parser::{Name, Program, Statement};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One difference between two programs.
+#[derive(Debug, Clone, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    /// What changed: `facc saluta`, `na famiglie Pizza: facc cuoci`, or
+    /// `statement` for statements that don't declare anything
+    pub label: String,
+    /// The statement in the old program, unless it was added
+    pub old: Option<Span>,
+    /// The statement in the new program, unless it was removed
+    pub new: Option<Span>,
+}
+
+/// The differences between `old` and `new`, in the order they appear in
+/// `new` (removals where they were in `old`).
+pub fn diff(old: &Program, new: &Program) -> Vec<Change> {
+    let mut changes = Vec::new();
+    compare(&old.statements, &new.statements, "", &mut changes);
+    changes.sort_by_key(|change| change.new.or(change.old).map(|span| span.start));
+    changes
+}
+
+fn compare(old: &[Statement], new: &[Statement], prefix: &str, changes: &mut Vec<Change>) {
+    let change = |kind, label: String, old: Option<&Statement>, new: Option<&Statement>| Change {
+        kind,
+        label: format!("{}{}", prefix, label),
+        old: old.map(Statement::span),
+        new: new.map(Statement::span),
+    };
+
+    // Declarations, by name
+    let old_decls: Vec<_> = old.iter().filter_map(|stmt| declaration(stmt).map(|decl| (decl, stmt))).collect();
+    let new_decls: Vec<_> = new.iter().filter_map(|stmt| declaration(stmt).map(|decl| (decl, stmt))).collect();
+    let mut matched = vec![false; old_decls.len()];
+    for (decl, new_stmt) in &new_decls {
+        let found = old_decls.iter().enumerate().position(|(i, (old_decl, _))| !matched[i] && old_decl == decl);
+        let Some(i) = found else {
+            changes.push(change(ChangeKind::Added, decl.label(), None, Some(new_stmt)));
+            continue;
+        };
+        matched[i] = true;
+        let old_stmt = old_decls[i].1;
+        if old_stmt.to_sexp() == new_stmt.to_sexp() {
+            continue;
+        }
+        match (unwrap_export(old_stmt), unwrap_export(new_stmt)) {
+            (Statement::ClassDecl { methods: old_methods, .. }, Statement::ClassDecl { methods: new_methods, .. })
+                if matches!(old_stmt, Statement::Export { .. }) == matches!(new_stmt, Statement::Export { .. }) =>
+            {
+                compare(old_methods, new_methods, &format!("{}: ", decl.label()), changes);
+            }
+            _ => changes.push(change(ChangeKind::Changed, decl.label(), Some(old_stmt), Some(new_stmt))),
+        }
+    }
+    for (i, (decl, old_stmt)) in old_decls.iter().enumerate() {
+        if !matched[i] {
+            changes.push(change(ChangeKind::Removed, decl.label(), Some(old_stmt), None));
+        }
+    }
+
+    // Everything else, in order
+    let old_rest: Vec<&Statement> = old.iter().filter(|stmt| declaration(stmt).is_none()).collect();
+    let new_rest: Vec<&Statement> = new.iter().filter(|stmt| declaration(stmt).is_none()).collect();
+    let old_keys: Vec<String> = old_rest.iter().map(|stmt| stmt.to_sexp()).collect();
+    let new_keys: Vec<String> = new_rest.iter().map(|stmt| stmt.to_sexp()).collect();
+    for edit in edits(&old_keys, &new_keys) {
+        changes.push(match edit {
+            Edit::Removed(i) => change(ChangeKind::Removed, "statement".into(), Some(old_rest[i]), None),
+            Edit::Added(j) => change(ChangeKind::Added, "statement".into(), None, Some(new_rest[j])),
+        });
+    }
+}
+
+/// What a statement declares, to match it across the two programs
+#[derive(Debug, PartialEq)]
+struct Declared<'a> {
+    keyword: &'static str,
+    name: &'a Name,
+}
+
+impl Declared<'_> {
+    fn label(&self) -> String {
+        format!("{} {}", self.keyword, self.name)
+    }
+}
+
+fn declaration(stmt: &Statement) -> Option<Declared<'_>> {
+    let (keyword, name) = match unwrap_export(stmt) {
+        Statement::VariableDecl { name, is_const: true, .. } => ("chist è", name),
+        Statement::VariableDecl { name, .. } => ("tien", name),
+        Statement::FunctionDecl { name, .. } => ("facc", name),
+        Statement::ClassDecl { name, .. } => ("na famiglie", name),
+        _ => return None,
+    };
+    Some(Declared { keyword, name })
+}
+
+/// The declaration of `mann for <declaration>`, or `stmt` itself
+fn unwrap_export(stmt: &Statement) -> &Statement {
+    match stmt {
+        Statement::Export { declaration: Some(declaration), .. } => declaration,
+        stmt => stmt,
+    }
+}
+
+enum Edit {
+    Removed(usize),
+    Added(usize),
+}
+
+/// The removals and additions turning `old` into `new`, from their
+/// longest common subsequence.
+fn edits(old: &[String], new: &[String]) -> Vec<Edit> {
+    // lengths[i][j]: LCS length of old[i..] and new[j..]
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] { lengths[i + 1][j + 1] + 1 } else { lengths[i + 1][j].max(lengths[i][j + 1]) };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut edits = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lengths[i][j + 1] >= lengths[i + 1][j]) {
+            edits.push(Edit::Added(j));
+            j += 1;
+        } else {
+            edits.push(Edit::Removed(i));
+            i += 1;
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(old: &str, new: &str) -> Vec<(ChangeKind, String)> {
+        let old = crate::parse_source(old).unwrap();
+        let new = crate::parse_source(new).unwrap();
+        diff(&old, &new).into_iter().map(|change| (change.kind, change.label)).collect()
+    }
+
+    #[test]
+    fn test_declarations() {
+        let old = "chist è a = 1\ntien b = 2\nfacc f() {}";
+        let new = "mann for facc f() {}\ntien b = 3\nfacc g() {}";
+        assert_eq!(changes(old, new), [
+            // Now exported
+            (ChangeKind::Changed, "facc f".to_string()),
+            (ChangeKind::Removed, "chist è a".to_string()),
+            (ChangeKind::Changed, "tien b".to_string()),
+            (ChangeKind::Added, "facc g".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_class_methods_and_statements() {
+        let old = "na famiglie P {\n  facc a() {}\n  facc b() {}\n}\nstamm a dì(1)\nstamm a dì(2)";
+        let new = "na famiglie P {\n  facc a() { piglie 1 }\n}\nstamm a dì(2)\nstamm a dì(3)";
+        assert_eq!(changes(old, new), [
+            (ChangeKind::Changed, "na famiglie P: facc a".to_string()),
+            (ChangeKind::Removed, "na famiglie P: facc b".to_string()),
+            (ChangeKind::Removed, "statement".to_string()),
+            (ChangeKind::Added, "statement".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,189 @@
+//! Compile-time environment variables
+//!
+//! `amb("API_URL")` is replaced by the value of `API_URL` in
+//! [`CompileOptions::env`](crate::CompileOptions::env) when compiling, so
+//! one source builds for different servers without a bundler. [`check`]
+//! reports the variables used but not given; only the branches of `#si`
+//! that get compiled count.
+//!
+//! ```rust
+//! use fratm_core::{compile, CompileOptions};
+//!
+//! let options = CompileOptions::builder().env("API_URL", "https://pizza.example").build();
+//! let result = compile("chist è url = amb(\"API_URL\")", options).unwrap();
+//! assert_eq!(result.code, "const url = \"https://pizza.example\";\n");
+//!
+//! let error = compile("chist è url = amb(\"API_URL\")", Default::default()).unwrap_err();
+//! assert_eq!(error.code, "undefined-env");
+//! ```
+//!
+//! The values usually come from a `.env` file, read with [`parse_env_file`].
+
+use std::collections::BTreeMap;
+
+use crate::errors::Diagnostic;
+use crate::lexer::Span;
+use crate::linter::for_each_child_expression;
+use crate::parser::{ArrowBody, Expression, Program, Statement};
+
+/// The variable `expr` reads if it is `amb("NAME")`, and the span of the
+/// name. Any other call of `amb` is left alone.
+pub fn variable(expr: &Expression) -> Option<(&str, Span)> {
+    match expr {
+        Expression::Call { callee, arguments, .. } => match (callee.as_ref(), arguments.as_slice()) {
+            (Expression::Identifier { name, .. }, [Expression::String { value, span }]) if &**name == "amb" => Some((value, *span)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Checks that every `amb("NAME")` of `program` compiled with `defines`
+/// has a value in `env`.
+pub fn check(program: &Program, env: &BTreeMap<String, String>, defines: &BTreeMap<String, String>) -> Vec<Diagnostic> {
+    let mut checker = Checker { env, defines, errors: Vec::new() };
+    checker.statements(&program.statements);
+    checker.errors
+}
+
+/// Reads a `.env` file: `NAME=value` lines, with `#` comments, optional
+/// `export` and quotes. Fails with the line that isn't an assignment.
+///
+/// ```rust
+/// use fratm_core::env::parse_env_file;
+///
+/// let env = parse_env_file("# server\nexport API_URL=\"https://pizza.example\"\nTAVOLI = 12\n").unwrap();
+/// assert_eq!(env["API_URL"], "https://pizza.example");
+/// assert_eq!(env["TAVOLI"], "12");
+/// assert_eq!(parse_env_file("API_URL\n").unwrap_err(), "line 1: expected NAME=value");
+/// ```
+pub fn parse_env_file(text: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut env = BTreeMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((name, value)) = line.split_once('=').filter(|(name, _)| !name.trim().is_empty()) else {
+            return Err(format!("line {}: expected NAME=value", i + 1));
+        };
+        let value = value.trim();
+        let value = match value.as_bytes() {
+            [b'"', .., b'"'] => value[1..value.len() - 1].replace("\\n", "\n").replace("\\\"", "\""),
+            [b'\'', .., b'\''] => value[1..value.len() - 1].to_string(),
+            _ => value.to_string(),
+        };
+        env.insert(name.trim().to_string(), value);
+    }
+    Ok(env)
+}
+
+struct Checker<'a> {
+    env: &'a BTreeMap<String, String>,
+    defines: &'a BTreeMap<String, String>,
+    errors: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn statements(&mut self, statements: &[Statement]) {
+        for stmt in statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::VariableDecl { value, .. } | Statement::Return { value, .. } => value.iter().for_each(|value| self.expression(value)),
+            Statement::FunctionDecl { decorators, body, .. } => {
+                decorators.iter().for_each(|decorator| self.expression(decorator));
+                self.statements(body);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                self.expression(condition);
+                self.statements(then_branch);
+                if let Some(else_branch) = else_branch { self.statements(else_branch); }
+            }
+            Statement::While { condition, body, .. } => {
+                self.expression(condition);
+                self.statements(body);
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                if let Some(init) = init { self.statement(init); }
+                condition.iter().chain(update).for_each(|expr| self.expression(expr));
+                self.statements(body);
+            }
+            Statement::Assert { condition, message, .. } => {
+                self.expression(condition);
+                if let Some(message) = message { self.expression(message); }
+            }
+            Statement::TryCatch { try_body, catch_type, catch_body, .. } => {
+                self.statements(try_body);
+                if let Some(catch_type) = catch_type { self.expression(catch_type); }
+                self.statements(catch_body);
+            }
+            Statement::Throw { value, .. } | Statement::Expression { expression: value, .. } => self.expression(value),
+            Statement::ClassDecl { decorators, superclass, methods, .. } => {
+                decorators.iter().chain(superclass).for_each(|expr| self.expression(expr));
+                self.statements(methods);
+            }
+            Statement::Export { declaration, default_value, .. } => {
+                if let Some(declaration) = declaration { self.statement(declaration); }
+                if let Some(value) = default_value { self.expression(value); }
+            }
+            Statement::Block { statements, .. } => self.statements(statements),
+            Statement::Conditional { condition, then_branch, else_branch, .. } => {
+                let branch = if condition.holds(self.defines) { Some(then_branch) } else { else_branch.as_ref() };
+                if let Some(branch) = branch { self.statements(branch); }
+            }
+            Statement::Break { .. } | Statement::Continue { .. } | Statement::Debugger { .. } | Statement::Import { .. } | Statement::Error { .. } => {}
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        if let Some((name, span)) = variable(expr) {
+            if !self.env.contains_key(name) {
+                self.errors.push(
+                    Diagnostic::error("undefined-env", format!("'A variabile d'ambiente \"{}\" nun tene valore", name), span)
+                        .with_suggestion(format!("💡 Dalle nu valore dint'o file .env: {}=...", name)),
+                );
+            }
+            return;
+        }
+        match expr {
+            Expression::ArrowFunction { body: ArrowBody::Block(body), .. } => self.statements(body),
+            expr => for_each_child_expression(expr, |child| self.expression(child)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn missing(source: &str, defines: &[(&str, &str)]) -> Vec<usize> {
+        let program = crate::parse_source(source).unwrap();
+        let env = BTreeMap::from([("PORTA".to_string(), "8080".to_string())]);
+        let defines = defines.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        check(&program, &env, &defines).into_iter().map(|e| e.span.line).collect()
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(missing("chist è p = amb(\"PORTA\")", &[]).is_empty());
+        assert_eq!(missing("facc f() {\n  piglie (x) => { piglie amb(\"HOST\") }\n}", &[]), [2]);
+        // Only the compiled branch of #si counts
+        let source = "#si DEBUG {\n  stamm a dì(amb(\"HOST\"))\n}";
+        assert!(missing(source, &[]).is_empty());
+        assert_eq!(missing(source, &[("DEBUG", "1")]), [2]);
+        // Not a lookup: left for the program's own amb
+        assert!(missing("amb(nome)\namb(\"A\", \"B\")", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_parse_env_file() {
+        let env = parse_env_file("A='x # y'\nB=\"riga\\nnova\"\n\n  # nient\nC=").unwrap();
+        assert_eq!((env["A"].as_str(), env["B"].as_str(), env["C"].as_str()), ("x # y", "riga\nnova", ""));
+        assert_eq!(parse_env_file("A=1\n=2").unwrap_err(), "line 2: expected NAME=value");
+    }
+}
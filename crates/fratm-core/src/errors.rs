@@ -1,105 +1,318 @@
 //! Error types and Neapolitan error messages
 //!
-//! All errors are in Neapolitan dialect! 🤌
+//! All errors are in Neapolitan dialect! 🤌 For everyone else there is an
+//! English catalog, selected with [`Locale::English`].
+
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
 
 use thiserror::Error;
+#[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 
-/// Main compilation error type
-#[derive(Debug, Error, Clone, Serialize, Deserialize)]
-pub enum CompileError {
-    #[error("Riga {line}, colonna {column}: {message}")]
-    LexerError {
-        message: String,
-        line: usize,
-        column: usize,
-    },
-    
-    #[error("Riga {line}, colonna {column}: {message}")]
-    ParseError {
-        message: String,
-        line: usize,
-        column: usize,
-    },
-    
-    #[error("Errore interno: {message}")]
-    CodeGenError {
-        message: String,
-    },
+use crate::fix::Fix;
+use crate::lexer::Span;
+
+/// Language of compiler messages
+///
+/// Neapolitan is the default; tools pick English with `FRATM_LANG=en`
+/// ([`Locale::from_env`]) or an explicit option.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Locale {
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "nap"))]
+    Napoletano,
+    #[cfg_attr(feature = "serde", serde(rename = "en"))]
+    English,
+}
+
+impl Locale {
+    /// Reads `FRATM_LANG`, falling back to Neapolitan when unset or unknown.
+    pub fn from_env() -> Self {
+        std::env::var("FRATM_LANG").ok().and_then(|lang| lang.parse().ok()).unwrap_or_default()
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    /// Accepts `nap`/`en`, full names, and POSIX-style values like `en_US.UTF-8`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lang = s.split(['_', '-', '.']).next().unwrap_or("").to_lowercase();
+        match lang.as_str() {
+            "nap" | "napoletano" | "it" => Ok(Locale::Napoletano),
+            "en" | "english" => Ok(Locale::English),
+            _ => Err(format!("unknown language '{}' (use 'nap' or 'en')", s)),
+        }
+    }
 }
 
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self { Locale::Napoletano => "nap", Locale::English => "en" })
+    }
+}
+
+/// How serious a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    /// The code can't be compiled
+    Error,
+    /// The code compiles but is probably wrong
+    Warning,
+}
+
+/// A problem found in the source, by the lexer, the parser or the linter
+///
+/// Every stage reports the same type, so tools can show all of them the
+/// same way. `code` identifies the kind of problem:
+///
+/// | Code | Reported by |
+/// |------|-------------|
+/// | `invalid-token` | lexer |
+/// | `syntax-error` | parser |
+/// | `unused-variable`, `const-reassign`, ... | linter (see [`crate::linter`]) |
+///
+/// ```rust
+/// use fratm_core::errors::Severity;
+///
+/// let errors = fratm_core::parse_source("chist x = 1").unwrap_err();
+/// assert_eq!(errors[0].code, "syntax-error");
+/// assert_eq!(errors[0].severity, Severity::Error);
+/// assert!(errors[0].message.contains("chist è"));
+/// ```
+#[derive(Debug, Error, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[error("Riga {}, colonna {}: {}", .span.line, .span.column, .message)]
+pub struct Diagnostic {
+    /// Kind of problem, e.g. `syntax-error` or `unused-variable`
+    pub code: Cow<'static, str>,
+    pub severity: Severity,
+    /// Human-readable message (in Neapolitan, of course)
+    pub message: String,
+    /// Where the problem is
+    pub span: Span,
+    /// Other places involved, e.g. the `{` that was never closed
+    pub labels: Vec<Label>,
+    /// Extra context shown below the message
+    pub notes: Vec<String>,
+    /// How to fix it, if we have an idea
+    pub suggestion: Option<String>,
+    /// The edit that fixes it, if one surely does: `fratm check --fix`
+    /// applies it with [`crate::fix::apply`]
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub fix: Option<Fix>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Self { code: Cow::Borrowed(code), severity: Severity::Error, message: message.into(), span, labels: vec![], notes: vec![], suggestion: None, fix: None }
+    }
+
+    pub fn warning(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Self { severity: Severity::Warning, ..Self::error(code, message, span) }
+    }
+
+    /// A lexer error. `message` is the lexer's raw message, translated here.
+    ///
+    /// An unterminated string or comment runs to the end of the file: the
+    /// error points at the opening `"` or `/*`, and a note says where the
+    /// lexer gave up.
+    pub fn lexical(message: &str, span: Span, locale: Locale) -> Self {
+        let suggestion = match locale {
+            _ if !cfg!(feature = "suggestions") => None,
+            Locale::Napoletano if message.contains("string") => Some("💡 'E stringhe s'aprono e se chiudono cu \" o '"),
+            Locale::English if message.contains("string") => Some("💡 Strings open and close with \" or '"),
+            Locale::Napoletano if message.contains("comment") => Some("💡 'E commenti /* se chiudono cu */"),
+            Locale::English if message.contains("comment") => Some("💡 Block comments close with */"),
+            _ => None,
+        };
+        let diagnostic = Self { suggestion: suggestion.map(str::to_string), ..Self::error("invalid-token", localize_error(message, locale), span) };
+        let opening = match message {
+            "Unterminated string" => 1,
+            "Unterminated comment" => 2,
+            _ => return diagnostic,
+        };
+        let label = match locale {
+            Locale::Napoletano => "aggio cercato 'a chiusura fino ccà, 'a fine d'o file",
+            Locale::English => "reached the end of the file here looking for the end",
+        };
+        // A label and not a note, so that its line moves with the edits
+        // of an incremental parse
+        let start = Span::new(span.start, span.start + opening, span.line, span.column).with_end(span.line, span.column + opening);
+        let end = Span::new(span.end, span.end, span.end_line, span.end_column).with_end(span.end_line, span.end_column);
+        Self { span: start, ..diagnostic }.with_label(end, label)
+    }
+
+    /// A parser error. `message` is the parser's raw message, translated here.
+    pub fn syntax(message: &str, span: Span, locale: Locale) -> Self {
+        Self { suggestion: syntax_suggestion(message, locale), ..Self::error("syntax-error", localize_error(message, locale), span) }
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label { span, message: message.into() });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// The `Display` text with the position worded in `locale`.
+    pub fn localized(&self, locale: Locale) -> String {
+        match locale {
+            Locale::Napoletano => self.to_string(),
+            Locale::English => format!("Line {}, column {}: {}", self.span.line, self.span.column, self.message),
+        }
+    }
+}
+
+/// A secondary location of a [`Diagnostic`], with a short explanation
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Error returned by [`crate::compile`]: the first error [`Diagnostic`]
+///
+/// Kept as its own type for compatibility; it dereferences to the
+/// diagnostic, so `error.span` and `error.message` work directly.
+#[derive(Debug, Error, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[error(transparent)]
+pub struct CompileError(Box<Diagnostic>);
+
 impl CompileError {
     pub fn line(&self) -> Option<usize> {
-        match self {
-            CompileError::LexerError { line, .. } => Some(*line),
-            CompileError::ParseError { line, .. } => Some(*line),
-            CompileError::CodeGenError { .. } => None,
-        }
+        Some(self.0.span.line)
     }
-    
+
     pub fn column(&self) -> Option<usize> {
-        match self {
-            CompileError::LexerError { column, .. } => Some(*column),
-            CompileError::ParseError { column, .. } => Some(*column),
-            CompileError::CodeGenError { .. } => None,
-        }
+        Some(self.0.span.column)
+    }
+
+    pub fn diagnostic(&self) -> &Diagnostic {
+        &self.0
+    }
+
+    pub fn into_diagnostic(self) -> Diagnostic {
+        *self.0
+    }
+}
+
+impl Deref for CompileError {
+    type Target = Diagnostic;
+
+    fn deref(&self) -> &Diagnostic {
+        &self.0
+    }
+}
+
+impl From<Diagnostic> for CompileError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        CompileError(Box::new(diagnostic))
     }
 }
 
 // ============== Napoletano Error Messages ==============
 
+/// Translate a raw lexer/parser message into `locale`
+pub fn localize_error(message: &str, locale: Locale) -> String {
+    match locale {
+        Locale::Napoletano => napoletanize_error(message),
+        Locale::English => englishize_error(message),
+    }
+}
+
 /// Get Napoletano message for error
 pub fn napoletanize_error(message: &str) -> String {
+    // The parser reports "Expected ...": match it the same as "Aspettavo ..."
+    let normalized = message
+        .replace("Expected an identifier", "Aspettavo un nome")
+        .replace("Expected a string", "Aspettavo una stringa")
+        .replace("Expected", "Aspettavo");
+
     // Pattern matching for common errors
-    if message.contains("Aspettavo '}'") {
+    if normalized.contains("Aspettavo '}'") {
         return "Uè, hai aperto 'na parentesi graffa ma nun l'hai chiusa! Mettece '}'!".to_string();
     }
-    if message.contains("Aspettavo ')'") {
+    if normalized.contains("Aspettavo ')'") {
         return "Manca 'a parentesi chiusa! Ce vo' ')'!".to_string();
     }
-    if message.contains("Aspettavo ']'") {
+    if normalized.contains("Aspettavo ']'") {
         return "E 'a parentesi quadra? Chiudela cu ']'!".to_string();
     }
-    if message.contains("Aspettavo '='") {
+    if normalized.contains("Aspettavo '='") {
         return "E addò sta l'uguale? Ce vo' '=' pe assegnà 'o valore!".to_string();
     }
-    if message.contains("Aspettavo ';'") {
+    if normalized.contains("Aspettavo ';'") {
         return "Manca 'o punto e virgola! Ma va bene, nun te preoccupà.".to_string();
     }
-    if message.contains("Aspettavo un nome") {
+    if normalized.contains("Aspettavo un nome") {
         return "Ccà ce vo' nu nome! Che cosa vuò chiamà sta variabile?".to_string();
     }
-    if message.contains("Aspettavo una stringa") {
+    if normalized.contains("Aspettavo una stringa") {
         return "Ccà ce vo' 'na stringa! Mettece 'e virgolette!".to_string();
     }
-    if message.contains("Aspettavo 'è'") {
+    if normalized.contains("Aspettavo 'è'") {
         return "Doppo 'chist' ce vo' 'è'! Scrivi 'chist è' pe fà 'na costante.".to_string();
     }
-    if message.contains("Aspettavo 'che'") {
+    if normalized.contains("Aspettavo 'che'") {
         return "Doppo 'mentre' ce vo' 'che'! Scrivi 'mentre che'.".to_string();
     }
-    if message.contains("Aspettavo 'ogni'") {
+    if normalized.contains("Aspettavo 'ogni'") {
         return "Doppo 'pe' ce vo' 'ogni'! Scrivi 'pe ogni'.".to_string();
     }
-    if message.contains("Aspettavo 'vir'") {
+    if normalized.contains("Aspettavo 'vir'") {
         return "Doppo 'mo' ce vo' 'vir'! Scrivi 'mo vir facc' pe 'na funzione asincrona.".to_string();
     }
-    if message.contains("Aspettavo 'bell'") {
+    if normalized.contains("Aspettavo 'bell'") {
         return "Doppo 'nu' ce vo' 'bell'! Scrivi 'nu bell' pe creà n'oggetto nuovo.".to_string();
     }
-    if message.contains("Aspettavo 'famiglie'") {
+    if normalized.contains("Aspettavo 'famiglie'") {
         return "Doppo 'na' ce vo' 'famiglie'! Scrivi 'na famiglie' pe fà 'na classe.".to_string();
     }
-    if message.contains("Aspettavo 'cos'") {
+    if normalized.contains("Aspettavo 'cos'") {
         return "Doppo 'stu' ce vo' 'cos'! Scrivi 'stu cos' pe riferisce a this.".to_string();
     }
-    if message.contains("Aspettavo 'for'") {
+    if normalized.contains("Aspettavo 'for'") {
         return "Doppo 'mann' ce vo' 'for'! Scrivi 'mann for' pe esportà.".to_string();
     }
-    if message.contains("Aspettavo 'dì'") {
+    if normalized.contains("Aspettavo 'dì'") {
         return "Doppo 'stamm a' ce vo' 'dì'! Scrivi 'stamm a dì' pe stampà.".to_string();
     }
-    if message.contains("expression") || message.contains("espressione") {
+    if normalized.contains("Too deeply nested") {
+        return "Espressione troppo annidata! Spezzala a piezze cu quacche variabile.".to_string();
+    }
+    if normalized.contains("Unterminated string") {
+        return "'Sta stringa nun se chiude maje! Chiudila cu 'e stesse virgolette.".to_string();
+    }
+    if normalized.contains("Unterminated comment") {
+        return "Stu commento nun se chiude maje! Ce vo' '*/'.".to_string();
+    }
+    if normalized.contains("expression") || normalized.contains("espressione") {
         return "Ma che staje scrivenn?! Ccà ce vo' 'na espressione!".to_string();
     }
     
@@ -107,62 +320,200 @@ pub fn napoletanize_error(message: &str) -> String {
     format!("Uè, c'è nu problema: {}", message)
 }
 
+// ============== English Error Messages ==============
+
+/// Get English message for error
+pub fn englishize_error(message: &str) -> String {
+    let expected = |what: &str| message.contains(&format!("Expected '{}'", what));
+    let after = |first: &str, second: &str, phrase: &str, meaning: &str| {
+        format!("'{}' must be followed by '{}': write '{}' {}.", first, second, phrase, meaning)
+    };
+
+    if expected("}") {
+        return "A curly brace was opened but never closed: add '}'.".to_string();
+    }
+    if expected(")") {
+        return "Missing closing parenthesis: add ')'.".to_string();
+    }
+    if expected("]") {
+        return "Missing closing square bracket: add ']'.".to_string();
+    }
+    if expected("=") {
+        return "Expected '=' to assign a value.".to_string();
+    }
+    if expected(";") {
+        return "Missing semicolon.".to_string();
+    }
+    if message.contains("Expected an identifier") {
+        return "Expected a name here. What do you want to call it?".to_string();
+    }
+    if message.contains("Expected a string") {
+        return "Expected a string here: wrap it in quotes.".to_string();
+    }
+    if expected("è") {
+        return after("chist", "è", "chist è", "to declare a constant");
+    }
+    if expected("che") {
+        return after("mentre", "che", "mentre che", "for a while loop");
+    }
+    if expected("ogni") {
+        return after("pe", "ogni", "pe ogni", "for a loop");
+    }
+    if expected("vir") {
+        return after("mo", "vir", "mo vir facc", "for an async function");
+    }
+    if expected("bell") {
+        return after("nu", "bell", "nu bell", "to create a new object");
+    }
+    if expected("famiglie") {
+        return after("na", "famiglie", "na famiglie", "to declare a class");
+    }
+    if expected("cos") {
+        return after("stu", "cos", "stu cos", "to refer to this");
+    }
+    if expected("for") {
+        return after("mann", "for", "mann for", "to export");
+    }
+    if expected("dì") {
+        return after("stamm a", "dì", "stamm a dì", "to print");
+    }
+    if message.contains("Too deeply nested") {
+        return "Expression nested too deeply: split it up with a few variables.".to_string();
+    }
+    if message.contains("expression") {
+        return "Expected an expression here.".to_string();
+    }
+    if message.contains("Unterminated string") {
+        return "Unterminated string: close it with the quote it was opened with.".to_string();
+    }
+    if message.contains("Unterminated comment") {
+        return "Unterminated comment: close it with '*/'.".to_string();
+    }
+    if message.chars().count() == 1 {
+        return format!("Unexpected character '{}'.", message);
+    }
+
+    message.to_string()
+}
+
 // ============== Error Suggestions ==============
 
 /// Get helpful suggestions for an error
 pub fn get_suggestion(error: &CompileError) -> Option<String> {
-    match error {
-        CompileError::ParseError { message, .. } => {
-            if message.contains("'}'") {
-                Some("💡 Conta 'e parentesi graffe: ogni '{' adda avè 'o suo '}'".to_string())
-            } else if message.contains("')'") {
-                Some("💡 Conta 'e parentesi tonne: ogni '(' adda avè 'o suo ')'".to_string())
-            } else if message.contains("chist") {
-                Some("💡 Esempio: chist è nome = \"Gennaro\"".to_string())
-            } else if message.contains("facc") {
-                Some("💡 Esempio: facc saluta(nome) { piglie \"Ciao \" + nome }".to_string())
-            } else if message.contains("stamm") {
-                Some("💡 Esempio: stamm a dì(\"Uè!\")".to_string())
-            } else {
-                None
-            }
-        }
-        CompileError::LexerError { message, .. } => {
-            if message.contains("string") {
-                Some("💡 'E stringhe s'aprono e se chiudono cu \" o '".to_string())
-            } else {
-                None
-            }
-        }
-        _ => None,
+    error.suggestion.clone()
+}
+
+/// Suggestion for a raw parser message, unless the `suggestions`
+/// feature is off
+fn syntax_suggestion(message: &str, locale: Locale) -> Option<String> {
+    if !cfg!(feature = "suggestions") {
+        return None;
+    }
+    let english = locale == Locale::English;
+    let example = if english { "💡 Example:" } else { "💡 Esempio:" };
+    if message.contains("'}'") {
+        Some(if english { "💡 Count your curly braces: every '{' needs its '}'" } else { "💡 Conta 'e parentesi graffe: ogni '{' adda avè 'o suo '}'" }.to_string())
+    } else if message.contains("')'") {
+        Some(if english { "💡 Count your parentheses: every '(' needs its ')'" } else { "💡 Conta 'e parentesi tonne: ogni '(' adda avè 'o suo ')'" }.to_string())
+    } else if message.contains("chist") || message.contains("'è'") {
+        Some(format!("{} chist è nome = \"Gennaro\"", example))
+    } else if message.contains("facc") {
+        Some(format!("{} facc saluta(nome) {{ piglie \"Ciao \" + nome }}", example))
+    } else if message.contains("stamm") {
+        Some(format!("{} stamm a dì(\"Uè!\")", example))
+    } else {
+        None
     }
 }
 
 // ============== Random Napoletano Phrases ==============
 
+const ENCOURAGEMENTS: &[&str] = &[
+    "Nun te preoccupà, capita a tutt'!",
+    "Vire buono 'o codice e riprova!",
+    "Cu 'a calma se fa tutto!",
+    "Nisciuno nasce imparato!",
+    "Piano piano se va luntano!",
+    "'A pazienza è 'a virtù d''e forte!",
+    "Pure Maradona sbagliava 'e rigore!",
+    "Fatte nu cafè e po' ce penze n'ata vota.",
+    "Chi nun fa niente, nun sbaglia niente!",
+    "Mo' 'o sistemamme, stai senza pensier'!",
+];
+
+const SUCCESSES: &[&str] = &[
+    "Tutto appost! 🤌",
+    "Uè, funziona! Bravo!",
+    "Perfetto! Comme 'na pizza margherita!",
+    "Eh, vedi che ce l'hai fatta!",
+    "Bellillo! 'O codice è pronto!",
+    "Jamme jà, è fatto!",
+    "Nu capolavoro, overo!",
+    "Cchiù liscio 'e n'olio 'e uliva!",
+];
+
+/// Picks the phrases for [`random_encouragement`] and [`success_message`].
+///
+/// [`PhraseRng::new`] starts from a random seed; [`PhraseRng::seeded`]
+/// always yields the same sequence, for tests and reproducible output.
+#[derive(Debug, Clone)]
+pub struct PhraseRng {
+    state: u64,
+}
+
+impl PhraseRng {
+    pub fn new() -> Self {
+        use std::hash::{BuildHasher, Hasher};
+        // RandomState is seeded by the OS, so no extra dependency is needed
+        Self::seeded(std::collections::hash_map::RandomState::new().build_hasher().finish())
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        // xorshift never leaves 0, so replace it with any odd constant
+        Self { state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed } }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    fn pick(&mut self, phrases: &[&'static str]) -> &'static str {
+        phrases[(self.next_u64() % phrases.len() as u64) as usize]
+    }
+
+    /// Next encouragement phrase from this generator
+    pub fn encouragement(&mut self) -> &'static str {
+        self.pick(ENCOURAGEMENTS)
+    }
+
+    /// Next success phrase from this generator
+    pub fn success(&mut self) -> &'static str {
+        self.pick(SUCCESSES)
+    }
+}
+
+impl Default for PhraseRng {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Message of a failed `giura che` that doesn't give its own.
+pub fn assertion_failed(line: usize) -> String {
+    format!("giura che: 'a condizione nun è overa (riga {})", line)
+}
+
 /// Get a random encouragement phrase
 pub fn random_encouragement() -> &'static str {
-    const PHRASES: &[&str] = &[
-        "Nun te preoccupà, capita a tutt'!",
-        "Vire buono 'o codice e riprova!",
-        "Cu 'a calma se fa tutto!",
-        "Nisciuno nasce imparato!",
-        "Piano piano se va luntano!",
-        "'A pazienza è 'a virtù d''e forte!",
-    ];
-    PHRASES[0] // In real implementation, use random
-}
-
-/// Get a success message
+    PhraseRng::new().encouragement()
+}
+
+/// Get a random success message
 pub fn success_message() -> &'static str {
-    const PHRASES: &[&str] = &[
-        "Tutto appost! 🤌",
-        "Uè, funziona! Bravo!",
-        "Perfetto! Comme 'na pizza margherita!",
-        "Eh, vedi che ce l'hai fatta!",
-        "Bellillo! 'O codice è pronto!",
-    ];
-    PHRASES[0]
+    PhraseRng::new().success()
 }
 
 #[cfg(test)]
@@ -171,25 +522,53 @@ mod tests {
 
     #[test]
     fn test_error_formatting() {
-        let error = CompileError::ParseError {
-            message: "Aspettavo '}'".to_string(),
-            line: 5,
-            column: 10,
-        };
+        let error = CompileError::from(Diagnostic::syntax("Aspettavo '}'", Span::new(40, 41, 5, 10), Locale::Napoletano));
         let msg = format!("{}", error);
         assert!(msg.contains("parentesi graffa"));
         assert!(msg.contains("Riga 5"));
+        assert_eq!(error.span.end, 41);
     }
 
     #[test]
+    #[cfg(feature = "suggestions")]
     fn test_suggestion() {
-        let error = CompileError::ParseError {
-            message: "Aspettavo '}'".to_string(),
-            line: 1,
-            column: 1,
-        };
+        let error = CompileError::from(Diagnostic::syntax("Aspettavo '}'", Span::default(), Locale::Napoletano));
         let suggestion = get_suggestion(&error);
         assert!(suggestion.is_some());
         assert!(suggestion.unwrap().contains("parentesi"));
     }
+
+    #[test]
+    fn test_warning_keeps_message() {
+        let warning = Diagnostic::warning("unused-variable", "'x' nun s'usa", Span::default()).with_note("leval");
+        assert!(!warning.is_error());
+        assert_eq!(warning.to_string(), "Riga 1, colonna 1: 'x' nun s'usa");
+        assert_eq!(warning.notes, vec!["leval"]);
+    }
+
+    #[test]
+    #[cfg(feature = "suggestions")]
+    fn test_english_catalog() {
+        let error = Diagnostic::syntax("Expected 'è', but found 'x'", Span::default(), Locale::English);
+        assert_eq!(error.message, "'chist' must be followed by 'è': write 'chist è' to declare a constant.");
+        assert_eq!(error.suggestion.as_deref(), Some("💡 Example: chist è nome = \"Gennaro\""));
+        let invalid = Diagnostic::lexical("@", Span::new(0, 1, 1, 1), Locale::English);
+        assert_eq!(invalid.localized(Locale::English), "Line 1, column 1: Unexpected character '@'.");
+
+        assert_eq!("en_US.UTF-8".parse(), Ok(Locale::English));
+        assert_eq!("nap".parse(), Ok(Locale::Napoletano));
+        assert!("fr".parse::<Locale>().is_err());
+    }
+
+    #[test]
+    fn test_seeded_phrases() {
+        let picks = |seed| {
+            let mut rng = PhraseRng::seeded(seed);
+            (0..20).map(|_| rng.encouragement()).collect::<Vec<_>>()
+        };
+        assert_eq!(picks(42), picks(42));
+        let distinct: std::collections::HashSet<_> = picks(42).into_iter().collect();
+        assert!(distinct.len() > 1);
+        assert!(SUCCESSES.contains(&PhraseRng::seeded(0).success()));
+    }
 }
@@ -0,0 +1,72 @@
+//! Fingerprints of programs
+//!
+//! A [`Fingerprint`] hashes the AST without its spans, so layout, comments
+//! and the spelling of literals (`'a'` or `"a"`, `1` or `1.0`) don't change
+//! it, while any change to what the program does does. It is the same on
+//! every platform and run, which makes it usable as a cache key or to spot
+//! the same answer written twice; a new version of the compiler may change
+//! it, as the AST changes.
+//!
+//! ```rust
+//! use fratm_core::fingerprint::fingerprint;
+//!
+//! let a = fingerprint("tien x = 1 // uno\nstamm a dì(x)").unwrap();
+//! let b = fingerprint("tien   x = 1\n\n\nstamm a dì( x )").unwrap();
+//! assert_eq!(a, b);
+//! assert_ne!(a, fingerprint("tien x = 2\nstamm a dì(x)").unwrap());
+//! assert_eq!(a.to_string().len(), 16);
+//! ```
+
+use std::fmt;
+
+use crate::errors::Diagnostic;
+use crate::parser::Program;
+
+/// 64-bit hash of a program, shown as 16 hex digits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint(pub u64);
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// The fingerprint of `source`, or its syntax errors.
+pub fn fingerprint(source: &str) -> Result<Fingerprint, Vec<Diagnostic>> {
+    crate::parse_source(source).map(|program| fingerprint_program(&program))
+}
+
+/// The fingerprint of `program`.
+pub fn fingerprint_program(program: &Program) -> Fingerprint {
+    Fingerprint(fnv1a(program.to_sexp().as_bytes()))
+}
+
+/// FNV-1a: `std`'s hasher is randomly seeded, and its algorithm may change
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        // Test vectors of the reference implementation
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(Fingerprint(0xab).to_string(), "00000000000000ab");
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let fingerprint = |source| fingerprint(source).unwrap();
+        assert_eq!(fingerprint("chist è s = 'pizza'"), fingerprint("chist è s = \"pizza\""));
+        assert_eq!(fingerprint("facc f() {\n  piglie 1\n}"), fingerprint("/* f */ facc f() { piglie 1.0 }"));
+        // Names and order count
+        assert_ne!(fingerprint("tien x = 1"), fingerprint("tien y = 1"));
+        assert_ne!(fingerprint("tien x = 1\ntien y = 2"), fingerprint("tien y = 2\ntien x = 1"));
+        assert!(super::fingerprint("tien = 1").is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Syntax upgrades for older FratmScript sources
+//!
+//! [`upgrade`] finds the spellings to bring up to date, as [`Fix`]es that
+//! [`apply`] writes into the source. Only the words change, so the layout
+//! and the comments stay as they are:
+//!
+//! - keywords written another way than the current one: `sinno` without
+//!   its accent, or a word of a dialect pack that has since renamed it
+//! - `pe (...)` gets its `ogni`, as the docs write it: `pe ogni (...)`
+//!
+//! The fixed source is checked against the original through the
+//! formatter: if they don't format to the same program, nothing is fixed.
+//!
+//! A [`Diagnostic`] can carry a [`Fix`] too, when one edit surely mends it:
+//! the `che` a `mentre` is missing, the `}` missing at the end of the file,
+//! or `==` for the `=` of a condition (see [`crate::linter::lint_source`]).
+//! [`fixes_of`] collects them for [`apply`].
+//!
+//! ```rust
+//! use fratm_core::fix::{apply, upgrade};
+//! use fratm_core::lexer::keywords::KeywordTable;
+//!
+//! let source = "si (x) { } sinno { }";
+//! let table = KeywordTable::default();
+//! let fixes = upgrade(source, &table, &table).unwrap();
+//! assert_eq!(apply(source, &fixes), "si (x) { } sinnò { }");
+//! ```
+
+use crate::errors::{CompileError, Diagnostic, Locale};
+use crate::formatter::{format_program, FormatOptions};
+use crate::lexer::keywords::{KeywordTable, KEYWORDS};
+use crate::lexer::{Lexer, Span, TokenKind};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// A machine-applicable edit: `span` of the source becomes `replacement`.
+/// An empty span inserts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Fix {
+    pub span: Span,
+    pub replacement: String,
+}
+
+impl Fix {
+    /// Inserts `text` at the end of `span`
+    pub fn insert_after(span: Span, text: impl Into<String>) -> Self {
+        Self { span: Span::new(span.end, span.end, span.end_line, span.end_column), replacement: text.into() }
+    }
+}
+
+/// The fixes attached to `diagnostics`, for [`apply`]. When two would
+/// touch the same text, only the first is kept: run again for the other.
+pub fn fixes_of(diagnostics: &[Diagnostic]) -> Vec<Fix> {
+    let mut fixes: Vec<Fix> = Vec::new();
+    for fix in diagnostics.iter().filter_map(|diagnostic| diagnostic.fix.clone()) {
+        let overlaps = |other: &Fix| fix.span.start < other.span.end.max(other.span.start + 1) && other.span.start < fix.span.end.max(fix.span.start + 1);
+        if !fixes.iter().any(overlaps) { fixes.push(fix); }
+    }
+    fixes
+}
+
+/// The fixes that bring `source`, written with the keywords of `from`, to
+/// the current spelling of `to`. Pass the same table twice to only update
+/// the spelling.
+///
+/// Fails if `source` doesn't parse, or if a name in it is a keyword of
+/// `to` and would stop being a name.
+pub fn upgrade(source: &str, from: &KeywordTable, to: &KeywordTable) -> Result<Vec<Fix>, CompileError> {
+    let parse = |source: &str, table: &KeywordTable| {
+        crate::parse_with_dialect(source, Locale::default(), table).map_err(|errors| CompileError::from(errors.into_iter().next().unwrap()))
+    };
+    let (old, _) = parse(source, from)?;
+    let tokens = Lexer::new(source).with_keywords(from).tokenize();
+    let mut fixes = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match &token.kind {
+            TokenKind::Identifier(name) if to.lookup(name).is_some() => {
+                let message = format!("'{}' è 'na parola chiave 'e {}: nun se pò cchiù usà comme nomme", name, to.name());
+                return Err(Diagnostic::error("keyword-conflict", message, token.span)
+                    .with_suggestion(format!("💡 Dalle n'ato nome, p'esempio '{}_'", name))
+                    .into());
+            }
+            kind if KEYWORDS.iter().any(|keyword| keyword.kind == *kind) => {
+                let word = to.word(kind);
+                if &source[token.span.start..token.span.end] != word {
+                    fixes.push(Fix { span: token.span, replacement: word.to_string() });
+                }
+                if *kind == TokenKind::Pe && tokens.get(i + 1).is_none_or(|next| next.kind != TokenKind::Ogni) {
+                    let end = Span::new(token.span.end, token.span.end, token.span.end_line, token.span.end_column);
+                    fixes.push(Fix { span: end, replacement: format!(" {}", to.word(&TokenKind::Ogni)) });
+                }
+            }
+            _ => {}
+        }
+    }
+    let (new, _) = parse(&apply(source, &fixes), to)?;
+    let options = FormatOptions::default();
+    if format_program(&old, "", &options) != format_program(&new, "", &options) {
+        return Err(Diagnostic::error("fix-failed", "Cu 'sti correzioni 'o programma cagnarría: nun aggio tuccato niente", Span::default()).into());
+    }
+    Ok(fixes)
+}
+
+/// `source` with `fixes` applied. The fixes must not overlap.
+pub fn apply(source: &str, fixes: &[Fix]) -> String {
+    let mut fixes: Vec<&Fix> = fixes.iter().collect();
+    fixes.sort_by_key(|fix| (fix.span.start, fix.span.end));
+    let mut output = String::with_capacity(source.len());
+    let mut position = 0;
+    for fix in fixes {
+        output.push_str(&source[position..fix.span.start]);
+        output.push_str(&fix.replacement);
+        position = fix.span.end;
+    }
+    output.push_str(&source[position..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(source: &str, from: &KeywordTable, to: &KeywordTable) -> String {
+        apply(source, &upgrade(source, from, to).unwrap())
+    }
+
+    #[test]
+    fn test_spelling_and_ogni() {
+        let table = KeywordTable::default();
+        let source = "pe (tien i = 0; i < 3; i = i + 1) {\n    si (sfols) { } sinno { } // pe (\n}\n";
+        assert_eq!(fixed(source, &table, &table), "pe ogni (tien i = 0; i < 3; i = i + 1) {\n    si (sfòls) { } sinnò { } // pe (\n}\n");
+        assert!(upgrade("pe ogni (;;) { rompe }", &table, &table).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_renamed_keywords() {
+        let old = KeywordTable::dialect("romanesco", [("tien", "tiè"), ("stamm", "damme")]).unwrap();
+        let new = KeywordTable::dialect("romanesco", [("tien", "tieni"), ("stamm", "damme")]).unwrap();
+        assert_eq!(fixed("tiè x = 1\ndamme a dì(x)", &old, &new), "tieni x = 1\ndamme a dì(x)");
+        // Back to Neapolitan
+        assert_eq!(fixed("tiè x = 1\ndamme a dì(x)", &old, &KeywordTable::default()), "tien x = 1\nstamm a dì(x)");
+    }
+
+    #[test]
+    fn test_syntax_error_fixes() {
+        let fix = |source: &str| {
+            let errors = crate::parse_source(source).unwrap_err();
+            apply(source, &fixes_of(&errors))
+        };
+        assert_eq!(fix("mentre (overo) { rompe }"), "mentre che (overo) { rompe }");
+        assert_eq!(fix("si (x) {\n  pe ogni (;;) {\n    rompe\n  }\n"), "si (x) {\n  pe ogni (;;) {\n    rompe\n  }\n}\n");
+        assert_eq!(fix("facc f() { piglie 1"), "facc f() { piglie 1\n}");
+        // The dialect's word
+        let dialect = KeywordTable::dialect("x", [("che", "ca")]).unwrap();
+        let errors = crate::parse_with_dialect("mentre (overo) { }", Locale::default(), &dialect).unwrap_err();
+        assert_eq!(apply("mentre (overo) { }", &fixes_of(&errors)), "mentre ca (overo) { }");
+    }
+
+    #[test]
+    fn test_name_becoming_a_keyword() {
+        let new = KeywordTable::dialect("romanesco", [("tien", "tieni")]).unwrap();
+        let error = upgrade("chist è tieni = 1", &KeywordTable::default(), &new).unwrap_err();
+        assert_eq!((error.code.as_ref(), error.span.column), ("keyword-conflict", 9));
+    }
+}
@@ -0,0 +1,256 @@
+//! Control-flow analysis for FratmScript
+//!
+//! Finds where the code can't go, and functions that return a value only
+//! on some paths. The warnings are [`Diagnostic`]s like the linter's, in
+//! the [`Locale`] given, and [`crate::compile`] reports them too:
+//!
+//! | Rule | What it catches |
+//! |------|-----------------|
+//! | `unreachable-code` | statements after `piglie`, `iett`, `rompe` or `salta` |
+//! | `infinite-loop` | `mentre che (overo)` or `pe (;;)` with no way out |
+//! | `missing-return` | functions that `piglie` a value only on some paths |
+//!
+//! ```rust
+//! use fratm_core::errors::Locale;
+//!
+//! let program = fratm_core::parse_source("facc f() {\n    piglie 1\n    stamm a dì(2)\n}").unwrap();
+//! let warnings = fratm_core::flow::check(&program, Locale::English);
+//! assert_eq!(warnings[0].code, "unreachable-code");
+//! assert_eq!(warnings[0].message, "This code can never run");
+//! ```
+
+use crate::errors::{Diagnostic, Locale};
+use crate::lexer::Span;
+use crate::linter::for_each_child_expression;
+use crate::parser::*;
+
+/// Checks the control flow of every function in `program`, and of the
+/// top level. Warnings are sorted by position.
+pub fn check(program: &Program, locale: Locale) -> Vec<Diagnostic> {
+    let mut flow = Flow { english: locale == Locale::English, ..Flow::default() };
+    flow.statements(&program.statements);
+    flow.warnings.sort_by_key(|w| w.span.start);
+    flow.warnings
+}
+
+#[derive(Default)]
+struct Flow {
+    warnings: Vec<Diagnostic>,
+    /// A `rompe` for the innermost loop
+    breaks: bool,
+    /// A `piglie` or `iett` inside the innermost loop, which leaves it too
+    exits: bool,
+    /// The first `piglie` with a value in the current function, and whether
+    /// one returns nothing
+    returns_value: Option<Span>,
+    returns_nothing: bool,
+    english: bool,
+}
+
+impl Flow {
+    /// Checks `statements` and returns the statement after which the list
+    /// can't go on, if there is one.
+    fn statements(&mut self, statements: &[Statement]) -> Option<Span> {
+        let mut stop = None;
+        let mut warned = false;
+        for stmt in statements {
+            // Functions are hoisted, so one declared after `piglie` still works
+            let hoisted = matches!(stmt, Statement::FunctionDecl { .. } | Statement::Error { .. });
+            if let Some(at) = stop.filter(|_| !warned && !hoisted) {
+                let (message, label) = if self.english {
+                    ("This code can never run", "the code stops here")
+                } else {
+                    ("Stu codice nun se pò maje eseguì", "'o codice se ferma ccà")
+                };
+                self.warnings.push(Diagnostic::warning("unreachable-code", message, stmt.span()).with_label(at, label));
+                warned = true;
+            }
+            let end = self.statement(stmt);
+            stop = stop.or(end);
+        }
+        stop
+    }
+
+    /// Returns the span of `stmt` if the code after it can't run.
+    fn statement(&mut self, stmt: &Statement) -> Option<Span> {
+        match stmt {
+            Statement::VariableDecl { value, .. } => {
+                if let Some(value) = value { self.expression(value); }
+                None
+            }
+            Statement::FunctionDecl { name, body, span, .. } => {
+                self.function(Some(name), body, *span);
+                None
+            }
+            Statement::Return { value, span } => {
+                match value {
+                    Some(value) => {
+                        self.expression(value);
+                        self.returns_value = self.returns_value.or(Some(*span));
+                    }
+                    None => self.returns_nothing = true,
+                }
+                self.exits = true;
+                Some(*span)
+            }
+            Statement::Throw { value, span } => {
+                self.expression(value);
+                self.exits = true;
+                Some(*span)
+            }
+            Statement::Break { span } => {
+                self.breaks = true;
+                Some(*span)
+            }
+            Statement::Continue { span } => Some(*span),
+            Statement::If { condition, then_branch, else_branch, span } => {
+                self.expression(condition);
+                let then_stops = self.statements(then_branch).is_some();
+                let else_stops = else_branch.as_ref().is_some_and(|else_branch| self.statements(else_branch).is_some());
+                (then_stops && else_stops).then_some(*span)
+            }
+            Statement::While { condition, body, span } => {
+                self.expression(condition);
+                self.looping(is_true(condition), body, "mentre che (overo)", *span)
+            }
+            Statement::For { init, condition, update, body, span } => {
+                if let Some(init) = init { self.statement(init); }
+                if let Some(condition) = condition { self.expression(condition); }
+                if let Some(update) = update { self.expression(update); }
+                self.looping(condition.as_ref().is_none_or(is_true), body, "pe", *span)
+            }
+            Statement::TryCatch { try_body, catch_type, catch_body, span, .. } => {
+                let try_stops = self.statements(try_body).is_some();
+                if let Some(catch_type) = catch_type { self.expression(catch_type); }
+                let catch_stops = self.statements(catch_body).is_some();
+                (try_stops && catch_stops).then_some(*span)
+            }
+            Statement::Assert { condition, message, .. } => {
+                self.expression(condition);
+                if let Some(message) = message { self.expression(message); }
+                None
+            }
+            Statement::ClassDecl { decorators, superclass, methods, .. } => {
+                for decorator in decorators { self.expression(decorator); }
+                if let Some(superclass) = superclass { self.expression(superclass); }
+                for method in methods {
+                    if let Statement::FunctionDecl { name, body, decorators, span, .. } = method {
+                        for decorator in decorators { self.expression(decorator); }
+                        self.function(Some(name), body, *span);
+                    }
+                }
+                None
+            }
+            Statement::Export { declaration, default_value, .. } => {
+                if let Some(decl) = declaration { self.statement(decl); }
+                if let Some(value) = default_value { self.expression(value); }
+                None
+            }
+            Statement::Expression { expression, .. } => {
+                self.expression(expression);
+                None
+            }
+            Statement::Block { statements, .. } => self.statements(statements),
+            Statement::Conditional { then_branch, else_branch, span, .. } => {
+                let then_stops = self.statements(then_branch).is_some();
+                let else_stops = else_branch.as_ref().is_some_and(|else_branch| self.statements(else_branch).is_some());
+                (then_stops && else_stops).then_some(*span)
+            }
+            Statement::Import { .. } | Statement::Debugger { .. } | Statement::Error { .. } => None,
+        }
+    }
+
+    /// A loop; `endless` when its condition is always true. Only a `rompe`
+    /// lets the code after an endless loop run.
+    fn looping(&mut self, endless: bool, body: &[Statement], keyword: &str, span: Span) -> Option<Span> {
+        let (breaks, exits) = (std::mem::take(&mut self.breaks), std::mem::take(&mut self.exits));
+        self.statements(body);
+        let (inner_breaks, inner_exits) = (self.breaks, self.exits);
+        self.breaks = breaks;
+        self.exits = exits || inner_exits;
+        if endless && !inner_breaks && !inner_exits {
+            let (message, suggestion) = if self.english {
+                (format!("This '{}' never ends", keyword), "💡 Put a 'rompe' in it to leave the loop")
+            } else {
+                (format!("Stu '{}' nun fernesce maje", keyword), "💡 Mietti nu 'rompe' pe ascì d'o ciclo")
+            };
+            self.warnings.push(Diagnostic::warning("infinite-loop", message, span).with_suggestion(suggestion));
+        }
+        (endless && !inner_breaks).then_some(span)
+    }
+
+    fn function(&mut self, name: Option<&Name>, body: &[Statement], span: Span) {
+        let saved = (std::mem::take(&mut self.breaks), std::mem::take(&mut self.exits));
+        let returns = (self.returns_value.take(), std::mem::take(&mut self.returns_nothing));
+        let falls_through = self.statements(body).is_none();
+        if let Some(value) = self.returns_value.filter(|_| falls_through || self.returns_nothing) {
+            let (message, label, suggestion) = if self.english {
+                let name = name.map_or("This function".to_string(), |name| format!("'{}'", name));
+                (format!("{} returns a value on some paths and nothing on others", name), "returns a value here", "💡 Return a value on every path, the end of the function too")
+            } else {
+                let name = name.map_or("Sta funzione".to_string(), |name| format!("'{}'", name));
+                (format!("{} quacche vota piglie nu valore e quacche vota no", name), "ccà piglie nu valore", "💡 Fa' piglià nu valore a ogni strada, pure 'a fine d'a funzione")
+            };
+            self.warnings.push(Diagnostic::warning("missing-return", message, span).with_label(value, label).with_suggestion(suggestion));
+        }
+        (self.breaks, self.exits) = saved;
+        (self.returns_value, self.returns_nothing) = returns;
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::ArrowFunction { body: ArrowBody::Block(body), span, .. } => self.function(None, body, *span),
+            other => for_each_child_expression(other, |child| self.expression(child)),
+        }
+    }
+}
+
+fn is_true(condition: &Expression) -> bool {
+    matches!(condition, Expression::Boolean { value: true, .. })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(source: &str) -> Vec<String> {
+        check(&crate::parse_source(source).unwrap(), Locale::default()).into_iter().map(|w| w.code.into_owned()).collect()
+    }
+
+    #[test]
+    fn test_unreachable_code() {
+        assert_eq!(rules("facc f() {\n  piglie 1\n  stamm a dì(1)\n  stamm a dì(2)\n}"), vec!["unreachable-code"]);
+        assert_eq!(rules("mentre che (x) {\n  rompe\n  x = 1\n}"), vec!["unreachable-code"]);
+        assert_eq!(rules("si (x) { iett 1 } sinnò { iett 2 }\nstamm a dì(x)"), vec!["unreachable-code"]);
+        // Hoisted, so still usable
+        assert!(rules("facc f() {\n  piglie g()\n  facc g() { piglie 1 }\n}").is_empty());
+        assert!(rules("si (x) { iett 1 }\nstamm a dì(x)").is_empty());
+    }
+
+    #[test]
+    fn test_infinite_loop() {
+        assert_eq!(rules("mentre che (overo) { stamm a dì(1) }\nstamm a dì(2)"), vec!["infinite-loop", "unreachable-code"]);
+        assert!(rules("mentre che (overo) { si (x) { rompe } }\nstamm a dì(2)").is_empty());
+        // A `rompe` for an inner loop doesn't leave the outer one
+        assert_eq!(rules("pe (;;) { mentre che (x) { rompe } }"), vec!["infinite-loop"]);
+        assert!(rules("facc f() {\n  mentre che (overo) { si (x) { piglie 1 } }\n}").is_empty());
+    }
+
+    #[test]
+    fn test_missing_return() {
+        assert_eq!(rules("facc f(x) {\n  si (x) { piglie 1 }\n}"), vec!["missing-return"]);
+        assert_eq!(rules("chist è f = (x) => {\n  si (x) { piglie 1 }\n  piglie\n}"), vec!["missing-return"]);
+        assert!(rules("facc f(x) {\n  si (x) { piglie 1 } sinnò { piglie 2 }\n}").is_empty());
+        assert!(rules("facc f(x) {\n  si (x) { piglie }\n  stamm a dì(x)\n}").is_empty());
+        assert!(rules("facc f(x) {\n  pruvamm { piglie 1 } e si schiatta (err) { iett err }\n}").is_empty());
+    }
+
+    #[test]
+    fn test_english() {
+        let program = crate::parse_source("facc f(x) {\n  si (x) { piglie 1 }\n}\nmentre che (overo) {}").unwrap();
+        let warnings = check(&program, Locale::English);
+        assert_eq!(warnings[0].message, "'f' returns a value on some paths and nothing on others");
+        assert_eq!(warnings[0].labels[0].message, "returns a value here");
+        assert_eq!(warnings[1].message, "This 'mentre che (overo)' never ends");
+    }
+}
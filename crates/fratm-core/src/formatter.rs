@@ -0,0 +1,783 @@
+//! Source formatter for FratmScript
+//!
+//! Pretty-prints a parsed program back to canonical FratmScript: one
+//! statement per line, consistent indentation, minimal parentheses and
+//! normalized keyword spelling. Comments collected by the lexer are put
+//! back next to the statements they were attached to, and single blank
+//! lines between statements are preserved.
+//!
+//! ```rust
+//! use fratm_core::formatter::{format, FormatOptions};
+//!
+//! let formatted = format("chist è x=(1+2)*3", &FormatOptions::default()).unwrap();
+//! assert_eq!(formatted, "chist è x = (1 + 2) * 3\n");
+//! ```
+
+use crate::errors::CompileError;
+use crate::lexer::{Comment, Span};
+use crate::parser::*;
+
+/// Formatting options.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Number of spaces per indentation level (ignored with `use_tabs`)
+    pub indent_width: usize,
+    /// Indent with tabs instead of spaces
+    pub use_tabs: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self { indent_width: 4, use_tabs: false }
+    }
+}
+
+/// Formats FratmScript source code.
+///
+/// Fails with the same error as [`crate::compile`] if the source doesn't parse.
+pub fn format(source: &str, options: &FormatOptions) -> Result<String, CompileError> {
+    let (program, comments) = crate::parse_program(source)?;
+    Ok(Formatter::new(options, comments, source).format(&program))
+}
+
+/// Formats an already parsed program (without comments).
+///
+/// Error nodes from [`Parser::parse_recovering`] are copied verbatim from
+/// `source`; pass an empty string to drop them.
+pub fn format_program(program: &Program, source: &str, options: &FormatOptions) -> String {
+    Formatter::new(options, vec![], source).format(program)
+}
+
+// Precedence levels around those of the binary operators (`BinaryOp::precedence`)
+const PREC_ASSIGN: u8 = 1;
+const PREC_TERNARY: u8 = 2;
+const PREC_UNARY: u8 = 10;
+const PREC_CALL: u8 = 11;
+const PREC_PRIMARY: u8 = 12;
+
+fn binary_keyword(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::And => "e",
+        BinaryOp::Or => "o",
+        BinaryOp::Nullish => "o sinnò",
+        other => other.to_js(),
+    }
+}
+
+fn precedence(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Assignment { .. } | Expression::ArrowFunction { .. } => PREC_ASSIGN,
+        Expression::Ternary { .. } => PREC_TERNARY,
+        Expression::Binary { operator, .. } => operator.precedence(),
+        Expression::Unary { .. } | Expression::Await { .. } | Expression::Delete { .. } | Expression::TypeOf { .. } => PREC_UNARY,
+        Expression::Call { .. } | Expression::Member { .. } | Expression::New { .. } => PREC_CALL,
+        _ => PREC_PRIMARY,
+    }
+}
+
+struct Formatter<'o> {
+    options: &'o FormatOptions,
+    /// Source of the program, for error nodes
+    source: &'o str,
+    comments: Vec<Comment>,
+    next_comment: usize,
+    indent: usize,
+    output: String,
+    /// Source line of the last statement or comment written
+    last_line: Option<usize>,
+}
+
+impl<'o> Formatter<'o> {
+    fn new(options: &'o FormatOptions, comments: Vec<Comment>, source: &'o str) -> Self {
+        Self { options, source, comments, next_comment: 0, indent: 0, output: String::new(), last_line: None }
+    }
+
+    fn verbatim(&mut self, span: Span) {
+        let text = self.source.get(span.start..span.end).unwrap_or("");
+        self.output.push_str(text.trim());
+    }
+
+    fn format(mut self, program: &Program) -> String {
+        self.statements(&program.statements);
+        self.flush_comments_before(usize::MAX);
+        self.output
+    }
+
+    // === Statements ===
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for stmt in statements {
+            let span = stmt.span();
+            self.flush_comments_before(span.start);
+            self.blank_line_before(span.line);
+            self.write_indent();
+            self.statement(stmt);
+            self.trailing_comment(&span);
+            self.output.push('\n');
+            self.last_line = Some(span.end_line);
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement], end: usize) {
+        let has_comments = self.comments[self.next_comment..].iter().any(|c| c.span.start < end);
+        if statements.is_empty() && !has_comments {
+            self.output.push_str("{}");
+            return;
+        }
+        self.output.push_str("{\n");
+        self.indent += 1;
+        self.last_line = None;
+        self.statements(statements);
+        self.flush_comments_before(end);
+        self.indent -= 1;
+        self.write_indent();
+        self.output.push('}');
+    }
+
+    /// One `@decoratore` per line, each followed by the indentation of the
+    /// declaration
+    fn decorators(&mut self, decorators: &[Expression]) {
+        for decorator in decorators {
+            self.output.push('@');
+            self.expression(decorator, PREC_CALL);
+            self.output.push('\n');
+            self.write_indent();
+        }
+    }
+
+    fn class(&mut self, name: &str, superclass: &Option<Expression>, methods: &[Statement], span: &Span) {
+        self.output.push_str("na famiglie ");
+        self.output.push_str(name);
+        if let Some(superclass) = superclass {
+            self.output.push_str(" figlio 'e ");
+            self.expression(superclass, PREC_CALL);
+        }
+        self.output.push(' ');
+        self.block(methods, span.end);
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::VariableDecl { name, annotation, value, is_const, .. } => {
+                self.output.push_str(if *is_const { "chist è " } else { "tien " });
+                self.output.push_str(name);
+                if let Some(annotation) = annotation { self.output.push_str(&format!(": {}", annotation)); }
+                if let Some(value) = value {
+                    self.output.push_str(" = ");
+                    self.expression(value, PREC_ASSIGN);
+                }
+            }
+            Statement::FunctionDecl { name, params, signature, body, is_async, decorators, span } => {
+                self.decorators(decorators);
+                if *is_async { self.output.push_str("mo vir "); }
+                self.output.push_str("facc ");
+                self.output.push_str(name);
+                self.output.push('(');
+                match signature {
+                    Some(signature) => {
+                        let params: Vec<String> = params.iter().zip(&signature.params).map(|(param, annotation)| match annotation {
+                            Some(annotation) => format!("{}: {}", param, annotation),
+                            None => param.to_string(),
+                        }).collect();
+                        self.output.push_str(&params.join(", "));
+                        self.output.push(')');
+                        if let Some(result) = &signature.result { self.output.push_str(&format!(": {}", result)); }
+                        self.output.push(' ');
+                    }
+                    None => {
+                        self.output.push_str(&params.join(", "));
+                        self.output.push_str(") ");
+                    }
+                }
+                self.block(body, span.end);
+            }
+            Statement::Return { value, .. } => {
+                self.output.push_str("piglie");
+                if let Some(value) = value {
+                    self.output.push(' ');
+                    self.expression(value, PREC_ASSIGN);
+                }
+            }
+            Statement::If { condition, then_branch, else_branch, span } => {
+                self.output.push_str("si (");
+                self.expression(condition, PREC_ASSIGN);
+                self.output.push_str(") ");
+                let then_end = match else_branch.as_ref().and_then(|b| b.first()) {
+                    Some(first) => first.span().start,
+                    None => span.end,
+                };
+                self.block(then_branch, then_end);
+                if let Some(else_body) = else_branch {
+                    self.output.push_str(" sinnò ");
+                    match else_body.as_slice() {
+                        [nested @ Statement::If { .. }] => self.statement(nested),
+                        _ => self.block(else_body, span.end),
+                    }
+                }
+            }
+            Statement::While { condition, body, span } => {
+                self.output.push_str("mentre che (");
+                self.expression(condition, PREC_ASSIGN);
+                self.output.push_str(") ");
+                self.block(body, span.end);
+            }
+            Statement::For { init, condition, update, body, span } => {
+                self.output.push_str("pe (");
+                if let Some(init) = init { self.statement(init); }
+                self.output.push_str("; ");
+                if let Some(condition) = condition { self.expression(condition, PREC_ASSIGN); }
+                self.output.push_str("; ");
+                if let Some(update) = update { self.expression(update, PREC_ASSIGN); }
+                self.output.push_str(") ");
+                self.block(body, span.end);
+            }
+            Statement::Break { .. } => self.output.push_str("rompe"),
+            Statement::Continue { .. } => self.output.push_str("salta"),
+            Statement::Debugger { .. } => self.output.push_str("fermete"),
+            Statement::Assert { condition, message, .. } => {
+                self.output.push_str("giura che (");
+                self.expression(condition, PREC_ASSIGN);
+                self.output.push(')');
+                if let Some(message) = message {
+                    self.output.push_str(" sinnò ");
+                    self.expression(message, PREC_ASSIGN);
+                }
+            }
+            Statement::TryCatch { try_body, catch_param, catch_type, catch_body, span } => {
+                self.output.push_str("pruvamm ");
+                let try_end = catch_body.first().map_or(span.end, |s| s.span().start);
+                self.block(try_body, try_end);
+                self.output.push_str(" e si schiatta ");
+                if let Some(param) = catch_param {
+                    self.output.push('(');
+                    self.output.push_str(param);
+                    if let Some(catch_type) = catch_type {
+                        self.output.push_str(" è uno ");
+                        self.expression(catch_type, PREC_CALL);
+                    }
+                    self.output.push_str(") ");
+                }
+                self.block(catch_body, span.end);
+            }
+            Statement::Throw { value, .. } => {
+                self.output.push_str("iett ");
+                self.expression(value, PREC_ASSIGN);
+            }
+            Statement::ClassDecl { name, decorators, superclass, methods, span } => {
+                self.decorators(decorators);
+                self.class(name, superclass, methods, span);
+            }
+            Statement::Import { specifiers, source, .. } => {
+                self.output.push_str("chiamm { ");
+                let names: Vec<&str> = specifiers.iter().map(|s| &*s.imported).collect();
+                self.output.push_str(&names.join(", "));
+                self.output.push_str(" } da ");
+                self.string(source);
+            }
+            Statement::Export { declaration, default_value, .. } => {
+                // Decorators of an exported class go before `mann for`, as in JavaScript
+                if let Some(Statement::ClassDecl { name, decorators, superclass, methods, span }) = declaration.as_deref() {
+                    self.decorators(decorators);
+                    self.output.push_str("mann for ");
+                    self.class(name, superclass, methods, span);
+                    return;
+                }
+                self.output.push_str("mann for ");
+                if let Some(value) = default_value {
+                    self.output.push_str("predefinit ");
+                    self.expression(value, PREC_ASSIGN);
+                } else if let Some(decl) = declaration {
+                    self.statement(decl);
+                }
+            }
+            Statement::Expression { expression, .. } => self.unbraced_expression(expression),
+            Statement::Block { statements, span } => self.block(statements, span.end),
+            Statement::Conditional { condition, then_branch, else_branch, span } => {
+                self.output.push_str("#si ");
+                if condition.negated && condition.value.is_none() { self.output.push_str("manco "); }
+                self.output.push_str(&condition.name);
+                if let Some(value) = &condition.value {
+                    self.output.push_str(if condition.negated { " != " } else { " == " });
+                    self.string(value);
+                }
+                self.output.push(' ');
+                let then_end = else_branch.as_ref().and_then(|b| b.first()).map_or(span.end, |first| first.span().start);
+                self.block(then_branch, then_end);
+                if let Some(else_body) = else_branch {
+                    self.output.push_str(" sinnò ");
+                    self.block(else_body, span.end);
+                }
+            }
+            Statement::Error { span } => self.verbatim(*span),
+        }
+    }
+
+    // === Expressions ===
+
+    fn expression(&mut self, expr: &Expression, min_prec: u8) {
+        let needs_parens = precedence(expr) < min_prec;
+        if needs_parens { self.output.push('('); }
+        self.expression_inner(expr);
+        if needs_parens { self.output.push(')'); }
+    }
+
+    /// An expression where a leading `{` would start a block instead: an
+    /// expression statement or an arrow function body
+    fn unbraced_expression(&mut self, expr: &Expression) {
+        let start = self.output.len();
+        self.expression(expr, PREC_ASSIGN);
+        if self.output[start..].starts_with('{') {
+            self.output.insert(start, '(');
+            self.output.push(')');
+        }
+    }
+
+    /// The callee of a call or the object of a member access. `nu bell`
+    /// takes the whole call chain after it, so a `nu bell` here needs
+    /// parentheses.
+    fn callee(&mut self, expr: &Expression) {
+        let min_prec = if matches!(expr, Expression::New { .. }) { PREC_PRIMARY } else { PREC_CALL };
+        self.expression(expr, min_prec);
+    }
+
+    fn number(&mut self, value: f64) {
+        if value == value.floor() && value.abs() < 1e15 {
+            self.output.push_str(&(value as i64).to_string());
+        } else {
+            self.output.push_str(&value.to_string());
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Wildcard { .. } => self.output.push('_'),
+            Pattern::Binding { name, .. } => self.output.push_str(name),
+            Pattern::Literal { value, .. } => match value {
+                Literal::Number(n) => self.number(*n),
+                Literal::String(s) => self.string(s),
+                Literal::Boolean(b) => self.output.push_str(if *b { "overo" } else { "sfòls" }),
+                Literal::Null => self.output.push_str("nisciun"),
+                Literal::Undefined => self.output.push_str("boh"),
+            },
+            Pattern::Array { elements, .. } => {
+                self.output.push('[');
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 { self.output.push_str(", "); }
+                    self.pattern(element);
+                }
+                self.output.push(']');
+            }
+            Pattern::Object { properties, .. } if properties.is_empty() => self.output.push_str("{}"),
+            Pattern::Object { properties, .. } => {
+                self.output.push_str("{ ");
+                for (i, (key, value)) in properties.iter().enumerate() {
+                    if i > 0 { self.output.push_str(", "); }
+                    self.output.push_str(key);
+                    // `{ nome }` is short for `{ nome: nome }`
+                    if !matches!(value, Pattern::Binding { name, .. } if name == key) {
+                        self.output.push_str(": ");
+                        self.pattern(value);
+                    }
+                }
+                self.output.push_str(" }");
+            }
+        }
+    }
+
+    /// A JSX element, with its text as written
+    fn jsx_element(&mut self, tag: &str, attributes: &[(Name, Option<Expression>)], children: &[JsxChild]) {
+        self.output.push('<');
+        self.output.push_str(tag);
+        for (name, value) in attributes {
+            self.output.push(' ');
+            self.output.push_str(name);
+            match value {
+                Some(Expression::String { value, .. }) => {
+                    self.output.push('=');
+                    self.string(value);
+                }
+                Some(value @ Expression::JsxElement { .. }) => {
+                    self.output.push('=');
+                    self.expression(value, PREC_ASSIGN);
+                }
+                Some(value) => {
+                    self.output.push_str("={");
+                    self.expression(value, PREC_ASSIGN);
+                    self.output.push('}');
+                }
+                None => {}
+            }
+        }
+        if children.is_empty() && !tag.is_empty() {
+            self.output.push_str(" />");
+            return;
+        }
+        self.output.push('>');
+        for child in children {
+            match child {
+                JsxChild::Text(text) => self.output.push_str(text),
+                JsxChild::Expression(child @ Expression::JsxElement { .. }) => self.expression(child, PREC_ASSIGN),
+                JsxChild::Expression(child) => {
+                    self.output.push('{');
+                    self.expression(child, PREC_ASSIGN);
+                    self.output.push('}');
+                }
+            }
+        }
+        self.output.push_str("</");
+        self.output.push_str(tag);
+        self.output.push('>');
+    }
+
+    fn expression_inner(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Identifier { name, .. } => self.output.push_str(name),
+            Expression::Number { value, .. } => self.number(*value),
+            Expression::String { value, .. } => self.string(value),
+            Expression::Template { quasis, expressions, .. } => self.template(quasis, expressions),
+            Expression::Boolean { value, .. } => self.output.push_str(if *value { "overo" } else { "sfòls" }),
+            Expression::Null { .. } => self.output.push_str("nisciun"),
+            Expression::Undefined { .. } => self.output.push_str("boh"),
+            Expression::This { .. } => self.output.push_str("stu cos"),
+            Expression::SuperCall { arguments, .. } => {
+                self.output.push_str("pate(");
+                self.list(arguments);
+                self.output.push(')');
+            }
+            Expression::Error { span } => self.verbatim(*span),
+            Expression::Array { elements, .. } => {
+                self.output.push('[');
+                self.list(elements);
+                self.output.push(']');
+            }
+            Expression::Object { properties, .. } => {
+                if properties.is_empty() {
+                    self.output.push_str("{}");
+                    return;
+                }
+                self.output.push_str("{ ");
+                for (i, (key, value)) in properties.iter().enumerate() {
+                    if i > 0 { self.output.push_str(", "); }
+                    self.output.push_str(key);
+                    self.output.push_str(": ");
+                    self.expression(value, PREC_ASSIGN);
+                }
+                self.output.push_str(" }");
+            }
+            Expression::Binary { left, operator, right, .. } => {
+                let prec = operator.precedence();
+                let (left_min, right_min) = if operator.is_right_associative() { (prec + 1, prec) } else { (prec, prec + 1) };
+                // `(-a) ** b` keeps its parentheses: `-a ** b` doesn't parse
+                let left_min = if *operator == BinaryOp::Power { left_min.max(PREC_UNARY + 1) } else { left_min };
+                self.expression(left, left_min);
+                self.output.push(' ');
+                self.output.push_str(binary_keyword(*operator));
+                self.output.push(' ');
+                self.expression(right, right_min);
+            }
+            Expression::Unary { operator, operand, .. } => {
+                self.output.push_str(match operator {
+                    UnaryOp::Negate => "-",
+                    UnaryOp::Not => "no ",
+                });
+                self.expression(operand, PREC_UNARY);
+            }
+            Expression::Assignment { target, value, .. } => {
+                self.expression(target, PREC_CALL);
+                self.output.push_str(" = ");
+                self.expression(value, PREC_ASSIGN);
+            }
+            Expression::Call { callee, arguments, .. } => {
+                self.callee(callee);
+                self.output.push('(');
+                self.list(arguments);
+                self.output.push(')');
+            }
+            // A bare `stamm a dì` (e.g. a callback)
+            Expression::Member { object, property, computed: false, .. }
+                if matches!(object.as_ref(), Expression::Console { .. })
+                    && matches!(property.as_ref(), Expression::Identifier { name, .. } if matches!(&**name, "log" | "warn" | "error")) => {
+                let Expression::Identifier { name, .. } = property.as_ref() else { unreachable!() };
+                self.output.push_str(match &**name { "log" => "stamm", "warn" => "avvis", _ => "scrive" });
+                self.output.push_str(" a dì");
+            }
+            Expression::Member { object, property, computed, .. } => {
+                self.callee(object);
+                if *computed {
+                    self.output.push('[');
+                    self.expression(property, PREC_ASSIGN);
+                    self.output.push(']');
+                } else {
+                    self.output.push('.');
+                    self.expression(property, PREC_PRIMARY);
+                }
+            }
+            Expression::New { callee, arguments, .. } => {
+                self.output.push_str("nu bell ");
+                self.expression(callee, PREC_CALL);
+                self.output.push('(');
+                self.list(arguments);
+                self.output.push(')');
+            }
+            Expression::ArrowFunction { params, body, span } => {
+                self.output.push('(');
+                self.output.push_str(&params.join(", "));
+                self.output.push_str(") => ");
+                match body {
+                    ArrowBody::Expression(e) => self.unbraced_expression(e),
+                    ArrowBody::Block(stmts) => self.block(stmts, span.end),
+                }
+            }
+            Expression::Await { argument, .. } => {
+                self.output.push_str("aspett ");
+                self.expression(argument, PREC_UNARY);
+            }
+            Expression::Ternary { condition, consequent, alternate, .. } => {
+                self.expression(condition, PREC_TERNARY + 1);
+                self.output.push_str(" ? ");
+                self.expression(consequent, PREC_ASSIGN);
+                self.output.push_str(" : ");
+                self.expression(alternate, PREC_TERNARY);
+            }
+            Expression::Console { .. } => self.output.push_str("stamm"),
+            Expression::Promise { helper, .. } => self.output.push_str(helper.phrase()),
+            Expression::Match { subject, arms, .. } => {
+                self.output.push_str("vir buono (");
+                self.expression(subject, PREC_ASSIGN);
+                self.output.push_str(") ");
+                if arms.is_empty() {
+                    self.output.push_str("{}");
+                    return;
+                }
+                self.output.push_str("{\n");
+                self.indent += 1;
+                for arm in arms {
+                    self.write_indent();
+                    self.pattern(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        self.output.push_str(" si (");
+                        self.expression(guard, PREC_ASSIGN);
+                        self.output.push(')');
+                    }
+                    self.output.push_str(" => ");
+                    self.expression(&arm.body, PREC_ASSIGN);
+                    self.output.push('\n');
+                }
+                self.indent -= 1;
+                self.write_indent();
+                self.output.push('}');
+            }
+            Expression::JsxElement { name, attributes, children, .. } => self.jsx_element(name.as_deref().unwrap_or(""), attributes, children),
+            Expression::ConsoleLog { arguments, .. } => self.console("stamm", arguments),
+            Expression::ConsoleWarn { arguments, .. } => self.console("avvis", arguments),
+            Expression::ConsoleError { arguments, .. } => self.console("scrive", arguments),
+            Expression::TypeOf { operand, .. } => {
+                self.output.push_str("chè è ");
+                self.expression(operand, PREC_UNARY);
+            }
+            Expression::Delete { operand, .. } => {
+                self.output.push_str("leva ");
+                self.expression(operand, PREC_UNARY);
+            }
+        }
+    }
+
+    fn console(&mut self, keyword: &str, arguments: &[Expression]) {
+        self.output.push_str(keyword);
+        self.output.push_str(" a dì(");
+        self.list(arguments);
+        self.output.push(')');
+    }
+
+    fn list(&mut self, items: &[Expression]) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 { self.output.push_str(", "); }
+            self.expression(item, PREC_ASSIGN);
+        }
+    }
+
+    fn string(&mut self, value: &str) {
+        self.output.push('"');
+        self.string_text(value, false);
+        self.output.push('"');
+    }
+
+    /// `"Ciao {nome}!"`, with the braces of the text doubled
+    fn template(&mut self, quasis: &[String], expressions: &[Expression]) {
+        self.output.push('"');
+        self.string_text(&quasis[0], true);
+        for (expression, quasi) in expressions.iter().zip(&quasis[1..]) {
+            self.output.push('{');
+            self.expression(expression, 0);
+            self.output.push('}');
+            self.string_text(quasi, true);
+        }
+        self.output.push('"');
+    }
+
+    fn string_text(&mut self, value: &str, in_template: bool) {
+        for c in value.chars() {
+            match c {
+                '"' => self.output.push_str("\\\""),
+                '\\' => self.output.push_str("\\\\"),
+                '\n' => self.output.push_str("\\n"),
+                '\r' => self.output.push_str("\\r"),
+                '\t' => self.output.push_str("\\t"),
+                '{' | '}' if in_template => {
+                    self.output.push(c);
+                    self.output.push(c);
+                }
+                _ => self.output.push(c),
+            }
+        }
+    }
+
+    // === Comments and layout ===
+
+    /// Writes every pending comment that starts before `offset` on its own line.
+    fn flush_comments_before(&mut self, offset: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.span.start >= offset { break; }
+            let comment = comment.clone();
+            self.next_comment += 1;
+            self.blank_line_before(comment.span.line);
+            self.write_indent();
+            self.output.push_str(&comment.text);
+            self.output.push('\n');
+            self.last_line = Some(comment.span.end_line);
+        }
+    }
+
+    /// Keeps a comment that sits on the same line right after a statement.
+    fn trailing_comment(&mut self, span: &Span) {
+        if let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.span.line == span.end_line && comment.span.start >= span.end {
+                self.output.push(' ');
+                self.output.push_str(&comment.text);
+                self.next_comment += 1;
+            }
+        }
+    }
+
+    /// Preserves (at most) one blank line between consecutive items.
+    fn blank_line_before(&mut self, line: usize) {
+        if let Some(last) = self.last_line {
+            if line > last + 1 { self.output.push('\n'); }
+        }
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.indent {
+            if self.options.use_tabs {
+                self.output.push('\t');
+            } else {
+                self.output.push_str(&" ".repeat(self.options.indent_width));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(source: &str) -> String {
+        format(source, &FormatOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_format_statements() {
+        let source = "facc somma(a,b){\npiglie a+b\n}\nsi (x>1){stamm a dì(x)} sinnò {rompe}";
+        assert_eq!(
+            fmt(source),
+            "facc somma(a, b) {\n    piglie a + b\n}\nsi (x > 1) {\n    stamm a dì(x)\n} sinnò {\n    rompe\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_minimal_parens() {
+        assert_eq!(fmt("tien x = ((a + b)) * (c - d)"), "tien x = (a + b) * (c - d)\n");
+        assert_eq!(fmt("tien x = a - (b - c)"), "tien x = a - (b - c)\n");
+        assert_eq!(fmt("tien x = (a ** b) ** c"), "tien x = (a ** b) ** c\n");
+        assert_eq!(fmt("tien x = (-a) ** -(b ** (no c) ** d)"), "tien x = (-a) ** -(b ** (no c) ** d)\n");
+        assert_eq!(fmt("tien x = no (a e b)"), "tien x = no (a e b)\n");
+        assert_eq!(fmt("tien x = (a.b o sinnò c) o sinnò (d o f)"), "tien x = a.b o sinnò c o sinnò (d o f)\n");
+    }
+
+    #[test]
+    fn test_format_keeps_comments_and_blank_lines() {
+        let source = "// 'ncapo\nchist è x = 1 // uno\n\n\n\nfacc f() {\n    // dinto\n}\n";
+        assert_eq!(fmt(source), "// 'ncapo\nchist è x = 1 // uno\n\nfacc f() {\n    // dinto\n}\n");
+    }
+
+    #[test]
+    fn test_format_console() {
+        let source = "stamm.tabella(x)\nx.forEach(scrive a dì)\nstamm a dì(1)\ngiura che (x) sinnò \"ahi\"\n";
+        assert_eq!(fmt(source), source);
+    }
+
+    #[test]
+    fn test_format_type_annotations() {
+        let source = "facc f(a: nummero, b, c: { nome: parola, età: nummero }): (parola) => boh {}\ntien x: nummero[] = []\n";
+        assert_eq!(fmt(source), source);
+        assert_eq!(fmt("tien x :{nome:parola\n età:nummero}"), "tien x: { nome: parola, età: nummero }\n");
+    }
+
+    #[test]
+    fn test_format_directives() {
+        let source = "#si manco DEBUG {\n    rompe\n} sinnò {\n    salta\n}\n#si MODE != \"prova\" {}\n";
+        assert_eq!(fmt(source), source);
+        assert_eq!(fmt("#si no DEBUG { }"), "#si manco DEBUG {}\n");
+    }
+
+    #[test]
+    fn test_format_decorators() {
+        let source = "@sigillata\n@registra(\"pizza\")\nmann for na famiglie Pizza {\n    @(debug o niente)\n    facc saluta() {}\n}\n";
+        assert_eq!(fmt(source), source);
+        assert_eq!(fmt("mann for @a na famiglie B {}"), "@a\nmann for na famiglie B {}\n");
+    }
+
+    #[test]
+    fn test_format_interpolation() {
+        let source = "stamm a dì(\"Ciao {nome}, {n + 1} {{graffe}}\", \"{\"a {b}\"}\")\n";
+        let options = crate::CompileOptions::builder().interpolation(true).build();
+        let (program, _) = crate::parse_with_options(source, &options).unwrap();
+        assert_eq!(format_program(&program, source, &FormatOptions::default()), source);
+    }
+
+    #[test]
+    fn test_format_match() {
+        let source = "chist è a = vir buono (x) {\n    [0, _] => \"zero\"\n    { nome, età: 18 } => nome\n    n si (n > 10) => -1\n}\n";
+        assert_eq!(fmt(source), source);
+        assert_eq!(fmt("vir buono(x){boh=>1,nisciun=>2}"), "vir buono (x) {\n    boh => 1\n    nisciun => 2\n}\n");
+    }
+
+    #[test]
+    fn test_format_promise_helpers() {
+        let source = "chist è p = aspett tutt' quant([a, b])\nchist è q = aspett 'o primm che arriva([a, b])\n";
+        assert_eq!(fmt(source), source);
+    }
+
+    #[test]
+    fn test_format_error_classes() {
+        let source = "na famiglie ErroreMio figlio 'e Errore {}\npruvamm {\n    iett nu bell ErroreMio()\n} e si schiatta (err è uno ErroreMio) {}\n";
+        assert_eq!(fmt(source), source);
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let source = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/10_nuove_feature.fratm")).unwrap();
+        let once = fmt(&source);
+        assert_eq!(fmt(&once), once);
+    }
+
+    #[cfg(feature = "serde")]
+    proptest::proptest! {
+        #[test]
+        fn test_formatted_programs_parse_back(program in crate::parser::arbitrary::program()) {
+            let source = format_program(&program, "", &FormatOptions::default());
+            let parsed = crate::parse_source(&source).map_err(|errors| proptest::test_runner::TestCaseError::fail(format!("{}\n{}", errors[0].message, source)))?;
+            proptest::prop_assert_eq!(parsed.to_sexp(), program.to_sexp(), "{}", source);
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Syntax grammars for editors
+//!
+//! Highlighting grammars generated from the keyword and operator tables
+//! the lexer uses ([`KEYWORDS`] and [`OPERATORS`]), so they can't drift
+//! from what the lexer reads:
+//!
+//! - [`tm_language`]: a TextMate grammar, for VS Code, Sublime Text and
+//!   most other editors
+//! - [`tree_sitter`]: a tree-sitter `grammar.js` that tells the tokens
+//!   apart, for Neovim, Helix and Zed
+//!
+//! Both take a [`KeywordTable`], so a dialect gets its own grammar.
+//!
+//! ```rust
+//! use fratm_core::grammar::tm_language;
+//! use fratm_core::lexer::keywords::KeywordTable;
+//!
+//! let grammar = tm_language(&KeywordTable::neapolitan());
+//! assert!(grammar.contains("\"scopeName\": \"source.fratm\""));
+//! ```
+//!
+//! The tree-sitter grammar names each kind of token after the capture
+//! that highlights it, so its `highlights.scm` is one line per rule:
+//! `(keyword) @keyword`, `(constant_builtin) @constant.builtin`,
+//! `(variable_builtin) @variable.builtin`, `(function_builtin) @function.builtin`,
+//! `(operator) @operator`, `(punctuation) @punctuation`, `(string) @string`,
+//! `(number) @number` and `(comment) @comment`.
+
+use serde_json::{json, Value};
+
+use crate::lexer::keywords::{without_accents, KeywordTable, KEYWORDS, OPERATORS};
+use crate::lexer::TokenKind;
+
+/// How a keyword or operator is highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Group {
+    Control,
+    Declaration,
+    Module,
+    Operator,
+    Logical,
+    Constant,
+    This,
+    Console,
+    Comparison,
+    Assignment,
+    Arithmetic,
+    Arrow,
+    Punctuation,
+}
+
+impl Group {
+    /// Groups in the order the TextMate grammar tries them
+    const ALL: [Group; 13] = [
+        Group::Control, Group::Declaration, Group::Module, Group::Operator, Group::Logical,
+        Group::Constant, Group::This, Group::Console,
+        Group::Arrow, Group::Comparison, Group::Assignment, Group::Arithmetic, Group::Punctuation,
+    ];
+
+    fn of(kind: &TokenKind) -> Group {
+        use TokenKind::*;
+        match kind {
+            Si | Sinno | Pe | Ogni | Mentre | Che | Rompe | Salta | Piglie | Pruvamm | Schiatta
+            | Iett | Caso | Aspett | Giura | Buono => Group::Control,
+            Chist | E | Tien | Facc | Mo | Vir | Na | Famiglie | Fisso | Figlio | De => Group::Declaration,
+            Chiamm | Da | Mann | For | Predefinit => Group::Module,
+            Nu | Bell | Leva | Caccia | Fermete | Tutt | O => Group::Operator,
+            And | Pure | Or | Not | Manco => Group::Logical,
+            Overo | Sfols | Nisciun | Boh => Group::Constant,
+            Stu | Cos => Group::This,
+            Stamm | Di | Avvis | Scrive => Group::Console,
+            Arrow => Group::Arrow,
+            EqualEqual | EqualEqualEqual | BangEqual | BangEqualEqual | Less | Greater | LessEqual
+            | GreaterEqual => Group::Comparison,
+            Equal | PlusEqual | MinusEqual | StarEqual | SlashEqual => Group::Assignment,
+            Plus | Minus | Star | Slash | Percent | StarStar | Question | Colon => Group::Arithmetic,
+            _ => Group::Punctuation,
+        }
+    }
+
+    /// Whether the group holds keywords rather than operators
+    fn is_word(self) -> bool {
+        !matches!(self.rule(), "operator" | "punctuation")
+    }
+
+    /// TextMate scope, without the `.fratm` suffix
+    fn scope(self) -> &'static str {
+        match self {
+            Group::Control => "keyword.control",
+            Group::Declaration => "storage.type",
+            Group::Module => "keyword.control.import",
+            Group::Operator => "keyword.operator.expression",
+            Group::Logical => "keyword.operator.logical",
+            Group::Constant => "constant.language",
+            Group::This => "variable.language.this",
+            Group::Console => "support.function.console",
+            Group::Arrow => "storage.type.function.arrow",
+            Group::Comparison => "keyword.operator.comparison",
+            Group::Assignment => "keyword.operator.assignment",
+            Group::Arithmetic => "keyword.operator.arithmetic",
+            Group::Punctuation => "punctuation",
+        }
+    }
+
+    /// Tree-sitter rule
+    fn rule(self) -> &'static str {
+        match self {
+            Group::Control | Group::Declaration | Group::Module | Group::Operator | Group::Logical => "keyword",
+            Group::Constant => "constant_builtin",
+            Group::This => "variable_builtin",
+            Group::Console => "function_builtin",
+            Group::Arrow | Group::Comparison | Group::Assignment | Group::Arithmetic => "operator",
+            Group::Punctuation => "punctuation",
+        }
+    }
+}
+
+/// Keywords of `table` in `group`, with the accent-free spellings the
+/// lexer accepts, longest first.
+fn words(table: &KeywordTable, group: Group) -> Vec<String> {
+    let mut words: Vec<String> = KEYWORDS.iter()
+        .filter(|info| Group::of(&info.kind) == group)
+        .flat_map(|info| {
+            let word = table.word(&info.kind);
+            let plain = without_accents(word);
+            // `è` can't be written `e`, which is `and`
+            let plain = (table.lookup(&plain).as_ref() == Some(&info.kind)).then_some(plain);
+            std::iter::once(word.to_string()).chain(plain)
+        })
+        .collect();
+    words.sort_by(|a, b| b.chars().count().cmp(&a.chars().count()).then(a.cmp(b)));
+    words.dedup();
+    words
+}
+
+/// Operators in `group`, longest first.
+fn operators(group: Group) -> Vec<String> {
+    let mut operators: Vec<String> = OPERATORS.iter().filter(|kind| Group::of(kind) == group).map(|kind| kind.to_string()).collect();
+    operators.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+    operators
+}
+
+fn escape_regex(text: &str) -> String {
+    text.chars().fold(String::new(), |mut out, c| {
+        if "\\^$.|?*+()[]{}/".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+        out
+    })
+}
+
+/// Letters a name can start with, and continue with, as the lexer reads them
+const IDENT: &str = "[\\p{L}_][\\p{L}\\p{N}_]*";
+
+/// A TextMate grammar for `table`, as pretty-printed JSON.
+pub fn tm_language(table: &KeywordTable) -> String {
+    let keyword = |group: Group| {
+        let words: Vec<String> = words(table, group).iter().map(|word| escape_regex(word)).collect();
+        json!({ "name": format!("{}.fratm", group.scope()), "match": format!("(?<![\\p{{L}}\\p{{N}}_])({})(?![\\p{{L}}\\p{{N}}_])", words.join("|")) })
+    };
+    let operator = |group: Group| {
+        let operators: Vec<String> = operators(group).iter().map(|op| escape_regex(op)).collect();
+        json!({ "name": format!("{}.fratm", group.scope()), "match": operators.join("|") })
+    };
+    let declared = |kind: TokenKind, scope: &str| json!({
+        "match": format!("(?<![\\p{{L}}\\p{{N}}_])({})\\s+({})", escape_regex(table.word(&kind)), IDENT),
+        "captures": { "1": { "name": "storage.type.fratm" }, "2": { "name": format!("{}.fratm", scope) } },
+    });
+    let string = |quote: &str, name: &str| json!({
+        "name": format!("string.quoted.{}.fratm", name),
+        "begin": quote,
+        "end": format!("{}|$", quote),
+        "patterns": [{ "name": "constant.character.escape.fratm", "match": "\\\\." }],
+    });
+
+    let (word_groups, operator_groups): (Vec<Group>, Vec<Group>) = Group::ALL.iter().partition(|group| group.is_word());
+    let grammar = json!({
+        "name": "FratmScript",
+        "scopeName": "source.fratm",
+        "fileTypes": ["fratm"],
+        "patterns": [
+            { "include": "#comments" },
+            { "include": "#strings" },
+            { "include": "#numbers" },
+            { "include": "#declarations" },
+            { "include": "#keywords" },
+            { "include": "#operators" },
+            { "include": "#calls" },
+        ],
+        "repository": {
+            "comments": { "patterns": [
+                { "name": "comment.line.double-slash.fratm", "match": "//.*$" },
+                { "name": "comment.block.fratm", "begin": "/\\*", "end": "\\*/" },
+            ] },
+            "strings": { "patterns": [string("\"", "double"), string("'", "single")] },
+            "numbers": { "patterns": [{ "name": "constant.numeric.decimal.fratm", "match": "\\b\\d+(\\.\\d+)?\\b" }] },
+            "declarations": { "patterns": [
+                declared(TokenKind::Facc, "entity.name.function"),
+                declared(TokenKind::Famiglie, "entity.name.type.class"),
+                declared(TokenKind::Tien, "variable.other.readwrite"),
+                declared(TokenKind::E, "variable.other.constant"),
+            ] },
+            "keywords": { "patterns": word_groups.into_iter().map(keyword).collect::<Vec<Value>>() },
+            "operators": { "patterns": operator_groups.into_iter().map(operator).collect::<Vec<Value>>() },
+            "calls": { "patterns": [
+                { "name": "entity.name.function.fratm", "match": format!("{}(?=\\s*\\()", IDENT) },
+            ] },
+        },
+    });
+    serde_json::to_string_pretty(&grammar).unwrap_or_default() + "\n"
+}
+
+/// A tree-sitter `grammar.js` for `table`.
+///
+/// It parses a flat list of tokens, which is enough to highlight and keeps
+/// the generated grammar in step with the lexer rather than the parser.
+pub fn tree_sitter(table: &KeywordTable) -> String {
+    let js_string = |text: &str| format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"));
+    let mut rules: Vec<(&str, Vec<String>)> = Vec::new();
+    for group in Group::ALL {
+        let items = if group.is_word() { words(table, group) } else { operators(group) };
+        match rules.iter_mut().find(|(rule, _)| *rule == group.rule()) {
+            Some((_, all)) => {
+                for item in items {
+                    if !all.contains(&item) {
+                        all.push(item);
+                    }
+                }
+            }
+            None => rules.push((group.rule(), items)),
+        }
+    }
+
+    let mut out = String::from("// Generated by `fratm grammar --format tree-sitter`, do not edit.\n");
+    out.push_str("module.exports = grammar({\n  name: 'fratmscript',\n  word: $ => $.identifier,\n  extras: $ => [/\\s/],\n  rules: {\n");
+    out.push_str("    source_file: $ => repeat($._token),\n");
+    let names: Vec<String> = rules.iter().map(|(rule, _)| format!("$.{}", rule)).collect();
+    out.push_str(&format!("    _token: $ => choice($.comment, $.string, $.number, {}, $.identifier),\n", names.join(", ")));
+    out.push_str("    comment: $ => token(choice(seq('//', /[^\\r\\n]*/), seq('/*', /[^*]*\\*+([^/*][^*]*\\*+)*/, '/'))),\n");
+    out.push_str("    string: $ => token(choice(seq('\"', repeat(choice(/[^\"\\\\\\r\\n]/, /\\\\./)), '\"'), seq(\"'\", repeat(choice(/[^'\\\\\\r\\n]/, /\\\\./)), \"'\"))),\n");
+    out.push_str("    number: $ => /\\d+(\\.\\d+)?/,\n");
+    for (rule, items) in &rules {
+        let items: Vec<String> = items.iter().map(|item| js_string(item)).collect();
+        out.push_str(&format!("    {}: $ => choice({}),\n", rule, items.join(", ")));
+    }
+    out.push_str("    identifier: $ => /[\\p{L}_][\\p{L}\\p{N}_]*/,\n  },\n});\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vscode_grammar_is_up_to_date() {
+        let checked_in = include_str!("../../../packages/vscode-extension/syntaxes/fratmscript.tmLanguage.json");
+        assert!(checked_in == tm_language(&KeywordTable::neapolitan()), "run `fratm grammar --format tmlanguage` to update the VS Code grammar");
+    }
+
+    #[test]
+    fn test_every_keyword_is_highlighted() {
+        let grammar = tree_sitter(&KeywordTable::neapolitan());
+        for info in KEYWORDS {
+            assert!(grammar.contains(&format!("'{}'", info.word.replace('\'', "\\'"))), "{}", info.word);
+        }
+        assert!(grammar.contains("'sfols'") && grammar.contains("'==='"));
+    }
+
+    #[test]
+    fn test_dialect_grammar() {
+        let table = KeywordTable::dialect("romanesco", [("tien", "tiè")]).unwrap();
+        let grammar = tm_language(&table);
+        assert!(grammar.contains("tiè") && !grammar.contains("tien"));
+    }
+}
@@ -0,0 +1,568 @@
+//! Editor support for FratmScript
+//!
+//! Helpers for editors and the playground. They work on possibly
+//! incomplete source, so they use the token stream, or the program
+//! [`parse_recovering`](crate::parse_recovering) makes of it, and never
+//! require the program to parse.
+//!
+//! ## Completions
+//!
+//! [`completions`] returns the keywords that make sense at the cursor,
+//! plus the identifiers declared before it:
+//!
+//! ```rust
+//! use fratm_core::ide::completions;
+//!
+//! let items = completions("tien pizza = 1\nstamm a dì(pi", 28);
+//! assert_eq!(items[0].label, "pizza");
+//! ```
+//!
+//! ## Folding and Selection
+//!
+//! [`folding_ranges`] finds what can be folded: blocks, multi-line objects
+//! and arrays, and comments. [`selection_ranges`] lists what expand
+//! selection goes through, from the innermost expression to the whole file:
+//!
+//! ```rust
+//! use fratm_core::ide::{folding_ranges, selection_ranges};
+//!
+//! let source = "facc f() {\n  piglie 1 + 2\n}";
+//! assert_eq!(folding_ranges(source)[0].span.end_line, 3);
+//! let spans: Vec<_> = selection_ranges(source, 20).iter().map(|span| &source[span.start..span.end]).collect();
+//! assert_eq!(spans[..3], ["1", "1 + 2", "piglie 1 + 2"]);
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use crate::lexer::keywords::keyword_info;
+use crate::lexer::{CommentKind, Lexer, Span, Token, TokenKind};
+use crate::line_index::LineIndex;
+use crate::parser::{ArrowBody, Expression, JsxChild, Statement};
+
+/// What a completion item refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CompletionKind {
+    Keyword,
+    Variable,
+    Constant,
+    Function,
+    Parameter,
+    Class,
+}
+
+/// A completion candidate.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Completion {
+    /// Text shown in the completion list
+    pub label: String,
+    /// Kind of item, for the icon
+    pub kind: CompletionKind,
+    /// Text that replaces the word under the cursor
+    pub insert_text: String,
+    /// Extra info, e.g. the JavaScript equivalent of a keyword
+    pub detail: Option<String>,
+}
+
+/// Keywords that can start a statement.
+const STATEMENT_KEYWORDS: &[&str] = &[
+    "chist è", "tien", "facc", "mo vir facc", "piglie", "si", "mentre che", "pe",
+    "pruvamm", "iett", "na famiglie", "chiamm", "mann for", "rompe", "salta",
+    "fermete", "stamm a dì", "avvis a dì", "scrive a dì", "leva",
+];
+
+/// Keywords that can start an expression.
+const EXPRESSION_KEYWORDS: &[&str] = &[
+    "overo", "sfòls", "nisciun", "boh", "stu cos", "nu bell", "aspett", "no", "manco",
+];
+
+/// Keywords that can follow an expression.
+const OPERATOR_KEYWORDS: &[&str] = &["e", "o", "pure"];
+
+/// Returns completion candidates at byte `offset` in `source`.
+///
+/// Candidates are filtered by the word being typed at `offset`.
+/// Identifiers come first (most recently declared first), then keywords.
+pub fn completions(source: &str, offset: usize) -> Vec<Completion> {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &source[..offset];
+    let word_start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+        .last()
+        .map_or(offset, |(i, _)| i);
+    let prefix = &source[word_start..offset];
+
+    let mut lexer = Lexer::new(&source[..word_start]);
+    let tokens: Vec<Token> = lexer.tokenize().into_iter()
+        .filter(|t| !matches!(t.kind, TokenKind::Eof))
+        .collect();
+    let in_comment = lexer.comments().last().is_some_and(|c| {
+        c.span.end == word_start && (c.kind == CommentKind::Line || !c.text.ends_with("*/"))
+    });
+    if in_comment || tokens.last().is_some_and(|t| matches!(t.kind, TokenKind::Invalid(_))) {
+        return vec![];
+    }
+
+    let mut items = Vec::new();
+    match context(&tokens) {
+        Context::Continue(words) => items.extend(words.iter().map(|w| keyword(w))),
+        Context::Name => {}
+        Context::Statement { after_block } => {
+            items.extend(declared_names(&tokens));
+            if after_block {
+                items.push(keyword("sinnò"));
+                items.push(keyword("e si schiatta"));
+            }
+            items.extend(STATEMENT_KEYWORDS.iter().chain(EXPRESSION_KEYWORDS).map(|w| keyword(w)));
+        }
+        Context::Expression => {
+            items.extend(declared_names(&tokens));
+            items.extend(EXPRESSION_KEYWORDS.iter().map(|w| keyword(w)));
+        }
+        Context::Operator => items.extend(OPERATOR_KEYWORDS.iter().map(|w| keyword(w))),
+    }
+
+    items.retain(|item| item.label.starts_with(prefix) && item.label != prefix);
+    items
+}
+
+/// Syntactic position of the cursor, judged from the preceding tokens.
+enum Context {
+    /// Inside a multi-word keyword: only these words fit
+    Continue(&'static [&'static str]),
+    /// Naming something new (after `tien`, `facc`, `.`, ...): nothing to suggest
+    Name,
+    /// Start of a statement
+    Statement { after_block: bool },
+    /// Where a value is expected
+    Expression,
+    /// Right after a complete operand
+    Operator,
+}
+
+fn context(tokens: &[Token]) -> Context {
+    let last = tokens.last().map(|t| &t.kind);
+    let second_last = tokens.len().checked_sub(2).map(|i| &tokens[i].kind);
+
+    match last {
+        None | Some(TokenKind::Newline | TokenKind::LeftBrace | TokenKind::Semicolon) => {
+            Context::Statement { after_block: false }
+        }
+        Some(TokenKind::RightBrace) => Context::Statement { after_block: true },
+        Some(TokenKind::Chist) => Context::Continue(&["è"]),
+        Some(TokenKind::Mo) => Context::Continue(&["vir"]),
+        Some(TokenKind::Vir) => Context::Continue(&["facc"]),
+        Some(TokenKind::Nu) => Context::Continue(&["bell"]),
+        Some(TokenKind::Na) => Context::Continue(&["famiglie"]),
+        Some(TokenKind::Stu) => Context::Continue(&["cos"]),
+        Some(TokenKind::Mentre | TokenKind::Giura) => Context::Continue(&["che"]),
+        Some(TokenKind::Pe) => Context::Continue(&["ogni"]),
+        Some(TokenKind::Mann) => Context::Continue(&["for"]),
+        Some(TokenKind::For) => Context::Continue(&["chist è", "tien", "facc", "na famiglie", "predefinit"]),
+        Some(TokenKind::Stamm | TokenKind::Avvis | TokenKind::Scrive) => Context::Continue(&["a dì"]),
+        Some(TokenKind::Identifier(a)) if *a == "a"
+            && matches!(second_last, Some(TokenKind::Stamm | TokenKind::Avvis | TokenKind::Scrive)) => {
+            Context::Continue(&["dì"])
+        }
+        Some(TokenKind::E) if matches!(second_last, Some(TokenKind::Chist)) => Context::Name,
+        Some(TokenKind::Tien | TokenKind::Facc | TokenKind::Famiglie | TokenKind::Dot) => Context::Name,
+        Some(
+            TokenKind::Identifier(_) | TokenKind::Number(_) | TokenKind::String(_) | TokenKind::TemplateTail(_)
+            | TokenKind::RightParen | TokenKind::RightBracket
+            | TokenKind::Overo | TokenKind::Sfols | TokenKind::Nisciun | TokenKind::Boh | TokenKind::Cos
+        ) => Context::Operator,
+        Some(_) => Context::Expression,
+    }
+}
+
+/// Names declared in `tokens`, most recent first, without duplicates.
+fn declared_names(tokens: &[Token]) -> Vec<Completion> {
+    let mut found: Vec<(String, CompletionKind)> = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let next_name = |at: usize| match tokens.get(at).map(|t| &t.kind) {
+            Some(TokenKind::Identifier(name)) => Some(name.to_string()),
+            _ => None,
+        };
+        match &tokens[i].kind {
+            TokenKind::Tien => found.extend(next_name(i + 1).map(|n| (n, CompletionKind::Variable))),
+            TokenKind::E if i > 0 && tokens[i - 1].kind == TokenKind::Chist => {
+                found.extend(next_name(i + 1).map(|n| (n, CompletionKind::Constant)));
+            }
+            TokenKind::Famiglie => found.extend(next_name(i + 1).map(|n| (n, CompletionKind::Class))),
+            TokenKind::Facc => {
+                found.extend(next_name(i + 1).map(|n| (n, CompletionKind::Function)));
+                // Parameters: facc name(a, b)
+                let mut j = i + 2;
+                if tokens.get(j).map(|t| &t.kind) == Some(&TokenKind::LeftParen) {
+                    j += 1;
+                    while let Some(token) = tokens.get(j) {
+                        match &token.kind {
+                            TokenKind::Identifier(name) => found.push((name.to_string(), CompletionKind::Parameter)),
+                            TokenKind::Comma => {}
+                            _ => break,
+                        }
+                        j += 1;
+                    }
+                }
+            }
+            TokenKind::Chiamm => {
+                // Named imports: chiamm { a, b } da "..."
+                let mut j = i + 1;
+                while let Some(token) = tokens.get(j) {
+                    match &token.kind {
+                        TokenKind::Identifier(name) => found.push((name.to_string(), CompletionKind::Variable)),
+                        TokenKind::LeftBrace | TokenKind::Comma => {}
+                        _ => break,
+                    }
+                    j += 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let mut items: Vec<Completion> = Vec::new();
+    for (name, kind) in found.into_iter().rev() {
+        if items.iter().any(|item| item.label == name) {
+            continue;
+        }
+        let insert_text = if kind == CompletionKind::Function { format!("{}()", name) } else { name.clone() };
+        items.push(Completion { label: name, kind, insert_text, detail: None });
+    }
+    items
+}
+
+fn keyword(phrase: &str) -> Completion {
+    let first = phrase.split(' ').next().unwrap_or(phrase);
+    Completion {
+        label: phrase.to_string(),
+        kind: CompletionKind::Keyword,
+        insert_text: phrase.to_string(),
+        detail: keyword_info(first).map(|info| info.javascript.to_string()),
+    }
+}
+
+/// What a folding range holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum FoldingKind {
+    /// A block, class body, object, array or argument list
+    Region,
+    /// A block comment, or a run of line comments
+    Comment,
+}
+
+/// A part of the source an editor can fold.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FoldingRange {
+    /// From the opening bracket to the closing one, or the comments
+    pub span: Span,
+    pub kind: FoldingKind,
+}
+
+/// Returns the foldable ranges of `source`, in source order: brackets
+/// spanning several lines, multi-line block comments and runs of line
+/// comments on consecutive lines.
+pub fn folding_ranges(source: &str) -> Vec<FoldingRange> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.tokenize();
+    let mut ranges: Vec<FoldingRange> = bracket_pairs(&tokens).into_iter()
+        .map(|span| FoldingRange { span, kind: FoldingKind::Region })
+        .collect();
+
+    let mut run: Option<Span> = None;
+    for comment in lexer.comments() {
+        let span = comment.span;
+        match comment.kind {
+            CommentKind::Line => match &mut run {
+                Some(run) if run.end_line + 1 == span.line => *run = run.to(&span),
+                _ => ranges.extend(run.replace(span).map(comment_range)),
+            },
+            CommentKind::Block => ranges.push(comment_range(span)),
+        }
+    }
+    ranges.extend(run.map(comment_range));
+    ranges.retain(|range| range.span.end_line > range.span.line);
+    ranges.sort_by_key(|range| range.span.start);
+    ranges
+}
+
+fn comment_range(span: Span) -> FoldingRange {
+    FoldingRange { span, kind: FoldingKind::Comment }
+}
+
+/// Spans of the matching `{ }`, `[ ]` and `( )` pairs of `tokens`, each
+/// from the opening bracket to the closing one. Unmatched brackets are
+/// left out.
+fn bracket_pairs(tokens: &[Token]) -> Vec<Span> {
+    let mut open: Vec<&Token> = Vec::new();
+    let mut pairs = Vec::new();
+    for token in tokens {
+        let opener = match token.kind {
+            TokenKind::LeftBrace | TokenKind::LeftBracket | TokenKind::LeftParen => {
+                open.push(token);
+                continue;
+            }
+            TokenKind::RightBrace => TokenKind::LeftBrace,
+            TokenKind::RightBracket => TokenKind::LeftBracket,
+            TokenKind::RightParen => TokenKind::LeftParen,
+            _ => continue,
+        };
+        if let Some(at) = open.iter().rposition(|token| token.kind == opener) {
+            pairs.push(open[at].span.to(&token.span));
+            open.truncate(at);
+        }
+    }
+    pairs
+}
+
+/// Returns the ranges expand selection goes through at byte `offset` of
+/// `source`: every statement, expression and bracket pair around it, from
+/// the innermost out, ending with the whole source.
+pub fn selection_ranges(source: &str, offset: usize) -> Vec<Span> {
+    let (program, _) = crate::parse_recovering(source);
+    let mut enclosing = Enclosing { offset, spans: Vec::new() };
+    enclosing.statements(&program.statements);
+    enclosing.spans.extend(bracket_pairs(&Lexer::new(source).tokenize()).into_iter().filter(|span| span.start <= offset && offset <= span.end));
+
+    let end = LineIndex::new(source).line_col(source.len());
+    let last_line = &source[source.len() - end.col..];
+    enclosing.spans.push(Span::new(0, source.len(), 1, 1).with_end(end.line + 1, last_line.chars().count() + 1));
+
+    let mut spans = enclosing.spans;
+    spans.sort_by_key(|span| (span.end - span.start, std::cmp::Reverse(span.start)));
+    spans.dedup_by_key(|span| (span.start, span.end));
+    spans
+}
+
+/// Collects the spans of the AST nodes around an offset.
+struct Enclosing {
+    offset: usize,
+    spans: Vec<Span>,
+}
+
+impl Enclosing {
+    fn add(&mut self, span: Span) {
+        if span.start <= self.offset && self.offset <= span.end {
+            self.spans.push(span);
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for stmt in statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        self.add(stmt.span());
+        match stmt {
+            Statement::VariableDecl { value, .. } | Statement::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            Statement::FunctionDecl { body, decorators, .. } => {
+                for decorator in decorators {
+                    self.expression(decorator);
+                }
+                self.statements(body);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                self.expression(condition);
+                self.statements(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statements(else_branch);
+                }
+            }
+            Statement::Conditional { then_branch, else_branch, .. } => {
+                self.statements(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statements(else_branch);
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                self.expression(condition);
+                self.statements(body);
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                if let Some(init) = init {
+                    self.statement(init);
+                }
+                for expr in condition.iter().chain(update) {
+                    self.expression(expr);
+                }
+                self.statements(body);
+            }
+            Statement::Assert { condition, message, .. } => {
+                self.expression(condition);
+                if let Some(message) = message {
+                    self.expression(message);
+                }
+            }
+            Statement::TryCatch { try_body, catch_type, catch_body, .. } => {
+                self.statements(try_body);
+                if let Some(catch_type) = catch_type {
+                    self.expression(catch_type);
+                }
+                self.statements(catch_body);
+            }
+            Statement::Throw { value, .. } | Statement::Expression { expression: value, .. } => self.expression(value),
+            Statement::ClassDecl { decorators, superclass, methods, .. } => {
+                for decorator in decorators {
+                    self.expression(decorator);
+                }
+                if let Some(superclass) = superclass {
+                    self.expression(superclass);
+                }
+                self.statements(methods);
+            }
+            Statement::Block { statements, .. } => self.statements(statements),
+            Statement::Export { declaration, default_value, .. } => {
+                if let Some(declaration) = declaration {
+                    self.statement(declaration);
+                }
+                if let Some(value) = default_value {
+                    self.expression(value);
+                }
+            }
+            Statement::Break { .. } | Statement::Continue { .. } | Statement::Debugger { .. }
+            | Statement::Import { .. } | Statement::Error { .. } => {}
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        self.add(expr.span());
+        match expr {
+            Expression::Array { elements: items, .. }
+            | Expression::Template { expressions: items, .. }
+            | Expression::ConsoleLog { arguments: items, .. }
+            | Expression::SuperCall { arguments: items, .. }
+            | Expression::ConsoleWarn { arguments: items, .. }
+            | Expression::ConsoleError { arguments: items, .. } => {
+                for item in items {
+                    self.expression(item);
+                }
+            }
+            Expression::Object { properties, .. } => {
+                for (_, value) in properties {
+                    self.expression(value);
+                }
+            }
+            Expression::Binary { left, right, .. } | Expression::Assignment { target: left, value: right, .. }
+            | Expression::Member { object: left, property: right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::Unary { operand, .. } | Expression::Await { argument: operand, .. }
+            | Expression::TypeOf { operand, .. } | Expression::Delete { operand, .. } => self.expression(operand),
+            Expression::Call { callee, arguments, .. } | Expression::New { callee, arguments, .. } => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            Expression::ArrowFunction { body, .. } => match body {
+                ArrowBody::Block(body) => self.statements(body),
+                ArrowBody::Expression(body) => self.expression(body),
+            },
+            Expression::Ternary { condition, consequent, alternate, .. } => {
+                self.expression(condition);
+                self.expression(consequent);
+                self.expression(alternate);
+            }
+            Expression::JsxElement { attributes, children, .. } => {
+                for value in attributes.iter().filter_map(|(_, value)| value.as_ref()) {
+                    self.expression(value);
+                }
+                for child in children {
+                    if let JsxChild::Expression(child) = child {
+                        self.expression(child);
+                    }
+                }
+            }
+            Expression::Match { subject, arms, .. } => {
+                self.expression(subject);
+                for arm in arms {
+                    self.add(arm.span);
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    self.expression(&arm.body);
+                }
+            }
+            Expression::Identifier { .. } | Expression::Number { .. } | Expression::String { .. }
+            | Expression::Boolean { .. } | Expression::Null { .. } | Expression::Undefined { .. }
+            | Expression::This { .. } | Expression::Console { .. } | Expression::Promise { .. } | Expression::Error { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(source: &str) -> Vec<String> {
+        completions(source, source.len()).into_iter().map(|c| c.label).collect()
+    }
+
+    #[test]
+    fn test_statement_keywords() {
+        let items = labels("tien x = 1\nmen");
+        assert_eq!(items, vec!["mentre che"]);
+
+        let items = labels("tien x = 1\nsi (x) {\n} ");
+        assert_eq!(items[0], "x");
+        assert!(items.contains(&"sinnò".to_string()));
+    }
+
+    #[test]
+    fn test_multi_word_keywords() {
+        assert_eq!(labels("chist "), vec!["è"]);
+        assert_eq!(labels("stamm a "), vec!["dì"]);
+        assert!(labels("tien ").is_empty());
+        assert_eq!(labels("tien x = 1 "), vec!["e", "o", "pure"]);
+    }
+
+    #[test]
+    fn test_declared_identifiers() {
+        let source = "chist è pizza = 1\nfacc mangia(fette) {\n  piglie f";
+        let items = completions(source, source.len());
+        assert_eq!(items[0].label, "fette");
+        assert_eq!(items[0].kind, CompletionKind::Parameter);
+
+        let items = completions(source, 49);
+        assert_eq!(items.iter().find(|c| c.label == "mangia").unwrap().insert_text, "mangia()");
+        assert!(labels("tien x = 1 // x").is_empty());
+    }
+
+    #[test]
+    fn test_folding_ranges() {
+        let source = "// Pizze\n// d'a casa\nfacc f() {\n  tien menu = [\n    \"margherita\",\n  ]\n  /* una\n     riga */\n  piglie { a: 1 }\n}";
+        let ranges: Vec<_> = folding_ranges(source).into_iter().map(|range| (range.span.line, range.span.end_line, range.kind)).collect();
+        assert_eq!(ranges, [(1, 2, FoldingKind::Comment), (3, 10, FoldingKind::Region), (4, 6, FoldingKind::Region), (7, 8, FoldingKind::Comment)]);
+    }
+
+    #[test]
+    fn test_selection_ranges() {
+        let source = "si (x) {\n  stamm a dì(x + 1)\n}";
+        let offset = source.rfind('1').unwrap();
+        let spans: Vec<_> = selection_ranges(source, offset).iter().map(|span| &source[span.start..span.end]).collect();
+        assert_eq!(spans, ["1", "x + 1", "(x + 1)", "stamm a dì(x + 1)", "{\n  stamm a dì(x + 1)\n}", source]);
+        // Broken source still gives the whole file
+        assert_eq!(selection_ranges("tien = ", 3).last().unwrap().end, 7);
+    }
+}
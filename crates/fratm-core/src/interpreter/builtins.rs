@@ -0,0 +1,572 @@
+//! Builtin globals and methods (`Math`, `JSON`, array and string methods, ...)
+
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use crate::lexer::keywords::CONSOLE_METHODS;
+use crate::lexer::Span;
+use crate::parser::PromiseHelper;
+
+use super::value::{NativeFn, NativeFunction, Object};
+use super::{ConsoleLevel, Exec, Interpreter, Value};
+
+fn native(name: &'static str, func: NativeFn) -> Value {
+    Value::Native(Rc::new(NativeFunction { name, func }))
+}
+
+fn arg(args: &[Value], index: usize) -> Value {
+    args.get(index).cloned().unwrap_or(Value::Undefined)
+}
+
+/// Creates an `Error` object, e.g. `make_error("TypeError", "...")`.
+pub(crate) fn make_error(name: &str, message: String) -> Value {
+    let object = Object {
+        properties: vec![("name".to_string(), Value::string(name)), ("message".to_string(), Value::string(message))],
+        class: None,
+        is_error: true,
+    };
+    Value::Object(Rc::new(std::cell::RefCell::new(object)))
+}
+
+/// `console`, with the Neapolitan method names (`stamm.tabella`) next to the JavaScript ones.
+fn console_object() -> Value {
+    let mut methods = vec![
+        ("log".to_string(), native("log", |i, _, args, _| console(i, ConsoleLevel::Log, args))),
+        ("info".to_string(), native("info", |i, _, args, _| console(i, ConsoleLevel::Log, args))),
+        ("debug".to_string(), native("debug", |i, _, args, _| console(i, ConsoleLevel::Log, args))),
+        // No table layout here: the rows are printed like `stamm a dì` would
+        ("table".to_string(), native("table", |i, _, args, _| console(i, ConsoleLevel::Log, args))),
+        ("warn".to_string(), native("warn", |i, _, args, _| console(i, ConsoleLevel::Warn, args))),
+        ("error".to_string(), native("error", |i, _, args, _| console(i, ConsoleLevel::Error, args))),
+    ];
+    for (alias, js) in CONSOLE_METHODS {
+        if let Some((_, method)) = methods.iter().find(|(name, _)| name == js) {
+            methods.push((alias.to_string(), method.clone()));
+        }
+    }
+    Value::object(methods)
+}
+
+/// Values in scope in every program.
+pub(crate) fn globals() -> Vec<(&'static str, Value)> {
+    vec![
+        ("NaN", Value::Number(f64::NAN)),
+        ("Infinity", Value::Number(f64::INFINITY)),
+        ("console", console_object()),
+        ("Math", Value::object(vec![
+            ("PI".to_string(), Value::Number(std::f64::consts::PI)),
+            ("E".to_string(), Value::Number(std::f64::consts::E)),
+            ("floor".to_string(), native("floor", |_, _, args, _| math(args, f64::floor))),
+            ("ceil".to_string(), native("ceil", |_, _, args, _| math(args, f64::ceil))),
+            ("round".to_string(), native("round", |_, _, args, _| math(args, |n| (n + 0.5).floor()))),
+            ("trunc".to_string(), native("trunc", |_, _, args, _| math(args, f64::trunc))),
+            ("abs".to_string(), native("abs", |_, _, args, _| math(args, f64::abs))),
+            ("sqrt".to_string(), native("sqrt", |_, _, args, _| math(args, f64::sqrt))),
+            ("sign".to_string(), native("sign", |_, _, args, _| math(args, |n| if n == 0.0 || n.is_nan() { n } else { n.signum() }))),
+            ("pow".to_string(), native("pow", |_, _, args, _| Ok(Value::Number(arg(&args, 0).to_number().powf(arg(&args, 1).to_number()))))),
+            ("min".to_string(), native("min", |_, _, args, _| Ok(Value::Number(fold_numbers(&args, f64::INFINITY, f64::min))))),
+            ("max".to_string(), native("max", |_, _, args, _| Ok(Value::Number(fold_numbers(&args, f64::NEG_INFINITY, f64::max))))),
+            ("random".to_string(), native("random", |i, _, _, _| Ok(Value::Number(i.next_random())))),
+        ])),
+        ("JSON", Value::object(vec![
+            ("stringify".to_string(), native("stringify", |_, _, args, _| {
+                let indent = match arg(&args, 2) {
+                    Value::Number(n) => " ".repeat(n.clamp(0.0, 10.0) as usize),
+                    Value::String(s) => s.to_string(),
+                    _ => String::new(),
+                };
+                Ok(json(&arg(&args, 0), &indent, 0).map_or(Value::Undefined, Value::string))
+            })),
+        ])),
+        ("Object", Value::object(vec![
+            ("keys".to_string(), native("keys", |_, _, args, _| Ok(entries(&arg(&args, 0), |k, _| Value::string(k))))),
+            ("values".to_string(), native("values", |_, _, args, _| Ok(entries(&arg(&args, 0), |_, v| v)))),
+            ("entries".to_string(), native("entries", |_, _, args, _| Ok(entries(&arg(&args, 0), |k, v| Value::array(vec![Value::string(k), v]))))),
+        ])),
+        ("Array", Value::object(vec![
+            ("isArray".to_string(), native("isArray", |_, _, args, _| Ok(Value::Boolean(matches!(arg(&args, 0), Value::Array(_)))))),
+        ])),
+        ("String", native("String", |i, _, args, span| Ok(Value::string(args.first().map_or(Ok(String::new()), |v| i.to_js_string(v, span))?)))),
+        ("Number", native("Number", |_, _, args, _| Ok(Value::Number(args.first().map_or(0.0, |v| v.to_number()))))),
+        ("Boolean", native("Boolean", |_, _, args, _| Ok(Value::Boolean(arg(&args, 0).is_truthy())))),
+        ("isNaN", native("isNaN", |_, _, args, _| Ok(Value::Boolean(arg(&args, 0).to_number().is_nan())))),
+        ("parseFloat", native("parseFloat", |_, _, args, _| Ok(Value::Number(parse_float(&arg(&args, 0).to_js_string()))))),
+        ("parseInt", native("parseInt", |_, _, args, _| {
+            let radix = match arg(&args, 1) {
+                Value::Undefined => 10,
+                v => v.to_number() as u32,
+            };
+            Ok(Value::Number(parse_int(&arg(&args, 0).to_js_string(), radix)))
+        })),
+        ("Error", native("Error", |_, _, args, _| Ok(make_error("Error", message(&args))))),
+        ("TypeError", native("TypeError", |_, _, args, _| Ok(make_error("TypeError", message(&args))))),
+        ("RangeError", native("RangeError", |_, _, args, _| Ok(make_error("RangeError", message(&args))))),
+    ]
+}
+
+fn console(interpreter: &mut Interpreter, level: ConsoleLevel, args: Vec<Value>) -> Exec<Value> {
+    interpreter.write(level, &args);
+    Ok(Value::Undefined)
+}
+
+fn message(args: &[Value]) -> String {
+    match args.first() {
+        None | Some(Value::Undefined) => String::new(),
+        Some(v) => v.to_js_string(),
+    }
+}
+
+fn math(args: Vec<Value>, f: fn(f64) -> f64) -> Exec<Value> {
+    Ok(Value::Number(f(arg(&args, 0).to_number())))
+}
+
+fn fold_numbers(args: &[Value], init: f64, f: fn(f64, f64) -> f64) -> f64 {
+    args.iter().map(Value::to_number).fold(init, |acc, n| if acc.is_nan() || n.is_nan() { f64::NAN } else { f(acc, n) })
+}
+
+fn entries(value: &Value, f: impl Fn(&str, Value) -> Value) -> Value {
+    match value {
+        Value::Object(obj) => Value::array(obj.borrow().properties.iter().map(|(k, v)| f(k, v.clone())).collect()),
+        Value::Array(items) => Value::array(items.borrow().iter().enumerate().map(|(i, v)| f(&i.to_string(), v.clone())).collect()),
+        _ => Value::array(vec![]),
+    }
+}
+
+fn parse_float(s: &str) -> f64 {
+    let s = s.trim_start();
+    // Longest prefix that parses as a number
+    (1..=s.len()).rev()
+        .filter(|&end| s.is_char_boundary(end))
+        .find_map(|end| s[..end].parse::<f64>().ok().filter(|_| !s[..end].ends_with(['e', 'E', '+', '-'])))
+        .unwrap_or(f64::NAN)
+}
+
+fn parse_int(s: &str, radix: u32) -> f64 {
+    let s = s.trim();
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let radix = if radix == 0 { 10 } else { radix };
+    if !(2..=36).contains(&radix) {
+        return f64::NAN;
+    }
+    let digits: String = digits.chars().take_while(|c| c.is_digit(radix)).collect();
+    if digits.is_empty() {
+        return f64::NAN;
+    }
+    let value = digits.chars().fold(0.0, |acc, c| acc * radix as f64 + c.to_digit(radix).unwrap() as f64);
+    if negative { -value } else { value }
+}
+
+fn json(value: &Value, indent: &str, depth: usize) -> Option<String> {
+    let (open_pad, close_pad, sep) = if indent.is_empty() {
+        (String::new(), String::new(), ":")
+    } else {
+        (format!("\n{}", indent.repeat(depth + 1)), format!("\n{}", indent.repeat(depth)), ": ")
+    };
+    match value {
+        Value::Undefined | Value::Function(_) | Value::Native(_) | Value::Class(_) => None,
+        Value::Null => Some("null".to_string()),
+        Value::Boolean(b) => Some(b.to_string()),
+        Value::Number(n) if n.is_finite() => Some(super::number_to_string(*n)),
+        Value::Number(_) => Some("null".to_string()),
+        Value::String(s) => Some(json_string(s)),
+        Value::Array(items) => {
+            let items = items.borrow();
+            if items.is_empty() {
+                return Some("[]".to_string());
+            }
+            let parts: Vec<String> = items.iter().map(|v| json(v, indent, depth + 1).unwrap_or_else(|| "null".to_string())).collect();
+            Some(format!("[{}{}{}]", open_pad, parts.join(&format!(",{}", open_pad)), close_pad))
+        }
+        Value::Object(obj) => {
+            let obj = obj.borrow();
+            let parts: Vec<String> = obj.properties.iter()
+                .filter_map(|(k, v)| json(v, indent, depth + 1).map(|v| format!("{}{}{}", json_string(k), sep, v)))
+                .collect();
+            if parts.is_empty() {
+                return Some("{}".to_string());
+            }
+            Some(format!("{{{}{}{}}}", open_pad, parts.join(&format!(",{}", open_pad)), close_pad))
+        }
+    }
+}
+
+/// A JSON string literal, escaped as `JSON.stringify` does
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `tutt' quant` and `'o primm che arriva`. Nothing runs in the background
+/// here, so a promise is already its value: `Promise.all` gives the values
+/// back as they are, `Promise.race` the first one.
+pub(crate) fn promise_helper(helper: PromiseHelper) -> Value {
+    match helper {
+        PromiseHelper::All => native("all", |i, _, args, span| Ok(Value::array(this_array(i, &arg(&args, 0), span)?.borrow().clone()))),
+        PromiseHelper::Race => native("race", |i, _, args, span| Ok(this_array(i, &arg(&args, 0), span)?.borrow().first().cloned().unwrap_or(Value::Undefined))),
+    }
+}
+
+// === Array methods ===
+
+fn this_array(interpreter: &Interpreter, this: &Value, span: Span) -> Exec<Rc<std::cell::RefCell<Vec<Value>>>> {
+    match this {
+        Value::Array(items) => Ok(items.clone()),
+        other => Err(interpreter.error("TypeError", format!("{} nun è n'array", super::inspect(other, 1)), span)),
+    }
+}
+
+/// Calls `callback(item, index)` for each item of a snapshot of the array.
+fn each(interpreter: &mut Interpreter, this: &Value, args: &[Value], span: Span, mut f: impl FnMut(Value, Value) -> bool) -> Exec<()> {
+    let items = this_array(interpreter, this, span)?.borrow().clone();
+    let callback = arg(args, 0);
+    for (index, item) in items.into_iter().enumerate() {
+        let result = interpreter.call(&callback, Value::Undefined, vec![item.clone(), Value::Number(index as f64)], span)?;
+        if !f(item, result) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn relative_index(value: &Value, len: usize, default: usize) -> usize {
+    match value {
+        Value::Undefined => default,
+        v => {
+            let n = v.to_number();
+            let n = if n.is_nan() { 0.0 } else { n.trunc() };
+            if n < 0.0 { (len as f64 + n).max(0.0) as usize } else { (n as usize).min(len) }
+        }
+    }
+}
+
+/// Method of an array value, e.g. `push`.
+pub(crate) fn array_method(name: &str) -> Option<Value> {
+    let func: NativeFn = match name {
+        "push" => |i, this, args, span| {
+            let items = this_array(i, &this, span)?;
+            i.check_array_length(items.borrow().len() + args.len(), span)?;
+            items.borrow_mut().extend(args);
+            let len = items.borrow().len();
+            Ok(Value::Number(len as f64))
+        },
+        "pop" => |i, this, _, span| Ok(this_array(i, &this, span)?.borrow_mut().pop().unwrap_or(Value::Undefined)),
+        "shift" => |i, this, _, span| {
+            let items = this_array(i, &this, span)?;
+            let mut items = items.borrow_mut();
+            Ok(if items.is_empty() { Value::Undefined } else { items.remove(0) })
+        },
+        "unshift" => |i, this, args, span| {
+            let items = this_array(i, &this, span)?;
+            i.check_array_length(items.borrow().len() + args.len(), span)?;
+            items.borrow_mut().splice(0..0, args);
+            let len = items.borrow().len();
+            Ok(Value::Number(len as f64))
+        },
+        "join" => |i, this, args, span| {
+            let separator = match arg(&args, 0) {
+                Value::Undefined => ",".to_string(),
+                v => i.to_js_string(&v, span)?,
+            };
+            let items = this_array(i, &this, span)?;
+            // The array written out, with `separator` for the commas
+            let (len, separators) = (Value::Array(items.clone()).js_string_len(i.max_string_length), items.borrow().len().saturating_sub(1));
+            i.check_string_length(len.saturating_add(separators.saturating_mul(separator.len().saturating_sub(1))), span)?;
+            let parts: Vec<String> = items.borrow().iter()
+                .map(|v| if matches!(v, Value::Undefined | Value::Null) { String::new() } else { v.to_js_string() })
+                .collect();
+            Ok(Value::string(parts.join(&separator)))
+        },
+        "includes" => |i, this, args, span| {
+            let needle = arg(&args, 0);
+            let items = this_array(i, &this, span)?;
+            let found = items.borrow().iter().any(|v| v.strict_equals(&needle) || (v.to_number().is_nan() && needle.to_number().is_nan() && matches!((v, &needle), (Value::Number(_), Value::Number(_)))));
+            Ok(Value::Boolean(found))
+        },
+        "indexOf" => |i, this, args, span| {
+            let needle = arg(&args, 0);
+            let items = this_array(i, &this, span)?;
+            let index = items.borrow().iter().position(|v| v.strict_equals(&needle));
+            Ok(Value::Number(index.map_or(-1.0, |i| i as f64)))
+        },
+        "slice" => |i, this, args, span| {
+            let items = this_array(i, &this, span)?;
+            let items = items.borrow();
+            let start = relative_index(&arg(&args, 0), items.len(), 0);
+            let end = relative_index(&arg(&args, 1), items.len(), items.len());
+            Ok(Value::array(if start < end { items[start..end].to_vec() } else { vec![] }))
+        },
+        "concat" => |i, this, args, span| {
+            let more = args.iter().map(|value| if let Value::Array(more) = value { more.borrow().len() } else { 1 }).fold(0usize, usize::saturating_add);
+            i.check_array_length(this_array(i, &this, span)?.borrow().len().saturating_add(more), span)?;
+            let mut result = this_array(i, &this, span)?.borrow().clone();
+            for value in args {
+                match value {
+                    Value::Array(more) => result.extend(more.borrow().iter().cloned()),
+                    other => result.push(other),
+                }
+            }
+            Ok(Value::array(result))
+        },
+        "reverse" => |i, this, _, span| {
+            this_array(i, &this, span)?.borrow_mut().reverse();
+            Ok(this)
+        },
+        "forEach" => |i, this, args, span| {
+            each(i, &this, &args, span, |_, _| true)?;
+            Ok(Value::Undefined)
+        },
+        "map" => |i, this, args, span| {
+            let mut result = Vec::new();
+            each(i, &this, &args, span, |_, mapped| { result.push(mapped); true })?;
+            Ok(Value::array(result))
+        },
+        "filter" => |i, this, args, span| {
+            let mut result = Vec::new();
+            each(i, &this, &args, span, |item, keep| { if keep.is_truthy() { result.push(item); } true })?;
+            Ok(Value::array(result))
+        },
+        "find" => |i, this, args, span| {
+            let mut found = Value::Undefined;
+            each(i, &this, &args, span, |item, matched| if matched.is_truthy() { found = item; false } else { true })?;
+            Ok(found)
+        },
+        "findIndex" => |i, this, args, span| {
+            let (mut index, mut found) = (0, -1.0);
+            each(i, &this, &args, span, |_, matched| if matched.is_truthy() { found = index as f64; false } else { index += 1; true })?;
+            Ok(Value::Number(found))
+        },
+        "some" => |i, this, args, span| {
+            let mut any = false;
+            each(i, &this, &args, span, |_, matched| { any = matched.is_truthy(); !any })?;
+            Ok(Value::Boolean(any))
+        },
+        "every" => |i, this, args, span| {
+            let mut all = true;
+            each(i, &this, &args, span, |_, matched| { all = matched.is_truthy(); all })?;
+            Ok(Value::Boolean(all))
+        },
+        "reduce" => |i, this, args, span| {
+            let items = this_array(i, &this, span)?.borrow().clone();
+            let callback = arg(&args, 0);
+            let mut items = items.into_iter().enumerate();
+            let mut acc = match args.get(1) {
+                Some(initial) => initial.clone(),
+                None => match items.next() {
+                    Some((_, first)) => first,
+                    None => return Err(i.error("TypeError", "'reduce' 'ncopp'a n'array vacante senza valore iniziale", span)),
+                },
+            };
+            for (index, item) in items {
+                acc = i.call(&callback, Value::Undefined, vec![acc, item, Value::Number(index as f64)], span)?;
+            }
+            Ok(acc)
+        },
+        "sort" => |i, this, args, span| {
+            let items = this_array(i, &this, span)?;
+            let values = items.borrow().clone();
+            let comparator = arg(&args, 0);
+            let sorted = merge_sort(values, &mut |a, b| match &comparator {
+                Value::Undefined => Ok(a.to_js_string().cmp(&b.to_js_string())),
+                f => {
+                    let result = i.call(f, Value::Undefined, vec![a.clone(), b.clone()], span)?.to_number();
+                    Ok(result.partial_cmp(&0.0).unwrap_or(Ordering::Equal))
+                }
+            })?;
+            *items.borrow_mut() = sorted;
+            Ok(this)
+        },
+        _ => return None,
+    };
+    Some(native(static_name(name), func))
+}
+
+/// Stable sort with a comparator that can throw.
+fn merge_sort(mut values: Vec<Value>, compare: &mut dyn FnMut(&Value, &Value) -> Exec<Ordering>) -> Exec<Vec<Value>> {
+    if values.len() <= 1 {
+        return Ok(values);
+    }
+    let right = values.split_off(values.len() / 2);
+    let left = merge_sort(values, compare)?;
+    let right = merge_sort(right, compare)?;
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        if compare(a, b)? == Ordering::Greater {
+            merged.push(right.next().unwrap());
+        } else {
+            merged.push(left.next().unwrap());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+// === String methods ===
+
+fn this_string(this: &Value) -> String {
+    this.to_js_string()
+}
+
+/// Method of a string value, e.g. `toUpperCase`.
+pub(crate) fn string_method(name: &str) -> Option<Value> {
+    let func: NativeFn = match name {
+        "toUpperCase" => |_, this, _, _| Ok(Value::string(this_string(&this).to_uppercase())),
+        "toLowerCase" => |_, this, _, _| Ok(Value::string(this_string(&this).to_lowercase())),
+        "trim" => |_, this, _, _| Ok(Value::string(this_string(&this).trim())),
+        "includes" => |_, this, args, _| Ok(Value::Boolean(this_string(&this).contains(&arg(&args, 0).to_js_string()))),
+        "startsWith" => |_, this, args, _| Ok(Value::Boolean(this_string(&this).starts_with(&arg(&args, 0).to_js_string()))),
+        "endsWith" => |_, this, args, _| Ok(Value::Boolean(this_string(&this).ends_with(&arg(&args, 0).to_js_string()))),
+        "indexOf" => |_, this, args, _| {
+            let s = this_string(&this);
+            let index = s.find(&arg(&args, 0).to_js_string()).map_or(-1.0, |byte| s[..byte].chars().count() as f64);
+            Ok(Value::Number(index))
+        },
+        "charAt" => |_, this, args, _| {
+            let index = arg(&args, 0).to_number();
+            let index = if index.is_nan() { 0 } else { index as usize };
+            Ok(Value::string(this_string(&this).chars().nth(index).map_or(String::new(), String::from)))
+        },
+        "slice" | "substring" => |_, this, args, _| {
+            let chars: Vec<char> = this_string(&this).chars().collect();
+            let start = relative_index(&arg(&args, 0), chars.len(), 0);
+            let end = relative_index(&arg(&args, 1), chars.len(), chars.len());
+            Ok(Value::string(if start < end { chars[start..end].iter().collect::<String>() } else { String::new() }))
+        },
+        "split" => |_, this, args, _| {
+            let s = this_string(&this);
+            let parts: Vec<Value> = match arg(&args, 0) {
+                Value::Undefined => vec![Value::string(s)],
+                separator => {
+                    let separator = separator.to_js_string();
+                    if separator.is_empty() {
+                        s.chars().map(|c| Value::string(c.to_string())).collect()
+                    } else {
+                        s.split(separator.as_str()).map(Value::string).collect()
+                    }
+                }
+            };
+            Ok(Value::array(parts))
+        },
+        "replace" => |i, this, args, span| {
+            let (s, pattern, replacement) = (this_string(&this), i.to_js_string(&arg(&args, 0), span)?, i.to_js_string(&arg(&args, 1), span)?);
+            i.check_string_length(s.len().saturating_add(replacement.len()), span)?;
+            Ok(Value::string(s.replacen(&pattern, &replacement, 1)))
+        },
+        "replaceAll" => |i, this, args, span| {
+            let (s, pattern, replacement) = (this_string(&this), i.to_js_string(&arg(&args, 0), span)?, i.to_js_string(&arg(&args, 1), span)?);
+            let matches = if pattern.is_empty() { s.chars().count() + 1 } else { s.matches(pattern.as_str()).count() };
+            i.check_string_length(s.len().saturating_add(matches.saturating_mul(replacement.len())), span)?;
+            Ok(Value::string(s.replace(&pattern, &replacement)))
+        },
+        "repeat" => |i, this, args, span| {
+            let count = arg(&args, 0).to_number();
+            if count < 0.0 || count.is_infinite() {
+                return Err(i.error("RangeError", "Nun se pò ripetere nu nummero negativo 'e vote", span));
+            }
+            let (s, count) = (this_string(&this), if count.is_nan() { 0 } else { count as usize });
+            i.check_string_length(s.len().saturating_mul(count), span)?;
+            Ok(Value::string(s.repeat(count)))
+        },
+        "padStart" => |i, this, args, span| pad(i, &this, &args, false, span),
+        "padEnd" => |i, this, args, span| pad(i, &this, &args, true, span),
+        _ => return None,
+    };
+    Some(native(static_name(name), func))
+}
+
+fn pad(interpreter: &Interpreter, this: &Value, args: &[Value], at_end: bool, span: Span) -> Exec<Value> {
+    let s = this_string(this);
+    let width = arg(args, 0).to_number();
+    let fill = match arg(args, 1) {
+        Value::Undefined => " ".to_string(),
+        v => interpreter.to_js_string(&v, span)?,
+    };
+    let missing = (if width.is_nan() { 0 } else { width as usize }).saturating_sub(s.chars().count());
+    if missing == 0 || fill.is_empty() {
+        return Ok(Value::string(s));
+    }
+    let fill_chars = fill.chars().count();
+    let padding_len = (missing / fill_chars).saturating_mul(fill.len()) + fill.chars().take(missing % fill_chars).map(char::len_utf8).sum::<usize>();
+    interpreter.check_string_length(s.len().saturating_add(padding_len), span)?;
+    let padding: String = fill.chars().cycle().take(missing).collect();
+    Ok(Value::string(if at_end { s + &padding } else { padding + &s }))
+}
+
+// === Number methods ===
+
+/// Method of a number value, e.g. `toFixed`.
+pub(crate) fn number_method(name: &str) -> Option<Value> {
+    let func: NativeFn = match name {
+        "toFixed" => |i, this, args, span| {
+            let digits = arg(&args, 0).to_number();
+            let digits = if digits.is_nan() { 0.0 } else { digits };
+            if !(0.0..=100.0).contains(&digits) {
+                return Err(i.error("RangeError", "'toFixed' vo' tra 0 e 100 cifre", span));
+            }
+            Ok(Value::string(format!("{:.*}", digits as usize, this.to_number())))
+        },
+        "toString" => |_, this, _, _| Ok(Value::string(this.to_js_string())),
+        _ => return None,
+    };
+    Some(native(static_name(name), func))
+}
+
+/// Names of the builtin methods, so `NativeFunction::name` can stay `&'static str`.
+fn static_name(name: &str) -> &'static str {
+    const NAMES: &[&str] = &[
+        "push", "pop", "shift", "unshift", "join", "includes", "indexOf", "slice", "concat", "reverse",
+        "forEach", "map", "filter", "find", "findIndex", "some", "every", "reduce", "sort",
+        "toUpperCase", "toLowerCase", "trim", "startsWith", "endsWith", "charAt", "substring",
+        "split", "replace", "replaceAll", "repeat", "padStart", "padEnd", "toFixed", "toString",
+    ];
+    NAMES.iter().find(|n| **n == name).copied().unwrap_or("anonymous")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str) -> String {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval(source).unwrap();
+        interpreter.output().iter().map(|line| line.text.clone()).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn test_array_methods() {
+        assert_eq!(run("stamm a dì([3, 1, 2].sort().join(\"-\"))"), "1-2-3");
+        assert_eq!(run("facc somma(a, b) { piglie a + b }\nstamm a dì([1, 2, 3, 4].filter((x) => x % 2 == 0).reduce(somma, 0))"), "6");
+        assert_eq!(run("facc crescente(a, b) { piglie a - b }\nstamm a dì([10, 9, 100].sort(crescente))"), "[ 9, 10, 100 ]");
+    }
+
+    #[test]
+    fn test_string_and_number_methods() {
+        assert_eq!(run("stamm a dì(\"sfòls\".toUpperCase(), \"a,b\".split(\",\").length, (3.14159).toFixed(2))"), "SFÒLS 2 3.14");
+        assert_eq!(run("stamm a dì(\"7\".padStart(3, \"0\"), \"7\".padEnd(3, \"!\"))"), "007 7!!");
+    }
+
+    #[test]
+    fn test_globals() {
+        assert_eq!(run("stamm a dì(Math.max(1, 5, 3), parseInt(\"42px\"), JSON.stringify({ a: [1, \"b\"] }))"), "5 42 {\"a\":[1,\"b\"]}");
+        assert_eq!(run("stamm a dì(Object.keys({ a: 1, b: 2 }))"), "[ 'a', 'b' ]");
+    }
+}
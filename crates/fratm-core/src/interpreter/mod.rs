@@ -0,0 +1,1192 @@
+//! # FratmScript Interpreter
+//!
+//! A tree-walking interpreter that runs a [`Program`] directly, without
+//! going through JavaScript. It powers the playground's "Run" button, where
+//! evaluating generated JS in the host page isn't an option.
+//!
+//! Values follow JavaScript semantics (see [`Value`]), and the most common
+//! builtins are available: `Math`, `JSON.stringify`, `Object.keys`, array and
+//! string methods, `Error`, `parseInt`, ...
+//!
+//! ## Differences from Node
+//!
+//! - `mo vir` functions run synchronously and `aspett` returns its operand
+//!   unchanged; there is no event loop, `fetch` or timers
+//! - `chiamm` (import) is not supported
+//! - programs are stopped after [`Interpreter::with_max_steps`] steps, so an
+//!   endless `mentre che` can't freeze the page
+//!
+//! ## Example
+//!
+//! ```rust
+//! use fratm_core::interpreter::Interpreter;
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.eval("tien x = 20\nstamm a dì(x * 2 + 2)").unwrap();
+//! assert_eq!(interpreter.output()[0].text, "42");
+//! ```
+//!
+//! Bindings survive between calls to [`Interpreter::eval`], so the same
+//! interpreter can back a REPL.
+
+mod builtins;
+mod value;
+
+pub use value::{display, inspect, number_to_string, Class, Function, FunctionBody, NativeFunction, Object, Value};
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::rc::Rc;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+
+use crate::errors::CompileError;
+use crate::lexer::keywords::class_name;
+use crate::lexer::Span;
+use crate::parser::*;
+use value::{Binding, Env, Scope};
+
+/// Default limit on evaluation steps, see [`Interpreter::with_max_steps`].
+pub const DEFAULT_MAX_STEPS: u64 = 10_000_000;
+
+/// Nested calls allowed before a `RangeError`.
+const MAX_CALL_DEPTH: usize = 200;
+
+/// Longest string, see [`Interpreter::with_max_string_length`]: V8's, in
+/// UTF-16 units. It is checked against bytes, which are never fewer, so a
+/// string too long for Node is too long here as well.
+pub const MAX_STRING_LENGTH: usize = (1 << 29) - 24;
+
+/// Longest array, see [`Interpreter::with_max_array_length`]: JavaScript's.
+pub const MAX_ARRAY_LENGTH: usize = u32::MAX as usize;
+
+/// Console method that produced a line of output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ConsoleLevel {
+    /// `stamm a dì`
+    Log,
+    /// `avvis a dì`
+    Warn,
+    /// `scrive a dì`
+    Error,
+}
+
+/// One line written to the console by the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ConsoleLine {
+    pub level: ConsoleLevel,
+    pub text: String,
+}
+
+/// An error that stopped the program: an uncaught `iett`, a runtime
+/// `TypeError`/`ReferenceError`, or the step limit.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("Riga {}, colonna {}: {message}", .span.line, .span.column)]
+pub struct RuntimeError {
+    pub message: String,
+    pub span: Span,
+}
+
+/// Anything that can go wrong in [`Interpreter::eval`].
+#[derive(Debug, Clone, Error)]
+pub enum EvalError {
+    #[error(transparent)]
+    Compile(#[from] CompileError),
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+}
+
+impl EvalError {
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            EvalError::Compile(e) => e.line(),
+            EvalError::Runtime(e) => Some(e.span.line),
+        }
+    }
+
+    pub fn column(&self) -> Option<usize> {
+        match self {
+            EvalError::Compile(e) => e.column(),
+            EvalError::Runtime(e) => Some(e.span.column),
+        }
+    }
+}
+
+/// Non-local control flow while evaluating.
+pub(crate) enum Interrupt {
+    Return(Value),
+    Break,
+    Continue,
+    /// A thrown value, catchable with `pruvamm`
+    Throw(Value, Span),
+    /// Uncatchable: the step limit was hit
+    Halt(RuntimeError),
+}
+
+type Exec<T> = Result<T, Interrupt>;
+
+/// Tree-walking interpreter with persistent global bindings.
+pub struct Interpreter {
+    globals: Env,
+    output: Vec<ConsoleLine>,
+    steps: u64,
+    max_steps: u64,
+    max_string_length: usize,
+    max_array_length: usize,
+    depth: usize,
+    rng_state: u64,
+    defines: BTreeMap<String, String>,
+    /// Parents of the classes whose constructor is running, for `pate(...)`
+    parents: Vec<Value>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Interpreter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interpreter").field("output", &self.output).finish_non_exhaustive()
+    }
+}
+
+impl Interpreter {
+    /// Creates an interpreter with the builtins in scope.
+    pub fn new() -> Self {
+        let globals: Env = Rc::new(RefCell::new(Scope::default()));
+        for (name, value) in builtins::globals() {
+            globals.borrow_mut().vars.insert(name.into(), Binding { value, is_const: true });
+        }
+        Self { globals, output: Vec::new(), steps: 0, max_steps: DEFAULT_MAX_STEPS, max_string_length: MAX_STRING_LENGTH, max_array_length: MAX_ARRAY_LENGTH, depth: 0, rng_state: 0x2545_F491_4F6C_DD1D, defines: BTreeMap::new(), parents: Vec::new() }
+    }
+
+    /// Sets how many steps (statements and expressions) a single
+    /// [`eval`](Self::eval) may take before it's stopped.
+    pub fn with_max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Sets the bytes a string may have before building it throws a
+    /// `RangeError`. The steps don't bound memory: `s = s + s` doubles it
+    /// each time, so a host short of it sets less than [`MAX_STRING_LENGTH`].
+    pub fn with_max_string_length(mut self, max_string_length: usize) -> Self {
+        self.max_string_length = max_string_length;
+        self
+    }
+
+    /// Sets the items an array may have, as [`with_max_string_length`](Self::with_max_string_length) does for strings.
+    pub fn with_max_array_length(mut self, max_array_length: usize) -> Self {
+        self.max_array_length = max_array_length;
+        self
+    }
+
+    /// Names for `#si` directives, which choose the branches that run, as
+    /// [`CompileOptions::defines`](crate::CompileOptions::defines) does for the compiler.
+    pub fn with_defines(mut self, defines: BTreeMap<String, String>) -> Self {
+        self.defines = defines;
+        self
+    }
+
+    /// Console output produced so far.
+    pub fn output(&self) -> &[ConsoleLine] {
+        &self.output
+    }
+
+    /// Takes the console output produced so far.
+    pub fn take_output(&mut self) -> Vec<ConsoleLine> {
+        std::mem::take(&mut self.output)
+    }
+
+    /// Parses and runs `source`. Returns the value of the last expression
+    /// statement (or `boh`), which is what a REPL prints.
+    pub fn eval(&mut self, source: &str) -> Result<Value, EvalError> {
+        let (program, _) = crate::parse_program(source)?;
+        Ok(self.run(&program)?)
+    }
+
+    /// Runs an already parsed program in the global scope.
+    pub fn run(&mut self, program: &Program) -> Result<Value, RuntimeError> {
+        self.steps = 0;
+        self.depth = 0;
+        let globals = self.globals.clone();
+        self.hoist(&program.statements, &globals);
+
+        let mut last = Value::Undefined;
+        for stmt in &program.statements {
+            let result = match stmt {
+                Statement::Expression { expression, .. } => self.eval_expr(expression, &globals),
+                _ => self.exec(stmt, &globals).map(|_| Value::Undefined),
+            };
+            match result {
+                Ok(value) => last = value,
+                Err(Interrupt::Throw(value, span)) => {
+                    return Err(RuntimeError { message: format!("Uncaught {}", inspect(&value, 1)), span });
+                }
+                Err(Interrupt::Halt(error)) => return Err(error),
+                Err(Interrupt::Return(_)) => return Err(RuntimeError { message: "'piglie' fora 'e na funzione".to_string(), span: stmt.span() }),
+                Err(Interrupt::Break | Interrupt::Continue) => {
+                    return Err(RuntimeError { message: "'rompe'/'salta' fora 'e nu ciclo".to_string(), span: stmt.span() });
+                }
+            }
+        }
+        Ok(last)
+    }
+
+    // === Errors ===
+
+    /// Builds a thrown JavaScript error (`TypeError`, `ReferenceError`, ...).
+    pub(crate) fn error(&self, name: &str, message: impl Into<String>, span: Span) -> Interrupt {
+        Interrupt::Throw(builtins::make_error(name, message.into()), span)
+    }
+
+    /// `Err` with a `RangeError` if a string of `len` bytes is too long, as
+    /// checked before building it
+    pub(crate) fn check_string_length(&self, len: usize, span: Span) -> Exec<()> {
+        if len > self.max_string_length {
+            return Err(self.error("RangeError", "'Sta stringa è troppo longa (Invalid string length)", span));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_array_length(&self, len: usize, span: Span) -> Exec<()> {
+        if len > self.max_array_length {
+            return Err(self.error("RangeError", "'St'array è troppo luongo (Invalid array length)", span));
+        }
+        Ok(())
+    }
+
+    /// [`Value::to_js_string`], unless the string would be too long
+    pub(crate) fn to_js_string(&self, value: &Value, span: Span) -> Exec<String> {
+        self.check_string_length(value.js_string_len(self.max_string_length), span)?;
+        Ok(value.to_js_string())
+    }
+
+    fn step(&mut self, span: Span) -> Exec<()> {
+        self.steps += 1;
+        if self.steps > self.max_steps {
+            return Err(Interrupt::Halt(RuntimeError {
+                message: format!("'O programma ha fatto cchiù 'e {} passi: forse nu ciclo ca nun fernesce maje?", self.max_steps),
+                span,
+            }));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn next_random(&mut self) -> f64 {
+        // xorshift64*: good enough for Math.random in a playground
+        self.rng_state ^= self.rng_state >> 12;
+        self.rng_state ^= self.rng_state << 25;
+        self.rng_state ^= self.rng_state >> 27;
+        (self.rng_state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // === Scopes ===
+
+    /// Declares functions and classes before the block runs, like JS hoisting.
+    fn hoist(&mut self, statements: &[Statement], env: &Env) {
+        for stmt in statements {
+            let decl = match stmt {
+                Statement::Export { declaration: Some(decl), .. } => decl.as_ref(),
+                Statement::Conditional { .. } => {
+                    if let Some(branch) = self.branch(stmt) { self.hoist(branch, env); }
+                    continue;
+                }
+                other => other,
+            };
+            if let Statement::FunctionDecl { name, params, body, .. } = decl {
+                let function = self.make_function(name, params, FunctionBody::Block(body.clone()), env, false);
+                declare(env, name, function, false);
+            }
+        }
+    }
+
+    /// The statements of the branch a `#si` runs, in the scope around it
+    fn branch<'s>(&self, stmt: &'s Statement) -> Option<&'s [Statement]> {
+        let Statement::Conditional { condition, then_branch, else_branch, .. } = stmt else { return None };
+        if condition.holds(&self.defines) { Some(then_branch) } else { else_branch.as_deref() }
+    }
+
+    fn make_function(&self, name: &str, params: &[Name], body: FunctionBody, env: &Env, is_arrow: bool) -> Value {
+        Value::Function(Rc::new(Function {
+            name: name.into(),
+            params: params.to_vec(),
+            body,
+            closure: env.clone(),
+            is_arrow,
+        }))
+    }
+
+    fn lookup(&self, name: &str, env: &Env, span: Span) -> Exec<Value> {
+        let mut scope = Some(env.clone());
+        while let Some(current) = scope {
+            if let Some(binding) = current.borrow().vars.get(name) {
+                return Ok(binding.value.clone());
+            }
+            scope = current.borrow().parent.clone();
+        }
+        Err(self.error("ReferenceError", format!("'{}' nun è stat dichiarat", name), span))
+    }
+
+    fn assign(&self, name: &str, value: Value, env: &Env, span: Span) -> Exec<()> {
+        let mut scope = Some(env.clone());
+        while let Some(current) = scope {
+            if let Some(binding) = current.borrow_mut().vars.get_mut(name) {
+                if binding.is_const {
+                    return Err(self.error("TypeError", format!("'{}' è 'na costante, nun se pò cagnà", name), span));
+                }
+                binding.value = value;
+                return Ok(());
+            }
+            scope = current.borrow().parent.clone();
+        }
+        Err(self.error("ReferenceError", format!("'{}' nun è stat dichiarat", name), span))
+    }
+
+    fn this_value(env: &Env) -> Value {
+        let mut scope = Some(env.clone());
+        while let Some(current) = scope {
+            if let Some(this) = &current.borrow().this {
+                return this.clone();
+            }
+            scope = current.borrow().parent.clone();
+        }
+        Value::Undefined
+    }
+
+    // === Statements ===
+
+    fn exec_block(&mut self, statements: &[Statement], env: &Env) -> Exec<()> {
+        let scope = Scope::child(env);
+        self.hoist(statements, &scope);
+        for stmt in statements {
+            self.exec(stmt, &scope)?;
+        }
+        Ok(())
+    }
+
+    fn exec(&mut self, stmt: &Statement, env: &Env) -> Exec<()> {
+        self.step(stmt.span())?;
+        match stmt {
+            Statement::VariableDecl { name, value, is_const, .. } => {
+                let value = match value {
+                    Some(expr) => self.eval_expr(expr, env)?,
+                    None => Value::Undefined,
+                };
+                declare(env, name, value, *is_const);
+            }
+            // Already declared by `hoist`
+            Statement::FunctionDecl { .. } => {}
+            Statement::Return { value, .. } => {
+                let value = match value {
+                    Some(expr) => self.eval_expr(expr, env)?,
+                    None => Value::Undefined,
+                };
+                return Err(Interrupt::Return(value));
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                if self.eval_expr(condition, env)?.is_truthy() {
+                    self.exec_block(then_branch, env)?;
+                } else if let Some(else_branch) = else_branch {
+                    self.exec_block(else_branch, env)?;
+                }
+            }
+            Statement::While { condition, body, span } => {
+                while self.eval_expr(condition, env)?.is_truthy() {
+                    self.step(*span)?;
+                    match self.exec_block(body, env) {
+                        Ok(()) | Err(Interrupt::Continue) => {}
+                        Err(Interrupt::Break) => break,
+                        Err(other) => return Err(other),
+                    }
+                }
+            }
+            Statement::For { init, condition, update, body, span } => {
+                let scope = Scope::child(env);
+                if let Some(init) = init {
+                    self.exec(init, &scope)?;
+                }
+                loop {
+                    if let Some(condition) = condition {
+                        if !self.eval_expr(condition, &scope)?.is_truthy() {
+                            break;
+                        }
+                    }
+                    self.step(*span)?;
+                    match self.exec_block(body, &scope) {
+                        Ok(()) | Err(Interrupt::Continue) => {}
+                        Err(Interrupt::Break) => break,
+                        Err(other) => return Err(other),
+                    }
+                    if let Some(update) = update {
+                        self.eval_expr(update, &scope)?;
+                    }
+                }
+            }
+            Statement::Break { .. } => return Err(Interrupt::Break),
+            Statement::Continue { .. } => return Err(Interrupt::Continue),
+            Statement::Debugger { .. } => {}
+            Statement::Conditional { .. } => {
+                for stmt in self.branch(stmt).unwrap_or_default() {
+                    self.exec(stmt, env)?;
+                }
+            }
+            Statement::Error { span } => return Err(self.error("SyntaxError", "Ccà ce sta codice ca nun se capisce", *span)),
+            Statement::TryCatch { try_body, catch_param, catch_type, catch_body, .. } => {
+                match self.exec_block(try_body, env) {
+                    Err(Interrupt::Throw(value, span)) => {
+                        if let Some(catch_type) = catch_type {
+                            let class = self.class_reference(catch_type, env)?;
+                            if !instance_of(&value, &class) {
+                                return Err(Interrupt::Throw(value, span));
+                            }
+                        }
+                        let scope = Scope::child(env);
+                        if let Some(param) = catch_param {
+                            declare(&scope, param, value, false);
+                        }
+                        self.exec_block(catch_body, &scope)?;
+                    }
+                    other => other?,
+                }
+            }
+            Statement::Throw { value, span } => {
+                let value = self.eval_expr(value, env)?;
+                return Err(Interrupt::Throw(value, *span));
+            }
+            Statement::Assert { condition, message, span } => {
+                if !self.eval_expr(condition, env)?.is_truthy() {
+                    let message = match message {
+                        Some(message) => self.eval_expr(message, env)?.to_js_string(),
+                        None => crate::errors::assertion_failed(span.line),
+                    };
+                    return Err(self.error("Error", message, *span));
+                }
+            }
+            Statement::ClassDecl { name, decorators, superclass, methods, .. } => {
+                if let Some(decorator) = decorators.iter().chain(methods.iter().flat_map(method_decorators)).next() {
+                    return Err(self.error("SyntaxError", "'E decoratori hanna essere compilati: ccà nun se ponno fà correre", decorator.span()));
+                }
+                let mut constructor = None;
+                let mut class_methods = HashMap::new();
+                for method in methods {
+                    if let Statement::FunctionDecl { name: method_name, params, body, .. } = method {
+                        let function = Rc::new(Function {
+                            name: method_name.clone(),
+                            params: params.clone(),
+                            body: FunctionBody::Block(body.clone()),
+                            closure: env.clone(),
+                            is_arrow: false,
+                        });
+                        if &**method_name == "costruttore" || &**method_name == "constructor" {
+                            constructor = Some(function);
+                        } else {
+                            class_methods.insert(method_name.clone(), function);
+                        }
+                    }
+                }
+                let superclass = match superclass {
+                    Some(superclass) => Some(self.class_reference(superclass, env)?),
+                    None => None,
+                };
+                let class = Class { name: name.clone(), constructor, methods: class_methods, superclass };
+                declare(env, name, Value::Class(Rc::new(class)), false);
+            }
+            Statement::Import { span, .. } => {
+                return Err(self.error("Error", "'chiamm' nun se pò usà ccà: 'o programma adda sta tutto int'a nu file", *span));
+            }
+            Statement::Export { declaration, default_value, .. } => {
+                if let Some(decl) = declaration {
+                    self.exec(decl, env)?;
+                }
+                if let Some(value) = default_value {
+                    self.eval_expr(value, env)?;
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                self.eval_expr(expression, env)?;
+            }
+            Statement::Block { statements, .. } => self.exec_block(statements, env)?,
+        }
+        Ok(())
+    }
+
+    // === Expressions ===
+
+    fn eval_expr(&mut self, expr: &Expression, env: &Env) -> Exec<Value> {
+        self.step(expr.span())?;
+        match expr {
+            Expression::Identifier { name, span } => self.lookup(name, env, *span),
+            Expression::Number { value, .. } => Ok(Value::Number(*value)),
+            Expression::String { value, .. } => Ok(Value::string(value.as_str())),
+            Expression::Boolean { value, .. } => Ok(Value::Boolean(*value)),
+            Expression::Null { .. } => Ok(Value::Null),
+            Expression::Undefined { .. } => Ok(Value::Undefined),
+            Expression::This { .. } => Ok(Self::this_value(env)),
+            Expression::SuperCall { arguments, span } => {
+                let args = arguments.iter().map(|a| self.eval_expr(a, env)).collect::<Exec<Vec<_>>>()?;
+                let parent = self.parents.last().cloned().unwrap_or(Value::Undefined);
+                // `stu cos` is still empty: it becomes the parent's instance, keeping its class
+                if let (Value::Object(built), Value::Object(this)) = (self.construct(&parent, args, *span)?, Self::this_value(env)) {
+                    let built = std::mem::take(&mut *built.borrow_mut());
+                    let mut this = this.borrow_mut();
+                    *this = Object { class: this.class.take(), ..built };
+                }
+                Ok(Value::Undefined)
+            }
+            Expression::Error { span } => Err(self.error("SyntaxError", "Ccà ce sta codice ca nun se capisce", *span)),
+            Expression::JsxElement { span, .. } => Err(self.error("SyntaxError", "'O JSX adda essere compilato pe React: ccà nun se pò fà correre", *span)),
+            Expression::Template { quasis, expressions, span } => {
+                let mut text = quasis[0].clone();
+                for (expression, quasi) in expressions.iter().zip(&quasis[1..]) {
+                    let value = self.eval_expr(expression, env)?;
+                    let value = self.to_js_string(&value, expression.span())?;
+                    self.check_string_length(text.len() + value.len() + quasi.len(), *span)?;
+                    text += &value;
+                    text += quasi;
+                }
+                Ok(Value::string(text))
+            }
+            Expression::Array { elements, .. } => {
+                let values = elements.iter().map(|e| self.eval_expr(e, env)).collect::<Exec<Vec<_>>>()?;
+                Ok(Value::array(values))
+            }
+            Expression::Object { properties, .. } => {
+                let mut object = Object::default();
+                for (key, value) in properties {
+                    let value = self.eval_expr(value, env)?;
+                    object.set(key, value);
+                }
+                Ok(Value::Object(Rc::new(RefCell::new(object))))
+            }
+            Expression::Binary { left, operator: BinaryOp::And, right, .. } => {
+                let left = self.eval_expr(left, env)?;
+                if left.is_truthy() { self.eval_expr(right, env) } else { Ok(left) }
+            }
+            Expression::Binary { left, operator: BinaryOp::Or, right, .. } => {
+                let left = self.eval_expr(left, env)?;
+                if left.is_truthy() { Ok(left) } else { self.eval_expr(right, env) }
+            }
+            Expression::Binary { left, operator: BinaryOp::Nullish, right, .. } => match self.eval_optional(left, env)? {
+                Some(Value::Undefined | Value::Null) | None => self.eval_expr(right, env),
+                Some(left) => Ok(left),
+            },
+            Expression::Binary { left, operator, right, span } => {
+                let left = self.eval_expr(left, env)?;
+                let right = self.eval_expr(right, env)?;
+                // Only strings and arrays can be long written out
+                if *operator == BinaryOp::Add && [&left, &right].iter().any(|v| matches!(v, Value::String(_) | Value::Array(_))) {
+                    let max = self.max_string_length;
+                    self.check_string_length(left.js_string_len(max).saturating_add(right.js_string_len(max)), *span)?;
+                }
+                Ok(binary(*operator, &left, &right))
+            }
+            Expression::Unary { operator, operand, .. } => {
+                let value = self.eval_expr(operand, env)?;
+                Ok(match operator {
+                    UnaryOp::Negate => Value::Number(-value.to_number()),
+                    UnaryOp::Not => Value::Boolean(!value.is_truthy()),
+                })
+            }
+            Expression::Assignment { target, value, span } => {
+                let value = self.eval_expr(value, env)?;
+                match target.as_ref() {
+                    Expression::Identifier { name, .. } => self.assign(name, value.clone(), env, *span)?,
+                    Expression::Member { object, property, computed, .. } => {
+                        let object = self.eval_expr(object, env)?;
+                        let key = self.property_key(property, *computed, env)?;
+                        self.set_property(&object, &key, value.clone(), *span)?;
+                    }
+                    _ => return Err(self.error("SyntaxError", "Nun se pò assegnà a chesta cosa", *span)),
+                }
+                Ok(value)
+            }
+            Expression::Call { callee, arguments, span } => {
+                let (function, this) = match callee.as_ref() {
+                    Expression::Member { object, property, computed, .. } => {
+                        let object = self.eval_expr(object, env)?;
+                        let key = self.property_key(property, *computed, env)?;
+                        (self.get_property(&object, &key, *span)?, object)
+                    }
+                    other => (self.eval_expr(other, env)?, Value::Undefined),
+                };
+                let args = arguments.iter().map(|a| self.eval_expr(a, env)).collect::<Exec<Vec<_>>>()?;
+                self.call(&function, this, args, *span)
+            }
+            Expression::Member { object, property, computed, span } => {
+                let object = self.eval_expr(object, env)?;
+                let key = self.property_key(property, *computed, env)?;
+                self.get_property(&object, &key, *span)
+            }
+            Expression::New { callee, arguments, span } => {
+                let class = self.eval_expr(callee, env)?;
+                let args = arguments.iter().map(|a| self.eval_expr(a, env)).collect::<Exec<Vec<_>>>()?;
+                self.construct(&class, args, *span)
+            }
+            Expression::ArrowFunction { params, body, .. } => {
+                let body = match body {
+                    ArrowBody::Expression(e) => FunctionBody::Expression(e.as_ref().clone()),
+                    ArrowBody::Block(stmts) => FunctionBody::Block(stmts.clone()),
+                };
+                Ok(self.make_function("", params, body, env, true))
+            }
+            // No event loop: awaiting a value just yields it
+            Expression::Await { argument, .. } => self.eval_expr(argument, env),
+            Expression::Ternary { condition, consequent, alternate, .. } => {
+                if self.eval_expr(condition, env)?.is_truthy() {
+                    self.eval_expr(consequent, env)
+                } else {
+                    self.eval_expr(alternate, env)
+                }
+            }
+            // No arm matching gives `boh`, like the compiled code
+            Expression::Match { subject, arms, .. } => {
+                let value = self.eval_expr(subject, env)?;
+                for arm in arms {
+                    let scope = Scope::child(env);
+                    if !match_pattern(&arm.pattern, &value, &scope) {
+                        continue;
+                    }
+                    if let Some(guard) = &arm.guard {
+                        if !self.eval_expr(guard, &scope)?.is_truthy() {
+                            continue;
+                        }
+                    }
+                    return self.eval_expr(&arm.body, &scope);
+                }
+                Ok(Value::Undefined)
+            }
+            Expression::Console { span } => self.lookup("console", env, *span),
+            Expression::Promise { helper, .. } => Ok(builtins::promise_helper(*helper)),
+            Expression::ConsoleLog { arguments, .. } => self.console(ConsoleLevel::Log, arguments, env),
+            Expression::ConsoleWarn { arguments, .. } => self.console(ConsoleLevel::Warn, arguments, env),
+            Expression::ConsoleError { arguments, .. } => self.console(ConsoleLevel::Error, arguments, env),
+            Expression::TypeOf { operand, .. } => {
+                // typeof on an undeclared name is "undefined", not an error
+                let value = match operand.as_ref() {
+                    Expression::Identifier { name, span } => self.lookup(name, env, *span).unwrap_or(Value::Undefined),
+                    other => self.eval_expr(other, env)?,
+                };
+                Ok(Value::string(value.type_of()))
+            }
+            Expression::Delete { operand, span } => {
+                if let Expression::Member { object, property, computed, .. } = operand.as_ref() {
+                    let object = self.eval_expr(object, env)?;
+                    let key = self.property_key(property, *computed, env)?;
+                    match &object {
+                        Value::Object(obj) => obj.borrow_mut().remove(&key),
+                        Value::Array(items) => {
+                            if let Ok(index) = key.parse::<usize>() {
+                                if let Some(slot) = items.borrow_mut().get_mut(index) {
+                                    *slot = Value::Undefined;
+                                }
+                            }
+                        }
+                        Value::Undefined | Value::Null => {
+                            return Err(self.error("TypeError", format!("Nun se pò leva '{}' 'a {}", key, object.to_js_string()), *span));
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(Value::Boolean(true))
+            }
+        }
+    }
+
+    pub(crate) fn console(&mut self, level: ConsoleLevel, arguments: &[Expression], env: &Env) -> Exec<Value> {
+        let values = arguments.iter().map(|a| self.eval_expr(a, env)).collect::<Exec<Vec<_>>>()?;
+        self.write(level, &values);
+        Ok(Value::Undefined)
+    }
+
+    pub(crate) fn write(&mut self, level: ConsoleLevel, values: &[Value]) {
+        let text = values.iter().map(display).collect::<Vec<_>>().join(" ");
+        self.output.push(ConsoleLine { level, text });
+    }
+
+    /// `expr` with its property accesses made optional, as on the left of
+    /// `o sinnò`: `None` if reading a property of `nisciun` or `boh` cut
+    /// the chain short.
+    fn eval_optional(&mut self, expr: &Expression, env: &Env) -> Exec<Option<Value>> {
+        match expr {
+            Expression::Member { object, property, computed, span } => {
+                let Some(object) = self.eval_optional(object, env)?.filter(|object| !matches!(object, Value::Undefined | Value::Null)) else {
+                    return Ok(None);
+                };
+                let key = self.property_key(property, *computed, env)?;
+                self.get_property(&object, &key, *span).map(Some)
+            }
+            Expression::Call { callee, arguments, span } => {
+                let (function, this) = match callee.as_ref() {
+                    Expression::Member { object, property, computed, .. } => {
+                        let Some(object) = self.eval_optional(object, env)?.filter(|object| !matches!(object, Value::Undefined | Value::Null)) else {
+                            return Ok(None);
+                        };
+                        let key = self.property_key(property, *computed, env)?;
+                        (self.get_property(&object, &key, *span)?, object)
+                    }
+                    other => match self.eval_optional(other, env)? {
+                        Some(function) => (function, Value::Undefined),
+                        None => return Ok(None),
+                    },
+                };
+                let args = arguments.iter().map(|a| self.eval_expr(a, env)).collect::<Exec<Vec<_>>>()?;
+                self.call(&function, this, args, *span).map(Some)
+            }
+            other => self.eval_expr(other, env).map(Some),
+        }
+    }
+
+    fn property_key(&mut self, property: &Expression, computed: bool, env: &Env) -> Exec<String> {
+        match (property, computed) {
+            (Expression::Identifier { name, .. }, false) => Ok(name.to_string()),
+            (expr, _) => Ok(self.eval_expr(expr, env)?.to_js_string()),
+        }
+    }
+
+    pub(crate) fn get_property(&self, object: &Value, key: &str, span: Span) -> Exec<Value> {
+        match object {
+            Value::Undefined | Value::Null => Err(self.error(
+                "TypeError",
+                format!("Nun se pò leggere '{}' 'e {}", key, object.to_js_string()),
+                span,
+            )),
+            Value::Object(obj) => {
+                let obj = obj.borrow();
+                if let Some(value) = obj.get(key) {
+                    return Ok(value.clone());
+                }
+                if let Some(method) = obj.class.as_ref().and_then(|c| c.method(key)) {
+                    return Ok(Value::Function(method));
+                }
+                Ok(Value::Undefined)
+            }
+            Value::Array(items) => {
+                if key == "length" {
+                    return Ok(Value::Number(items.borrow().len() as f64));
+                }
+                if let Ok(index) = key.parse::<usize>() {
+                    return Ok(items.borrow().get(index).cloned().unwrap_or(Value::Undefined));
+                }
+                Ok(builtins::array_method(key).unwrap_or(Value::Undefined))
+            }
+            Value::String(s) => {
+                if key == "length" {
+                    return Ok(Value::Number(s.encode_utf16().count() as f64));
+                }
+                if let Ok(index) = key.parse::<usize>() {
+                    return Ok(s.chars().nth(index).map_or(Value::Undefined, |c| Value::string(c.to_string())));
+                }
+                Ok(builtins::string_method(key).unwrap_or(Value::Undefined))
+            }
+            Value::Number(_) => Ok(builtins::number_method(key).unwrap_or(Value::Undefined)),
+            Value::Function(f) if key == "name" => Ok(Value::string(&*f.name)),
+            Value::Class(c) if key == "name" => Ok(Value::string(&*c.name)),
+            _ => Ok(Value::Undefined),
+        }
+    }
+
+    fn set_property(&self, object: &Value, key: &str, value: Value, span: Span) -> Exec<()> {
+        match object {
+            Value::Object(obj) => obj.borrow_mut().set(key, value),
+            Value::Array(items) => {
+                let mut items = items.borrow_mut();
+                if key == "length" {
+                    let len = value.to_number();
+                    if len >= 0.0 && len.fract() == 0.0 {
+                        items.resize(len as usize, Value::Undefined);
+                    }
+                } else if let Ok(index) = key.parse::<usize>() {
+                    if index >= items.len() {
+                        items.resize(index + 1, Value::Undefined);
+                    }
+                    items[index] = value;
+                }
+            }
+            Value::Undefined | Value::Null => {
+                return Err(self.error("TypeError", format!("Nun se pò scrivere '{}' 'ncopp'a {}", key, object.to_js_string()), span));
+            }
+            // Like sloppy-mode JS: silently ignored on primitives
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Calls any callable value with the given `this`.
+    pub(crate) fn call(&mut self, function: &Value, this: Value, args: Vec<Value>, span: Span) -> Exec<Value> {
+        match function {
+            Value::Function(f) => self.call_function(f, Some(this), args, span),
+            Value::Native(native) => (native.func)(self, this, args, span),
+            Value::Class(c) => Err(self.error("TypeError", format!("'{}' è 'na famiglie: ce vo' 'nu bell'", c.name), span)),
+            other => Err(self.error("TypeError", format!("{} nun è 'na funzione", inspect(other, 1)), span)),
+        }
+    }
+
+    fn call_function(&mut self, function: &Rc<Function>, this: Option<Value>, args: Vec<Value>, span: Span) -> Exec<Value> {
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(self.error("RangeError", "Troppe chiamate una dint'a n'ata (Maximum call stack size exceeded)", span));
+        }
+        let scope = Scope::child(&function.closure);
+        if !function.is_arrow {
+            scope.borrow_mut().this = Some(this.unwrap_or(Value::Undefined));
+        }
+        let mut args = args.into_iter();
+        for param in &function.params {
+            declare(&scope, param, args.next().unwrap_or(Value::Undefined), false);
+        }
+
+        self.depth += 1;
+        let result = match &function.body {
+            FunctionBody::Expression(expr) => self.eval_expr(expr, &scope),
+            FunctionBody::Block(body) => {
+                self.hoist(body, &scope);
+                let mut result = Ok(Value::Undefined);
+                for stmt in body {
+                    match self.exec(stmt, &scope) {
+                        Ok(()) => {}
+                        Err(Interrupt::Return(value)) => {
+                            result = Ok(value);
+                            break;
+                        }
+                        Err(other) => {
+                            result = Err(other);
+                            break;
+                        }
+                    }
+                }
+                result
+            }
+        };
+        self.depth -= 1;
+        result
+    }
+
+    /// A class after `figlio 'e` or `è uno`, where `Errore` is `Error`
+    fn class_reference(&mut self, expr: &Expression, env: &Env) -> Exec<Value> {
+        match expr {
+            Expression::Identifier { name, span } if class_name(name) != &**name => self.lookup(class_name(name), env, *span),
+            expr => self.eval_expr(expr, env),
+        }
+    }
+
+    fn construct(&mut self, class: &Value, args: Vec<Value>, span: Span) -> Exec<Value> {
+        match class {
+            Value::Class(class) => {
+                // A derived class starts from an instance of its parent, as
+                // the `super(...arguments)` before its constructor makes it,
+                // unless the constructor builds it itself with `pate(...)`
+                let explicit = class.constructor.as_ref().is_some_and(|constructor| matches!(&constructor.body, FunctionBody::Block(body) if calls_parent(body)));
+                let instance = match &class.superclass {
+                    Some(parent) if !explicit => {
+                        let instance = self.construct(parent, args.clone(), span)?;
+                        if let Value::Object(obj) = &instance {
+                            obj.borrow_mut().class = Some(class.clone());
+                        }
+                        instance
+                    }
+                    _ => Value::Object(Rc::new(RefCell::new(Object { class: Some(class.clone()), ..Default::default() }))),
+                };
+                if let Some(constructor) = &class.constructor {
+                    self.parents.extend(class.superclass.clone());
+                    let result = self.call_function(constructor, Some(instance.clone()), args, span);
+                    if class.superclass.is_some() { self.parents.pop(); }
+                    result?;
+                }
+                Ok(instance)
+            }
+            // Builtins like Error work the same with or without `nu bell`
+            Value::Native(native) => (native.func)(self, Value::Undefined, args, span),
+            Value::Function(function) => {
+                let instance = Value::object(vec![]);
+                let result = self.call_function(function, Some(instance.clone()), args, span)?;
+                Ok(if matches!(result, Value::Object(_) | Value::Array(_)) { result } else { instance })
+            }
+            other => Err(self.error("TypeError", format!("{} nun se pò usà cu 'nu bell'", inspect(other, 1)), span)),
+        }
+    }
+}
+
+/// `value instanceof class`. Every error is an `Error`, and a builtin error
+/// class like `TypeError` has its name.
+fn instance_of(value: &Value, class: &Value) -> bool {
+    let Value::Object(obj) = value else { return false };
+    let obj = obj.borrow();
+    match class {
+        Value::Class(class) => obj.class.as_ref().is_some_and(|own| own.is_a(class)),
+        Value::Native(native) => obj.is_error && (native.name == "Error" || obj.get("name").is_some_and(|name| name.to_js_string() == native.name)),
+        _ => false,
+    }
+}
+
+fn method_decorators(method: &Statement) -> &[Expression] {
+    match method {
+        Statement::FunctionDecl { decorators, .. } => decorators,
+        _ => &[],
+    }
+}
+
+/// Whether `value` matches `pattern`, declaring what it binds in `env`
+fn match_pattern(pattern: &Pattern, value: &Value, env: &Env) -> bool {
+    match pattern {
+        Pattern::Wildcard { .. } => true,
+        Pattern::Binding { name, .. } => {
+            declare(env, name, value.clone(), true);
+            true
+        }
+        Pattern::Literal { value: literal, .. } => {
+            let literal = match literal {
+                Literal::Number(number) => Value::Number(*number),
+                Literal::String(string) => Value::string(string.as_str()),
+                Literal::Boolean(boolean) => Value::Boolean(*boolean),
+                Literal::Null => Value::Null,
+                Literal::Undefined => Value::Undefined,
+            };
+            value.strict_equals(&literal)
+        }
+        Pattern::Array { elements, .. } => match value {
+            Value::Array(items) => {
+                let items = items.borrow().clone();
+                items.len() == elements.len() && elements.iter().zip(&items).all(|(element, item)| match_pattern(element, item, env))
+            }
+            _ => false,
+        },
+        Pattern::Object { properties, .. } => match value {
+            Value::Object(obj) => properties.iter().all(|(key, property)| {
+                let obj = obj.borrow();
+                let found = obj.get(key).cloned().or_else(|| obj.class.as_ref().and_then(|c| c.method(key)).map(Value::Function));
+                drop(obj);
+                found.is_some_and(|found| match_pattern(property, &found, env))
+            }),
+            _ => false,
+        },
+    }
+}
+
+/// Declares `name` in `env`, replacing an existing binding in the same
+/// scope (so a REPL can redeclare).
+fn declare(env: &Env, name: &Name, value: Value, is_const: bool) {
+    env.borrow_mut().vars.insert(name.clone(), Binding { value, is_const });
+}
+
+fn binary(operator: BinaryOp, left: &Value, right: &Value) -> Value {
+    let num = |f: fn(f64, f64) -> f64| Value::Number(f(left.to_number(), right.to_number()));
+    match operator {
+        BinaryOp::Add => {
+            let is_stringy = |v: &Value| matches!(v, Value::String(_) | Value::Array(_) | Value::Object(_) | Value::Function(_) | Value::Native(_) | Value::Class(_));
+            if is_stringy(left) || is_stringy(right) {
+                Value::string(left.to_js_string() + &right.to_js_string())
+            } else {
+                num(|a, b| a + b)
+            }
+        }
+        BinaryOp::Subtract => num(|a, b| a - b),
+        BinaryOp::Multiply => num(|a, b| a * b),
+        BinaryOp::Divide => num(|a, b| a / b),
+        BinaryOp::Modulo => num(|a, b| a % b),
+        BinaryOp::Power => num(f64::powf),
+        BinaryOp::Equal => Value::Boolean(left.loose_equals(right)),
+        BinaryOp::NotEqual => Value::Boolean(!left.loose_equals(right)),
+        BinaryOp::StrictEqual => Value::Boolean(left.strict_equals(right)),
+        BinaryOp::StrictNotEqual => Value::Boolean(!left.strict_equals(right)),
+        BinaryOp::LessThan | BinaryOp::GreaterThan | BinaryOp::LessEqual | BinaryOp::GreaterEqual => {
+            let ordering = match (left, right) {
+                (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+                _ => left.to_number().partial_cmp(&right.to_number()),
+            };
+            let result = ordering.is_some_and(|o| match operator {
+                BinaryOp::LessThan => o.is_lt(),
+                BinaryOp::GreaterThan => o.is_gt(),
+                BinaryOp::LessEqual => o.is_le(),
+                _ => o.is_ge(),
+            });
+            Value::Boolean(result)
+        }
+        // Short-circuited in `eval_expr`
+        BinaryOp::And | BinaryOp::Or | BinaryOp::Nullish => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str) -> Vec<String> {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval(source).unwrap();
+        interpreter.output().iter().map(|line| line.text.clone()).collect()
+    }
+
+    #[test]
+    fn test_conditional_directives() {
+        let source = "#si DEBUG { facc log(x) { stamm a dì(x) } } sinnò { facc log(x) { } }\nlog(\"debug\")";
+        assert!(run(source).is_empty());
+        let mut interpreter = Interpreter::new().with_defines(BTreeMap::from([("DEBUG".to_string(), "1".to_string())]));
+        interpreter.eval(source).unwrap();
+        assert_eq!(interpreter.output()[0].text, "debug");
+    }
+
+    #[test]
+    fn test_match() {
+        let source = r#"
+facc descrivi(v) {
+    piglie vir buono (v) {
+        [a, 0] => "coppia " + a
+        { nome, età: 18 } => nome
+        n si (n > 10) => "gruosso"
+        "ciao" => "saluto"
+    }
+}
+stamm a dì(descrivi([5, 0]), descrivi({ nome: "Ciro", età: 18 }), descrivi(20), descrivi("ciao"), descrivi(2))
+"#;
+        assert_eq!(run(source), vec!["coppia 5 Ciro gruosso saluto undefined"]);
+    }
+
+    #[test]
+    fn test_nullish() {
+        let source = "chist è u = { nomme: \"Ciro\", casa: nisciun }\nstamm a dì(u.nomme o sinnò \"anonimo\", u.casa.via o sinnò \"?\", u.casa.chiave() o sinnò 1, 0 o sinnò 2)";
+        assert_eq!(run(source), vec!["Ciro ? 1 0"]);
+    }
+
+    #[test]
+    fn test_promise_helpers() {
+        let source = "mo vir facc pizza(n) { piglie n }\nstamm a dì(aspett tutt' quant([pizza(1), 2]), aspett 'o primm che arriva([pizza(3), 4]))";
+        assert_eq!(run(source), vec!["[ 1, 2 ] 3"]);
+    }
+
+    #[test]
+    fn test_error_classes() {
+        let source = "na famiglie ErroreMio figlio 'e Errore {\n    facc saluta() { piglie \"uè\" }\n}\nna famiglie Figlio figlio 'e ErroreMio {}\n\
+            pruvamm { iett nu bell Figlio(\"guaio\") } e si schiatta (err è uno ErroreMio) { stamm a dì(err.message, err.saluta()) }\n\
+            pruvamm {\n    pruvamm { iett nu bell TypeError(\"x\") } e si schiatta (err è uno ErroreMio) { stamm a dì(\"no\") }\n\
+            } e si schiatta (err è uno Errore) { stamm a dì(err.name) }";
+        assert_eq!(run(source), vec!["guaio uè", "TypeError"]);
+        // The parent is built with the constructor's arguments first
+        let source = "na famiglie Pizza {\n    facc costruttore(nomme) { stu cos.nomme = nomme }\n}\n\
+            na famiglie Margherita figlio 'e Pizza {\n    facc costruttore(nomme, prezzo) { stu cos.prezzo = prezzo }\n}\n\
+            chist è m = nu bell Margherita(\"margherita\", 5)\nstamm a dì(m.nomme, m.prezzo)";
+        assert_eq!(run(source), vec!["margherita 5"]);
+        // ...or with its own, given to pate(...)
+        let source = "na famiglie Pizza {\n    facc costruttore(nomme, prezzo) { stu cos.nomme = nomme\n stu cos.prezzo = prezzo }\n}\n\
+            na famiglie Margherita figlio 'e Pizza {\n    facc costruttore(prezzo) {\n        pate(\"margherita\", prezzo * 2)\n        stu cos.bufala = sfòls\n    }\n}\n\
+            na famiglie Bufala figlio 'e Margherita {\n    facc costruttore() {\n        pate(4)\n        stu cos.bufala = overo\n    }\n}\n\
+            chist è b = nu bell Bufala()\nstamm a dì(b.nomme, b.prezzo, b.bufala)";
+        assert_eq!(run(source), vec!["margherita 8 true"]);
+    }
+
+    #[test]
+    fn test_arithmetic_and_strings() {
+        assert_eq!(run("stamm a dì(1 + 2 * 3, 7 / 2, 2 ** 10)"), vec!["7 3.5 1024"]);
+        assert_eq!(run("stamm a dì(\"Uè \" + 1 + 1, 0.1 + 0.2)"), vec!["Uè 11 0.30000000000000004"]);
+        assert_eq!(run("stamm a dì([1, \"a\"], { nome: \"Ciro\" })"), vec!["[ 1, 'a' ] { nome: 'Ciro' }"]);
+    }
+
+    #[test]
+    fn test_functions_loops_and_closures() {
+        let source = r#"
+facc fibonacci(n) {
+    si (n <= 1) { piglie n }
+    piglie fibonacci(n - 1) + fibonacci(n - 2)
+}
+tien risultati = []
+pe (tien i = 0; i < 10; i = i + 1) {
+    risultati.push(fibonacci(i))
+}
+stamm a dì(risultati.join(", "))
+chist è doppio = (x) => x * 2
+stamm a dì([1, 2, 3].map(doppio))
+"#;
+        assert_eq!(run(source), vec!["0, 1, 1, 2, 3, 5, 8, 13, 21, 34", "[ 2, 4, 6 ]"]);
+    }
+
+    #[test]
+    fn test_classes_and_exceptions() {
+        let source = r#"
+na famiglie Pizzaiolo {
+    facc costruttore(nome) {
+        stu cos.nome = nome
+        stu cos.pizze = 0
+    }
+    facc faiPizza() {
+        stu cos.pizze = stu cos.pizze + 1
+        piglie stu cos.nome + " ha fatto " + stu cos.pizze + " pizze"
+    }
+}
+chist è gennaro = nu bell Pizzaiolo("Gennaro")
+gennaro.faiPizza()
+stamm a dì(gennaro.faiPizza())
+pruvamm {
+    iett nu bell Error("Guaio!")
+} e si schiatta (err) {
+    scrive a dì(err.message)
+}
+"#;
+        let mut interpreter = Interpreter::new();
+        interpreter.eval(source).unwrap();
+        let output = interpreter.output();
+        assert_eq!(output[0].text, "Gennaro ha fatto 2 pizze");
+        assert_eq!(output[1], ConsoleLine { level: ConsoleLevel::Error, text: "Guaio!".to_string() });
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        let mut interpreter = Interpreter::new();
+        let error = interpreter.eval("stamm a dì(1)\nchist è x = 1\nx = 2").unwrap_err();
+        assert_eq!(error.line(), Some(3));
+        assert_eq!(interpreter.output().len(), 1);
+
+        let mut interpreter = Interpreter::new().with_max_steps(1000);
+        let error = interpreter.eval("mentre che (overo) { }").unwrap_err();
+        assert!(error.to_string().contains("passi"));
+    }
+
+    #[test]
+    fn test_length_limits() {
+        let doubling = "tien s = \"ab\"\npe (tien i = 0; i < 40; i = i + 1) { s = s + s }";
+        let error = Interpreter::new().with_max_string_length(1000).eval(doubling).unwrap_err();
+        assert!(error.to_string().contains("RangeError") && error.to_string().contains("Invalid string length"), "{}", error);
+        // JavaScript's own limits, checked before anything is built
+        assert!(Interpreter::new().eval("\"ab\".repeat(2 ** 30)").is_err());
+        assert!(Interpreter::new().eval("\"\".padEnd(2 ** 30, \"ab\")").is_err());
+
+        let mut interpreter = Interpreter::new().with_max_string_length(100).with_max_array_length(10);
+        let source = "tien a = [1]\npruvamm { mentre che (overo) { a = a.concat(a) } } e si schiatta (err) { stamm a dì(err.name, a.length) }\n\
+            pruvamm { \"ab\".replaceAll(\"\", \"-\".repeat(60)) } e si schiatta (err) { stamm a dì(err.name) }\n\
+            pruvamm { a.join(\"-\".repeat(20)) } e si schiatta (err) { stamm a dì(err.name) }";
+        interpreter.eval(source).unwrap();
+        let output: Vec<&str> = interpreter.output().iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(output, ["RangeError 8", "RangeError", "RangeError"]);
+    }
+
+    #[test]
+    fn test_failed_assertion_throws() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval("giura che (1 < 2)").unwrap();
+        let error = interpreter.eval("tien x = 0\ngiura che (x > 0) sinnò \"x adda essere positivo\"").unwrap_err();
+        assert!(error.to_string().contains("x adda essere positivo"), "{}", error);
+        assert_eq!(error.line(), Some(2));
+    }
+
+    #[test]
+    fn test_bindings_persist() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval("tien x = 40").unwrap();
+        let value = interpreter.eval("x + 2").unwrap();
+        assert_eq!(value, Value::Number(42.0));
+    }
+
+    #[test]
+    fn test_console_methods() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval("stamm.tabella(1)\nchist è avvisa = avvis a dì\navvisa(\"occhio\")\nstamm.info(2)").unwrap();
+        let levels: Vec<_> = interpreter.output().iter().map(|line| (line.level, line.text.as_str())).collect();
+        assert_eq!(levels, vec![(ConsoleLevel::Log, "1"), (ConsoleLevel::Warn, "occhio"), (ConsoleLevel::Log, "2")]);
+    }
+}
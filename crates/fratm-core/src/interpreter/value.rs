@@ -0,0 +1,374 @@
+//! Runtime values of the interpreter
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::lexer::Span;
+use crate::parser::{Expression, Name, Statement};
+
+use super::{Interpreter, Interrupt};
+
+/// A FratmScript value at runtime, with JavaScript semantics.
+///
+/// Arrays, objects and functions are reference types: cloning a `Value`
+/// shares the underlying data, like JavaScript does.
+#[derive(Clone)]
+pub enum Value {
+    Undefined,
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(Rc<str>),
+    Array(Rc<RefCell<Vec<Value>>>),
+    Object(Rc<RefCell<Object>>),
+    Function(Rc<Function>),
+    Native(Rc<NativeFunction>),
+    Class(Rc<Class>),
+}
+
+/// An object: plain (`{ a: 1 }`), a class instance, or an error.
+#[derive(Default)]
+pub struct Object {
+    /// Properties in insertion order
+    pub properties: Vec<(String, Value)>,
+    /// Class the object was created from with `nu bell`
+    pub class: Option<Rc<Class>>,
+    /// Whether this is an `Error` (printed as `Name: message`)
+    pub is_error: bool,
+}
+
+impl Object {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.properties.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn set(&mut self, key: &str, value: Value) {
+        match self.properties.iter_mut().find(|(k, _)| k == key) {
+            Some((_, slot)) => *slot = value,
+            None => self.properties.push((key.to_string(), value)),
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.properties.retain(|(k, _)| k != key);
+    }
+}
+
+/// A user-defined function or arrow function, with the scope it closes over.
+pub struct Function {
+    pub name: Name,
+    pub params: Vec<Name>,
+    pub body: FunctionBody,
+    pub(crate) closure: Env,
+    /// Arrow functions take `stu cos` from where they were defined
+    pub is_arrow: bool,
+}
+
+pub enum FunctionBody {
+    Block(Vec<Statement>),
+    Expression(Expression),
+}
+
+pub(crate) type NativeFn = fn(&mut Interpreter, Value, Vec<Value>, Span) -> Result<Value, Interrupt>;
+
+/// A function implemented in Rust (`Math.floor`, `push`, ...).
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub(crate) func: NativeFn,
+}
+
+/// A class declared with `na famiglie`.
+pub struct Class {
+    pub name: Name,
+    pub constructor: Option<Rc<Function>>,
+    pub methods: HashMap<Name, Rc<Function>>,
+    /// The class after `figlio 'e`: a `Class`, or a builtin like `Error`
+    pub superclass: Option<Value>,
+}
+
+impl Class {
+    /// The method `name`, inherited if the class doesn't define it
+    pub fn method(&self, name: &str) -> Option<Rc<Function>> {
+        match (self.methods.get(name), &self.superclass) {
+            (Some(method), _) => Some(method.clone()),
+            (None, Some(Value::Class(parent))) => parent.method(name),
+            (None, _) => None,
+        }
+    }
+
+    /// Whether the class is `other` or inherits from it
+    pub fn is_a(&self, other: &Class) -> bool {
+        std::ptr::eq(self, other) || matches!(&self.superclass, Some(Value::Class(parent)) if parent.is_a(other))
+    }
+}
+
+pub(crate) type Env = Rc<RefCell<Scope>>;
+
+pub(crate) struct Binding {
+    pub value: Value,
+    pub is_const: bool,
+}
+
+/// One level of variable scope.
+#[derive(Default)]
+pub(crate) struct Scope {
+    pub vars: HashMap<Name, Binding>,
+    pub parent: Option<Env>,
+    /// `stu cos` inside a method or constructor
+    pub this: Option<Value>,
+}
+
+impl Scope {
+    pub fn child(parent: &Env) -> Env {
+        Rc::new(RefCell::new(Scope { parent: Some(parent.clone()), ..Default::default() }))
+    }
+}
+
+impl Value {
+    pub fn string(s: impl Into<Rc<str>>) -> Self {
+        Value::String(s.into())
+    }
+
+    pub fn array(values: Vec<Value>) -> Self {
+        Value::Array(Rc::new(RefCell::new(values)))
+    }
+
+    pub fn object(properties: Vec<(String, Value)>) -> Self {
+        Value::Object(Rc::new(RefCell::new(Object { properties, ..Default::default() })))
+    }
+
+    /// JavaScript truthiness
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Undefined | Value::Null => false,
+            Value::Boolean(b) => *b,
+            Value::Number(n) => *n != 0.0 && !n.is_nan(),
+            Value::String(s) => !s.is_empty(),
+            _ => true,
+        }
+    }
+
+    /// Result of `chè è` (typeof)
+    pub fn type_of(&self) -> &'static str {
+        match self {
+            Value::Undefined => "undefined",
+            Value::Null | Value::Array(_) | Value::Object(_) => "object",
+            Value::Boolean(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Function(_) | Value::Native(_) | Value::Class(_) => "function",
+        }
+    }
+
+    /// JavaScript `ToNumber`
+    pub fn to_number(&self) -> f64 {
+        match self {
+            Value::Undefined => f64::NAN,
+            Value::Null => 0.0,
+            Value::Boolean(b) => if *b { 1.0 } else { 0.0 },
+            Value::Number(n) => *n,
+            Value::String(s) => {
+                let s = s.trim();
+                if s.is_empty() { 0.0 } else { s.parse().unwrap_or(f64::NAN) }
+            }
+            Value::Array(items) => {
+                let items = items.borrow();
+                match items.len() {
+                    0 => 0.0,
+                    1 => items[0].to_number(),
+                    _ => f64::NAN,
+                }
+            }
+            _ => f64::NAN,
+        }
+    }
+
+    /// JavaScript `ToString`, as used by `+` and `String(x)`
+    pub fn to_js_string(&self) -> String {
+        match self {
+            Value::Undefined => "undefined".to_string(),
+            Value::Null => "null".to_string(),
+            Value::Boolean(b) => b.to_string(),
+            Value::Number(n) => number_to_string(*n),
+            Value::String(s) => s.to_string(),
+            Value::Array(items) => items.borrow().iter()
+                .map(|v| match v {
+                    Value::Undefined | Value::Null => String::new(),
+                    v => v.to_js_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            Value::Object(obj) => {
+                let obj = obj.borrow();
+                if obj.is_error {
+                    error_string(&obj)
+                } else {
+                    "[object Object]".to_string()
+                }
+            }
+            Value::Function(f) => format!("facc {}() {{ ... }}", f.name),
+            Value::Native(f) => format!("facc {}() {{ [native code] }}", f.name),
+            Value::Class(c) => format!("na famiglie {} {{ ... }}", c.name),
+        }
+    }
+
+    /// Bytes of [`to_js_string`](Self::to_js_string), without building it;
+    /// counting stops past `max`
+    pub fn js_string_len(&self, max: usize) -> usize {
+        match self {
+            Value::String(s) => s.len(),
+            Value::Array(items) => {
+                let mut len = 0usize;
+                for (i, item) in items.borrow().iter().enumerate() {
+                    if len > max { break; }
+                    let item_len = match item {
+                        Value::Undefined | Value::Null => 0,
+                        item => item.js_string_len(max - len),
+                    };
+                    len = len.saturating_add(item_len).saturating_add(usize::from(i > 0));
+                }
+                len
+            }
+            other => other.to_js_string().len(),
+        }
+    }
+
+    /// Strict equality (`===`)
+    pub fn strict_equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Undefined, Value::Undefined) | (Value::Null, Value::Null) => true,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => Rc::ptr_eq(a, b),
+            (Value::Object(a), Value::Object(b)) => Rc::ptr_eq(a, b),
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Native(a), Value::Native(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// Loose equality (`==`)
+    pub fn loose_equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Undefined | Value::Null, Value::Undefined | Value::Null) => true,
+            (Value::Undefined | Value::Null, _) | (_, Value::Undefined | Value::Null) => false,
+            (Value::Number(_) | Value::String(_) | Value::Boolean(_), Value::Number(_) | Value::String(_) | Value::Boolean(_))
+                if self.type_of() != other.type_of() => self.to_number() == other.to_number(),
+            (Value::Array(_) | Value::Object(_), Value::Number(_) | Value::String(_) | Value::Boolean(_)) => {
+                Value::string(self.to_js_string()).loose_equals(other)
+            }
+            (Value::Number(_) | Value::String(_) | Value::Boolean(_), Value::Array(_) | Value::Object(_)) => {
+                other.loose_equals(self)
+            }
+            _ => self.strict_equals(other),
+        }
+    }
+}
+
+/// Formats a number the way JavaScript does (`1`, `0.5`, `1e+21`, `NaN`).
+pub fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 { "Infinity".to_string() } else { "-Infinity".to_string() }
+    } else if n == 0.0 {
+        "0".to_string()
+    } else if n.abs() >= 1e21 || n.abs() < 1e-6 {
+        let s = format!("{:e}", n);
+        match s.split_once('e') {
+            Some((mantissa, exp)) if !exp.starts_with('-') => format!("{}e+{}", mantissa, exp),
+            _ => s,
+        }
+    } else {
+        format!("{}", n)
+    }
+}
+
+fn error_string(obj: &Object) -> String {
+    let name = obj.get("name").map_or("Error".to_string(), |v| v.to_js_string());
+    match obj.get("message").map(|v| v.to_js_string()) {
+        Some(message) if !message.is_empty() => format!("{}: {}", name, message),
+        _ => name,
+    }
+}
+
+/// Formats a value like `console.log` does in Node: strings print as-is,
+/// everything else is inspected (`[ 1, 2 ]`, `{ nome: 'Gennaro' }`, ...).
+pub fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.to_string(),
+        other => inspect(other, 0),
+    }
+}
+
+/// Formats a value for the console, quoting nested strings.
+pub fn inspect(value: &Value, depth: usize) -> String {
+    match value {
+        Value::String(s) => {
+            if depth == 0 { s.to_string() } else { format!("'{}'", s.replace('\'', "\\'")) }
+        }
+        Value::Number(n) if *n == 0.0 && n.is_sign_negative() => "-0".to_string(),
+        Value::Array(items) => {
+            let items = items.borrow();
+            if items.is_empty() {
+                "[]".to_string()
+            } else if depth > 2 {
+                "[Array]".to_string()
+            } else {
+                let parts: Vec<String> = items.iter().map(|v| inspect(v, depth + 1)).collect();
+                format!("[ {} ]", parts.join(", "))
+            }
+        }
+        Value::Object(obj) => {
+            let obj = obj.borrow();
+            if obj.is_error {
+                return error_string(&obj);
+            }
+            let prefix = obj.class.as_ref().map_or(String::new(), |c| format!("{} ", c.name));
+            if obj.properties.is_empty() {
+                format!("{}{{}}", prefix)
+            } else if depth > 2 {
+                format!("[{}]", obj.class.as_ref().map_or("Object", |c| &*c.name))
+            } else {
+                let parts: Vec<String> = obj.properties.iter()
+                    .map(|(k, v)| format!("{}: {}", inspect_key(k), inspect(v, depth + 1)))
+                    .collect();
+                format!("{}{{ {} }}", prefix, parts.join(", "))
+            }
+        }
+        Value::Function(f) if f.name.is_empty() => "[Function (anonymous)]".to_string(),
+        Value::Function(f) => format!("[Function: {}]", f.name),
+        Value::Native(f) => format!("[Function: {}]", f.name),
+        Value::Class(c) => format!("[class {}]", c.name),
+        other => other.to_js_string(),
+    }
+}
+
+fn inspect_key(key: &str) -> String {
+    let mut chars = key.chars();
+    let is_identifier = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    if is_identifier { key.to_string() } else { format!("'{}'", key) }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", inspect(self, 1))
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", display(self))
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.strict_equals(other)
+    }
+}
@@ -0,0 +1,296 @@
+//! Exercises of the `fratm learn` tutorial
+//!
+//! Each [`Exercise`] asks for a small program ("declare a constant",
+//! "write a loop"). [`Exercise::check`] compiles the answer and looks at
+//! its AST for what the exercise is about, so any way of writing it that
+//! does the job passes. [`Progress`] keeps the exercises done between
+//! sessions.
+//!
+//! ```rust
+//! use fratm_core::learn::{Mistake, Progress, EXERCISES};
+//!
+//! let mut progress = Progress::default();
+//! let exercise = progress.next().unwrap();
+//! assert_eq!(exercise.id, "constant");
+//! assert!(matches!(exercise.check("tien nome = \"Gennaro\""), Err(Mistake::Missing(_))));
+//! assert_eq!(exercise.check("chist è nome = \"Gennaro\"").unwrap(), "const nome = \"Gennaro\";\n");
+//!
+//! progress.complete(exercise);
+//! assert_eq!(progress.next().unwrap().id, EXERCISES[1].id);
+//! ```
+
+use std::collections::BTreeSet;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::errors::Locale;
+use crate::linter::for_each_child_expression;
+use crate::parser::{ArrowBody, Expression, Program, Statement};
+use crate::{compile, CompileOptions};
+
+/// One step of the tutorial.
+#[derive(Debug)]
+pub struct Exercise {
+    /// Stable name, kept in the progress file
+    pub id: &'static str,
+    pub title: &'static str,
+    /// What to write
+    pub task: &'static str,
+    /// Shown on request, or after a wrong answer
+    pub hint: &'static str,
+    /// What the answer must contain, or why it falls short
+    expect: fn(&Program) -> Result<(), &'static str>,
+}
+
+/// Why an answer doesn't pass
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mistake {
+    /// It doesn't compile: the compiler's message
+    Compile(String),
+    /// It compiles, but doesn't do what the exercise asks (yet)
+    Missing(&'static str),
+}
+
+impl std::fmt::Display for Mistake {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mistake::Compile(message) => f.write_str(message),
+            Mistake::Missing(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Exercise {
+    /// The JavaScript of `source` if it compiles and does what the exercise
+    /// asks, or what is wrong with it.
+    pub fn check(&self, source: &str) -> Result<String, Mistake> {
+        self.check_localized(source, Locale::default())
+    }
+
+    /// Like [`Exercise::check`], with compile errors in `locale`.
+    pub fn check_localized(&self, source: &str, locale: Locale) -> Result<String, Mistake> {
+        let program = crate::parse_source(source).map_err(|errors| Mistake::Compile(errors[0].localized(locale)))?;
+        (self.expect)(&program).map_err(Mistake::Missing)?;
+        compile(source, CompileOptions::builder().locale(locale).build()).map(|result| result.code).map_err(|e| Mistake::Compile(e.localized(locale)))
+    }
+}
+
+/// Every exercise, easiest first.
+pub static EXERCISES: &[Exercise] = &[
+    Exercise {
+        id: "constant",
+        title: "Constants",
+        task: "Declare a constant called `nome` holding your name.",
+        hint: "A constant is `chist è`: chist è nome = \"Gennaro\"",
+        expect: |program| match find_statement(program, &|stmt| matches!(stmt, Statement::VariableDecl { name, is_const: true, .. } if &**name == "nome")) {
+            true => Ok(()),
+            false if find_statement(program, &|stmt| matches!(stmt, Statement::VariableDecl { is_const: false, .. })) => Err("`tien` declares a variable, which can change: a constant is `chist è`"),
+            false => Err("There is no constant called `nome`"),
+        },
+    },
+    Exercise {
+        id: "variable",
+        title: "Variables",
+        task: "Declare a variable `contatore` starting at 0, then add 1 to it.",
+        hint: "A variable is `tien`: tien contatore = 0, then contatore = contatore + 1",
+        expect: |program| {
+            if !find_statement(program, &|stmt| matches!(stmt, Statement::VariableDecl { name, is_const: false, .. } if &**name == "contatore")) {
+                return Err("There is no variable called `contatore` declared with `tien`");
+            }
+            let assigns = |expr: &Expression| matches!(expr, Expression::Assignment { target, .. } if matches!(&**target, Expression::Identifier { name, .. } if &**name == "contatore"));
+            if find_expression(program, &assigns) { Ok(()) } else { Err("`contatore` never changes: add 1 to it") }
+        },
+    },
+    Exercise {
+        id: "print",
+        title: "Printing",
+        task: "Print \"Uè, munno!\" to the console.",
+        hint: "console.log is `stamm a dì`: stamm a dì(\"Uè, munno!\")",
+        expect: |program| {
+            let prints = |expr: &Expression| matches!(expr, Expression::ConsoleLog { arguments, .. } if !arguments.is_empty());
+            if find_expression(program, &prints) { Ok(()) } else { Err("Nothing is printed with `stamm a dì(...)`") }
+        },
+    },
+    Exercise {
+        id: "function",
+        title: "Functions",
+        task: "Write a function `doppio` that takes a number and gives back twice it.",
+        hint: "A function is `facc`, and it gives back with `piglie`: facc doppio(n) { piglie n * 2 }",
+        expect: |program| {
+            let function = |stmt: &Statement| matches!(stmt, Statement::FunctionDecl { name, params, .. } if &**name == "doppio" && params.len() == 1);
+            if !find_statement(program, &function) {
+                return Err("There is no function `doppio` with one parameter");
+            }
+            if find_statement(program, &|stmt| matches!(stmt, Statement::Return { value: Some(_), .. })) { Ok(()) } else { Err("`doppio` gives nothing back: use `piglie`") }
+        },
+    },
+    Exercise {
+        id: "condition",
+        title: "Conditions",
+        task: "Given `tien eta = 20`, print \"maggiorenne\" if eta is at least 18, \"minorenne\" otherwise.",
+        hint: "if is `si` and else is `sinnò`: si (eta >= 18) { ... } sinnò { ... }",
+        expect: |program| match find_statement(program, &|stmt| matches!(stmt, Statement::If { else_branch: Some(_), .. })) {
+            true => Ok(()),
+            false if find_statement(program, &|stmt| matches!(stmt, Statement::If { .. })) => Err("The `si` needs a `sinnò` for the other case"),
+            false => Err("There is no `si`"),
+        },
+    },
+    Exercise {
+        id: "loop",
+        title: "Loops",
+        task: "Print the numbers from 1 to 5 with a loop.",
+        hint: "`pe (tien i = 1; i <= 5; i = i + 1) { ... }`, or `mentre che (condition) { ... }`",
+        expect: |program| {
+            if find_statement(program, &|stmt| matches!(stmt, Statement::For { .. } | Statement::While { .. })) { Ok(()) } else { Err("There is no loop: use `pe` or `mentre che`") }
+        },
+    },
+    Exercise {
+        id: "arrow",
+        title: "Arrow functions",
+        task: "Declare a constant `saluta` holding an arrow function that takes a name and gives back a greeting.",
+        hint: "chist è saluta = (nome) => \"Uè \" + nome",
+        expect: |program| {
+            let arrow = |stmt: &Statement| matches!(stmt, Statement::VariableDecl { name, value: Some(Expression::ArrowFunction { params, .. }), .. } if &**name == "saluta" && params.len() == 1);
+            if find_statement(program, &arrow) { Ok(()) } else { Err("`saluta` has to be an arrow function with one parameter, `(nome) => ...`") }
+        },
+    },
+    Exercise {
+        id: "class",
+        title: "Classes",
+        task: "Declare a class `Pizza` whose constructor keeps a `nome`, and create one.",
+        hint: "na famiglie Pizza { facc costruttore(nome) { stu cos.nome = nome } }, then nu bell Pizza(\"Margherita\")",
+        expect: |program| {
+            let class = |stmt: &Statement| matches!(stmt, Statement::ClassDecl { name, methods, .. } if &**name == "Pizza"
+                && methods.iter().any(|method| matches!(method, Statement::FunctionDecl { name, .. } if &**name == "costruttore")));
+            if !find_statement(program, &class) {
+                return Err("There is no class `Pizza` with a `costruttore`");
+            }
+            let creates = |expr: &Expression| matches!(expr, Expression::New { callee, .. } if matches!(&**callee, Expression::Identifier { name, .. } if &**name == "Pizza"));
+            if find_expression(program, &creates) { Ok(()) } else { Err("No `Pizza` is created: use `nu bell Pizza(...)`") }
+        },
+    },
+];
+
+/// The exercises done so far, by id.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Progress {
+    pub done: BTreeSet<String>,
+}
+
+impl Progress {
+    /// The first exercise not done yet
+    pub fn next(&self) -> Option<&'static Exercise> {
+        EXERCISES.iter().find(|exercise| !self.is_done(exercise))
+    }
+
+    pub fn is_done(&self, exercise: &Exercise) -> bool {
+        self.done.contains(exercise.id)
+    }
+
+    pub fn complete(&mut self, exercise: &Exercise) {
+        self.done.insert(exercise.id.to_string());
+    }
+}
+
+/// Whether a statement of `program`, at any depth, satisfies `f`
+fn find_statement(program: &Program, f: &dyn Fn(&Statement) -> bool) -> bool {
+    let mut found = false;
+    walk(&program.statements, &mut |stmt| found |= f(stmt), &mut |_| {});
+    found
+}
+
+/// Whether an expression of `program`, at any depth, satisfies `f`
+fn find_expression(program: &Program, f: &dyn Fn(&Expression) -> bool) -> bool {
+    let mut found = false;
+    walk(&program.statements, &mut |_| {}, &mut |expr| found |= f(expr));
+    found
+}
+
+fn walk(statements: &[Statement], on_statement: &mut dyn FnMut(&Statement), on_expression: &mut dyn FnMut(&Expression)) {
+    for stmt in statements {
+        on_statement(stmt);
+        let mut expression = |expr: &Expression| walk_expression(expr, on_statement, on_expression);
+        match stmt {
+            Statement::VariableDecl { value, .. } | Statement::Return { value, .. } => value.iter().for_each(expression),
+            Statement::Expression { expression: value, .. } | Statement::Throw { value, .. } => expression(value),
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                expression(condition);
+                walk(then_branch, on_statement, on_expression);
+                walk(else_branch.as_deref().unwrap_or_default(), on_statement, on_expression);
+            }
+            Statement::While { condition, body, .. } => {
+                expression(condition);
+                walk(body, on_statement, on_expression);
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                condition.iter().chain(update).for_each(&mut expression);
+                walk(init.as_deref().map(std::slice::from_ref).unwrap_or_default(), on_statement, on_expression);
+                walk(body, on_statement, on_expression);
+            }
+            Statement::FunctionDecl { body, .. } | Statement::Block { statements: body, .. } => walk(body, on_statement, on_expression),
+            Statement::ClassDecl { methods, .. } => walk(methods, on_statement, on_expression),
+            Statement::TryCatch { try_body, catch_body, .. } => {
+                walk(try_body, on_statement, on_expression);
+                walk(catch_body, on_statement, on_expression);
+            }
+            Statement::Export { declaration: Some(declaration), .. } => walk(std::slice::from_ref(declaration), on_statement, on_expression),
+            _ => {}
+        }
+    }
+}
+
+fn walk_expression(expr: &Expression, on_statement: &mut dyn FnMut(&Statement), on_expression: &mut dyn FnMut(&Expression)) {
+    on_expression(expr);
+    match expr {
+        Expression::ArrowFunction { body: ArrowBody::Block(body), .. } => walk(body, on_statement, on_expression),
+        expr => for_each_child_expression(expr, |child| walk_expression(child, on_statement, on_expression)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exercise(id: &str) -> &'static Exercise {
+        EXERCISES.iter().find(|exercise| exercise.id == id).unwrap()
+    }
+
+    #[test]
+    fn test_hints_pass() {
+        // The hint of every exercise, completed, is an answer
+        let answers = [
+            "chist è nome = \"Gennaro\"",
+            "tien contatore = 0\ncontatore = contatore + 1",
+            "stamm a dì(\"Uè, munno!\")",
+            "facc doppio(n) { piglie n * 2 }",
+            "tien eta = 20\nsi (eta >= 18) { stamm a dì(\"maggiorenne\") } sinnò { stamm a dì(\"minorenne\") }",
+            "pe (tien i = 1; i <= 5; i = i + 1) { stamm a dì(i) }",
+            "chist è saluta = (nome) => \"Uè \" + nome",
+            "na famiglie Pizza { facc costruttore(nome) { stu cos.nome = nome } }\nchist è p = nu bell Pizza(\"Margherita\")",
+        ];
+        assert_eq!(answers.len(), EXERCISES.len());
+        for (exercise, answer) in EXERCISES.iter().zip(answers) {
+            assert!(exercise.check(answer).is_ok(), "{}: {:?}", exercise.id, exercise.check(answer));
+        }
+    }
+
+    #[test]
+    fn test_feedback() {
+        assert_eq!(exercise("condition").check("si (overo) { }").unwrap_err(), Mistake::Missing("The `si` needs a `sinnò` for the other case"));
+        // Found inside a function too
+        assert!(exercise("loop").check("facc f() { mentre che (sfòls) { } }").is_ok());
+        assert!(exercise("print").check("chist è f = (x) => { stamm a dì(x) }").is_ok());
+        // A syntax error is the compiler's message
+        assert!(matches!(exercise("constant").check("chist è = 1"), Err(Mistake::Compile(message)) if message.contains("nome")));
+    }
+
+    #[test]
+    fn test_ids_are_unique() {
+        for (i, exercise) in EXERCISES.iter().enumerate() {
+            assert!(EXERCISES[i + 1..].iter().all(|other| other.id != exercise.id), "{}", exercise.id);
+        }
+    }
+}
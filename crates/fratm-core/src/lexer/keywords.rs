@@ -0,0 +1,463 @@
+//! # Keyword Reference Table
+//!
+//! The single source of truth for FratmScript's reserved words. The lexer
+//! uses it to recognize keywords (see [`lookup_keyword`](super::lookup_keyword)),
+//! and editors use it for hover tooltips and the cheat sheet.
+//!
+//! Multi-word keywords (`chist è`, `mentre che`, ...) have one entry per
+//! word; every word carries the full `phrase` it belongs to.
+//!
+//! ## Dialects
+//!
+//! A [`KeywordTable`] is the set of words the lexer treats as keywords.
+//! Neapolitan is the default; a dialect pack renames some of its words
+//! (`tien` → `tiè`) and keeps the grammar, so `mentre che` can become
+//! `mentre ché` but not a single word.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::TokenKind;
+
+/// Reference information for one keyword.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct KeywordInfo {
+    /// The word as the lexer sees it (e.g. `chist`)
+    pub word: &'static str,
+    /// The complete construct the word belongs to (e.g. `chist è`)
+    pub phrase: &'static str,
+    /// Token produced by the lexer
+    pub kind: TokenKind<'static>,
+    /// JavaScript equivalent
+    pub javascript: &'static str,
+    /// One-line description
+    pub description: &'static str,
+    /// Example snippet
+    pub example: &'static str,
+}
+
+macro_rules! keywords {
+    ($($word:literal, $phrase:literal, $kind:ident, $js:literal, $desc:literal, $example:literal;)*) => {
+        &[$(KeywordInfo { word: $word, phrase: $phrase, kind: TokenKind::$kind, javascript: $js, description: $desc, example: $example }),*]
+    };
+}
+
+/// Every FratmScript keyword, in the order they appear in the docs.
+pub static KEYWORDS: &[KeywordInfo] = keywords! {
+    "chist", "chist è", Chist, "const", "Declares a constant", "chist è nome = \"Gennaro\"";
+    "è", "chist è", E, "const", "Declares a constant", "chist è nome = \"Gennaro\"";
+    "tien", "tien", Tien, "let", "Declares a variable", "tien contatore = 0";
+    "facc", "facc", Facc, "function", "Declares a function", "facc saluta(nome) { piglie \"Uè \" + nome }";
+    "piglie", "piglie", Piglie, "return", "Returns a value from a function", "piglie risultato";
+    "si", "si", Si, "if", "Runs a block when the condition holds", "si (x > 0) { stamm a dì(x) }";
+    "sinnò", "sinnò", Sinno, "else", "Runs a block when the condition fails", "si (x > 0) { } sinnò { }";
+    "pe", "pe", Pe, "for", "Loops with init, condition and update", "pe (tien i = 0; i < 3; i = i + 1) { }";
+    "ogni", "pe ogni", Ogni, "for", "Optional word after `pe`", "pe ogni (tien i = 0; i < 3; i = i + 1) { }";
+    "mentre", "mentre che", Mentre, "while", "Loops while the condition holds", "mentre che (x < 10) { x = x + 1 }";
+    "che", "mentre che", Che, "while", "Loops while the condition holds", "mentre che (x < 10) { x = x + 1 }";
+    "overo", "overo", Overo, "true", "Boolean true", "tien pronto = overo";
+    "sfòls", "sfòls", Sfols, "false", "Boolean false", "tien pronto = sfòls";
+    "nisciun", "nisciun", Nisciun, "null", "No value", "tien niente = nisciun";
+    "boh", "boh", Boh, "undefined", "Undefined value", "tien chissà = boh";
+    "stamm", "stamm a dì", Stamm, "console.log", "Prints to the console", "stamm a dì(\"Uè!\")";
+    "dì", "stamm a dì", Di, "console.log", "Prints to the console", "stamm a dì(\"Uè!\")";
+    "avvis", "avvis a dì", Avvis, "console.warn", "Prints a warning", "avvis a dì(\"Attenzione!\")";
+    "scrive", "scrive a dì", Scrive, "console.error", "Prints an error", "scrive a dì(\"Guaio!\")";
+    "mo", "mo vir", Mo, "async", "Declares an async function", "mo vir facc carica() { }";
+    "vir", "mo vir", Vir, "async", "Declares an async function", "mo vir facc carica() { }";
+    "aspett", "aspett", Aspett, "await", "Waits for a promise", "chist è dati = aspett carica()";
+    "pruvamm", "pruvamm", Pruvamm, "try", "Runs code that may throw", "pruvamm { } e si schiatta (err) { }";
+    "schiatta", "e si schiatta", Schiatta, "catch", "Handles an error thrown in `pruvamm`", "pruvamm { } e si schiatta (err) { }";
+    "iett", "iett", Iett, "throw", "Throws an error", "iett \"Guaio!\"";
+    "nu", "nu bell", Nu, "new", "Creates an instance", "chist è p = nu bell Pizza()";
+    "bell", "nu bell", Bell, "new", "Creates an instance", "chist è p = nu bell Pizza()";
+    "na", "na famiglie", Na, "class", "Declares a class", "na famiglie Pizza { }";
+    "famiglie", "na famiglie", Famiglie, "class", "Declares a class", "na famiglie Pizza { }";
+    "stu", "stu cos", Stu, "this", "The current object", "stu cos.nome = nome";
+    "cos", "stu cos", Cos, "this", "The current object", "stu cos.nome = nome";
+    "chiamm", "chiamm", Chiamm, "import", "Imports from a module", "chiamm { pizza } da \"./cucina\"";
+    "da", "da", Da, "from", "Names the module to import from", "chiamm { pizza } da \"./cucina\"";
+    "mann", "mann for", Mann, "export", "Exports from a module", "mann for facc pizza() { }";
+    "for", "mann for", For, "export", "Exports from a module", "mann for facc pizza() { }";
+    "predefinit", "mann for predefinit", Predefinit, "default", "Default export", "mann for predefinit pizza";
+    "rompe", "rompe", Rompe, "break", "Exits the current loop", "rompe";
+    "salta", "salta", Salta, "continue", "Skips to the next iteration", "salta";
+    "caso", "caso", Caso, "case", "Switch case (reserved)", "// caso: riservata, nun se pò ancora usà";
+    "fisso", "fisso", Fisso, "static", "Static class member (reserved)", "// fisso: riservata, nun se pò ancora usà";
+    "figlio", "figlio 'e", Figlio, "extends", "Class inheritance", "na famiglie ErroreMio figlio 'e Errore { }";
+    "'e", "figlio 'e", De, "extends", "Class inheritance", "na famiglie ErroreMio figlio 'e Errore { }";
+    "leva", "leva", Leva, "delete", "Removes a property", "leva oggetto.citta";
+    "caccia", "caccia", Caccia, "yield", "Yields from a generator (reserved)", "// caccia: riservata, nun se pò ancora usà";
+    "fermete", "fermete", Fermete, "debugger", "Pauses in the debugger", "fermete";
+    "buono", "vir buono", Buono, "match", "Picks the first pattern the value matches", "vir buono (x) { 0 => \"zero\", _ => \"ato\" }";
+    "giura", "giura che", Giura, "console.assert", "Fails if the condition doesn't hold", "giura che (x > 0) sinnò \"x adda essere positivo\"";
+    "tutt'", "tutt' quant", Tutt, "Promise.all", "Waits for every promise", "chist è pizze = aspett tutt' quant([margherita(), marinara()])";
+    "'o", "'o primm che arriva", O, "Promise.race", "Settles like the first promise that settles", "chist è pizza = aspett 'o primm che arriva([forno(), asporto()])";
+    "e", "e", And, "&&", "Logical and", "si (a e b) { }";
+    "pure", "pure", Pure, "&&", "Logical and (alias of `e`)", "si (a pure b) { }";
+    "o", "o", Or, "||", "Logical or", "si (a o b) { }";
+    "no", "no", Not, "!", "Logical not", "si (no pronto) { }";
+    "manco", "manco", Manco, "!", "Logical not (alias of `no`)", "si (manco pronto) { }";
+};
+
+/// Every operator and punctuation token, as the lexer reads them. Their
+/// text is their [`Display`](std::fmt::Display) form.
+pub static OPERATORS: &[TokenKind<'static>] = &[
+    TokenKind::Plus, TokenKind::Minus, TokenKind::Star, TokenKind::Slash, TokenKind::Percent, TokenKind::StarStar,
+    TokenKind::EqualEqual, TokenKind::EqualEqualEqual, TokenKind::BangEqual, TokenKind::BangEqualEqual,
+    TokenKind::Less, TokenKind::Greater, TokenKind::LessEqual, TokenKind::GreaterEqual,
+    TokenKind::Equal, TokenKind::PlusEqual, TokenKind::MinusEqual, TokenKind::StarEqual, TokenKind::SlashEqual,
+    TokenKind::LeftParen, TokenKind::RightParen, TokenKind::LeftBrace, TokenKind::RightBrace,
+    TokenKind::LeftBracket, TokenKind::RightBracket, TokenKind::Comma, TokenKind::Dot, TokenKind::Colon,
+    TokenKind::Semicolon, TokenKind::Question, TokenKind::Arrow,
+];
+
+/// Neapolitan names of `console` methods, for `stamm.tabella(...)` and
+/// friends. Other names go through as they are, so `stamm.table` works too.
+pub static CONSOLE_METHODS: &[(&str, &str)] = &[
+    ("tabella", "table"),
+    ("informa", "info"),
+    ("tempo", "time"),
+    ("fineTempo", "timeEnd"),
+    ("conta", "count"),
+    ("gruppo", "group"),
+    ("fineGruppo", "groupEnd"),
+    ("traccia", "trace"),
+    ("pulizza", "clear"),
+];
+
+/// The JavaScript name of the `console` method `name`.
+///
+/// ```rust
+/// use fratm_core::lexer::keywords::console_method;
+///
+/// assert_eq!(console_method("tabella"), "table");
+/// assert_eq!(console_method("dir"), "dir");
+/// ```
+pub fn console_method(name: &str) -> &str {
+    CONSOLE_METHODS.iter().find(|(alias, _)| *alias == name).map_or(name, |(_, js)| js)
+}
+
+/// Neapolitan names of JavaScript classes, after `figlio 'e` and in a typed
+/// catch (`e si schiatta (err è uno Errore)`). Elsewhere they are names like any other.
+pub static CLASS_ALIASES: &[(&str, &str)] = &[
+    ("Errore", "Error"),
+];
+
+/// The JavaScript name of the class `name` in `figlio 'e name`.
+///
+/// ```rust
+/// use fratm_core::lexer::keywords::class_name;
+///
+/// assert_eq!(class_name("Errore"), "Error");
+/// assert_eq!(class_name("Pizza"), "Pizza");
+/// ```
+pub fn class_name(name: &str) -> &str {
+    CLASS_ALIASES.iter().find(|(alias, _)| *alias == name).map_or(name, |(_, js)| js)
+}
+
+/// The keywords of a dialect, as the lexer uses them.
+///
+/// ```rust
+/// use fratm_core::lexer::keywords::KeywordTable;
+/// use fratm_core::lexer::TokenKind;
+///
+/// let romanesco = KeywordTable::dialect("romanesco", [("tien", "tiè"), ("stamm", "damme")]).unwrap();
+/// assert_eq!(romanesco.lookup("tiè"), Some(TokenKind::Tien));
+/// assert_eq!(romanesco.lookup("tien"), None); // free to use as a name
+/// assert_eq!(romanesco.lookup("facc"), Some(TokenKind::Facc));
+/// assert_eq!(KeywordTable::default().name(), "napulitano");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "DialectPack"))]
+pub struct KeywordTable {
+    name: Cow<'static, str>,
+    /// Dialect words, each replacing the Neapolitan word for the same token
+    words: Vec<(String, TokenKind<'static>)>,
+}
+
+/// A dialect pack as written in `fratm.json`: a name and the Neapolitan
+/// words it renames.
+///
+/// ```json
+/// { "name": "romanesco", "words": { "tien": "tiè", "stamm": "damme" } }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct DialectPack {
+    pub name: String,
+    pub words: BTreeMap<String, String>,
+}
+
+impl KeywordTable {
+    /// The Neapolitan keywords of [`KEYWORDS`].
+    pub const fn neapolitan() -> Self {
+        Self { name: Cow::Borrowed("napulitano"), words: Vec::new() }
+    }
+
+    /// Neapolitan with the words in `renames` (Neapolitan word, dialect word) replaced.
+    ///
+    /// Fails if a Neapolitan word is not a keyword, if a dialect word is not
+    /// a valid name, or if two keywords end up with the same word.
+    pub fn dialect<S: AsRef<str>, T: Into<String>>(name: impl Into<String>, renames: impl IntoIterator<Item = (S, T)>) -> Result<Self, String> {
+        let mut table = Self { name: Cow::Owned(name.into()), words: Vec::new() };
+        for (word, replacement) in renames {
+            let (word, replacement) = (word.as_ref(), replacement.into());
+            let Some(keyword) = KEYWORDS.iter().find(|k| k.word == word) else {
+                return Err(format!("'{}' is not a keyword", word));
+            };
+            // Apostrophes are allowed between letters (`dint'a`)
+            let mut chars = replacement.chars();
+            let inner = |c: char| super::is_ident_continue(c) || c == '\'';
+            if !chars.next().is_some_and(super::is_ident_start) || !chars.all(inner) || replacement.ends_with('\'') {
+                return Err(format!("'{}' is not a valid keyword", replacement));
+            }
+            table.words.push((replacement, keyword.kind.clone()));
+        }
+        for keyword in KEYWORDS {
+            let word = table.word(&keyword.kind);
+            if table.lookup(word).as_ref() != Some(&keyword.kind) {
+                return Err(format!("'{}' is used for more than one keyword", word));
+            }
+        }
+        Ok(table)
+    }
+
+    /// Name of the dialect (`napulitano` by default).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The token for `word`, if it is a keyword of this dialect. Like
+    /// [`lookup_keyword`](super::lookup_keyword), accents are optional.
+    pub fn lookup(&self, word: &str) -> Option<TokenKind<'static>> {
+        let own = self.words.iter().find(|(w, _)| w == word)
+            .or_else(|| self.words.iter().find(|(w, _)| same_ignoring_accents(w, word)));
+        if let Some((_, kind)) = own {
+            return Some(kind.clone());
+        }
+        super::lookup_keyword(word).filter(|kind| self.words.iter().all(|(_, k)| k != kind))
+    }
+
+    /// `text` with its Neapolitan keywords in this dialect's words. Double
+    /// quoted strings are left as they are.
+    fn translate(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let (mut word, mut in_string) = (String::new(), false);
+        // Apostrophes belong to words: `'e`, `tutt'`
+        for c in text.chars().map(Some).chain([None]) {
+            if let Some(c) = c.filter(|&c| !in_string && (super::is_ident_continue(c) || c == '\'')) {
+                word.push(c);
+                continue;
+            }
+            if !word.is_empty() {
+                out.push_str(KEYWORDS.iter().find(|k| k.word == word).map_or(word.as_str(), |k| self.word(&k.kind)));
+                word.clear();
+            }
+            if let Some(c) = c {
+                in_string ^= c == '"';
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    /// The word this dialect uses for the keyword token `kind`.
+    pub(crate) fn word(&self, kind: &TokenKind) -> &str {
+        match self.words.iter().find(|(_, k)| k == kind) {
+            Some((word, _)) => word,
+            None => KEYWORDS.iter().find(|k| k.kind == *kind).map_or("", |k| k.word),
+        }
+    }
+}
+
+impl Default for KeywordTable {
+    fn default() -> Self {
+        Self::neapolitan()
+    }
+}
+
+impl TryFrom<DialectPack> for KeywordTable {
+    type Error = String;
+
+    fn try_from(pack: DialectPack) -> Result<Self, String> {
+        Self::dialect(pack.name, pack.words)
+    }
+}
+
+/// Whether `keyword` and `text` are the same word once accents are dropped
+/// (`sfòls` and `sfols`).
+pub(crate) fn same_ignoring_accents(keyword: &str, text: &str) -> bool {
+    keyword.chars().map(plain).eq(text.chars().map(plain))
+}
+
+/// `word` with its accents dropped (`sfòls` → `sfols`).
+#[cfg(feature = "serde")]
+pub(crate) fn without_accents(word: &str) -> String {
+    word.chars().map(plain).collect()
+}
+
+fn plain(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' => 'a',
+        'è' | 'é' | 'ê' => 'e',
+        'ì' | 'í' | 'î' => 'i',
+        'ò' | 'ó' | 'ô' => 'o',
+        'ù' | 'ú' | 'û' => 'u',
+        c => c,
+    }
+}
+
+/// Looks up a keyword by word (`chist`) or full phrase (`chist è`).
+///
+/// # Example
+///
+/// ```rust
+/// use fratm_core::lexer::keywords::keyword_info;
+///
+/// assert_eq!(keyword_info("tien").unwrap().javascript, "let");
+/// assert_eq!(keyword_info("mentre che").unwrap().javascript, "while");
+/// assert!(keyword_info("pizza").is_none());
+/// ```
+pub fn keyword_info(word: &str) -> Option<&'static KeywordInfo> {
+    KEYWORDS.iter()
+        .find(|k| k.word == word)
+        .or_else(|| KEYWORDS.iter().find(|k| k.phrase == word))
+}
+
+/// Returns every keyword entry.
+pub fn all_keywords() -> &'static [KeywordInfo] {
+    KEYWORDS
+}
+
+/// One construct of the cheat sheet, in the words of a dialect.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CheatSheetEntry {
+    pub phrase: String,
+    pub javascript: &'static str,
+    pub description: &'static str,
+    pub example: String,
+}
+
+impl CheatSheetEntry {
+    /// Whether `term` is in the phrase, the JavaScript or the description,
+    /// ignoring case and accents.
+    pub fn matches(&self, term: &str) -> bool {
+        let fold = |text: &str| text.to_lowercase().chars().map(plain).collect::<String>();
+        let term = fold(term);
+        [self.phrase.as_str(), self.javascript, self.description].iter().any(|text| fold(text).contains(&term))
+    }
+}
+
+/// The keyword table as a cheat sheet: one entry per construct, in the
+/// order of [`KEYWORDS`], with the words of `table`.
+///
+/// ```rust
+/// use fratm_core::lexer::keywords::{cheat_sheet, KeywordTable};
+///
+/// let sheet = cheat_sheet(&KeywordTable::default());
+/// let entry = sheet.iter().find(|entry| entry.javascript == "while").unwrap();
+/// assert_eq!((entry.phrase.as_str(), entry.example.as_str()), ("mentre che", "mentre che (x < 10) { x = x + 1 }"));
+///
+/// let romanesco = KeywordTable::dialect("romanesco", [("tien", "tiè")]).unwrap();
+/// assert_eq!(cheat_sheet(&romanesco)[1].example, "tiè contatore = 0");
+/// ```
+pub fn cheat_sheet(table: &KeywordTable) -> Vec<CheatSheetEntry> {
+    let mut entries: Vec<CheatSheetEntry> = Vec::new();
+    for keyword in KEYWORDS {
+        if entries.last().is_some_and(|last| last.javascript == keyword.javascript && last.description == keyword.description) {
+            continue;
+        }
+        entries.push(CheatSheetEntry {
+            phrase: table.translate(keyword.phrase),
+            javascript: keyword.javascript,
+            description: keyword.description,
+            example: table.translate(keyword.example),
+        });
+    }
+    entries
+}
+
+/// `entries` as a Markdown table.
+pub fn cheat_sheet_markdown(entries: &[CheatSheetEntry]) -> String {
+    let cell = |text: &str| text.replace('|', "\\|");
+    let mut out = String::from("| FratmScript | JavaScript | Description | Example |\n|---|---|---|---|\n");
+    for entry in entries {
+        out.push_str(&format!("| `{}` | `{}` | {} | `{}` |\n", cell(&entry.phrase), cell(entry.javascript), cell(entry.description), cell(&entry.example)));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_examples_compile() {
+        for keyword in KEYWORDS {
+            assert!(crate::compile(keyword.example, Default::default()).is_ok(), "{}: {}", keyword.word, keyword.example);
+        }
+    }
+
+    #[test]
+    fn test_words_are_unique() {
+        for (i, k) in KEYWORDS.iter().enumerate() {
+            assert!(KEYWORDS[i + 1..].iter().all(|other| other.word != k.word), "duplicate keyword {}", k.word);
+        }
+    }
+
+    #[test]
+    fn test_phrase_contains_word() {
+        for k in KEYWORDS {
+            assert!(k.phrase.split(' ').any(|w| w == k.word), "{} not in {}", k.word, k.phrase);
+        }
+    }
+
+    #[test]
+    fn test_operators_lex_as_listed() {
+        for kind in OPERATORS {
+            let text = kind.to_string();
+            assert_eq!(&super::super::Lexer::new(&text).tokenize()[0].kind, kind, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_dialect_errors() {
+        assert!(KeywordTable::dialect("x", [("pizza", "pizz")]).unwrap_err().contains("pizza"));
+        assert!(KeywordTable::dialect("x", [("tien", "tiè!")]).is_err());
+        // `facc` still means `function`
+        assert!(KeywordTable::dialect("x", [("tien", "facc")]).is_err());
+        assert!(KeywordTable::dialect("x", [("tien", "facc"), ("facc", "fà")]).is_ok());
+        assert!(KeywordTable::dialect("x", [("tien", "fà"), ("facc", "fà")]).is_err());
+    }
+
+    #[test]
+    fn test_cheat_sheet() {
+        let sheet = cheat_sheet(&KeywordTable::default());
+        // One entry per construct, and every construct has one
+        assert_eq!(sheet.iter().filter(|entry| entry.javascript == "const").count(), 1);
+        assert!(KEYWORDS.iter().all(|k| sheet.iter().any(|entry| entry.phrase == k.phrase)));
+        let dialect = KeywordTable::dialect("x", [("stamm", "dimme"), ("'e", "dde")]).unwrap();
+        let translated = cheat_sheet(&dialect);
+        assert!(translated.iter().any(|entry| entry.example == "dimme a dì(\"Uè!\")"));
+        assert!(translated.iter().any(|entry| entry.phrase == "figlio dde"));
+        // Words in strings stay
+        assert_eq!(KeywordTable::dialect("x", [("si", "se")]).unwrap().translate("si (\"si\") { }"), "se (\"si\") { }");
+        assert!(sheet.iter().filter(|entry| entry.matches("WHILE")).count() == 1 && sheet.iter().any(|entry| entry.matches("sfols")));
+    }
+
+    #[test]
+    fn test_console_aliases_are_not_js_names() {
+        for (alias, js) in CONSOLE_METHODS {
+            assert!(CONSOLE_METHODS.iter().all(|(_, other)| other != alias), "{} is also a JavaScript name", alias);
+            assert_eq!(console_method(js), *js);
+        }
+    }
+}
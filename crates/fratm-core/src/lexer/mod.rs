@@ -27,14 +27,34 @@
 //! assert!(matches!(tokens[4].kind, TokenKind::Number(42.0)));
 //! ```
 //!
+//! ## Identifiers
+//!
+//! Names follow the default identifiers of [UAX #31]: a letter or `_`,
+//! then letters, digits, marks and `_` (`XID_Start` and `XID_Continue`).
+//! They are compared in NFC, so `è` typed as `e` and a combining accent is
+//! the same name, and the same keyword, as the single `è`. Names that only
+//! look alike, like Latin `a` and Cyrillic `а`, are told apart, and the
+//! linter warns about them.
+//!
+//! [UAX #31]: https://www.unicode.org/reports/tr31/
+//!
 //! ## Position Tracking
 //!
 //! Each token includes information about its position in the source code
 //! (line, column, offset). This enables precise error messages.
+//!
+//! ## Keyword Reference
+//!
+//! The [`keywords`] module holds the keyword table, with JavaScript
+//! equivalents and examples for editor tooling.
+
+use std::borrow::Cow;
 
+pub mod keywords;
 mod token;
 
-pub use token::{lookup_keyword, Span, Token, TokenKind};
+use keywords::{KeywordTable, KEYWORDS};
+pub use token::{lookup_keyword, Comment, CommentKind, Span, Token, TokenKind};
 
 /// Lexical analyzer for FratmScript.
 ///
@@ -61,7 +81,7 @@ pub use token::{lookup_keyword, Span, Token, TokenKind};
 /// ```rust
 /// use fratm_core::lexer::{Lexer, TokenKind};
 ///
-/// let mut lexer = Lexer::new("@"); // Invalid character
+/// let mut lexer = Lexer::new("§"); // Invalid character
 /// let tokens = lexer.tokenize();
 /// assert!(matches!(tokens[0].kind, TokenKind::Invalid(_)));
 /// ```
@@ -82,6 +102,38 @@ pub struct Lexer<'a> {
     token_line: usize,
     /// Column where current token starts
     token_column: usize,
+    /// Comments skipped so far, in source order
+    comments: Vec<Comment>,
+    /// Whether the `Eof` token was returned by the iterator
+    finished: bool,
+    /// Dialect keywords, if not Neapolitan
+    keywords: Option<&'a KeywordTable>,
+    /// A `/*` comment that ran to the end of the file, reported as the next token
+    unclosed_comment: Option<Span>,
+    /// Whether `<` may open a JSX element
+    jsx: bool,
+    /// The JSX being read, innermost last
+    jsx_modes: Vec<JsxMode>,
+    /// Whether the last token ends an operand, after which `<` is "less than"
+    after_operand: bool,
+    /// Whether `{` in a double-quoted string starts an interpolation
+    interpolation: bool,
+    /// Interpolations being read, innermost last, with the number of `{`
+    /// opened in each since it started
+    interpolations: Vec<usize>,
+}
+
+/// Where the lexer is inside a JSX element
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JsxMode {
+    /// Between `<` and `>` of an opening tag
+    Tag,
+    /// Between `</` and `>`
+    ClosingTag,
+    /// Between the tags: text, `{...}` and nested elements
+    Children,
+    /// Inside `{...}`, with the number of `{` opened in it since
+    Expression(usize),
 }
 
 impl<'a> Lexer<'a> {
@@ -108,9 +160,86 @@ impl<'a> Lexer<'a> {
             token_start: 0,
             token_line: 1,
             token_column: 1,
+            comments: Vec::new(),
+            finished: false,
+            keywords: None,
+            unclosed_comment: None,
+            jsx: false,
+            jsx_modes: Vec::new(),
+            after_operand: false,
+            interpolation: false,
+            interpolations: Vec::new(),
         }
     }
 
+    /// Recognizes the keywords of `table` instead of the Neapolitan ones.
+    ///
+    /// ```rust
+    /// use fratm_core::lexer::keywords::KeywordTable;
+    /// use fratm_core::lexer::{Lexer, TokenKind};
+    ///
+    /// let milanese = KeywordTable::dialect("milanese", [("tien", "ten")]).unwrap();
+    /// let tokens = Lexer::new("ten x").with_keywords(&milanese).tokenize();
+    /// assert_eq!(tokens[0].kind, TokenKind::Tien);
+    /// ```
+    pub fn with_keywords(mut self, table: &'a KeywordTable) -> Self {
+        self.keywords = Some(table);
+        self
+    }
+
+    /// Reads JSX elements, `<Saluto nome={nome} />`, where an expression
+    /// can start. A `<` after an operand is still "less than".
+    ///
+    /// ```rust
+    /// use fratm_core::lexer::{Lexer, TokenKind};
+    ///
+    /// let kinds: Vec<TokenKind> = Lexer::new("<b>Uè</b>").with_jsx(true).map(|t| t.kind).collect();
+    /// assert_eq!(kinds[..3], [TokenKind::JsxTagStart, TokenKind::JsxName("b"), TokenKind::JsxTagEnd]);
+    /// assert_eq!(kinds[3], TokenKind::JsxText("Uè"));
+    /// ```
+    pub fn with_jsx(mut self, enabled: bool) -> Self {
+        self.jsx = enabled;
+        self
+    }
+
+    /// Reads `{...}` in double-quoted strings as expressions to put in the
+    /// text, as JavaScript does with `${...}` in backticks. `{{` and `}}`
+    /// are literal braces; single-quoted strings are left as they are.
+    ///
+    /// ```rust
+    /// use fratm_core::lexer::{Lexer, TokenKind};
+    ///
+    /// let kinds: Vec<TokenKind> = Lexer::new("\"Ciao {nome}! {{}}\"").with_interpolation(true).map(|t| t.kind).collect();
+    /// assert_eq!(kinds[0], TokenKind::TemplateHead("Ciao ".into()));
+    /// assert_eq!(kinds[1], TokenKind::Identifier("nome"));
+    /// assert_eq!(kinds[2], TokenKind::TemplateTail("! {}".into()));
+    /// ```
+    pub fn with_interpolation(mut self, enabled: bool) -> Self {
+        self.interpolation = enabled;
+        self
+    }
+
+    /// Returns the comments skipped while tokenizing, in source order.
+    ///
+    /// Comments never become tokens; tools that need them (formatter,
+    /// comment passthrough) read them from here after tokenizing.
+    ///
+    /// ```rust
+    /// use fratm_core::lexer::Lexer;
+    ///
+    /// let mut lexer = Lexer::new("tien x // 'o contatore");
+    /// lexer.tokenize();
+    /// assert_eq!(lexer.comments()[0].text, "// 'o contatore");
+    /// ```
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
+    /// Takes ownership of the collected comments.
+    pub fn take_comments(&mut self) -> Vec<Comment> {
+        std::mem::take(&mut self.comments)
+    }
+
     /// Tokenizes the entire source code and returns all tokens.
     ///
     /// Continues reading tokens until reaching end of file (EOF).
@@ -128,17 +257,8 @@ impl<'a> Lexer<'a> {
     /// assert_eq!(tokens.len(), 4);
     /// assert!(matches!(tokens.last().unwrap().kind, TokenKind::Eof));
     /// ```
-    pub fn tokenize(&mut self) -> Vec<Token> {
-        let mut tokens = Vec::new();
-        loop {
-            let token = self.next_token();
-            let is_eof = token.kind == TokenKind::Eof;
-            tokens.push(token);
-            if is_eof {
-                break;
-            }
-        }
-        tokens
+    pub fn tokenize(&mut self) -> Vec<Token<'a>> {
+        self.by_ref().collect()
     }
 
     /// Reads and returns the next token from the source.
@@ -154,8 +274,32 @@ impl<'a> Lexer<'a> {
     /// - Literals (numbers, strings)
     /// - Punctuation (`(`, `)`, `{`, `}`, etc.)
     /// - Identifiers (variable/function names)
-    pub fn next_token(&mut self) -> Token {
+    /// - JSX tags and text, see [`Lexer::with_jsx`]
+    /// - Interpolated strings, see [`Lexer::with_interpolation`]
+    pub fn next_token(&mut self) -> Token<'a> {
+        let token = match self.jsx_modes.last() {
+            Some(JsxMode::Tag | JsxMode::ClosingTag) => self.scan_jsx_tag(),
+            Some(JsxMode::Children) => self.scan_jsx_child(),
+            Some(JsxMode::Expression(_)) | None => {
+                let token = self.scan_token();
+                self.track_braces(&token.kind);
+                token
+            }
+        };
+        self.track_interpolation(&token.kind);
+        self.after_operand = matches!(
+            token.kind,
+            TokenKind::Identifier(_) | TokenKind::Number(_) | TokenKind::String(_) | TokenKind::TemplateTail(_) | TokenKind::RightParen | TokenKind::RightBracket
+                | TokenKind::RightBrace | TokenKind::Overo | TokenKind::Sfols | TokenKind::Nisciun | TokenKind::Boh | TokenKind::Cos
+        );
+        token
+    }
+
+    fn scan_token(&mut self) -> Token<'a> {
         self.skip_whitespace_and_comments();
+        if let Some(span) = self.unclosed_comment.take() {
+            return Token::new(TokenKind::Invalid("Unterminated comment".into()), span, &self.source[span.start..span.end]);
+        }
         self.mark_token_start();
 
         match self.advance() {
@@ -164,6 +308,10 @@ impl<'a> Lexer<'a> {
                 '(' => self.make_token(TokenKind::LeftParen),
                 ')' => self.make_token(TokenKind::RightParen),
                 '{' => self.make_token(TokenKind::LeftBrace),
+                '}' if self.interpolations.last() == Some(&0) => {
+                    self.interpolations.pop();
+                    self.scan_template(true)
+                }
                 '}' => self.make_token(TokenKind::RightBrace),
                 '[' => self.make_token(TokenKind::LeftBracket),
                 ']' => self.make_token(TokenKind::RightBracket),
@@ -172,6 +320,8 @@ impl<'a> Lexer<'a> {
                 ':' => self.make_token(TokenKind::Colon),
                 ';' => self.make_token(TokenKind::Semicolon),
                 '?' => self.make_token(TokenKind::Question),
+                '#' => self.make_token(TokenKind::Hash),
+                '@' => self.make_token(TokenKind::At),
                 
                 '+' => {
                     if self.match_char('=') {
@@ -230,6 +380,10 @@ impl<'a> Lexer<'a> {
                         self.make_token(TokenKind::Not)
                     }
                 }
+                '<' if self.opens_jsx() => {
+                    self.jsx_modes.push(JsxMode::Tag);
+                    self.make_token(TokenKind::JsxTagStart)
+                }
                 '<' => {
                     if self.match_char('=') {
                         self.make_token(TokenKind::LessEqual)
@@ -245,17 +399,158 @@ impl<'a> Lexer<'a> {
                     }
                 }
                 
+                '"' if self.interpolation => self.scan_template(false),
+                '\'' if self.keyword_after_apostrophe() => self.scan_identifier(),
                 '"' | '\'' => self.scan_string(c),
                 c if c.is_ascii_digit() => self.scan_number(),
                 c if is_ident_start(c) => self.scan_identifier(),
                 
-                '\n' => {
-                    self.line += 1;
-                    self.column = 1;
+                '\n' => self.make_token(TokenKind::Newline),
+                // `\r\n`, or a lone `\r` as old Mac files have
+                '\r' => {
+                    self.match_char('\n');
                     self.make_token(TokenKind::Newline)
                 }
                 
-                _ => self.make_token(TokenKind::Invalid(c.to_string())),
+                _ => self.make_token(TokenKind::Invalid(self.source[self.token_start..self.position].into())),
+            }
+        }
+    }
+
+    /// Whether the `<` just read opens a JSX element: `<Nome`, or `<>` for a fragment
+    fn opens_jsx(&mut self) -> bool {
+        self.jsx && !self.after_operand && self.peek().is_some_and(|c| is_ident_start(c) || c == '>')
+    }
+
+    /// Leaves a JSX `{...}` at the `}` that closes it
+    fn track_braces(&mut self, kind: &TokenKind) {
+        let Some(JsxMode::Expression(depth)) = self.jsx_modes.last_mut() else { return };
+        match kind {
+            TokenKind::LeftBrace => *depth += 1,
+            TokenKind::RightBrace if *depth == 0 => { self.jsx_modes.pop(); }
+            TokenKind::RightBrace => *depth -= 1,
+            _ => {}
+        }
+    }
+
+    /// Counts the braces inside an interpolation, so only the `}` that
+    /// closes it goes back to the string
+    fn track_interpolation(&mut self, kind: &TokenKind) {
+        let Some(depth) = self.interpolations.last_mut() else { return };
+        match kind {
+            TokenKind::LeftBrace => *depth += 1,
+            TokenKind::RightBrace => *depth -= 1,
+            _ => {}
+        }
+    }
+
+    /// Reads a double-quoted string up to its end or to the next `{`,
+    /// after the opening `"` or, if `resumed`, the `}` of an interpolation
+    fn scan_template(&mut self, resumed: bool) -> Token<'a> {
+        let content_start = self.position;
+        let mut value: Option<String> = None;
+        let content = |lexer: &Self, value: Option<String>, end: usize| match value {
+            Some(value) => Cow::Owned(value),
+            None => Cow::Borrowed(&lexer.source[content_start..end]),
+        };
+
+        while let Some(c) = self.peek() {
+            match c {
+                '"' => {
+                    let content = content(self, value, self.position);
+                    self.advance();
+                    return self.make_token(if resumed { TokenKind::TemplateTail(content) } else { TokenKind::String(content) });
+                }
+                '{' | '}' if self.peek_next() == Some(c) => {
+                    let value = value.get_or_insert_with(|| self.source[content_start..self.position].to_string());
+                    self.advance();
+                    self.advance();
+                    value.push(c);
+                }
+                '{' => {
+                    let content = content(self, value, self.position);
+                    self.advance();
+                    self.interpolations.push(0);
+                    return self.make_token(if resumed { TokenKind::TemplateMiddle(content) } else { TokenKind::TemplateHead(content) });
+                }
+                '\\' => {
+                    let value = value.get_or_insert_with(|| self.source[content_start..self.position].to_string());
+                    self.advance();
+                    match self.advance() {
+                        Some('n') => value.push('\n'),
+                        Some('t') => value.push('\t'),
+                        Some('r') => value.push('\r'),
+                        Some(c) => value.push(c),
+                        None => break,
+                    }
+                }
+                c => {
+                    self.advance();
+                    if let Some(value) = &mut value { value.push(c); }
+                }
+            }
+        }
+
+        self.make_token(TokenKind::Invalid("Unterminated string".into()))
+    }
+
+    fn scan_jsx_tag(&mut self) -> Token<'a> {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.advance();
+        }
+        self.mark_token_start();
+        match self.advance() {
+            None => self.make_token(TokenKind::Eof),
+            Some('>') => {
+                if self.jsx_modes.pop() == Some(JsxMode::Tag) {
+                    self.jsx_modes.push(JsxMode::Children);
+                }
+                self.make_token(TokenKind::JsxTagEnd)
+            }
+            Some('/') if self.match_char('>') => {
+                self.jsx_modes.pop();
+                self.make_token(TokenKind::JsxSelfClosing)
+            }
+            Some('=') => self.make_token(TokenKind::Equal),
+            Some('{') => {
+                self.jsx_modes.push(JsxMode::Expression(0));
+                self.make_token(TokenKind::LeftBrace)
+            }
+            Some(c @ ('"' | '\'')) => self.scan_string(c),
+            Some(c) if is_ident_start(c) => {
+                while self.peek().is_some_and(|c| is_ident_continue(c) || matches!(c, '-' | '.' | ':')) {
+                    self.advance();
+                }
+                self.make_token(TokenKind::JsxName(&self.source[self.token_start..self.position]))
+            }
+            Some(_) => self.make_token(TokenKind::Invalid(self.source[self.token_start..self.position].into())),
+        }
+    }
+
+    fn scan_jsx_child(&mut self) -> Token<'a> {
+        self.mark_token_start();
+        match self.peek() {
+            None => self.make_token(TokenKind::Eof),
+            Some('{') => {
+                self.advance();
+                self.jsx_modes.push(JsxMode::Expression(0));
+                self.make_token(TokenKind::LeftBrace)
+            }
+            Some('<') => {
+                self.advance();
+                if self.match_char('/') {
+                    if let Some(mode) = self.jsx_modes.last_mut() { *mode = JsxMode::ClosingTag; }
+                    self.make_token(TokenKind::JsxClosingTagStart)
+                } else {
+                    self.jsx_modes.push(JsxMode::Tag);
+                    self.make_token(TokenKind::JsxTagStart)
+                }
+            }
+            Some(_) => {
+                while !matches!(self.peek(), Some('{' | '<') | None) {
+                    self.advance();
+                }
+                self.make_token(TokenKind::JsxText(&self.source[self.token_start..self.position]))
             }
         }
     }
@@ -263,29 +558,33 @@ impl<'a> Lexer<'a> {
     fn skip_whitespace_and_comments(&mut self) {
         loop {
             match self.peek() {
-                Some(' ') | Some('\t') | Some('\r') => {
+                Some(' ') | Some('\t') => {
                     self.advance();
                 }
                 Some('/') => {
                     if self.peek_next() == Some('/') {
-                        while self.peek() != Some('\n') && self.peek().is_some() {
+                        self.mark_token_start();
+                        while !matches!(self.peek(), Some('\n' | '\r') | None) {
                             self.advance();
                         }
+                        self.push_comment(CommentKind::Line);
                     } else if self.peek_next() == Some('*') {
+                        self.mark_token_start();
                         self.advance();
                         self.advance();
                         while !(self.peek() == Some('*') && self.peek_next() == Some('/')) {
                             if self.peek().is_none() {
                                 break;
                             }
-                            if self.peek() == Some('\n') {
-                                self.line += 1;
-                                self.column = 0;
-                            }
                             self.advance();
                         }
+                        let closed = self.peek().is_some();
                         self.advance();
                         self.advance();
+                        self.push_comment(CommentKind::Block);
+                        if !closed {
+                            self.unclosed_comment = self.comments.last().map(|comment| comment.span);
+                        }
                     } else {
                         break;
                     }
@@ -295,16 +594,28 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    fn push_comment(&mut self, kind: CommentKind) {
+        let token = self.make_token(TokenKind::Eof);
+        self.comments.push(Comment { kind, text: token.literal.to_string(), span: token.span });
+    }
+
     fn mark_token_start(&mut self) {
         self.token_start = self.position;
         self.token_line = self.line;
         self.token_column = self.column;
     }
 
+    /// Moves past the next character, keeping `line` and `column` up to
+    /// date: `\n`, `\r\n` and a lone `\r` all end a line.
     fn advance(&mut self) -> Option<char> {
         if let Some((pos, c)) = self.chars.next() {
             self.position = pos + c.len_utf8();
-            self.column += 1;
+            if c == '\n' || (c == '\r' && self.peek() != Some('\n')) {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
             Some(c)
         } else {
             None
@@ -330,28 +641,32 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn make_token(&self, kind: TokenKind) -> Token {
-        let literal = self.source[self.token_start..self.position].to_string();
+    fn make_token(&self, kind: TokenKind<'a>) -> Token<'a> {
+        let literal = &self.source[self.token_start..self.position];
         Token::new(
             kind,
-            Span::new(self.token_start, self.position, self.token_line, self.token_column),
+            Span::new(self.token_start, self.position, self.token_line, self.token_column)
+                .with_end(self.line, self.column),
             literal,
         )
     }
 
-    fn scan_string(&mut self, quote: char) -> Token {
-        let mut value = String::new();
-        
+    fn scan_string(&mut self, quote: char) -> Token<'a> {
+        let content_start = self.position;
+        // Only allocated once an escape sequence shows up
+        let mut value: Option<String> = None;
+
         while let Some(c) = self.peek() {
             if c == quote {
+                let content = match value {
+                    Some(value) => Cow::Owned(value),
+                    None => Cow::Borrowed(&self.source[content_start..self.position]),
+                };
                 self.advance();
-                return self.make_token(TokenKind::String(value));
-            }
-            if c == '\n' {
-                self.line += 1;
-                self.column = 0;
+                return self.make_token(TokenKind::String(content));
             }
             if c == '\\' {
+                let value = value.get_or_insert_with(|| self.source[content_start..self.position].to_string());
                 self.advance();
                 match self.peek() {
                     Some('n') => { self.advance(); value.push('\n'); }
@@ -365,14 +680,14 @@ impl<'a> Lexer<'a> {
                 }
             } else {
                 self.advance();
-                value.push(c);
+                if let Some(value) = &mut value { value.push(c); }
             }
         }
-        
-        self.make_token(TokenKind::Invalid("Unterminated string".to_string()))
+
+        self.make_token(TokenKind::Invalid("Unterminated string".into()))
     }
 
-    fn scan_number(&mut self) -> Token {
+    fn scan_number(&mut self) -> Token<'a> {
         while self.peek().map(|c| c.is_ascii_digit()).unwrap_or(false) {
             self.advance();
         }
@@ -391,31 +706,113 @@ impl<'a> Lexer<'a> {
         let literal = &self.source[self.token_start..self.position];
         match literal.parse::<f64>() {
             Ok(n) => self.make_token(TokenKind::Number(n)),
-            Err(_) => self.make_token(TokenKind::Invalid(format!("Invalid number: {}", literal))),
+            Err(_) => self.make_token(TokenKind::Invalid(format!("Invalid number: {}", literal).into())),
         }
     }
 
-    fn scan_identifier(&mut self) -> Token {
+    fn scan_identifier(&mut self) -> Token<'a> {
         while self.peek().map(is_ident_continue).unwrap_or(false) {
             self.advance();
         }
         
+        // An apostrophe between letters continues the word if that makes a keyword (`dint'a`)
+        while let Some(end) = self.keyword_with_apostrophe() {
+            while self.position < end {
+                self.advance();
+            }
+        }
+
         let literal = &self.source[self.token_start..self.position];
-        
-        if let Some(keyword) = lookup_keyword(literal) {
+        if let Some(keyword) = self.keyword(&nfc(literal)) {
             self.make_token(keyword)
         } else {
-            self.make_token(TokenKind::Identifier(literal.to_string()))
+            self.make_token(TokenKind::Identifier(literal))
+        }
+    }
+}
+
+/// Yields tokens one at a time, ending with `TokenKind::Eof`.
+///
+/// Unlike [`Lexer::tokenize`], nothing is buffered: pair it with
+/// [`Parser::streaming`](crate::parser::Parser::streaming) to parse while lexing.
+///
+/// ```rust
+/// use fratm_core::lexer::{Lexer, TokenKind};
+///
+/// let kinds: Vec<TokenKind> = Lexer::new("tien x").map(|t| t.kind).collect();
+/// assert_eq!(kinds.len(), 3);
+/// assert_eq!(kinds[2], TokenKind::Eof);
+/// ```
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        if self.finished {
+            return None;
+        }
+        let token = self.next_token();
+        self.finished = token.kind == TokenKind::Eof;
+        Some(token)
+    }
+}
+
+impl Lexer<'_> {
+    fn keyword(&self, word: &str) -> Option<TokenKind<'static>> {
+        match self.keywords {
+            Some(table) => table.lookup(word),
+            None => lookup_keyword(word),
+        }
+    }
+
+    /// End of the keyword the current word makes with an apostrophe and the
+    /// letters after it (`dint'a`), or with a last apostrophe (`tutt'`)
+    fn keyword_with_apostrophe(&self) -> Option<usize> {
+        let rest = self.source[self.position..].strip_prefix('\'')?;
+        let len = rest.find(|c| !is_ident_continue(c)).unwrap_or(rest.len());
+        let end = self.position + 1 + len;
+        self.keyword(&self.source[self.token_start..end]).is_some().then_some(end)
+    }
+
+    /// Whether the `'` just read starts a keyword (`'o`, `'e`) rather than a
+    /// string. The next word of its phrase must follow (`'o primm`), or the
+    /// word before it come first (`figlio 'e`), so `'o sole mio'` stays a string.
+    fn keyword_after_apostrophe(&self) -> bool {
+        let rest = &self.source[self.position..];
+        let len = rest.find(|c| !is_ident_continue(c)).unwrap_or(rest.len());
+        let Some(info) = self.keyword(&self.source[self.token_start..self.position + len]).and_then(|kind| KEYWORDS.iter().find(|k| k.kind == kind)) else {
+            return false;
+        };
+        let words: Vec<&str> = info.phrase.split(' ').collect();
+        let index = words.iter().position(|word| *word == info.word).unwrap_or(0);
+        if let Some(next) = words.get(index + 1) {
+            let after = rest[len..].trim_start_matches([' ', '\t']);
+            return self.is_phrase_word(after.split(|c| !is_ident_continue(c)).next().unwrap_or(""), next);
+        }
+        let before = self.source[..self.token_start].trim_end_matches([' ', '\t']);
+        index > 0 && self.is_phrase_word(before.rsplit(|c| !is_ident_continue(c)).next().unwrap_or(""), words[index - 1])
+    }
+
+    /// Whether `word` in the source is `expected` of a phrase, in this dialect
+    fn is_phrase_word(&self, word: &str, expected: &str) -> bool {
+        match lookup_keyword(expected) {
+            Some(kind) => self.keyword(word) == Some(kind),
+            None => word == expected,
         }
     }
 }
 
 fn is_ident_start(c: char) -> bool {
-    c.is_alphabetic() || c == '_'
+    unicode_ident::is_xid_start(c) || c == '_'
 }
 
 fn is_ident_continue(c: char) -> bool {
-    c.is_alphanumeric() || c == '_'
+    unicode_ident::is_xid_continue(c)
+}
+
+/// `word` in Unicode normalization form C, the one names are compared in
+pub fn nfc(word: &str) -> Cow<'_, str> {
+    use unicode_normalization::UnicodeNormalization;
+    if unicode_normalization::is_nfc(word) { Cow::Borrowed(word) } else { Cow::Owned(word.nfc().collect()) }
 }
 
 #[cfg(test)]
@@ -434,6 +831,40 @@ mod tests {
         assert!(matches!(tokens[4].kind, TokenKind::Piglie));
     }
 
+    #[test]
+    fn test_plain_keyboard_keywords() {
+        let kinds: Vec<_> = Lexer::new("sfols sinno mentre che e è").map(|t| t.kind).collect();
+        assert_eq!(kinds[..6], [TokenKind::Sfols, TokenKind::Sinno, TokenKind::Mentre, TokenKind::Che, TokenKind::And, TokenKind::E]);
+
+        let table = KeywordTable::dialect("x", [("ogni", "dint'a")]).unwrap();
+        let kinds: Vec<_> = Lexer::new("pe dint'a x'y'").with_keywords(&table).map(|t| t.kind).collect();
+        assert_eq!(kinds[..4], [TokenKind::Pe, TokenKind::Ogni, TokenKind::Identifier("x"), TokenKind::String("y".into())]);
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        // `è` as `e` and a combining grave accent
+        let kinds: Vec<_> = Lexer::new("chist e\u{300} caffe\u{300} = 1").map(|t| t.kind).collect();
+        assert_eq!(kinds[..3], [TokenKind::Chist, TokenKind::E, TokenKind::Identifier("caffe\u{300}")]);
+        assert_eq!(nfc("caffe\u{300}"), "caffè");
+        let kinds: Vec<_> = Lexer::new("_x1 ναπολι пицца x·y").map(|t| t.kind).collect();
+        assert_eq!(kinds[..4], [TokenKind::Identifier("_x1"), TokenKind::Identifier("ναπολι"), TokenKind::Identifier("пицца"), TokenKind::Identifier("x·y")]);
+        // Not letters: symbols and a mark with nothing to sit on
+        assert!(matches!(Lexer::new("€").next().unwrap().kind, TokenKind::Invalid(_)));
+        assert!(matches!(Lexer::new("\u{300}x").next().unwrap().kind, TokenKind::Invalid(_)));
+    }
+
+    #[test]
+    fn test_apostrophe_keywords() {
+        let kinds: Vec<_> = Lexer::new("tutt' quant 'o primm che arriva 'o sole'").map(|t| t.kind).collect();
+        assert_eq!(kinds[..7], [
+            TokenKind::Tutt, TokenKind::Identifier("quant"), TokenKind::O, TokenKind::Identifier("primm"),
+            TokenKind::Che, TokenKind::Identifier("arriva"), TokenKind::String("o sole".into()),
+        ]);
+        let kinds: Vec<_> = Lexer::new("figlio 'e Pizza 'e'").map(|t| t.kind).collect();
+        assert_eq!(kinds[..4], [TokenKind::Figlio, TokenKind::De, TokenKind::Identifier("Pizza"), TokenKind::String("e".into())]);
+    }
+
     #[test]
     fn test_string_literal() {
         let mut lexer = Lexer::new("\"Uè!\"");
@@ -442,10 +873,93 @@ mod tests {
     }
 
     #[test]
+    fn test_comments_collected() {
+        let mut lexer = Lexer::new("// primma\ntien x /* mmiez */ = 1");
+        let tokens = lexer.tokenize();
+        assert!(matches!(tokens[0].kind, TokenKind::Newline));
+        let comments = lexer.comments();
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].kind, CommentKind::Line);
+        assert_eq!(comments[1].text, "/* mmiez */");
+        assert_eq!((comments[1].span.line, comments[1].span.column), (2, 8));
+    }
+
+    #[test]
+    fn test_span_end_positions() {
+        let mut lexer = Lexer::new("tien x\n  sfòls");
+        let tokens = lexer.tokenize();
+        assert_eq!((tokens[1].span.column, tokens[1].span.end_column), (6, 7));
+        assert_eq!((tokens[3].span.line, tokens[3].span.column), (2, 3));
+        assert_eq!((tokens[3].span.end_line, tokens[3].span.end_column), (2, 8));
+    }
+
+    #[test]
+    fn test_line_endings() {
+        // Same positions whatever the line endings
+        for newline in ["\n", "\r\n", "\r"] {
+            let source = ["tien x = \"a", "b\" /* c", "d */ tien y // e", "f"].join(newline);
+            let tokens: Vec<_> = Lexer::new(&source).collect();
+            let positions: Vec<_> = tokens.iter().map(|t| (t.span.line, t.span.column, t.span.end_line, t.span.end_column)).collect();
+            assert_eq!(positions, [(1, 1, 1, 5), (1, 6, 1, 7), (1, 8, 1, 9), (1, 10, 2, 3), (3, 6, 3, 10), (3, 11, 3, 12), (3, 17, 4, 1), (4, 1, 4, 2), (4, 2, 4, 2)], "{:?}", newline);
+            assert!(tokens.iter().all(|t| !matches!(t.kind, TokenKind::Invalid(_))));
+
+            let mut lexer = Lexer::new(&source);
+            lexer.tokenize();
+            assert_eq!(lexer.comments()[1].text, "// e");
+        }
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
     fn test_numbers() {
         let mut lexer = Lexer::new("42 3.14");
         let tokens = lexer.tokenize();
         assert!(matches!(tokens[0].kind, TokenKind::Number(n) if n == 42.0));
         assert!(matches!(tokens[1].kind, TokenKind::Number(n) if (n - 3.14).abs() < 0.001));
     }
+
+    #[test]
+    fn test_iterator_matches_tokenize() {
+        let source = "facc f(x) {\n  piglie x ** 2 // quadrato\n}";
+        let streamed: Vec<Token> = Lexer::new(source).collect();
+        assert_eq!(streamed, Lexer::new(source).tokenize());
+
+        let mut lexer = Lexer::new("");
+        assert_eq!(lexer.next().map(|t| t.kind), Some(TokenKind::Eof));
+        assert!(lexer.next().is_none());
+    }
+
+    #[test]
+    fn test_jsx() {
+        let kinds = |source| Lexer::new(source).with_jsx(true).tokenize().into_iter().map(|t| t.kind).collect::<Vec<_>>();
+        assert_eq!(kinds("a < b")[1], TokenKind::Less);
+        assert_eq!(kinds("f() <b")[3], TokenKind::Less);
+        assert_eq!(kinds(r#"<p id="x">Uè {nome}</p>"#), [
+            TokenKind::JsxTagStart, TokenKind::JsxName("p"), TokenKind::JsxName("id"), TokenKind::Equal,
+            TokenKind::String(Cow::Borrowed("x")), TokenKind::JsxTagEnd, TokenKind::JsxText("Uè "),
+            TokenKind::LeftBrace, TokenKind::Identifier("nome"), TokenKind::RightBrace,
+            TokenKind::JsxClosingTagStart, TokenKind::JsxName("p"), TokenKind::JsxTagEnd, TokenKind::Eof,
+        ]);
+        assert_eq!(Lexer::new("<p>").tokenize()[0].kind, TokenKind::Less);
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let kinds = |source| Lexer::new(source).with_interpolation(true).tokenize().into_iter().map(|t| t.kind).collect::<Vec<_>>();
+        assert_eq!(kinds(r#""a {x} b {{c}} { { k: 1 }.k}""#), [
+            TokenKind::TemplateHead(Cow::Borrowed("a ")), TokenKind::Identifier("x"), TokenKind::TemplateMiddle(Cow::Owned(" b {c} ".into())),
+            TokenKind::LeftBrace, TokenKind::Identifier("k"), TokenKind::Colon, TokenKind::Number(1.0), TokenKind::RightBrace,
+            TokenKind::Dot, TokenKind::Identifier("k"), TokenKind::TemplateTail(Cow::Borrowed("")), TokenKind::Eof,
+        ]);
+        assert_eq!(kinds("'{x}' \"{{}}\""), [TokenKind::String(Cow::Borrowed("{x}")), TokenKind::String(Cow::Owned("{}".into())), TokenKind::Eof]);
+        assert_eq!(Lexer::new("\"{x}\"").tokenize()[0].kind, TokenKind::String(Cow::Borrowed("{x}")));
+    }
+
+    #[test]
+    fn test_tokens_borrow_the_source() {
+        let tokens = Lexer::new(r#"chist è nome = "Gennaro" + "a\tcapo""#).tokenize();
+        assert_eq!(tokens[2].kind, TokenKind::Identifier("nome"));
+        assert!(matches!(&tokens[4].kind, TokenKind::String(Cow::Borrowed("Gennaro"))));
+        assert!(matches!(&tokens[6].kind, TokenKind::String(Cow::Owned(s)) if s == "a\tcapo"));
+    }
 }
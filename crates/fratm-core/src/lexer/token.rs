@@ -19,9 +19,13 @@
 //! - **Logical**: `e` (and), `o` (or), `no` (not)
 //! - **Assignment**: `=`, `+=`, `-=`, `*=`, `/=`
 
+use std::borrow::Cow;
 use std::fmt;
+#[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 
+use crate::errors::{Diagnostic, Locale};
+
 /// Position of a token in the source code.
 ///
 /// Tracks both byte offset and line/column position for
@@ -33,17 +37,21 @@ use serde::{Serialize, Deserialize};
 /// * `end` - Byte offset of token end
 /// * `line` - Line number (1-indexed)
 /// * `column` - Column number (1-indexed)
+/// * `end_line` - Line where the span ends (1-indexed)
+/// * `end_column` - Column just past the span end (1-indexed, exclusive)
 ///
 /// # Example
 ///
 /// ```rust
 /// use fratm_core::lexer::Span;
 ///
-/// let span = Span::new(0, 5, 1, 1);
+/// let span = Span::new(0, 5, 1, 1).with_end(1, 6);
 /// assert_eq!(span.line, 1);
 /// assert_eq!(span.column, 1);
+/// assert_eq!(span.end_column, 6);
 /// ```
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Span {
     /// Byte offset from file start (0-indexed)
     pub start: usize,
@@ -53,37 +61,61 @@ pub struct Span {
     pub line: usize,
     /// Column number (1-indexed)
     pub column: usize,
+    /// Line number of the span end (1-indexed)
+    pub end_line: usize,
+    /// Column just past the span end (1-indexed, exclusive)
+    pub end_column: usize,
 }
 
 impl Span {
     /// Creates a new span with the specified positions.
+    ///
+    /// The end line/column default to the start; use [`Span::with_end`]
+    /// when the end position is known.
     pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
-        Self { start, end, line, column }
+        Self { start, end, line, column, end_line: line, end_column: column }
+    }
+
+    /// Sets the end line/column of the span.
+    pub fn with_end(mut self, end_line: usize, end_column: usize) -> Self {
+        self.end_line = end_line;
+        self.end_column = end_column;
+        self
+    }
+
+    /// Creates a span from the start of `self` to the end of `other`.
+    pub fn to(&self, other: &Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+            line: self.line,
+            column: self.column,
+            end_line: other.end_line,
+            end_column: other.end_column,
+        }
     }
 
     /// Merges two spans creating one that covers both.
     ///
     /// Useful for creating spans that cover entire compound expressions.
     pub fn merge(&self, other: &Span) -> Span {
-        Span {
-            start: self.start.min(other.start),
-            end: self.end.max(other.end),
-            line: self.line.min(other.line),
-            column: if self.line <= other.line { self.column } else { other.column },
-        }
+        let (first, last) = if self.start <= other.start { (self, other) } else { (other, self) };
+        let last = if last.end >= first.end { last } else { first };
+        first.to(last)
     }
 }
 
 impl Default for Span {
     fn default() -> Self {
-        Self { start: 0, end: 0, line: 1, column: 1 }
+        Self { start: 0, end: 0, line: 1, column: 1, end_line: 1, end_column: 1 }
     }
 }
 
 /// A token with its position in source.
 ///
 /// Represents a single lexical element recognized by the lexer,
-/// complete with position information and original text.
+/// complete with position information and original text. Tokens borrow
+/// their text from the source instead of copying it.
 ///
 /// # Example
 ///
@@ -93,25 +125,58 @@ impl Default for Span {
 /// let token = Token::new(
 ///     TokenKind::Chist,
 ///     Span::new(0, 5, 1, 1),
-///     "chist".to_string()
+///     "chist"
 /// );
 /// assert_eq!(token.literal, "chist");
 /// ```
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Token {
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Token<'a> {
     /// The token type (keyword, operator, literal, etc.)
-    pub kind: TokenKind,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub kind: TokenKind<'a>,
     /// Position of the token in source
     pub span: Span,
     /// Original text of the token as it appears in source
-    pub literal: String,
+    pub literal: &'a str,
 }
 
-impl Token {
+impl<'a> Token<'a> {
     /// Creates a new token with type, position, and literal text.
-    pub fn new(kind: TokenKind, span: Span, literal: String) -> Self {
+    pub fn new(kind: TokenKind<'a>, span: Span, literal: &'a str) -> Self {
         Self { kind, span, literal }
     }
+
+    /// The lexer error carried by a [`TokenKind::Invalid`] token, worded in `locale`.
+    pub fn diagnostic(&self, locale: Locale) -> Option<Diagnostic> {
+        match &self.kind {
+            TokenKind::Invalid(message) => Some(Diagnostic::lexical(message, self.span, locale)),
+            _ => None,
+        }
+    }
+}
+
+/// A comment skipped by the lexer.
+///
+/// Comments are not tokens; the lexer collects them on the side so that
+/// tools like the formatter can put them back.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Comment {
+    /// Line (`//`) or block (`/* */`) comment
+    pub kind: CommentKind,
+    /// Full comment text, including the `//` or `/* */` delimiters
+    pub text: String,
+    /// Position of the comment in source
+    pub span: Span,
+}
+
+/// The two comment styles supported by FratmScript.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CommentKind {
+    Line,
+    Block,
 }
 
 /// All token types recognized by the FratmScript lexer.
@@ -135,8 +200,9 @@ impl Token {
 /// | `Sinno` | `sinnò` | `else` |
 /// | `Pe` | `pe` | `for` |
 /// | `Mentre` + `Che` | `mentre che` | `while` |
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub enum TokenKind {
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TokenKind<'a> {
     // === Keywords ===
     Chist,          // const (part 1)
     E,              // const (part 2: "è")
@@ -189,9 +255,13 @@ pub enum TokenKind {
     Leva,           // delete
     DintA,          // in - "dint'a" (inside)
     Caccia,         // yield
+    Buono,          // pattern matching (part 2) - "vir buono" (look closely)
     Fermete,        // debugger
+    Giura,          // assertion (part 1) - "giura che" (swear that)
     Scrive,         // console.error (part 1)
     Avvis,          // console.warn (part 1)
+    Tutt,           // Promise.all (part 1) - "tutt' quant" (all of them)
+    O,              // Promise.race (part 1) - "'o primm che arriva" (the first to arrive)
 
     // === Logical Operators ===
     And,            // && - "e"
@@ -237,20 +307,54 @@ pub enum TokenKind {
     Colon,
     Semicolon,
     Question,
+    /// `#`, which starts a compile-time directive: `#si DEBUG { }`
+    Hash,
+    /// `@`, before a decorator: `@sigillata na famiglie Pizza { }`
+    At,
     Arrow,
 
     // === Literals ===
-    Identifier(String),
-    String(String),
+    /// A name, borrowed from the source
+    Identifier(&'a str),
+    /// A string's value: borrowed unless it had escapes to process
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    String(Cow<'a, str>),
     Number(f64),
 
+    // === Interpolated strings (only with `Lexer::with_interpolation`) ===
+    /// `"Ciao {`: the text before the first interpolation
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    TemplateHead(Cow<'a, str>),
+    /// `} e {`: the text between two interpolations
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    TemplateMiddle(Cow<'a, str>),
+    /// `}!"`: the text after the last interpolation
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    TemplateTail(Cow<'a, str>),
+
+    // === JSX (only with `Lexer::with_jsx`) ===
+    /// `<` opening a tag
+    JsxTagStart,
+    /// `</` opening a closing tag
+    JsxClosingTagStart,
+    /// `>` ending a tag
+    JsxTagEnd,
+    /// `/>` ending a tag without children
+    JsxSelfClosing,
+    /// A tag or attribute name, which may have `-`, `.` and `:` in it
+    JsxName(&'a str),
+    /// Text between tags, as written
+    JsxText(&'a str),
+
     // === Special ===
     Newline,
     Eof,
-    Invalid(String),
+    /// Text the lexer could not read, with the reason
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    Invalid(Cow<'a, str>),
 }
 
-impl fmt::Display for TokenKind {
+impl fmt::Display for TokenKind<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             TokenKind::Chist => write!(f, "chist"),
@@ -303,9 +407,13 @@ impl fmt::Display for TokenKind {
             TokenKind::Leva => write!(f, "leva"),
             TokenKind::DintA => write!(f, "dint'a"),
             TokenKind::Caccia => write!(f, "caccia"),
+            TokenKind::Buono => write!(f, "buono"),
             TokenKind::Fermete => write!(f, "fermete"),
+            TokenKind::Giura => write!(f, "giura"),
             TokenKind::Scrive => write!(f, "scrive"),
             TokenKind::Avvis => write!(f, "avvis"),
+            TokenKind::Tutt => write!(f, "tutt'"),
+            TokenKind::O => write!(f, "'o"),
             // Logical Operators
             TokenKind::And => write!(f, "e"),
             TokenKind::Or => write!(f, "o"),
@@ -342,10 +450,20 @@ impl fmt::Display for TokenKind {
             TokenKind::Colon => write!(f, ":"),
             TokenKind::Semicolon => write!(f, ";"),
             TokenKind::Question => write!(f, "?"),
+            TokenKind::Hash => write!(f, "#"),
+            TokenKind::At => write!(f, "@"),
             TokenKind::Arrow => write!(f, "=>"),
             TokenKind::Identifier(s) => write!(f, "{}", s),
             TokenKind::String(s) => write!(f, "\"{}\"", s),
             TokenKind::Number(n) => write!(f, "{}", n),
+            TokenKind::TemplateHead(s) => write!(f, "\"{}{{", s),
+            TokenKind::TemplateMiddle(s) => write!(f, "}}{}{{", s),
+            TokenKind::TemplateTail(s) => write!(f, "}}{}\"", s),
+            TokenKind::JsxTagStart => write!(f, "<"),
+            TokenKind::JsxClosingTagStart => write!(f, "</"),
+            TokenKind::JsxTagEnd => write!(f, ">"),
+            TokenKind::JsxSelfClosing => write!(f, "/>"),
+            TokenKind::JsxName(s) | TokenKind::JsxText(s) => write!(f, "{}", s),
             TokenKind::Newline => write!(f, "\\n"),
             TokenKind::Eof => write!(f, "EOF"),
             TokenKind::Invalid(s) => write!(f, "INVALID({})", s),
@@ -356,7 +474,9 @@ impl fmt::Display for TokenKind {
 /// Maps a string to the corresponding keyword (if it exists).
 ///
 /// Used by the lexer to determine if an identifier is a reserved
-/// word of the language.
+/// word of the language. Backed by the [`KEYWORDS`](super::keywords::KEYWORDS) table.
+/// Accents are optional for words that aren't keywords as written, so
+/// `sfols` and `sinno` work on keyboards without `ò`.
 ///
 /// # Arguments
 ///
@@ -375,61 +495,14 @@ impl fmt::Display for TokenKind {
 /// assert!(matches!(lookup_keyword("chist"), Some(TokenKind::Chist)));
 /// assert!(matches!(lookup_keyword("facc"), Some(TokenKind::Facc)));
 /// assert!(lookup_keyword("pizza").is_none()); // Not a keyword
+/// assert!(matches!(lookup_keyword("sinno"), Some(TokenKind::Sinno)));
+/// assert!(matches!(lookup_keyword("e"), Some(TokenKind::And))); // not `è`
 /// ```
-pub fn lookup_keyword(ident: &str) -> Option<TokenKind> {
-    match ident {
-        "chist" => Some(TokenKind::Chist),
-        "è" => Some(TokenKind::E),
-        "tien" => Some(TokenKind::Tien),
-        "facc" => Some(TokenKind::Facc),
-        "piglie" => Some(TokenKind::Piglie),
-        "si" => Some(TokenKind::Si),
-        "sinnò" => Some(TokenKind::Sinno),
-        "pe" => Some(TokenKind::Pe),
-        "ogni" => Some(TokenKind::Ogni),
-        "mentre" => Some(TokenKind::Mentre),
-        "che" => Some(TokenKind::Che),
-        "overo" => Some(TokenKind::Overo),
-        "sfòls" => Some(TokenKind::Sfols),
-        "nisciun" => Some(TokenKind::Nisciun),
-        "boh" => Some(TokenKind::Boh),
-        "stamm" => Some(TokenKind::Stamm),
-        // "a" is NOT a keyword - it's parsed contextually in "stamm a dì"
-        "dì" => Some(TokenKind::Di),
-        "mo" => Some(TokenKind::Mo),
-        "vir" => Some(TokenKind::Vir),
-        "aspett" => Some(TokenKind::Aspett),
-        "pruvamm" => Some(TokenKind::Pruvamm),
-        "schiatta" => Some(TokenKind::Schiatta),
-        "iett" => Some(TokenKind::Iett),
-        "nu" => Some(TokenKind::Nu),
-        "bell" => Some(TokenKind::Bell),
-        "na" => Some(TokenKind::Na),
-        "famiglie" => Some(TokenKind::Famiglie),
-        "stu" => Some(TokenKind::Stu),
-        "cos" => Some(TokenKind::Cos),
-        "chiamm" => Some(TokenKind::Chiamm),
-        "da" => Some(TokenKind::Da),
-        "mann" => Some(TokenKind::Mann),
-        "for" => Some(TokenKind::For),
-        "predefinit" => Some(TokenKind::Predefinit),
-        "rompe" => Some(TokenKind::Rompe),
-        "salta" => Some(TokenKind::Salta),
-        // New goliardic keywords
-        "caso" => Some(TokenKind::Caso),
-        "fisso" => Some(TokenKind::Fisso),
-        "figlio" => Some(TokenKind::Figlio),
-        "leva" => Some(TokenKind::Leva),
-        "caccia" => Some(TokenKind::Caccia),
-        "fermete" => Some(TokenKind::Fermete),
-        "scrive" => Some(TokenKind::Scrive),
-        "avvis" => Some(TokenKind::Avvis),
-        // Logical operators
-        "e" => Some(TokenKind::And),
-        "o" => Some(TokenKind::Or),
-        "no" => Some(TokenKind::Not),
-        "manco" => Some(TokenKind::Manco),
-        "pure" => Some(TokenKind::Pure),
-        _ => None,
-    }
+pub fn lookup_keyword(ident: &str) -> Option<TokenKind<'static>> {
+    use super::keywords::{same_ignoring_accents, KEYWORDS};
+    // "a" is NOT a keyword - it's parsed contextually in "stamm a dì"
+    KEYWORDS.iter()
+        .find(|k| k.word == ident)
+        .or_else(|| KEYWORDS.iter().find(|k| same_ignoring_accents(k.word, ident)))
+        .map(|k| k.kind.clone())
 }
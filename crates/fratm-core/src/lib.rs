@@ -11,6 +11,20 @@
 //! 2. **Parser** ([`parser`]): Syntax analysis - builds the Abstract Syntax Tree (AST)
 //! 3. **CodeGen** ([`codegen`]): Code generation - produces JavaScript from the AST
 //!
+//! Alongside the pipeline, [`formatter`] pretty-prints FratmScript source and
+//! [`linter`] reports suspicious-but-valid code, with the help of [`flow`]
+//! for code that can never run. [`ide`] has editor helpers
+//! such as completions, and [`line_index`] converts spans to the UTF-16
+//! positions editors use. [`interpreter`] runs programs without Node,
+//! [`render`] draws the code frame shown under errors, [`semantic`]
+//! resolves names to their declarations, [`grammar`] generates
+//! highlighting grammars for editors, [`diff`] compares two programs
+//! ignoring their layout, [`fix`] brings old sources up to date,
+//! [`modules`] checks imports between files, [`types`] checks values
+//! against their type annotations, [`syntax`] keeps the source as a
+//! lossless tree, spaces and comments included, and [`limits`] bounds the
+//! size of input that can't be trusted.
+//!
 //! ## Basic Example
 //!
 //! ```rust
@@ -53,13 +67,13 @@
 //! ```rust
 //! use fratm_core::{compile, CompileOptions};
 //!
-//! let options = CompileOptions {
-//!     source_map: true,
-//!     filename: Some("main.fratm".to_string()),
-//!     ..Default::default()
-//! };
+//! let options = CompileOptions::builder()
+//!     .source_map(true)
+//!     .filename("main.fratm")
+//!     .build();
 //!
 //! let result = compile("chist è x = 42", options).unwrap();
+//! #[cfg(feature = "sourcemap")]
 //! if let Some(map) = result.source_map {
 //!     println!("{}", map.to_json());
 //! }
@@ -76,33 +90,92 @@
 //! assert!(result.is_err());
 //! // Error: "Doppo 'chist' ce vo' 'è'! Scrivi 'chist è' pe fà 'na costante."
 //! ```
+//!
+//! ## Determinism
+//!
+//! The same source and options always compile to the same bytes: code,
+//! source map and warnings, on any platform, from one run to the next, and
+//! through [`compile`] or [`compile_many`] alike. Nothing in the output
+//! depends on the order of a hash map, the time, the machine or the thread
+//! that did the work, so it can be cached by a hash of its input and
+//! builds are reproducible. Paths in `filename` are taken as given: pass
+//! the same ones to get the same source maps.
+//!
+//! ## Cargo Features
+//!
+//! All on by default; turn them off to shrink embedded builds such as the
+//! WebAssembly one:
+//!
+//! - `serde`: `Serialize`/`Deserialize` on tokens, the AST, diagnostics and
+//!   options, dialect packs from JSON, and the modules built on them
+//!   ([`grammar`], [`diff`], [`fingerprint`], the AST dumps)
+//! - `sourcemap`: source map generation; without it
+//!   [`CompileResult::source_map`] is always `None`. Needs `serde`, which
+//!   writes the maps as JSON
+//! - `suggestions`: the `💡` hints attached to syntax errors
 
 pub mod lexer;
 pub mod parser;
 pub mod codegen;
 pub mod errors;
 pub mod sourcemap;
+pub mod formatter;
+pub mod linter;
+pub mod flow;
+pub mod fix;
+pub mod modules;
+pub mod env;
+pub mod coverage;
+pub mod learn;
+pub mod ide;
+pub mod line_index;
+pub mod interpreter;
+pub mod render;
+pub mod semantic;
+pub mod types;
+pub mod limits;
+pub mod syntax;
+#[cfg(feature = "serde")]
+pub mod grammar;
+#[cfg(feature = "serde")]
+pub mod diff;
+#[cfg(feature = "serde")]
+pub mod fingerprint;
 
-use errors::CompileError;
-use lexer::Lexer;
-use parser::Parser;
-use codegen::CodeGen;
+use std::collections::BTreeMap;
+
+use errors::{CompileError, Diagnostic, Locale};
+use lexer::{Comment, Lexer};
+use lexer::keywords::KeywordTable;
+use limits::Limits;
+use parser::{Names, Parser, ParserOptions, Program};
+use codegen::{CodeGen, IoWriter};
 use sourcemap::SourceMap;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
 
 /// Compilation options to customize the transpiler behavior.
 ///
+/// The struct is `#[non_exhaustive]` so new options can be added without
+/// breaking callers: build it with [`CompileOptions::builder`] (or
+/// `Default::default()`) instead of a struct literal.
+///
 /// # Example
 ///
 /// ```rust
-/// use fratm_core::CompileOptions;
+/// use fratm_core::{CompileOptions, ModuleFormat, Target};
 ///
-/// let options = CompileOptions {
-///     source_map: true,
-///     filename: Some("main.fratm".to_string()),
-///     minify: false,
-/// };
+/// let options = CompileOptions::builder()
+///     .source_map(true)
+///     .filename("main.fratm")
+///     .output_filename("main.js")
+///     .target(Target::Es2020)
+///     .module_format(ModuleFormat::CommonJs)
+///     .build();
+/// assert_eq!(options.module_format, ModuleFormat::CommonJs);
 /// ```
 #[derive(Debug, Clone, Default)]
+#[non_exhaustive]
 pub struct CompileOptions {
     /// Generate source maps for debugging.
     ///
@@ -115,10 +188,469 @@ pub struct CompileOptions {
     /// If not specified, "input.fratm" is used as default.
     pub filename: Option<String>,
 
-    /// Minify the JavaScript output (not yet implemented).
+    /// Generated JavaScript filename (written to the source map `file` field).
     ///
-    /// When enabled, removes whitespace and shortens variable names.
+    /// If not specified, it is derived from `filename` by swapping the
+    /// extension for `.js`.
+    pub output_filename: Option<String>,
+
+    /// Minify the JavaScript output.
+    ///
+    /// When enabled, shortens the names of local variables and parameters
+    /// (the source map keeps the originals). Whitespace is not removed yet.
     pub minify: bool,
+
+    /// Oldest JavaScript version the output must run on.
+    pub target: Target,
+
+    /// Module system used for `chiamm` (import) and `mann for` (export).
+    pub module_format: ModuleFormat,
+
+    /// Global object a [`ModuleFormat::Script`] exports to and imports
+    /// from: `globalThis.Fratm` unless given. It must be an identifier.
+    pub global_name: Option<String>,
+
+    /// Simplify the generated code.
+    ///
+    /// When enabled, `+` chains with string literals are folded into one
+    /// string, or into a template literal when variables come in between.
+    pub optimize: bool,
+
+    /// Language of error messages (Neapolitan by default).
+    pub locale: Locale,
+
+    /// Indentation, quotes and semicolons of the generated code.
+    pub style: CodegenStyle,
+
+    /// Text put before the code, e.g. a license notice. Unless it already
+    /// starts with a comment, each line becomes a `//` comment.
+    pub banner: Option<String>,
+
+    /// Text put after the code, commented out like `banner`.
+    pub footer: Option<String>,
+
+    /// Start CommonJS output with `"use strict";` (ES modules are always strict).
+    pub use_strict: bool,
+
+    /// Copy the source comments into the output, next to the statements around them.
+    pub comments: bool,
+
+    /// Write each line of the source as a comment above the JavaScript it
+    /// compiles to. Only [`compile`], which has the source, does it.
+    pub annotate: bool,
+
+    /// What `giura che` assertions turn into.
+    pub assertions: Assertions,
+
+    /// What `@decoratore` on classes and methods turns into.
+    pub decorators: Decorators,
+
+    /// Keywords of the source, Neapolitan unless a dialect pack is given.
+    pub dialect: KeywordTable,
+
+    /// Module names to write as other specifiers, e.g. `pizzeria` as
+    /// `@acme/pizzeria` (see [`modules::resolve_alias`]).
+    pub module_aliases: BTreeMap<String, String>,
+
+    /// Names for `#si` directives, with their values: only the branches
+    /// they choose are compiled (see [`parser::Directive::holds`]).
+    pub defines: BTreeMap<String, String>,
+
+    /// Environment variables for `amb("NAME")`, which compiles to the
+    /// value as a string (see [`env`]).
+    pub env: BTreeMap<String, String>,
+
+    /// Read JSX elements, `<Saluto nome="Ciro" />`, and compile them to
+    /// the calls of this runtime. Off by default, since it gives `<` a
+    /// second meaning.
+    pub jsx: Option<Jsx>,
+
+    /// Functions that log their calls and returns (see [`Trace`]).
+    pub trace: Trace,
+
+    /// Function called as `hook("enter", name, arguments)` and `hook("exit",
+    /// name, value)` by traced functions, instead of `console.log`.
+    pub trace_hook: Option<String>,
+
+    /// Put counters in the code for coverage, and a collector that writes
+    /// their counts out on exit (see [`coverage`]).
+    pub coverage: bool,
+
+    /// Read `{...}` in double-quoted strings as interpolation, `"Ciao
+    /// {nome}!"`, compiled to a template literal; `{{` and `}}` are literal
+    /// braces. Off by default, since it changes what existing strings say.
+    pub interpolation: bool,
+
+    /// Whether parsing goes on after an error, and for how many.
+    /// [`parse_with_options`] returns the errors found, [`compile`] the first.
+    pub parser: ParserOptions,
+
+    /// How large the source, its tokens and AST, and the JavaScript may
+    /// get, for input that can't be trusted (see [`limits`]).
+    pub limits: Limits,
+}
+
+impl CompileOptions {
+    /// Starts a builder with the default options.
+    pub fn builder() -> CompileOptionsBuilder {
+        CompileOptionsBuilder::default()
+    }
+}
+
+/// Builder for [`CompileOptions`], created with [`CompileOptions::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptionsBuilder {
+    options: CompileOptions,
+}
+
+impl CompileOptionsBuilder {
+    pub fn source_map(mut self, enabled: bool) -> Self {
+        self.options.source_map = enabled;
+        self
+    }
+
+    pub fn filename(mut self, filename: impl Into<String>) -> Self {
+        self.options.filename = Some(filename.into());
+        self
+    }
+
+    pub fn output_filename(mut self, filename: impl Into<String>) -> Self {
+        self.options.output_filename = Some(filename.into());
+        self
+    }
+
+    pub fn minify(mut self, enabled: bool) -> Self {
+        self.options.minify = enabled;
+        self
+    }
+
+    pub fn target(mut self, target: Target) -> Self {
+        self.options.target = target;
+        self
+    }
+
+    pub fn module_format(mut self, format: ModuleFormat) -> Self {
+        self.options.module_format = format;
+        self
+    }
+
+    pub fn global_name(mut self, name: impl Into<String>) -> Self {
+        self.options.global_name = Some(name.into());
+        self
+    }
+
+    pub fn optimize(mut self, enabled: bool) -> Self {
+        self.options.optimize = enabled;
+        self
+    }
+
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.options.locale = locale;
+        self
+    }
+
+    pub fn style(mut self, style: CodegenStyle) -> Self {
+        self.options.style = style;
+        self
+    }
+
+    pub fn banner(mut self, banner: impl Into<String>) -> Self {
+        self.options.banner = Some(banner.into());
+        self
+    }
+
+    pub fn footer(mut self, footer: impl Into<String>) -> Self {
+        self.options.footer = Some(footer.into());
+        self
+    }
+
+    pub fn use_strict(mut self, enabled: bool) -> Self {
+        self.options.use_strict = enabled;
+        self
+    }
+
+    pub fn comments(mut self, enabled: bool) -> Self {
+        self.options.comments = enabled;
+        self
+    }
+
+    pub fn annotate(mut self, enabled: bool) -> Self {
+        self.options.annotate = enabled;
+        self
+    }
+
+    pub fn assertions(mut self, assertions: Assertions) -> Self {
+        self.options.assertions = assertions;
+        self
+    }
+
+    pub fn decorators(mut self, decorators: Decorators) -> Self {
+        self.options.decorators = decorators;
+        self
+    }
+
+    pub fn dialect(mut self, dialect: KeywordTable) -> Self {
+        self.options.dialect = dialect;
+        self
+    }
+
+    /// `chiamm ... da "alias"` imports `specifier` instead, and so does
+    /// `"alias/path"` with `"specifier/path"`.
+    pub fn module_alias(mut self, alias: impl Into<String>, specifier: impl Into<String>) -> Self {
+        self.options.module_aliases.insert(alias.into(), specifier.into());
+        self
+    }
+
+    /// Defines `name` for `#si` directives; `-D DEBUG` on the command line
+    /// is `define("DEBUG", "1")`.
+    pub fn define(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.defines.insert(name.into(), value.into());
+        self
+    }
+
+    /// Gives `amb("name")` the value `value`.
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Enables JSX, compiled for `runtime`.
+    pub fn jsx(mut self, runtime: Jsx) -> Self {
+        self.options.jsx = Some(runtime);
+        self
+    }
+
+    pub fn trace(mut self, trace: Trace) -> Self {
+        self.options.trace = trace;
+        self
+    }
+
+    pub fn trace_hook(mut self, hook: impl Into<String>) -> Self {
+        self.options.trace_hook = Some(hook.into());
+        self
+    }
+
+    pub fn coverage(mut self, enabled: bool) -> Self {
+        self.options.coverage = enabled;
+        self
+    }
+
+    /// Reads `{...}` in double-quoted strings as interpolation.
+    pub fn interpolation(mut self, enabled: bool) -> Self {
+        self.options.interpolation = enabled;
+        self
+    }
+
+    pub fn parser_options(mut self, options: ParserOptions) -> Self {
+        self.options.parser = options;
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.options.limits = limits;
+        self
+    }
+
+    pub fn build(self) -> CompileOptions {
+        self.options
+    }
+}
+
+/// JavaScript version targeted by the generated code.
+///
+/// Syntax newer than the target is rewritten when possible: for
+/// [`Target::Es2015`], `a ** b` becomes `Math.pow(a, b)`. Async functions
+/// (`mo vir facc`) are always emitted as-is and need ES2017, and so are the
+/// `?.` and `??` of `o sinnò`, which need ES2020.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Target {
+    Es2015,
+    Es2020,
+    #[default]
+    EsNext,
+}
+
+/// Module system of the generated code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ModuleFormat {
+    /// `import { a } from "x"` / `export const a = 1`
+    #[default]
+    Esm,
+    /// `const { a } = require("x")` / `exports.a = a`
+    CommonJs,
+    /// A classic browser `<script>`: the code runs in a function, exports
+    /// go on the [`global_name`](CompileOptions::global_name) object and
+    /// imports come from it, as other scripts of the page put them there.
+    /// Node.js's own modules can't be imported.
+    ///
+    /// ```rust
+    /// use fratm_core::{compile, CompileOptions, ModuleFormat};
+    ///
+    /// let options = CompileOptions::builder().module_format(ModuleFormat::Script).global_name("Pizzeria").build();
+    /// let result = compile("chiamm { forno } da \"./forno.fratm\"\nmann for chist è pizza = forno()", options.clone()).unwrap();
+    /// assert!(result.code.starts_with("(function (exports) {\n  const { forno } = globalThis.Pizzeria;"));
+    /// assert!(result.code.ends_with("})(globalThis.Pizzeria = globalThis.Pizzeria || {});\n"));
+    /// assert!(compile("chiamm { readFile } da \"fs\"", options).is_err());
+    /// ```
+    Script,
+}
+
+/// Code generated for `giura che (condition) sinnò message`.
+///
+/// ```rust
+/// use fratm_core::{compile, Assertions, CompileOptions};
+///
+/// let source = "giura che (x > 0) sinnò \"x adda essere positivo\"";
+/// let options = CompileOptions::builder().assertions(Assertions::Console).build();
+/// assert_eq!(compile(source, options).unwrap().code, "console.assert((x > 0), \"x adda essere positivo\");\n");
+///
+/// let options = CompileOptions::builder().assertions(Assertions::Strip).build();
+/// assert_eq!(compile(source, options).unwrap().code, "");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Assertions {
+    /// `if (!(condition)) throw new Error(message)`
+    #[default]
+    Throw,
+    /// `console.assert(condition, message)`, which only logs the failure
+    Console,
+    /// Nothing at all, for release builds
+    Strip,
+}
+
+/// Code generated for `@decoratore` before a class or a method.
+///
+/// ```rust
+/// use fratm_core::{compile, CompileOptions, Decorators};
+///
+/// let source = "@sigillata\nna famiglie Pizza {}";
+/// assert_eq!(compile(source, Default::default()).unwrap().code, "@sigillata class Pizza {\n}\n");
+///
+/// let options = CompileOptions::builder().decorators(Decorators::Legacy).build();
+/// assert_eq!(
+///     compile(source, options).unwrap().code,
+///     "class Pizza {\n}\nPizza = (sigillata(Pizza, { kind: \"class\", name: \"Pizza\" }) || Pizza);\n",
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Decorators {
+    /// The decorators as written, for runtimes and bundlers that follow the
+    /// TC39 proposal
+    #[default]
+    Standard,
+    /// Calls after the class that replace it and its methods with what the
+    /// decorators return, `decoratore(valore, { kind, name })`, for targets
+    /// without decorator syntax
+    Legacy,
+}
+
+/// Functions that log their calls and returns, with [`CompileOptions::trace`].
+///
+/// A function traced logs `→ name` and its arguments when called, and
+/// `← name` and the value when it returns (not when it throws). A `//
+/// traccia` or `// nun traccià` comment on the line before a function
+/// turns it on or off.
+///
+/// ```rust
+/// use fratm_core::{compile, CompileOptions, Trace};
+///
+/// let source = "// traccia\nfacc somma(a, b) {\n    piglie a + b\n}\nfacc zero() {\n    piglie 0\n}";
+/// let options = CompileOptions::builder().trace(Trace::Annotated).build();
+/// let code = compile(source, options).unwrap().code;
+/// assert!(code.contains("function somma(a, b) {\n  console.log(\"→ somma\", a, b);\n  return __fratm_exit(\"somma\", (a + b));\n}"));
+/// assert!(code.contains("function zero() {\n  return 0;\n}"));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Trace {
+    /// No function, whatever the comments say
+    #[default]
+    Off,
+    /// Only the functions marked `// traccia`
+    Annotated,
+    /// Every function and method, except those marked `// nun traccià`
+    All,
+}
+
+/// What JSX elements compile to, with [`CompileOptions::jsx`].
+///
+/// ```rust
+/// use fratm_core::{compile, CompileOptions, Jsx};
+///
+/// let source = "chist è saluto = <p classe=\"grande\">Uè, {nome}!</p>";
+/// let options = CompileOptions::builder().jsx(Jsx::default()).build();
+/// assert_eq!(compile(source, options).unwrap().code, "const saluto = React.createElement(\"p\", { classe: \"grande\" }, \"Uè, \", nome, \"!\");\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "runtime", rename_all = "lowercase"))]
+pub enum Jsx {
+    /// `factory(tag, props, ...children)`, with `fragment` as the tag of `<>...</>`
+    Classic { factory: String, fragment: String },
+    /// `_jsx(tag, { ...props, children })`, imported from `<import_source>/jsx-runtime`
+    Automatic {
+        #[cfg_attr(feature = "serde", serde(rename = "importSource"))]
+        import_source: String,
+    },
+}
+
+/// `React.createElement` and `React.Fragment`
+impl Default for Jsx {
+    fn default() -> Self {
+        Jsx::Classic { factory: "React.createElement".to_string(), fragment: "React.Fragment".to_string() }
+    }
+}
+
+/// Formatting of the generated JavaScript, to match the codebase it goes into.
+///
+/// ```rust
+/// use fratm_core::{compile, CodegenStyle, CompileOptions, Quote};
+///
+/// let style = CodegenStyle { quote: Quote::Single, semicolons: false, ..Default::default() };
+/// let options = CompileOptions::builder().style(style).build();
+/// assert_eq!(compile("stamm a dì(\"uè\")", options).unwrap().code, "console.log('uè')\n");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+pub struct CodegenStyle {
+    /// Number of spaces per indentation level (ignored with `use_tabs`)
+    pub indent_width: usize,
+    /// Indent with tabs instead of spaces
+    pub use_tabs: bool,
+    /// Quotes around strings and module paths
+    pub quote: Quote,
+    /// End statements with `;`. Without them, a statement that would
+    /// continue the previous line (starting with `(`, `[` or `-`) gets a leading `;`.
+    pub semicolons: bool,
+}
+
+impl Default for CodegenStyle {
+    fn default() -> Self {
+        Self { indent_width: 2, use_tabs: false, quote: Quote::Double, semicolons: true }
+    }
+}
+
+/// Quote character for string literals.
+///
+/// A string with more of these quotes than of the other kind uses the other
+/// kind instead, to need fewer escapes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Quote {
+    #[default]
+    Double,
+    Single,
 }
 
 /// Compilation result containing the generated code and metadata.
@@ -149,8 +681,23 @@ pub struct CompileResult {
     /// Non-fatal warnings generated during compilation.
     ///
     /// These are issues that don't prevent compilation but may
-    /// indicate logical errors in the code.
+    /// indicate logical errors in the code, e.g. a variable named after a
+    /// JavaScript reserved word, which the output renames.
     pub warnings: Vec<String>,
+
+    /// Whether the code is an ES module, with `import` or `export` from
+    /// `chiamm` and `mann for`. Node runs `.js` files as CommonJS unless told
+    /// otherwise, so save it as `.mjs` or run it with `--input-type=module`.
+    ///
+    /// ```rust
+    /// use fratm_core::{compile, CompileOptions, ModuleFormat};
+    ///
+    /// assert!(compile("mann for chist è x = 1", Default::default()).unwrap().is_module);
+    /// assert!(!compile("chist è x = 1", Default::default()).unwrap().is_module);
+    /// let commonjs = CompileOptions::builder().module_format(ModuleFormat::CommonJs).build();
+    /// assert!(!compile("mann for chist è x = 1", commonjs).unwrap().is_module);
+    /// ```
+    pub is_module: bool,
 }
 
 /// Compiles FratmScript code to JavaScript.
@@ -183,10 +730,10 @@ pub struct CompileResult {
 /// let result = compile("chist è x = 42", Default::default()).unwrap();
 /// assert!(result.code.contains("const x = 42"));
 ///
-/// // With source map
-/// let options = CompileOptions { source_map: true, ..Default::default() };
+/// // With source map, if built with the `sourcemap` feature
+/// let options = CompileOptions::builder().source_map(true).build();
 /// let result = compile("chist è x = 42", options).unwrap();
-/// assert!(result.source_map.is_some());
+/// assert_eq!(result.source_map.is_some(), cfg!(feature = "sourcemap"));
 /// ```
 ///
 /// # Compilation Pipeline
@@ -195,55 +742,275 @@ pub struct CompileResult {
 /// Source Code → Lexer → Tokens → Parser → AST → CodeGen → JavaScript
 /// ```
 pub fn compile(source: &str, options: CompileOptions) -> Result<CompileResult, CompileError> {
-    // Lexing
-    let mut lexer = Lexer::new(source);
-    let tokens = lexer.tokenize();
-
-    // Check for lexer errors
-    let lex_errors: Vec<_> = tokens.iter()
-        .filter_map(|t| {
-            if let lexer::TokenKind::Invalid(msg) = &t.kind {
-                Some(CompileError::LexerError {
-                    message: msg.clone(),
-                    line: t.span.line,
-                    column: t.span.column,
-                })
-            } else {
-                None
+    compile_named(source, None, &options, &mut Names::new())
+}
+
+/// Compiles many sources held in memory, as `(name, source)` pairs, with
+/// the same options. Results come back in the order of `sources`.
+///
+/// Each name stands for [`CompileOptions::filename`] and gives the
+/// source map's `file`, as `filename` would. The sources are spread over
+/// the available cores, and the keyword table, the options and the
+/// interned names are shared by the inputs instead of set up for each,
+/// which adds up for bundler plugins compiling hundreds of modules.
+///
+/// ```rust
+/// use fratm_core::{compile_many, CompileOptions};
+///
+/// let sources = [("a.fratm", "chist è x = 1"), ("b.fratm", "chist è = 2")];
+/// let options = CompileOptions::builder().source_map(true).build();
+/// let results = compile_many(&sources, &options);
+/// let a = results[0].as_ref().unwrap();
+/// assert!(a.code.contains("const x = 1"));
+/// #[cfg(feature = "sourcemap")]
+/// assert_eq!(a.source_map.as_ref().unwrap().file.as_deref(), Some("a.js"));
+/// assert!(results[1].is_err());
+/// ```
+pub fn compile_many<N, S>(sources: &[(N, S)], options: &CompileOptions) -> Vec<Result<CompileResult, CompileError>>
+where
+    N: AsRef<str> + Sync,
+    S: AsRef<str> + Sync,
+{
+    let compile = |(name, source): &(N, S), names: &mut Names| compile_named(source.as_ref(), Some(name.as_ref()), options, names);
+    let workers = std::thread::available_parallelism().map_or(1, |n| n.get()).min(sources.len());
+    if workers <= 1 {
+        let mut names = Names::new();
+        return sources.iter().map(|input| compile(input, &mut names)).collect();
+    }
+    // Each worker takes the next source until none is left, keeping its names from one to the next
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let mut results: Vec<(usize, Result<CompileResult, CompileError>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers).map(|_| scope.spawn(|| {
+            let (mut names, mut done) = (Names::new(), Vec::new());
+            loop {
+                let index = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                let Some(input) = sources.get(index) else { break done };
+                done.push((index, compile(input, &mut names)));
             }
-        })
-        .collect();
+        })).collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))).collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// [`compile`] with `name` for the filename, if given, and the names
+/// interned by earlier compilations
+fn compile_named(source: &str, name: Option<&str>, options: &CompileOptions, names: &mut Names) -> Result<CompileResult, CompileError> {
+    let (program, comments) = parse_sharing(source, options, names)
+        .map_err(|errors| CompileError::from(errors.into_iter().next().unwrap()))?;
+    let mut codegen = codegen(options.limits.bound_output(String::new()), comments, options);
+    if options.annotate { codegen = codegen.with_annotations(source); }
+    // Only fails past the output limit
+    let _ = codegen.write(&program);
+    let source_map = finish_source_map(&codegen, options, name).map(|map| map.with_content(source));
+    let is_module = codegen.is_module();
+    let code = options.limits.check_output(codegen.into_output(), options.locale)?;
+    Ok(CompileResult { code, source_map, warnings: warnings(&program, options), is_module })
+}
 
-    if !lex_errors.is_empty() {
-        return Err(lex_errors.into_iter().next().unwrap());
+/// Parses FratmScript code into an AST without generating JavaScript.
+///
+/// This is the first half of [`compile`]. Together with [`generate`] it lets
+/// tools inspect or transform the [`Program`] between the two stages.
+/// Unlike [`compile`], it reports every error found, not just the first.
+///
+/// # Example
+///
+/// ```rust
+/// use fratm_core::{generate, parse_source};
+///
+/// let program = parse_source("chist è x = 42").unwrap();
+/// assert_eq!(program.statements.len(), 1);
+///
+/// let result = generate(&program, &Default::default());
+/// assert!(result.code.contains("const x = 42"));
+///
+/// let errors = parse_source("chist x = 1\nchist y = 2").unwrap_err();
+/// assert_eq!(errors.len(), 2);
+/// ```
+pub fn parse_source(source: &str) -> Result<Program, Vec<Diagnostic>> {
+    parse_with_comments(source, Locale::default()).map(|(program, _comments)| program)
+}
+
+/// Parses FratmScript code, keeping whatever parses even when there are errors.
+///
+/// Broken constructs become `Statement::Error`/`Expression::Error` nodes, so
+/// editors can still outline, lint or format the rest of the file. Lexer and
+/// parser errors are all reported, sorted by position.
+///
+/// # Example
+///
+/// ```rust
+/// use fratm_core::parse_recovering;
+/// use fratm_core::parser::Statement;
+///
+/// let (program, errors) = parse_recovering("tien x = 1\nchist y = 2\ntien z = 3");
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(program.statements.len(), 3);
+/// assert!(matches!(program.statements[1], Statement::Error { .. }));
+/// ```
+pub fn parse_recovering(source: &str) -> (Program, Vec<Diagnostic>) {
+    Parser::streaming(Lexer::new(source)).parse_recovering()
+}
+
+/// Generates JavaScript from an AST.
+///
+/// This is the second half of [`compile`]. The AST can come from
+/// [`parse_source`] or be built by hand. Since the original source is not
+/// available here, the source map has no `sourcesContent`.
+pub fn generate(program: &Program, options: &CompileOptions) -> CompileResult {
+    let mut codegen = codegen(String::new(), Vec::new(), options);
+    let code = codegen.generate(program);
+    CompileResult {
+        code,
+        source_map: finish_source_map(&codegen, options, None),
+        warnings: warnings(program, options),
+        is_module: codegen.is_module(),
     }
+}
+
+/// Like [`generate`], but writes the JavaScript to `out` as it is produced
+/// instead of building it in memory. Returns the source map, if enabled.
+///
+/// `comments` (from [`parse_with_comments`]) are only written with
+/// [`CompileOptions::comments`]. `out` gets many small writes, so wrap
+/// files in a [`std::io::BufWriter`].
+///
+/// ```rust
+/// use fratm_core::{generate_to, parse_source};
+///
+/// let program = parse_source("chist è x = 42").unwrap();
+/// let mut out = Vec::new();
+/// generate_to(&program, Vec::new(), &Default::default(), &mut out).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "const x = 42;\n");
+/// ```
+pub fn generate_to(program: &Program, comments: Vec<Comment>, options: &CompileOptions, out: impl std::io::Write) -> std::io::Result<Option<SourceMap>> {
+    let mut codegen = codegen(IoWriter::new(out), comments, options);
+    // The I/O error itself is kept by the writer
+    let _ = codegen.write(program);
+    let source_map = finish_source_map(&codegen, options, None);
+    codegen.into_output().finish()?.flush()?;
+    Ok(source_map)
+}
 
-    // Parsing
-    let mut parser = Parser::new(tokens);
-    let program = parser.parse().map_err(|errors| {
-        let first = errors.into_iter().next().unwrap();
-        CompileError::ParseError {
-            message: first.message,
-            line: first.span.line,
-            column: first.span.column,
+fn codegen<W: std::fmt::Write>(output: W, comments: Vec<Comment>, options: &CompileOptions) -> CodeGen<W> {
+    let codegen = CodeGen::with_output(output, options.source_map)
+        .with_target(options.target)
+        .with_module_format(options.module_format)
+        .with_global_name(options.global_name.clone())
+        .with_style(options.style)
+        .with_banner(options.banner.clone(), options.footer.clone())
+        .with_use_strict(options.use_strict)
+        .with_minify(options.minify)
+        .with_optimize(options.optimize)
+        .with_module_aliases(options.module_aliases.clone())
+        .with_defines(options.defines.clone())
+        .with_env(options.env.clone())
+        .with_coverage(options.coverage)
+        .with_trace(options.trace, options.trace_hook.clone(), &comments)
+        .with_jsx(options.jsx.clone())
+        .with_decorators(options.decorators)
+        .with_assertions(options.assertions);
+    if options.comments { codegen.with_comments(comments) } else { codegen }
+}
+
+fn warnings(program: &Program, options: &CompileOptions) -> Vec<String> {
+    linter::compile_warnings(program, options.locale).iter().map(|warning| warning.localized(options.locale)).collect()
+}
+
+/// The source map of `codegen`, for the file `name` if given, else for the
+/// one of `options`
+fn finish_source_map<W: std::fmt::Write>(codegen: &CodeGen<W>, options: &CompileOptions, name: Option<&str>) -> Option<SourceMap> {
+    if !options.source_map || !cfg!(feature = "sourcemap") {
+        return None;
+    }
+    let (filename, output_filename) = match name {
+        Some(name) => (name, default_output_filename(name)),
+        None => {
+            let filename = options.filename.as_deref().unwrap_or("input.fratm");
+            (filename, options.output_filename.clone().unwrap_or_else(|| default_output_filename(filename)))
         }
-    })?;
+    };
+    Some(codegen.get_source_map()
+        .with_source(filename)
+        .with_file(&output_filename))
+}
 
-    // Code generation
-    let mut codegen = CodeGen::new(options.source_map);
-    let code = codegen.generate(&program);
+/// Lexes and parses `source`, returning the AST and the comments the lexer skipped.
+///
+/// Shared by [`compile`], the formatter and the linter so they all report
+/// the same first error.
+pub(crate) fn parse_program(source: &str) -> Result<(Program, Vec<Comment>), CompileError> {
+    parse_with_comments(source, Locale::default()).map_err(|errors| CompileError::from(errors.into_iter().next().unwrap()))
+}
 
-    let source_map = if options.source_map {
-        Some(codegen.get_source_map())
-    } else {
+/// Lexes and parses `source`, also returning the comments, which the AST doesn't keep.
+///
+/// Fails with every lexer error, or else every parser error, in `locale`.
+///
+/// ```rust
+/// use fratm_core::errors::Locale;
+///
+/// let (program, comments) = fratm_core::parse_with_comments("// saluto\nstamm a dì(1)", Locale::default()).unwrap();
+/// assert_eq!(program.statements.len(), 1);
+/// assert_eq!(comments[0].text, "// saluto");
+/// ```
+pub fn parse_with_comments(source: &str, locale: Locale) -> Result<(Program, Vec<Comment>), Vec<Diagnostic>> {
+    parse_with_dialect(source, locale, &KeywordTable::neapolitan())
+}
+
+/// Like [`parse_with_comments`], for source written in `dialect`.
+pub fn parse_with_dialect(source: &str, locale: Locale, dialect: &KeywordTable) -> Result<(Program, Vec<Comment>), Vec<Diagnostic>> {
+    parse_lexed(Lexer::new(source).with_keywords(dialect), dialect, locale, ParserOptions::default(), &Limits::default(), &mut Names::new())
+}
+
+/// Like [`parse_with_comments`], as [`compile`] parses with `options`: in
+/// their dialect and locale, and with JSX and interpolation if enabled.
+/// `amb` of a variable missing from [`CompileOptions::env`] is an error,
+/// and so is importing a Node.js module in a [`ModuleFormat::Script`].
+pub fn parse_with_options(source: &str, options: &CompileOptions) -> Result<(Program, Vec<Comment>), Vec<Diagnostic>> {
+    parse_sharing(source, options, &mut Names::new())
+}
+
+/// [`parse_with_options`] starting from, and adding to, `names`
+fn parse_sharing(source: &str, options: &CompileOptions, names: &mut Names) -> Result<(Program, Vec<Comment>), Vec<Diagnostic>> {
+    options.limits.check_source(source, options.locale).map_err(|error| vec![error.into_diagnostic()])?;
+    let lexer = Lexer::new(source).with_keywords(&options.dialect).with_jsx(options.jsx.is_some()).with_interpolation(options.interpolation);
+    let (program, comments) = parse_lexed(lexer, &options.dialect, options.locale, options.parser, &options.limits, names)?;
+    let mut errors = env::check(&program, &options.env, &options.defines);
+    if options.module_format == ModuleFormat::Script {
+        errors.extend(modules::check_browser_imports(&program, &options.module_aliases));
+        errors.sort_by_key(|error| error.span.start);
+    }
+    if !errors.is_empty() { return Err(errors); }
+    Ok((program, comments))
+}
+
+fn parse_lexed(mut lexer: Lexer, dialect: &KeywordTable, locale: Locale, options: ParserOptions, limits: &Limits, names: &mut Names) -> Result<(Program, Vec<Comment>), Vec<Diagnostic>> {
+    // The tokens past the limit are never read: the parser sees the end of the file there
+    let (mut past_limit, max_tokens) = (None, limits.max_tokens.unwrap_or(usize::MAX));
+    let tokens = lexer.by_ref().enumerate().map_while(|(i, token)| {
+        if i < max_tokens { return Some(token); }
+        past_limit = Some(token.span);
         None
-    };
+    });
+    let mut parser = Parser::streaming(tokens).with_locale(locale).with_options(options).with_keywords(dialect).with_names(std::mem::take(names));
+    let program = parser.parse();
+    *names = parser.into_names();
+    limits.check_tokens(past_limit, locale).map_err(|error| vec![error.into_diagnostic()])?;
+    let mut program = program?;
+    limits.check_nodes(&mut program, locale).map_err(|error| vec![error.into_diagnostic()])?;
+    Ok((program, lexer.take_comments()))
+}
 
-    Ok(CompileResult {
-        code,
-        source_map,
-        warnings: vec![],
-    })
+/// Derives the generated file name from a source path (`dir/main.fratm` → `main.js`).
+/// Both `/` and `\\` separate directories, whatever the platform.
+fn default_output_filename(filename: &str) -> String {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+    if matches!(name, "" | "." | "..") { return "output.js".to_string(); }
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| if stem.is_empty() { name } else { stem });
+    format!("{}.js", stem)
 }
 
 /// Returns the current FratmScript version.
@@ -259,6 +1026,18 @@ pub fn version() -> &'static str {
     env!("CARGO_PKG_VERSION")
 }
 
+/// The version followed by the dialect in use, e.g. `0.1.0 (napulitano)`.
+///
+/// ```rust
+/// use fratm_core::lexer::keywords::KeywordTable;
+///
+/// let version = fratm_core::version_with_dialect(&KeywordTable::default());
+/// assert!(version.ends_with(" (napulitano)"));
+/// ```
+pub fn version_with_dialect(dialect: &KeywordTable) -> String {
+    format!("{} ({})", version(), dialect.name())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -275,4 +1054,393 @@ mod tests {
         assert!(result.code.contains("function test()"));
         assert!(result.code.contains("return 1"));
     }
+
+    #[test]
+    #[cfg(feature = "sourcemap")]
+    fn test_source_map_embeds_source() {
+        let options = CompileOptions::builder().source_map(true).filename("src/main.fratm").build();
+        let map = compile("chist è x = 42", options).unwrap().source_map.unwrap();
+        assert_eq!(map.sources, vec!["src/main.fratm".to_string()]);
+        assert_eq!(map.sources_content, Some(vec!["chist è x = 42".to_string()]));
+        assert_eq!(map.file.as_deref(), Some("main.js"));
+    }
+
+    #[test]
+    fn test_parse_then_generate() {
+        let mut program = parse_source("stamm a dì(1)").unwrap();
+        let first = program.statements[0].clone();
+        program.statements.push(first);
+        let result = generate(&program, &Default::default());
+        assert_eq!(result.code.matches("console.log(1)").count(), 2);
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        let js = |source: &str| compile(source, Default::default()).unwrap().code;
+        assert_eq!(js("a o b e c == d < f + g * h ** k"), "(a || (b && (c == (d < (f + (g * (h ** k)))))));\n");
+        assert_eq!(js("1 - 2 - 3 / 4 / 5"), "((1 - 2) - ((3 / 4) / 5));\n");
+        assert_eq!(js("2 ** 3 ** (-2) ** 2"), "(2 ** (3 ** ((-2) ** 2)));\n");
+        assert_eq!(js("a pure b e c o manco d ? 1 : 2"), "((((a && b) && c) || !d) ? 1 : 2);\n");
+    }
+
+    #[test]
+    fn test_unary_before_power() {
+        let js = |source: &str| compile(source, Default::default()).unwrap().code;
+        assert_eq!(js("2 ** -2"), "(2 ** -2);\n");
+        assert_eq!(js("(aspett a) ** 2 * -b"), "(((await a) ** 2) * -b);\n");
+        for source in ["-2 ** 2", "a * -b ** c", "2 ** -a ** b", "no a ** b", "aspett a ** 2", "leva a.b ** 2"] {
+            assert!(parse_source(source).is_err(), "{}", source);
+        }
+        let error = &parse_source("tien x = -a ** 2").unwrap_err()[0];
+        assert_eq!((error.span.column, error.labels[0].span.column), (10, 13));
+        assert!(error.suggestion.as_deref().unwrap().contains("(- x) ** y"));
+    }
+
+    #[test]
+    fn test_parse_source_reports_all_errors() {
+        let errors = parse_source("chist x = 1\ntien = 2").unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[1].span.line, 2);
+        assert!(errors[0].to_string().contains("chist è"));
+    }
+
+    #[test]
+    fn test_unclosed_brace_points_at_opener() {
+        let source = "si (overo) {\n    stamm a dì(1)\n";
+        let errors = parse_source(source).unwrap_err();
+        let label = &errors[0].labels[0];
+        assert_eq!((label.span.line, label.span.column), (1, 12));
+        assert!(label.message.contains("'{'"));
+        assert_eq!(errors[0].span.line, 3);
+    }
+
+    #[test]
+    #[cfg(feature = "suggestions")]
+    fn test_english_errors() {
+        let options = CompileOptions::builder().locale(Locale::English).build();
+        let error = compile("si (overo) {\n", options).unwrap_err();
+        assert!(error.message.starts_with("A curly brace was opened"), "{}", error.message);
+        assert_eq!(error.labels[0].message, "'{' opened here");
+        assert!(error.suggestion.as_deref().unwrap().starts_with("💡 Count"));
+    }
+
+    #[test]
+    fn test_recovery_keeps_rest_of_block() {
+        let (program, errors) = parse_recovering("facc f() {\n    tien = 1\n    piglie 2\n}\nstamm a dì(f(), )");
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        let parser::Statement::FunctionDecl { body, .. } = &program.statements[0] else { panic!() };
+        assert!(matches!(body[0], parser::Statement::Error { .. }));
+        assert!(matches!(body[1], parser::Statement::Return { .. }));
+        assert_eq!(program.statements.len(), 2);
+    }
+
+    #[test]
+    fn test_eof_inside_call_is_an_error() {
+        let errors = parse_source("stamm a dì(").unwrap_err();
+        assert_eq!(errors[0].span.line, 1);
+        let (_, errors) = parse_recovering("x = (((");
+        assert!(!errors.is_empty());
+        let (program, errors) = parse_recovering(")\nstamm a dì(1)");
+        assert_eq!((program.statements.len(), errors.len()), (2, 1));
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let parse = |source: &str| {
+            let tokens = Lexer::new(source).tokenize();
+            Parser::new(tokens).with_max_depth(16).parse()
+        };
+        let nested = |depth: usize| format!("x = {}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse(&nested(8)).is_ok());
+
+        let errors = parse(&nested(10_000)).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("troppo annidata"), "{}", errors[0].message);
+        assert!(parse(&format!("x = {}1", "-".repeat(10_000))).is_err());
+        assert!(parse(&"si (x) {\n".repeat(10_000)).is_err());
+        // Chains build trees as deep as they are long
+        assert!(parse(&format!("x = 1{}", " + 1".repeat(8))).is_ok());
+        assert!(parse(&format!("x = 1{}", " + 1".repeat(20))).is_err());
+        assert!(parse(&format!("x = a{}", ".b".repeat(20))).is_err());
+        assert!(parse(&format!("x = f{}", "()".repeat(20))).is_err());
+
+        // On the 1 MiB stack of WASM, every pass gets a tree it can walk
+        let chain = format!("stamm a dì(1{})", " + 1".repeat(2_000));
+        let error = std::thread::Builder::new().stack_size(1 << 20).spawn(move || compile(&chain, CompileOptions::default()).unwrap_err())
+            .unwrap().join().unwrap();
+        assert!(error.message.contains("troppo annidata"));
+        let options = CompileOptions::builder().parser_options(ParserOptions { max_depth: 200, ..Default::default() }).build();
+        assert!(compile(&format!("stamm a dì(1{})", " + 1".repeat(100)), options).is_ok());
+    }
+
+    #[test]
+    fn test_streaming_parser() {
+        // A stream without Eof, with a lexer error in the middle
+        let tokens: Vec<_> = Lexer::new("tien x = 1\n§\ntien y = 2").filter(|t| t.kind != lexer::TokenKind::Eof).collect();
+        let (program, errors) = Parser::streaming(tokens.into_iter()).parse_recovering();
+        assert_eq!(program.statements.len(), 2);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, "invalid-token");
+    }
+
+    #[test]
+    fn test_names_are_interned() {
+        use parser::{Expression, Statement};
+        let program = parse_source("tien conto = 1
+conto = conto + 1").unwrap();
+        let Statement::VariableDecl { name, .. } = &program.statements[0] else { panic!() };
+        let Statement::Expression { expression: Expression::Assignment { value, .. }, .. } = &program.statements[1] else { panic!() };
+        let Expression::Binary { left, .. } = value.as_ref() else { panic!() };
+        let Expression::Identifier { name: read, .. } = left.as_ref() else { panic!() };
+        assert!(std::sync::Arc::ptr_eq(name, read));
+    }
+
+    #[test]
+    fn test_parser_options() {
+        let source = "tien a = 1\nchist b = 2\nsi (a) {\n  chist c = 3\n  tien d = )\n}\nchist e = 4";
+        let errors = |parser| parse_with_options(source, &CompileOptions::builder().parser_options(parser).build()).unwrap_err();
+        assert_eq!(errors(ParserOptions::default()).len(), 5);
+        // Giving up inside a block doesn't add its missing brace
+        let two = errors(ParserOptions { max_errors: 2, ..Default::default() });
+        assert_eq!(two.iter().map(|e| e.span.line).collect::<Vec<_>>(), [2, 4]);
+        let first = errors(ParserOptions { recovery: false, max_errors: 3, ..Default::default() });
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].span.line, 2);
+        // Lexer errors count too
+        let (_, errors) = Parser::streaming(Lexer::new("tien a = 1 ¤\nchist b = 2\nchist c = 3")).with_options(ParserOptions { max_errors: 2, ..Default::default() }).parse_recovering();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_compile_many() {
+        let sources: Vec<(String, String)> = (0..40).map(|i| (format!("dir/m{}.fratm", i), format!("chist è conto{} = {}", i, i))).collect();
+        let results = compile_many(&sources, &CompileOptions::builder().source_map(true).build());
+        assert_eq!(results.len(), 40);
+        for (i, result) in results.iter().enumerate() {
+            let result = result.as_ref().unwrap();
+            assert_eq!(result.code, format!("const conto{} = {};\n", i, i));
+            #[cfg(feature = "sourcemap")]
+            {
+                let map = result.source_map.as_ref().unwrap();
+                assert_eq!(map.file.as_deref(), Some(format!("m{}.js", i).as_str()));
+                assert_eq!(map.sources, [format!("dir/m{}.fratm", i)]);
+            }
+        }
+        assert!(compile_many::<&str, &str>(&[], &CompileOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_deterministic() {
+        // Errors too must always be the same
+        let output = |result: &Result<CompileResult, CompileError>| match result {
+            Ok(result) => Ok((result.code.clone(), format!("{:?}", result.source_map), result.warnings.clone())),
+            Err(error) => Err(format!("{:?}", error)),
+        };
+        let mut sources = Vec::new();
+        for entry in std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples")).unwrap() {
+            let path = entry.unwrap().path();
+            sources.push((path.file_name().unwrap().to_string_lossy().into_owned(), std::fs::read_to_string(path).unwrap()));
+        }
+        for options in [
+            CompileOptions::builder().source_map(true).build(),
+            CompileOptions::builder().source_map(true).minify(true).optimize(true).comments(true).build(),
+            CompileOptions::builder().coverage(true).trace(Trace::All).module_format(ModuleFormat::CommonJs).build(),
+        ] {
+            let first: Vec<_> = compile_many(&sources, &options).iter().map(output).collect();
+            for _ in 0..5 {
+                let again: Vec<_> = sources.iter().map(|(name, source)| output(&compile_named(source, Some(name), &options, &mut Names::new()))).collect();
+                assert_eq!(first, again);
+            }
+        }
+        // Directories end at `\\` too, on every platform
+        assert_eq!(default_output_filename("src\\app\\main.fratm"), default_output_filename("src/app/main.fratm"));
+        assert_eq!(default_output_filename(".fratm"), ".fratm.js");
+    }
+
+    #[test]
+    fn test_names_shared_between_parses() {
+        use parser::Statement;
+        let mut parser = Parser::streaming(Lexer::new("tien conto = 1"));
+        let first = parser.parse().unwrap();
+        let mut parser = Parser::streaming(Lexer::new("chist è conto = 2")).with_names(parser.into_names());
+        let second = parser.parse().unwrap();
+        let (Statement::VariableDecl { name: a, .. }, Statement::VariableDecl { name: b, .. }) = (&first.statements[0], &second.statements[0]) else { panic!() };
+        assert!(std::sync::Arc::ptr_eq(a, b));
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        use parser::Statement;
+        let source = "facc somma(a: nummero, b): nummero { piglie a + b }\ntien x: { nome: parola }[] = []\ntien f: ((nummero) => boh)[]";
+        let program = parse_source(source).unwrap();
+        let Statement::FunctionDecl { signature: Some(signature), .. } = &program.statements[0] else { panic!() };
+        let types: Vec<_> = signature.params.iter().map(|param| param.as_ref().map(ToString::to_string)).collect();
+        assert_eq!((types, signature.result.as_ref().unwrap().to_string()), (vec![Some("nummero".into()), None], "nummero".into()));
+        let Statement::VariableDecl { annotation: Some(annotation), .. } = &program.statements[1] else { panic!() };
+        assert_eq!(annotation.to_string(), "{ nome: parola }[]");
+        let Statement::VariableDecl { annotation: Some(annotation), .. } = &program.statements[2] else { panic!() };
+        assert_eq!(annotation.to_string(), "((nummero) => boh)[]");
+        // Left out of the JavaScript
+        assert_eq!(compile(source, Default::default()).unwrap().code, "function somma(a, b) {\n  return (a + b);\n}\nlet x = [];\nlet f;\n");
+        assert!(parse_source("tien x: = 1").is_err());
+    }
+
+    #[test]
+    fn test_console_namespace() {
+        let code = compile("stamm.tabella(lista)\nstamm.dir(lista)\nlista.forEach(avvis a dì)", Default::default()).unwrap().code;
+        assert_eq!(code, "console.table(lista);\nconsole.dir(lista);\nlista.forEach(console.warn);\n");
+        assert!(parse_source("stamm(1)").is_err());
+    }
+
+    #[test]
+    fn test_unterminated_string_and_comment() {
+        let errors = parse_source("tien x = 1\nstamm a dì(\"ciao)\ntien y = 2\n").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let span = errors[0].span;
+        assert_eq!((span.line, span.column, span.end_line, span.end_column), (2, 12, 2, 13));
+        assert_eq!(errors[0].labels[0].message, "aggio cercato 'a chiusura fino ccà, 'a fine d'o file");
+        assert_eq!(errors[0].labels[0].span.line, 4);
+
+        let errors = parse_source("tien x = 1\n  /* ciao\ntien y = 2").unwrap_err();
+        let span = errors[0].span;
+        assert_eq!((span.line, span.column, span.end_column), (2, 3, 5));
+        assert!(errors[0].message.contains("commento"), "{}", errors[0].message);
+        assert_eq!(errors[0].labels[0].span.line, 3);
+    }
+
+    #[test]
+    fn test_jsx() {
+        let source = "chist è a = <Lista.Voce key={id} aria-label=\"x\" attiva>\n    Uè, {nome}!\n    <b>forte</b>{}\n</Lista.Voce>\nchist è f = <><br /></>";
+        let classic = compile(source, CompileOptions::builder().jsx(Jsx::default()).build()).unwrap().code;
+        assert_eq!(classic, "const a = React.createElement(Lista.Voce, { key: id, \"aria-label\": \"x\", attiva: true }, \"Uè, \", nome, \"!\", React.createElement(\"b\", null, \"forte\"));\n\
+            const f = React.createElement(React.Fragment, null, React.createElement(\"br\", null));\n");
+
+        let automatic = CompileOptions::builder().jsx(Jsx::Automatic { import_source: "preact".to_string() }).build();
+        let code = compile(source, automatic).unwrap().code;
+        assert_eq!(code, "import { Fragment as _Fragment, jsx as _jsx, jsxs as _jsxs } from \"preact/jsx-runtime\";\n\
+            const a = _jsxs(Lista.Voce, { \"aria-label\": \"x\", attiva: true, children: [\"Uè, \", nome, \"!\", _jsx(\"b\", { children: \"forte\" })] }, id);\n\
+            const f = _jsx(_Fragment, { children: _jsx(\"br\", {}) });\n");
+
+        assert!(compile(source, Default::default()).is_err());
+        let error = compile("chist è x = <div>ciao</span>", CompileOptions::builder().jsx(Jsx::default()).build()).unwrap_err().to_string();
+        assert!(error.contains("'<div>' nun se chiude"), "{}", error);
+    }
+
+    #[test]
+    fn test_decorators() {
+        let source = "@sigillata\nmann for na famiglie Pizza {\n    facc costruttore() {}\n    @log\n    @(debug o niente)\n    facc saluta() {}\n}";
+        let code = compile(source, Default::default()).unwrap().code;
+        assert_eq!(code, "export @sigillata class Pizza {\n  constructor() {\n  }\n  @log @(debug || niente) saluta() {\n  }\n}\n");
+
+        let legacy = CompileOptions::builder().decorators(Decorators::Legacy).module_format(ModuleFormat::CommonJs).build();
+        let code = compile(source, legacy).unwrap().code;
+        assert_eq!(code, "class Pizza {\n  constructor() {\n  }\n  saluta() {\n  }\n}\n\
+            Pizza.prototype.saluta = ((debug || niente)(Pizza.prototype.saluta, { kind: \"method\", name: \"saluta\" }) || Pizza.prototype.saluta);\n\
+            Pizza.prototype.saluta = (log(Pizza.prototype.saluta, { kind: \"method\", name: \"saluta\" }) || Pizza.prototype.saluta);\n\
+            Pizza = (sigillata(Pizza, { kind: \"class\", name: \"Pizza\" }) || Pizza);\n\
+            exports.Pizza = Pizza;\n");
+
+        for misplaced in ["@log\nfacc f() {}", "na famiglie A {\n    @log\n    facc costruttore() {}\n}", "@log mann for tien x = 1"] {
+            let errors = parse_source(misplaced).unwrap_err();
+            assert!(errors[0].message.starts_with("'@' va sulo primma"), "{}", errors[0].message);
+        }
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let options = CompileOptions::builder().interpolation(true).build();
+        let code = compile("chist è s = \"Ciao {nome}, {n + 1} {{graffe}} `{\"a {b}\"}`\"", options.clone()).unwrap().code;
+        assert_eq!(code, "const s = `Ciao ${nome}, ${(n + 1)} {graffe} \\`${`a ${b}`}\\``;\n");
+        assert_eq!(compile("chist è s = \"{x}\"", Default::default()).unwrap().code, "const s = \"{x}\";\n");
+
+        let error = compile("chist è s = \"a {x y}\"", options).unwrap_err().to_string();
+        assert!(error.contains("'{' dint'a stringa nun se chiude"), "{}", error);
+    }
+
+    #[test]
+    fn test_match() {
+        let code = compile("tien n = 1\nstamm a dì(vir buono (n) { 0 => \"zero\", _ => \"ato\" })", Default::default()).unwrap().code;
+        assert_eq!(code, "let n = 1;\nconsole.log(((n === 0) ? \"zero\" : \"ato\"));\n");
+
+        let source = "tien p = [1, 2]\nstamm a dì(vir buono (p) {\n    [x, 2] si (x > 0) => x\n    _ => 0\n})";
+        let code = compile(source, Default::default()).unwrap().code;
+        assert_eq!(code, "let p = [1, 2];\nconsole.log((($valore) => {\n  \
+            if (((Array.isArray($valore) && ($valore.length === 2)) && ($valore[1] === 2))) {\n    \
+            const x = $valore[0];\n    if ((x > 0)) return x;\n  }\n  return 0;\n})(p));\n");
+
+        let errors = parse_source("vir buono (x) { + => 1 }").unwrap_err();
+        assert!(errors[0].message.starts_with("Ccà ce vo' nu caso"), "{}", errors[0].message);
+    }
+
+    #[test]
+    fn test_nullish() {
+        let code = compile("stamm a dì(utente.nomme o sinnò \"anonimo\")", Default::default()).unwrap().code;
+        assert_eq!(code, "console.log((utente?.nomme ?? \"anonimo\"));\n");
+        let code = compile("tien x = a.b[0].c() o sinnò b o c", Default::default()).unwrap().code;
+        assert_eq!(code, "let x = ((a?.b?.[0]?.c() ?? b) || c);\n");
+        assert!(parse_source("tien x = a o sinnò").is_err());
+    }
+
+    #[test]
+    fn test_promise_helpers() {
+        let code = compile("chist è p = aspett tutt' quant([a(), b()])\nchist è q = 'o primm che arriva(p)", Default::default()).unwrap().code;
+        assert_eq!(code, "const p = await Promise.all([a(), b()]);\nconst q = Promise.race(p);\n");
+        assert!(parse_source("tutt' e").is_err());
+        assert!(parse_source("'o primm arriva").is_err());
+    }
+
+    #[test]
+    fn test_error_classes() {
+        let code = compile("na famiglie ErroreMio figlio 'e Errore {}\npruvamm { } e si schiatta (err è uno ErroreMio) { }", Default::default()).unwrap().code;
+        assert_eq!(code, "class ErroreMio extends Error {\n}\ntry {\n} catch (err) {\n  if (!(err instanceof ErroreMio)) throw err;\n}\n");
+        let code = compile("na famiglie A figlio 'e B {\n    facc costruttore(x) { stu cos.x = x }\n}", Default::default()).unwrap().code;
+        assert_eq!(code, "class A extends B {\n  constructor(x) {\n    super(...arguments);\n    this.x = x;\n  }\n}\n");
+        // pate(...) passes the parent its own arguments, in place of those
+        let code = compile("na famiglie A figlio 'e B {\n    facc costruttore(x) {\n        pate(x, 2)\n        stu cos.x = x\n    }\n}", Default::default()).unwrap().code;
+        assert_eq!(code, "class A extends B {\n  constructor(x) {\n    super(x, 2);\n    this.x = x;\n  }\n}\n");
+        let error = compile("na famiglie A figlio 'e B {\n    facc costruttore(x) {\n        stu cos.x = x\n        pate(x)\n    }\n}", Default::default()).unwrap_err();
+        assert_eq!((error.diagnostic().span.line, error.diagnostic().span.column), (4, 9));
+        // Elsewhere it's a name like any other
+        assert_eq!(compile("pate(1)", Default::default()).unwrap().code, "pate(1);\n");
+        assert!(parse_source("pruvamm { } e si schiatta (err è ErroreMio) { }").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_dialect() {
+        let pack = r#"{ "name": "romanesco", "words": { "tien": "tiè", "stamm": "damme", "mentre": "finché" } }"#;
+        let dialect: KeywordTable = serde_json::from_str(pack).unwrap();
+        let source = "tiè tien = 0\nfinché che (tien < 2) { tien = tien + 1 }\ndamme a dì(tien)";
+        let code = compile(source, CompileOptions::builder().dialect(dialect).build()).unwrap().code;
+        assert_eq!(code, "let tien = 0;\nwhile ((tien < 2)) {\n  tien = (tien + 1);\n}\nconsole.log(tien);\n");
+        assert!(compile(source, Default::default()).is_err());
+
+        let unknown = r#"{ "name": "romanesco", "words": { "pizza": "pizz" } }"#;
+        assert!(serde_json::from_str::<KeywordTable>(unknown).unwrap_err().to_string().contains("'pizza' is not a keyword"));
+    }
+
+    /// Lexing, parsing and formatting must end in a result or a diagnostic
+    fn never_panics(source: &str) {
+        let _ = lexer::Lexer::new(source).tokenize();
+        let _ = parse_recovering(source);
+        if parse_source(source).is_ok() {
+            let _ = compile(source, Default::default());
+        }
+        let _ = formatter::format(source, &Default::default());
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_any_bytes_never_panic(bytes in proptest::collection::vec(proptest::prelude::any::<u8>(), 0..200)) {
+            never_panics(&String::from_utf8_lossy(&bytes));
+        }
+
+        #[test]
+        fn test_any_tokens_never_panic(words in proptest::collection::vec(
+            "tien|chist è|si|sinnò|pe ogni|mentre che|facc|piglie|stamm a dì|nu bell|pruvamm|e si schiatta|mo vir|aspett|na famiglie|\\(|\\)|\\{|\\}|\\[|\\]|,|;|=|=>|\\?|:|\\.|\\+|\\*\\*|!|\"|`|\\$\\{|/\\*|//|\n|x|1|1\\.5e|0x",
+            0..40,
+        )) {
+            never_panics(&words.join(" "));
+        }
+    }
 }
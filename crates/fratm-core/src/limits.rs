@@ -0,0 +1,169 @@
+//! Size limits for untrusted input
+//!
+//! A service compiling whatever it is sent, such as the playground, sets
+//! [`CompileOptions::limits`](crate::CompileOptions::limits) so that a huge
+//! source fails with a `too-large` error instead of taking all the memory
+//! there is. Each limit is checked as soon as it can be: the source before
+//! lexing, the tokens as the parser reads them (it stops at the limit), the
+//! AST nodes once parsed and the JavaScript as it is generated (it stops at
+//! the limit too). The token limit is the one that bounds the memory of
+//! parsing; the others bound what is kept. No limit is set by default.
+//!
+//! ```rust
+//! use fratm_core::limits::Limits;
+//! use fratm_core::{compile, CompileOptions};
+//!
+//! let limits = Limits { max_tokens: Some(100), ..Default::default() };
+//! let options = CompileOptions::builder().limits(limits).build();
+//! assert!(compile("tien x = 1", options.clone()).is_ok());
+//! let error = compile(&"tien x = 1\n".repeat(100), options).unwrap_err();
+//! assert!(error.message.contains("troppo gruosso"));
+//! ```
+
+use std::fmt;
+
+use crate::errors::{CompileError, Diagnostic, Locale};
+use crate::lexer::Span;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// How large a compilation may get; `None` is no limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+pub struct Limits {
+    /// Bytes of source
+    pub max_source_bytes: Option<usize>,
+    /// Tokens, line breaks included
+    pub max_tokens: Option<usize>,
+    /// Statements, expressions, patterns and type annotations of the AST
+    pub max_nodes: Option<usize>,
+    /// Bytes of JavaScript, without the source map
+    pub max_output_bytes: Option<usize>,
+}
+
+/// What a limit counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Measure {
+    SourceBytes,
+    Tokens,
+    Nodes,
+    OutputBytes,
+}
+
+impl Limits {
+    /// `Err` if `source` is over [`max_source_bytes`](Self::max_source_bytes),
+    /// for the tools that take a source without compiling it
+    pub fn check_source(&self, source: &str, locale: Locale) -> Result<(), CompileError> {
+        check(Measure::SourceBytes, source.len(), self.max_source_bytes, Span::default(), locale)
+    }
+
+    /// `Err` at `past_limit`, the first token past the limit, if the parser got that far
+    pub(crate) fn check_tokens(&self, past_limit: Option<Span>, locale: Locale) -> Result<(), CompileError> {
+        match (past_limit, self.max_tokens) {
+            (Some(span), Some(max)) => check(Measure::Tokens, max + 1, Some(max), span, locale),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_nodes(&self, program: &mut crate::parser::Program, locale: Locale) -> Result<(), CompileError> {
+        let Some(max) = self.max_nodes else { return Ok(()) };
+        let mut nodes = 0;
+        program.statements.iter_mut().for_each(|statement| statement.spans_mut(&mut |_| nodes += 1));
+        check(Measure::Nodes, nodes, Some(max), Span::default(), locale)
+    }
+
+    /// `inner`, taking no more than [`max_output_bytes`](Self::max_output_bytes)
+    pub(crate) fn bound_output<W>(&self, inner: W) -> Bounded<W> {
+        Bounded { inner, left: self.max_output_bytes.unwrap_or(usize::MAX), exceeded: false }
+    }
+
+    /// What was written to `output`, or `Err` if it went past the limit
+    pub(crate) fn check_output<W>(&self, output: Bounded<W>, locale: Locale) -> Result<W, CompileError> {
+        match self.max_output_bytes {
+            Some(max) if output.exceeded => check(Measure::OutputBytes, max + 1, Some(max), Span::default(), locale).map(|_| output.inner),
+            _ => Ok(output.inner),
+        }
+    }
+}
+
+/// A writer that fails once more is written to it than it has room for, so
+/// the generator stops there instead of once all the output is in memory
+pub(crate) struct Bounded<W> {
+    inner: W,
+    left: usize,
+    exceeded: bool,
+}
+
+impl<W: fmt::Write> fmt::Write for Bounded<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.len() > self.left {
+            self.exceeded = true;
+            return Err(fmt::Error);
+        }
+        self.left -= s.len();
+        self.inner.write_str(s)
+    }
+}
+
+fn check(measure: Measure, count: usize, max: Option<usize>, span: Span, locale: Locale) -> Result<(), CompileError> {
+    let Some(max) = max.filter(|&max| count > max) else { return Ok(()) };
+    let (message, note) = match locale {
+        Locale::Napoletano => {
+            let what = match measure {
+                Measure::SourceBytes => "'O sorgente è cchiù 'e",
+                Measure::Tokens => "'O sorgente tene cchiù 'e",
+                Measure::Nodes => "L'AST tene cchiù 'e",
+                Measure::OutputBytes => "'O JavaScript è cchiù 'e",
+            };
+            let unit = match measure { Measure::Tokens => "token", Measure::Nodes => "nodi", _ => "byte" };
+            ("Statt accuorto, 'o file è troppo gruosso!", format!("{} {} {}: spartiscilo in cchiù file", what, max, unit))
+        }
+        Locale::English => {
+            let what = match measure {
+                Measure::SourceBytes => "The source is over",
+                Measure::Tokens => "The source has over",
+                Measure::Nodes => "The AST has over",
+                Measure::OutputBytes => "The JavaScript is over",
+            };
+            let unit = match measure { Measure::Tokens => "tokens", Measure::Nodes => "nodes", _ => "bytes" };
+            ("Careful, the file is too large!", format!("{} {} {}: split it into more files", what, max, unit))
+        }
+    };
+    let mut diagnostic = Diagnostic::error("too-large", message, span);
+    diagnostic.notes.push(note);
+    Err(diagnostic.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compile, parse_with_options, CompileOptions};
+
+    fn options(limits: Limits) -> CompileOptions {
+        CompileOptions::builder().limits(limits).build()
+    }
+
+    #[test]
+    fn test_limits() {
+        let source = "tien x = 1 + 2\n".repeat(10);
+        assert!(compile(&source, options(Limits { max_source_bytes: Some(150), max_tokens: Some(80), max_nodes: Some(40), max_output_bytes: Some(200) })).is_ok());
+        for limits in [
+            Limits { max_source_bytes: Some(149), ..Default::default() },
+            Limits { max_tokens: Some(50), ..Default::default() },
+            Limits { max_nodes: Some(39), ..Default::default() },
+            Limits { max_output_bytes: Some(100), ..Default::default() },
+        ] {
+            let error = compile(&source, options(limits)).unwrap_err();
+            assert_eq!(error.diagnostic().code, "too-large", "{:?}", limits);
+        }
+        // The error is at the first token past the limit
+        let errors = parse_with_options(&source, &options(Limits { max_tokens: Some(10), ..Default::default() })).unwrap_err();
+        assert_eq!((errors.len(), errors[0].span.line), (1, 2));
+        assert_eq!(errors[0].notes[0], "'O sorgente tene cchiù 'e 10 token: spartiscilo in cchiù file");
+        // Generating stops at the output limit
+        let mut output = Limits { max_output_bytes: Some(3), ..Default::default() }.bound_output(String::new());
+        assert!(fmt::Write::write_str(&mut output, "ab").is_ok() && fmt::Write::write_str(&mut output, "cd").is_err());
+        assert_eq!(output.inner, "ab");
+    }
+}
@@ -0,0 +1,204 @@
+//! Line index for editor positions
+//!
+//! [`Span`]s store byte offsets, and their columns count Unicode scalar
+//! values. Editors count differently: Monaco, CodeMirror and LSP all use
+//! UTF-16 code units, so `sfòls` is 5 wide for them but 6 bytes for us.
+//! [`LineIndex`] converts between the two.
+//!
+//! All positions produced here are **0-based**, like LSP.
+//!
+//! ```rust
+//! use fratm_core::line_index::{LineCol, LineIndex};
+//!
+//! let index = LineIndex::new("tien x = sfòls\nx");
+//! // "x" on the second line: byte 16, but UTF-16 offset 15
+//! assert_eq!(index.line_col(16), LineCol { line: 1, col: 0 });
+//! assert_eq!(index.utf16_offset(16), 15);
+//! assert_eq!(index.offset_from_utf16(15), 16);
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use crate::lexer::Span;
+
+/// A 0-based line and column.
+///
+/// Whether `col` counts bytes or UTF-16 code units depends on the method
+/// that produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LineCol {
+    pub line: usize,
+    pub col: usize,
+}
+
+/// A start/end pair of UTF-16 positions, shaped like an LSP `Range`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Range {
+    pub start: LineCol,
+    pub end: LineCol,
+}
+
+/// A character that takes more than one byte, within its line.
+#[derive(Debug, Clone, Copy)]
+struct WideChar {
+    /// Byte column where the character starts
+    start: usize,
+    len_utf8: usize,
+    len_utf16: usize,
+}
+
+/// Maps byte offsets to lines and UTF-16 columns, and back.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    /// Byte offset where each line starts
+    line_starts: Vec<usize>,
+    /// Byte offset where each line's text ends, before its `\n` or `\r\n`
+    line_ends: Vec<usize>,
+    /// UTF-16 offset where each line starts
+    utf16_line_starts: Vec<usize>,
+    /// Multi-byte characters of each line
+    wide_chars: Vec<Vec<WideChar>>,
+    len: usize,
+}
+
+impl LineIndex {
+    /// Builds the index for `text`. Lines end at `\n`, `\r\n` or a lone
+    /// `\r`, the same as for the lexer.
+    pub fn new(text: &str) -> Self {
+        let bytes = text.as_bytes();
+        let mut line_starts = vec![0];
+        let mut line_ends = Vec::new();
+        let mut utf16_line_starts = vec![0];
+        let mut wide_chars = vec![Vec::new()];
+        let mut utf16_offset = 0;
+
+        for (offset, c) in text.char_indices() {
+            utf16_offset += c.len_utf16();
+            if c == '\n' || (c == '\r' && bytes.get(offset + 1) != Some(&b'\n')) {
+                let crlf = c == '\n' && offset > 0 && bytes[offset - 1] == b'\r';
+                line_ends.push(if crlf { offset - 1 } else { offset });
+                line_starts.push(offset + 1);
+                utf16_line_starts.push(utf16_offset);
+                wide_chars.push(Vec::new());
+            } else if !c.is_ascii() {
+                let line_start = line_starts[line_starts.len() - 1];
+                wide_chars.last_mut().unwrap().push(WideChar {
+                    start: offset - line_start,
+                    len_utf8: c.len_utf8(),
+                    len_utf16: c.len_utf16(),
+                });
+            }
+        }
+
+        line_ends.push(text.len());
+        Self { line_starts, line_ends, utf16_line_starts, wide_chars, len: text.len() }
+    }
+
+    /// Line and byte column of a byte offset.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = offset.min(self.len);
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        LineCol { line, col: offset - self.line_starts[line] }
+    }
+
+    /// Line and UTF-16 column of a byte offset.
+    pub fn line_col_utf16(&self, offset: usize) -> LineCol {
+        let LineCol { line, col } = self.line_col(offset);
+        let mut utf16_col = col;
+        for wide in &self.wide_chars[line] {
+            if wide.start >= col {
+                break;
+            }
+            utf16_col -= wide.len_utf8 - wide.len_utf16;
+        }
+        LineCol { line, col: utf16_col }
+    }
+
+    /// Byte offset of a line and UTF-16 column, clamped to the line end.
+    pub fn offset_utf16(&self, pos: LineCol) -> usize {
+        let line = pos.line.min(self.line_starts.len() - 1);
+        let line_end = self.line_ends[line];
+        let mut byte_col = pos.col;
+        for wide in &self.wide_chars[line] {
+            let utf16_start = wide.start - (byte_col - pos.col);
+            if utf16_start >= pos.col {
+                break;
+            }
+            byte_col += wide.len_utf8 - wide.len_utf16;
+        }
+        (self.line_starts[line] + byte_col).min(line_end)
+    }
+
+    /// Absolute UTF-16 offset (a JavaScript string index) of a byte offset.
+    pub fn utf16_offset(&self, offset: usize) -> usize {
+        let pos = self.line_col_utf16(offset);
+        self.utf16_line_starts[pos.line] + pos.col
+    }
+
+    /// Byte offset of an absolute UTF-16 offset.
+    pub fn offset_from_utf16(&self, utf16_offset: usize) -> usize {
+        let line = self.utf16_line_starts.partition_point(|&start| start <= utf16_offset) - 1;
+        self.offset_utf16(LineCol { line, col: utf16_offset - self.utf16_line_starts[line] })
+    }
+
+    /// UTF-16 range covered by a span.
+    pub fn range_utf16(&self, span: Span) -> Range {
+        Range { start: self.line_col_utf16(span.start), end: self.line_col_utf16(span.end) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_col() {
+        let index = LineIndex::new("chist è x = 1\n\ntien y");
+        assert_eq!(index.line_col(0), LineCol { line: 0, col: 0 });
+        assert_eq!(index.line_col(9), LineCol { line: 0, col: 9 });
+        assert_eq!(index.line_col(16), LineCol { line: 2, col: 0 });
+        assert_eq!(index.line_col(100), LineCol { line: 2, col: 6 });
+    }
+
+    #[test]
+    fn test_utf16_columns() {
+        // "è" is 2 bytes but 1 UTF-16 unit, "🍕" is 4 bytes and 2 units
+        let source = "chist è x = \"🍕\" + sfòls";
+        let index = LineIndex::new(source);
+        let x = source.find('x').unwrap();
+        assert_eq!(index.line_col_utf16(x), LineCol { line: 0, col: 8 });
+        let end = source.len();
+        assert_eq!(index.line_col_utf16(end), LineCol { line: 0, col: 24 });
+
+        for (offset, _) in source.char_indices() {
+            let pos = index.line_col_utf16(offset);
+            assert_eq!(index.offset_utf16(pos), offset);
+            assert_eq!(index.offset_from_utf16(index.utf16_offset(offset)), offset);
+        }
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let source = "tien x\r\ntien y\rtien z\n";
+        let index = LineIndex::new(source);
+        assert_eq!(index.line_col(source.find('y').unwrap()), LineCol { line: 1, col: 5 });
+        assert_eq!(index.line_col(source.find('z').unwrap()), LineCol { line: 2, col: 5 });
+        // Past the end of a line is its end, not the `\r`
+        assert_eq!(index.offset_utf16(LineCol { line: 0, col: 50 }), 6);
+        assert_eq!(index.offset_utf16(LineCol { line: 1, col: 50 }), 14);
+    }
+
+    #[test]
+    fn test_span_range() {
+        let source = "tien è = 1\nsfòls";
+        let mut lexer = crate::lexer::Lexer::new(source);
+        let tokens = lexer.tokenize();
+        let sfols = tokens.iter().find(|t| t.literal == "sfòls").unwrap();
+        let range = LineIndex::new(source).range_utf16(sfols.span);
+        assert_eq!(range.start, LineCol { line: 1, col: 0 });
+        assert_eq!(range.end, LineCol { line: 1, col: 5 });
+    }
+}
@@ -0,0 +1,491 @@
+//! Linter for FratmScript
+//!
+//! Reports code that compiles but is probably a mistake. Warnings are
+//! [`Diagnostic`]s whose `code` is the rule that produced them, so tools
+//! can filter or silence individual rules.
+//!
+//! | Rule | What it catches |
+//! |------|-----------------|
+//! | `unused-variable` | `chist è`/`tien` bindings that are never read |
+//! | `assign-in-condition` | `si (x = 1)` where `==` was probably meant |
+//! | `const-reassign` | assigning to a `chist è` constant |
+//! | `empty-block` | `si`/`mentre che`/`pe`/`e si schiatta` with an empty body |
+//! | `reserved-word` | names JavaScript reserves, like `class` (written as `$class` in the output) |
+//! | `confusable-name` | names mixing alphabets, or looking like another name: Cyrillic `а` and Latin `a` |
+//!
+//! The control-flow rules of [`crate::flow`], such as `unreachable-code`,
+//! are reported too. With the source at hand, `assign-in-condition` comes
+//! with the [`Fix`](crate::fix::Fix) turning `=` into `==`. Messages and
+//! suggestions are in the [`Locale`] given.
+//!
+//! ```rust
+//! use fratm_core::errors::Locale;
+//! use fratm_core::linter::lint;
+//!
+//! let warnings = lint("tien x = 1", Locale::English).unwrap();
+//! assert_eq!(warnings[0].code, "unused-variable");
+//! assert_eq!(warnings[0].message, "'x' is declared but never used");
+//! ```
+
+use std::collections::HashMap;
+
+use unicode_security::{skeleton, MixedScript};
+
+use crate::codegen::{is_reserved_word, js_name};
+use crate::errors::{CompileError, Diagnostic, Locale};
+use crate::fix::Fix;
+use crate::lexer::{Lexer, Span, TokenKind};
+use crate::parser::*;
+
+/// Lints FratmScript source code.
+///
+/// Fails with the same error as [`crate::compile`] if the source doesn't parse.
+pub fn lint(source: &str, locale: Locale) -> Result<Vec<Diagnostic>, CompileError> {
+    let (program, _) = crate::parse_with_comments(source, locale).map_err(|errors| CompileError::from(errors.into_iter().next().unwrap()))?;
+    Ok(lint_source(source, &program, locale))
+}
+
+/// Like [`lint_program`], with the fixes that need the text of `source`,
+/// which `program` was parsed from.
+pub fn lint_source(source: &str, program: &Program, locale: Locale) -> Vec<Diagnostic> {
+    let mut warnings = lint_program(program, locale);
+    if !warnings.iter().any(|w| w.code == "assign-in-condition") { return warnings; }
+    let tokens = Lexer::new(source).tokenize();
+    for warning in warnings.iter_mut().filter(|w| w.code == "assign-in-condition") {
+        // The `=` of the assignment: the first outside the brackets of its target
+        let mut depth = 0i32;
+        let equal = tokens.iter().skip_while(|t| t.span.start < warning.span.start).take_while(|t| t.span.end <= warning.span.end).find(|t| {
+            match t.kind {
+                TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace => depth += 1,
+                TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace => depth -= 1,
+                _ => {}
+            }
+            depth == 0 && t.kind == TokenKind::Equal
+        });
+        if let Some(equal) = equal { warning.fix = Some(Fix::insert_after(equal.span, "=")); }
+    }
+    warnings
+}
+
+/// Lints an already parsed program. Warnings are sorted by position.
+pub fn lint_program(program: &Program, locale: Locale) -> Vec<Diagnostic> {
+    let mut linter = Linter { locale, ..Linter::default() };
+    linter.collect_declarations(&program.statements, false);
+    linter.statements(&program.statements);
+
+    for decl in linter.declarations.values().flatten() {
+        if !decl.exported && !linter.reads.contains_key(&decl.name) {
+            let message = match locale {
+                Locale::Napoletano => format!("'{}' è dichiarat ma nun s'usa maje", decl.name),
+                Locale::English => format!("'{}' is declared but never used", decl.name),
+            };
+            linter.warnings.push(Diagnostic::warning("unused-variable", message, decl.span));
+        }
+    }
+    linter.warnings.extend(crate::flow::check(program, locale));
+    linter.warnings.extend(confusable_names(&linter.names, locale));
+
+    linter.warnings.sort_by(|a, b| (a.span.start, &a.code).cmp(&(b.span.start, &b.code)));
+    linter.warnings
+}
+
+/// Only the `reserved-word` warnings, which [`crate::compile`] reports too
+/// since they change the names in the output.
+pub fn reserved_words(program: &Program, locale: Locale) -> Vec<Diagnostic> {
+    lint_program(program, locale).into_iter().filter(|w| w.code == "reserved-word").collect()
+}
+
+/// The warnings [`crate::compile`] reports: `reserved-word`, and the
+/// control-flow ones of [`crate::flow`]. Sorted by position.
+pub fn compile_warnings(program: &Program, locale: Locale) -> Vec<Diagnostic> {
+    let mut warnings = reserved_words(program, locale);
+    warnings.extend(crate::flow::check(program, locale));
+    warnings.sort_by_key(|w| w.span.start);
+    warnings
+}
+
+struct Declaration {
+    name: Name,
+    is_const: bool,
+    exported: bool,
+    span: Span,
+}
+
+/// Name-based (not scope-aware) bookkeeping: good enough for warnings,
+/// which must never produce false errors.
+#[derive(Default)]
+struct Linter {
+    declarations: HashMap<Name, Vec<Declaration>>,
+    reads: HashMap<Name, usize>,
+    /// Every name declared or read, where it first shows up
+    names: HashMap<Name, Span>,
+    warnings: Vec<Diagnostic>,
+    locale: Locale,
+}
+
+/// Non-ASCII names that mix alphabets, or look like another of the names:
+/// same skeleton in the sense of UTS #39
+fn confusable_names(names: &HashMap<Name, Span>, locale: Locale) -> Vec<Diagnostic> {
+    let english = locale == Locale::English;
+    let mut lookalikes: HashMap<String, Vec<&Name>> = HashMap::new();
+    for name in names.keys() {
+        lookalikes.entry(skeleton(name).collect()).or_default().push(name);
+    }
+    let mut warnings = Vec::new();
+    for (name, span) in names.iter().filter(|(name, _)| !name.is_ascii()) {
+        if !name.is_single_script() {
+            let (message, suggestion) = if english {
+                (format!("'{}' mixes letters of different alphabets", name), "💡 Write the name in the letters of one alphabet")
+            } else {
+                (format!("'{}' mmesca lettere 'e alfabeti diversi", name), "💡 Scrive 'o nomme cu 'e lettere 'e n'alfabeto sulo")
+            };
+            warnings.push(Diagnostic::warning("confusable-name", message, *span).with_suggestion(suggestion));
+            continue;
+        }
+        let twin = lookalikes[&skeleton(name).collect::<String>()].iter().filter(|other| **other != name).min();
+        if let Some(twin) = twin {
+            let (message, suggestion) = if english {
+                (format!("'{}' looks like '{}', but it is another name", name, twin), "💡 If it is the same, write both with the same letters; if not, give them names that don't look alike")
+            } else {
+                (format!("'{}' pare '{}', ma è n'ato nomme", name, twin), "💡 Si è 'o stesso, scrivile cu 'e stesse lettere; si no, dalle nomme ca nun s'assumigliano")
+            };
+            warnings.push(Diagnostic::warning("confusable-name", message, *span).with_suggestion(suggestion));
+        }
+    }
+    warnings
+}
+
+impl Linter {
+    fn collect_declarations(&mut self, statements: &[Statement], exported: bool) {
+        for stmt in statements {
+            match stmt {
+                Statement::VariableDecl { name, is_const, span, .. } => {
+                    self.declarations.entry(name.clone()).or_default().push(Declaration {
+                        name: name.clone(),
+                        is_const: *is_const,
+                        exported,
+                        span: *span,
+                    });
+                }
+                Statement::Export { declaration: Some(decl), .. } => {
+                    self.collect_declarations(std::slice::from_ref(decl), true);
+                }
+                _ => for_each_child_body(stmt, |body| self.collect_declarations(body, false)),
+            }
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for stmt in statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::VariableDecl { name, value, span, .. } => {
+                self.check_name(name, *span);
+                if let Some(value) = value { self.expression(value); }
+            }
+            Statement::FunctionDecl { name, params, body, span, .. } => {
+                self.check_name(name, *span);
+                self.function(params, body, *span);
+            }
+            Statement::Return { value, .. } => {
+                if let Some(value) = value { self.expression(value); }
+            }
+            Statement::If { condition, then_branch, else_branch, span } => {
+                self.condition(condition);
+                self.check_empty(then_branch, "si", span);
+                self.statements(then_branch);
+                if let Some(else_branch) = else_branch { self.statements(else_branch); }
+            }
+            Statement::While { condition, body, span } => {
+                self.condition(condition);
+                self.check_empty(body, "mentre che", span);
+                self.statements(body);
+            }
+            Statement::For { init, condition, update, body, span } => {
+                if let Some(init) = init { self.statement(init); }
+                if let Some(condition) = condition { self.condition(condition); }
+                if let Some(update) = update { self.expression(update); }
+                self.check_empty(body, "pe", span);
+                self.statements(body);
+            }
+            Statement::Break { .. } | Statement::Continue { .. } | Statement::Debugger { .. } | Statement::Error { .. } => {}
+            Statement::Conditional { then_branch, else_branch, .. } => {
+                self.statements(then_branch);
+                if let Some(else_branch) = else_branch { self.statements(else_branch); }
+            }
+            Statement::TryCatch { try_body, catch_param, catch_type, catch_body, span } => {
+                if let Some(param) = catch_param { self.check_name(param, *span); }
+                if let Some(catch_type) = catch_type { self.expression(catch_type); }
+                self.statements(try_body);
+                self.check_empty(catch_body, "e si schiatta", span);
+                self.statements(catch_body);
+            }
+            Statement::Throw { value, .. } => self.expression(value),
+            Statement::Assert { condition, message, .. } => {
+                self.condition(condition);
+                if let Some(message) = message { self.expression(message); }
+            }
+            Statement::ClassDecl { name, decorators, superclass, methods, span } => {
+                self.check_name(name, *span);
+                for decorator in decorators { self.expression(decorator); }
+                if let Some(superclass) = superclass { self.expression(superclass); }
+                // Method names are properties: `delete() {}` is fine
+                for method in methods {
+                    if let Statement::FunctionDecl { params, body, decorators, span, .. } = method {
+                        for decorator in decorators { self.expression(decorator); }
+                        self.function(params, body, *span);
+                    }
+                }
+            }
+            Statement::Import { specifiers, span, .. } => {
+                for specifier in specifiers { self.check_name(&specifier.local, *span); }
+            }
+            Statement::Export { declaration, default_value, .. } => {
+                if let Some(decl) = declaration { self.statement(decl); }
+                if let Some(value) = default_value { self.expression(value); }
+            }
+            Statement::Expression { expression, .. } => self.expression(expression),
+            Statement::Block { statements, .. } => self.statements(statements),
+        }
+    }
+
+    fn condition(&mut self, condition: &Expression) {
+        if let Expression::Assignment { span, .. } = condition {
+            let (message, suggestion) = match self.locale {
+                Locale::Napoletano => ("Ccà ce sta n'assegnazione 'int'a condizione: vulive dicere '=='?", "💡 Pe confrontà usa '===' (o '==')"),
+                Locale::English => ("There is an assignment in the condition: did you mean '=='?", "💡 To compare, use '===' (or '==')"),
+            };
+            self.warnings.push(Diagnostic::warning("assign-in-condition", message, *span).with_suggestion(suggestion));
+        }
+        self.expression(condition);
+    }
+
+    fn function(&mut self, params: &[Name], body: &[Statement], span: Span) {
+        for param in params { self.check_name(param, span); }
+        self.statements(body);
+    }
+
+    fn check_name(&mut self, name: &Name, span: Span) {
+        self.names.entry(name.clone()).or_insert(span);
+        if is_reserved_word(name) {
+            let (message, suggestion) = match self.locale {
+                Locale::Napoletano => (
+                    format!("'{}' è 'na parola riservata 'e JavaScript: 'int'o JavaScript addiventa '{}'", name, js_name(name)),
+                    format!("💡 Dalle n'ato nome, p'esempio '{}_'", name),
+                ),
+                Locale::English => (
+                    format!("'{}' is a JavaScript reserved word: in the JavaScript it becomes '{}'", name, js_name(name)),
+                    format!("💡 Give it another name, for example '{}_'", name),
+                ),
+            };
+            self.warnings.push(Diagnostic::warning("reserved-word", message, span).with_suggestion(suggestion));
+        }
+    }
+
+    fn check_empty(&mut self, body: &[Statement], keyword: &str, span: &Span) {
+        if body.is_empty() {
+            let message = match self.locale {
+                Locale::Napoletano => format!("'O blocco 'e '{}' è vacante", keyword),
+                Locale::English => format!("The '{}' block is empty", keyword),
+            };
+            self.warnings.push(Diagnostic::warning("empty-block", message, *span));
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Identifier { name, span } => {
+                *self.reads.entry(name.clone()).or_default() += 1;
+                self.names.entry(name.clone()).or_insert(*span);
+            }
+            Expression::Assignment { target, value, span } => {
+                if let Expression::Identifier { name, .. } = target.as_ref() {
+                    let is_const = self.declarations.get(name).is_some_and(|d| d.iter().all(|d| d.is_const));
+                    if is_const {
+                        let (message, suggestion) = match self.locale {
+                            Locale::Napoletano => (format!("'{}' è 'na costante (chist è), nun se pò cagnà", name), format!("💡 Si 'o vuò cagnà, dichiaralo cu 'tien {}'", name)),
+                            Locale::English => (format!("'{}' is a constant (chist è) and can't be changed", name), format!("💡 To change it, declare it with 'tien {}'", name)),
+                        };
+                        self.warnings.push(Diagnostic::warning("const-reassign", message, *span).with_suggestion(suggestion));
+                    }
+                } else {
+                    self.expression(target);
+                }
+                self.expression(value);
+            }
+            Expression::Member { object, property, computed, .. } => {
+                self.expression(object);
+                if *computed { self.expression(property); }
+            }
+            Expression::ArrowFunction { params, body, span } => {
+                for param in params { self.check_name(param, *span); }
+                match body {
+                    ArrowBody::Expression(e) => self.expression(e),
+                    ArrowBody::Block(stmts) => {
+                        self.collect_declarations(stmts, false);
+                        self.statements(stmts);
+                    }
+                }
+            }
+            Expression::JsxElement { name: Some(tag), .. } if jsx_component(tag).is_some() => {
+                *self.reads.entry(Name::from(jsx_component(tag).unwrap_or_default())).or_default() += 1;
+                for_each_child_expression(expr, |child| self.expression(child));
+            }
+            Expression::Match { arms, .. } => {
+                for (name, span) in arms.iter().flat_map(|arm| arm.pattern.bindings()) { self.check_name(name, span); }
+                for_each_child_expression(expr, |child| self.expression(child));
+            }
+            other => for_each_child_expression(other, |child| self.expression(child)),
+        }
+    }
+}
+
+/// Calls `f` on every nested statement list of `stmt` (one level deep).
+fn for_each_child_body(stmt: &Statement, mut f: impl FnMut(&[Statement])) {
+    match stmt {
+        Statement::FunctionDecl { body, .. } | Statement::While { body, .. } => f(body),
+        Statement::For { init, body, .. } => {
+            if let Some(init) = init { f(std::slice::from_ref(init)); }
+            f(body);
+        }
+        Statement::If { then_branch, else_branch, .. } | Statement::Conditional { then_branch, else_branch, .. } => {
+            f(then_branch);
+            if let Some(else_branch) = else_branch { f(else_branch); }
+        }
+        Statement::TryCatch { try_body, catch_body, .. } => {
+            f(try_body);
+            f(catch_body);
+        }
+        Statement::ClassDecl { methods, .. } => f(methods),
+        Statement::Block { statements, .. } => f(statements),
+        _ => {}
+    }
+}
+
+/// Calls `f` on every direct sub-expression of `expr`.
+pub(crate) fn for_each_child_expression(expr: &Expression, mut f: impl FnMut(&Expression)) {
+    match expr {
+        Expression::Array { elements, .. } | Expression::Template { expressions: elements, .. } => elements.iter().for_each(f),
+        Expression::Object { properties, .. } => properties.iter().for_each(|(_, v)| f(v)),
+        Expression::Binary { left, right, .. } => {
+            f(left);
+            f(right);
+        }
+        Expression::Unary { operand, .. } | Expression::TypeOf { operand, .. } | Expression::Delete { operand, .. } => f(operand),
+        Expression::Await { argument, .. } => f(argument),
+        Expression::Assignment { target, value, .. } => {
+            f(target);
+            f(value);
+        }
+        Expression::Call { callee, arguments, .. } | Expression::New { callee, arguments, .. } => {
+            f(callee);
+            arguments.iter().for_each(f);
+        }
+        Expression::Member { object, property, .. } => {
+            f(object);
+            f(property);
+        }
+        Expression::Ternary { condition, consequent, alternate, .. } => {
+            f(condition);
+            f(consequent);
+            f(alternate);
+        }
+        Expression::ConsoleLog { arguments, .. }
+        | Expression::SuperCall { arguments, .. }
+        | Expression::ConsoleWarn { arguments, .. }
+        | Expression::ConsoleError { arguments, .. } => arguments.iter().for_each(f),
+        Expression::ArrowFunction { body: ArrowBody::Expression(body), .. } => f(body),
+        Expression::JsxElement { attributes, children, .. } => {
+            attributes.iter().filter_map(|(_, value)| value.as_ref()).for_each(&mut f);
+            children.iter().for_each(|child| if let JsxChild::Expression(child) = child { f(child) });
+        }
+        Expression::Match { subject, arms, .. } => {
+            f(subject);
+            for arm in arms {
+                if let Some(guard) = &arm.guard { f(guard); }
+                f(&arm.body);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(source: &str) -> Vec<String> {
+        lint(source, Locale::default()).unwrap().into_iter().map(|w| w.code.into_owned()).collect()
+    }
+
+    #[test]
+    fn test_unused_variable() {
+        assert_eq!(rules("tien x = 1"), vec!["unused-variable"]);
+        assert!(rules("tien x = 1\nstamm a dì(x)").is_empty());
+        assert!(rules("mann for chist è x = 1").is_empty());
+    }
+
+    #[test]
+    fn test_assign_in_condition_and_empty_block() {
+        let warnings = rules("tien x = 1\nsi (x = 2) {}\nstamm a dì(x)");
+        assert_eq!(warnings, vec!["empty-block", "assign-in-condition"]);
+    }
+
+    #[test]
+    fn test_assign_in_condition_fix() {
+        let source = "tien a = [1]\nmentre che (a[a = 0] = 2) { stamm a dì(a) }";
+        let warnings = lint(source, Locale::default()).unwrap();
+        let fixes = crate::fix::fixes_of(&warnings);
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(crate::fix::apply(source, &fixes), "tien a = [1]\nmentre che (a[a = 0] == 2) { stamm a dì(a) }");
+        // Without the source there's no telling where the `=` is
+        let program = crate::parse_source(source).unwrap();
+        assert!(lint_program(&program, Locale::default()).iter().all(|w| w.fix.is_none()));
+    }
+
+    #[test]
+    fn test_const_reassign() {
+        assert_eq!(rules("chist è x = 1\nx = 2\nstamm a dì(x)"), vec!["const-reassign"]);
+    }
+
+    #[test]
+    fn test_english() {
+        let warnings = lint("chist è x = 1\nsi (x = 2) {}\nstamm a dì(x)", Locale::English).unwrap();
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages, ["The 'si' block is empty", "There is an assignment in the condition: did you mean '=='?", "'x' is a constant (chist è) and can't be changed"]);
+        assert_eq!(warnings[2].suggestion.as_deref(), Some("💡 To change it, declare it with 'tien x'"));
+        // The parse error too
+        assert!(lint("chist x = 1", Locale::English).unwrap_err().message.contains("must be followed by 'è'"));
+    }
+
+    #[test]
+    fn test_confusable_name() {
+        // The second `a` is Cyrillic
+        let warnings = lint("tien a = 1\ntien а = 2\nstamm a dì(a + а)", Locale::default()).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!((warnings[0].code.as_ref(), warnings[0].span.line), ("confusable-name", 2));
+        assert!(warnings[0].message.contains("'a'"));
+        // Latin with a Cyrillic `о`
+        let warnings = lint("chist è pizzо = 1\nstamm a dì(pizzо)", Locale::default()).unwrap();
+        assert!(warnings[0].message.contains("alfabeti diversi"));
+        let warnings = lint("chist è pizzо = 1\nstamm a dì(pizzо)", Locale::English).unwrap();
+        assert!(warnings[0].message.contains("different alphabets"));
+        // One alphabet and nothing alike is fine, as are accents
+        assert!(lint("chist è пицца = 1\nchist è caffè = 2\nstamm a dì(пицца, caffè)", Locale::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reserved_word() {
+        let program = crate::parse_source("facc delete(class) { piglie class }\nstamm a dì(delete(1).new)").unwrap();
+        let warnings = reserved_words(&program, Locale::default());
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].message.contains("'delete'") && warnings[1].message.contains("'$class'"));
+        let warnings = reserved_words(&program, Locale::English);
+        assert_eq!(warnings[1].message, "'class' is a JavaScript reserved word: in the JavaScript it becomes '$class'");
+        assert_eq!(warnings[1].suggestion.as_deref(), Some("💡 Give it another name, for example 'class_'"));
+    }
+}
@@ -0,0 +1,208 @@
+//! Imports between FratmScript files
+//!
+//! [`check_imports`] verifies that every `chiamm { ... } da "./x.fratm"`
+//! names a file that exists and exports what is imported, so a typo shows
+//! up at compile time with the import's span instead of as a `SyntaxError`
+//! from Node. Reading the files is left to the caller, which knows where
+//! they are:
+//!
+//! ```rust
+//! use fratm_core::modules::{check_imports, exports};
+//!
+//! let pizzeria = fratm_core::parse_source("mann for facc pizza() { }").unwrap();
+//! let main = fratm_core::parse_source("chiamm { pizza, pasta } da \"./pizzeria.fratm\"").unwrap();
+//! let errors = check_imports(&main, |source| (source == "./pizzeria.fratm").then(|| exports(&pizzeria)));
+//! assert_eq!(errors[0].code, "missing-export");
+//! ```
+//!
+//! Imports of npm packages, `chiamm { express } da "express"`, go through
+//! as they are, after [`resolve_alias`] maps the project's aliases to
+//! their specifiers. [`check_packages`] optionally verifies that they are
+//! installed. For a browser `<script>`, [`check_browser_imports`] rejects
+//! Node.js's own modules.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use crate::errors::Diagnostic;
+use crate::parser::{Name, Program, Statement};
+
+/// Modules that come with Node.js, which need no package
+const NODE_BUILTINS: &[&str] = &[
+    "assert", "async_hooks", "buffer", "child_process", "cluster", "console", "crypto", "dgram", "dns",
+    "events", "fs", "http", "http2", "https", "inspector", "module", "net", "os", "path", "perf_hooks",
+    "process", "querystring", "readline", "repl", "stream", "string_decoder", "timers", "tls", "tty",
+    "url", "util", "v8", "vm", "worker_threads", "zlib",
+];
+
+/// The names `program` exports, `default` for `mann for predefinit`.
+pub fn exports(program: &Program) -> Vec<Name> {
+    program.statements.iter().filter_map(|stmt| match stmt {
+        Statement::Export { declaration: Some(declaration), .. } => match declaration.as_ref() {
+            Statement::VariableDecl { name, .. } | Statement::FunctionDecl { name, .. } | Statement::ClassDecl { name, .. } => Some(name.clone()),
+            _ => None,
+        },
+        Statement::Export { default_value: Some(_), .. } => Some(Name::from("default")),
+        _ => None,
+    }).collect()
+}
+
+/// Whether the module `source` is another FratmScript file, given by its
+/// path: `./cucina.fratm` or `../pizze/margherita.fratm`. Packages and
+/// JavaScript files are not checked.
+pub fn is_local(source: &str) -> bool {
+    (source.starts_with("./") || source.starts_with("../")) && source.ends_with(".fratm")
+}
+
+/// `source` with its alias replaced: with `pizzeria` → `@acme/pizzeria`,
+/// `"pizzeria"` is `"@acme/pizzeria"` and `"pizzeria/forno"` is
+/// `"@acme/pizzeria/forno"`.
+///
+/// ```rust
+/// use std::collections::BTreeMap;
+/// use fratm_core::modules::resolve_alias;
+///
+/// let aliases = BTreeMap::from([("pizzeria".to_string(), "@acme/pizzeria".to_string())]);
+/// assert_eq!(resolve_alias("pizzeria/forno", &aliases), "@acme/pizzeria/forno");
+/// assert_eq!(resolve_alias("pizzeriaa", &aliases), "pizzeriaa");
+/// ```
+pub fn resolve_alias<'a>(source: &'a str, aliases: &BTreeMap<String, String>) -> Cow<'a, str> {
+    if let Some(specifier) = aliases.get(source) {
+        return Cow::Owned(specifier.clone());
+    }
+    match source.split_once('/').and_then(|(alias, rest)| Some((aliases.get(alias)?, rest))) {
+        Some((specifier, rest)) => Cow::Owned(format!("{}/{}", specifier, rest)),
+        None => Cow::Borrowed(source),
+    }
+}
+
+/// The npm package the module `source` comes from: `lodash` for
+/// `lodash/fp`, `@acme/pizzeria` for `@acme/pizzeria/forno`. `None` for
+/// paths, URLs and Node.js's own modules (`fs`, `node:fs`).
+pub fn package_name(source: &str) -> Option<&str> {
+    if source.starts_with('.') || source.starts_with('/') || source.contains(':') {
+        return None;
+    }
+    let end = match source.strip_prefix('@') {
+        Some(scoped) => scoped.find('/').and_then(|slash| scoped[slash + 1..].find('/').map(|end| slash + end + 2)),
+        None => source.find('/'),
+    };
+    let name = end.map_or(source, |end| &source[..end]);
+    (!NODE_BUILTINS.contains(&name)).then_some(name)
+}
+
+/// Whether `source` is one of Node.js's own modules: `fs`, `fs/promises`, `node:fs`.
+pub fn is_node_builtin(source: &str) -> bool {
+    source.starts_with("node:") || NODE_BUILTINS.contains(&source.split('/').next().unwrap_or(source))
+}
+
+/// Checks that `program` imports none of Node.js's own modules once
+/// `aliases` are resolved: a browser has no `fs` to give to a
+/// [`ModuleFormat::Script`](crate::ModuleFormat::Script).
+pub fn check_browser_imports(program: &Program, aliases: &BTreeMap<String, String>) -> Vec<Diagnostic> {
+    program.statements.iter().filter_map(|stmt| match stmt {
+        Statement::Import { source, span, .. } if is_node_builtin(&resolve_alias(source, aliases)) => Some(
+            Diagnostic::error("node-builtin", format!("'O modulo \"{}\" è 'e Node.js: dint'o browser nun ce sta", source), *span)
+                .with_suggestion("💡 Usa n'API d'o browser, o compila 'o file pe Node.js"),
+        ),
+        _ => None,
+    }).collect()
+}
+
+/// Checks that the npm packages `program` imports, once `aliases` are
+/// resolved, are installed. `installed` tells whether a package is.
+pub fn check_packages(program: &Program, aliases: &BTreeMap<String, String>, mut installed: impl FnMut(&str) -> bool) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    for stmt in &program.statements {
+        let Statement::Import { source, span, .. } = stmt else { continue };
+        let specifier = resolve_alias(source, aliases);
+        let Some(package) = package_name(&specifier) else { continue };
+        if !installed(package) {
+            errors.push(
+                Diagnostic::error("unresolved-package", format!("'O pacchetto '{}' nun è installato", package), *span)
+                    .with_suggestion(format!("💡 Installalo cu 'npm install {}'", package)),
+            );
+        }
+    }
+    errors
+}
+
+/// Checks the imports of local modules in `program` (see [`is_local`]).
+/// `exports_of` gives the exports of a module, or `None` if there is no
+/// such file.
+pub fn check_imports(program: &Program, mut exports_of: impl FnMut(&str) -> Option<Vec<Name>>) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    for stmt in &program.statements {
+        let Statement::Import { specifiers, source, span } = stmt else { continue };
+        if !is_local(source) {
+            continue;
+        }
+        let Some(exports) = exports_of(source) else {
+            errors.push(
+                Diagnostic::error("unresolved-module", format!("Nun trovo 'o modulo \"{}\"", source), *span)
+                    .with_suggestion("💡 Cuntrolla 'o percorso: è relativo 'o file ca fa 'chiamm'"),
+            );
+            continue;
+        };
+        for specifier in specifiers.iter().filter(|specifier| !exports.contains(&specifier.imported)) {
+            let mut error = Diagnostic::error("missing-export", format!("\"{}\" nun manna for '{}'", source, specifier.imported), *span);
+            error = match exports.iter().filter(|name| &***name != "default").map(|name| name.to_string()).collect::<Vec<_>>() {
+                names if names.is_empty() => error.with_note(format!("\"{}\" nun manna for niente", source)),
+                names => error.with_note(format!("manna for: {}", names.join(", "))),
+            };
+            errors.push(error.with_suggestion(format!("💡 Mietti 'mann for' nnanz'a '{}' dint'o modulo", specifier.imported)));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(source: &str, module: &str) -> Vec<String> {
+        let module = crate::parse_source(module).unwrap();
+        let program = crate::parse_source(source).unwrap();
+        check_imports(&program, |source| (source == "./m.fratm").then(|| exports(&module))).into_iter().map(|e| e.code.into_owned()).collect()
+    }
+
+    #[test]
+    fn test_packages() {
+        assert_eq!(package_name("lodash/fp"), Some("lodash"));
+        assert_eq!(package_name("@acme/pizzeria/forno"), Some("@acme/pizzeria"));
+        assert_eq!(package_name("@acme/pizzeria"), Some("@acme/pizzeria"));
+        assert_eq!([package_name("fs"), package_name("node:fs"), package_name("./x.fratm")], [None; 3]);
+
+        let aliases = BTreeMap::from([("forno".to_string(), "@acme/forno".to_string())]);
+        let program = crate::parse_source("chiamm { a } da \"express\"\nchiamm { b } da \"forno/legna\"\nchiamm { c } da \"path\"").unwrap();
+        let mut asked = Vec::new();
+        let errors = check_packages(&program, &aliases, |package| { asked.push(package.to_string()); package == "express" });
+        assert_eq!(asked, ["express", "@acme/forno"]);
+        assert_eq!((errors.len(), errors[0].span.line), (1, 2));
+    }
+
+    #[test]
+    fn test_browser_imports() {
+        assert!(is_node_builtin("fs/promises") && is_node_builtin("node:test") && !is_node_builtin("express"));
+        let aliases = BTreeMap::from([("disco".to_string(), "node:fs".to_string())]);
+        let program = crate::parse_source("chiamm { a } da \"./a.fratm\"\nchiamm { b } da \"path\"\nchiamm { c } da \"disco\"").unwrap();
+        let errors = check_browser_imports(&program, &aliases);
+        assert_eq!(errors.iter().map(|e| (e.code.as_ref(), e.span.line)).collect::<Vec<_>>(), [("node-builtin", 2), ("node-builtin", 3)]);
+    }
+
+    #[test]
+    fn test_exports() {
+        let program = crate::parse_source("mann for chist è a = 1\nmann for facc b() { }\nmann for na famiglie C { }\nmann for predefinit a\ntien d = 2").unwrap();
+        assert_eq!(exports(&program), ["a", "b", "C", "default"].map(Name::from));
+    }
+
+    #[test]
+    fn test_check_imports() {
+        let module = "mann for chist è pizza = 1\nchist è segreto = 2";
+        assert!(errors("chiamm { pizza } da \"./m.fratm\"", module).is_empty());
+        assert_eq!(errors("chiamm { pizza, segreto } da \"./m.fratm\"", module), ["missing-export"]);
+        assert_eq!(errors("chiamm { pizza } da \"./n.fratm\"", module), ["unresolved-module"]);
+        // Packages and JavaScript are someone else's business
+        assert!(errors("chiamm { express } da \"express\"\nchiamm { x } da \"./x.js\"", module).is_empty());
+    }
+}
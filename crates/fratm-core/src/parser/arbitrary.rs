@@ -0,0 +1,125 @@
+//! proptest generators of valid programs, for the property tests
+//!
+//! Every generated tree is something the parser could have produced: names
+//! are never keywords, assignments target names or members, numbers are
+//! non-negative (`-1` parses as a negation). Arrow functions have one
+//! parameter and there is no `chè è`, as that's all the parser reads for
+//! now. Spans are all default, so
+//! compare trees with [`Program::to_sexp`], which leaves them out.
+
+use proptest::prelude::*;
+
+use super::*;
+
+const NAMES: &[&str] = &["a", "b", "pizza", "x1", "_tmp", "Gennaro"];
+
+pub fn name() -> impl Strategy<Value = Name> {
+    proptest::sample::select(NAMES).prop_map(Name::from)
+}
+
+fn binary_op() -> impl Strategy<Value = BinaryOp> {
+    use BinaryOp::*;
+    proptest::sample::select(vec![
+        Add, Subtract, Multiply, Divide, Modulo, Power,
+        Equal, StrictEqual, NotEqual, StrictNotEqual,
+        LessThan, GreaterThan, LessEqual, GreaterEqual,
+        And, Or,
+    ])
+}
+
+fn leaf() -> impl Strategy<Value = Expression> {
+    let span = Span::default();
+    prop_oneof![
+        name().prop_map(move |name| Expression::Identifier { name, span }),
+        (0u32..1000).prop_map(move |n| Expression::Number { value: n as f64, span }),
+        (0u32..1000).prop_map(move |n| Expression::Number { value: n as f64 / 8.0, span }),
+        "[a-z àè'\"\\\\\n\t]{0,6}".prop_map(move |value| Expression::String { value, span }),
+        any::<bool>().prop_map(move |value| Expression::Boolean { value, span }),
+        Just(Expression::Null { span }),
+        Just(Expression::Undefined { span }),
+        Just(Expression::This { span }),
+    ]
+}
+
+/// Any expression, nested a few levels deep
+pub fn expression() -> impl Strategy<Value = Expression> {
+    let span = Span::default();
+    leaf().prop_recursive(4, 32, 3, move |inner| {
+        let boxed = inner.clone().prop_map(Box::new);
+        let target = prop_oneof![
+            name().prop_map(move |name| Expression::Identifier { name, span }),
+            (inner.clone(), name()).prop_map(move |(object, name)| Expression::Member {
+                object: Box::new(object),
+                property: Box::new(Expression::Identifier { name, span }),
+                computed: false,
+                span,
+            }),
+        ];
+        prop_oneof![
+            (boxed.clone(), binary_op(), boxed.clone())
+                .prop_map(move |(left, operator, right)| Expression::Binary { left, operator, right, span }),
+            (prop_oneof![Just(UnaryOp::Negate), Just(UnaryOp::Not)], boxed.clone())
+                .prop_map(move |(operator, operand)| Expression::Unary { operator, operand, span }),
+            (target, boxed.clone())
+                .prop_map(move |(target, value)| Expression::Assignment { target: Box::new(target), value, span }),
+            (boxed.clone(), prop::collection::vec(inner.clone(), 0..3))
+                .prop_map(move |(callee, arguments)| Expression::Call { callee, arguments, span }),
+            (boxed.clone(), name()).prop_map(move |(object, name)| Expression::Member {
+                object,
+                property: Box::new(Expression::Identifier { name, span }),
+                computed: false,
+                span,
+            }),
+            (boxed.clone(), boxed.clone())
+                .prop_map(move |(object, property)| Expression::Member { object, property, computed: true, span }),
+            (name(), prop::collection::vec(inner.clone(), 0..3)).prop_map(move |(name, arguments)| Expression::New {
+                callee: Box::new(Expression::Identifier { name, span }),
+                arguments,
+                span,
+            }),
+            (name(), boxed.clone())
+                .prop_map(move |(param, body)| Expression::ArrowFunction { params: vec![param], body: ArrowBody::Expression(body), span }),
+            (boxed.clone(), boxed.clone(), boxed.clone())
+                .prop_map(move |(condition, consequent, alternate)| Expression::Ternary { condition, consequent, alternate, span }),
+            prop::collection::vec(inner.clone(), 0..4).prop_map(move |elements| Expression::Array { elements, span }),
+            prop::collection::vec((name(), inner.clone()), 0..3)
+                .prop_map(move |properties| Expression::Object { properties, span }),
+            prop::collection::vec(inner, 0..3).prop_map(move |arguments| Expression::ConsoleLog { arguments, span }),
+        ]
+    })
+}
+
+/// Any statement; `return` only inside functions
+pub fn statement() -> impl Strategy<Value = Statement> {
+    let span = Span::default();
+    let simple = prop_oneof![
+        (name(), proptest::option::of(expression()), any::<bool>()).prop_map(move |(name, value, is_const)| {
+            // `chist è` needs a value
+            let value = if is_const { Some(value.unwrap_or(Expression::Null { span })) } else { value };
+            Statement::VariableDecl { name, annotation: None, value, is_const, span }
+        }),
+        expression().prop_map(move |expression| Statement::Expression { expression, span }),
+        (expression(), proptest::option::of(expression()))
+            .prop_map(move |(condition, message)| Statement::Assert { condition, message, span }),
+        expression().prop_map(move |value| Statement::Throw { value, span }),
+    ];
+    simple.prop_recursive(3, 16, 3, move |inner| {
+        let body = prop::collection::vec(inner, 0..3);
+        prop_oneof![
+            (expression(), body.clone(), proptest::option::of(body.clone()))
+                .prop_map(move |(condition, then_branch, else_branch)| Statement::If { condition, then_branch, else_branch, span }),
+            (expression(), body.clone()).prop_map(move |(condition, body)| Statement::While { condition, body, span }),
+            (name(), prop::collection::vec(name(), 0..3), body.clone(), proptest::option::of(expression()), any::<bool>())
+                .prop_map(move |(name, params, mut body, value, is_async)| {
+                    body.push(Statement::Return { value, span });
+                    Statement::FunctionDecl { name, params, signature: None, body, is_async, decorators: Vec::new(), span }
+                }),
+            (body.clone(), proptest::option::of(name()), body)
+                .prop_map(move |(try_body, catch_param, catch_body)| Statement::TryCatch { try_body, catch_param, catch_type: None, catch_body, span }),
+        ]
+    })
+}
+
+pub fn program() -> impl Strategy<Value = Program> {
+    prop::collection::vec(statement(), 0..6).prop_map(|statements| Program { statements })
+}
@@ -0,0 +1,611 @@
+//! Arena-allocated AST
+//!
+//! [`ArenaProgram`] stores the same tree as [`Program`], but every node
+//! lives in one of a few flat vectors and refers to its children by index
+//! ([`ExprId`], [`StmtId`]) instead of through a `Box`. A large file then
+//! costs a handful of allocations instead of one per node, and dropping it
+//! is a few `free`s.
+//!
+//! [`Parser::parse_arena`](super::Parser::parse_arena) moves each top-level
+//! statement into the arena as soon as it is parsed. Use
+//! [`ArenaProgram::to_program`] (or serialize it, which does the same) to
+//! get the boxed tree back.
+//!
+//! ```rust
+//! use fratm_core::lexer::Lexer;
+//! use fratm_core::parser::arena::StmtNode;
+//! use fratm_core::parser::Parser;
+//!
+//! let program = Parser::streaming(Lexer::new("tien x = 1 + 2")).parse_arena().unwrap();
+//! let StmtNode::VariableDecl { name, .. } = &program[program.statements()[0]] else { panic!() };
+//! assert_eq!(&**name, "x");
+//! assert_eq!(program.to_program().statements.len(), 1);
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Index;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+
+use super::ast::*;
+use crate::lexer::Span;
+
+/// Index of an expression in an [`ArenaProgram`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId(u32);
+
+/// Index of a statement in an [`ArenaProgram`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StmtId(u32);
+
+/// A run of consecutive `T`s stored in an [`ArenaProgram`]; index the program with it to get a slice.
+pub struct List<T> {
+    start: u32,
+    len: u32,
+    marker: PhantomData<T>,
+}
+
+// Derives would require `T: Copy`
+impl<T> Clone for List<T> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<T> Copy for List<T> {}
+
+impl<T> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "List({}..{})", self.start, self.start + self.len)
+    }
+}
+
+impl<T> List<T> {
+    pub fn len(&self) -> usize { self.len as usize }
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+    fn range(&self) -> std::ops::Range<usize> { self.start as usize..(self.start + self.len) as usize }
+}
+
+/// A [`Statement`] whose children are indices into the arena.
+#[derive(Debug, Clone)]
+pub enum StmtNode {
+    VariableDecl { name: Name, annotation: Option<TypeAnnotation>, value: Option<ExprId>, is_const: bool, span: Span },
+    FunctionDecl { name: Name, params: List<Name>, signature: Option<Box<Signature>>, body: List<StmtId>, is_async: bool, decorators: List<ExprId>, span: Span },
+    Return { value: Option<ExprId>, span: Span },
+    If { condition: ExprId, then_branch: List<StmtId>, else_branch: Option<List<StmtId>>, span: Span },
+    While { condition: ExprId, body: List<StmtId>, span: Span },
+    For { init: Option<StmtId>, condition: Option<ExprId>, update: Option<ExprId>, body: List<StmtId>, span: Span },
+    Break { span: Span },
+    Continue { span: Span },
+    Debugger { span: Span },
+    Assert { condition: ExprId, message: Option<ExprId>, span: Span },
+    TryCatch { try_body: List<StmtId>, catch_param: Option<Name>, catch_type: Option<ExprId>, catch_body: List<StmtId>, span: Span },
+    Throw { value: ExprId, span: Span },
+    ClassDecl { name: Name, decorators: List<ExprId>, superclass: Option<ExprId>, methods: List<StmtId>, span: Span },
+    Import { specifiers: List<ImportSpecifier>, source: String, span: Span },
+    Export { declaration: Option<StmtId>, default_value: Option<ExprId>, span: Span },
+    Expression { expression: ExprId, span: Span },
+    Block { statements: List<StmtId>, span: Span },
+    Conditional { condition: Directive, then_branch: List<StmtId>, else_branch: Option<List<StmtId>>, span: Span },
+    Error { span: Span },
+}
+
+/// An [`Expression`] whose children are indices into the arena.
+#[derive(Debug, Clone)]
+pub enum ExprNode {
+    Identifier { name: Name, span: Span },
+    Number { value: f64, span: Span },
+    String { value: String, span: Span },
+    Boolean { value: bool, span: Span },
+    Null { span: Span },
+    Undefined { span: Span },
+    This { span: Span },
+    SuperCall { arguments: List<ExprId>, span: Span },
+    Array { elements: List<ExprId>, span: Span },
+    Object { properties: List<(Name, ExprId)>, span: Span },
+    Binary { left: ExprId, operator: BinaryOp, right: ExprId, span: Span },
+    Unary { operator: UnaryOp, operand: ExprId, span: Span },
+    Assignment { target: ExprId, value: ExprId, span: Span },
+    Call { callee: ExprId, arguments: List<ExprId>, span: Span },
+    Member { object: ExprId, property: ExprId, computed: bool, span: Span },
+    New { callee: ExprId, arguments: List<ExprId>, span: Span },
+    ArrowFunction { params: List<Name>, body: ArrowBodyNode, span: Span },
+    Await { argument: ExprId, span: Span },
+    Ternary { condition: ExprId, consequent: ExprId, alternate: ExprId, span: Span },
+    ConsoleLog { arguments: List<ExprId>, span: Span },
+    Console { span: Span },
+    Promise { helper: PromiseHelper, span: Span },
+    ConsoleWarn { arguments: List<ExprId>, span: Span },
+    ConsoleError { arguments: List<ExprId>, span: Span },
+    TypeOf { operand: ExprId, span: Span },
+    Delete { operand: ExprId, span: Span },
+    JsxElement { name: Option<Name>, attributes: List<(Name, Option<ExprId>)>, children: List<JsxChildNode>, span: Span },
+    Template { quasis: Vec<String>, expressions: List<ExprId>, span: Span },
+    Match { subject: ExprId, arms: List<MatchArmNode>, span: Span },
+    Error { span: Span },
+}
+
+/// A [`MatchArm`] whose expressions are in the arena. Patterns hold no
+/// expressions and are kept as they are.
+#[derive(Debug, Clone)]
+pub struct MatchArmNode {
+    pub pattern: Pattern,
+    pub guard: Option<ExprId>,
+    pub body: ExprId,
+    pub span: Span,
+}
+
+/// A [`JsxChild`] whose expression is in the arena.
+#[derive(Debug, Clone)]
+pub enum JsxChildNode {
+    Text(String),
+    Expression(ExprId),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ArrowBodyNode {
+    Expression(ExprId),
+    Block(List<StmtId>),
+}
+
+/// A program stored in flat vectors, see the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct ArenaProgram {
+    statements: Vec<StmtId>,
+    stmts: Vec<StmtNode>,
+    exprs: Vec<ExprNode>,
+    stmt_lists: Vec<StmtId>,
+    expr_lists: Vec<ExprId>,
+    names: Vec<Name>,
+    properties: Vec<(Name, ExprId)>,
+    specifiers: Vec<ImportSpecifier>,
+    jsx_attributes: Vec<(Name, Option<ExprId>)>,
+    jsx_children: Vec<JsxChildNode>,
+    match_arms: Vec<MatchArmNode>,
+    /// Children of the lists being built; a list is copied out once complete
+    pending_stmts: Vec<StmtId>,
+    pending_exprs: Vec<ExprId>,
+}
+
+impl ArenaProgram {
+    /// The top-level statements, in order
+    pub fn statements(&self) -> &[StmtId] {
+        &self.statements
+    }
+
+    /// Number of statement and expression nodes
+    pub fn node_count(&self) -> usize {
+        self.stmts.len() + self.exprs.len()
+    }
+
+    /// Moves `statement` into the arena as the next top-level statement.
+    pub fn push(&mut self, statement: Statement) {
+        let id = self.alloc_stmt(statement);
+        self.statements.push(id);
+    }
+
+    /// Rebuilds the boxed tree, e.g. to serialize it or hand it to the code generator.
+    pub fn to_program(&self) -> Program {
+        Program { statements: self.statements.iter().map(|&id| self.statement(id)).collect() }
+    }
+
+    /// The boxed form of one statement
+    pub fn statement(&self, id: StmtId) -> Statement {
+        match self[id].clone() {
+            StmtNode::VariableDecl { name, annotation, value, is_const, span } => Statement::VariableDecl { name, annotation, value: value.map(|e| self.expression(e)), is_const, span },
+            StmtNode::FunctionDecl { name, params, signature, body, is_async, decorators, span } => {
+                Statement::FunctionDecl { name, params: self[params].to_vec(), signature, body: self.block(body), is_async, decorators: self.expressions(decorators), span }
+            }
+            StmtNode::Return { value, span } => Statement::Return { value: value.map(|e| self.expression(e)), span },
+            StmtNode::If { condition, then_branch, else_branch, span } => Statement::If {
+                condition: self.expression(condition),
+                then_branch: self.block(then_branch),
+                else_branch: else_branch.map(|b| self.block(b)),
+                span,
+            },
+            StmtNode::While { condition, body, span } => Statement::While { condition: self.expression(condition), body: self.block(body), span },
+            StmtNode::For { init, condition, update, body, span } => Statement::For {
+                init: init.map(|s| Box::new(self.statement(s))),
+                condition: condition.map(|e| self.expression(e)),
+                update: update.map(|e| self.expression(e)),
+                body: self.block(body),
+                span,
+            },
+            StmtNode::Break { span } => Statement::Break { span },
+            StmtNode::Continue { span } => Statement::Continue { span },
+            StmtNode::Debugger { span } => Statement::Debugger { span },
+            StmtNode::Assert { condition, message, span } => Statement::Assert {
+                condition: self.expression(condition),
+                message: message.map(|e| self.expression(e)),
+                span,
+            },
+            StmtNode::TryCatch { try_body, catch_param, catch_type, catch_body, span } => Statement::TryCatch {
+                try_body: self.block(try_body),
+                catch_param,
+                catch_type: catch_type.map(|e| self.expression(e)),
+                catch_body: self.block(catch_body),
+                span,
+            },
+            StmtNode::Throw { value, span } => Statement::Throw { value: self.expression(value), span },
+            StmtNode::ClassDecl { name, decorators, superclass, methods, span } => Statement::ClassDecl {
+                name,
+                decorators: self.expressions(decorators),
+                superclass: superclass.map(|e| self.expression(e)),
+                methods: self.block(methods),
+                span,
+            },
+            StmtNode::Import { specifiers, source, span } => Statement::Import { specifiers: self[specifiers].to_vec(), source, span },
+            StmtNode::Export { declaration, default_value, span } => Statement::Export {
+                declaration: declaration.map(|s| Box::new(self.statement(s))),
+                default_value: default_value.map(|e| self.expression(e)),
+                span,
+            },
+            StmtNode::Expression { expression, span } => Statement::Expression { expression: self.expression(expression), span },
+            StmtNode::Block { statements, span } => Statement::Block { statements: self.block(statements), span },
+            StmtNode::Conditional { condition, then_branch, else_branch, span } => Statement::Conditional {
+                condition,
+                then_branch: self.block(then_branch),
+                else_branch: else_branch.map(|b| self.block(b)),
+                span,
+            },
+            StmtNode::Error { span } => Statement::Error { span },
+        }
+    }
+
+    /// The boxed form of one expression
+    pub fn expression(&self, id: ExprId) -> Expression {
+        let boxed = |e: ExprId| Box::new(self.expression(e));
+        match self[id].clone() {
+            ExprNode::Identifier { name, span } => Expression::Identifier { name, span },
+            ExprNode::Number { value, span } => Expression::Number { value, span },
+            ExprNode::String { value, span } => Expression::String { value, span },
+            ExprNode::Boolean { value, span } => Expression::Boolean { value, span },
+            ExprNode::Null { span } => Expression::Null { span },
+            ExprNode::Undefined { span } => Expression::Undefined { span },
+            ExprNode::This { span } => Expression::This { span },
+            ExprNode::SuperCall { arguments, span } => Expression::SuperCall { arguments: self.expressions(arguments), span },
+            ExprNode::Array { elements, span } => Expression::Array { elements: self.expressions(elements), span },
+            ExprNode::Object { properties, span } => Expression::Object {
+                properties: self[properties].iter().map(|(key, value)| (key.clone(), self.expression(*value))).collect(),
+                span,
+            },
+            ExprNode::Binary { left, operator, right, span } => Expression::Binary { left: boxed(left), operator, right: boxed(right), span },
+            ExprNode::Unary { operator, operand, span } => Expression::Unary { operator, operand: boxed(operand), span },
+            ExprNode::Assignment { target, value, span } => Expression::Assignment { target: boxed(target), value: boxed(value), span },
+            ExprNode::Call { callee, arguments, span } => Expression::Call { callee: boxed(callee), arguments: self.expressions(arguments), span },
+            ExprNode::Member { object, property, computed, span } => Expression::Member { object: boxed(object), property: boxed(property), computed, span },
+            ExprNode::New { callee, arguments, span } => Expression::New { callee: boxed(callee), arguments: self.expressions(arguments), span },
+            ExprNode::ArrowFunction { params, body, span } => Expression::ArrowFunction {
+                params: self[params].to_vec(),
+                body: match body {
+                    ArrowBodyNode::Expression(e) => ArrowBody::Expression(boxed(e)),
+                    ArrowBodyNode::Block(b) => ArrowBody::Block(self.block(b)),
+                },
+                span,
+            },
+            ExprNode::Await { argument, span } => Expression::Await { argument: boxed(argument), span },
+            ExprNode::Ternary { condition, consequent, alternate, span } => Expression::Ternary {
+                condition: boxed(condition),
+                consequent: boxed(consequent),
+                alternate: boxed(alternate),
+                span,
+            },
+            ExprNode::ConsoleLog { arguments, span } => Expression::ConsoleLog { arguments: self.expressions(arguments), span },
+            ExprNode::Console { span } => Expression::Console { span },
+            ExprNode::Promise { helper, span } => Expression::Promise { helper, span },
+            ExprNode::ConsoleWarn { arguments, span } => Expression::ConsoleWarn { arguments: self.expressions(arguments), span },
+            ExprNode::ConsoleError { arguments, span } => Expression::ConsoleError { arguments: self.expressions(arguments), span },
+            ExprNode::TypeOf { operand, span } => Expression::TypeOf { operand: boxed(operand), span },
+            ExprNode::Delete { operand, span } => Expression::Delete { operand: boxed(operand), span },
+            ExprNode::JsxElement { name, attributes, children, span } => Expression::JsxElement {
+                name,
+                attributes: self[attributes].iter().map(|(key, value)| (key.clone(), value.map(|value| self.expression(value)))).collect(),
+                children: self[children].iter().map(|child| match child {
+                    JsxChildNode::Text(text) => JsxChild::Text(text.clone()),
+                    JsxChildNode::Expression(e) => JsxChild::Expression(self.expression(*e)),
+                }).collect(),
+                span,
+            },
+            ExprNode::Template { quasis, expressions, span } => Expression::Template { quasis, expressions: self.expressions(expressions), span },
+            ExprNode::Match { subject, arms, span } => Expression::Match {
+                subject: boxed(subject),
+                arms: self[arms].iter().map(|arm| MatchArm {
+                    pattern: arm.pattern.clone(),
+                    guard: arm.guard.map(|guard| self.expression(guard)),
+                    body: self.expression(arm.body),
+                    span: arm.span,
+                }).collect(),
+                span,
+            },
+            ExprNode::Error { span } => Expression::Error { span },
+        }
+    }
+
+    fn block(&self, list: List<StmtId>) -> Vec<Statement> {
+        self[list].iter().map(|&id| self.statement(id)).collect()
+    }
+
+    fn expressions(&self, list: List<ExprId>) -> Vec<Expression> {
+        self[list].iter().map(|&id| self.expression(id)).collect()
+    }
+
+    fn alloc_stmt(&mut self, statement: Statement) -> StmtId {
+        let node = match statement {
+            Statement::VariableDecl { name, annotation, value, is_const, span } => StmtNode::VariableDecl { name, annotation, value: value.map(|e| self.alloc_expr(e)), is_const, span },
+            Statement::FunctionDecl { name, params, signature, body, is_async, decorators, span } => {
+                let params = append(&mut self.names, params);
+                let decorators = self.alloc_exprs(decorators);
+                StmtNode::FunctionDecl { name, params, signature, body: self.alloc_block(body), is_async, decorators, span }
+            }
+            Statement::Return { value, span } => StmtNode::Return { value: value.map(|e| self.alloc_expr(e)), span },
+            Statement::If { condition, then_branch, else_branch, span } => StmtNode::If {
+                condition: self.alloc_expr(condition),
+                then_branch: self.alloc_block(then_branch),
+                else_branch: else_branch.map(|b| self.alloc_block(b)),
+                span,
+            },
+            Statement::While { condition, body, span } => StmtNode::While { condition: self.alloc_expr(condition), body: self.alloc_block(body), span },
+            Statement::For { init, condition, update, body, span } => StmtNode::For {
+                init: init.map(|s| self.alloc_stmt(*s)),
+                condition: condition.map(|e| self.alloc_expr(e)),
+                update: update.map(|e| self.alloc_expr(e)),
+                body: self.alloc_block(body),
+                span,
+            },
+            Statement::Break { span } => StmtNode::Break { span },
+            Statement::Continue { span } => StmtNode::Continue { span },
+            Statement::Debugger { span } => StmtNode::Debugger { span },
+            Statement::Assert { condition, message, span } => StmtNode::Assert {
+                condition: self.alloc_expr(condition),
+                message: message.map(|e| self.alloc_expr(e)),
+                span,
+            },
+            Statement::TryCatch { try_body, catch_param, catch_type, catch_body, span } => StmtNode::TryCatch {
+                try_body: self.alloc_block(try_body),
+                catch_param,
+                catch_type: catch_type.map(|e| self.alloc_expr(e)),
+                catch_body: self.alloc_block(catch_body),
+                span,
+            },
+            Statement::Throw { value, span } => StmtNode::Throw { value: self.alloc_expr(value), span },
+            Statement::ClassDecl { name, decorators, superclass, methods, span } => StmtNode::ClassDecl {
+                name,
+                decorators: self.alloc_exprs(decorators),
+                superclass: superclass.map(|e| self.alloc_expr(e)),
+                methods: self.alloc_block(methods),
+                span,
+            },
+            Statement::Import { specifiers, source, span } => StmtNode::Import { specifiers: append(&mut self.specifiers, specifiers), source, span },
+            Statement::Export { declaration, default_value, span } => StmtNode::Export {
+                declaration: declaration.map(|s| self.alloc_stmt(*s)),
+                default_value: default_value.map(|e| self.alloc_expr(e)),
+                span,
+            },
+            Statement::Expression { expression, span } => StmtNode::Expression { expression: self.alloc_expr(expression), span },
+            Statement::Block { statements, span } => StmtNode::Block { statements: self.alloc_block(statements), span },
+            Statement::Conditional { condition, then_branch, else_branch, span } => StmtNode::Conditional {
+                condition,
+                then_branch: self.alloc_block(then_branch),
+                else_branch: else_branch.map(|b| self.alloc_block(b)),
+                span,
+            },
+            Statement::Error { span } => StmtNode::Error { span },
+        };
+        self.stmts.push(node);
+        StmtId(index(self.stmts.len() - 1))
+    }
+
+    fn alloc_expr(&mut self, expression: Expression) -> ExprId {
+        let node = match expression {
+            Expression::Identifier { name, span } => ExprNode::Identifier { name, span },
+            Expression::Number { value, span } => ExprNode::Number { value, span },
+            Expression::String { value, span } => ExprNode::String { value, span },
+            Expression::Boolean { value, span } => ExprNode::Boolean { value, span },
+            Expression::Null { span } => ExprNode::Null { span },
+            Expression::Undefined { span } => ExprNode::Undefined { span },
+            Expression::This { span } => ExprNode::This { span },
+            Expression::SuperCall { arguments, span } => ExprNode::SuperCall { arguments: self.alloc_exprs(arguments), span },
+            Expression::Array { elements, span } => ExprNode::Array { elements: self.alloc_exprs(elements), span },
+            Expression::Object { properties, span } => {
+                // Values first: they may hold objects of their own
+                let properties: Vec<(Name, ExprId)> = properties.into_iter().map(|(key, value)| (key, self.alloc_expr(value))).collect();
+                ExprNode::Object { properties: append(&mut self.properties, properties), span }
+            }
+            Expression::Binary { left, operator, right, span } => ExprNode::Binary { left: self.alloc_expr(*left), operator, right: self.alloc_expr(*right), span },
+            Expression::Unary { operator, operand, span } => ExprNode::Unary { operator, operand: self.alloc_expr(*operand), span },
+            Expression::Assignment { target, value, span } => ExprNode::Assignment { target: self.alloc_expr(*target), value: self.alloc_expr(*value), span },
+            Expression::Call { callee, arguments, span } => ExprNode::Call { callee: self.alloc_expr(*callee), arguments: self.alloc_exprs(arguments), span },
+            Expression::Member { object, property, computed, span } => ExprNode::Member {
+                object: self.alloc_expr(*object),
+                property: self.alloc_expr(*property),
+                computed,
+                span,
+            },
+            Expression::New { callee, arguments, span } => ExprNode::New { callee: self.alloc_expr(*callee), arguments: self.alloc_exprs(arguments), span },
+            Expression::ArrowFunction { params, body, span } => {
+                let params = append(&mut self.names, params);
+                let body = match body {
+                    ArrowBody::Expression(e) => ArrowBodyNode::Expression(self.alloc_expr(*e)),
+                    ArrowBody::Block(b) => ArrowBodyNode::Block(self.alloc_block(b)),
+                };
+                ExprNode::ArrowFunction { params, body, span }
+            }
+            Expression::Await { argument, span } => ExprNode::Await { argument: self.alloc_expr(*argument), span },
+            Expression::Ternary { condition, consequent, alternate, span } => ExprNode::Ternary {
+                condition: self.alloc_expr(*condition),
+                consequent: self.alloc_expr(*consequent),
+                alternate: self.alloc_expr(*alternate),
+                span,
+            },
+            Expression::ConsoleLog { arguments, span } => ExprNode::ConsoleLog { arguments: self.alloc_exprs(arguments), span },
+            Expression::Console { span } => ExprNode::Console { span },
+            Expression::Promise { helper, span } => ExprNode::Promise { helper, span },
+            Expression::ConsoleWarn { arguments, span } => ExprNode::ConsoleWarn { arguments: self.alloc_exprs(arguments), span },
+            Expression::ConsoleError { arguments, span } => ExprNode::ConsoleError { arguments: self.alloc_exprs(arguments), span },
+            Expression::TypeOf { operand, span } => ExprNode::TypeOf { operand: self.alloc_expr(*operand), span },
+            Expression::Delete { operand, span } => ExprNode::Delete { operand: self.alloc_expr(*operand), span },
+            Expression::JsxElement { name, attributes, children, span } => {
+                let attributes: Vec<(Name, Option<ExprId>)> = attributes.into_iter().map(|(key, value)| (key, value.map(|e| self.alloc_expr(e)))).collect();
+                let children: Vec<JsxChildNode> = children.into_iter().map(|child| match child {
+                    JsxChild::Text(text) => JsxChildNode::Text(text),
+                    JsxChild::Expression(e) => JsxChildNode::Expression(self.alloc_expr(e)),
+                }).collect();
+                ExprNode::JsxElement { name, attributes: append(&mut self.jsx_attributes, attributes), children: append(&mut self.jsx_children, children), span }
+            }
+            Expression::Template { quasis, expressions, span } => ExprNode::Template { quasis, expressions: self.alloc_exprs(expressions), span },
+            Expression::Match { subject, arms, span } => {
+                let subject = self.alloc_expr(*subject);
+                let arms: Vec<MatchArmNode> = arms.into_iter().map(|arm| MatchArmNode {
+                    pattern: arm.pattern,
+                    guard: arm.guard.map(|guard| self.alloc_expr(guard)),
+                    body: self.alloc_expr(arm.body),
+                    span: arm.span,
+                }).collect();
+                ExprNode::Match { subject, arms: append(&mut self.match_arms, arms), span }
+            }
+            Expression::Error { span } => ExprNode::Error { span },
+        };
+        self.exprs.push(node);
+        ExprId(index(self.exprs.len() - 1))
+    }
+
+    /// Children are allocated first (their own lists land in between), then
+    /// the ids are copied out of `pending_stmts` so the list is contiguous.
+    fn alloc_block(&mut self, statements: Vec<Statement>) -> List<StmtId> {
+        let pending = self.pending_stmts.len();
+        for statement in statements {
+            let id = self.alloc_stmt(statement);
+            self.pending_stmts.push(id);
+        }
+        let ids = self.pending_stmts.drain(pending..);
+        let list = List { start: index(self.stmt_lists.len()), len: index(ids.len()), marker: PhantomData };
+        self.stmt_lists.extend(ids);
+        list
+    }
+
+    fn alloc_exprs(&mut self, expressions: Vec<Expression>) -> List<ExprId> {
+        let pending = self.pending_exprs.len();
+        for expression in expressions {
+            let id = self.alloc_expr(expression);
+            self.pending_exprs.push(id);
+        }
+        let ids = self.pending_exprs.drain(pending..);
+        let list = List { start: index(self.expr_lists.len()), len: index(ids.len()), marker: PhantomData };
+        self.expr_lists.extend(ids);
+        list
+    }
+}
+
+fn append<T>(items: &mut Vec<T>, new: Vec<T>) -> List<T> {
+    let list = List { start: index(items.len()), len: index(new.len()), marker: PhantomData };
+    items.extend(new);
+    list
+}
+
+fn index(n: usize) -> u32 {
+    u32::try_from(n).expect("more than 4 billion AST nodes")
+}
+
+impl From<Program> for ArenaProgram {
+    fn from(program: Program) -> Self {
+        let mut arena = ArenaProgram::default();
+        for statement in program.statements {
+            arena.push(statement);
+        }
+        arena
+    }
+}
+
+impl From<&ArenaProgram> for Program {
+    fn from(arena: &ArenaProgram) -> Self {
+        arena.to_program()
+    }
+}
+
+/// Serializes as the equivalent [`Program`]
+#[cfg(feature = "serde")]
+impl Serialize for ArenaProgram {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_program().serialize(serializer)
+    }
+}
+
+impl Index<StmtId> for ArenaProgram {
+    type Output = StmtNode;
+    fn index(&self, id: StmtId) -> &StmtNode { &self.stmts[id.0 as usize] }
+}
+
+impl Index<ExprId> for ArenaProgram {
+    type Output = ExprNode;
+    fn index(&self, id: ExprId) -> &ExprNode { &self.exprs[id.0 as usize] }
+}
+
+impl Index<List<StmtId>> for ArenaProgram {
+    type Output = [StmtId];
+    fn index(&self, list: List<StmtId>) -> &[StmtId] { &self.stmt_lists[list.range()] }
+}
+
+impl Index<List<ExprId>> for ArenaProgram {
+    type Output = [ExprId];
+    fn index(&self, list: List<ExprId>) -> &[ExprId] { &self.expr_lists[list.range()] }
+}
+
+impl Index<List<Name>> for ArenaProgram {
+    type Output = [Name];
+    fn index(&self, list: List<Name>) -> &[Name] { &self.names[list.range()] }
+}
+
+impl Index<List<(Name, ExprId)>> for ArenaProgram {
+    type Output = [(Name, ExprId)];
+    fn index(&self, list: List<(Name, ExprId)>) -> &[(Name, ExprId)] { &self.properties[list.range()] }
+}
+
+impl Index<List<ImportSpecifier>> for ArenaProgram {
+    type Output = [ImportSpecifier];
+    fn index(&self, list: List<ImportSpecifier>) -> &[ImportSpecifier] { &self.specifiers[list.range()] }
+}
+
+impl Index<List<(Name, Option<ExprId>)>> for ArenaProgram {
+    type Output = [(Name, Option<ExprId>)];
+    fn index(&self, list: List<(Name, Option<ExprId>)>) -> &[(Name, Option<ExprId>)] { &self.jsx_attributes[list.range()] }
+}
+
+impl Index<List<JsxChildNode>> for ArenaProgram {
+    type Output = [JsxChildNode];
+    fn index(&self, list: List<JsxChildNode>) -> &[JsxChildNode] { &self.jsx_children[list.range()] }
+}
+
+impl Index<List<MatchArmNode>> for ArenaProgram {
+    type Output = [MatchArmNode];
+    fn index(&self, list: List<MatchArmNode>) -> &[MatchArmNode] { &self.match_arms[list.range()] }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn json(value: &impl Serialize) -> String {
+        serde_json::to_string(value).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = "chiamm { a } da \"m\"\nfacc f(x, y) {\n  si (x) { piglie [1, { k: (y) => y * 2 }] } sinno { f(x - 1, nu bell C()) }\n}\nmann for tien z = x ? 1 : 2";
+        let program = Parser::streaming(Lexer::new(source)).parse().unwrap();
+        let arena = Parser::streaming(Lexer::new(source)).parse_arena().unwrap();
+        assert_eq!(json(&arena), json(&program));
+        assert_eq!(json(&ArenaProgram::from(program.clone())), json(&program));
+    }
+
+    #[test]
+    fn test_lists_are_contiguous() {
+        let arena = Parser::streaming(Lexer::new("f(g(1, 2), [3, 4], 5)")).parse_arena().unwrap();
+        let StmtNode::Expression { expression, .. } = arena[arena.statements()[0]] else { panic!() };
+        let ExprNode::Call { arguments, .. } = arena[expression] else { panic!() };
+        assert_eq!(arguments.len(), 3);
+        assert!(matches!(arena[arena[arguments][2]], ExprNode::Number { value, .. } if value == 5.0));
+        assert_eq!(arena.node_count(), 11);
+    }
+}
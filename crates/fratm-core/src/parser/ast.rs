@@ -1,26 +1,49 @@
 //! Abstract Syntax Tree definitions
 
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
 use crate::lexer::Span;
+#[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// An identifier in the AST.
+///
+/// The parser interns names, so every occurrence of the same name in a
+/// program shares one allocation. Serialized as a plain string.
+pub type Name = Arc<str>;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Program {
     pub statements: Vec<Statement>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Statement {
     VariableDecl {
-        name: String,
+        name: Name,
+        /// "tien x: parola"
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        annotation: Option<TypeAnnotation>,
         value: Option<Expression>,
         is_const: bool,
         span: Span,
     },
     FunctionDecl {
-        name: String,
-        params: Vec<String>,
+        name: Name,
+        params: Vec<Name>,
+        /// The annotations, if any parameter or the result has one
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        signature: Option<Box<Signature>>,
         body: Vec<Statement>,
         is_async: bool,
+        /// `@decoratore` expressions before a method, outermost first.
+        /// Always empty outside a class.
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+        decorators: Vec<Expression>,
         span: Span,
     },
     Return {
@@ -49,9 +72,18 @@ pub enum Statement {
     Continue { span: Span },
     /// debugger statement - "fermete"
     Debugger { span: Span },
+    /// Assertion - "giura che (condition) sinnò message"
+    Assert {
+        condition: Expression,
+        message: Option<Expression>,
+        span: Span,
+    },
     TryCatch {
         try_body: Vec<Statement>,
-        catch_param: Option<String>,
+        catch_param: Option<Name>,
+        /// `ErroreMio` in `e si schiatta (err è uno ErroreMio)`: other errors are thrown again
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        catch_type: Option<Expression>,
         catch_body: Vec<Statement>,
         span: Span,
     },
@@ -60,7 +92,13 @@ pub enum Statement {
         span: Span,
     },
     ClassDecl {
-        name: String,
+        name: Name,
+        /// `@decoratore` expressions before the class, outermost first
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+        decorators: Vec<Expression>,
+        /// The class after `figlio 'e`
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        superclass: Option<Expression>,
         methods: Vec<Statement>,
         span: Span,
     },
@@ -82,25 +120,233 @@ pub enum Statement {
         statements: Vec<Statement>,
         span: Span,
     },
+    /// Conditional compilation - "#si DEBUG { ... } sinnò { ... }": only
+    /// the branch the defines choose is compiled, in place of the directive
+    Conditional {
+        condition: Directive,
+        then_branch: Vec<Statement>,
+        else_branch: Option<Vec<Statement>>,
+        span: Span,
+    },
+    /// A statement that failed to parse (only in recovered programs)
+    Error { span: Span },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::VariableDecl { span, .. } => *span,
+            Statement::FunctionDecl { span, .. } => *span,
+            Statement::Return { span, .. } => *span,
+            Statement::If { span, .. } => *span,
+            Statement::While { span, .. } => *span,
+            Statement::For { span, .. } => *span,
+            Statement::Break { span } => *span,
+            Statement::Continue { span } => *span,
+            Statement::Debugger { span } => *span,
+            Statement::Assert { span, .. } => *span,
+            Statement::TryCatch { span, .. } => *span,
+            Statement::Throw { span, .. } => *span,
+            Statement::ClassDecl { span, .. } => *span,
+            Statement::Import { span, .. } => *span,
+            Statement::Export { span, .. } => *span,
+            Statement::Expression { span, .. } => *span,
+            Statement::Block { span, .. } => *span,
+            Statement::Conditional { span, .. } => *span,
+            Statement::Error { span } => *span,
+        }
+    }
+
+    /// Calls `f` with the span of every node in the statement, the
+    /// statement's last: to move it in the source, or to count its nodes
+    pub fn spans_mut(&mut self, f: &mut impl FnMut(&mut Span)) {
+        match self {
+            Statement::VariableDecl { annotation, value, span, .. } => {
+                if let Some(annotation) = annotation { annotation.spans_mut(f); }
+                if let Some(value) = value { value.spans_mut(f); }
+                f(span);
+            }
+            Statement::FunctionDecl { signature, body, decorators, span, .. } => {
+                if let Some(signature) = signature {
+                    signature.params.iter_mut().flatten().chain(&mut signature.result).for_each(|annotation| annotation.spans_mut(f));
+                }
+                decorators.iter_mut().for_each(|decorator| decorator.spans_mut(f));
+                body.iter_mut().for_each(|statement| statement.spans_mut(f));
+                f(span);
+            }
+            Statement::Return { value, span } => {
+                if let Some(value) = value { value.spans_mut(f); }
+                f(span);
+            }
+            Statement::If { condition, then_branch, else_branch, span } => {
+                condition.spans_mut(f);
+                then_branch.iter_mut().chain(else_branch.iter_mut().flatten()).for_each(|statement| statement.spans_mut(f));
+                f(span);
+            }
+            Statement::Conditional { then_branch, else_branch, span, .. } => {
+                then_branch.iter_mut().chain(else_branch.iter_mut().flatten()).for_each(|statement| statement.spans_mut(f));
+                f(span);
+            }
+            Statement::While { condition, body, span } => {
+                condition.spans_mut(f);
+                body.iter_mut().for_each(|statement| statement.spans_mut(f));
+                f(span);
+            }
+            Statement::For { init, condition, update, body, span } => {
+                if let Some(init) = init { init.spans_mut(f); }
+                condition.iter_mut().chain(update).for_each(|expression| expression.spans_mut(f));
+                body.iter_mut().for_each(|statement| statement.spans_mut(f));
+                f(span);
+            }
+            Statement::Break { span } | Statement::Continue { span } | Statement::Debugger { span } | Statement::Import { span, .. } | Statement::Error { span } => f(span),
+            Statement::Assert { condition, message, span } => {
+                condition.spans_mut(f);
+                if let Some(message) = message { message.spans_mut(f); }
+                f(span);
+            }
+            Statement::TryCatch { try_body, catch_type, catch_body, span, .. } => {
+                if let Some(catch_type) = catch_type { catch_type.spans_mut(f); }
+                try_body.iter_mut().chain(catch_body).for_each(|statement| statement.spans_mut(f));
+                f(span);
+            }
+            Statement::Throw { value, span } | Statement::Expression { expression: value, span } => {
+                value.spans_mut(f);
+                f(span);
+            }
+            Statement::ClassDecl { decorators, superclass, methods, span, .. } => {
+                decorators.iter_mut().chain(superclass).for_each(|expression| expression.spans_mut(f));
+                methods.iter_mut().for_each(|statement| statement.spans_mut(f));
+                f(span);
+            }
+            Statement::Export { declaration, default_value, span } => {
+                if let Some(declaration) = declaration { declaration.spans_mut(f); }
+                if let Some(value) = default_value { value.spans_mut(f); }
+                f(span);
+            }
+            Statement::Block { statements, span } => {
+                statements.iter_mut().for_each(|statement| statement.spans_mut(f));
+                f(span);
+            }
+        }
+    }
+}
+
+/// A type annotation - "nummero", "parola[]", "{ nome: parola }" or
+/// "(nummero) => parola". The compiler records them for tools and leaves
+/// them out of the JavaScript.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TypeAnnotation {
+    /// `nummero`, `parola`, `buleano`, `nisciun`, `boh` or any other name
+    Named { name: Name, span: Span },
+    Array { element: Box<TypeAnnotation>, span: Span },
+    Object { fields: Vec<(Name, TypeAnnotation)>, span: Span },
+    Function { params: Vec<TypeAnnotation>, result: Box<TypeAnnotation>, span: Span },
+}
+
+impl TypeAnnotation {
+    pub fn span(&self) -> Span {
+        match self {
+            TypeAnnotation::Named { span, .. }
+            | TypeAnnotation::Array { span, .. }
+            | TypeAnnotation::Object { span, .. }
+            | TypeAnnotation::Function { span, .. } => *span,
+        }
+    }
+
+    /// Calls `f` with the span of every node in it, its own last
+    pub fn spans_mut(&mut self, f: &mut impl FnMut(&mut Span)) {
+        match self {
+            TypeAnnotation::Named { span, .. } => f(span),
+            TypeAnnotation::Array { element, span } => {
+                element.spans_mut(f);
+                f(span);
+            }
+            TypeAnnotation::Object { fields, span } => {
+                fields.iter_mut().for_each(|(_, field)| field.spans_mut(f));
+                f(span);
+            }
+            TypeAnnotation::Function { params, result, span } => {
+                params.iter_mut().chain([&mut **result]).for_each(|param| param.spans_mut(f));
+                f(span);
+            }
+        }
+    }
+}
+
+/// Written as in the source: `{ nome: parola, età: nummero }[]`
+impl fmt::Display for TypeAnnotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeAnnotation::Named { name, .. } => f.write_str(name),
+            TypeAnnotation::Array { element, .. } if matches!(**element, TypeAnnotation::Function { .. }) => write!(f, "({})[]", element),
+            TypeAnnotation::Array { element, .. } => write!(f, "{}[]", element),
+            TypeAnnotation::Object { fields, .. } if fields.is_empty() => f.write_str("{}"),
+            TypeAnnotation::Object { fields, .. } => {
+                let fields: Vec<String> = fields.iter().map(|(name, field)| format!("{}: {}", name, field)).collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+            TypeAnnotation::Function { params, result, .. } => {
+                let params: Vec<String> = params.iter().map(ToString::to_string).collect();
+                write!(f, "({}) => {}", params.join(", "), result)
+            }
+        }
+    }
+}
+
+/// The annotations of a function: one for each parameter, and its result
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Signature {
+    pub params: Vec<Option<TypeAnnotation>>,
+    pub result: Option<TypeAnnotation>,
+}
+
+/// The condition of `#si`: `DEBUG`, `manco DEBUG` or `MODE == "prova"`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Directive {
+    pub name: Name,
+    /// The value `name` must have, instead of just being on
+    pub value: Option<String>,
+    /// `manco`, or `!=` with a value
+    pub negated: bool,
+}
+
+impl Directive {
+    /// Whether the condition holds with `defines`. A name is on when it is
+    /// defined to anything but `0`, `false` or `sfòls`.
+    pub fn holds(&self, defines: &BTreeMap<String, String>) -> bool {
+        let defined = defines.get(&*self.name);
+        let holds = match &self.value {
+            Some(value) => defined == Some(value),
+            None => defined.is_some_and(|defined| !matches!(defined.as_str(), "0" | "false" | "sfòls")),
+        };
+        holds != self.negated
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ImportSpecifier {
-    pub imported: String,
-    pub local: String,
+    pub imported: Name,
+    pub local: Name,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Expression {
-    Identifier { name: String, span: Span },
+    Identifier { name: Name, span: Span },
     Number { value: f64, span: Span },
     String { value: String, span: Span },
     Boolean { value: bool, span: Span },
     Null { span: Span },
     Undefined { span: Span },
     This { span: Span },
+    /// The parent's constructor called from a derived one - "'o pate(...)", `super(...)`
+    SuperCall { arguments: Vec<Expression>, span: Span },
     Array { elements: Vec<Expression>, span: Span },
-    Object { properties: Vec<(String, Expression)>, span: Span },
+    Object { properties: Vec<(Name, Expression)>, span: Span },
     Binary {
         left: Box<Expression>,
         operator: BinaryOp,
@@ -134,7 +380,7 @@ pub enum Expression {
         span: Span,
     },
     ArrowFunction {
-        params: Vec<String>,
+        params: Vec<Name>,
         body: ArrowBody,
         span: Span,
     },
@@ -152,6 +398,8 @@ pub enum Expression {
         arguments: Vec<Expression>,
         span: Span,
     },
+    /// The `console` object - "stamm" before a `.`, as in `stamm.tabella(...)`
+    Console { span: Span },
     /// console.warn() - "avvis"
     ConsoleWarn {
         arguments: Vec<Expression>,
@@ -162,6 +410,9 @@ pub enum Expression {
         arguments: Vec<Expression>,
         span: Span,
     },
+    /// A `Promise` combinator - "tutt' quant" or "'o primm che arriva",
+    /// called like the function it stands for: `tutt' quant([a, b])`
+    Promise { helper: PromiseHelper, span: Span },
     /// typeof operand - "chè è"
     TypeOf {
         operand: Box<Expression>,
@@ -172,6 +423,31 @@ pub enum Expression {
         operand: Box<Expression>,
         span: Span,
     },
+    /// JSX element - `<Saluto nome="Ciro">Uè!</Saluto>`, only lexed with
+    /// `CompileOptions::jsx`. A fragment, `<>...</>`, has no name.
+    JsxElement {
+        name: Option<Name>,
+        /// `nome="Ciro"` or `nome={x}`; just `nome` has no value
+        attributes: Vec<(Name, Option<Expression>)>,
+        children: Vec<JsxChild>,
+        span: Span,
+    },
+    /// Interpolated string - `"Ciao {nome}!"`, only lexed with
+    /// `CompileOptions::interpolation`. One more text part than expressions.
+    Template {
+        quasis: Vec<String>,
+        expressions: Vec<Expression>,
+        span: Span,
+    },
+    /// Pattern matching - `vir buono (x) { 0 => "zero", n si (n > 0) => "cchiù", _ => "meno" }`.
+    /// The first arm that matches gives the value, `boh` if none does.
+    Match {
+        subject: Box<Expression>,
+        arms: Vec<MatchArm>,
+        span: Span,
+    },
+    /// An expression that failed to parse (only in recovered programs)
+    Error { span: Span },
 }
 
 impl Expression {
@@ -184,6 +460,7 @@ impl Expression {
             Expression::Null { span } => *span,
             Expression::Undefined { span } => *span,
             Expression::This { span } => *span,
+            Expression::SuperCall { span, .. } => *span,
             Expression::Array { span, .. } => *span,
             Expression::Object { span, .. } => *span,
             Expression::Binary { span, .. } => *span,
@@ -196,26 +473,193 @@ impl Expression {
             Expression::Await { span, .. } => *span,
             Expression::Ternary { span, .. } => *span,
             Expression::ConsoleLog { span, .. } => *span,
+            Expression::Console { span } => *span,
+            Expression::Promise { span, .. } => *span,
             Expression::ConsoleWarn { span, .. } => *span,
             Expression::ConsoleError { span, .. } => *span,
             Expression::TypeOf { span, .. } => *span,
             Expression::Delete { span, .. } => *span,
+            Expression::JsxElement { span, .. } => *span,
+            Expression::Template { span, .. } => *span,
+            Expression::Match { span, .. } => *span,
+            Expression::Error { span } => *span,
         }
     }
+
+    /// Calls `f` with the span of every node in it, its own last
+    pub fn spans_mut(&mut self, f: &mut impl FnMut(&mut Span)) {
+        match self {
+            Expression::Identifier { span, .. } | Expression::Number { span, .. } | Expression::String { span, .. } | Expression::Boolean { span, .. }
+            | Expression::Null { span } | Expression::Undefined { span } | Expression::This { span } | Expression::Console { span }
+            | Expression::Promise { span, .. } | Expression::Error { span } => f(span),
+            Expression::Array { elements: expressions, span } | Expression::ConsoleLog { arguments: expressions, span }
+            | Expression::SuperCall { arguments: expressions, span } | Expression::ConsoleWarn { arguments: expressions, span } | Expression::ConsoleError { arguments: expressions, span }
+            | Expression::Template { expressions, span, .. } => {
+                expressions.iter_mut().for_each(|expression| expression.spans_mut(f));
+                f(span);
+            }
+            Expression::Object { properties, span } => {
+                properties.iter_mut().for_each(|(_, value)| value.spans_mut(f));
+                f(span);
+            }
+            Expression::Binary { left, right, span, .. } | Expression::Assignment { target: left, value: right, span }
+            | Expression::Member { object: left, property: right, span, .. } => {
+                left.spans_mut(f);
+                right.spans_mut(f);
+                f(span);
+            }
+            Expression::Unary { operand, span, .. } | Expression::Await { argument: operand, span } | Expression::TypeOf { operand, span }
+            | Expression::Delete { operand, span } => {
+                operand.spans_mut(f);
+                f(span);
+            }
+            Expression::Call { callee, arguments, span } | Expression::New { callee, arguments, span } => {
+                callee.spans_mut(f);
+                arguments.iter_mut().for_each(|argument| argument.spans_mut(f));
+                f(span);
+            }
+            Expression::ArrowFunction { body, span, .. } => {
+                match body {
+                    ArrowBody::Expression(expression) => expression.spans_mut(f),
+                    ArrowBody::Block(statements) => statements.iter_mut().for_each(|statement| statement.spans_mut(f)),
+                }
+                f(span);
+            }
+            Expression::Ternary { condition, consequent, alternate, span } => {
+                [condition, consequent, alternate].into_iter().for_each(|expression| expression.spans_mut(f));
+                f(span);
+            }
+            Expression::JsxElement { attributes, children, span, .. } => {
+                attributes.iter_mut().filter_map(|(_, value)| value.as_mut()).for_each(|value| value.spans_mut(f));
+                for child in children {
+                    if let JsxChild::Expression(expression) = child { expression.spans_mut(f); }
+                }
+                f(span);
+            }
+            Expression::Match { subject, arms, span } => {
+                subject.spans_mut(f);
+                for arm in arms {
+                    arm.pattern.spans_mut(f);
+                    arm.guard.iter_mut().chain([&mut arm.body]).for_each(|expression| expression.spans_mut(f));
+                    f(&mut arm.span);
+                }
+                f(span);
+            }
+        }
+    }
+}
+
+/// Whether a constructor's `body` starts with `pate(...)`, the only place
+/// the parser lets it be
+pub fn calls_parent(body: &[Statement]) -> bool {
+    matches!(body.first(), Some(Statement::Expression { expression: Expression::SuperCall { .. }, .. }))
+}
+
+/// The variable a JSX tag refers to: `Saluto` for `<Saluto>`, `ui` for
+/// `<ui.Bottone>`. `None` for the tags of HTML elements, such as `<div>`.
+pub fn jsx_component(tag: &str) -> Option<&str> {
+    let variable = tag.split('.').next().unwrap_or(tag);
+    (tag.contains('.') || variable.starts_with(|c: char| c.is_uppercase() || c == '_')).then_some(variable)
+}
+
+/// What goes between a JSX element's tags
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum JsxChild {
+    /// Text as written, spaces and line breaks included
+    Text(String),
+    /// `{expression}`, or a nested element
+    Expression(Expression),
+}
+
+/// One arm of `vir buono`: `pattern si (guard) => body`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    /// Checked after the pattern matched, with its names bound
+    pub guard: Option<Expression>,
+    pub body: Expression,
+    pub span: Span,
+}
+
+/// What an arm of `vir buono` compares its value against
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Pattern {
+    /// `_`, which matches anything
+    Wildcard { span: Span },
+    /// A name, which matches anything and holds the value in the arm
+    Binding { name: Name, span: Span },
+    /// Matches a value `===` to it
+    Literal { value: Literal, span: Span },
+    /// `[a, b]`: an array of exactly that length, with matching elements
+    Array { elements: Vec<Pattern>, span: Span },
+    /// `{ nome, età: 18 }`: an object with those properties, matching. Just
+    /// `nome` is `nome: nome`.
+    Object { properties: Vec<(Name, Pattern)>, span: Span },
+}
+
+impl Pattern {
+    pub fn span(&self) -> Span {
+        match self {
+            Pattern::Wildcard { span } | Pattern::Binding { span, .. } | Pattern::Literal { span, .. } | Pattern::Array { span, .. } | Pattern::Object { span, .. } => *span,
+        }
+    }
+
+    /// Calls `f` with the span of every node in it, its own last
+    pub fn spans_mut(&mut self, f: &mut impl FnMut(&mut Span)) {
+        match self {
+            Pattern::Wildcard { span } | Pattern::Binding { span, .. } | Pattern::Literal { span, .. } => f(span),
+            Pattern::Array { elements, span } => {
+                elements.iter_mut().for_each(|element| element.spans_mut(f));
+                f(span);
+            }
+            Pattern::Object { properties, span } => {
+                properties.iter_mut().for_each(|(_, property)| property.spans_mut(f));
+                f(span);
+            }
+        }
+    }
+
+    /// The names the pattern binds, in order
+    pub fn bindings(&self) -> Vec<(&Name, Span)> {
+        match self {
+            Pattern::Binding { name, span } => vec![(name, *span)],
+            Pattern::Array { elements, .. } => elements.iter().flat_map(Pattern::bindings).collect(),
+            Pattern::Object { properties, .. } => properties.iter().flat_map(|(_, pattern)| pattern.bindings()).collect(),
+            Pattern::Wildcard { .. } | Pattern::Literal { .. } => vec![],
+        }
+    }
+}
+
+/// A constant in a pattern
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Literal {
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    Null,
+    Undefined,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ArrowBody {
     Expression(Box<Expression>),
     Block(Vec<Statement>),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum BinaryOp {
     Add, Subtract, Multiply, Divide, Modulo, Power,
     Equal, StrictEqual, NotEqual, StrictNotEqual,
     LessThan, GreaterThan, LessEqual, GreaterEqual,
     And, Or,
+    /// `o sinnò`: the right side if the left is `nisciun` or `boh`
+    Nullish,
 }
 
 impl BinaryOp {
@@ -237,11 +681,59 @@ impl BinaryOp {
             BinaryOp::GreaterEqual => ">=",
             BinaryOp::And => "&&",
             BinaryOp::Or => "||",
+            BinaryOp::Nullish => "??",
+        }
+    }
+
+    /// How tightly the operator binds, tighter being higher: `||` and `??` are 3,
+    /// `**` is 9. Assignment and `?:` bind looser than any of them (the
+    /// formatter counts them as 1 and 2), unary operators tighter.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or | BinaryOp::Nullish => 3,
+            BinaryOp::And => 4,
+            BinaryOp::Equal | BinaryOp::StrictEqual | BinaryOp::NotEqual | BinaryOp::StrictNotEqual => 5,
+            BinaryOp::LessThan | BinaryOp::GreaterThan | BinaryOp::LessEqual | BinaryOp::GreaterEqual => 6,
+            BinaryOp::Add | BinaryOp::Subtract => 7,
+            BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo => 8,
+            BinaryOp::Power => 9,
+        }
+    }
+
+    /// Whether `a op b op c` is `a op (b op c)`, as for `**`
+    pub fn is_right_associative(self) -> bool {
+        matches!(self, BinaryOp::Power)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PromiseHelper {
+    /// `tutt' quant`: every promise, or the first failure
+    All,
+    /// `'o primm che arriva`: whichever promise settles first
+    Race,
+}
+
+impl PromiseHelper {
+    pub fn to_js(&self) -> &'static str {
+        match self {
+            PromiseHelper::All => "Promise.all",
+            PromiseHelper::Race => "Promise.race",
+        }
+    }
+
+    /// The phrase as written in FratmScript
+    pub fn phrase(&self) -> &'static str {
+        match self {
+            PromiseHelper::All => "tutt' quant",
+            PromiseHelper::Race => "'o primm che arriva",
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum UnaryOp {
     Negate,
     Not,
@@ -0,0 +1,288 @@
+//! Dumps of the AST, for `fratm ast`, external tools and tests
+//!
+//! They work from the serialized form of the tree, so they cover every
+//! node without listing them: a node is printed as its variant name and
+//! its fields in alphabetical order, nested nodes below or inside it.
+
+use serde_json::{json, Map, Value};
+
+use super::{Program, Statement};
+
+impl Program {
+    /// An indented outline of the tree, one node per line with its span
+    /// (`line:column-line:column`) and plain fields:
+    ///
+    /// ```rust
+    /// let program = fratm_core::parse_source("tien x = 1").unwrap();
+    /// assert_eq!(program.to_tree(), "Program\n  VariableDecl 1:1-1:11 is_const=false name=\"x\"\n    value: Number 1:10-1:11 value=1.0\n");
+    /// ```
+    pub fn to_tree(&self) -> String {
+        let mut out = String::from("Program\n");
+        for stmt in statements(self) {
+            tree_node(&stmt, 1, "", &mut out);
+        }
+        out
+    }
+
+    /// A compact S-expression without spans, so moving code around only
+    /// changes the lines that changed:
+    ///
+    /// ```rust
+    /// let program = fratm_core::parse_source("tien x = 1").unwrap();
+    /// assert_eq!(program.to_sexp(), "(VariableDecl :is_const false :name \"x\" :value (Number :value 1.0))\n");
+    /// ```
+    pub fn to_sexp(&self) -> String {
+        self.statements.iter().map(|stmt| stmt.to_sexp() + "\n").collect()
+    }
+}
+
+impl Program {
+    /// The tree as JSON for external tools, in a flatter shape than the
+    /// serde one: every node is an object with its `type` (the variant
+    /// name), a numeric `id`, its `start` and `end` as
+    /// `{ offset, line, column }`, and its fields.
+    ///
+    /// Ids are handed out depth-first, parents before children and fields
+    /// in alphabetical order, starting from 0 for the program itself, so
+    /// the same source always gets the same ids.
+    ///
+    /// ```rust
+    /// let program = fratm_core::parse_source("tien x = 1").unwrap();
+    /// let json = program.to_json();
+    /// let decl = &json["statements"][0];
+    /// assert_eq!((decl["type"].as_str(), decl["id"].as_u64()), (Some("VariableDecl"), Some(1)));
+    /// assert_eq!(decl["value"]["end"]["column"], 11);
+    /// ```
+    pub fn to_json(&self) -> Value {
+        let mut next = 1;
+        let statements: Vec<Value> = statements(self).iter().map(|stmt| json_node(stmt, &mut next)).collect();
+        json!({ "type": "Program", "id": 0, "statements": statements })
+    }
+}
+
+impl Statement {
+    /// The S-expression of one statement, as in [`Program::to_sexp`].
+    pub fn to_sexp(&self) -> String {
+        let mut out = String::new();
+        sexp(&serde_json::to_value(self).unwrap_or(Value::Null), &mut out);
+        out
+    }
+}
+
+fn statements(program: &Program) -> Vec<Value> {
+    program.statements.iter().map(|stmt| serde_json::to_value(stmt).unwrap_or(Value::Null)).collect()
+}
+
+/// The variant name and fields of a serialized enum value such as
+/// `{"Number": {"value": 1.0, "span": {...}}}`.
+fn variant(value: &Value) -> Option<(&str, &Value)> {
+    match value {
+        Value::Object(map) if map.len() == 1 => {
+            let (name, inner) = map.iter().next()?;
+            name.starts_with(|c: char| c.is_ascii_uppercase()).then_some((name.as_str(), inner))
+        }
+        _ => None,
+    }
+}
+
+/// Whether `value` is printed inline rather than as a nested node
+fn is_plain(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => items.iter().all(|item| matches!(item, Value::String(_) | Value::Number(_) | Value::Bool(_))),
+        Value::Object(_) => false,
+        _ => true,
+    }
+}
+
+fn json_node(value: &Value, next: &mut usize) -> Value {
+    if let Some((name, inner)) = variant(value) {
+        let Value::Object(fields) = inner else {
+            // A wrapper such as `ArrowBody::Block`
+            return json_node(inner, next);
+        };
+        if variant(inner).is_some() {
+            return json_node(inner, next);
+        }
+        let mut node = Map::new();
+        node.insert("type".into(), name.into());
+        node.insert("id".into(), (*next).into());
+        *next += 1;
+        if let Some(span) = fields.get("span") {
+            let position = |offset: &str, line: &str, column: &str| json!({ "offset": span[offset], "line": span[line], "column": span[column] });
+            node.insert("start".into(), position("start", "line", "column"));
+            node.insert("end".into(), position("end", "end_line", "end_column"));
+        }
+        for (key, value) in fields.iter().filter(|(key, _)| *key != "span") {
+            node.insert(key.clone(), json_node(value, next));
+        }
+        return Value::Object(node);
+    }
+    match value {
+        Value::Array(items) => items.iter().map(|item| json_node(item, next)).collect(),
+        Value::Object(fields) => fields.iter().map(|(key, value)| (key.clone(), json_node(value, next))).collect(),
+        plain => plain.clone(),
+    }
+}
+
+fn span_text(span: &Value) -> String {
+    let field = |name: &str| span.get(name).and_then(Value::as_u64).unwrap_or(0);
+    format!("{}:{}-{}:{}", field("line"), field("column"), field("end_line"), field("end_column"))
+}
+
+fn tree_node(value: &Value, depth: usize, label: &str, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let Some((name, inner)) = variant(value) else {
+        // Plain structs (import specifiers) and values
+        match value {
+            Value::Object(fields) => tree_fields(&format!("{}{}", indent, label), fields, depth, out),
+            Value::Array(items) => {
+                out.push_str(&format!("{}{}\n", indent, label.trim_end()));
+                for item in items {
+                    tree_node(item, depth + 1, "", out);
+                }
+            }
+            plain => out.push_str(&format!("{}{}{}\n", indent, label, plain)),
+        }
+        return;
+    };
+    let head = format!("{}{}{}", indent, label, name);
+    match inner {
+        Value::Object(fields) => tree_fields(&head, fields, depth, out),
+        Value::Null => out.push_str(&format!("{}\n", head)),
+        nested if is_plain(nested) => out.push_str(&format!("{} {}\n", head, nested)),
+        Value::Array(items) => {
+            out.push_str(&format!("{}\n", head));
+            for item in items {
+                tree_node(item, depth + 1, "", out);
+            }
+        }
+        nested => {
+            out.push_str(&format!("{}\n", head));
+            tree_node(nested, depth + 1, "", out);
+        }
+    }
+}
+
+/// One line for `head`, its span and plain fields, then the nested ones
+fn tree_fields(head: &str, fields: &Map<String, Value>, depth: usize, out: &mut String) {
+    let mut line = head.trim_end().to_string();
+    if let Some(span) = fields.get("span") {
+        line.push(' ');
+        line.push_str(&span_text(span));
+    }
+    for (key, value) in fields.iter().filter(|(key, value)| *key != "span" && !value.is_null() && is_plain(value)) {
+        line.push_str(&format!(" {}={}", key, value));
+    }
+    out.push_str(&line);
+    out.push('\n');
+    for (key, value) in fields.iter().filter(|(key, value)| *key != "span" && !is_plain(value)) {
+        match value {
+            // Object properties: [key, value] pairs
+            Value::Array(items) if items.iter().all(|item| matches!(item, Value::Array(pair) if pair.len() == 2)) && !items.is_empty() => {
+                out.push_str(&format!("{}  {}:\n", "  ".repeat(depth), key));
+                for pair in items {
+                    tree_node(&pair[1], depth + 2, &format!("{}: ", pair[0]), out);
+                }
+            }
+            Value::Array(items) => {
+                out.push_str(&format!("{}  {}:\n", "  ".repeat(depth), key));
+                for item in items {
+                    tree_node(item, depth + 2, "", out);
+                }
+            }
+            nested => tree_node(nested, depth + 1, &format!("{}: ", key), out),
+        }
+    }
+}
+
+fn sexp(value: &Value, out: &mut String) {
+    if let Some((name, inner)) = variant(value) {
+        out.push('(');
+        out.push_str(name);
+        match inner {
+            Value::Object(fields) => sexp_fields(fields, out),
+            Value::Null => {}
+            nested => {
+                out.push(' ');
+                sexp(nested, out);
+            }
+        }
+        out.push(')');
+        return;
+    }
+    match value {
+        Value::Object(fields) => {
+            out.push('(');
+            sexp_fields(fields, out);
+            out.push(')');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                sexp(item, out);
+            }
+            out.push(']');
+        }
+        Value::Null => out.push_str("nil"),
+        plain => out.push_str(&plain.to_string()),
+    }
+}
+
+fn sexp_fields(fields: &Map<String, Value>, out: &mut String) {
+    for (key, value) in fields.iter().filter(|(key, value)| *key != "span" && !value.is_null()) {
+        if !out.ends_with('(') {
+            out.push(' ');
+        }
+        out.push(':');
+        out.push_str(key);
+        out.push(' ');
+        sexp(value, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_tree_nests_children() {
+        let program = crate::parse_source("facc f(a) {\n  piglie { x: a }\n}").unwrap();
+        assert_eq!(program.to_tree(), "\
+Program
+  FunctionDecl 1:1-3:2 is_async=false name=\"f\" params=[\"a\"]
+    body:
+      Return 2:3-2:18
+        value: Object 2:10-2:18
+          properties:
+            \"x\": Identifier 2:15-2:16 name=\"a\"
+");
+    }
+
+    #[test]
+    fn test_json_ids() {
+        let json = crate::parse_source("facc f(a) {\n  piglie a + 1\n}\nf(2)").unwrap().to_json();
+        let mut ids = Vec::new();
+        fn collect(value: &serde_json::Value, ids: &mut Vec<u64>) {
+            if let Some(id) = value.get("id").and_then(serde_json::Value::as_u64) {
+                assert!(value["type"].is_string(), "{}", value);
+                ids.push(id);
+            }
+            match value {
+                serde_json::Value::Array(items) => items.iter().for_each(|item| collect(item, ids)),
+                serde_json::Value::Object(fields) => fields.values().for_each(|field| collect(field, ids)),
+                _ => {}
+            }
+        }
+        collect(&json, &mut ids);
+        assert_eq!(ids, (0..10).collect::<Vec<_>>());
+        assert_eq!(json["statements"][1]["expression"]["type"], "Call");
+    }
+
+    #[test]
+    fn test_sexp_ignores_layout() {
+        let sexp = |source: &str| crate::parse_source(source).unwrap().to_sexp();
+        assert_eq!(sexp("stamm a dì(1 + 2)"), sexp("\n\n   stamm a dì( 1+2 ) // tre"));
+        assert_eq!(sexp("x = y o z"), "(Expression :expression (Assignment :target (Identifier :name \"x\") :value (Binary :left (Identifier :name \"y\") :operator \"Or\" :right (Identifier :name \"z\"))))\n");
+    }
+}
@@ -0,0 +1,235 @@
+//! Incremental reparsing
+//!
+//! [`Parse`] keeps a source with its AST and errors. [`Parse::edit`] applies
+//! an edit and parses again only the top-level statements it touches, with
+//! the one before and the one after, as whole lines. The statements before
+//! them are left alone, and those after only have their spans moved, so on
+//! a large file a keystroke costs a few statements instead of the file.
+//!
+//! The region is lexed on its own, so a string or `/*` the edit leaves open
+//! is an error there. On an error the whole source is parsed again, since
+//! it may reach past the region: the result is always what a full
+//! [`Parser::parse_recovering`] gives.
+//!
+//! ```rust
+//! use fratm_core::fix::Fix;
+//! use fratm_core::lexer::Span;
+//! use fratm_core::parser::incremental::Parse;
+//!
+//! let mut parse = Parse::new("tien a = 1\ntien b = 2\ntien c = 3\ntien d = 4\n");
+//! let reparsed = parse.edit(&Fix { span: Span::new(0, 6, 1, 1), replacement: "tien alfa".into() });
+//! assert_eq!(reparsed, 0..2);
+//! assert_eq!(parse.source(), "tien alfa = 1\ntien b = 2\ntien c = 3\ntien d = 4\n");
+//! assert_eq!(parse.program().statements[3].span().start, 36);
+//! ```
+
+use std::ops::Range;
+
+use super::ast::*;
+use super::{Names, Parser};
+use crate::errors::Diagnostic;
+use crate::fix::{apply, Fix};
+use crate::lexer::{Lexer, Span};
+use crate::CompileOptions;
+
+/// A source with its AST and errors, kept up to date edit by edit
+#[derive(Debug, Clone)]
+pub struct Parse {
+    source: String,
+    program: Program,
+    errors: Vec<Diagnostic>,
+    /// Dialect, locale, JSX, interpolation and source size limit to read
+    /// the source with
+    options: CompileOptions,
+    names: Names,
+}
+
+impl Parse {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self::with_options(source, CompileOptions::default())
+    }
+
+    /// Parses with the dialect, locale, JSX and interpolation of `options`.
+    /// A source over its [`max_source_bytes`](crate::limits::Limits::max_source_bytes)
+    /// is not parsed: the program is empty, with a `too-large` error.
+    pub fn with_options(source: impl Into<String>, options: CompileOptions) -> Self {
+        let mut parse = Self { source: source.into(), program: Program { statements: Vec::new() }, errors: Vec::new(), options, names: Names::new() };
+        parse.parse_all();
+        parse
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Lexer and parser errors, sorted by position
+    pub fn errors(&self) -> &[Diagnostic] {
+        &self.errors
+    }
+
+    pub fn into_parts(self) -> (String, Program, Vec<Diagnostic>) {
+        (self.source, self.program, self.errors)
+    }
+
+    /// Applies `edit`, whose span must be in the source, and parses what it
+    /// changed. Returns the indices of the statements parsed again; the
+    /// others are the ones from before.
+    pub fn edit(&mut self, edit: &Fix) -> Range<usize> {
+        let source = apply(&self.source, std::slice::from_ref(edit));
+        let too_large = self.options.limits.check_source(&source, self.options.locale).is_err();
+        let reparsed = if too_large { None } else { self.reparse(edit, &source) };
+        self.source = source;
+        reparsed.unwrap_or_else(|| {
+            self.parse_all();
+            0..self.program.statements.len()
+        })
+    }
+
+    fn parse_all(&mut self) {
+        if let Err(error) = self.options.limits.check_source(&self.source, self.options.locale) {
+            (self.program, self.errors) = (Program { statements: Vec::new() }, vec![error.into_diagnostic()]);
+            return;
+        }
+        let (program, errors) = self.parse_region(&self.source.clone());
+        (self.program, self.errors) = (program, errors);
+    }
+
+    fn parse_region(&mut self, source: &str) -> (Program, Vec<Diagnostic>) {
+        let options = &self.options;
+        let lexer = Lexer::new(source).with_keywords(&options.dialect).with_jsx(options.jsx.is_some()).with_interpolation(options.interpolation);
+        let mut parser = Parser::streaming(lexer).with_locale(options.locale).with_max_depth(options.parser.max_depth).with_keywords(&options.dialect).with_names(std::mem::take(&mut self.names));
+        let parsed = parser.parse_recovering();
+        self.names = parser.into_names();
+        parsed
+    }
+
+    /// Parses the statements around `edit` in the edited `source`, or
+    /// `None` if the whole source must be parsed again
+    fn reparse(&mut self, edit: &Fix, source: &str) -> Option<Range<usize>> {
+        let statements = &self.program.statements;
+        let line_start = |position: usize| self.source[..position].rfind(['\n', '\r']).map_or(0, |i| i + 1);
+        // The statements the edit touches, one more on each side
+        let mut first = statements.partition_point(|statement| statement.span().end < edit.span.start).saturating_sub(1);
+        let mut end = (statements.partition_point(|statement| statement.span().start <= edit.span.end) + 1).min(statements.len());
+        // ...and those sharing a line with them
+        let (start, line) = loop {
+            if first == 0 { break (0, 1); }
+            let start = line_start(statements[first].span().start);
+            if statements[first - 1].span().end <= start { break (start, statements[first].span().line); }
+            first -= 1;
+        };
+        let old_end = loop {
+            if end == statements.len() { break self.source.len(); }
+            let old_end = line_start(statements[end].span().start);
+            if statements[end - 1].span().end <= old_end { break old_end; }
+            end += 1;
+        };
+        let new_end = old_end + source.len() - self.source.len();
+        let next_line = statements.get(end).map(|statement| statement.span().line);
+
+        let region = &source[start..new_end];
+        let (mut program, errors) = self.parse_region(region);
+        if !errors.is_empty() { return None; }
+        for statement in &mut program.statements {
+            statement.spans_mut(&mut |span| shift(span, start as isize, line as isize - 1));
+        }
+        self.errors.retain(|error| error.span.start < start || error.span.start >= old_end);
+        if let Some(next_line) = next_line {
+            // The region ends with a line break
+            let new_next_line = line + region.matches('\n').count() + region.matches('\r').count() - region.matches("\r\n").count();
+            let (bytes, lines) = (new_end as isize - old_end as isize, new_next_line as isize - next_line as isize);
+            if bytes != 0 || lines != 0 {
+                for statement in &mut self.program.statements[end..] {
+                    statement.spans_mut(&mut |span| shift(span, bytes, lines));
+                }
+                for error in self.errors.iter_mut().filter(|error| error.span.start >= old_end) {
+                    diagnostic_spans(error, &mut |span| shift(span, bytes, lines));
+                }
+            }
+        }
+        let count = program.statements.len();
+        self.program.statements.splice(first..end, program.statements);
+        Some(first..first + count)
+    }
+}
+
+fn shift(span: &mut Span, bytes: isize, lines: isize) {
+    span.start = span.start.wrapping_add_signed(bytes);
+    span.end = span.end.wrapping_add_signed(bytes);
+    span.line = span.line.wrapping_add_signed(lines);
+    span.end_line = span.end_line.wrapping_add_signed(lines);
+}
+
+fn diagnostic_spans(diagnostic: &mut Diagnostic, f: &mut impl FnMut(&mut Span)) {
+    f(&mut diagnostic.span);
+    diagnostic.labels.iter_mut().for_each(|label| f(&mut label.span));
+    if let Some(fix) = &mut diagnostic.fix { f(&mut fix.span); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(start: usize, end: usize, replacement: &str) -> Fix {
+        Fix { span: Span::new(start, end, 1, 1), replacement: replacement.to_string() }
+    }
+
+    /// Applies every edit to `source` incrementally and checks the result
+    /// against a full parse after each
+    fn check_edits(source: &str, edits: &[Fix]) {
+        let mut parse = Parse::new(source);
+        for edit in edits {
+            parse.edit(edit);
+            let (program, errors) = crate::parse_recovering(parse.source());
+            assert_eq!(format!("{:?}", parse.program()), format!("{:?}", program), "after {:?}", edit);
+            assert_eq!(format!("{:?}", parse.errors()), format!("{:?}", errors), "after {:?}", edit);
+        }
+    }
+
+    #[test]
+    fn test_same_as_full_parse() {
+        let source = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/08_classi.fratm")).unwrap();
+        // Every insertion and deletion of a line break or a character
+        for position in (0..source.len()).filter(|&i| source.is_char_boundary(i)).step_by(7) {
+            check_edits(&source, &[fix(position, position, "\n"), fix(position, position, "x"), fix(position, position + 1, "")]);
+        }
+        check_edits("tien a = 1; tien b = 2\r\nsi (a) {\r\n  b = 3\r\n}\r\nsinnò { b = 4 }\r\n", &[
+            fix(0, 0, "chist è z = 0\r\n"),
+            fix(17, 17, " /* apert"),
+            fix(26, 26, "*/"),
+            fix(0, 15, ""),
+        ]);
+        // The end of the file an open comment reaches moves with the lines before it
+        check_edits("tien a = 1\ntien b = 2\ntien c = 3\ntien d = 4\ntien e = 5\n/* aperto\n", &[fix(0, 0, "tien z = 0\n")]);
+    }
+
+    #[test]
+    fn test_reparses_only_the_region() {
+        let source: String = (0..100).map(|i| format!("tien x{} = {}\n", i, i)).collect();
+        let mut parse = Parse::new(source.as_str());
+        let start = source.find("tien x50").unwrap();
+        assert_eq!(parse.edit(&fix(start + 5, start + 8, "cinquanta")), 49..52);
+        assert_eq!(parse.program().statements.len(), 100);
+        let last = parse.program().statements[99].span();
+        assert_eq!((last.line, &parse.source()[last.start..last.end]), (100, "tien x99 = 99"));
+        // An unclosed string reaches past the region: all parsed again
+        assert_eq!(parse.edit(&fix(start, start, "\"")), 0..parse.program().statements.len());
+        assert!(!parse.errors().is_empty());
+    }
+
+    #[test]
+    fn test_source_limit() {
+        let limits = crate::limits::Limits { max_source_bytes: Some(20), ..Default::default() };
+        let mut parse = Parse::with_options("tien a = 1\n", CompileOptions::builder().limits(limits).build());
+        assert_eq!((parse.program().statements.len(), parse.errors().len()), (1, 0));
+        parse.edit(&fix(11, 11, "tien b = 2\n"));
+        assert_eq!(parse.program().statements.len(), 0);
+        assert_eq!(parse.errors()[0].code, "too-large");
+        parse.edit(&fix(0, 11, ""));
+        assert_eq!((parse.program().statements.len(), parse.errors().len()), (1, 0));
+    }
+}
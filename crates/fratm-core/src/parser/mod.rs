@@ -1,59 +1,291 @@
 //! Parser for FratmScript
+//!
+//! The parser reads tokens lazily, so it can be fed straight from a
+//! [`Lexer`](crate::lexer::Lexer) without tokenizing the whole file first:
+//!
+//! ```rust
+//! use fratm_core::lexer::Lexer;
+//! use fratm_core::parser::Parser;
+//!
+//! let program = Parser::streaming(Lexer::new("tien x = 1")).parse().unwrap();
+//! assert_eq!(program.statements.len(), 1);
+//! ```
 
+pub mod arena;
+pub mod incremental;
 mod ast;
+#[cfg(feature = "serde")]
+mod dump;
+// Only the formatter's property test uses it, which compares dumps
+#[cfg(all(test, feature = "serde"))]
+pub(crate) mod arbitrary;
+
+use std::collections::{HashSet, VecDeque};
 
 pub use ast::*;
+use arena::ArenaProgram;
+use crate::errors::{Diagnostic, Locale};
+use crate::fix::Fix;
+use crate::lexer::keywords::KeywordTable;
 use crate::lexer::{Span, Token, TokenKind};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// Interned identifiers, see [`Name`]. Parsers can share them with
+/// [`Parser::with_names`], so a name used by many files is allocated once.
+pub type Names = HashSet<Name>;
+
+/// `Err` with the errors of a whole parse, if any. Lexer errors alone are
+/// kept when there are some: the parse errors that follow are mostly noise.
+fn failed(errors: Vec<Diagnostic>) -> Result<(), Vec<Diagnostic>> {
+    let lexical: Vec<Diagnostic> = errors.iter().filter(|e| e.code == "invalid-token").cloned().collect();
+    if !lexical.is_empty() {
+        Err(lexical)
+    } else if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Boxed so the many `Result`s passed around while parsing stay small
+type ParseError = Box<Diagnostic>;
+
+/// Default for [`ParserOptions::max_depth`]: deep enough for real code,
+/// shallow enough not to overflow a 1 MiB stack (the WASM default).
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// How far the parser goes after a syntax error.
+///
+/// Editors want every error and as much of the tree as can be saved, which
+/// is the default. Batch builds only print the first few errors, so they
+/// can stop there and skip the rest of the file.
+///
+/// ```rust
+/// use fratm_core::lexer::Lexer;
+/// use fratm_core::parser::{Parser, ParserOptions};
+///
+/// let source = "chist a = 1\nchist b = 2\nchist c = 3";
+/// assert_eq!(Parser::streaming(Lexer::new(source)).parse().unwrap_err().len(), 3);
+/// let options = ParserOptions { max_errors: 2, ..Default::default() };
+/// assert_eq!(Parser::streaming(Lexer::new(source)).with_options(options).parse().unwrap_err().len(), 2);
+/// let options = ParserOptions { recovery: false, ..Default::default() };
+/// assert_eq!(Parser::streaming(Lexer::new(source)).with_options(options).parse().unwrap_err().len(), 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+pub struct ParserOptions {
+    /// Go on after an error; otherwise stop at the first, as with a `max_errors` of 1
+    pub recovery: bool,
+    /// Stop once this many errors were found, lexer errors included
+    pub max_errors: usize,
+    /// Maximum depth of the tree, blocks and expressions, before giving up
+    /// with an error instead of overflowing the stack. A chain such as
+    /// `a + b + c` or `a.b.c` counts one level per link, since the tree it
+    /// builds is that deep and every later pass recurses down it.
+    pub max_depth: usize,
+}
 
-#[derive(Debug, Clone)]
-pub struct ParseError {
-    pub message: String,
-    pub span: Span,
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self { recovery: true, max_errors: usize::MAX, max_depth: DEFAULT_MAX_DEPTH }
+    }
 }
 
-impl ParseError {
-    pub fn new(message: impl Into<String>, span: Span) -> Self {
-        Self { message: message.into(), span }
+impl ParserOptions {
+    /// The errors after which to stop
+    fn limit(&self) -> usize {
+        if self.recovery { self.max_errors.max(1) } else { 1 }
     }
 }
 
-pub struct Parser {
-    tokens: Vec<Token>,
+static NEAPOLITAN: KeywordTable = KeywordTable::neapolitan();
+
+/// Tokens kept before the current one, for `previous()` and one step of backtracking
+const KEEP_BEHIND: usize = 2;
+
+pub struct Parser<'a> {
+    /// Tokens not read yet
+    source: Box<dyn Iterator<Item = Token<'a>> + 'a>,
+    /// The tokens around the current one: a few already read, then the current one and the next
+    window: VecDeque<Token<'a>>,
+    /// Stream index of `window[0]`
+    window_start: usize,
+    /// Stream index of the current token
     current: usize,
+    /// Set once `source` has yielded `Eof` (or run dry)
+    source_done: bool,
+    /// `Invalid` tokens skipped so far, reported as lexer errors
+    invalid: Vec<Token<'a>>,
+    names: Names,
+    locale: Locale,
+    /// Errors already recovered from, inside blocks and expressions
+    errors: Vec<Diagnostic>,
+    /// Current statement/expression nesting, checked against `options.max_depth`
+    depth: usize,
+    options: ParserOptions,
+    /// Keywords of the source, for the fixes that add one
+    keywords: &'a KeywordTable,
+    /// Set by `parse_class` before a method of a class with `figlio 'e`
+    derived: bool,
+    /// The `pate(...)` calls so far, inside the constructor of such a class
+    parent_calls: Option<Vec<Span>>,
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+impl<'a> Parser<'a> {
+    pub fn new(tokens: Vec<Token<'a>>) -> Self {
+        Parser::streaming(tokens.into_iter())
+    }
+
+    /// Parses tokens as they are produced, e.g. by a [`Lexer`](crate::lexer::Lexer).
+    ///
+    /// Only a couple of tokens are held at a time. A stream that stops
+    /// without `Eof` is treated as if it ended with one.
+    pub fn streaming(tokens: impl Iterator<Item = Token<'a>> + 'a) -> Self {
+        let mut parser = Self {
+            source: Box::new(tokens),
+            window: VecDeque::new(),
+            window_start: 0,
+            current: 0,
+            source_done: false,
+            invalid: Vec::new(),
+            names: Names::new(),
+            locale: Locale::default(),
+            errors: Vec::new(),
+            depth: 0,
+            options: ParserOptions::default(),
+            keywords: &NEAPOLITAN,
+            derived: false,
+            parent_calls: None,
+        };
+        parser.fill();
+        parser
+    }
+
+    /// Sets [`ParserOptions::max_depth`] alone
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.options.max_depth = max_depth;
+        self
     }
 
-    pub fn parse(&mut self) -> Result<Program, Vec<ParseError>> {
+    /// The keywords the tokens were read with, if not Neapolitan: the
+    /// fixes attached to errors write them
+    pub fn with_keywords(mut self, table: &'a KeywordTable) -> Self {
+        self.keywords = table;
+        self
+    }
+
+    /// Whether and how long to go on after an error
+    pub fn with_options(mut self, options: ParserOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Starts from the names interned by earlier parses, see [`into_names`](Self::into_names)
+    pub fn with_names(mut self, names: Names) -> Self {
+        self.names = names;
+        self
+    }
+
+    /// The names interned so far, for the next parser
+    pub fn into_names(self) -> Names {
+        self.names
+    }
+
+    /// Language of the error messages
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Parses the whole input, failing with every error found.
+    ///
+    /// If the lexer rejected some input, only those errors are returned:
+    /// the parse errors that follow from them are mostly noise.
+    pub fn parse(&mut self) -> Result<Program, Vec<Diagnostic>> {
+        let (program, errors) = self.parse_recovering();
+        failed(errors).map(|()| program)
+    }
+
+    /// Like [`parse`](Self::parse), but builds an [`ArenaProgram`]: each
+    /// top-level statement is moved into the arena once parsed, so the boxed
+    /// nodes only ever exist one statement at a time.
+    pub fn parse_arena(&mut self) -> Result<ArenaProgram, Vec<Diagnostic>> {
+        let mut program = ArenaProgram::default();
+        let errors = self.parse_each(|statement| program.push(statement));
+        failed(errors).map(|()| program)
+    }
+
+    /// Parses the whole input even when it has errors.
+    ///
+    /// Broken statements and expressions are replaced by `Statement::Error`
+    /// and `Expression::Error` nodes, so editors can still work with the rest
+    /// of the program. The returned errors are sorted by position.
+    pub fn parse_recovering(&mut self) -> (Program, Vec<Diagnostic>) {
         let mut statements = Vec::new();
-        let mut errors = Vec::new();
+        let errors = self.parse_each(|statement| statements.push(statement));
+        (Program { statements }, errors)
+    }
 
+    /// Hands each top-level statement to `emit` as soon as it is parsed,
+    /// recovering from errors, and returns the errors sorted by position.
+    fn parse_each(&mut self, mut emit: impl FnMut(Statement)) -> Vec<Diagnostic> {
         while !self.is_at_end() {
             while self.check(&TokenKind::Newline) {
                 self.advance();
             }
             if self.is_at_end() { break; }
 
-            match self.parse_statement() {
-                Ok(stmt) => statements.push(stmt),
-                Err(e) => {
-                    errors.push(e);
-                    self.synchronize();
-                }
-            }
+            let start = self.current;
+            let stmt = self.parse_statement_recovering(true);
+            emit(stmt);
+            // A stray closing delimiter is reported but never consumed by an expression
+            if self.current == start { self.advance(); }
         }
 
-        if errors.is_empty() {
-            Ok(Program { statements })
-        } else {
-            Err(errors)
+        let mut errors = std::mem::take(&mut self.errors);
+        errors.extend(self.invalid.drain(..).filter_map(|t| t.diagnostic(self.locale)));
+        errors.sort_by_key(|e| e.span.start);
+        // The blocks around the spot we gave up at fail too
+        errors.truncate(self.options.limit());
+        errors
+    }
+
+    /// Records a recovered error, and ends the input there if it is the
+    /// last one wanted
+    fn record(&mut self, error: Diagnostic) {
+        self.errors.push(error);
+        if self.errors.len() + self.invalid.len() >= self.options.limit() { self.give_up(); }
+    }
+
+    /// Makes the current token the end of the input
+    fn give_up(&mut self) {
+        let span = self.peek().span;
+        self.window.truncate(self.current - self.window_start);
+        self.window.push_back(Token::new(TokenKind::Eof, Span::new(span.start, span.start, span.line, span.column), ""));
+        self.source_done = true;
+    }
+
+    /// Parses a statement, or records the error and skips past it.
+    fn parse_statement_recovering(&mut self, top_level: bool) -> Statement {
+        let start = self.current_span();
+        match self.parse_statement() {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                // Every enclosing block fails at the same spot when a nested one does
+                if self.errors.last().is_none_or(|last| last.span != e.span) { self.record(*e); }
+                if top_level { self.synchronize(); } else { self.synchronize_in_block(); }
+                Statement::Error { span: self.span_from(start) }
+            }
         }
     }
 
     fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+        self.nested(Self::parse_statement_inner)
+    }
+
+    fn parse_statement_inner(&mut self) -> Result<Statement, ParseError> {
         while self.check(&TokenKind::Newline) { self.advance(); }
 
         if self.check(&TokenKind::Chist) { return self.parse_const_declaration(); }
@@ -67,9 +299,12 @@ impl Parser {
         if self.check(&TokenKind::Rompe) { return self.parse_break(); }
         if self.check(&TokenKind::Salta) { return self.parse_continue(); }
         if self.check(&TokenKind::Fermete) { return self.parse_debugger(); }
+        if self.check(&TokenKind::Giura) { return self.parse_assert(); }
+        if self.check(&TokenKind::Hash) { return self.parse_conditional(); }
         if self.check(&TokenKind::Pruvamm) { return self.parse_try_catch(); }
         if self.check(&TokenKind::Iett) { return self.parse_throw(); }
-        if self.check(&TokenKind::Na) { return self.parse_class(); }
+        if self.check(&TokenKind::Na) { return self.parse_class(self.current_span(), Vec::new()); }
+        if self.check(&TokenKind::At) { return self.parse_decorated(); }
         if self.check(&TokenKind::Chiamm) { return self.parse_import(); }
         if self.check(&TokenKind::Mann) { return self.parse_export(); }
         if self.check(&TokenKind::LeftBrace) { return self.parse_block(); }
@@ -82,26 +317,29 @@ impl Parser {
         self.expect(&TokenKind::Chist)?;
         self.expect(&TokenKind::E)?;
         let name = self.expect_identifier()?;
+        let annotation = self.parse_annotation()?;
         self.expect(&TokenKind::Equal)?;
         let value = self.parse_expression()?;
-        Ok(Statement::VariableDecl { name, value: Some(value), is_const: true, span: self.span_from(start.start) })
+        Ok(Statement::VariableDecl { name, annotation, value: Some(value), is_const: true, span: self.span_from(start) })
     }
 
     fn parse_let_declaration(&mut self) -> Result<Statement, ParseError> {
         let start = self.current_span();
         self.expect(&TokenKind::Tien)?;
         let name = self.expect_identifier()?;
+        let annotation = self.parse_annotation()?;
         let value = if self.match_token(&TokenKind::Equal) { Some(self.parse_expression()?) } else { None };
-        Ok(Statement::VariableDecl { name, value, is_const: false, span: self.span_from(start.start) })
+        Ok(Statement::VariableDecl { name, annotation, value, is_const: false, span: self.span_from(start) })
     }
 
     fn parse_function(&mut self) -> Result<Statement, ParseError> {
+        let derived = std::mem::take(&mut self.derived);
         let start = self.current_span();
         self.expect(&TokenKind::Facc)?;
         let name = self.expect_identifier()?;
-        let params = self.parse_parameters()?;
-        let body = self.parse_block_body()?;
-        Ok(Statement::FunctionDecl { name, params, body, is_async: false, span: self.span_from(start.start) })
+        let (params, signature) = self.parse_parameters()?;
+        let body = self.parse_function_body(derived && &*name == "costruttore")?;
+        Ok(Statement::FunctionDecl { name, params, signature, body, is_async: false, decorators: Vec::new(), span: self.span_from(start) })
     }
 
     fn parse_async_function(&mut self) -> Result<Statement, ParseError> {
@@ -110,22 +348,100 @@ impl Parser {
         self.expect(&TokenKind::Vir)?;
         self.expect(&TokenKind::Facc)?;
         let name = self.expect_identifier()?;
-        let params = self.parse_parameters()?;
-        let body = self.parse_block_body()?;
-        Ok(Statement::FunctionDecl { name, params, body, is_async: true, span: self.span_from(start.start) })
+        let (params, signature) = self.parse_parameters()?;
+        let body = self.parse_function_body(false)?;
+        Ok(Statement::FunctionDecl { name, params, signature, body, is_async: true, decorators: Vec::new(), span: self.span_from(start) })
     }
 
-    fn parse_parameters(&mut self) -> Result<Vec<String>, ParseError> {
-        self.expect(&TokenKind::LeftParen)?;
-        let mut params = Vec::new();
+    /// The body of a function. Only the `constructor` of a class with
+    /// `figlio 'e` can call `pate(...)`, and only as its first statement.
+    fn parse_function_body(&mut self, constructor: bool) -> Result<Vec<Statement>, ParseError> {
+        let outer = std::mem::replace(&mut self.parent_calls, constructor.then(Vec::new));
+        let body = self.parse_block_body();
+        let calls = std::mem::replace(&mut self.parent_calls, outer).unwrap_or_default();
+        let body = body?;
+        let first = match body.first() {
+            Some(Statement::Expression { expression: Expression::SuperCall { span, .. }, .. }) => Some(*span),
+            _ => None,
+        };
+        match calls.into_iter().find(|call| Some(*call) != first) {
+            Some(misplaced) => Err(self.misplaced_parent_call(misplaced)),
+            None => Ok(body),
+        }
+    }
+
+    /// `(a: nummero, b): parola`: the parameters, and their annotations if
+    /// there are any
+    fn parse_parameters(&mut self) -> Result<(Vec<Name>, Option<Box<Signature>>), ParseError> {
+        let open = self.expect(&TokenKind::LeftParen)?.span;
+        let (mut params, mut types) = (Vec::new(), Vec::new());
         if !self.check(&TokenKind::RightParen) {
-            params.push(self.expect_identifier()?);
-            while self.match_token(&TokenKind::Comma) {
+            loop {
                 params.push(self.expect_identifier()?);
+                types.push(self.parse_annotation()?);
+                if !self.match_token(&TokenKind::Comma) { break; }
+            }
+        }
+        self.expect_closing(&TokenKind::RightParen, open)?;
+        let result = self.parse_annotation()?;
+        let annotated = result.is_some() || types.iter().any(Option::is_some);
+        Ok((params, annotated.then(|| Box::new(Signature { params: types, result }))))
+    }
+
+    /// `: type`, if there is one
+    fn parse_annotation(&mut self) -> Result<Option<TypeAnnotation>, ParseError> {
+        if self.match_token(&TokenKind::Colon) { self.nested(Self::parse_type).map(Some) } else { Ok(None) }
+    }
+
+    fn parse_type(&mut self) -> Result<TypeAnnotation, ParseError> {
+        let start = self.current_span();
+        let mut annotation = match self.peek().kind {
+            TokenKind::LeftBrace => {
+                let open = self.advance().span;
+                let mut fields = Vec::new();
+                while self.check(&TokenKind::Newline) { self.advance(); }
+                while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+                    let name = self.expect_identifier()?;
+                    self.expect(&TokenKind::Colon)?;
+                    fields.push((name, self.nested(Self::parse_type)?));
+                    let separated = self.match_token(&TokenKind::Comma) || self.check(&TokenKind::Newline);
+                    while self.check(&TokenKind::Newline) { self.advance(); }
+                    if !separated { break; }
+                }
+                self.expect_closing(&TokenKind::RightBrace, open)?;
+                TypeAnnotation::Object { fields, span: self.span_from(start) }
             }
+            TokenKind::LeftParen => {
+                // `(nummero) => parola`, or just `(nummero)`
+                let open = self.advance().span;
+                let mut params = Vec::new();
+                if !self.check(&TokenKind::RightParen) {
+                    loop {
+                        params.push(self.nested(Self::parse_type)?);
+                        if !self.match_token(&TokenKind::Comma) { break; }
+                    }
+                }
+                self.expect_closing(&TokenKind::RightParen, open)?;
+                match params.pop() {
+                    Some(inner) if params.is_empty() && !self.check(&TokenKind::Arrow) => inner,
+                    last => {
+                        params.extend(last);
+                        self.expect(&TokenKind::Arrow)?;
+                        let result = Box::new(self.nested(Self::parse_type)?);
+                        TypeAnnotation::Function { params, result, span: self.span_from(start) }
+                    }
+                }
+            }
+            TokenKind::Nisciun => { self.advance(); TypeAnnotation::Named { name: self.intern("nisciun"), span: start } }
+            TokenKind::Boh => { self.advance(); TypeAnnotation::Named { name: self.intern("boh"), span: start } }
+            _ => TypeAnnotation::Named { name: self.expect_identifier()?, span: start },
+        };
+        while self.check(&TokenKind::LeftBracket) && self.check_next(&TokenKind::RightBracket) {
+            self.advance();
+            self.advance();
+            annotation = TypeAnnotation::Array { element: Box::new(annotation), span: self.span_from(start) };
         }
-        self.expect(&TokenKind::RightParen)?;
-        Ok(params)
+        Ok(annotation)
     }
 
     fn parse_return(&mut self) -> Result<Statement, ParseError> {
@@ -134,32 +450,33 @@ impl Parser {
         let value = if !self.check(&TokenKind::Newline) && !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
             Some(self.parse_expression()?)
         } else { None };
-        Ok(Statement::Return { value, span: self.span_from(start.start) })
+        Ok(Statement::Return { value, span: self.span_from(start) })
     }
 
     fn parse_if(&mut self) -> Result<Statement, ParseError> {
         let start = self.current_span();
         self.expect(&TokenKind::Si)?;
-        self.expect(&TokenKind::LeftParen)?;
+        let open = self.expect(&TokenKind::LeftParen)?.span;
         let condition = self.parse_expression()?;
-        self.expect(&TokenKind::RightParen)?;
+        self.expect_closing(&TokenKind::RightParen, open)?;
         let then_branch = self.parse_block_body()?;
         let else_branch = if self.match_token(&TokenKind::Sinno) {
-            if self.check(&TokenKind::Si) { Some(vec![self.parse_if()?]) }
+            if self.check(&TokenKind::Si) { Some(vec![self.nested(Self::parse_if)?]) }
             else { Some(self.parse_block_body()?) }
         } else { None };
-        Ok(Statement::If { condition, then_branch, else_branch, span: self.span_from(start.start) })
+        Ok(Statement::If { condition, then_branch, else_branch, span: self.span_from(start) })
     }
 
     fn parse_while(&mut self) -> Result<Statement, ParseError> {
         let start = self.current_span();
-        self.expect(&TokenKind::Mentre)?;
-        self.expect(&TokenKind::Che)?;
-        self.expect(&TokenKind::LeftParen)?;
+        let mentre = self.expect(&TokenKind::Mentre)?.span;
+        let che = self.keywords.word(&TokenKind::Che);
+        self.expect(&TokenKind::Che).map_err(|error| Box::new(error.with_fix(Fix::insert_after(mentre, format!(" {}", che)))))?;
+        let open = self.expect(&TokenKind::LeftParen)?.span;
         let condition = self.parse_expression()?;
-        self.expect(&TokenKind::RightParen)?;
+        self.expect_closing(&TokenKind::RightParen, open)?;
         let body = self.parse_block_body()?;
-        Ok(Statement::While { condition, body, span: self.span_from(start.start) })
+        Ok(Statement::While { condition, body, span: self.span_from(start) })
     }
 
     fn parse_for(&mut self) -> Result<Statement, ParseError> {
@@ -167,37 +484,63 @@ impl Parser {
         self.expect(&TokenKind::Pe)?;
         // "ogni" is now optional for backwards compatibility
         self.match_token(&TokenKind::Ogni);
-        self.expect(&TokenKind::LeftParen)?;
+        let open = self.expect(&TokenKind::LeftParen)?.span;
         let init = if self.check(&TokenKind::Tien) { Some(Box::new(self.parse_let_declaration()?)) }
         else if !self.check(&TokenKind::Semicolon) {
             let expr = self.parse_expression()?;
-            Some(Box::new(Statement::Expression { span: self.current_span(), expression: expr }))
+            Some(Box::new(Statement::Expression { span: expr.span(), expression: expr }))
         } else { None };
         self.expect(&TokenKind::Semicolon)?;
         let condition = if !self.check(&TokenKind::Semicolon) { Some(self.parse_expression()?) } else { None };
         self.expect(&TokenKind::Semicolon)?;
         let update = if !self.check(&TokenKind::RightParen) { Some(self.parse_expression()?) } else { None };
-        self.expect(&TokenKind::RightParen)?;
+        self.expect_closing(&TokenKind::RightParen, open)?;
         let body = self.parse_block_body()?;
-        Ok(Statement::For { init, condition, update, body, span: self.span_from(start.start) })
+        Ok(Statement::For { init, condition, update, body, span: self.span_from(start) })
     }
 
     fn parse_break(&mut self) -> Result<Statement, ParseError> {
         let start = self.current_span();
         self.expect(&TokenKind::Rompe)?;
-        Ok(Statement::Break { span: self.span_from(start.start) })
+        Ok(Statement::Break { span: self.span_from(start) })
     }
 
     fn parse_continue(&mut self) -> Result<Statement, ParseError> {
         let start = self.current_span();
         self.expect(&TokenKind::Salta)?;
-        Ok(Statement::Continue { span: self.span_from(start.start) })
+        Ok(Statement::Continue { span: self.span_from(start) })
     }
 
     fn parse_debugger(&mut self) -> Result<Statement, ParseError> {
         let start = self.current_span();
         self.expect(&TokenKind::Fermete)?;
-        Ok(Statement::Debugger { span: self.span_from(start.start) })
+        Ok(Statement::Debugger { span: self.span_from(start) })
+    }
+
+    fn parse_conditional(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+        self.expect(&TokenKind::Hash)?;
+        self.expect(&TokenKind::Si)?;
+        let mut negated = self.match_token(&TokenKind::Manco) || self.match_token(&TokenKind::Not);
+        let name = self.expect_identifier()?;
+        let value = if self.check(&TokenKind::EqualEqual) || self.check(&TokenKind::BangEqual) {
+            negated ^= self.advance().kind == TokenKind::BangEqual;
+            Some(self.expect_string()?)
+        } else { None };
+        let then_branch = self.parse_block_body()?;
+        let else_branch = if self.match_token(&TokenKind::Sinno) { Some(self.parse_block_body()?) } else { None };
+        Ok(Statement::Conditional { condition: Directive { name, value, negated }, then_branch, else_branch, span: self.span_from(start) })
+    }
+
+    fn parse_assert(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+        self.expect(&TokenKind::Giura)?;
+        self.expect(&TokenKind::Che)?;
+        let open = self.expect(&TokenKind::LeftParen)?.span;
+        let condition = self.parse_expression()?;
+        self.expect_closing(&TokenKind::RightParen, open)?;
+        let message = if self.match_token(&TokenKind::Sinno) { Some(self.parse_expression()?) } else { None };
+        Ok(Statement::Assert { condition, message, span: self.span_from(start) })
     }
 
     fn parse_try_catch(&mut self) -> Result<Statement, ParseError> {
@@ -207,42 +550,94 @@ impl Parser {
         self.expect(&TokenKind::And)?;
         self.expect(&TokenKind::Si)?;
         self.expect(&TokenKind::Schiatta)?;
-        let catch_param = if self.match_token(&TokenKind::LeftParen) {
+        let (catch_param, catch_type) = if self.match_token(&TokenKind::LeftParen) {
+            let open = self.previous().span;
             let param = self.expect_identifier()?;
-            self.expect(&TokenKind::RightParen)?;
-            Some(param)
-        } else { None };
+            // `(err è uno ErroreMio)`
+            let catch_type = if self.match_token(&TokenKind::E) {
+                self.expect_word("uno")?;
+                Some(self.nested(Self::parse_call)?)
+            } else { None };
+            self.expect_closing(&TokenKind::RightParen, open)?;
+            (Some(param), catch_type)
+        } else { (None, None) };
         let catch_body = self.parse_block_body()?;
-        Ok(Statement::TryCatch { try_body, catch_param, catch_body, span: self.span_from(start.start) })
+        Ok(Statement::TryCatch { try_body, catch_param, catch_type, catch_body, span: self.span_from(start) })
     }
 
     fn parse_throw(&mut self) -> Result<Statement, ParseError> {
         let start = self.current_span();
         self.expect(&TokenKind::Iett)?;
         let value = self.parse_expression()?;
-        Ok(Statement::Throw { value, span: self.span_from(start.start) })
+        Ok(Statement::Throw { value, span: self.span_from(start) })
     }
 
-    fn parse_class(&mut self) -> Result<Statement, ParseError> {
-        let start = self.current_span();
+    /// `decorators` were parsed before `na famiglie`, from `start`
+    fn parse_class(&mut self, start: Span, decorators: Vec<Expression>) -> Result<Statement, ParseError> {
         self.expect(&TokenKind::Na)?;
         self.expect(&TokenKind::Famiglie)?;
         let name = self.expect_identifier()?;
-        self.expect(&TokenKind::LeftBrace)?;
+        let superclass = if self.match_token(&TokenKind::Figlio) {
+            self.expect(&TokenKind::De)?;
+            Some(self.nested(Self::parse_call)?)
+        } else { None };
+        let open = self.expect(&TokenKind::LeftBrace)?.span;
         let mut methods = Vec::new();
         while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
             while self.check(&TokenKind::Newline) { self.advance(); }
-            if self.check(&TokenKind::RightBrace) { break; }
-            methods.push(self.parse_function()?);
+            if self.check(&TokenKind::RightBrace) || self.is_at_end() { break; }
+            let method_start = self.current_span();
+            let method_decorators = self.parse_decorators()?;
+            self.derived = superclass.is_some();
+            let mut method = self.parse_function()?;
+            if let Statement::FunctionDecl { name, decorators, span, .. } = &mut method {
+                if !method_decorators.is_empty() {
+                    if &**name == "costruttore" { return Err(self.misplaced_decorator(method_start)); }
+                    *decorators = method_decorators;
+                    *span = self.span_from(method_start);
+                }
+            }
+            methods.push(method);
         }
-        self.expect(&TokenKind::RightBrace)?;
-        Ok(Statement::ClassDecl { name, methods, span: self.span_from(start.start) })
+        self.expect_closing(&TokenKind::RightBrace, open)?;
+        Ok(Statement::ClassDecl { name, decorators, superclass, methods, span: self.span_from(start) })
+    }
+
+    /// A class with decorators, or the export of one: TC39 puts them before
+    /// `export` too
+    fn parse_decorated(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+        let decorators = self.parse_decorators()?;
+        if self.check(&TokenKind::Na) { return self.parse_class(start, decorators); }
+        if !self.check(&TokenKind::Mann) { return Err(self.misplaced_decorator(start)); }
+        let mut export = self.parse_export()?;
+        match &mut export {
+            Statement::Export { declaration: Some(declaration), span, .. } if matches!(**declaration, Statement::ClassDecl { .. }) => {
+                if let Statement::ClassDecl { decorators: class_decorators, .. } = declaration.as_mut() {
+                    class_decorators.splice(0..0, decorators);
+                }
+                *span = start.to(span);
+                Ok(export)
+            }
+            _ => Err(self.misplaced_decorator(start)),
+        }
+    }
+
+    /// `@nome`, `@nome.campo`, `@nome(argomenti)` or `@(espressione)`,
+    /// each followed by a newline or another decorator
+    fn parse_decorators(&mut self) -> Result<Vec<Expression>, ParseError> {
+        let mut decorators = Vec::new();
+        while self.match_token(&TokenKind::At) {
+            decorators.push(self.parse_call()?);
+            while self.check(&TokenKind::Newline) { self.advance(); }
+        }
+        Ok(decorators)
     }
 
     fn parse_import(&mut self) -> Result<Statement, ParseError> {
         let start = self.current_span();
         self.expect(&TokenKind::Chiamm)?;
-        self.expect(&TokenKind::LeftBrace)?;
+        let open = self.expect(&TokenKind::LeftBrace)?.span;
         let mut specifiers = Vec::new();
         if !self.check(&TokenKind::RightBrace) {
             loop {
@@ -251,10 +646,10 @@ impl Parser {
                 if !self.match_token(&TokenKind::Comma) { break; }
             }
         }
-        self.expect(&TokenKind::RightBrace)?;
+        self.expect_closing(&TokenKind::RightBrace, open)?;
         self.expect(&TokenKind::Da)?;
         let source = self.expect_string()?;
-        Ok(Statement::Import { specifiers, source, span: self.span_from(start.start) })
+        Ok(Statement::Import { specifiers, source, span: self.span_from(start) })
     }
 
     fn parse_export(&mut self) -> Result<Statement, ParseError> {
@@ -263,35 +658,37 @@ impl Parser {
         self.expect(&TokenKind::For)?;
         if self.match_token(&TokenKind::Predefinit) {
             let value = self.parse_expression()?;
-            Ok(Statement::Export { declaration: None, default_value: Some(value), span: self.span_from(start.start) })
+            Ok(Statement::Export { declaration: None, default_value: Some(value), span: self.span_from(start) })
         } else {
             let decl = self.parse_statement()?;
-            Ok(Statement::Export { declaration: Some(Box::new(decl)), default_value: None, span: self.span_from(start.start) })
+            Ok(Statement::Export { declaration: Some(Box::new(decl)), default_value: None, span: self.span_from(start) })
         }
     }
 
     fn parse_block(&mut self) -> Result<Statement, ParseError> {
         let start = self.current_span();
         let statements = self.parse_block_body()?;
-        Ok(Statement::Block { statements, span: self.span_from(start.start) })
+        Ok(Statement::Block { statements, span: self.span_from(start) })
     }
 
     fn parse_block_body(&mut self) -> Result<Vec<Statement>, ParseError> {
-        self.expect(&TokenKind::LeftBrace)?;
+        let open = self.expect(&TokenKind::LeftBrace)?.span;
         let mut statements = Vec::new();
         while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
             while self.check(&TokenKind::Newline) { self.advance(); }
-            if self.check(&TokenKind::RightBrace) { break; }
-            statements.push(self.parse_statement()?);
+            if self.check(&TokenKind::RightBrace) || self.is_at_end() { break; }
+            let start = self.current;
+            statements.push(self.parse_statement_recovering(false));
+            if self.current == start { self.advance(); }
         }
-        self.expect(&TokenKind::RightBrace)?;
+        self.expect_closing(&TokenKind::RightBrace, open)?;
         Ok(statements)
     }
 
     fn parse_expression_statement(&mut self) -> Result<Statement, ParseError> {
         let start = self.current_span();
         let expr = self.parse_expression()?;
-        Ok(Statement::Expression { expression: expr, span: self.span_from(start.start) })
+        Ok(Statement::Expression { expression: expr, span: self.span_from(start) })
     }
 
     fn parse_expression(&mut self) -> Result<Expression, ParseError> {
@@ -301,226 +698,239 @@ impl Parser {
     fn parse_assignment(&mut self) -> Result<Expression, ParseError> {
         let expr = self.parse_ternary()?;
         if self.match_token(&TokenKind::Equal) {
-            let value = self.parse_assignment()?;
-            let span = self.span_from(expr.span().start);
+            let value = self.nested(Self::parse_assignment)?;
+            let span = self.span_from(expr.span());
             return Ok(Expression::Assignment { target: Box::new(expr), value: Box::new(value), span });
         }
         Ok(expr)
     }
 
     fn parse_ternary(&mut self) -> Result<Expression, ParseError> {
-        let mut expr = self.parse_or()?;
+        let mut expr = self.parse_binary(0)?;
         if self.match_token(&TokenKind::Question) {
             let consequent = self.parse_expression()?;
             self.expect(&TokenKind::Colon)?;
-            let alternate = self.parse_ternary()?;
-            let span = self.span_from(expr.span().start);
+            let alternate = self.nested(Self::parse_ternary)?;
+            let span = self.span_from(expr.span());
             expr = Expression::Ternary { condition: Box::new(expr), consequent: Box::new(consequent), alternate: Box::new(alternate), span };
         }
         Ok(expr)
     }
 
-    fn parse_or(&mut self) -> Result<Expression, ParseError> {
-        let mut expr = self.parse_and()?;
-        // Supports both "o" and "||" style
-        while self.match_token(&TokenKind::Or) {
-            let right = self.parse_and()?;
-            let span = self.span_from(expr.span().start);
-            expr = Expression::Binary { left: Box::new(expr), operator: BinaryOp::Or, right: Box::new(right), span };
-        }
-        Ok(expr)
-    }
-
-    fn parse_and(&mut self) -> Result<Expression, ParseError> {
-        let mut expr = self.parse_equality()?;
-        // Supports both "e" and "pure" (both mean AND)
-        while self.match_token(&TokenKind::And) || self.match_token(&TokenKind::Pure) {
-            let right = self.parse_equality()?;
-            let span = self.span_from(expr.span().start);
-            expr = Expression::Binary { left: Box::new(expr), operator: BinaryOp::And, right: Box::new(right), span };
-        }
-        Ok(expr)
-    }
-
-    fn parse_equality(&mut self) -> Result<Expression, ParseError> {
-        let mut expr = self.parse_comparison()?;
-        loop {
-            let op = if self.match_token(&TokenKind::EqualEqualEqual) { BinaryOp::StrictEqual }
-            else if self.match_token(&TokenKind::EqualEqual) { BinaryOp::Equal }
-            else if self.match_token(&TokenKind::BangEqualEqual) { BinaryOp::StrictNotEqual }
-            else if self.match_token(&TokenKind::BangEqual) { BinaryOp::NotEqual }
-            else { break };
-            let right = self.parse_comparison()?;
-            let span = self.span_from(expr.span().start);
-            expr = Expression::Binary { left: Box::new(expr), operator: op, right: Box::new(right), span };
-        }
-        Ok(expr)
-    }
-
-    fn parse_comparison(&mut self) -> Result<Expression, ParseError> {
-        let mut expr = self.parse_term()?;
-        loop {
-            let op = if self.match_token(&TokenKind::Less) { BinaryOp::LessThan }
-            else if self.match_token(&TokenKind::LessEqual) { BinaryOp::LessEqual }
-            else if self.match_token(&TokenKind::Greater) { BinaryOp::GreaterThan }
-            else if self.match_token(&TokenKind::GreaterEqual) { BinaryOp::GreaterEqual }
-            else { break };
-            let right = self.parse_term()?;
-            let span = self.span_from(expr.span().start);
-            expr = Expression::Binary { left: Box::new(expr), operator: op, right: Box::new(right), span };
-        }
-        Ok(expr)
-    }
-
-    fn parse_term(&mut self) -> Result<Expression, ParseError> {
-        let mut expr = self.parse_factor()?;
-        loop {
-            let op = if self.match_token(&TokenKind::Plus) { BinaryOp::Add }
-            else if self.match_token(&TokenKind::Minus) { BinaryOp::Subtract }
-            else { break };
-            let right = self.parse_factor()?;
-            let span = self.span_from(expr.span().start);
-            expr = Expression::Binary { left: Box::new(expr), operator: op, right: Box::new(right), span };
-        }
-        Ok(expr)
+    /// Binary operators by precedence climbing: parses operands joined by
+    /// operators binding at least as tightly as `min_precedence`. What an
+    /// operator is and how it binds is all in [`binary_operator`] and
+    /// [`BinaryOp::precedence`].
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expression, ParseError> {
+        self.chained(|parser| parser.parse_binary_chain(min_precedence))
     }
 
-    fn parse_factor(&mut self) -> Result<Expression, ParseError> {
-        let mut expr = self.parse_power()?;
-        loop {
-            let op = if self.match_token(&TokenKind::Star) { BinaryOp::Multiply }
-            else if self.match_token(&TokenKind::Slash) { BinaryOp::Divide }
-            else if self.match_token(&TokenKind::Percent) { BinaryOp::Modulo }
-            else { break };
-            let right = self.parse_power()?;
-            let span = self.span_from(expr.span().start);
+    fn parse_binary_chain(&mut self, min_precedence: u8) -> Result<Expression, ParseError> {
+        let start = self.current_span();
+        let mut expr = self.parse_unary()?;
+        while let Some(op) = binary_operator(&self.peek().kind).filter(|op| op.precedence() >= min_precedence) {
+            let op = if op == BinaryOp::Or && self.check_next(&TokenKind::Sinno) {
+                self.advance();
+                BinaryOp::Nullish
+            } else {
+                op
+            };
+            // `-a ** b` could be `(-a) ** b` or `-(a ** b)`: JavaScript refuses it, so do we
+            if op == BinaryOp::Power && expr.span().start == start.start {
+                if let Some(unary) = unary_keyword(&expr) {
+                    return Err(self.unary_before_power(unary, expr.span(), self.peek().span));
+                }
+            }
+            self.advance();
+            // `expr` goes one level down the tree
+            self.deeper()?;
+            let right = if op.is_right_associative() {
+                self.nested(|parser| parser.parse_binary(op.precedence()))?
+            } else {
+                self.parse_binary(op.precedence() + 1)?
+            };
+            let span = self.span_from(expr.span());
             expr = Expression::Binary { left: Box::new(expr), operator: op, right: Box::new(right), span };
         }
         Ok(expr)
     }
 
-    fn parse_power(&mut self) -> Result<Expression, ParseError> {
-        let expr = self.parse_unary()?;
-        if self.match_token(&TokenKind::StarStar) {
-            let right = self.parse_power()?;
-            let span = self.span_from(expr.span().start);
-            return Ok(Expression::Binary { left: Box::new(expr), operator: BinaryOp::Power, right: Box::new(right), span });
-        }
-        Ok(expr)
+    fn parse_unary(&mut self) -> Result<Expression, ParseError> {
+        self.nested(Self::parse_unary_inner)
     }
 
-    fn parse_unary(&mut self) -> Result<Expression, ParseError> {
+    fn parse_unary_inner(&mut self) -> Result<Expression, ParseError> {
         // Numeric negation: -x
         if self.match_token(&TokenKind::Minus) {
             let start = self.previous().span;
             let operand = self.parse_unary()?;
-            return Ok(Expression::Unary { operator: UnaryOp::Negate, operand: Box::new(operand), span: self.span_from(start.start) });
+            return Ok(Expression::Unary { operator: UnaryOp::Negate, operand: Box::new(operand), span: self.span_from(start) });
         }
         // Logical negation: no, !, manco
         if self.match_token(&TokenKind::Not) || self.match_token(&TokenKind::Manco) {
             let start = self.previous().span;
             let operand = self.parse_unary()?;
-            return Ok(Expression::Unary { operator: UnaryOp::Not, operand: Box::new(operand), span: self.span_from(start.start) });
+            return Ok(Expression::Unary { operator: UnaryOp::Not, operand: Box::new(operand), span: self.span_from(start) });
         }
         // Await keyword: aspett
         if self.match_token(&TokenKind::Aspett) {
             let start = self.previous().span;
             let argument = self.parse_unary()?;
-            return Ok(Expression::Await { argument: Box::new(argument), span: self.span_from(start.start) });
+            return Ok(Expression::Await { argument: Box::new(argument), span: self.span_from(start) });
         }
         // Delete keyword: leva
         if self.match_token(&TokenKind::Leva) {
             let start = self.previous().span;
             let operand = self.parse_unary()?;
-            return Ok(Expression::Delete { operand: Box::new(operand), span: self.span_from(start.start) });
+            return Ok(Expression::Delete { operand: Box::new(operand), span: self.span_from(start) });
         }
         // Typeof: chè è - not yet implemented as compound keyword, will use CheE if present
         self.parse_call()
     }
 
     fn parse_call(&mut self) -> Result<Expression, ParseError> {
+        self.chained(Self::parse_call_chain)
+    }
+
+    fn parse_call_chain(&mut self) -> Result<Expression, ParseError> {
         let mut expr = self.parse_primary()?;
         loop {
+            if matches!(self.peek().kind, TokenKind::LeftParen | TokenKind::Dot | TokenKind::LeftBracket) { self.deeper()?; }
             if self.match_token(&TokenKind::LeftParen) {
                 let arguments = self.parse_arguments()?;
-                let span = self.span_from(expr.span().start);
+                let span = self.span_from(expr.span());
                 expr = Expression::Call { callee: Box::new(expr), arguments, span };
             } else if self.match_token(&TokenKind::Dot) {
                 let name = self.expect_identifier()?;
-                let span = self.span_from(expr.span().start);
+                let span = self.span_from(expr.span());
                 expr = Expression::Member { object: Box::new(expr), property: Box::new(Expression::Identifier { name, span: self.previous().span }), computed: false, span };
             } else if self.match_token(&TokenKind::LeftBracket) {
+                let open = self.previous().span;
                 let property = self.parse_expression()?;
-                self.expect(&TokenKind::RightBracket)?;
-                let span = self.span_from(expr.span().start);
+                self.expect_closing(&TokenKind::RightBracket, open)?;
+                let span = self.span_from(expr.span());
                 expr = Expression::Member { object: Box::new(expr), property: Box::new(property), computed: true, span };
             } else { break; }
         }
         Ok(expr)
     }
 
+    /// Parses `a dì(...)` after `stamm`/`avvis`/`scrive`. Without the
+    /// parentheses there are no arguments: the method is used as a value.
+    fn parse_console_arguments(&mut self) -> Result<Option<Vec<Expression>>, ParseError> {
+        self.expect_word("a")?;
+        self.expect(&TokenKind::Di)?;
+        if !self.match_token(&TokenKind::LeftParen) {
+            return Ok(None);
+        }
+        self.parse_arguments().map(Some)
+    }
+
+    /// `console.<method>` for a bare `stamm a dì`, e.g. passed as a callback
+    fn console_method(&mut self, start: Span, method: &'static str) -> Expression {
+        let span = self.span_from(start);
+        let property = Expression::Identifier { name: self.intern(method), span };
+        Expression::Member { object: Box::new(Expression::Console { span: start }), property: Box::new(property), computed: false, span }
+    }
+
+    /// Parses call arguments, right after the opening `(`.
     fn parse_arguments(&mut self) -> Result<Vec<Expression>, ParseError> {
+        let open = self.previous().span;
         let mut args = Vec::new();
         if !self.check(&TokenKind::RightParen) {
             args.push(self.parse_expression()?);
             while self.match_token(&TokenKind::Comma) { args.push(self.parse_expression()?); }
         }
-        self.expect(&TokenKind::RightParen)?;
+        self.expect_closing(&TokenKind::RightParen, open)?;
         Ok(args)
     }
 
     fn parse_primary(&mut self) -> Result<Expression, ParseError> {
+        if self.is_at_end() {
+            let eof = self.peek().span;
+            return Err(syntax_error("Expected an expression, but the file ended", eof, self.locale));
+        }
+        if self.check(&TokenKind::Vir) && self.check_next(&TokenKind::Buono) {
+            let start = self.advance().span;
+            return self.nested(|parser| parser.parse_match(start));
+        }
+        // super(...) - "pate(...)", the father, in a derived constructor
+        if self.parent_calls.is_some() && matches!(self.peek().kind, TokenKind::Identifier("pate")) && self.check_next(&TokenKind::LeftParen) {
+            let start = self.advance().span;
+            self.advance();
+            let arguments = self.parse_arguments()?;
+            let span = self.span_from(start);
+            self.parent_calls.iter_mut().for_each(|calls| calls.push(span));
+            return Ok(Expression::SuperCall { arguments, span });
+        }
+        let locale = self.locale;
         let token = self.advance();
         let span = token.span;
 
         match &token.kind {
             TokenKind::Number(n) => Ok(Expression::Number { value: *n, span }),
-            TokenKind::String(s) => Ok(Expression::String { value: s.clone(), span }),
+            TokenKind::String(s) => Ok(Expression::String { value: s.to_string(), span }),
+            TokenKind::TemplateHead(s) => {
+                let head = s.to_string();
+                self.nested(|parser| parser.parse_template(head, span))
+            }
             TokenKind::Overo => Ok(Expression::Boolean { value: true, span }),
             TokenKind::Sfols => Ok(Expression::Boolean { value: false, span }),
             TokenKind::Nisciun => Ok(Expression::Null { span }),
             TokenKind::Boh => Ok(Expression::Undefined { span }),
             TokenKind::Stu => {
                 self.expect(&TokenKind::Cos)?;
-                Ok(Expression::This { span: self.span_from(span.start) })
+                Ok(Expression::This { span: self.span_from(span) })
             }
             TokenKind::Nu => {
                 self.expect(&TokenKind::Bell)?;
-                let callee = self.parse_call()?;
-                let span = self.span_from(span.start);
+                let callee = self.nested(Self::parse_call)?;
+                let span = self.span_from(span);
                 if let Expression::Call { callee: inner, arguments, .. } = callee {
                     Ok(Expression::New { callee: inner, arguments, span })
                 } else {
                     Ok(Expression::New { callee: Box::new(callee), arguments: vec![], span })
                 }
             }
+            // Promise.all - "tutt' quant"
+            TokenKind::Tutt => {
+                self.expect_word("quant")?;
+                Ok(Expression::Promise { helper: PromiseHelper::All, span: self.span_from(span) })
+            }
+            // Promise.race - "'o primm che arriva"
+            TokenKind::O => {
+                self.expect_word("primm")?;
+                self.expect(&TokenKind::Che)?;
+                self.expect_word("arriva")?;
+                Ok(Expression::Promise { helper: PromiseHelper::Race, span: self.span_from(span) })
+            }
             TokenKind::Stamm => {
-                self.expect_identifier_a()?;
-                self.expect(&TokenKind::Di)?;
-                self.expect(&TokenKind::LeftParen)?;
-                let arguments = self.parse_arguments()?;
-                Ok(Expression::ConsoleLog { arguments, span: self.span_from(span.start) })
+                // console.table() and the other methods - "stamm.tabella(...)"
+                if self.check(&TokenKind::Dot) {
+                    return Ok(Expression::Console { span });
+                }
+                match self.parse_console_arguments()? {
+                    Some(arguments) => Ok(Expression::ConsoleLog { arguments, span: self.span_from(span) }),
+                    None => Ok(self.console_method(span, "log")),
+                }
             }
             // console.warn() - "avvis a dì(...)"
-            TokenKind::Avvis => {
-                self.expect_identifier_a()?;
-                self.expect(&TokenKind::Di)?;
-                self.expect(&TokenKind::LeftParen)?;
-                let arguments = self.parse_arguments()?;
-                Ok(Expression::ConsoleWarn { arguments, span: self.span_from(span.start) })
-            }
+            TokenKind::Avvis => match self.parse_console_arguments()? {
+                Some(arguments) => Ok(Expression::ConsoleWarn { arguments, span: self.span_from(span) }),
+                None => Ok(self.console_method(span, "warn")),
+            },
             // console.error() - "scrive a dì(...)"
-            TokenKind::Scrive => {
-                self.expect_identifier_a()?;
-                self.expect(&TokenKind::Di)?;
-                self.expect(&TokenKind::LeftParen)?;
-                let arguments = self.parse_arguments()?;
-                Ok(Expression::ConsoleError { arguments, span: self.span_from(span.start) })
+            TokenKind::Scrive => match self.parse_console_arguments()? {
+                Some(arguments) => Ok(Expression::ConsoleError { arguments, span: self.span_from(span) }),
+                None => Ok(self.console_method(span, "error")),
+            },
+            TokenKind::Identifier(name) => {
+                let name = *name;
+                Ok(Expression::Identifier { name: self.intern(name), span })
             }
-            TokenKind::Identifier(name) => Ok(Expression::Identifier { name: name.clone(), span }),
             TokenKind::LeftParen => {
                 let expr = self.parse_expression()?;
-                self.expect(&TokenKind::RightParen)?;
+                self.expect_closing(&TokenKind::RightParen, span)?;
                 if self.match_token(&TokenKind::Arrow) {
                     let params = if let Expression::Identifier { name, .. } = expr { vec![name] } else { vec![] };
                     let body = if self.check(&TokenKind::LeftBrace) {
@@ -528,10 +938,11 @@ impl Parser {
                     } else {
                         ArrowBody::Expression(Box::new(self.parse_expression()?))
                     };
-                    return Ok(Expression::ArrowFunction { params, body, span: self.span_from(span.start) });
+                    return Ok(Expression::ArrowFunction { params, body, span: self.span_from(span) });
                 }
                 Ok(expr)
             }
+            TokenKind::JsxTagStart => self.nested(|parser| parser.parse_jsx_element(span)),
             TokenKind::LeftBracket => {
                 let mut elements = Vec::new();
                 if !self.check(&TokenKind::RightBracket) {
@@ -541,8 +952,8 @@ impl Parser {
                         elements.push(self.parse_expression()?);
                     }
                 }
-                self.expect(&TokenKind::RightBracket)?;
-                Ok(Expression::Array { elements, span: self.span_from(span.start) })
+                self.expect_closing(&TokenKind::RightBracket, span)?;
+                Ok(Expression::Array { elements, span: self.span_from(span) })
             }
             TokenKind::LeftBrace => {
                 let mut properties = Vec::new();
@@ -567,54 +978,436 @@ impl Parser {
                 }
                 // Skip trailing newlines
                 while self.check(&TokenKind::Newline) { self.advance(); }
-                self.expect(&TokenKind::RightBrace)?;
-                Ok(Expression::Object { properties, span: self.span_from(span.start) })
+                self.expect_closing(&TokenKind::RightBrace, span)?;
+                Ok(Expression::Object { properties, span: self.span_from(span) })
+            }
+            _ => {
+                let error = syntax_error(&format!("What is '{}' here? Expected an expression!", token.kind), span, locale);
+                // Leave closing delimiters and line ends to whoever is waiting for them
+                if matches!(token.kind, TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace | TokenKind::Newline | TokenKind::Semicolon) {
+                    self.current -= 1;
+                }
+                self.record(*error);
+                Ok(Expression::Error { span })
+            }
+        }
+    }
+
+    /// Parses `vir buono (subject) { arms }` after its `vir`. Arms are
+    /// separated by newlines or commas.
+    fn parse_match(&mut self, start: Span) -> Result<Expression, ParseError> {
+        self.expect(&TokenKind::Buono)?;
+        let open = self.expect(&TokenKind::LeftParen)?.span;
+        let subject = self.parse_expression()?;
+        self.expect_closing(&TokenKind::RightParen, open)?;
+        let open = self.expect(&TokenKind::LeftBrace)?.span;
+        let mut arms = Vec::new();
+        loop {
+            while self.check(&TokenKind::Newline) || self.check(&TokenKind::Comma) { self.advance(); }
+            if self.check(&TokenKind::RightBrace) || self.is_at_end() { break; }
+            let arm_start = self.current_span();
+            let pattern = self.nested(Self::parse_pattern)?;
+            let guard = if self.match_token(&TokenKind::Si) {
+                let open = self.expect(&TokenKind::LeftParen)?.span;
+                let guard = self.parse_expression()?;
+                self.expect_closing(&TokenKind::RightParen, open)?;
+                Some(guard)
+            } else { None };
+            self.expect(&TokenKind::Arrow)?;
+            let body = self.parse_expression()?;
+            arms.push(MatchArm { pattern, guard, body, span: self.span_from(arm_start) });
+        }
+        self.expect_closing(&TokenKind::RightBrace, open)?;
+        Ok(Expression::Match { subject: Box::new(subject), arms, span: self.span_from(start) })
+    }
+
+    /// `_`, a name, a constant, `[patterns]` or `{ name: pattern, name }`
+    fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let token = self.advance().clone();
+        let span = token.span;
+        let literal = |value| Ok(Pattern::Literal { value, span });
+        match &token.kind {
+            TokenKind::Identifier("_") => Ok(Pattern::Wildcard { span }),
+            TokenKind::Identifier(name) => {
+                let name = *name;
+                Ok(Pattern::Binding { name: self.intern(name), span })
+            }
+            TokenKind::Number(n) => literal(Literal::Number(*n)),
+            TokenKind::String(s) => literal(Literal::String(s.to_string())),
+            TokenKind::Overo => literal(Literal::Boolean(true)),
+            TokenKind::Sfols => literal(Literal::Boolean(false)),
+            TokenKind::Nisciun => literal(Literal::Null),
+            TokenKind::Boh => literal(Literal::Undefined),
+            TokenKind::Minus if matches!(self.peek().kind, TokenKind::Number(_)) => {
+                let TokenKind::Number(n) = self.advance().kind else { unreachable!("checked above") };
+                Ok(Pattern::Literal { value: Literal::Number(-n), span: self.span_from(span) })
+            }
+            TokenKind::LeftBracket => {
+                let mut elements = Vec::new();
+                loop {
+                    while self.check(&TokenKind::Newline) { self.advance(); }
+                    if self.check(&TokenKind::RightBracket) { break; }
+                    elements.push(self.nested(Self::parse_pattern)?);
+                    while self.check(&TokenKind::Newline) { self.advance(); }
+                    if !self.match_token(&TokenKind::Comma) { break; }
+                }
+                self.expect_closing(&TokenKind::RightBracket, span)?;
+                Ok(Pattern::Array { elements, span: self.span_from(span) })
+            }
+            TokenKind::LeftBrace => {
+                let mut properties = Vec::new();
+                loop {
+                    while self.check(&TokenKind::Newline) || self.check(&TokenKind::Comma) { self.advance(); }
+                    if self.check(&TokenKind::RightBrace) { break; }
+                    let key_span = self.current_span();
+                    let key = self.expect_identifier()?;
+                    let pattern = if self.match_token(&TokenKind::Colon) {
+                        self.nested(Self::parse_pattern)?
+                    } else {
+                        Pattern::Binding { name: key.clone(), span: key_span }
+                    };
+                    properties.push((key, pattern));
+                }
+                self.expect_closing(&TokenKind::RightBrace, span)?;
+                Ok(Pattern::Object { properties, span: self.span_from(span) })
+            }
+            other => {
+                let message = match self.locale {
+                    Locale::Napoletano => format!("Ccà ce vo' nu caso, no '{}': nu valore, nu nome, _, [...] o {{...}}", other),
+                    Locale::English => format!("Expected a pattern, not '{}': a value, a name, _, [...] or {{...}}", other),
+                };
+                Err(Box::new(Diagnostic::error("syntax-error", message, span)))
+            }
+        }
+    }
+
+    /// Parses a JSX element after its `<`, up to the `>` of its closing tag.
+    fn parse_jsx_element(&mut self, start: Span) -> Result<Expression, ParseError> {
+        let name = match self.peek().kind {
+            TokenKind::JsxName(name) => { self.advance(); Some(self.intern(name)) }
+            _ => None,
+        };
+        let mut attributes = Vec::new();
+        while let TokenKind::JsxName(attribute) = self.peek().kind {
+            self.advance();
+            let attribute = self.intern(attribute);
+            let value = if self.match_token(&TokenKind::Equal) { Some(self.parse_jsx_value()?) } else { None };
+            attributes.push((attribute, value));
+        }
+        if name.is_some() && self.match_token(&TokenKind::JsxSelfClosing) {
+            return Ok(Expression::JsxElement { name, attributes, children: Vec::new(), span: self.span_from(start) });
+        }
+        self.expect(&TokenKind::JsxTagEnd)?;
+        let mut children = Vec::new();
+        loop {
+            match self.peek().kind {
+                TokenKind::JsxText(text) => {
+                    self.advance();
+                    children.push(JsxChild::Text(text.to_string()));
+                }
+                TokenKind::LeftBrace => {
+                    let open = self.advance().span;
+                    // `{}` holds nothing, or just a comment
+                    if self.match_token(&TokenKind::RightBrace) { continue; }
+                    children.push(JsxChild::Expression(self.parse_expression()?));
+                    self.expect_closing(&TokenKind::RightBrace, open)?;
+                }
+                TokenKind::JsxTagStart => {
+                    let open = self.advance().span;
+                    children.push(JsxChild::Expression(self.nested(|parser| parser.parse_jsx_element(open))?));
+                }
+                _ => break,
+            }
+        }
+        let at = self.current_span();
+        let closed = self.match_token(&TokenKind::JsxClosingTagStart)
+            && match self.peek().kind {
+                TokenKind::JsxName(closing) => { self.advance(); name.as_deref() == Some(closing) }
+                _ => name.is_none(),
+            }
+            && self.match_token(&TokenKind::JsxTagEnd);
+        if !closed {
+            return Err(self.unclosed_jsx(name.as_deref().unwrap_or(""), start, at));
+        }
+        Ok(Expression::JsxElement { name, attributes, children, span: self.span_from(start) })
+    }
+
+    /// An attribute's value, after its `=`: `"testo"`, `{expression}` or an element
+    fn parse_jsx_value(&mut self) -> Result<Expression, ParseError> {
+        let token = self.advance();
+        let span = token.span;
+        match &token.kind {
+            TokenKind::String(value) => Ok(Expression::String { value: value.to_string(), span }),
+            TokenKind::LeftBrace => {
+                let value = self.parse_expression()?;
+                self.expect_closing(&TokenKind::RightBrace, span)?;
+                Ok(value)
             }
-            _ => Err(ParseError::new(format!("What is '{}' here? Expected an expression!", token.kind), span)),
+            TokenKind::JsxTagStart => self.nested(|parser| parser.parse_jsx_element(span)),
+            kind => Err(syntax_error(&format!("Expected a string or '{{', not '{}'", kind), span, self.locale)),
         }
     }
 
+    /// The `{` of an interpolation opened at `open` isn't closed where
+    /// `found` is, still inside the string
+    fn unclosed_interpolation(&self, open: Span, found: &TokenKind) -> ParseError {
+        let (message, label) = match self.locale {
+            Locale::Napoletano => (format!("'{{' dint'a stringa nun se chiude: ce vo' '}}' primma 'e '{}'", found), "'{' s'arape ccà"),
+            Locale::English => (format!("The '{{' in the string is never closed: add '}}' before '{}'", found), "'{' opened here"),
+        };
+        Box::new(Diagnostic::error("syntax-error", message, self.peek().span).with_label(open, label))
+    }
+
+    /// The rest of an interpolated string, after its `"text{`
+    fn parse_template(&mut self, head: String, start: Span) -> Result<Expression, ParseError> {
+        let (mut quasis, mut expressions, mut open) = (vec![head], Vec::new(), start);
+        loop {
+            expressions.push(self.parse_expression()?);
+            let token = self.peek();
+            match &token.kind {
+                TokenKind::TemplateMiddle(text) => {
+                    quasis.push(text.to_string());
+                    open = token.span;
+                }
+                TokenKind::TemplateTail(text) => {
+                    quasis.push(text.to_string());
+                    self.advance();
+                    break;
+                }
+                other => return Err(self.unclosed_interpolation(open, other)),
+            }
+            self.advance();
+        }
+        Ok(Expression::Template { quasis, expressions, span: self.span_from(start) })
+    }
+
+    /// A decorator before anything but a class or one of its methods
+    fn misplaced_decorator(&self, at: Span) -> ParseError {
+        let (message, suggestion) = match self.locale {
+            Locale::Napoletano => ("'@' va sulo primma 'e 'na famiglie o 'e nu metodo, e no d'o costruttore", "💡 Miette '@decoratore' 'ncopp'a 'na riga primma 'e 'na famiglie o 'e nu facc dint'a essa"),
+            Locale::English => ("'@' only goes before a class or one of its methods other than the constructor", "💡 Put '@decorator' on the line before 'na famiglie' or a 'facc' inside it"),
+        };
+        Box::new(Diagnostic::error("syntax-error", message, at).with_suggestion(suggestion))
+    }
+
+    /// A `pate(...)` that isn't the first statement of the constructor
+    fn misplaced_parent_call(&self, at: Span) -> ParseError {
+        let (message, suggestion) = match self.locale {
+            Locale::Napoletano => ("'pate(...)' adda essere 'a primma cosa d'o costruttore", "💡 Miette 'pate(...)' 'ncopp'a tutto, primma 'e usà 'stu cos'"),
+            Locale::English => ("'pate(...)' must be the first statement of the constructor", "💡 Move 'pate(...)' to the top, before 'stu cos' is used"),
+        };
+        Box::new(Diagnostic::error("syntax-error", message, at).with_suggestion(suggestion))
+    }
+
+    /// `-a ** b` and the like: the unary `operator` at `at` is the left side of the `**` at `power`
+    fn unary_before_power(&self, operator: &str, at: Span, power: Span) -> ParseError {
+        let (message, label, suggestion) = match self.locale {
+            Locale::Napoletano => (
+                format!("Nun se pò mettere '{}' annanz'a '**' senza parentesi", operator),
+                "'**' è ccà",
+                format!("💡 Scrive '({} x) ** y' o '{} (x ** y)', comme vuò dicere", operator, operator),
+            ),
+            Locale::English => (
+                format!("'{}' can't come right before '**' without parentheses", operator),
+                "'**' is here",
+                format!("💡 Write '({} x) ** y' or '{} (x ** y)', whichever you mean", operator, operator),
+            ),
+        };
+        Box::new(Diagnostic::error("syntax-error", message, at).with_label(power, label).with_suggestion(suggestion))
+    }
+
+    /// The element `<tag>` opened at `open` has no matching `</tag>` at `at`
+    fn unclosed_jsx(&self, tag: &str, open: Span, at: Span) -> ParseError {
+        let (message, label) = match self.locale {
+            Locale::Napoletano => (format!("'<{}>' nun se chiude: ce vo' '</{}>'", tag, tag), format!("'<{}>' s'arape ccà", tag)),
+            Locale::English => (format!("'<{}>' is never closed: add '</{}>'", tag, tag), format!("'<{}>' opened here", tag)),
+        };
+        Box::new(Diagnostic::error("syntax-error", message, at).with_label(open, label))
+    }
+
     // === Helpers ===
 
     fn is_at_end(&self) -> bool { self.peek().kind == TokenKind::Eof }
-    fn peek(&self) -> &Token { &self.tokens[self.current] }
-    fn previous(&self) -> &Token { &self.tokens[self.current - 1] }
-    fn advance(&mut self) -> &Token { if !self.is_at_end() { self.current += 1; } self.previous() }
+    fn token(&self, index: usize) -> Option<&Token<'a>> { index.checked_sub(self.window_start).and_then(|i| self.window.get(i)) }
+    fn peek(&self) -> &Token<'a> { self.token(self.current).expect("current token is buffered") }
+    fn previous(&self) -> &Token<'a> { self.token(self.current - 1).expect("previous token is buffered") }
+    fn advance(&mut self) -> &Token<'a> {
+        if !self.is_at_end() {
+            self.current += 1;
+            self.fill();
+        }
+        self.previous()
+    }
+    /// Reads from the stream until the token after the current one is buffered,
+    /// and drops tokens too far behind.
+    fn fill(&mut self) {
+        while !self.source_done && self.window_start + self.window.len() <= self.current + 1 {
+            match self.source.next() {
+                Some(token) if matches!(token.kind, TokenKind::Invalid(_)) => self.invalid.push(token),
+                Some(token) => {
+                    self.source_done = token.kind == TokenKind::Eof;
+                    self.window.push_back(token);
+                }
+                None => {
+                    let end = self.window.back().map_or(Span::default(), |t| Span::new(t.span.end, t.span.end, t.span.end_line, t.span.end_column));
+                    self.window.push_back(Token::new(TokenKind::Eof, end, ""));
+                    self.source_done = true;
+                }
+            }
+        }
+        while self.current - self.window_start > KEEP_BEHIND {
+            self.window.pop_front();
+            self.window_start += 1;
+        }
+    }
     fn check(&self, kind: &TokenKind) -> bool { std::mem::discriminant(&self.peek().kind) == std::mem::discriminant(kind) }
     fn check_next(&self, kind: &TokenKind) -> bool {
-        if self.current + 1 >= self.tokens.len() { return false; }
-        std::mem::discriminant(&self.tokens[self.current + 1].kind) == std::mem::discriminant(kind)
+        self.token(self.current + 1).is_some_and(|t| std::mem::discriminant(&t.kind) == std::mem::discriminant(kind))
     }
     fn match_token(&mut self, kind: &TokenKind) -> bool { if self.check(kind) { self.advance(); true } else { false } }
-    fn expect(&mut self, kind: &TokenKind) -> Result<&Token, ParseError> {
+    fn expect(&mut self, kind: &TokenKind) -> Result<&Token<'a>, ParseError> {
         if self.check(kind) { Ok(self.advance()) }
-        else { Err(ParseError::new(format!("Expected '{}', but found '{}'", kind, self.peek().kind), self.peek().span)) }
+        else { Err(syntax_error(&format!("Expected '{}', but found '{}'", kind, self.peek().kind), self.peek().span, self.locale)) }
     }
-    fn expect_identifier(&mut self) -> Result<String, ParseError> {
+    /// Like `expect`, for a closing delimiter: on failure the error also points at `open`.
+    /// A `}` missing at the end of the input comes with the fix adding it.
+    fn expect_closing(&mut self, kind: &TokenKind, open: Span) -> Result<&Token<'a>, ParseError> {
+        let locale = self.locale;
+        // On a line of its own, as the source ends with one or not
+        let fix = (*kind == TokenKind::RightBrace && self.is_at_end()).then(|| match self.previous().kind {
+            TokenKind::Newline => Fix::insert_after(self.peek().span, "}\n"),
+            _ => Fix::insert_after(self.peek().span, "\n}"),
+        });
+        self.expect(kind).map_err(|error| {
+            let opener = match kind { TokenKind::RightBrace => "{", TokenKind::RightBracket => "[", _ => "(" };
+            let label = match locale {
+                Locale::Napoletano => format!("'{}' s'arape ccà", opener),
+                Locale::English => format!("'{}' opened here", opener),
+            };
+            let error = error.with_label(open, label);
+            Box::new(match fix { Some(fix) => error.with_fix(fix), None => error })
+        })
+    }
+    fn expect_identifier(&mut self) -> Result<Name, ParseError> {
+        let locale = self.locale;
         let token = self.advance();
-        if let TokenKind::Identifier(name) = &token.kind { Ok(name.clone()) }
-        else { Err(ParseError::new(format!("Expected an identifier, not '{}'", token.kind), token.span)) }
+        if let TokenKind::Identifier(name) = token.kind { Ok(self.intern(name)) }
+        else { Err(syntax_error(&format!("Expected an identifier, not '{}'", token.kind), token.span, locale)) }
+    }
+    fn intern(&mut self, name: &str) -> Name {
+        let name = crate::lexer::nfc(name);
+        if let Some(interned) = self.names.get(name.as_ref()) { return interned.clone(); }
+        let interned = Name::from(name.as_ref());
+        self.names.insert(interned.clone());
+        interned
     }
     fn expect_string(&mut self) -> Result<String, ParseError> {
+        let locale = self.locale;
         let token = self.advance();
-        if let TokenKind::String(s) = &token.kind { Ok(s.clone()) }
-        else { Err(ParseError::new(format!("Expected a string, not '{}'", token.kind), token.span)) }
+        if let TokenKind::String(s) = &token.kind { Ok(s.to_string()) }
+        else { Err(syntax_error(&format!("Expected a string, not '{}'", token.kind), token.span, locale)) }
     }
-    // Expect the identifier "a" specifically (used in "stamm a dì", "avvis a dì", etc.)
-    fn expect_identifier_a(&mut self) -> Result<(), ParseError> {
+    // Expect a word that is not a keyword on its own: "a" in "stamm a dì", "quant" in "tutt' quant", etc.
+    fn expect_word(&mut self, word: &str) -> Result<(), ParseError> {
+        let locale = self.locale;
         let token = self.advance();
         if let TokenKind::Identifier(name) = &token.kind {
-            if name == "a" { return Ok(()); }
+            if *name == word { return Ok(()); }
+        }
+        Err(syntax_error(&format!("Expected '{}', but found '{}'", word, token.kind), token.span, locale))
+    }
+    /// Runs `parse` one nesting level deeper, failing past `max_depth`.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        self.chained(|parser| {
+            parser.deeper()?;
+            parse(parser)
+        })
+    }
+    /// Runs `parse`, which goes [`deeper`](Self::deeper) for each link of
+    /// the chain it builds, and comes back to the depth it started at
+    fn chained<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        let depth = self.depth;
+        let result = parse(self);
+        self.depth = depth;
+        result
+    }
+    /// One level deeper, failing past `max_depth`
+    fn deeper(&mut self) -> Result<(), ParseError> {
+        if self.depth >= self.options.max_depth {
+            return Err(syntax_error("Too deeply nested", self.peek().span, self.locale));
         }
-        Err(ParseError::new(format!("Expected 'a', but found '{}'", token.kind), token.span))
+        self.depth += 1;
+        Ok(())
     }
     fn current_span(&self) -> Span { self.peek().span }
-    fn span_from(&self, start: usize) -> Span { Span::new(start, self.previous().span.end, self.previous().span.line, 0) }
+    fn span_from(&self, start: Span) -> Span {
+        // Nothing consumed since `start` (an expression left a stray delimiter alone)
+        match self.current.checked_sub(1).and_then(|i| self.token(i)) {
+            Some(last) if last.span.end >= start.end => start.to(&last.span),
+            _ => start,
+        }
+    }
+    /// Skips the rest of a broken statement inside a block, stopping at the
+    /// next line or at the `}` that closes the block.
+    fn synchronize_in_block(&mut self) {
+        let mut depth = 0usize;
+        while !self.is_at_end() {
+            match self.peek().kind {
+                TokenKind::Newline if depth == 0 => return,
+                TokenKind::RightBrace if depth == 0 => return,
+                TokenKind::RightBrace => depth -= 1,
+                TokenKind::LeftBrace => depth += 1,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
     fn synchronize(&mut self) {
         self.advance();
         while !self.is_at_end() {
-            if matches!(self.peek().kind, TokenKind::Chist | TokenKind::Tien | TokenKind::Facc | TokenKind::Si | TokenKind::Mentre | TokenKind::Pe | TokenKind::Piglie | TokenKind::Na | TokenKind::Chiamm | TokenKind::Mann) { return; }
+            if matches!(self.peek().kind, TokenKind::Chist | TokenKind::Tien | TokenKind::Facc | TokenKind::Si | TokenKind::Mentre | TokenKind::Giura | TokenKind::Hash | TokenKind::At | TokenKind::Pe | TokenKind::Piglie | TokenKind::Na | TokenKind::Chiamm | TokenKind::Mann) { return; }
             self.advance();
         }
     }
 }
+
+/// The operator of a unary expression, which can't be the left side of `**`
+fn unary_keyword(expr: &Expression) -> Option<&'static str> {
+    match expr {
+        Expression::Unary { operator: UnaryOp::Negate, .. } => Some("-"),
+        Expression::Unary { operator: UnaryOp::Not, .. } => Some("no"),
+        Expression::Await { .. } => Some("aspett"),
+        Expression::Delete { .. } => Some("leva"),
+        Expression::TypeOf { .. } => Some("chè è"),
+        _ => None,
+    }
+}
+
+/// The binary operator `kind` stands for, if any. `o` followed by `sinnò`
+/// is [`BinaryOp::Nullish`], which binds like `o`.
+fn binary_operator(kind: &TokenKind) -> Option<BinaryOp> {
+    Some(match kind {
+        TokenKind::Or => BinaryOp::Or,
+        // "e" and "pure"
+        TokenKind::And | TokenKind::Pure => BinaryOp::And,
+        TokenKind::EqualEqualEqual => BinaryOp::StrictEqual,
+        TokenKind::EqualEqual => BinaryOp::Equal,
+        TokenKind::BangEqualEqual => BinaryOp::StrictNotEqual,
+        TokenKind::BangEqual => BinaryOp::NotEqual,
+        TokenKind::Less => BinaryOp::LessThan,
+        TokenKind::LessEqual => BinaryOp::LessEqual,
+        TokenKind::Greater => BinaryOp::GreaterThan,
+        TokenKind::GreaterEqual => BinaryOp::GreaterEqual,
+        TokenKind::Plus => BinaryOp::Add,
+        TokenKind::Minus => BinaryOp::Subtract,
+        TokenKind::Star => BinaryOp::Multiply,
+        TokenKind::Slash => BinaryOp::Divide,
+        TokenKind::Percent => BinaryOp::Modulo,
+        TokenKind::StarStar => BinaryOp::Power,
+        _ => return None,
+    })
+}
+
+fn syntax_error(message: &str, span: Span, locale: Locale) -> ParseError {
+    Box::new(Diagnostic::syntax(message, span, locale))
+}
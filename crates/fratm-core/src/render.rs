@@ -0,0 +1,134 @@
+//! Code frames for diagnostics
+//!
+//! [`render_diagnostic`] draws a [`Diagnostic`] the way the CLI shows it:
+//! the message, the source lines involved with the error underlined (`^`)
+//! and its labels marked (`-`), then notes and the suggestion.
+//! [`render_code_frame`] draws just the source lines.
+//!
+//! ```rust
+//! use fratm_core::render::{render_diagnostic, RenderOptions};
+//!
+//! let source = "tien x = [1, 2";
+//! let errors = fratm_core::parse_source(source).unwrap_err();
+//! let frame = render_diagnostic(source, &errors[0], RenderOptions::default());
+//! assert!(frame.contains("1 │ tien x = [1, 2"));
+//! assert!(frame.contains("- '[' s'arape ccà"));
+//! ```
+
+use crate::errors::{Diagnostic, Locale, Severity};
+use crate::lexer::Span;
+
+/// How to render a diagnostic.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderOptions {
+    /// Use ANSI colors (for terminals). Off gives plain text, e.g. for the playground.
+    pub color: bool,
+    /// Language of the position line (`Riga 1, colonna 7` or `Line 1, column 7`).
+    pub locale: Locale,
+}
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders `diagnostic`, reported on `source`, as a multi-line string ending with a newline.
+pub fn render_diagnostic(source: &str, diagnostic: &Diagnostic, options: RenderOptions) -> String {
+    let (title, style) = match diagnostic.severity {
+        Severity::Error => ("✗ Error:", RED),
+        Severity::Warning => ("⚠ Warning:", YELLOW),
+    };
+
+    let mut out = format!("{} {}\n", paint(title, style, options), diagnostic.localized(options.locale));
+    out.push_str(&render_code_frame(source, diagnostic, options));
+    for note in &diagnostic.notes {
+        out.push_str(&format!("  {} {}\n", paint("=", DIM, options), note));
+    }
+    if let Some(suggestion) = &diagnostic.suggestion {
+        out.push_str(&format!("\n{}\n", paint(suggestion, YELLOW, options)));
+    }
+    out
+}
+
+/// Renders only the source lines of `diagnostic` with their markers, for
+/// callers that show the message and the suggestion themselves.
+pub fn render_code_frame(source: &str, diagnostic: &Diagnostic, options: RenderOptions) -> String {
+    let style = if diagnostic.is_error() { RED } else { YELLOW };
+    // `lines` handles `\r\n`; what is left of `\r` are lone line breaks
+    let lines: Vec<&str> = source.lines().flat_map(|line| line.split('\r')).collect();
+    let mut marks: Vec<(Span, &str, bool)> = vec![(diagnostic.span, "", true)];
+    marks.extend(diagnostic.labels.iter().map(|label| (label.span, label.message.as_str(), false)));
+    marks.sort_by_key(|(span, _, _)| span.column);
+
+    let mut line_nums: Vec<usize> = marks.iter().map(|(span, _, _)| span.line).filter(|&l| l > 0).collect();
+    line_nums.sort_unstable();
+    line_nums.dedup();
+    let gutter = line_nums.last().map_or(1, |l| l.to_string().len());
+
+    let mut out = String::new();
+    let mut previous: Option<usize> = None;
+    for line_num in line_nums {
+        if previous.is_some_and(|p| line_num > p + 1) {
+            out.push_str(&format!("  {} │\n", paint(&"…".repeat(gutter), DIM, options)));
+        }
+        // An error at EOF sits on the line after the last one
+        let text = lines.get(line_num - 1).copied().unwrap_or("");
+        out.push_str(&format!("  {} │ {}\n", paint(&format!("{:>gutter$}", line_num), DIM, options), text));
+
+        for (span, message, primary) in marks.iter().filter(|(span, _, _)| span.line == line_num) {
+            // Keep tabs so the marker lines up with the source
+            let indent: String = text.chars().chain(std::iter::repeat(' '))
+                .take(span.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let width = if span.end_line == span.line { span.end_column.saturating_sub(span.column).max(1) } else { 1 };
+            let marker = if *primary { "^" } else { "-" }.repeat(width);
+            let underline = format!("{} {}", marker, message);
+            let underline = paint(underline.trim_end(), if *primary { style } else { CYAN }, options);
+            out.push_str(&format!("  {} │ {}{}\n", " ".repeat(gutter), indent, underline));
+        }
+        previous = Some(line_num);
+    }
+    out
+}
+
+fn paint(text: &str, style: &str, options: RenderOptions) -> String {
+    if options.color { format!("{}{}{}", style, text, RESET) } else { text.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_error(source: &str) -> Diagnostic {
+        crate::parse_source(source).unwrap_err().remove(0)
+    }
+
+    #[test]
+    #[cfg(feature = "suggestions")]
+    fn test_plain_frame() {
+        let source = "tien x = 1\nchist y = 2";
+        let frame = render_diagnostic(source, &first_error(source), RenderOptions::default());
+        let expected = "  2 │ chist y = 2\n    │       ^\n";
+        assert!(frame.starts_with("✗ Error: Riga 2, colonna 7:"));
+        assert!(frame.contains(expected), "{}", frame);
+        assert!(frame.ends_with("💡 Esempio: chist è nome = \"Gennaro\"\n"));
+        assert!(!frame.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_labels_on_other_lines() {
+        let source = "facc f() {\n\tsi (overo) {\n\t\tstamm a dì(1)\n\t}\n";
+        let frame = render_diagnostic(source, &first_error(source), RenderOptions::default());
+        assert!(frame.contains("  1 │ facc f() {\n    │          - '{' s'arape ccà\n  … │\n  5 │ \n    │ ^\n"), "{}", frame);
+    }
+
+    #[test]
+    fn test_colors_and_tabs() {
+        let source = "\tchist y = 2";
+        let frame = render_diagnostic(source, &first_error(source), RenderOptions { color: true, ..Default::default() });
+        assert!(frame.contains("\x1b[1;31m✗ Error:\x1b[0m"));
+        assert!(frame.contains("│ \t      \x1b[1;31m^\x1b[0m"), "{:?}", frame);
+    }
+}
@@ -0,0 +1,761 @@
+//! Scope analysis
+//!
+//! [`analyze`] builds a [`ScopeTree`]: the scopes of a program, the names
+//! declared in each, every identifier reference resolved to the declaration
+//! it reads or writes, and what the module exports. Names not declared
+//! anywhere (`console`, `Math`, other globals) are references without a
+//! declaration.
+//!
+//! Scopes follow the JavaScript the program compiles to: `chist è`/`tien`
+//! are block scoped, and the declarations of a block are visible in the
+//! whole block, so a function can call one declared further down.
+//!
+//! ```rust
+//! use fratm_core::semantic::{analyze, DeclarationKind};
+//!
+//! let program = fratm_core::parse_source("tien x = 1\nfacc f(y) { piglie x + y }").unwrap();
+//! let tree = analyze(&program);
+//! let x = tree.lookup(tree.root(), "x").unwrap();
+//! assert_eq!(tree.declaration(x).kind, DeclarationKind::Let);
+//! assert_eq!(tree.references_to(x).count(), 1);
+//! ```
+//!
+//! ## References and Renaming
+//!
+//! [`find_references`] and [`rename`] work from a position in the source,
+//! for editors' highlight and rename commands:
+//!
+//! ```rust
+//! use fratm_core::semantic::rename;
+//!
+//! let source = "tien x = 1\nstamm a dì(x + 1)";
+//! assert_eq!(rename(source, 5, "conto").unwrap(), "tien conto = 1\nstamm a dì(conto + 1)");
+//! assert!(rename(source, 5, "tien").is_err());
+//! ```
+//!
+//! ## Outline
+//!
+//! [`document_symbols`] lists the functions, classes and top-level
+//! variables of a program as a tree, for an editor's outline view:
+//!
+//! ```rust
+//! use fratm_core::semantic::{document_symbols, SymbolKind};
+//!
+//! let program = fratm_core::parse_source("na famiglie Pizza {\n  facc cuoci() {}\n}").unwrap();
+//! let symbols = document_symbols(&program);
+//! assert_eq!(symbols[0].kind, SymbolKind::Class);
+//! assert_eq!(&*symbols[0].children[0].name, "cuoci");
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::errors::{CompileError, Diagnostic};
+use crate::lexer::keywords::KEYWORDS;
+use crate::lexer::{Lexer, Span, Token, TokenKind};
+use crate::parser::{jsx_component, ArrowBody, Expression, JsxChild, Name, Program, Statement};
+
+/// Index of a scope in [`ScopeTree::scopes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ScopeId(pub usize);
+
+/// Index of a declaration in [`ScopeTree::declarations`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DeclarationId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ScopeKind {
+    /// The top level of the file
+    Program,
+    /// Parameters and body of a function, method or arrow function
+    Function,
+    /// `{ }` of `si`, loops, `pruvamm` and plain blocks
+    Block,
+    /// A `pe` loop, holding the variable declared in its header
+    For,
+    /// `e si schiatta`, holding the error parameter
+    Catch,
+    /// An arm of `vir buono`, holding the names its pattern binds
+    Match,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Scope {
+    pub kind: ScopeKind,
+    /// `None` for the program scope
+    pub parent: Option<ScopeId>,
+    pub span: Span,
+    pub declarations: Vec<DeclarationId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum DeclarationKind {
+    Const,
+    Let,
+    Function,
+    Class,
+    Parameter,
+    Import,
+    /// The error of `e si schiatta (err)`
+    Catch,
+    /// A name in a pattern of `vir buono`
+    Binding,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Declaration {
+    pub name: Name,
+    pub kind: DeclarationKind,
+    /// The declaring statement; for parameters, the whole function
+    pub span: Span,
+    pub scope: ScopeId,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Reference {
+    pub name: Name,
+    pub span: Span,
+    /// Scope the reference appears in
+    pub scope: ScopeId,
+    /// `None` for globals
+    pub declaration: Option<DeclarationId>,
+    /// Assigned to (`x = ...`) rather than read
+    pub write: bool,
+}
+
+/// Something the module exports with `mann for`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Export {
+    /// Exported name, `default` for `mann for predefinit`
+    pub name: Name,
+    /// The exported declaration, if the export names one
+    pub declaration: Option<DeclarationId>,
+    pub span: Span,
+}
+
+/// The result of [`analyze`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ScopeTree {
+    /// Every scope, the program scope first
+    pub scopes: Vec<Scope>,
+    pub declarations: Vec<Declaration>,
+    /// Identifier references, in source order
+    pub references: Vec<Reference>,
+    pub exports: Vec<Export>,
+}
+
+impl ScopeTree {
+    /// The program scope.
+    pub fn root(&self) -> ScopeId {
+        ScopeId(0)
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.0]
+    }
+
+    pub fn declaration(&self, id: DeclarationId) -> &Declaration {
+        &self.declarations[id.0]
+    }
+
+    /// The declaration `name` refers to in `scope`, looking through the enclosing scopes.
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<DeclarationId> {
+        let mut scope = Some(scope);
+        while let Some(id) = scope {
+            let found = self.scope(id).declarations.iter().find(|decl| &*self.declaration(**decl).name == name);
+            if found.is_some() {
+                return found.copied();
+            }
+            scope = self.scope(id).parent;
+        }
+        None
+    }
+
+    /// References that resolve to `declaration`.
+    pub fn references_to(&self, declaration: DeclarationId) -> impl Iterator<Item = &Reference> {
+        self.references.iter().filter(move |reference| reference.declaration == Some(declaration))
+    }
+
+    /// References to names declared nowhere in the program.
+    pub fn globals(&self) -> impl Iterator<Item = &Reference> {
+        self.references.iter().filter(|reference| reference.declaration.is_none())
+    }
+}
+
+/// Builds the scope tree of `program`.
+pub fn analyze(program: &Program) -> ScopeTree {
+    let span = match (program.statements.first(), program.statements.last()) {
+        (Some(first), Some(last)) => first.span().to(&last.span()),
+        _ => Span::default(),
+    };
+    let mut analyzer = Analyzer {
+        tree: ScopeTree { scopes: Vec::new(), declarations: Vec::new(), references: Vec::new(), exports: Vec::new() },
+        current: ScopeId(0),
+    };
+    analyzer.push_scope(ScopeKind::Program, span);
+    analyzer.statements(&program.statements);
+    analyzer.tree
+}
+
+struct Analyzer {
+    tree: ScopeTree,
+    current: ScopeId,
+}
+
+impl Analyzer {
+    fn push_scope(&mut self, kind: ScopeKind, span: Span) {
+        let parent = (!self.tree.scopes.is_empty()).then_some(self.current);
+        self.current = ScopeId(self.tree.scopes.len());
+        self.tree.scopes.push(Scope { kind, parent, span, declarations: Vec::new() });
+    }
+
+    fn pop_scope(&mut self) {
+        self.current = self.tree.scope(self.current).parent.unwrap_or(ScopeId(0));
+    }
+
+    fn declare(&mut self, name: &Name, kind: DeclarationKind, span: Span) -> DeclarationId {
+        let id = DeclarationId(self.tree.declarations.len());
+        self.tree.declarations.push(Declaration { name: name.clone(), kind, span, scope: self.current });
+        self.tree.scopes[self.current.0].declarations.push(id);
+        id
+    }
+
+    fn reference(&mut self, name: &Name, span: Span, write: bool) {
+        let declaration = self.tree.lookup(self.current, name);
+        self.tree.references.push(Reference { name: name.clone(), span, scope: self.current, declaration, write });
+    }
+
+    /// Declares what `statements` declare, then walks them, so that names
+    /// can be used anywhere in the block
+    fn statements(&mut self, statements: &[Statement]) {
+        for stmt in statements {
+            self.hoist(stmt);
+        }
+        for stmt in statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement], span: Span) {
+        self.push_scope(ScopeKind::Block, span);
+        self.statements(statements);
+        self.pop_scope();
+    }
+
+    fn hoist(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::VariableDecl { name, is_const, span, .. } => {
+                let kind = if *is_const { DeclarationKind::Const } else { DeclarationKind::Let };
+                self.declare(name, kind, *span);
+            }
+            Statement::FunctionDecl { name, span, .. } => {
+                self.declare(name, DeclarationKind::Function, *span);
+            }
+            Statement::ClassDecl { name, span, .. } => {
+                self.declare(name, DeclarationKind::Class, *span);
+            }
+            Statement::Import { specifiers, span, .. } => {
+                for specifier in specifiers {
+                    self.declare(&specifier.local, DeclarationKind::Import, *span);
+                }
+            }
+            Statement::Export { declaration: Some(declaration), .. } => self.hoist(declaration),
+            // Either branch may be compiled, in the scope around it
+            Statement::Conditional { then_branch, else_branch, .. } => {
+                for stmt in then_branch.iter().chain(else_branch.iter().flatten()) {
+                    self.hoist(stmt);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn function(&mut self, params: &[Name], body: &[Statement], span: Span) {
+        self.push_scope(ScopeKind::Function, span);
+        for param in params {
+            self.declare(param, DeclarationKind::Parameter, span);
+        }
+        self.statements(body);
+        self.pop_scope();
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::VariableDecl { value, .. } => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            Statement::FunctionDecl { params, body, span, .. } => self.function(params, body, *span),
+            Statement::ClassDecl { decorators, superclass, methods, .. } => {
+                for decorator in decorators { self.expression(decorator); }
+                if let Some(superclass) = superclass { self.expression(superclass); }
+                for method in methods {
+                    // Method names are properties, not declarations
+                    if let Statement::FunctionDecl { params, body, decorators, span, .. } = method {
+                        for decorator in decorators { self.expression(decorator); }
+                        self.function(params, body, *span);
+                    }
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            Statement::If { condition, then_branch, else_branch, span } => {
+                self.expression(condition);
+                self.block(then_branch, *span);
+                if let Some(else_branch) = else_branch {
+                    self.block(else_branch, *span);
+                }
+            }
+            Statement::While { condition, body, span } => {
+                self.expression(condition);
+                self.block(body, *span);
+            }
+            Statement::For { init, condition, update, body, span } => {
+                self.push_scope(ScopeKind::For, *span);
+                if let Some(init) = init {
+                    self.hoist(init);
+                    self.statement(init);
+                }
+                if let Some(condition) = condition {
+                    self.expression(condition);
+                }
+                if let Some(update) = update {
+                    self.expression(update);
+                }
+                self.block(body, *span);
+                self.pop_scope();
+            }
+            Statement::TryCatch { try_body, catch_param, catch_type, catch_body, span } => {
+                self.block(try_body, *span);
+                if let Some(catch_type) = catch_type {
+                    self.expression(catch_type);
+                }
+                self.push_scope(ScopeKind::Catch, *span);
+                if let Some(param) = catch_param {
+                    self.declare(param, DeclarationKind::Catch, *span);
+                }
+                self.statements(catch_body);
+                self.pop_scope();
+            }
+            Statement::Throw { value, .. } | Statement::Expression { expression: value, .. } => self.expression(value),
+            Statement::Assert { condition, message, .. } => {
+                self.expression(condition);
+                if let Some(message) = message {
+                    self.expression(message);
+                }
+            }
+            Statement::Export { declaration, default_value, span } => {
+                if let Some(declaration) = declaration {
+                    self.statement(declaration);
+                    if let Statement::VariableDecl { name, .. } | Statement::FunctionDecl { name, .. } | Statement::ClassDecl { name, .. } = declaration.as_ref() {
+                        let id = self.tree.lookup(self.current, name);
+                        self.tree.exports.push(Export { name: name.clone(), declaration: id, span: *span });
+                    }
+                }
+                if let Some(value) = default_value {
+                    self.expression(value);
+                    let declaration = match value {
+                        Expression::Identifier { name, .. } => self.tree.lookup(self.current, name),
+                        _ => None,
+                    };
+                    self.tree.exports.push(Export { name: "default".into(), declaration, span: *span });
+                }
+            }
+            Statement::Block { statements, span } => self.block(statements, *span),
+            Statement::Conditional { then_branch, else_branch, .. } => {
+                for stmt in then_branch.iter().chain(else_branch.iter().flatten()) {
+                    self.statement(stmt);
+                }
+            }
+            Statement::Break { .. } | Statement::Continue { .. } | Statement::Debugger { .. }
+            | Statement::Import { .. } | Statement::Error { .. } => {}
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Identifier { name, span } => self.reference(name, *span, false),
+            Expression::Assignment { target, value, .. } => {
+                match target.as_ref() {
+                    Expression::Identifier { name, span } => self.reference(name, *span, true),
+                    target => self.expression(target),
+                }
+                self.expression(value);
+            }
+            Expression::Array { elements: items, .. }
+            | Expression::Template { expressions: items, .. }
+            | Expression::ConsoleLog { arguments: items, .. }
+            | Expression::SuperCall { arguments: items, .. }
+            | Expression::ConsoleWarn { arguments: items, .. }
+            | Expression::ConsoleError { arguments: items, .. } => {
+                for item in items {
+                    self.expression(item);
+                }
+            }
+            Expression::Object { properties, .. } => {
+                for (_, value) in properties {
+                    self.expression(value);
+                }
+            }
+            Expression::Binary { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::Unary { operand, .. } | Expression::Await { argument: operand, .. }
+            | Expression::TypeOf { operand, .. } | Expression::Delete { operand, .. } => self.expression(operand),
+            Expression::Call { callee, arguments, .. } | Expression::New { callee, arguments, .. } => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            Expression::Member { object, property, computed, .. } => {
+                self.expression(object);
+                // `a.b` names a property, not a variable
+                if *computed {
+                    self.expression(property);
+                }
+            }
+            Expression::ArrowFunction { params, body, span } => match body {
+                ArrowBody::Block(body) => self.function(params, body, *span),
+                ArrowBody::Expression(body) => {
+                    self.push_scope(ScopeKind::Function, *span);
+                    for param in params {
+                        self.declare(param, DeclarationKind::Parameter, *span);
+                    }
+                    self.expression(body);
+                    self.pop_scope();
+                }
+            },
+            Expression::Ternary { condition, consequent, alternate, .. } => {
+                self.expression(condition);
+                self.expression(consequent);
+                self.expression(alternate);
+            }
+            Expression::JsxElement { name, attributes, children, span } => {
+                if let Some(component) = name.as_deref().and_then(jsx_component) {
+                    // The tag comes right after the `<`
+                    let column = span.column + 1;
+                    let tag = Span::new(span.start + 1, span.start + 1 + component.len(), span.line, column).with_end(span.line, column + component.chars().count());
+                    self.reference(&Name::from(component), tag, false);
+                }
+                for value in attributes.iter().filter_map(|(_, value)| value.as_ref()) {
+                    self.expression(value);
+                }
+                for child in children {
+                    if let JsxChild::Expression(child) = child {
+                        self.expression(child);
+                    }
+                }
+            }
+            Expression::Match { subject, arms, .. } => {
+                self.expression(subject);
+                for arm in arms {
+                    self.push_scope(ScopeKind::Match, arm.span);
+                    for (name, span) in arm.pattern.bindings() {
+                        self.declare(name, DeclarationKind::Binding, span);
+                    }
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    self.expression(&arm.body);
+                    self.pop_scope();
+                }
+            }
+            Expression::Number { .. } | Expression::String { .. } | Expression::Boolean { .. }
+            | Expression::Null { .. } | Expression::Undefined { .. } | Expression::This { .. }
+            | Expression::Console { .. } | Expression::Promise { .. } | Expression::Error { .. } => {}
+        }
+    }
+}
+
+/// Spans of the name at byte `offset` of `source`: its declaration first,
+/// then every reference, in source order. Empty if there is no declared
+/// name at `offset`.
+///
+/// `program` must be parsed from `source`, which is needed since the AST
+/// only keeps the span of a whole declaration, not of the name in it.
+pub fn find_references(program: &Program, source: &str, offset: usize) -> Vec<Span> {
+    let tree = analyze(program);
+    let names = name_spans(&tree, &Lexer::new(source).tokenize());
+    symbol_at(&tree, &names, offset).map_or_else(Vec::new, |declaration| occurrences(&tree, &names, declaration))
+}
+
+/// Renames the name at byte `offset` of `source` to `new_name`, with
+/// every reference to it, and returns the edited source.
+///
+/// Fails if the source doesn't parse, if there is no declared name at
+/// `offset`, if `new_name` isn't a valid name, or if it would change what
+/// another name refers to.
+pub fn rename(source: &str, offset: usize, new_name: &str) -> Result<String, CompileError> {
+    let (program, _) = crate::parse_program(source)?;
+    let tree = analyze(&program);
+    let names = name_spans(&tree, &Lexer::new(source).tokenize());
+    let Some(id) = symbol_at(&tree, &names, offset) else {
+        return Err(Diagnostic::error("rename", "Ccà nun ce sta nisciun nome da cagnà", point(source, offset)).into());
+    };
+    let declaration = tree.declaration(id);
+    let span = names[id.0].unwrap_or(declaration.span);
+    let conflict = |message: String, span: Span| Err(Diagnostic::error("rename-conflict", message, span).into());
+
+    match Lexer::new(new_name).map(|token| token.kind).collect::<Vec<_>>().as_slice() {
+        [TokenKind::Identifier(_), TokenKind::Eof] => {}
+        [kind, TokenKind::Eof] if KEYWORDS.iter().any(|keyword| keyword.kind == *kind) => return Err(Diagnostic::error("rename", format!("'{}' è 'na parola chiave, nun se pò usà comme nome", new_name), span).into()),
+        _ => return Err(Diagnostic::error("rename", format!("'{}' nun è nu nome valido", new_name), span).into()),
+    }
+    if *declaration.name == *new_name {
+        return Ok(source.to_string());
+    }
+    if declaration.kind == DeclarationKind::Import {
+        return conflict(format!("'{}' vene 'a nu modulo: cagnal addò è esportat", declaration.name), span);
+    }
+    // Another declaration in the same scope
+    if let Some(other) = tree.scope(declaration.scope).declarations.iter().find(|other| *tree.declaration(**other).name == *new_name) {
+        return conflict(format!("'{}' già sta dichiarat ccà", new_name), names[other.0].unwrap_or(span));
+    }
+    // A reference that would see another `new_name` declared closer to it
+    for reference in tree.references_to(id) {
+        if tree.lookup(reference.scope, new_name).is_some_and(|other| is_inside(&tree, tree.declaration(other).scope, declaration.scope)) {
+            return conflict(format!("Ccà '{}' è n'ata cosa: '{}' nun se vedarria cchiù", new_name, declaration.name), reference.span);
+        }
+    }
+    // A reference to another `new_name` that would now see the renamed declaration
+    for reference in tree.references.iter().filter(|reference| *reference.name == *new_name) {
+        if tree.lookup(reference.scope, &declaration.name) == Some(id) {
+            return conflict(format!("'{}' s'usa già ccà e se cunfunnarria cu '{}'", new_name, declaration.name), reference.span);
+        }
+    }
+
+    let mut edited = source.to_string();
+    for span in occurrences(&tree, &names, id).iter().rev() {
+        edited.replace_range(span.start..span.end, new_name);
+    }
+    Ok(edited)
+}
+
+/// The declaration whose name or reference is at `offset`
+fn symbol_at(tree: &ScopeTree, names: &[Option<Span>], offset: usize) -> Option<DeclarationId> {
+    let covers = |span: &Span| span.start <= offset && offset <= span.end;
+    tree.references.iter().find(|reference| covers(&reference.span)).and_then(|reference| reference.declaration)
+        .or_else(|| names.iter().position(|span| span.as_ref().is_some_and(covers)).map(DeclarationId))
+}
+
+fn occurrences(tree: &ScopeTree, names: &[Option<Span>], declaration: DeclarationId) -> Vec<Span> {
+    let references = tree.references_to(declaration).map(|reference| reference.span);
+    let mut spans: Vec<Span> = names[declaration.0].into_iter().chain(references).collect();
+    spans.sort_by_key(|span| span.start);
+    spans
+}
+
+/// Whether `scope` is `ancestor` or nested in it
+fn is_inside(tree: &ScopeTree, scope: ScopeId, ancestor: ScopeId) -> bool {
+    let mut scope = Some(scope);
+    while let Some(id) = scope {
+        if id == ancestor {
+            return true;
+        }
+        scope = tree.scope(id).parent;
+    }
+    false
+}
+
+/// Where each declaration's name is written, found among the tokens of
+/// the declaring statement: the first matching identifier that is neither
+/// a reference nor a property name. Parameters come after the `(` (or are
+/// the whole head of `x => ...`), a catch parameter after `schiatta`.
+fn name_spans(tree: &ScopeTree, tokens: &[Token]) -> Vec<Option<Span>> {
+    let references: std::collections::HashSet<usize> = tree.references.iter().map(|reference| reference.span.start).collect();
+    tree.declarations.iter().map(|declaration| {
+        let span = declaration.span;
+        let first = tokens.partition_point(|token| token.span.start < span.start);
+        let last = tokens.partition_point(|token| token.span.start < span.end);
+        let statement = &tokens[first..last];
+        let start = match declaration.kind {
+            DeclarationKind::Parameter if !matches!(statement.first().map(|t| &t.kind), Some(TokenKind::Identifier(_))) => {
+                statement.iter().position(|t| t.kind == TokenKind::LeftParen)? + 1
+            }
+            DeclarationKind::Catch => statement.iter().position(|t| t.kind == TokenKind::Schiatta)? + 1,
+            _ => 0,
+        };
+        (start..statement.len()).find(|&i| {
+            let token = &statement[i];
+            let previous = i.checked_sub(1).map(|i| &statement[i].kind);
+            let is_key = matches!(previous, Some(TokenKind::LeftBrace | TokenKind::Comma)) && statement.get(i + 1).is_some_and(|t| t.kind == TokenKind::Colon);
+            token.kind == TokenKind::Identifier(&declaration.name)
+                && !references.contains(&token.span.start)
+                && previous != Some(&TokenKind::Dot)
+                && !is_key
+        }).map(|i| statement[i].span)
+    }).collect()
+}
+
+/// An empty span at byte `offset`, for errors that have nothing else to point at
+fn point(source: &str, offset: usize) -> Span {
+    let offset = offset.min(source.len());
+    let before = source.get(..offset).unwrap_or(source);
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count() + 1;
+    let column = before[line_start..].chars().count() + 1;
+    Span::new(offset, offset, line, column)
+}
+
+/// What a [`DocumentSymbol`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum SymbolKind {
+    Function,
+    Class,
+    Method,
+    Variable,
+    Constant,
+}
+
+/// An entry of the outline returned by [`document_symbols`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DocumentSymbol {
+    pub name: Name,
+    pub kind: SymbolKind,
+    /// The whole declaration
+    pub span: Span,
+    /// Methods of a class, functions and classes declared in a function
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// The outline of `program`: its functions and classes, with their methods
+/// and nested functions, and its top-level variables. Exported
+/// declarations are listed as if they weren't exported.
+///
+/// Broken statements are skipped, so the program can come from
+/// [`parse_recovering`](crate::parse_recovering) while the user types.
+pub fn document_symbols(program: &Program) -> Vec<DocumentSymbol> {
+    symbols(&program.statements, true)
+}
+
+fn symbols(statements: &[Statement], top_level: bool) -> Vec<DocumentSymbol> {
+    statements.iter().filter_map(|stmt| symbol(stmt, top_level)).collect()
+}
+
+fn symbol(stmt: &Statement, top_level: bool) -> Option<DocumentSymbol> {
+    let (name, kind, children) = match stmt {
+        Statement::VariableDecl { name, is_const, .. } if top_level => {
+            (name, if *is_const { SymbolKind::Constant } else { SymbolKind::Variable }, vec![])
+        }
+        Statement::FunctionDecl { name, body, .. } => (name, SymbolKind::Function, symbols(body, false)),
+        Statement::ClassDecl { name, methods, .. } => {
+            let methods = methods.iter().filter_map(|method| match method {
+                Statement::FunctionDecl { name, body, span, .. } => {
+                    Some(DocumentSymbol { name: name.clone(), kind: SymbolKind::Method, span: *span, children: symbols(body, false) })
+                }
+                _ => None,
+            });
+            (name, SymbolKind::Class, methods.collect())
+        }
+        Statement::Export { declaration: Some(declaration), .. } => return symbol(declaration, top_level),
+        _ => return None,
+    };
+    Some(DocumentSymbol { name: name.clone(), kind, span: stmt.span(), children })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(source: &str) -> ScopeTree {
+        analyze(&crate::parse_source(source).unwrap())
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let tree = tree("tien x = 1\nfacc f(x) {\n  piglie x\n}\nstamm a dì(x)");
+        let outer = tree.lookup(tree.root(), "x").unwrap();
+        let [inner_read, outer_read] = [&tree.references[0], &tree.references[1]];
+        assert_eq!(tree.declaration(inner_read.declaration.unwrap()).kind, DeclarationKind::Parameter);
+        assert_eq!(outer_read.declaration, Some(outer));
+        assert_eq!(tree.scope(inner_read.scope).kind, ScopeKind::Function);
+    }
+
+    #[test]
+    fn test_hoisting_and_globals() {
+        let tree = tree("facc f() { piglie g() }\nfacc g() { piglie Math.max(1, 2) }");
+        let g = tree.lookup(tree.root(), "g").unwrap();
+        assert_eq!(tree.references_to(g).count(), 1);
+        let globals: Vec<_> = tree.globals().map(|reference| &*reference.name).collect();
+        assert_eq!(globals, ["Math"]);
+    }
+
+    #[test]
+    fn test_block_scopes_and_writes() {
+        let tree = tree("tien n = 0\npe (tien i = 0; i < 3; i = i + 1) {\n  chist è d = i\n  n = n + d\n}");
+        assert_eq!(tree.lookup(tree.root(), "i"), None);
+        let n = tree.lookup(tree.root(), "n").unwrap();
+        let writes: Vec<_> = tree.references_to(n).map(|reference| reference.write).collect();
+        assert_eq!(writes, [true, false]);
+        let d = tree.declarations.iter().find(|decl| &*decl.name == "d").unwrap();
+        assert_eq!(tree.scope(tree.scope(d.scope).parent.unwrap()).kind, ScopeKind::For);
+    }
+
+    #[test]
+    fn test_find_references() {
+        let source = "tien x = 1\nfacc f(x) { piglie x.x }\nstamm a dì(x, { x: x })";
+        let program = crate::parse_source(source).unwrap();
+        let starts = |offset| find_references(&program, source, offset).iter().map(|span| span.start).collect::<Vec<_>>();
+        // The parameter and its one use, not the property
+        assert_eq!(starts(18), [18, 30]);
+        // From the use of the outer `x`, not the object key
+        assert_eq!(starts(48), [5, 48, 56]);
+        assert!(starts(7).is_empty());
+    }
+
+    #[test]
+    fn test_rename_conflicts() {
+        let source = "tien x = 1\ntien y = 2\nfacc f(z) {\n  piglie x + z\n}";
+        assert_eq!(rename(source, 5, "y").unwrap_err().code, "rename-conflict");
+        // `z` would hide `x` inside `f`
+        assert_eq!(rename(source, 5, "z").unwrap_err().span.line, 4);
+        // `x` inside `f` would become the parameter
+        assert!(rename(source, 29, "x").is_err());
+        assert_eq!(rename(source, 29, "w").unwrap(), "tien x = 1\ntien y = 2\nfacc f(w) {\n  piglie x + w\n}");
+        assert!(rename(source, 5, "1x").is_err());
+        assert!(rename(source, 5, "123").unwrap_err().message.contains("nun è nu nome valido"));
+        assert!(rename(source, 5, "tien").unwrap_err().message.contains("'na parola chiave"));
+    }
+
+    #[test]
+    fn test_exports() {
+        let tree = tree("chiamm { pizza } da \"./cucina\"\nmann for facc servi() { piglie pizza }\nmann for predefinit servi");
+        let names: Vec<_> = tree.exports.iter().map(|export| &*export.name).collect();
+        assert_eq!(names, ["servi", "default"]);
+        assert_eq!(tree.exports[0].declaration, tree.exports[1].declaration);
+        let pizza = tree.lookup(tree.root(), "pizza").unwrap();
+        assert_eq!(tree.declaration(pizza).kind, DeclarationKind::Import);
+        assert_eq!(tree.references_to(pizza).count(), 1);
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        let program = crate::parse_source("chist è PI = 3\nmann for facc area(r) {\n  tien a = PI * r * r\n  facc doppio() { piglie a * 2 }\n  piglie a\n}\nna famiglie Cerchio {\n  facc costruttore(r) {}\n  facc area() {}\n}").unwrap();
+        let symbols = document_symbols(&program);
+        let outline: Vec<_> = symbols.iter().map(|symbol| (&*symbol.name, symbol.kind, symbol.children.len())).collect();
+        assert_eq!(outline, [("PI", SymbolKind::Constant, 0), ("area", SymbolKind::Function, 1), ("Cerchio", SymbolKind::Class, 2)]);
+        assert_eq!(&*symbols[1].children[0].name, "doppio");
+        assert_eq!(symbols[2].children[1].kind, SymbolKind::Method);
+        assert_eq!(symbols[2].span, program.statements[2].span());
+    }
+}
@@ -1,24 +1,31 @@
 //! Source Map generation for FratmScript
 //!
 //! Implements Source Map v3 specification for debugging support.
+//!
+//! Besides regular maps with one or more `sources`, index maps made of
+//! `sections` ([`IndexSourceMap`]) are supported for bundled output that
+//! concatenates several compiled modules.
 
+#[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 
 /// A source map following the v3 specification
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct SourceMap {
     /// Version (always 3)
     pub version: u8,
     /// Generated file name
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub file: Option<String>,
     /// Source file name
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub source_root: Option<String>,
     /// List of source files
     pub sources: Vec<String>,
     /// Source contents (optional)
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub sources_content: Option<Vec<String>>,
     /// Symbol names
     pub names: Vec<String>,
@@ -55,17 +62,58 @@ impl SourceMap {
         self
     }
 
+    pub fn with_file(mut self, file: &str) -> Self {
+        self.file = Some(file.to_string());
+        self
+    }
+
+    /// Parse a source map from its JSON representation
+    #[cfg(feature = "serde")]
+    pub fn parse(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Decode the VLQ mappings into absolute positions.
+    ///
+    /// Returns `None` if the mappings string is malformed.
+    pub fn decode_mappings(&self) -> Option<Vec<Mapping>> {
+        decode_mappings(&self.mappings)
+    }
+
+    /// Find the original position of a generated position (all 0-indexed).
+    ///
+    /// Uses the closest mapping at or before `gen_col` on `gen_line`, which
+    /// is how browsers and Node resolve positions that fall inside a segment.
+    /// Returns the source line, source column, and symbol name if any.
+    pub fn lookup(&self, gen_line: usize, gen_col: usize) -> Option<(usize, usize, Option<&str>)> {
+        let mapping = self.find_mapping(gen_line, gen_col)?;
+        let name = mapping.name.and_then(|idx| self.names.get(idx)).map(|n| n.as_str());
+        Some((mapping.src_line, mapping.src_col, name))
+    }
+
+    /// Like [`SourceMap::lookup`], but returns the whole mapping (including
+    /// the source index) for maps that cover several sources.
+    pub fn find_mapping(&self, gen_line: usize, gen_col: usize) -> Option<Mapping> {
+        self.decode_mappings()?
+            .into_iter()
+            .filter(|m| m.gen_line == gen_line && m.gen_col <= gen_col)
+            .max_by_key(|m| m.gen_col)
+    }
+
     /// Convert to JSON string
+    #[cfg(feature = "serde")]
     pub fn to_json(&self) -> String {
         serde_json::to_string(self).unwrap_or_default()
     }
 
     /// Convert to JSON string (pretty)
+    #[cfg(feature = "serde")]
     pub fn to_json_pretty(&self) -> String {
         serde_json::to_string_pretty(self).unwrap_or_default()
     }
 
     /// Get as data URL for inline source maps
+    #[cfg(feature = "serde")]
     pub fn to_data_url(&self) -> String {
         let json = self.to_json();
         let encoded = base64_encode(&json);
@@ -73,6 +121,123 @@ impl SourceMap {
     }
 }
 
+/// An index source map: a list of regular maps placed at generated offsets.
+///
+/// Produced when several compiled modules are concatenated into one bundle.
+/// Each section covers the generated code from its offset up to the next
+/// section's offset.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IndexSourceMap {
+    /// Version (always 3)
+    pub version: u8,
+    /// Generated file name
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub file: Option<String>,
+    /// Sections, ordered by offset
+    pub sections: Vec<Section>,
+}
+
+/// One section of an [`IndexSourceMap`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Section {
+    /// Generated position where this section starts
+    pub offset: SectionOffset,
+    /// The map for this section's code
+    pub map: SourceMap,
+}
+
+/// Generated position (0-indexed) of a section start
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SectionOffset {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl IndexSourceMap {
+    pub fn new() -> Self {
+        Self { version: 3, ..Default::default() }
+    }
+
+    /// Append a section whose generated code starts at `line`/`column`.
+    pub fn add_section(&mut self, line: usize, column: usize, map: SourceMap) {
+        self.sections.push(Section { offset: SectionOffset { line, column }, map });
+    }
+
+    /// Parse an index map from its JSON representation
+    #[cfg(feature = "serde")]
+    pub fn parse(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Convert to JSON string
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Find the original position of a generated position (all 0-indexed).
+    ///
+    /// Returns the source name, source line, source column, and symbol name.
+    pub fn lookup(&self, gen_line: usize, gen_col: usize) -> Option<(&str, usize, usize, Option<&str>)> {
+        let section = self.sections.iter()
+            .filter(|s| (s.offset.line, s.offset.column) <= (gen_line, gen_col))
+            .max_by_key(|s| (s.offset.line, s.offset.column))?;
+        let line = gen_line - section.offset.line;
+        let col = if line == 0 { gen_col - section.offset.column } else { gen_col };
+        let mapping = section.map.find_mapping(line, col)?;
+        let source = section.map.sources.get(mapping.source)?;
+        let name = mapping.name.and_then(|idx| section.map.names.get(idx)).map(|n| n.as_str());
+        Some((source, mapping.src_line, mapping.src_col, name))
+    }
+
+    /// Merge all sections into one regular map with multiple sources.
+    ///
+    /// Useful for consumers that don't understand index maps.
+    pub fn flatten(&self) -> SourceMap {
+        let mut builder = SourceMapBuilder::new();
+        let mut all_content = true;
+
+        for section in &self.sections {
+            let contents = section.map.sources_content.as_ref();
+            all_content &= contents.is_some();
+            let indices: Vec<usize> = section.map.sources.iter().enumerate()
+                .map(|(i, name)| builder.add_source(name, contents.and_then(|c| c.get(i)).map(|c| c.as_str())))
+                .collect();
+
+            for m in section.map.decode_mappings().unwrap_or_default() {
+                let gen_line = m.gen_line + section.offset.line;
+                let gen_col = if m.gen_line == 0 { m.gen_col + section.offset.column } else { m.gen_col };
+                builder.set_source(indices.get(m.source).copied().unwrap_or(0));
+                match m.name.and_then(|idx| section.map.names.get(idx)) {
+                    Some(name) => builder.add_named_mapping(gen_line, gen_col, m.src_line, m.src_col, name),
+                    None => builder.add_mapping(gen_line, gen_col, m.src_line, m.src_col),
+                }
+            }
+        }
+
+        let mut map = builder.build(None);
+        if !all_content {
+            map.sources_content = None;
+        }
+        map.file = self.file.clone();
+        map
+    }
+}
+
+/// A decoded mapping segment with absolute, 0-indexed positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mapping {
+    pub gen_line: usize,
+    pub gen_col: usize,
+    pub source: usize,
+    pub src_line: usize,
+    pub src_col: usize,
+    pub name: Option<usize>,
+}
+
 /// Source map builder for incremental construction
 #[derive(Debug, Default, Clone)]
 pub struct SourceMapBuilder {
@@ -84,12 +249,19 @@ pub struct SourceMapBuilder {
     gen_col: usize,
     /// Symbol names
     names: Vec<String>,
+    /// Registered source files (empty means a single implicit source)
+    sources: Vec<String>,
+    /// Contents of the registered sources
+    sources_content: Vec<Option<String>>,
+    /// Source index used by new mappings
+    current_source: usize,
 }
 
 /// A single mapping segment
 #[derive(Debug, Clone)]
 struct Segment {
     gen_col: usize,
+    src_idx: usize,
     src_line: usize,
     src_col: usize,
     name_idx: Option<usize>,
@@ -112,13 +284,15 @@ impl SourceMapBuilder {
 
         self.segments[gen_line].push(Segment {
             gen_col,
+            src_idx: self.current_source,
             src_line,
             src_col,
             name_idx: None,
         });
     }
 
-    /// Add a named mapping
+    /// Add a named mapping. It replaces a mapping just added at the same
+    /// generated position, e.g. the one for the statement the name starts.
     pub fn add_named_mapping(
         &mut self,
         gen_line: usize,
@@ -136,14 +310,38 @@ impl SourceMapBuilder {
             self.segments.push(vec![]);
         }
 
-        self.segments[gen_line].push(Segment {
+        let line = &mut self.segments[gen_line];
+        if line.last().is_some_and(|segment| segment.gen_col == gen_col) {
+            line.pop();
+        }
+        line.push(Segment {
             gen_col,
+            src_idx: self.current_source,
             src_line,
             src_col,
             name_idx: Some(name_idx),
         });
     }
 
+    /// Register a source file; subsequent mappings refer to it.
+    ///
+    /// Returns the index of the source in the `sources` list. Registering the
+    /// same name twice reuses the existing entry.
+    pub fn add_source(&mut self, name: &str, content: Option<&str>) -> usize {
+        let idx = self.sources.iter().position(|s| s == name).unwrap_or_else(|| {
+            self.sources.push(name.to_string());
+            self.sources_content.push(content.map(|c| c.to_string()));
+            self.sources.len() - 1
+        });
+        self.current_source = idx;
+        idx
+    }
+
+    /// Switch the source used by subsequent mappings.
+    pub fn set_source(&mut self, idx: usize) {
+        self.current_source = idx;
+    }
+
     /// Notify that we moved to a new generated line
     pub fn new_line(&mut self) {
         self.gen_line += 1;
@@ -151,15 +349,25 @@ impl SourceMapBuilder {
     }
 
     /// Build the final source map
+    ///
+    /// `source_file` names the implicit single source; it is ignored when
+    /// sources were registered with [`SourceMapBuilder::add_source`].
     pub fn build(self, source_file: Option<&str>) -> SourceMap {
         let mappings = self.encode_mappings();
+        let (sources, sources_content) = if self.sources.is_empty() {
+            (vec![source_file.unwrap_or("input.fratm").to_string()], None)
+        } else {
+            // sourcesContent is all-or-nothing here: a partial list would misalign indices
+            let contents: Option<Vec<String>> = self.sources_content.into_iter().collect();
+            (self.sources, contents)
+        };
 
         SourceMap {
             version: 3,
             file: None,
             source_root: None,
-            sources: vec![source_file.unwrap_or("input.fratm").to_string()],
-            sources_content: None,
+            sources,
+            sources_content,
             names: self.names,
             mappings,
         }
@@ -168,6 +376,7 @@ impl SourceMapBuilder {
     /// Encode all mappings to VLQ string
     fn encode_mappings(&self) -> String {
         let mut result = String::new();
+        let mut prev_src_idx: i64 = 0;
         let mut prev_src_line: i64 = 0;
         let mut prev_src_col: i64 = 0;
         let mut prev_name: i64 = 0;
@@ -189,8 +398,10 @@ impl SourceMapBuilder {
                 result.push_str(&vlq_encode(gen_col_delta));
                 prev_gen_col = segment.gen_col as i64;
 
-                // Source index (always 0 for single source)
-                result.push_str(&vlq_encode(0));
+                // Source index (delta)
+                let src_idx_delta = segment.src_idx as i64 - prev_src_idx;
+                result.push_str(&vlq_encode(src_idx_delta));
+                prev_src_idx = segment.src_idx as i64;
 
                 // Source line (delta)
                 let src_line_delta = segment.src_line as i64 - prev_src_line;
@@ -251,7 +462,80 @@ fn vlq_encode(value: i64) -> String {
     encoded
 }
 
+/// Decode a single VLQ value, advancing the byte iterator
+fn vlq_decode(input: &mut std::iter::Peekable<std::str::Bytes>) -> Option<i64> {
+    let mut result: i64 = 0;
+    let mut shift = 0;
+
+    loop {
+        let byte = input.next()?;
+        let digit = BASE64_CHARS.iter().position(|&c| c == byte)? as i64;
+        result += (digit & VLQ_BASE_MASK) << shift;
+        shift += VLQ_BASE_SHIFT;
+
+        if digit & VLQ_CONTINUATION_BIT == 0 {
+            break;
+        }
+        if shift > 60 {
+            return None;
+        }
+    }
+
+    Some(if result & 1 == 1 { -(result >> 1) } else { result >> 1 })
+}
+
+/// Decode a full mappings string into absolute segments
+fn decode_mappings(mappings: &str) -> Option<Vec<Mapping>> {
+    let mut result = Vec::new();
+    let mut source: i64 = 0;
+    let mut src_line: i64 = 0;
+    let mut src_col: i64 = 0;
+    let mut name: i64 = 0;
+
+    for (gen_line, line) in mappings.split(';').enumerate() {
+        let mut gen_col: i64 = 0;
+
+        for segment in line.split(',').filter(|s| !s.is_empty()) {
+            let mut bytes = segment.bytes().peekable();
+            let mut fields = Vec::with_capacity(5);
+            while bytes.peek().is_some() {
+                fields.push(vlq_decode(&mut bytes)?);
+            }
+
+            gen_col += fields[0];
+            // Segments with only a generated column map to nothing
+            if fields.len() < 4 {
+                continue;
+            }
+            source += fields[1];
+            src_line += fields[2];
+            src_col += fields[3];
+            let name_idx = if fields.len() >= 5 {
+                name += fields[4];
+                Some(name)
+            } else {
+                None
+            };
+
+            if gen_col < 0 || source < 0 || src_line < 0 || src_col < 0 || name < 0 {
+                return None;
+            }
+            result.push(Mapping {
+                gen_line,
+                gen_col: gen_col as usize,
+                source: source as usize,
+                src_line: src_line as usize,
+                src_col: src_col as usize,
+                name: name_idx.map(|n| n as usize),
+            });
+        }
+    }
+
+    Some(result)
+}
+
 /// Simple base64 encoding
+#[cfg(feature = "serde")]
 fn base64_encode(input: &str) -> String {
     let bytes = input.as_bytes();
     let mut result = String::new();
@@ -280,7 +564,7 @@ fn base64_encode(input: &str) -> String {
     result
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "serde"))]
 mod tests {
     use super::*;
 
@@ -305,10 +589,78 @@ mod tests {
         assert!(!map.mappings.is_empty());
     }
 
+    #[test]
+    fn test_vlq_round_trip() {
+        for value in [0, 1, -1, 15, 16, -16, 1000, -123456] {
+            let encoded = vlq_encode(value);
+            let mut bytes = encoded.bytes().peekable();
+            assert_eq!(vlq_decode(&mut bytes), Some(value));
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut builder = SourceMapBuilder::new();
+        builder.add_mapping(0, 0, 0, 0);
+        builder.add_named_mapping(0, 6, 0, 8, "nome");
+        builder.new_line();
+        builder.add_mapping(1, 2, 3, 4);
+
+        let map = SourceMap::parse(&builder.build(None).to_json()).unwrap();
+        assert_eq!(map.lookup(0, 0), Some((0, 0, None)));
+        assert_eq!(map.lookup(0, 9), Some((0, 8, Some("nome"))));
+        assert_eq!(map.lookup(1, 10), Some((3, 4, None)));
+        assert_eq!(map.lookup(1, 0), None);
+        assert_eq!(map.lookup(5, 0), None);
+    }
+
+    #[test]
+    fn test_multiple_sources() {
+        let mut builder = SourceMapBuilder::new();
+        builder.add_source("a.fratm", Some("tien a"));
+        builder.add_mapping(0, 0, 0, 0);
+        builder.add_source("b.fratm", Some("tien b"));
+        builder.add_mapping(1, 0, 0, 5);
+
+        let map = builder.build(None);
+        assert_eq!(map.sources, vec!["a.fratm", "b.fratm"]);
+        assert_eq!(map.find_mapping(1, 0).map(|m| m.source), Some(1));
+        assert_eq!(map.find_mapping(0, 3).map(|m| m.source), Some(0));
+    }
+
+    #[test]
+    fn test_index_map() {
+        let mut first = SourceMapBuilder::new();
+        first.add_mapping(0, 0, 0, 0);
+        let mut second = SourceMapBuilder::new();
+        second.add_named_mapping(0, 0, 2, 4, "x");
+
+        let mut index = IndexSourceMap::new();
+        index.add_section(0, 0, first.build(Some("a.fratm")));
+        index.add_section(3, 0, second.build(Some("b.fratm")));
+
+        let index = IndexSourceMap::parse(&index.to_json()).unwrap();
+        assert_eq!(index.lookup(0, 5), Some(("a.fratm", 0, 0, None)));
+        assert_eq!(index.lookup(3, 1), Some(("b.fratm", 2, 4, Some("x"))));
+
+        let flat = index.flatten();
+        assert_eq!(flat.sources, vec!["a.fratm", "b.fratm"]);
+        assert_eq!(flat.lookup(3, 1), Some((2, 4, Some("x"))));
+        assert_eq!(flat.find_mapping(3, 1).map(|m| m.source), Some(1));
+    }
+
     #[test]
     fn test_source_map_json() {
         let map = SourceMap::default();
         let json = map.to_json();
         assert!(json.contains("\"version\":3"));
     }
+
+    #[test]
+    fn test_source_map_json_field_names() {
+        let map = SourceMap::new().with_content("tien x").with_file("main.js");
+        let json = map.to_json();
+        assert!(json.contains("\"sourcesContent\":[\"tien x\"]"));
+        assert!(json.contains("\"file\":\"main.js\""));
+    }
 }
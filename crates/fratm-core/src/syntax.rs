@@ -0,0 +1,488 @@
+//! Lossless syntax trees
+//!
+//! The AST of [`crate::parser`] keeps what the program means and drops the
+//! rest: spaces, comments, parentheses, the spelling of keywords. The
+//! trees here keep every byte of the source, so tools that rewrite code
+//! (formatting a range, renaming, quick fixes) can change one spot and
+//! leave the rest as it was written. `tree.text()` is always the source,
+//! errors included.
+//!
+//! As in rowan, a tree has two layers. [`GreenNode`]s know their kind, their
+//! length and their children, not where they are, so they can be shared:
+//! between the places a token repeats, and between the trees before and
+//! after an edit ([`SyntaxNode::reparse`]). [`SyntaxNode`]s wrap them with
+//! their offset in the source, and are built as they are walked.
+//!
+//! The structure is shallow: the file holds its top-level statements, each
+//! statement its tokens, and the tokens between brackets are grouped in a
+//! node per pair. Statements the parser couldn't read, and brackets left
+//! unmatched, are [`SyntaxKind::Error`] nodes.
+//!
+//! ```rust
+//! use fratm_core::syntax::{parse, SyntaxKind};
+//!
+//! let source = "tien x = 1 // uno\nstamm a dì(x)\n";
+//! let tree = parse(source);
+//! assert_eq!(tree.text(), source);
+//! let statements: Vec<_> = tree.child_nodes().collect();
+//! assert_eq!(statements.len(), 2);
+//! assert_eq!(statements[1].text(), "stamm a dì(x)");
+//! let comment = tree.token_at_offset(12).unwrap();
+//! assert_eq!((comment.kind(), comment.text()), (SyntaxKind::Comment, "// uno"));
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::fix::{apply, Fix};
+use crate::lexer::keywords::KEYWORDS;
+use crate::lexer::{Lexer, Token, TokenKind};
+use crate::parser::{Parser, Statement};
+use crate::CompileOptions;
+
+/// What a node or token of the tree is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    /// The whole file
+    SourceFile,
+    /// A top-level statement
+    Statement,
+    /// `{ ... }`
+    Braces,
+    /// `( ... )`
+    Parens,
+    /// `[ ... ]`
+    Brackets,
+    /// A statement that doesn't parse, a bracket that closes nothing, or
+    /// one never closed with what follows it
+    Error,
+    Keyword,
+    Identifier,
+    /// Numbers, strings and the parts of interpolated strings, JSX text
+    Literal,
+    /// Operators, brackets and separators
+    Punctuation,
+    /// A line end the lexer reads as a token
+    Newline,
+    Whitespace,
+    Comment,
+}
+
+impl SyntaxKind {
+    /// Spaces and comments, which don't change what the code means
+    pub fn is_trivia(self) -> bool {
+        matches!(self, SyntaxKind::Whitespace | SyntaxKind::Comment)
+    }
+
+    fn of_token(kind: &TokenKind) -> Self {
+        match kind {
+            TokenKind::Identifier(_) | TokenKind::JsxName(_) => SyntaxKind::Identifier,
+            TokenKind::Number(_) | TokenKind::String(_) | TokenKind::TemplateHead(_) | TokenKind::TemplateMiddle(_)
+            | TokenKind::TemplateTail(_) | TokenKind::JsxText(_) => SyntaxKind::Literal,
+            TokenKind::Newline => SyntaxKind::Newline,
+            TokenKind::Invalid(_) => SyntaxKind::Error,
+            kind if KEYWORDS.iter().any(|keyword| keyword.kind == *kind) => SyntaxKind::Keyword,
+            _ => SyntaxKind::Punctuation,
+        }
+    }
+}
+
+/// A token with its text, wherever it is
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+    pub kind: SyntaxKind,
+    pub text: String,
+}
+
+/// A node with its children, wherever it is
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenNode {
+    pub kind: SyntaxKind,
+    /// Length of the text in bytes
+    pub len: usize,
+    pub children: Vec<GreenElement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GreenElement {
+    Node(Arc<GreenNode>),
+    Token(Arc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            GreenElement::Node(node) => node.kind,
+            GreenElement::Token(token) => token.kind,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.len,
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn write_text(&self, out: &mut String) {
+        match self {
+            GreenElement::Node(node) => node.children.iter().for_each(|child| child.write_text(out)),
+            GreenElement::Token(token) => out.push_str(&token.text),
+        }
+    }
+}
+
+impl GreenNode {
+    fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        Self { kind, len: children.iter().map(GreenElement::len).sum(), children }
+    }
+}
+
+/// A node of the tree, with its place in the source
+#[derive(Clone, PartialEq, Eq)]
+pub struct SyntaxNode {
+    green: Arc<GreenNode>,
+    offset: usize,
+}
+
+/// A token of the tree, with its place in the source
+#[derive(Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    green: Arc<GreenToken>,
+    offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// The lossless tree of `source`, in Neapolitan and without JSX or interpolation.
+pub fn parse(source: &str) -> SyntaxNode {
+    parse_with_options(source, &CompileOptions::default())
+}
+
+/// The lossless tree of `source`, read with the dialect, JSX and
+/// interpolation of `options`.
+pub fn parse_with_options(source: &str, options: &CompileOptions) -> SyntaxNode {
+    Builder::new(options).build(source)
+}
+
+impl SyntaxNode {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+
+    /// Byte offsets of the node in the source
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.len
+    }
+
+    pub fn green(&self) -> &Arc<GreenNode> {
+        &self.green
+    }
+
+    /// The source of the node, every byte of it
+    pub fn text(&self) -> String {
+        let mut out = String::with_capacity(self.green.len);
+        self.green.children.iter().for_each(|child| child.write_text(&mut out));
+        out
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxElement> + '_ {
+        let mut offset = self.offset;
+        self.green.children.iter().map(move |child| {
+            let element = match child {
+                GreenElement::Node(node) => SyntaxElement::Node(SyntaxNode { green: Arc::clone(node), offset }),
+                GreenElement::Token(token) => SyntaxElement::Token(SyntaxToken { green: Arc::clone(token), offset }),
+            };
+            offset += child.len();
+            element
+        })
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = SyntaxNode> + '_ {
+        self.children().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Every token under the node, trivia included, in source order
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    /// The token with the byte at `offset`
+    pub fn token_at_offset(&self, offset: usize) -> Option<SyntaxToken> {
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) if node.range().contains(&offset) => return node.token_at_offset(offset),
+                SyntaxElement::Token(token) if token.range().contains(&offset) => return Some(token),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// The tree of the source with `edit` applied. The statements the edit
+    /// leaves as they were keep their green nodes, shared with this tree.
+    ///
+    /// ```rust
+    /// use fratm_core::fix::Fix;
+    /// use fratm_core::lexer::Span;
+    ///
+    /// let tree = fratm_core::syntax::parse("tien x = 1\ntien y = 2\n");
+    /// let edit = Fix { span: Span::new(9, 10, 1, 10), replacement: "42".into() };
+    /// let edited = tree.reparse(&edit);
+    /// assert_eq!(edited.text(), "tien x = 42\ntien y = 2\n");
+    /// let (old, new): (Vec<_>, Vec<_>) = (tree.child_nodes().collect(), edited.child_nodes().collect());
+    /// assert!(!std::sync::Arc::ptr_eq(old[0].green(), new[0].green()));
+    /// assert!(std::sync::Arc::ptr_eq(old[1].green(), new[1].green()));
+    /// ```
+    pub fn reparse(&self, edit: &Fix) -> SyntaxNode {
+        self.reparse_with_options(edit, &CompileOptions::default())
+    }
+
+    /// [`reparse`](Self::reparse), for a tree read with `options`
+    pub fn reparse_with_options(&self, edit: &Fix, options: &CompileOptions) -> SyntaxNode {
+        let mut builder = Builder::new(options);
+        for statement in self.green.children.iter() {
+            if let GreenElement::Node(node) = statement {
+                let mut text = String::new();
+                statement.write_text(&mut text);
+                builder.statements.insert((node.kind, text), Arc::clone(node));
+            }
+        }
+        builder.build(&apply(&self.text(), std::slice::from_ref(edit)))
+    }
+
+    /// The tree as an indented outline, a line per node and token:
+    /// `Statement@0..10`, `Keyword@0..4 "tien"`
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        self.dump_into(0, &mut out);
+        out
+    }
+
+    fn dump_into(&self, depth: usize, out: &mut String) {
+        out.push_str(&format!("{}{:?}@{:?}\n", "  ".repeat(depth), self.kind(), self.range()));
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => node.dump_into(depth + 1, out),
+                SyntaxElement::Token(token) => out.push_str(&format!("{}{:?}\n", "  ".repeat(depth + 1), token)),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{:?}", self.kind(), self.range())
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    /// Byte offsets of the token in the source
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+
+    pub fn green(&self) -> &Arc<GreenToken> {
+        &self.green
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{:?} {:?}", self.kind(), self.range(), self.text())
+    }
+}
+
+/// Builds trees, sharing the green tokens and statements it has seen
+struct Builder<'o> {
+    options: &'o CompileOptions,
+    tokens: HashMap<(SyntaxKind, String), Arc<GreenToken>>,
+    /// Statements of an earlier tree, by kind and text
+    statements: HashMap<(SyntaxKind, String), Arc<GreenNode>>,
+}
+
+impl<'o> Builder<'o> {
+    fn new(options: &'o CompileOptions) -> Self {
+        Self { options, tokens: HashMap::new(), statements: HashMap::new() }
+    }
+
+    fn lexer<'a>(&self, source: &'a str) -> Lexer<'a> where 'o: 'a {
+        let options = self.options;
+        Lexer::new(source).with_keywords(&options.dialect).with_jsx(options.jsx.is_some()).with_interpolation(options.interpolation)
+    }
+
+    fn token(&mut self, kind: SyntaxKind, text: &str) -> GreenElement {
+        let token = self.tokens.entry((kind, text.to_string())).or_insert_with(|| Arc::new(GreenToken { kind, text: text.to_string() }));
+        GreenElement::Token(Arc::clone(token))
+    }
+
+    fn build(mut self, source: &str) -> SyntaxNode {
+        let mut lexer = self.lexer(source);
+        let tokens: Vec<Token> = lexer.by_ref().filter(|token| token.kind != TokenKind::Eof).collect();
+        let comments = lexer.take_comments();
+        let (program, _) = Parser::streaming(self.lexer(source)).with_max_depth(self.options.parser.max_depth).parse_recovering();
+        let statements: Vec<(Range<usize>, bool)> = program.statements.iter()
+            .map(|statement| (statement.span().start..statement.span().end, matches!(statement, Statement::Error { .. })))
+            .collect();
+
+        // Every byte as a leaf: the tokens, and the trivia between them
+        let mut leaves: Vec<(usize, GreenElement)> = Vec::new();
+        let mut comments = comments.iter().peekable();
+        let mut position = 0;
+        for (start, end, kind) in tokens.iter().map(|token| (token.span.start, token.span.end, SyntaxKind::of_token(&token.kind))).chain([(source.len(), source.len(), SyntaxKind::Error)]) {
+            // Tokens never overlap, but better safe than lossy
+            let start = start.max(position);
+            while position < start {
+                let comment = comments.next_if(|comment| comment.span.start < start);
+                let (gap_end, comment) = match comment {
+                    Some(comment) => (comment.span.start.max(position), Some(comment)),
+                    None => (start, None),
+                };
+                if gap_end > position {
+                    let gap = &source[position..gap_end];
+                    let kind = if gap.trim().is_empty() { SyntaxKind::Whitespace } else { SyntaxKind::Error };
+                    leaves.push((position, self.token(kind, gap)));
+                    position = gap_end;
+                }
+                if let Some(comment) = comment {
+                    let end = comment.span.end.clamp(position, start);
+                    leaves.push((position, self.token(SyntaxKind::Comment, &source[position..end])));
+                    position = end;
+                }
+            }
+            if end > position {
+                leaves.push((position, self.token(kind, &source[position..end])));
+                position = end;
+            }
+        }
+
+        let mut children = Vec::new();
+        let mut leaves = leaves.into_iter().peekable();
+        let mut statements = statements.into_iter().peekable();
+        while let Some((offset, leaf)) = leaves.next() {
+            let Some((range, is_error)) = statements.next_if(|(range, _)| range.start <= offset && offset < range.end) else {
+                children.push(leaf);
+                continue;
+            };
+            let mut inside = vec![leaf];
+            while let Some((_, leaf)) = leaves.next_if(|(offset, _)| *offset < range.end) { inside.push(leaf); }
+            // Statements the parser skipped over
+            while statements.next_if(|(next, _)| next.start < range.end).is_some() {}
+            let kind = if is_error { SyntaxKind::Error } else { SyntaxKind::Statement };
+            children.push(GreenElement::Node(self.statement(kind, inside)));
+        }
+        SyntaxNode { green: Arc::new(GreenNode::new(SyntaxKind::SourceFile, children)), offset: 0 }
+    }
+
+    /// A statement of `leaves`, grouped by brackets, or the same one of the earlier tree
+    fn statement(&mut self, kind: SyntaxKind, leaves: Vec<GreenElement>) -> Arc<GreenNode> {
+        let mut text = String::new();
+        leaves.iter().for_each(|leaf| leaf.write_text(&mut text));
+        if let Some(node) = self.statements.get(&(kind, text)) { return Arc::clone(node); }
+
+        // Open brackets, each with what follows it so far
+        let mut stack: Vec<(&str, Vec<GreenElement>)> = vec![("", Vec::new())];
+        for leaf in leaves {
+            let text = match &leaf { GreenElement::Token(token) if token.kind == SyntaxKind::Punctuation => token.text.as_str(), _ => "" };
+            match text {
+                "(" | "[" | "{" => {
+                    let opener = if text == "(" { "(" } else if text == "[" { "[" } else { "{" };
+                    stack.push((opener, vec![leaf]));
+                }
+                ")" | "]" | "}" => {
+                    let (opener, kind) = match text { ")" => ("(", SyntaxKind::Parens), "]" => ("[", SyntaxKind::Brackets), _ => ("{", SyntaxKind::Braces) };
+                    if stack.len() > 1 && stack.last().is_some_and(|(open, _)| *open == opener) {
+                        let (_, mut group) = stack.pop().expect("checked");
+                        group.push(leaf);
+                        stack.last_mut().expect("the statement").1.push(GreenElement::Node(Arc::new(GreenNode::new(kind, group))));
+                    } else {
+                        stack.last_mut().expect("the statement").1.push(GreenElement::Node(Arc::new(GreenNode::new(SyntaxKind::Error, vec![leaf]))));
+                    }
+                }
+                _ => stack.last_mut().expect("the statement").1.push(leaf),
+            }
+        }
+        while stack.len() > 1 {
+            let (_, group) = stack.pop().expect("checked");
+            stack.last_mut().expect("the statement").1.push(GreenElement::Node(Arc::new(GreenNode::new(SyntaxKind::Error, group))));
+        }
+        let (_, children) = stack.pop().expect("the statement");
+        Arc::new(GreenNode::new(kind, children))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lossless() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let source = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert_eq!(parse(&source).text(), source);
+        }
+        // Errors, stray brackets, CRLF, unterminated strings and comments
+        for source in ["chist x = 1\r\n)\r\nsi (x { ] }", "tien s = \"aperta\n/* mai chiuso", "€ tien x = 1 ¤", ""] {
+            assert_eq!(parse(source).text(), source);
+        }
+    }
+
+    #[test]
+    fn test_structure() {
+        let tree = parse("si (x) {\n  stamm a dì([1, 2]) // ok\n}\nchist y = 1\n");
+        assert_eq!(tree.dump().lines().take(8).collect::<Vec<_>>(), [
+            "SourceFile@0..51",
+            "  Statement@0..38",
+            "    Keyword@0..2 \"si\"",
+            "    Whitespace@2..3 \" \"",
+            "    Parens@3..6",
+            "      Punctuation@3..4 \"(\"",
+            "      Identifier@4..5 \"x\"",
+            "      Punctuation@5..6 \")\"",
+        ]);
+        let kinds: Vec<SyntaxKind> = tree.child_nodes().next().unwrap().tokens().iter().map(SyntaxToken::kind).collect();
+        assert!(kinds.contains(&SyntaxKind::Comment) && kinds.contains(&SyntaxKind::Newline));
+        // A statement that doesn't parse, with a bracket never closed
+        let tree = parse("chist = (1\ntien z = 2");
+        let nodes: Vec<SyntaxNode> = tree.child_nodes().collect();
+        assert_eq!(nodes[0].kind(), SyntaxKind::Error);
+        assert_eq!(nodes.last().unwrap().text(), "tien z = 2");
+    }
+
+    #[test]
+    fn test_shared_tokens() {
+        let tree = parse("tien a = 1\ntien b = 1");
+        let tokens: Vec<SyntaxToken> = tree.tokens().into_iter().filter(|token| token.text() == "tien").collect();
+        assert!(Arc::ptr_eq(tokens[0].green(), tokens[1].green()));
+        assert_eq!(tokens[1].range(), 11..15);
+    }
+}
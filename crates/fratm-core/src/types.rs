@@ -0,0 +1,522 @@
+//! Type checking for FratmScript's type annotations
+//!
+//! [`check`] compares values with the [annotations](TypeAnnotation) they
+//! meet: declarations, arguments, results and assignments. Unannotated
+//! `chist è` and `tien` take the type of their value, and what the checker
+//! can't tell is `qualsiasi`, which goes with everything, so code without
+//! annotations gets no warnings.
+//!
+//! The checker is bidirectional: the expected type flows into array and
+//! object literals and arrow functions, so a warning points at the element
+//! or the property that's wrong, not at the whole value.
+//!
+//! | Rule | What it catches |
+//! |------|-----------------|
+//! | `type-mismatch` | a value of another type: `tien x: nummero = "uno"` |
+//! | `unknown-type` | an annotation that names no type: `tien x: nummer` |
+//!
+//! ```rust
+//! let program = fratm_core::parse_source("facc doppio(x: nummero): nummero { piglie x * 2 }\ndoppio(\"due\")").unwrap();
+//! let warnings = fratm_core::types::check(&program);
+//! assert_eq!(warnings[0].message, "Aspettavo nu 'nummero', ma chist è nu 'parola'");
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::errors::Diagnostic;
+use crate::lexer::Span;
+use crate::parser::*;
+
+/// A type, as the checker sees it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Number,
+    String,
+    Boolean,
+    Null,
+    Undefined,
+    Array(Box<Type>),
+    Object(Vec<(Name, Type)>),
+    Function { params: Vec<Type>, result: Box<Type> },
+    /// `qualsiasi`: anything the checker can't tell, accepted everywhere
+    Any,
+}
+
+impl Type {
+    /// Whether a value of type `actual` can go where `self` is expected.
+    /// Objects may have more properties than expected, and a function
+    /// expected to give `boh` may give anything.
+    pub fn accepts(&self, actual: &Type) -> bool {
+        match (self, actual) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Array(expected), Type::Array(actual)) => expected.accepts(actual),
+            (Type::Object(expected), Type::Object(actual)) => expected.iter().all(|(name, expected)| {
+                actual.iter().find(|(other, _)| other == name).is_some_and(|(_, actual)| expected.accepts(actual))
+            }),
+            (Type::Function { params: expected_params, result: expected_result }, Type::Function { params, result }) => {
+                params.len() <= expected_params.len()
+                    && expected_params.iter().zip(params).all(|(expected, param)| param.accepts(expected))
+                    && (**expected_result == Type::Undefined || expected_result.accepts(result))
+            }
+            (expected, actual) => expected == actual,
+        }
+    }
+}
+
+/// Written as in annotations: `{ nome: parola }[]`
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Number => f.write_str("nummero"),
+            Type::String => f.write_str("parola"),
+            Type::Boolean => f.write_str("buleano"),
+            Type::Null => f.write_str("nisciun"),
+            Type::Undefined => f.write_str("boh"),
+            Type::Any => f.write_str("qualsiasi"),
+            Type::Array(element) if matches!(**element, Type::Function { .. }) => write!(f, "({})[]", element),
+            Type::Array(element) => write!(f, "{}[]", element),
+            Type::Object(fields) if fields.is_empty() => f.write_str("{}"),
+            Type::Object(fields) => {
+                let fields: Vec<String> = fields.iter().map(|(name, field)| format!("{}: {}", name, field)).collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+            Type::Function { params, result } => {
+                let params: Vec<String> = params.iter().map(ToString::to_string).collect();
+                write!(f, "({}) => {}", params.join(", "), result)
+            }
+        }
+    }
+}
+
+/// Checks the types in `program`. Warnings are sorted by position.
+pub fn check(program: &Program) -> Vec<Diagnostic> {
+    let mut checker = Checker { scopes: vec![HashMap::new()], classes: HashSet::new(), result: None, warnings: Vec::new() };
+    checker.statements(&program.statements);
+    checker.warnings.sort_by_key(|w| w.span.start);
+    checker.warnings
+}
+
+struct Checker {
+    scopes: Vec<HashMap<Name, Type>>,
+    /// Classes declared so far, which are fine as annotations
+    classes: HashSet<Name>,
+    /// What the current function must `piglie`, if it says
+    result: Option<Type>,
+    warnings: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn declare(&mut self, name: &Name, ty: Type) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.clone(), ty);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Type {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).cloned().unwrap_or(Type::Any)
+    }
+
+    fn mismatch(&mut self, expected: &Type, actual: &Type, span: Span) {
+        self.warnings.push(Diagnostic::warning("type-mismatch", format!("Aspettavo nu '{}', ma chist è nu '{}'", expected, actual), span));
+    }
+
+    fn resolve(&mut self, annotation: &TypeAnnotation) -> Type {
+        match annotation {
+            TypeAnnotation::Named { name, span } => match &**name {
+                "nummero" => Type::Number,
+                "parola" => Type::String,
+                "buleano" => Type::Boolean,
+                "nisciun" => Type::Null,
+                "boh" => Type::Undefined,
+                "qualsiasi" => Type::Any,
+                _ if self.classes.contains(name) => Type::Any,
+                _ => {
+                    self.warnings.push(
+                        Diagnostic::warning("unknown-type", format!("Nun saccio che tipo è '{}'", name), *span)
+                            .with_suggestion("💡 'E tipi so' nummero, parola, buleano, nisciun, boh, qualsiasi e 'e famiglie"),
+                    );
+                    Type::Any
+                }
+            },
+            TypeAnnotation::Array { element, .. } => Type::Array(Box::new(self.resolve(element))),
+            TypeAnnotation::Object { fields, .. } => Type::Object(fields.iter().map(|(name, field)| (name.clone(), self.resolve(field))).collect()),
+            TypeAnnotation::Function { params, result, .. } => Type::Function {
+                params: params.iter().map(|param| self.resolve(param)).collect(),
+                result: Box::new(self.resolve(result)),
+            },
+        }
+    }
+
+    fn signature(&mut self, params: &[Name], signature: Option<&Signature>) -> (Vec<Type>, Option<Type>) {
+        let Some(signature) = signature else { return (vec![Type::Any; params.len()], None) };
+        let params = signature.params.iter().map(|param| param.as_ref().map_or(Type::Any, |param| self.resolve(param))).collect();
+        (params, signature.result.as_ref().map(|result| self.resolve(result)))
+    }
+
+    /// Declares the functions and classes of `statements` first, since
+    /// they can be used before they appear
+    fn statements(&mut self, statements: &[Statement]) {
+        for stmt in statements {
+            match stmt {
+                Statement::ClassDecl { name, .. } => { self.classes.insert(name.clone()); }
+                Statement::Export { declaration: Some(declaration), .. } if matches!(**declaration, Statement::ClassDecl { .. }) => {
+                    if let Statement::ClassDecl { name, .. } = declaration.as_ref() { self.classes.insert(name.clone()); }
+                }
+                _ => {}
+            }
+        }
+        for stmt in statements {
+            let decl = match stmt {
+                Statement::Export { declaration: Some(declaration), .. } => declaration.as_ref(),
+                other => other,
+            };
+            if let Statement::FunctionDecl { name, params, signature, .. } = decl {
+                let (params, result) = self.signature(params, signature.as_deref());
+                self.declare(name, Type::Function { params, result: Box::new(result.unwrap_or(Type::Any)) });
+            }
+        }
+        for stmt in statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        self.scopes.push(HashMap::new());
+        self.statements(statements);
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::VariableDecl { name, annotation, value, .. } => {
+                let ty = match (annotation.as_ref().map(|annotation| self.resolve(annotation)), value) {
+                    (Some(ty), Some(value)) => { self.check(value, &ty); ty }
+                    (Some(ty), None) => ty,
+                    // `tien x = nisciun` is a placeholder, for a value that comes later
+                    (None, Some(value)) => match self.infer(value) {
+                        Type::Null | Type::Undefined => Type::Any,
+                        ty => ty,
+                    },
+                    (None, None) => Type::Any,
+                };
+                self.declare(name, ty);
+            }
+            Statement::FunctionDecl { params, signature, body, .. } => {
+                let (types, result) = self.signature(params, signature.as_deref());
+                self.function(params, types, result, body);
+            }
+            Statement::Return { value: Some(value), .. } => match self.result.clone() {
+                Some(result) => self.check(value, &result),
+                None => { self.infer(value); }
+            },
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                self.infer(condition);
+                self.block(then_branch);
+                if let Some(else_branch) = else_branch { self.block(else_branch); }
+            }
+            Statement::While { condition, body, .. } => {
+                self.infer(condition);
+                self.block(body);
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                self.scopes.push(HashMap::new());
+                if let Some(init) = init { self.statement(init); }
+                if let Some(condition) = condition { self.infer(condition); }
+                if let Some(update) = update { self.infer(update); }
+                self.block(body);
+                self.scopes.pop();
+            }
+            Statement::TryCatch { try_body, catch_type, catch_body, .. } => {
+                self.block(try_body);
+                if let Some(catch_type) = catch_type { self.infer(catch_type); }
+                self.block(catch_body);
+            }
+            Statement::Throw { value, .. } | Statement::Expression { expression: value, .. } => { self.infer(value); }
+            Statement::Assert { condition, message, .. } => {
+                self.infer(condition);
+                if let Some(message) = message { self.infer(message); }
+            }
+            Statement::ClassDecl { decorators, superclass, methods, .. } => {
+                for decorator in decorators { self.infer(decorator); }
+                if let Some(superclass) = superclass { self.infer(superclass); }
+                for method in methods {
+                    if let Statement::FunctionDecl { params, signature, body, decorators, .. } = method {
+                        for decorator in decorators { self.infer(decorator); }
+                        let (types, result) = self.signature(params, signature.as_deref());
+                        self.function(params, types, result, body);
+                    }
+                }
+            }
+            Statement::Export { declaration, default_value, .. } => {
+                if let Some(declaration) = declaration { self.statement(declaration); }
+                if let Some(value) = default_value { self.infer(value); }
+            }
+            Statement::Block { statements, .. } => self.block(statements),
+            // Either branch may be compiled, in the scope around it
+            Statement::Conditional { then_branch, else_branch, .. } => {
+                for stmt in then_branch.iter().chain(else_branch.iter().flatten()) {
+                    self.statement(stmt);
+                }
+            }
+            Statement::Return { value: None, .. } | Statement::Break { .. } | Statement::Continue { .. }
+            | Statement::Debugger { .. } | Statement::Import { .. } | Statement::Error { .. } => {}
+        }
+    }
+
+    fn function(&mut self, params: &[Name], types: Vec<Type>, result: Option<Type>, body: &[Statement]) {
+        self.scopes.push(HashMap::new());
+        for (param, ty) in params.iter().zip(types) {
+            self.declare(param, ty);
+        }
+        let saved = std::mem::replace(&mut self.result, result);
+        self.statements(body);
+        self.result = saved;
+        self.scopes.pop();
+    }
+
+    /// Checks `expr` against `expected`, looking inside literals
+    fn check(&mut self, expr: &Expression, expected: &Type) {
+        match (expr, expected) {
+            (_, Type::Any) => { self.infer(expr); }
+            (Expression::Array { elements, .. }, Type::Array(element)) => {
+                for item in elements { self.check(item, element); }
+            }
+            (Expression::Object { properties, span }, Type::Object(fields)) => {
+                for (name, value) in properties {
+                    match fields.iter().find(|(field, _)| field == name) {
+                        Some((_, field)) => self.check(value, field),
+                        None => { self.infer(value); }
+                    }
+                }
+                for (name, field) in fields.iter().filter(|(name, _)| !properties.iter().any(|(property, _)| property == name)) {
+                    let message = format!("Ce manca '{}', ca adda essere nu '{}'", name, field);
+                    self.warnings.push(Diagnostic::warning("type-mismatch", message, *span));
+                }
+            }
+            (Expression::ArrowFunction { params, body, .. }, Type::Function { params: types, result }) => {
+                let types = params.iter().enumerate().map(|(i, _)| types.get(i).cloned().unwrap_or(Type::Any)).collect();
+                match body {
+                    ArrowBody::Expression(value) => {
+                        self.scopes.push(HashMap::new());
+                        for (param, ty) in params.iter().zip(types) { self.declare(param, ty); }
+                        if **result == Type::Undefined { self.infer(value); } else { self.check(value, result); }
+                        self.scopes.pop();
+                    }
+                    ArrowBody::Block(body) => self.function(params, types, Some((**result).clone()), body),
+                }
+            }
+            (Expression::Ternary { condition, consequent, alternate, .. }, _) => {
+                self.infer(condition);
+                self.check(consequent, expected);
+                self.check(alternate, expected);
+            }
+            _ => {
+                let actual = self.infer(expr);
+                if !expected.accepts(&actual) {
+                    self.mismatch(expected, &actual, expr.span());
+                }
+            }
+        }
+    }
+
+    /// Checks that an operand of an arithmetic `operator` is a number
+    fn number(&mut self, operand: &Expression, operator: &str) {
+        let ty = self.infer(operand);
+        if !Type::Number.accepts(&ty) {
+            self.warnings.push(Diagnostic::warning("type-mismatch", format!("'{}' vo' nu 'nummero', ma chist è nu '{}'", operator, ty), operand.span()));
+        }
+    }
+
+    fn infer(&mut self, expr: &Expression) -> Type {
+        match expr {
+            Expression::Identifier { name, .. } => self.lookup(name),
+            Expression::Number { .. } => Type::Number,
+            Expression::String { .. } | Expression::TypeOf { .. } => {
+                if let Expression::TypeOf { operand, .. } = expr { self.infer(operand); }
+                Type::String
+            }
+            Expression::Template { expressions, .. } => {
+                for expression in expressions { self.infer(expression); }
+                Type::String
+            }
+            Expression::Boolean { .. } => Type::Boolean,
+            Expression::Null { .. } => Type::Null,
+            Expression::Undefined { .. } => Type::Undefined,
+            Expression::This { .. } | Expression::Console { .. } | Expression::Promise { .. } | Expression::Error { .. } => Type::Any,
+            Expression::JsxElement { attributes, children, .. } => {
+                for value in attributes.iter().filter_map(|(_, value)| value.as_ref()) { self.infer(value); }
+                for child in children {
+                    if let JsxChild::Expression(child) = child { self.infer(child); }
+                }
+                Type::Any
+            }
+            Expression::Array { elements, .. } => {
+                let types: Vec<Type> = elements.iter().map(|element| self.infer(element)).collect();
+                match types.split_first() {
+                    Some((first, rest)) if rest.iter().all(|ty| ty == first) => Type::Array(Box::new(first.clone())),
+                    _ => Type::Array(Box::new(Type::Any)),
+                }
+            }
+            Expression::Object { properties, .. } => {
+                Type::Object(properties.iter().map(|(name, value)| (name.clone(), self.infer(value))).collect())
+            }
+            Expression::Binary { left, operator, right, .. } => match operator {
+                BinaryOp::Add => match (self.infer(left), self.infer(right)) {
+                    (Type::Number, Type::Number) => Type::Number,
+                    (Type::String, _) | (_, Type::String) => Type::String,
+                    _ => Type::Any,
+                },
+                BinaryOp::Subtract | BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo | BinaryOp::Power => {
+                    self.number(left, operator.to_js());
+                    self.number(right, operator.to_js());
+                    Type::Number
+                }
+                BinaryOp::And | BinaryOp::Or | BinaryOp::Nullish => {
+                    let (left, right) = (self.infer(left), self.infer(right));
+                    if left == right { left } else { Type::Any }
+                }
+                _ => {
+                    self.infer(left);
+                    self.infer(right);
+                    Type::Boolean
+                }
+            },
+            Expression::Unary { operator: UnaryOp::Negate, operand, .. } => {
+                self.number(operand, "-");
+                Type::Number
+            }
+            Expression::Unary { operand, .. } | Expression::Delete { operand, .. } => {
+                self.infer(operand);
+                Type::Boolean
+            }
+            Expression::Assignment { target, value, .. } => {
+                let expected = match target.as_ref() {
+                    Expression::Identifier { name, .. } => self.lookup(name),
+                    other => self.infer(other),
+                };
+                self.check(value, &expected);
+                expected
+            }
+            Expression::Call { callee, arguments, .. } => match self.infer(callee) {
+                Type::Function { params, result } => {
+                    for (i, argument) in arguments.iter().enumerate() {
+                        match params.get(i) {
+                            Some(param) => self.check(argument, param),
+                            None => { self.infer(argument); }
+                        }
+                    }
+                    *result
+                }
+                _ => {
+                    for argument in arguments { self.infer(argument); }
+                    Type::Any
+                }
+            },
+            Expression::Member { object, property, computed, .. } => {
+                let object = self.infer(object);
+                match (object, property.as_ref(), computed) {
+                    (Type::Object(fields), Expression::Identifier { name, .. }, false) => {
+                        fields.into_iter().find(|(field, _)| field == name).map_or(Type::Any, |(_, ty)| ty)
+                    }
+                    (Type::Array(_) | Type::String, Expression::Identifier { name, .. }, false) if &**name == "length" => Type::Number,
+                    (object, property, true) => match (object, self.infer(property)) {
+                        (Type::Array(element), Type::Number | Type::Any) => *element,
+                        (Type::String, Type::Number) => Type::String,
+                        _ => Type::Any,
+                    },
+                    _ => Type::Any,
+                }
+            }
+            Expression::New { arguments, .. } => {
+                for argument in arguments { self.infer(argument); }
+                Type::Any
+            }
+            Expression::ArrowFunction { params, body, .. } => match body {
+                ArrowBody::Expression(value) => {
+                    self.scopes.push(HashMap::new());
+                    for param in params { self.declare(param, Type::Any); }
+                    let result = self.infer(value);
+                    self.scopes.pop();
+                    Type::Function { params: vec![Type::Any; params.len()], result: Box::new(result) }
+                }
+                ArrowBody::Block(body) => {
+                    self.function(params, vec![Type::Any; params.len()], None, body);
+                    Type::Function { params: vec![Type::Any; params.len()], result: Box::new(Type::Any) }
+                }
+            },
+            Expression::Await { argument, .. } => {
+                self.infer(argument);
+                Type::Any
+            }
+            Expression::Ternary { condition, consequent, alternate, .. } => {
+                self.infer(condition);
+                let (consequent, alternate) = (self.infer(consequent), self.infer(alternate));
+                if consequent == alternate { consequent } else { Type::Any }
+            }
+            // No arm matching gives `boh`, so any type could come out
+            Expression::Match { subject, arms, .. } => {
+                self.infer(subject);
+                for arm in arms {
+                    self.scopes.push(HashMap::new());
+                    for (name, _) in arm.pattern.bindings() { self.declare(name, Type::Any); }
+                    if let Some(guard) = &arm.guard { self.infer(guard); }
+                    self.infer(&arm.body);
+                    self.scopes.pop();
+                }
+                Type::Any
+            }
+            Expression::ConsoleLog { arguments, .. } | Expression::ConsoleWarn { arguments, .. } | Expression::ConsoleError { arguments, .. } => {
+                for argument in arguments { self.infer(argument); }
+                Type::Undefined
+            }
+            Expression::SuperCall { arguments, .. } => {
+                for argument in arguments { self.infer(argument); }
+                Type::Any
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(source: &str) -> Vec<String> {
+        check(&crate::parse_source(source).unwrap()).into_iter().map(|w| w.message).collect()
+    }
+
+    #[test]
+    fn test_annotations_and_inference() {
+        assert_eq!(messages("tien x: nummero = \"uno\""), ["Aspettavo nu 'nummero', ma chist è nu 'parola'"]);
+        assert_eq!(messages("tien x = 1\nx = \"uno\""), ["Aspettavo nu 'nummero', ma chist è nu 'parola'"]);
+        assert_eq!(messages("tien x: parola[] = [\"a\", 2]"), ["Aspettavo nu 'parola', ma chist è nu 'nummero'"]);
+        assert_eq!(messages("tien p: { nome: parola, età: nummero } = { nome: 1 }").len(), 2);
+        assert_eq!(messages("tien x: nummer = 1")[0], "Nun saccio che tipo è 'nummer'");
+        // Without annotations, nothing to say
+        assert!(messages("tien x = nisciun\nx = 1\nchist è f = (a) => a * 2\nf(\"x\").length").is_empty());
+    }
+
+    #[test]
+    fn test_functions() {
+        let source = "facc saluta(nome: parola): parola {\n    piglie 42\n}\nsaluta(1)\nchist è n: nummero = saluta(\"Ciro\")";
+        assert_eq!(messages(source), [
+            "Aspettavo nu 'parola', ma chist è nu 'nummero'",
+            "Aspettavo nu 'parola', ma chist è nu 'nummero'",
+            "Aspettavo nu 'nummero', ma chist è nu 'parola'",
+        ]);
+        // The expected type flows into arrow functions
+        assert_eq!(messages("tien f: (nummero) => parola = (x) => x * 2").len(), 1);
+        assert!(messages("tien f: (nummero) => boh = (x) => x * 2").is_empty());
+        assert_eq!(messages("tien x = \"a\" - 1"), ["'-' vo' nu 'nummero', ma chist è nu 'parola'"]);
+    }
+
+    #[test]
+    fn test_accepts() {
+        let object = |fields: &[(&str, Type)]| Type::Object(fields.iter().map(|(name, ty)| (Name::from(*name), ty.clone())).collect());
+        assert!(object(&[("a", Type::Number)]).accepts(&object(&[("a", Type::Number), ("b", Type::String)])));
+        assert!(!object(&[("a", Type::Number), ("b", Type::String)]).accepts(&object(&[("a", Type::Number)])));
+        assert!(Type::Array(Box::new(Type::Number)).accepts(&Type::Array(Box::new(Type::Any))));
+        assert_eq!(Type::Array(Box::new(Type::Function { params: vec![Type::Number], result: Box::new(Type::Undefined) })).to_string(), "((nummero) => boh)[]");
+    }
+}
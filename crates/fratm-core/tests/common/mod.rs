@@ -0,0 +1,45 @@
+//! Helpers shared by the integration tests
+
+// Not every test uses all of them
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// The `.fratm` files in `tests/DIR`, sorted
+pub fn fixtures(dir: &str) -> Vec<PathBuf> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join(dir);
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "fratm"))
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no fixtures in {}", dir.display());
+    fixtures
+}
+
+/// The Node.js binary in `FRATM_NODE`, if the tests that need one should run
+pub fn node() -> Option<String> {
+    std::env::var("FRATM_NODE").ok().filter(|node| !node.is_empty())
+}
+
+/// Runs `code` with `node` and returns what it printed on stdout
+pub fn run_node(node: &str, code: &str) -> String {
+    let mut child = Command::new(node)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|e| panic!("can't start {}: {}", node, e));
+    child.stdin.take().unwrap().write_all(code.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let mut stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if !output.status.success() {
+        stdout.push_str(&format!("--- {}\n{}", output.status, String::from_utf8_lossy(&output.stderr)));
+    }
+    stdout
+}
@@ -0,0 +1,124 @@
+//! Snapshot tests of the whole pipeline
+//!
+//! Every `tests/fixtures/NAME.fratm` is compiled and the result compared
+//! with the snapshots next to it:
+//!
+//! - `NAME.js`: the generated code
+//! - `NAME.map`: the source map, only checked if the file exists (create
+//!   it empty and bless to start)
+//! - `NAME.stderr`: the error, or the warnings, if there are any
+//!
+//! After a change to the output, run `FRATM_BLESS=1 cargo test --test fixtures`
+//! to rewrite the snapshots and review them in the diff.
+//!
+//! With `FRATM_NODE` set to a Node.js binary (`FRATM_NODE=node`), the code
+//! of the fixtures that have a `NAME.stdout` is also run, and what it
+//! prints compared with that file. This catches generated code that reads
+//! fine but doesn't do what the source says.
+
+#![cfg(feature = "serde")]
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use fratm_core::render::{render_diagnostic, RenderOptions};
+use fratm_core::CompileOptions;
+use pretty_assertions::StrComparison;
+
+#[derive(Default)]
+struct Output {
+    js: Option<String>,
+    map: Option<String>,
+    stderr: String,
+}
+
+fn run(path: &Path, source: &str, source_map: bool) -> Output {
+    let name = path.file_name().unwrap().to_string_lossy();
+    let options = CompileOptions::builder()
+        .filename(&*name)
+        .output_filename(name.replace(".fratm", ".js"))
+        .source_map(source_map)
+        .build();
+    match fratm_core::compile(source, options) {
+        Ok(result) => Output {
+            js: Some(result.code),
+            map: result.source_map.map(|map| map.to_json_pretty() + "\n"),
+            stderr: result.warnings.iter().map(|warning| format!("{}\n", warning)).collect(),
+        },
+        Err(e) => Output { stderr: render_diagnostic(source, e.diagnostic(), RenderOptions::default()), ..Default::default() },
+    }
+}
+
+/// Compares `actual` with the snapshot at `path`, or writes it when blessing.
+/// `None` means the snapshot must not exist.
+fn check(path: &Path, actual: Option<&str>, bless: bool, failures: &mut Vec<String>) {
+    let expected = fs::read_to_string(path).ok();
+    if expected.as_deref() == actual {
+        return;
+    }
+    if bless {
+        match actual {
+            Some(actual) => fs::write(path, actual).unwrap(),
+            None => fs::remove_file(path).unwrap(),
+        }
+        return;
+    }
+    let failure = match (expected, actual) {
+        (None, _) => "missing snapshot".to_string(),
+        (Some(_), None) => "snapshot of nothing: the file should not exist".to_string(),
+        (Some(expected), Some(actual)) => StrComparison::new(&expected, actual).to_string(),
+    };
+    failures.push(format!("{}: {}", path.display(), failure));
+}
+
+fn bless() -> bool {
+    std::env::var_os("FRATM_BLESS").is_some_and(|value| value != "0")
+}
+
+fn assert_no_failures(failures: Vec<String>) {
+    assert!(
+        failures.is_empty(),
+        "{} snapshot(s) differ (left is the snapshot), rerun with FRATM_BLESS=1 to update them:\n\n{}",
+        failures.len(),
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn test_fixtures() {
+    let bless = bless();
+    let mut failures = Vec::new();
+    for path in &common::fixtures("fixtures") {
+        let source = fs::read_to_string(path).unwrap();
+        let map_path = path.with_extension("map");
+        let output = run(path, &source, map_path.exists());
+        check(&path.with_extension("js"), output.js.as_deref(), bless, &mut failures);
+        if map_path.exists() {
+            check(&map_path, output.map.as_deref(), bless, &mut failures);
+        }
+        let stderr = Some(output.stderr.as_str()).filter(|stderr| !stderr.is_empty());
+        check(&path.with_extension("stderr"), stderr, bless, &mut failures);
+    }
+    assert_no_failures(failures);
+}
+
+#[test]
+fn test_fixtures_under_node() {
+    let Some(node) = common::node() else {
+        eprintln!("FRATM_NODE is not set, not running the fixtures");
+        return;
+    };
+    let bless = bless();
+    let mut failures = Vec::new();
+    for path in common::fixtures("fixtures").iter().filter(|path| path.with_extension("stdout").exists()) {
+        let source = fs::read_to_string(path).unwrap();
+        let Some(js) = run(path, &source, false).js else {
+            failures.push(format!("{}: doesn't compile", path.display()));
+            continue;
+        };
+        check(&path.with_extension("stdout"), Some(&common::run_node(&node, &js)), bless, &mut failures);
+    }
+    assert_no_failures(failures);
+}
@@ -0,0 +1,14 @@
+let x = 5;
+if (((x > 3) && (x !== 10))) {
+  console.log("gruosso");
+} else if ((x === 3)) {
+  console.log("tre");
+} else {
+  console.log("piccirillo");
+}
+while ((x > 0)) {
+  x = (x - 1);
+}
+for (let i = 0; (i < 3); i = (i + 1)) {
+  console.log(i);
+}
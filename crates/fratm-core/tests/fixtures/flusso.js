@@ -0,0 +1,19 @@
+function sconto(prezzo) {
+  if ((prezzo > 10)) {
+    return (prezzo - 2);
+  }
+}
+function cerca(lista, cosa) {
+  for (let i = 0; (i < lista.length); i = (i + 1)) {
+    if ((lista[i] === cosa)) {
+      return i;
+    }
+  }
+  return -1;
+  console.log("nun ce arrive maje");
+}
+function aspetta() {
+  while (true) {
+    console.log("n'ato ppoco");
+  }
+}
@@ -0,0 +1,11 @@
+function somma(a, b) {
+  return (a + b);
+}
+const doppio = (n) => (n * 2);
+function fattoriale(n) {
+  if ((n <= 1)) {
+    return 1;
+  }
+  return (n * fattoriale((n - 1)));
+}
+console.log(somma(1, 2), doppio(4), fattoriale(5));
@@ -0,0 +1,4 @@
+function saluta(nome) {
+  return ("Uè " + nome);
+}
+console.log(saluta("Totò"));
@@ -0,0 +1,5 @@
+import { leggi } from "./file";
+export function calcolaIVA(prezzo) {
+  return (prezzo * 1.22);
+}
+export const aliquota = 22;
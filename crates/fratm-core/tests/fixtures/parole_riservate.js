@@ -0,0 +1,2 @@
+let $delete = 1;
+console.log($delete);
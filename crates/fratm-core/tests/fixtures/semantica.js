@@ -0,0 +1,19 @@
+console.log((2 ** (3 ** 2)), (-2 + (3 * 4)), ((1 + 2) * 3), (7 % 4));
+console.log(((1 + 2) + "3"), (("1" + 2) + 3));
+console.log("virgolette \" e 'apici' e \\ e\ttab");
+console.log((true && false), (true || false), !true);
+let n = 0;
+function conta() {
+  n = (n + 1);
+  return n;
+}
+console.log(((conta() > 0) ? "overo" : "sfòls"), n);
+const crea = (base) => (x) => (base + x);
+console.log(crea(10)(5));
+try {
+  throw new Error("ahia");
+} catch (err) {
+  console.log(("acchiappato: " + err.message));
+}
+const dati = { a: 1, b: { c: [1, 2, 3] } };
+console.log(dati.b.c[1], dati["a"]);
@@ -0,0 +1,5 @@
+let contatore = 0;
+const nome = "Gennaro";
+const prezzo = ((2 ** 3) + (1.5 * (4 - 1)));
+contatore = (contatore + 1);
+console.log((((("Uè " + nome) + ", so' ") + prezzo) + " euro"));
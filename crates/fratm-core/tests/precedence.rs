@@ -0,0 +1,125 @@
+//! Operator precedence against JavaScript's
+//!
+//! For every pair of operators, `a op1 b op2 c` (and `op1 a op2 b` for
+//! unary ones) must group the way JavaScript groups it. The grouping in the
+//! generated code is checked against JavaScript's precedence table, and,
+//! with `FRATM_NODE` set to a Node.js binary, the generated and the plain
+//! JavaScript expression are evaluated on a few values and must agree.
+
+mod common;
+
+/// (FratmScript, JavaScript, JavaScript precedence, right-associative)
+const BINARY: &[(&str, &str, u8, bool)] = &[
+    ("o", "||", 3, false),
+    ("e", "&&", 4, false),
+    ("pure", "&&", 4, false),
+    ("==", "==", 8, false),
+    ("!=", "!=", 8, false),
+    ("===", "===", 8, false),
+    ("!==", "!==", 8, false),
+    ("<", "<", 9, false),
+    ("<=", "<=", 9, false),
+    (">", ">", 9, false),
+    (">=", ">=", 9, false),
+    ("+", "+", 11, false),
+    ("-", "-", 11, false),
+    ("*", "*", 12, false),
+    ("/", "/", 12, false),
+    ("%", "%", 12, false),
+    ("**", "**", 13, true),
+];
+
+/// (FratmScript, JavaScript), all binding tighter than any binary operator
+const UNARY: &[(&str, &str)] = &[("-", "-"), ("!", "!"), ("manco ", "!")];
+
+struct Case {
+    source: String,
+    /// The expression as JavaScript groups it, with parentheses only where
+    /// JavaScript requires them
+    javascript: String,
+    /// The generated code, without the `;`
+    generated: String,
+}
+
+fn generated(source: &str) -> String {
+    let code = fratm_core::compile(source, Default::default()).unwrap_or_else(|e| panic!("{}: {}", source, e.message)).code;
+    code.trim_end().trim_end_matches(';').to_string()
+}
+
+fn cases() -> Vec<Case> {
+    let mut cases = Vec::new();
+    for &(fratm1, js1, _, _) in BINARY {
+        for &(fratm2, js2, _, _) in BINARY {
+            let source = format!("a {} b {} c", fratm1, fratm2);
+            cases.push(Case { generated: generated(&source), source, javascript: format!("a {} b {} c", js1, js2) });
+        }
+    }
+    for &(fratm1, js1) in UNARY {
+        for &(fratm2, js2, _, _) in BINARY {
+            // JavaScript rejects `-a ** b` as ambiguous, and so does FratmScript
+            let (source, javascript) = if js2 == "**" {
+                (format!("({}a) ** b", fratm1), format!("({}a) ** b", js1))
+            } else {
+                (format!("{}a {} b", fratm1, fratm2), format!("{}a {} b", js1, js2))
+            };
+            cases.push(Case { generated: generated(&source), source, javascript });
+        }
+    }
+    cases
+}
+
+#[test]
+fn test_binary_operators_group_like_javascript() {
+    for &(fratm1, js1, precedence1, right1) in BINARY {
+        for &(fratm2, js2, precedence2, _) in BINARY {
+            let source = format!("a {} b {} c", fratm1, fratm2);
+            let left_first = precedence1 > precedence2 || (precedence1 == precedence2 && !right1);
+            let expected = if left_first { format!("((a {} b) {} c)", js1, js2) } else { format!("(a {} (b {} c))", js1, js2) };
+            assert_eq!(generated(&source), expected, "{}", source);
+        }
+    }
+}
+
+#[test]
+fn test_unary_operators_bind_tighter() {
+    for &(fratm1, js1) in UNARY {
+        for &(fratm2, js2, _, _) in BINARY {
+            let source = format!("{}a {} b", fratm1, fratm2);
+            if js2 == "**" {
+                assert!(fratm_core::compile(&source, Default::default()).is_err(), "{}", source);
+                assert_eq!(generated(&format!("({}a) ** b", fratm1)), format!("(({}a) ** b)", js1));
+                assert_eq!(generated(&format!("{}(a ** b)", fratm1)), format!("{}(a ** b)", js1));
+            } else {
+                assert_eq!(generated(&source), format!("({}a {} b)", js1, js2), "{}", source);
+            }
+        }
+    }
+    assert_eq!(generated("- -a"), "-(-a)");
+}
+
+#[test]
+fn test_precedence_under_node() {
+    let Some(node) = common::node() else {
+        eprintln!("FRATM_NODE is not set, not evaluating under Node");
+        return;
+    };
+    let cases: Vec<String> = cases()
+        .iter()
+        .map(|case| format!("  [{:?}, (a, b, c) => {}, (a, b, c) => {}],\n", case.source, case.javascript, case.generated))
+        .collect();
+    let script = format!(
+        r#"const values = [[2, 3, 4], [0, 5, 1], [7, 2, 3], [-1, 2, 0], [1, 1, 2]]
+const cases = [
+{}]
+for (const [source, javascript, generated] of cases) {{
+  for (const [a, b, c] of values) {{
+    const expected = String(javascript(a, b, c)), actual = String(generated(a, b, c))
+    if (actual !== expected) console.log(`${{source}} with ${{a}}, ${{b}}, ${{c}}: ${{actual}} instead of ${{expected}}`)
+  }}
+}}
+"#,
+        cases.concat()
+    );
+    let output = common::run_node(&node, &script);
+    assert!(output.is_empty(), "evaluated differently from JavaScript:\n{}", output);
+}
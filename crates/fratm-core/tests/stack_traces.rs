@@ -0,0 +1,110 @@
+//! Source maps against Node's stack traces
+//!
+//! Every `tests/stack_traces/NAME.fratm` ends in an uncaught error. Under
+//! `node --enable-source-maps`, the frames of its stack trace that are in
+//! the source must be where the source says, marked with a `^N` on a
+//! comment line under the construct: frame `N` (the top one is 1) is at
+//! that column of the line above.
+//!
+//! ```text
+//! piglie stu cos.forno.accendi(pizza)
+//! //                   ^1
+//! ```
+//!
+//! Only runs with `FRATM_NODE` set to a Node.js binary (`FRATM_NODE=node`);
+//! otherwise the fixtures are only compiled.
+
+#![cfg(feature = "sourcemap")]
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use fratm_core::CompileOptions;
+
+/// The JavaScript of the fixture at `path`, with its source map inline
+fn compile(path: &Path, source: &str) -> String {
+    let name = path.file_name().unwrap().to_string_lossy();
+    let options = CompileOptions::builder()
+        .filename(&*name)
+        .output_filename(name.replace(".fratm", ".js"))
+        .source_map(true)
+        .build();
+    let result = fratm_core::compile(source, options).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    format!("{}\n{}\n", result.code, result.source_map.unwrap().to_data_url())
+}
+
+/// The `(line, column)` of each frame marked in `source`, the top first
+fn marked_frames(source: &str) -> Vec<(usize, usize)> {
+    let mut marks = Vec::new();
+    let mut code_line = 0;
+    for (i, line) in source.lines().enumerate() {
+        if !line.trim_start().starts_with("//") {
+            code_line = i + 1;
+            continue;
+        }
+        let chars: Vec<char> = line.chars().collect();
+        for (column, _) in chars.iter().enumerate().filter(|(_, &c)| c == '^') {
+            let frame: String = chars[column + 1..].iter().take_while(|c| c.is_ascii_digit()).collect();
+            marks.push((frame.parse::<usize>().unwrap(), (code_line, column + 1)));
+        }
+    }
+    marks.sort();
+    assert!(marks.iter().enumerate().all(|(i, (frame, _))| *frame == i + 1), "frames marked out of sequence: {:?}", marks);
+    marks.into_iter().map(|(_, position)| position).collect()
+}
+
+/// The `(line, column)` of each frame of the stack traces in `stderr`
+/// that is in the file called `name`, the top first
+fn frames(stderr: &str, name: &str) -> Vec<(usize, usize)> {
+    stderr
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("at "))
+        .filter_map(|frame| {
+            let location = frame.strip_suffix(')').map_or(frame, |frame| frame.rsplit_once('(').map_or(frame, |(_, location)| location));
+            let (location, column) = location.rsplit_once(':')?;
+            let (path, line) = location.rsplit_once(':')?;
+            if Path::new(path).file_name()? != name {
+                return None;
+            }
+            Some((line.parse().ok()?, column.parse().ok()?))
+        })
+        .collect()
+}
+
+#[test]
+fn test_stack_traces() {
+    let compiled: Vec<(PathBuf, String, String)> = common::fixtures("stack_traces")
+        .into_iter()
+        .map(|path| {
+            let source = fs::read_to_string(&path).unwrap();
+            let js = compile(&path, &source);
+            (path, source, js)
+        })
+        .collect();
+    let Some(node) = common::node() else {
+        eprintln!("FRATM_NODE is not set, not running the stack traces");
+        return;
+    };
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("stack_traces");
+    fs::create_dir_all(&dir).unwrap();
+    let mut failures = Vec::new();
+    for (path, source, js) in &compiled {
+        let name = path.file_name().unwrap().to_string_lossy();
+        let script = dir.join(name.replace(".fratm", ".js"));
+        fs::write(&script, js).unwrap();
+        let output = Command::new(&node)
+            .arg("--enable-source-maps")
+            .arg(&script)
+            .output()
+            .unwrap_or_else(|e| panic!("can't start {}: {}", node, e));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let (expected, actual) = (marked_frames(source), frames(&stderr, &name));
+        if output.status.success() || expected != actual {
+            failures.push(format!("{}: expected the frames at {:?}, got {:?} from\n{}", path.display(), expected, actual, stderr));
+        }
+    }
+    assert!(failures.is_empty(), "{} stack trace(s) differ:\n\n{}", failures.len(), failures.join("\n\n"));
+}
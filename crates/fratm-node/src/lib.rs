@@ -0,0 +1,297 @@
+//! Native Node.js bindings for FratmScript
+//!
+//! Bundler plugins and the VS Code extension load this addon to call the
+//! compiler in-process instead of spawning the CLI for every file. Every
+//! function returns a plain object; `napi build` writes its TypeScript
+//! types to `index.d.ts`.
+//!
+//! Problems in the source are part of the result (`success: false` and a
+//! list of diagnostics); only invalid options throw.
+
+use fratm_core::errors::{Diagnostic as CoreDiagnostic, Locale, Severity};
+use fratm_core::line_index::LineIndex;
+use napi::{Error, Result};
+use napi_derive::napi;
+use serde::de::DeserializeOwned;
+
+/// Options of `compile`, mirroring the core `CompileOptions`
+#[napi(object)]
+#[derive(Default)]
+pub struct CompileOptions {
+    pub source_map: Option<bool>,
+    pub filename: Option<String>,
+    pub output_filename: Option<String>,
+    pub minify: Option<bool>,
+    pub optimize: Option<bool>,
+    #[napi(ts_type = "'es2015' | 'es2020' | 'esnext'")]
+    pub target: Option<String>,
+    #[napi(ts_type = "'esm' | 'commonjs' | 'script'")]
+    pub module_format: Option<String>,
+    /// Global object a 'script' exports to (Fratm by default)
+    pub global_name: Option<String>,
+    #[napi(ts_type = "'nap' | 'en'")]
+    pub locale: Option<String>,
+    /// Comment put before the code
+    pub banner: Option<String>,
+    /// Comment put after the code
+    pub footer: Option<String>,
+    /// Start CommonJS output with "use strict"
+    pub use_strict: Option<bool>,
+    /// Copy source comments into the output
+    pub comments: Option<bool>,
+    /// What "giura che" becomes: a throw, console.assert, or nothing
+    #[napi(ts_type = "'throw' | 'console' | 'strip'")]
+    pub assertions: Option<String>,
+    /// Fail with a "too-large" error past these sizes
+    pub limits: Option<Limits>,
+}
+
+/// How large a compilation may get, for sources that can't be trusted;
+/// mirroring the core `Limits`
+#[napi(object)]
+#[derive(Default)]
+pub struct Limits {
+    pub max_source_bytes: Option<u32>,
+    pub max_tokens: Option<u32>,
+    pub max_nodes: Option<u32>,
+    pub max_output_bytes: Option<u32>,
+}
+
+impl From<Limits> for fratm_core::limits::Limits {
+    fn from(limits: Limits) -> Self {
+        let size = |max: Option<u32>| max.map(|max| max as usize);
+        Self {
+            max_source_bytes: size(limits.max_source_bytes),
+            max_tokens: size(limits.max_tokens),
+            max_nodes: size(limits.max_nodes),
+            max_output_bytes: size(limits.max_output_bytes),
+        }
+    }
+}
+
+/// Options of `format`, mirroring the core `FormatOptions`
+#[napi(object)]
+#[derive(Default)]
+pub struct FormatOptions {
+    pub indent_width: Option<u32>,
+    pub use_tabs: Option<bool>,
+}
+
+/// A problem found in the source. Lines and columns are 1-based, offsets
+/// are UTF-16 (JavaScript string indexes).
+#[napi(object)]
+pub struct Diagnostic {
+    pub code: String,
+    #[napi(ts_type = "'error' | 'warning'")]
+    pub severity: String,
+    pub message: String,
+    pub line: u32,
+    pub column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+    pub start: u32,
+    pub end: u32,
+    pub notes: Vec<String>,
+    pub suggestion: Option<String>,
+}
+
+#[napi(object)]
+pub struct CompileResult {
+    pub success: bool,
+    pub code: Option<String>,
+    pub source_map: Option<String>,
+    pub warnings: Vec<String>,
+    pub errors: Vec<Diagnostic>,
+}
+
+#[napi(object)]
+pub struct ParseResult {
+    pub success: bool,
+    /// The tree as `Program.to_json` writes it: nodes with `type`, `id`,
+    /// `start`, `end` and their fields. Broken parts are `Error` nodes.
+    pub ast: serde_json::Value,
+    pub errors: Vec<Diagnostic>,
+}
+
+#[napi(object)]
+pub struct FormatResult {
+    pub success: bool,
+    pub code: Option<String>,
+    pub errors: Vec<Diagnostic>,
+}
+
+#[napi(object)]
+pub struct LintResult {
+    pub success: bool,
+    /// Lint warnings, `code` being the rule name
+    pub warnings: Vec<Diagnostic>,
+    pub errors: Vec<Diagnostic>,
+}
+
+/// Get the compiler version
+#[napi]
+pub fn version() -> String {
+    fratm_core::version().to_string()
+}
+
+/// Compile FratmScript source to JavaScript
+#[napi]
+pub fn compile(source: String, options: Option<CompileOptions>) -> Result<CompileResult> {
+    let options = options.unwrap_or_default();
+    let mut builder = fratm_core::CompileOptions::builder()
+        .source_map(options.source_map.unwrap_or(false))
+        .minify(options.minify.unwrap_or(false))
+        .optimize(options.optimize.unwrap_or(false))
+        .use_strict(options.use_strict.unwrap_or(false))
+        .comments(options.comments.unwrap_or(false));
+    if let Some(target) = option(options.target)? {
+        builder = builder.target(target);
+    }
+    if let Some(module_format) = option(options.module_format)? {
+        builder = builder.module_format(module_format);
+    }
+    let locale = option(options.locale)?.unwrap_or_default();
+    builder = builder.locale(locale);
+    if let Some(assertions) = option(options.assertions)? {
+        builder = builder.assertions(assertions);
+    }
+    if let Some(banner) = &options.banner {
+        builder = builder.banner(banner);
+    }
+    if let Some(footer) = &options.footer {
+        builder = builder.footer(footer);
+    }
+    if let Some(filename) = &options.filename {
+        builder = builder.filename(filename);
+    }
+    if let Some(output_filename) = &options.output_filename {
+        builder = builder.output_filename(output_filename);
+    }
+    if let Some(global_name) = &options.global_name {
+        builder = builder.global_name(global_name);
+    }
+    if let Some(limits) = options.limits {
+        builder = builder.limits(limits.into());
+    }
+
+    Ok(match fratm_core::compile(&source, builder.build()) {
+        Ok(result) => CompileResult {
+            success: true,
+            code: Some(result.code),
+            source_map: result.source_map.map(|map| map.to_json()),
+            warnings: result.warnings,
+            errors: vec![],
+        },
+        Err(e) => {
+            let mut diagnostic = diagnostic(e.diagnostic().clone(), &LineIndex::new(&source));
+            diagnostic.message = e.localized(locale);
+            CompileResult { success: false, code: None, source_map: None, warnings: vec![], errors: vec![diagnostic] }
+        }
+    })
+}
+
+/// Parse source code into an AST, recovering from errors
+#[napi]
+pub fn parse(source: String) -> ParseResult {
+    let (program, errors) = fratm_core::parse_recovering(&source);
+    let errors = diagnostics(errors, &source);
+    ParseResult { success: errors.is_empty(), ast: program.to_json(), errors }
+}
+
+/// Format source code
+#[napi]
+pub fn format(source: String, options: Option<FormatOptions>) -> FormatResult {
+    let options = options.unwrap_or_default();
+    let defaults = fratm_core::formatter::FormatOptions::default();
+    let options = fratm_core::formatter::FormatOptions {
+        indent_width: options.indent_width.map_or(defaults.indent_width, |width| width as usize),
+        use_tabs: options.use_tabs.unwrap_or(defaults.use_tabs),
+    };
+    match fratm_core::formatter::format(&source, &options) {
+        Ok(code) => FormatResult { success: true, code: Some(code), errors: vec![] },
+        Err(e) => FormatResult { success: false, code: None, errors: diagnostics(vec![e.diagnostic().clone()], &source) },
+    }
+}
+
+/// Lint source code
+#[napi]
+pub fn lint(source: String) -> LintResult {
+    match fratm_core::linter::lint(&source, Locale::default()) {
+        Ok(warnings) => LintResult { success: true, warnings: diagnostics(warnings, &source), errors: vec![] },
+        Err(e) => LintResult { success: false, warnings: vec![], errors: diagnostics(vec![e.diagnostic().clone()], &source) },
+    }
+}
+
+/// An enum option given by its name, such as `"es2015"`
+fn option<T: DeserializeOwned>(value: Option<String>) -> Result<Option<T>> {
+    value
+        .map(|name| serde_json::from_value(serde_json::Value::String(name)))
+        .transpose()
+        .map_err(|e| Error::from_reason(format!("Opzioni sbagliate: {}", e)))
+}
+
+fn diagnostics(diagnostics: Vec<CoreDiagnostic>, source: &str) -> Vec<Diagnostic> {
+    let index = LineIndex::new(source);
+    diagnostics.into_iter().map(|d| diagnostic(d, &index)).collect()
+}
+
+fn diagnostic(diagnostic: CoreDiagnostic, index: &LineIndex) -> Diagnostic {
+    let span = diagnostic.span;
+    Diagnostic {
+        code: diagnostic.code.into_owned(),
+        severity: match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+        .to_string(),
+        message: diagnostic.message,
+        line: span.line as u32,
+        column: span.column as u32,
+        end_line: span.end_line as u32,
+        end_column: span.end_column as u32,
+        start: index.utf16_offset(span.start) as u32,
+        end: index.utf16_offset(span.end) as u32,
+        notes: diagnostic.notes,
+        suggestion: diagnostic.suggestion,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile() {
+        let options = CompileOptions { module_format: Some("commonjs".into()), source_map: Some(true), ..Default::default() };
+        let result = compile("mann for chist è x = 42".into(), Some(options)).unwrap();
+        assert!(result.success);
+        assert!(result.code.unwrap().contains("exports.x = x;"));
+        assert!(result.source_map.is_some());
+
+        let result = compile("chist x = 42".into(), None).unwrap();
+        assert!(!result.success);
+        assert_eq!((result.errors[0].line, result.errors[0].column), (1, 7));
+
+        let options = CompileOptions { target: Some("es1999".into()), ..Default::default() };
+        assert!(compile("".into(), Some(options)).is_err());
+
+        let limits = Limits { max_output_bytes: Some(10), ..Default::default() };
+        let result = compile("chist è x = 42".into(), Some(CompileOptions { limits: Some(limits), ..Default::default() })).unwrap();
+        assert_eq!(result.errors[0].code, "too-large");
+    }
+
+    #[test]
+    fn test_parse_format_and_lint() {
+        let result = parse("tien x = sfòls\nchist x".into());
+        assert!(!result.success);
+        assert_eq!(result.ast["statements"].as_array().map(Vec::len), Some(2));
+        // "ò" is two bytes but one UTF-16 unit
+        assert_eq!((result.errors[0].start, result.errors[0].line), (21, 2));
+
+        let result = format("tien   x=1+2".into(), Some(FormatOptions { indent_width: Some(4), use_tabs: None }));
+        assert_eq!(result.code.as_deref(), Some("tien x = 1 + 2\n"));
+
+        let result = lint("tien x = 1".into());
+        assert_eq!((result.warnings[0].code.as_str(), result.warnings[0].severity.as_str()), ("unused-variable", "warning"));
+    }
+}
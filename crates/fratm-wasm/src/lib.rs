@@ -3,7 +3,284 @@
 //! Enables running the compiler in the browser for the playground.
 
 use wasm_bindgen::prelude::*;
-use fratm_core::{compile as core_compile, CompileOptions};
+use fratm_core::{compile as core_compile, Assertions, CodegenStyle, CompileOptions, Decorators, Jsx, ModuleFormat, Target};
+use fratm_core::line_index::{LineIndex, Range};
+use fratm_core::errors::{Diagnostic, Locale, Severity};
+use fratm_core::fix::Fix;
+use fratm_core::ide::FoldingKind;
+use fratm_core::interpreter::Interpreter;
+use fratm_core::lexer::keywords::{DialectPack, KeywordTable};
+use fratm_core::lexer::Span;
+use fratm_core::limits::Limits;
+use fratm_core::parser::incremental::Parse;
+use fratm_core::parser::{ParserOptions, DEFAULT_MAX_DEPTH};
+use fratm_core::render::{render_code_frame, RenderOptions};
+use fratm_core::semantic::{DocumentSymbol, SymbolKind};
+
+/// TypeScript shapes of the values returned to JavaScript.
+///
+/// wasm-bindgen copies this into the generated `fratm_wasm.d.ts`, so the
+/// typings are rebuilt with the crate. Keep it in sync with the response
+/// structs below.
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &str = r#"
+export interface Span {
+    start: number;
+    end: number;
+    line: number;
+    column: number;
+    end_line: number;
+    end_column: number;
+}
+
+export type TokenKind =
+    | string
+    | { Identifier: string }
+    | { String: string }
+    | { Number: number }
+    | { Invalid: string };
+
+export interface Token {
+    kind: TokenKind;
+    span: Span;
+    literal: string;
+}
+
+export type Target = "es2015" | "es2020" | "esnext";
+
+export type ModuleFormat = "esm" | "commonjs" | "script";
+
+export type Locale = "nap" | "en";
+
+export type Quote = "double" | "single";
+
+export type Assertions = "throw" | "console" | "strip";
+
+export type Decorators = "standard" | "legacy";
+
+/** Keywords of a dialect, as Neapolitan word → dialect word */
+export interface DialectPack {
+    name: string;
+    words: Record<string, string>;
+}
+
+export interface CodegenStyle {
+    indentWidth?: number;
+    useTabs?: boolean;
+    quote?: Quote;
+    semicolons?: boolean;
+}
+
+/** What JSX elements compile to */
+export type Jsx =
+    | { runtime: "classic"; factory: string; fragment: string }
+    | { runtime: "automatic"; importSource: string };
+
+/** How large a compilation may get, for input that can't be trusted */
+export interface Limits {
+    maxSourceBytes?: number;
+    maxTokens?: number;
+    maxNodes?: number;
+    maxOutputBytes?: number;
+}
+
+export interface CompileOptions {
+    sourceMap?: boolean;
+    filename?: string;
+    outputFilename?: string;
+    minify?: boolean;
+    target?: Target;
+    moduleFormat?: ModuleFormat;
+    /** Global object a "script" exports to (Fratm by default) */
+    globalName?: string;
+    optimize?: boolean;
+    locale?: Locale;
+    style?: CodegenStyle;
+    /** Comment put before the code */
+    banner?: string;
+    /** Comment put after the code */
+    footer?: string;
+    /** Start CommonJS output with "use strict" */
+    useStrict?: boolean;
+    /** Copy source comments into the output */
+    comments?: boolean;
+    /** What "giura che" becomes: a throw, console.assert, or nothing */
+    assertions?: Assertions;
+    /** What "@decoratore" becomes: the decorator syntax, or plain calls */
+    decorators?: Decorators;
+    /** Write the source in another dialect (Neapolitan by default) */
+    dialect?: DialectPack;
+    /** Accept JSX elements, compiled to React calls */
+    jsx?: Jsx;
+    /** Read "Ciao {nome}!" as a template literal */
+    interpolation?: boolean;
+    /** Fail with a "too-large" error past these sizes (a megabyte of
+     * source and a few of JavaScript by default) */
+    limits?: Limits;
+    /** Deepest nesting of blocks, expressions and chains (64 by default) */
+    maxDepth?: number;
+}
+
+export interface CompileResponse {
+    success: boolean;
+    code?: string;
+    sourceMap?: string;
+    error?: string;
+    line?: number;
+    column?: number;
+    suggestion?: string;
+    /** Plain-text code frame under the error, as the CLI prints it */
+    frame?: string;
+    options?: CompileOptions;
+}
+
+/** 0-based line and UTF-16 column, as used by Monaco, CodeMirror and LSP */
+export interface TextPosition {
+    line: number;
+    col: number;
+}
+
+export interface TextRange {
+    start: TextPosition;
+    end: TextPosition;
+}
+
+export type Severity = "error" | "warning";
+
+/** A secondary location of a diagnostic, e.g. the `{` that was never closed */
+export interface DiagnosticLabel {
+    message: string;
+    span: Span;
+    range: TextRange;
+}
+
+export interface Diagnostic {
+    code: string;
+    severity: Severity;
+    message: string;
+    span: Span;
+    range: TextRange;
+    labels: DiagnosticLabel[];
+    notes: string[];
+    suggestion?: string;
+}
+
+export type Statement = { [kind: string]: unknown };
+
+export interface Program {
+    statements: Statement[];
+}
+
+export interface ParseResponse {
+    success: boolean;
+    ast: Program;
+    errors: Diagnostic[];
+}
+
+export interface FormatOptions {
+    indentWidth?: number;
+    useTabs?: boolean;
+}
+
+export interface FormatResponse {
+    success: boolean;
+    code?: string;
+    error?: string;
+    line?: number;
+    column?: number;
+}
+
+/** Lint warnings are diagnostics whose `code` is the rule name */
+export type LintWarning = Diagnostic;
+
+export interface LintResponse {
+    success: boolean;
+    warnings: LintWarning[];
+    error?: string;
+    line?: number;
+    column?: number;
+}
+
+export interface KeywordInfo {
+    word: string;
+    phrase: string;
+    kind: TokenKind;
+    javascript: string;
+    description: string;
+    example: string;
+}
+
+/** A program of the playground's examples */
+export interface Example {
+    name: string;
+    title: string;
+    description: string;
+    source: string;
+    /** What it prints when run, one entry per line */
+    output: string[];
+}
+
+export type ConsoleLevel = "log" | "warn" | "error";
+
+export interface ConsoleLine {
+    level: ConsoleLevel;
+    text: string;
+}
+
+export interface RunResponse {
+    success: boolean;
+    output: string[];
+    console: ConsoleLine[];
+    error?: string;
+    line?: number;
+    column?: number;
+}
+
+export interface ReplResponse {
+    /** What the input printed */
+    output: ConsoleLine[];
+    /** The JavaScript of the input, once complete and valid */
+    js?: string;
+    /** The value of the last expression, as the console shows it */
+    value?: string;
+    /** Syntax errors, or the runtime error that stopped the input */
+    diagnostics: Diagnostic[];
+    /** The input goes on: send the next line */
+    incomplete: boolean;
+}
+
+export type CompletionKind = "keyword" | "variable" | "constant" | "function" | "parameter" | "class";
+
+export interface Completion {
+    label: string;
+    kind: CompletionKind;
+    insertText: string;
+    detail?: string;
+}
+
+export type SymbolKind = "function" | "class" | "method" | "variable" | "constant";
+
+export interface DocumentSymbol {
+    name: string;
+    kind: SymbolKind;
+    span: Span;
+    range: TextRange;
+    children: DocumentSymbol[];
+}
+
+export type FoldingKind = "region" | "comment";
+
+export interface FoldingRange {
+    kind: FoldingKind;
+    span: Span;
+    range: TextRange;
+}
+
+export interface SelectionRange {
+    span: Span;
+    range: TextRange;
+}
+"#;
 
 /// Initialize panic hook for better error messages in browser console
 #[wasm_bindgen(start)]
@@ -13,47 +290,114 @@ pub fn init() {
 
 /// Compile FratmScript source to JavaScript
 ///
-/// Returns a JSON object with:
-/// - `success`: boolean
-/// - `code`: string (if success)
-/// - `sourceMap`: string (if success and requested)
-/// - `error`: string (if failure)
-/// - `line`: number (if failure)
-/// - `column`: number (if failure)
-#[wasm_bindgen]
-pub fn compile(source: &str, generate_source_map: bool) -> JsValue {
-    let options = CompileOptions {
-        source_map: generate_source_map,
-        filename: Some("input.fratm".to_string()),
-        minify: false,
+/// `options` is a JS object of the `CompileOptions` TypeScript type above,
+/// every field optional. A plain boolean is still accepted as the
+/// `sourceMap` flag.
+///
+/// Returns a `CompileResponse`: `code` (and `sourceMap` if requested) on
+/// success, `error` and where it is on failure, and in both cases the
+/// `options` that were actually applied.
+#[wasm_bindgen(unchecked_return_type = "CompileResponse")]
+pub fn compile(source: &str, #[wasm_bindgen(unchecked_param_type = "CompileOptions | boolean")] options: JsValue) -> JsValue {
+    let response = match parse_options(options) {
+        Ok(options) => compile_response(source, options),
+        Err(message) => CompileResponse::failure(format!("Opzioni sbagliate: {}", message)),
     };
+    serde_wasm_bindgen::to_value(&response).unwrap_or(JsValue::NULL)
+}
 
-    match core_compile(source, options) {
-        Ok(result) => {
-            let response = CompileResponse {
-                success: true,
-                code: Some(result.code),
-                source_map: result.source_map.map(|sm| sm.to_json()),
-                error: None,
-                line: None,
-                column: None,
-                suggestion: None,
-            };
-            serde_wasm_bindgen::to_value(&response).unwrap_or(JsValue::NULL)
-        }
-        Err(e) => {
-            let response = CompileResponse {
-                success: false,
-                code: None,
-                source_map: None,
-                error: Some(format!("{}", e)),
-                line: e.line(),
-                column: e.column(),
-                suggestion: fratm_core::errors::get_suggestion(&e),
-            };
-            serde_wasm_bindgen::to_value(&response).unwrap_or(JsValue::NULL)
+fn parse_options(options: JsValue) -> Result<WasmCompileOptions, String> {
+    if options.is_undefined() || options.is_null() {
+        return Ok(WasmCompileOptions::default());
+    }
+    if let Some(source_map) = options.as_bool() {
+        return Ok(WasmCompileOptions { source_map, ..Default::default() });
+    }
+    serde_wasm_bindgen::from_value(options).map_err(|e| e.to_string())
+}
+
+/// The limits of everything the page is sent, unless `compile` is given
+/// others: the other functions take no options, and check the source size
+/// before anything else
+const PAGE_LIMITS: Limits = Limits {
+    max_source_bytes: Some(1 << 20),
+    max_tokens: Some(1 << 18),
+    max_nodes: Some(1 << 19),
+    max_output_bytes: Some(4 << 20),
+};
+
+/// The `too-large` error if `source` is over the page's limit
+fn too_large(source: &str) -> Option<Diagnostic> {
+    PAGE_LIMITS.check_source(source, Locale::default()).err().map(|error| error.into_diagnostic())
+}
+
+fn compile_response(source: &str, options: WasmCompileOptions) -> CompileResponse {
+    let applied = options.resolved();
+    let mut builder = CompileOptions::builder()
+        .source_map(applied.source_map)
+        .minify(applied.minify)
+        .target(applied.target)
+        .module_format(applied.module_format)
+        .optimize(applied.optimize)
+        .locale(applied.locale)
+        .style(applied.style)
+        .use_strict(applied.use_strict)
+        .comments(applied.comments)
+        .assertions(applied.assertions)
+        .decorators(applied.decorators)
+        .interpolation(applied.interpolation)
+        .limits(applied.limits.unwrap_or(PAGE_LIMITS))
+        .parser_options(ParserOptions { max_depth: applied.max_depth.unwrap_or(DEFAULT_MAX_DEPTH), ..Default::default() });
+    if let Some(banner) = &applied.banner {
+        builder = builder.banner(banner);
+    }
+    if let Some(footer) = &applied.footer {
+        builder = builder.footer(footer);
+    }
+    if let Some(filename) = &applied.filename {
+        builder = builder.filename(filename);
+    }
+    if let Some(output_filename) = &applied.output_filename {
+        builder = builder.output_filename(output_filename);
+    }
+    if let Some(global_name) = &applied.global_name {
+        builder = builder.global_name(global_name);
+    }
+    if let Some(jsx) = &applied.jsx {
+        builder = builder.jsx(jsx.clone());
+    }
+    if let Some(pack) = applied.dialect.clone() {
+        match KeywordTable::try_from(pack) {
+            Ok(dialect) => builder = builder.dialect(dialect),
+            Err(message) => return CompileResponse::failure(format!("Opzioni sbagliate: {}", message)),
         }
     }
+    let core_options = builder.build();
+
+    match core_compile(source, core_options) {
+        Ok(result) => CompileResponse {
+            success: true,
+            code: Some(result.code),
+            source_map: result.source_map.map(|sm| sm.to_json()),
+            error: None,
+            line: None,
+            column: None,
+            suggestion: None,
+            frame: None,
+            options: Some(applied),
+        },
+        Err(e) => CompileResponse {
+            success: false,
+            code: None,
+            source_map: None,
+            error: Some(e.localized(applied.locale)),
+            line: e.line(),
+            column: e.column(),
+            suggestion: fratm_core::errors::get_suggestion(&e),
+            frame: Some(render_code_frame(source, e.diagnostic(), RenderOptions::default())),
+            options: Some(applied),
+        },
+    }
 }
 
 /// Get the compiler version
@@ -62,14 +406,460 @@ pub fn version() -> String {
     fratm_core::version().to_string()
 }
 
-/// Tokenize source code (for syntax highlighting)
+/// Parse source code into an AST (for the playground AST viewer)
+///
+/// Returns a JSON object with:
+/// - `success`: boolean
+/// - `ast`: the serialized `Program`; with errors, broken parts are `Error` nodes
+/// - `errors`: list of diagnostics (if failure), where `range` holds
+///   0-based UTF-16 positions for the editor
+#[wasm_bindgen(unchecked_return_type = "ParseResponse")]
+pub fn parse(source: &str) -> JsValue {
+    let response = parse_response(source);
+    serde_wasm_bindgen::to_value(&response).unwrap_or(JsValue::NULL)
+}
+
+fn parse_response(source: &str) -> ParseResponse {
+    let (program, errors) = match too_large(source) {
+        Some(error) => (fratm_core::parser::Program { statements: Vec::new() }, vec![error]),
+        None => fratm_core::parse_recovering(source),
+    };
+    let index = LineIndex::new(source);
+    let errors: Vec<DiagnosticInfo> = errors.into_iter().map(|e| DiagnosticInfo::new(e, &index)).collect();
+    ParseResponse { success: errors.is_empty(), ast: program, errors }
+}
+
+/// Format source code (for the playground Format button)
+///
+/// `options` is an optional `{ indentWidth, useTabs }` object.
+///
+/// Returns a JSON object with:
+/// - `success`: boolean
+/// - `code`: the formatted source (if success)
+/// - `error`, `line`, `column`: (if failure)
+#[wasm_bindgen(unchecked_return_type = "FormatResponse")]
+pub fn format(source: &str, #[wasm_bindgen(unchecked_param_type = "FormatOptions | undefined")] options: JsValue) -> JsValue {
+    let response = if options.is_undefined() || options.is_null() {
+        format_response(source, WasmFormatOptions::default())
+    } else {
+        match serde_wasm_bindgen::from_value(options) {
+            Ok(options) => format_response(source, options),
+            Err(e) => FormatResponse { success: false, code: None, error: Some(format!("Opzioni sbagliate: {}", e)), line: None, column: None },
+        }
+    };
+    serde_wasm_bindgen::to_value(&response).unwrap_or(JsValue::NULL)
+}
+
+fn format_response(source: &str, options: WasmFormatOptions) -> FormatResponse {
+    if let Some(error) = too_large(source) {
+        return FormatResponse { success: false, code: None, error: Some(error.message), line: None, column: None };
+    }
+    let options = fratm_core::formatter::FormatOptions { indent_width: options.indent_width, use_tabs: options.use_tabs };
+    match fratm_core::formatter::format(source, &options) {
+        Ok(code) => FormatResponse { success: true, code: Some(code), error: None, line: None, column: None },
+        Err(e) => FormatResponse { success: false, code: None, error: Some(format!("{}", e)), line: e.line(), column: e.column() },
+    }
+}
+
+/// Lint source code (for inline warnings in the editor)
+///
+/// Returns a JSON object with:
+/// - `success`: boolean
+/// - `warnings`: list of diagnostics, `code` being the rule (if success)
+/// - `error`, `line`, `column`: (if the source doesn't compile)
+#[wasm_bindgen(unchecked_return_type = "LintResponse")]
+pub fn lint(source: &str) -> JsValue {
+    let response = lint_response(source);
+    serde_wasm_bindgen::to_value(&response).unwrap_or(JsValue::NULL)
+}
+
+fn lint_response(source: &str) -> LintResponse {
+    if let Some(error) = too_large(source) {
+        return LintResponse { success: false, warnings: vec![], error: Some(error.message), line: None, column: None };
+    }
+    match fratm_core::linter::lint(source, Locale::default()) {
+        Ok(warnings) => {
+            let index = LineIndex::new(source);
+            let warnings = warnings.into_iter()
+                .map(|w| DiagnosticInfo::new(w, &index))
+                .collect();
+            LintResponse { success: true, warnings, error: None, line: None, column: None }
+        }
+        Err(e) => LintResponse { success: false, warnings: vec![], error: Some(format!("{}", e)), line: e.line(), column: e.column() },
+    }
+}
+
+/// Autocomplete candidates at the cursor (for the editor)
+///
+/// `offset` is a UTF-16 offset, i.e. a JavaScript string index.
+///
+/// Returns a list of `{ label, kind, insertText, detail }`, where `kind` is
+/// one of `keyword`, `variable`, `constant`, `function`, `parameter`, `class`.
+#[wasm_bindgen(unchecked_return_type = "Completion[]")]
+pub fn complete(source: &str, offset: usize) -> JsValue {
+    if too_large(source).is_some() { return empty_list(); }
+    let offset = LineIndex::new(source).offset_from_utf16(offset);
+    let items = fratm_core::ide::completions(source, offset);
+    serde_wasm_bindgen::to_value(&items).unwrap_or(JsValue::NULL)
+}
+
+/// Outline of the functions, classes and top-level variables (for the
+/// outline panel)
+///
+/// Works on incomplete source: broken statements are left out. Returns a
+/// tree of `{ name, kind, span, range, children }`, where `kind` is one of
+/// `function`, `class`, `method`, `variable`, `constant`.
+#[wasm_bindgen(unchecked_return_type = "DocumentSymbol[]")]
+pub fn document_symbols(source: &str) -> JsValue {
+    if too_large(source).is_some() { return empty_list(); }
+    let (program, _) = fratm_core::parse_recovering(source);
+    let index = LineIndex::new(source);
+    let symbols: Vec<SymbolInfo> = fratm_core::semantic::document_symbols(&program).into_iter()
+        .map(|symbol| SymbolInfo::new(symbol, &index))
+        .collect();
+    serde_wasm_bindgen::to_value(&symbols).unwrap_or(JsValue::NULL)
+}
+
+/// Foldable blocks, brackets and comments (for code folding)
+///
+/// Returns a list of `{ kind, span, range }` in source order, where `kind`
+/// is `region` or `comment`.
+#[wasm_bindgen(unchecked_return_type = "FoldingRange[]")]
+pub fn folding_ranges(source: &str) -> JsValue {
+    if too_large(source).is_some() { return empty_list(); }
+    let index = LineIndex::new(source);
+    let ranges: Vec<FoldingInfo> = fratm_core::ide::folding_ranges(source).into_iter()
+        .map(|folding| FoldingInfo { kind: folding.kind, range: index.range_utf16(folding.span), span: folding.span })
+        .collect();
+    serde_wasm_bindgen::to_value(&ranges).unwrap_or(JsValue::NULL)
+}
+
+/// Ranges around the cursor, innermost first (for expand selection)
+///
+/// `offset` is a UTF-16 offset, i.e. a JavaScript string index.
+/// Returns a list of `{ span, range }` ending with the whole source.
+#[wasm_bindgen(unchecked_return_type = "SelectionRange[]")]
+pub fn selection_ranges(source: &str, offset: usize) -> JsValue {
+    if too_large(source).is_some() { return empty_list(); }
+    let index = LineIndex::new(source);
+    let ranges: Vec<SelectionInfo> = fratm_core::ide::selection_ranges(source, index.offset_from_utf16(offset)).into_iter()
+        .map(|span| SelectionInfo { range: index.range_utf16(span), span })
+        .collect();
+    serde_wasm_bindgen::to_value(&ranges).unwrap_or(JsValue::NULL)
+}
+
+/// What the editor functions return for a source over the page's limit
+fn empty_list() -> JsValue {
+    serde_wasm_bindgen::to_value(&[(); 0]).unwrap_or(JsValue::NULL)
+}
+
+/// Reference info for one keyword (for hover tooltips)
+///
+/// Accepts a single word (`chist`) or a full phrase (`chist è`).
+/// Returns `{ word, phrase, kind, javascript, description, example }`,
+/// or `null` if `word` isn't a keyword.
+#[wasm_bindgen(unchecked_return_type = "KeywordInfo | null")]
+pub fn keyword_info(word: &str) -> JsValue {
+    match fratm_core::lexer::keywords::keyword_info(word) {
+        Some(info) => serde_wasm_bindgen::to_value(info).unwrap_or(JsValue::NULL),
+        None => JsValue::NULL,
+    }
+}
+
+/// Every keyword with its reference info (for the cheat sheet)
+#[wasm_bindgen(unchecked_return_type = "KeywordInfo[]")]
+pub fn all_keywords() -> JsValue {
+    serde_wasm_bindgen::to_value(fratm_core::lexer::keywords::all_keywords()).unwrap_or(JsValue::NULL)
+}
+
+/// One of the [`EXAMPLES`]: `fixtures/NAME.fratm`, and in `NAME.stdout`
+/// what it prints
+struct Example {
+    name: &'static str,
+    title: &'static str,
+    description: &'static str,
+    source: &'static str,
+    output: &'static str,
+}
+
+macro_rules! examples {
+    ($($name:literal, $title:literal, $description:literal;)*) => {
+        &[$(Example {
+            name: $name,
+            title: $title,
+            description: $description,
+            source: include_str!(concat!("../fixtures/", $name, ".fratm")),
+            output: include_str!(concat!("../fixtures/", $name, ".stdout")),
+        }),*]
+    };
+}
+
+/// The programs of the playground's examples, in the order of its menu
+static EXAMPLES: &[Example] = examples! {
+    "hello", "Hello World", "Printing, constants and variables";
+    "fibonacci", "Fibonacci", "A recursive function and a loop";
+    "classe", "Pizzaiolo Class", "A class with a constructor and methods";
+    "async", "Async/Await", "Async functions and aspett";
+    "operatori", "Logical Operators", "e, o, no and their aliases";
+    "array", "Arrays and Objects", "Lists, objects and reading their fields";
+};
+
+#[derive(serde::Serialize)]
+struct ExampleResponse {
+    name: &'static str,
+    title: &'static str,
+    description: &'static str,
+    source: &'static str,
+    output: Vec<&'static str>,
+}
+
+/// The example programs, for the playground's menu and the docs
+///
+/// Each is `{ name, title, description, source, output }`, `output` being
+/// the lines [`run`] prints for it.
+#[wasm_bindgen(unchecked_return_type = "Example[]")]
+pub fn examples() -> JsValue {
+    serde_wasm_bindgen::to_value(&examples_response()).unwrap_or(JsValue::NULL)
+}
+
+fn examples_response() -> Vec<ExampleResponse> {
+    EXAMPLES.iter().map(|example| ExampleResponse {
+        name: example.name,
+        title: example.title,
+        description: example.description,
+        source: example.source,
+        output: example.output.lines().collect(),
+    }).collect()
+}
+
+/// Run a program with the built-in interpreter (for the playground "Run" button)
+///
+/// Nothing is evaluated in the host page: the program runs inside the
+/// WASM module, and whatever it prints is captured.
+///
+/// Returns a JSON object with:
+/// - `success`: boolean
+/// - `output`: the printed lines, also on failure
+/// - `console`: the same lines as `{ level, text }`, `level` being `log`, `warn` or `error`
+/// - `error`, `line`, `column`: compile or runtime error (if failure)
+#[wasm_bindgen(unchecked_return_type = "RunResponse")]
+pub fn run(source: &str) -> JsValue {
+    let response = run_response(source);
+    serde_wasm_bindgen::to_value(&response).unwrap_or(JsValue::NULL)
+}
+
+/// Longest string, in bytes, and array a program run in the page may
+/// build: JavaScript allows more than the page has memory for
+const MAX_STRING_LENGTH: usize = 1 << 24;
+const MAX_ARRAY_LENGTH: usize = 1 << 22;
+
+fn interpreter() -> Interpreter {
+    Interpreter::new().with_max_string_length(MAX_STRING_LENGTH).with_max_array_length(MAX_ARRAY_LENGTH)
+}
+
+fn run_response(source: &str) -> RunResponse {
+    if let Some(error) = too_large(source) {
+        return RunResponse { success: false, output: vec![], console: vec![], error: Some(error.message), line: None, column: None };
+    }
+    let mut interpreter = interpreter();
+    let result = interpreter.eval(source);
+    let console = interpreter.take_output();
+    let output = console.iter().map(|line| line.text.clone()).collect();
+    match result {
+        Ok(_) => RunResponse { success: true, output, console, error: None, line: None, column: None },
+        Err(e) => RunResponse { success: false, output, console, error: Some(e.to_string()), line: e.line(), column: e.column() },
+    }
+}
+
+/// A REPL session for the browser, like `fratm repl`
+///
+/// What one `eval` declares stays for the next. A line that leaves a
+/// statement open (`facc f() {`) comes back `incomplete`, and the next
+/// lines add to it until it is whole; an empty line evaluates it as it is.
+#[wasm_bindgen]
+pub struct Repl {
+    interpreter: Interpreter,
+    /// The lines of the statement still open
+    pending: String,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self { interpreter: interpreter(), pending: String::new() }
+    }
+}
+
+#[wasm_bindgen]
+impl Repl {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Repl {
+        Self::default()
+    }
+
+    /// Evaluates one line of input
+    #[wasm_bindgen(unchecked_return_type = "ReplResponse")]
+    pub fn eval(&mut self, line: &str) -> JsValue {
+        let response = self.eval_response(line);
+        serde_wasm_bindgen::to_value(&response).unwrap_or(JsValue::NULL)
+    }
+
+    /// Forgets every binding and the input still open
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn eval_response(&mut self, line: &str) -> ReplResponse {
+        let mut response = ReplResponse { output: Vec::new(), js: None, value: None, diagnostics: Vec::new(), incomplete: false };
+        let force = line.trim().is_empty();
+        if force && self.pending.is_empty() {
+            return response;
+        }
+        self.pending.push_str(line);
+        self.pending.push('\n');
+        if let Some(error) = too_large(&self.pending) {
+            self.pending.clear();
+            response.diagnostics.push(DiagnosticInfo::new(error, &LineIndex::new("")));
+            return response;
+        }
+        let index = LineIndex::new(&self.pending);
+        let program = match fratm_core::parse_source(&self.pending) {
+            Ok(program) => program,
+            // Errors at the very end only say the input stops too soon
+            Err(errors) if !force && errors.iter().all(|e| e.span.start >= self.pending.trim_end().len()) => {
+                response.incomplete = true;
+                return response;
+            }
+            Err(errors) => {
+                response.diagnostics = errors.into_iter().map(|e| DiagnosticInfo::new(e, &index)).collect();
+                self.pending.clear();
+                return response;
+            }
+        };
+        let source = std::mem::take(&mut self.pending);
+        response.js = core_compile(&source, CompileOptions::builder().limits(PAGE_LIMITS).build()).ok().map(|result| result.code);
+        let result = self.interpreter.run(&program);
+        response.output = self.interpreter.take_output();
+        match result {
+            Ok(fratm_core::interpreter::Value::Undefined) => {}
+            Ok(value) => response.value = Some(fratm_core::interpreter::inspect(&value, 1)),
+            Err(e) => response.diagnostics.push(DiagnosticInfo::new(Diagnostic::error("runtime-error", e.message, e.span), &index)),
+        }
+        response
+    }
+}
+
+/// A source kept parsed from one edit to the next, for the playground
+/// editor
+///
+/// `edit` parses again only the statements around the change, so the AST
+/// viewer and the diagnostics keep up with typing on large files.
+#[wasm_bindgen]
+pub struct Document {
+    parse: Parse,
+}
+
+#[wasm_bindgen]
+impl Document {
+    /// A source or edit past the page's limit leaves an empty AST with a
+    /// `too-large` error
+    #[wasm_bindgen(constructor)]
+    pub fn new(source: &str) -> Document {
+        Document { parse: Parse::with_options(source, CompileOptions::builder().limits(PAGE_LIMITS).build()) }
+    }
+
+    /// Replaces `start..end`, UTF-16 offsets as the editor's, with `text`
+    #[wasm_bindgen(unchecked_return_type = "ParseResponse")]
+    pub fn edit(&mut self, start: usize, end: usize, text: &str) -> JsValue {
+        let response = self.edit_response(start, end, text);
+        serde_wasm_bindgen::to_value(&response).unwrap_or(JsValue::NULL)
+    }
+
+    pub fn source(&self) -> String {
+        self.parse.source().to_string()
+    }
+
+    fn edit_response(&mut self, start: usize, end: usize, text: &str) -> ParseResponse {
+        let index = LineIndex::new(self.parse.source());
+        let span = Span { start: index.offset_from_utf16(start), end: index.offset_from_utf16(end), ..Span::default() };
+        self.parse.edit(&Fix { span, replacement: text.to_string() });
+        let index = LineIndex::new(self.parse.source());
+        let errors: Vec<DiagnosticInfo> = self.parse.errors().iter().map(|e| DiagnosticInfo::new(e.clone(), &index)).collect();
+        ParseResponse { success: errors.is_empty(), ast: self.parse.program().clone(), errors }
+    }
+}
+
+/// Hash of the program's structure, as 16 hex digits (for share links and caches)
+///
+/// Layout and comments don't change it. Returns `undefined` if the source
+/// doesn't parse or is over the page's limit.
 #[wasm_bindgen]
+pub fn fingerprint(source: &str) -> Option<String> {
+    if too_large(source).is_some() { return None; }
+    fratm_core::fingerprint::fingerprint(source).ok().map(|fingerprint| fingerprint.to_string())
+}
+
+/// Tokenize source code (for syntax highlighting)
+#[wasm_bindgen(unchecked_return_type = "Token[]")]
 pub fn tokenize(source: &str) -> JsValue {
+    if too_large(source).is_some() { return empty_list(); }
     let mut lexer = fratm_core::lexer::Lexer::new(source);
     let tokens = lexer.tokenize();
     serde_wasm_bindgen::to_value(&tokens).unwrap_or(JsValue::NULL)
 }
 
+/// Options accepted by the compile function, mirroring `CompileOptions`
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct WasmCompileOptions {
+    source_map: bool,
+    filename: Option<String>,
+    output_filename: Option<String>,
+    minify: bool,
+    target: Target,
+    module_format: ModuleFormat,
+    global_name: Option<String>,
+    optimize: bool,
+    locale: Locale,
+    style: CodegenStyle,
+    banner: Option<String>,
+    footer: Option<String>,
+    use_strict: bool,
+    comments: bool,
+    assertions: Assertions,
+    decorators: Decorators,
+    dialect: Option<DialectPack>,
+    jsx: Option<Jsx>,
+    interpolation: bool,
+    limits: Option<Limits>,
+    max_depth: Option<usize>,
+}
+
+impl WasmCompileOptions {
+    /// Fill in the defaults the playground relies on
+    fn resolved(mut self) -> Self {
+        self.filename.get_or_insert_with(|| "input.fratm".to_string());
+        self.output_filename.get_or_insert_with(|| "output.js".to_string());
+        self.max_depth.get_or_insert(DEFAULT_MAX_DEPTH);
+        self.limits.get_or_insert(PAGE_LIMITS);
+        self
+    }
+}
+
+/// Options accepted by the format function, mirroring `FormatOptions`
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct WasmFormatOptions {
+    indent_width: usize,
+    use_tabs: bool,
+}
+
+impl Default for WasmFormatOptions {
+    fn default() -> Self {
+        let defaults = fratm_core::formatter::FormatOptions::default();
+        Self { indent_width: defaults.indent_width, use_tabs: defaults.use_tabs }
+    }
+}
+
 /// Response structure for compile function
 #[derive(serde::Serialize)]
 struct CompileResponse {
@@ -81,6 +871,148 @@ struct CompileResponse {
     line: Option<usize>,
     column: Option<usize>,
     suggestion: Option<String>,
+    frame: Option<String>,
+    options: Option<WasmCompileOptions>,
+}
+
+/// Response structure for parse function
+#[derive(serde::Serialize)]
+struct ParseResponse {
+    success: bool,
+    ast: fratm_core::parser::Program,
+    errors: Vec<DiagnosticInfo>,
+}
+
+/// A diagnostic, plus its editor range
+#[derive(serde::Serialize)]
+struct DiagnosticInfo {
+    code: String,
+    severity: Severity,
+    message: String,
+    span: Span,
+    range: Range,
+    labels: Vec<LabelInfo>,
+    notes: Vec<String>,
+    suggestion: Option<String>,
+}
+
+/// A diagnostic label, plus its editor range
+#[derive(serde::Serialize)]
+struct LabelInfo {
+    message: String,
+    span: Span,
+    range: Range,
+}
+
+impl DiagnosticInfo {
+    fn new(diagnostic: Diagnostic, index: &LineIndex) -> Self {
+        Self {
+            range: index.range_utf16(diagnostic.span),
+            code: diagnostic.code.into_owned(),
+            severity: diagnostic.severity,
+            message: diagnostic.message,
+            span: diagnostic.span,
+            labels: diagnostic.labels.into_iter()
+                .map(|label| LabelInfo { range: index.range_utf16(label.span), message: label.message, span: label.span })
+                .collect(),
+            notes: diagnostic.notes,
+            suggestion: diagnostic.suggestion,
+        }
+    }
+}
+
+/// An outline entry, plus its editor range
+#[derive(serde::Serialize)]
+struct SymbolInfo {
+    name: String,
+    kind: SymbolKind,
+    span: Span,
+    range: Range,
+    children: Vec<SymbolInfo>,
+}
+
+impl SymbolInfo {
+    fn new(symbol: DocumentSymbol, index: &LineIndex) -> Self {
+        Self {
+            name: symbol.name.to_string(),
+            kind: symbol.kind,
+            span: symbol.span,
+            range: index.range_utf16(symbol.span),
+            children: symbol.children.into_iter().map(|child| SymbolInfo::new(child, index)).collect(),
+        }
+    }
+}
+
+/// A folding range, plus its editor range
+#[derive(serde::Serialize)]
+struct FoldingInfo {
+    kind: FoldingKind,
+    span: Span,
+    range: Range,
+}
+
+/// A selection range, plus its editor range
+#[derive(serde::Serialize)]
+struct SelectionInfo {
+    span: Span,
+    range: Range,
+}
+
+/// Response structure for format function
+#[derive(serde::Serialize)]
+struct FormatResponse {
+    success: bool,
+    code: Option<String>,
+    error: Option<String>,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+/// Response structure for lint function
+#[derive(serde::Serialize)]
+struct LintResponse {
+    success: bool,
+    warnings: Vec<DiagnosticInfo>,
+    error: Option<String>,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+/// Response structure for run function
+#[derive(serde::Serialize)]
+struct RunResponse {
+    success: bool,
+    output: Vec<String>,
+    console: Vec<fratm_core::interpreter::ConsoleLine>,
+    error: Option<String>,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+/// Response structure for `Repl.eval`
+#[derive(serde::Serialize)]
+struct ReplResponse {
+    output: Vec<fratm_core::interpreter::ConsoleLine>,
+    js: Option<String>,
+    value: Option<String>,
+    diagnostics: Vec<DiagnosticInfo>,
+    incomplete: bool,
+}
+
+impl CompileResponse {
+    fn failure(error: String) -> Self {
+        Self {
+            success: false,
+            code: None,
+            source_map: None,
+            error: Some(error),
+            line: None,
+            column: None,
+            suggestion: None,
+            frame: None,
+            options: None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -89,7 +1021,165 @@ mod tests {
 
     #[test]
     fn test_compile() {
-        let result = compile("chist è x = 42", false);
-        assert!(!result.is_null());
+        let result = compile_response("chist è x = 42", WasmCompileOptions::default());
+        assert!(result.success);
+        assert!(result.code.unwrap().contains("const x = 42"));
+
+        let result = compile_response("chist x = 42", WasmCompileOptions::default());
+        assert!(result.frame.unwrap().contains("1 │ chist x = 42"));
+
+        let limits = Limits { max_source_bytes: Some(10), ..Default::default() };
+        let result = compile_response("chist è x = 42", WasmCompileOptions { limits: Some(limits), ..Default::default() });
+        assert!(result.error.unwrap().contains("troppo gruosso"));
+        let huge = "tien x = 1\n".repeat(100_000);
+        assert!(compile_response(&huge, WasmCompileOptions::default()).error.unwrap().contains("troppo gruosso"));
+        assert!(compile_response(&huge, WasmCompileOptions { limits: Some(Limits::default()), ..Default::default() }).success);
+
+        let chain = format!("stamm a dì(1{})", " + 1".repeat(100));
+        assert!(!compile_response(&chain, WasmCompileOptions::default()).success);
+        assert!(compile_response(&chain, WasmCompileOptions { max_depth: Some(200), ..Default::default() }).success);
+    }
+
+    #[test]
+    fn test_parse() {
+        let result = parse_response("chist è x = 42");
+        assert!(result.success);
+        assert_eq!(result.ast.statements.len(), 1);
+
+        let result = parse_response("chist x = 42");
+        assert!(!result.success);
+        assert_eq!(result.errors[0].span.column, 7);
+        assert_eq!(result.errors[0].code, "syntax-error");
+        assert_eq!(result.errors[0].suggestion.is_some(), cfg!(feature = "suggestions"));
+
+        let result = parse_response("tien x = sfòls\nchist x");
+        assert_eq!(result.errors[0].range.start.line, 1);
+        assert_eq!(result.errors[0].range.start.col, 6);
+        assert_eq!(result.ast.statements.len(), 2);
+
+        // Past the page's limit nothing is parsed
+        let result = parse_response(&"tien x = 1\n".repeat(100_000));
+        assert!(result.ast.statements.is_empty());
+        assert_eq!(result.errors[0].code, "too-large");
+    }
+
+    #[test]
+    fn test_format_and_lint() {
+        let result = format_response("tien   x=1+2\nstamm a dì( x )", WasmFormatOptions::default());
+        assert_eq!(result.code.as_deref(), Some("tien x = 1 + 2\nstamm a dì(x)\n"));
+
+        let result = lint_response("tien x = 1");
+        assert!(result.success);
+        assert_eq!(result.warnings[0].code, "unused-variable");
+        assert_eq!(result.warnings[0].severity, Severity::Warning);
+
+        let result = lint_response("chist x = 42");
+        assert!(!result.success);
+        assert_eq!(result.line, Some(1));
+
+        assert_eq!(fingerprint("tien   x=1+2\nstamm a dì( x )"), fingerprint("tien x = 1 + 2 // tre\nstamm a dì(x)"));
+        assert_eq!(fingerprint("chist x = 42"), None);
+
+        let huge = "tien x = 1\n".repeat(100_000);
+        assert!(format_response(&huge, WasmFormatOptions::default()).error.unwrap().contains("troppo gruosso"));
+        assert!(!lint_response(&huge).success);
+        assert_eq!(fingerprint(&huge), None);
+    }
+
+    #[test]
+    fn test_run() {
+        let result = run_response("tien x = 21\nstamm a dì(\"Risultato:\", x * 2)");
+        assert!(result.success);
+        assert_eq!(result.output, vec!["Risultato: 42"]);
+
+        let result = run_response("stamm a dì(1)\nnisciun.x");
+        assert!(!result.success);
+        assert_eq!(result.output, vec!["1"]);
+        assert_eq!(result.line, Some(2));
+
+        // Doubling a string stops well before the page runs out of memory
+        let result = run_response("tien s = \"pizza\"\nmentre che (overo) { s = s + s }");
+        assert!(result.error.unwrap().contains("Invalid string length"));
+        assert!(!run_response(&"tien x = 1\n".repeat(100_000)).success);
+    }
+
+    #[test]
+    fn test_document() {
+        let mut document = Document::new("tien città = 1\nstamm a dì(città)\n");
+        // UTF-16 offsets: `à` is one unit and two bytes
+        let result = document.edit_response(5, 10, "x");
+        assert!(result.errors.is_empty());
+        let result = document.edit_response(22, 27, "x");
+        assert_eq!(document.source(), "tien x = 1\nstamm a dì(x)\n");
+        assert!(result.success && result.ast.statements.len() == 2);
+        assert!(!document.edit_response(0, 4, "chist").success);
+        let result = document.edit_response(0, 0, &"tien y = 1\n".repeat(100_000));
+        assert_eq!(result.errors[0].code, "too-large");
+    }
+
+    #[test]
+    fn test_repl() {
+        let mut repl = Repl::new();
+        assert!(repl.eval_response("facc doppio(n) {").incomplete);
+        let result = repl.eval_response("  piglie n * 2 }");
+        assert!(!result.incomplete && result.diagnostics.is_empty());
+        assert!(result.js.unwrap().contains("function doppio(n)"));
+
+        // Bindings stay from one line to the next
+        repl.eval_response("tien x = 21");
+        let result = repl.eval_response("stamm a dì(doppio(x))\ndoppio(\"pizza\")");
+        assert_eq!(result.output[0].text, "42");
+        assert_eq!(result.value.as_deref(), Some("NaN"));
+
+        let result = repl.eval_response("nisciun.x");
+        assert_eq!(result.diagnostics[0].code, "runtime-error");
+        assert_eq!(repl.eval_response("chist x = 1").diagnostics[0].code, "syntax-error");
+        // An empty line gives up on the open input
+        assert!(repl.eval_response("si (x) {").incomplete);
+        assert_eq!(repl.eval_response("").diagnostics.len(), 1);
+        repl.reset();
+        assert_eq!(repl.eval_response("x").diagnostics[0].code, "runtime-error");
+        // An open statement stops growing at the page's limit
+        assert!(repl.eval_response("facc f() {").incomplete);
+        assert_eq!(repl.eval_response(&"tien y = 1\n".repeat(100_000)).diagnostics[0].code, "too-large");
+    }
+
+    #[test]
+    fn test_examples() {
+        // The expected output is what running them gives
+        for example in examples_response() {
+            let result = run_response(example.source);
+            assert!(result.success, "{}: {:?}", example.name, result.error);
+            assert_eq!(result.output, example.output, "{}", example.name);
+        }
+    }
+
+    #[test]
+    fn test_applied_options() {
+        let options = WasmCompileOptions {
+            source_map: true,
+            filename: Some("main.fratm".to_string()),
+            ..Default::default()
+        };
+        let result = compile_response("chist è x = 42", options);
+        let applied = result.options.unwrap();
+        assert_eq!(applied.filename.as_deref(), Some("main.fratm"));
+        assert_eq!(applied.output_filename.as_deref(), Some("output.js"));
+        if cfg!(feature = "sourcemap") {
+            assert!(result.source_map.unwrap().contains("main.fratm"));
+        }
+
+        let options = WasmCompileOptions { module_format: ModuleFormat::CommonJs, ..Default::default() };
+        let result = compile_response("mann for chist è x = 42", options);
+        assert!(result.code.unwrap().contains("exports.x = x;"));
+
+        let options = WasmCompileOptions { locale: Locale::English, ..Default::default() };
+        let result = compile_response("chist x = 1", options);
+        assert!(result.error.unwrap().contains("must be followed by 'è'"));
+
+        let style = CodegenStyle { quote: fratm_core::Quote::Single, ..Default::default() };
+        let options = WasmCompileOptions { style, ..Default::default() };
+        let result = compile_response("stamm a dì(\"uè\")", options);
+        assert_eq!(result.code.unwrap(), "console.log('uè');\n");
     }
 }
@@ -1,4 +1,4 @@
-import { useState, useEffect, useCallback } from 'react'
+import { useState, useEffect, useCallback, useMemo } from 'react'
 import { Header } from '@/components/Header'
 import { Editor } from '@/components/Editor'
 import { OutputPanel } from '@/components/OutputPanel'
@@ -8,13 +8,16 @@ import { NativeSelect } from '@/components/ui/select'
 import { Badge } from '@/components/ui/badge'
 import { ToastProvider, toastManager } from '@/components/ui/toast'
 import { useCompiler } from '@/hooks/useCompiler'
-import { examples, defaultCode } from '@/lib/examples'
+import { defaultCode } from '@/lib/examples'
+import { examples } from '@/lib/compiler'
 import { SyntaxHelp } from '@/components/SyntaxHelp'
 
 function App() {
   const [code, setCode] = useState(defaultCode)
   const [selectedExample, setSelectedExample] = useState('')
   const compiler = useCompiler()
+  // Only there once the WASM module is
+  const exampleList = useMemo(() => (compiler.isWasmLoaded ? examples() : []), [compiler.isWasmLoaded])
 
   // Load code from URL on mount
   useEffect(() => {
@@ -53,12 +56,12 @@ function App() {
   }, [code])
 
   const handleExampleChange = useCallback((e: React.ChangeEvent<HTMLSelectElement>) => {
-    const name = e.target.value as keyof typeof examples
+    const example = exampleList.find(example => example.name === e.target.value)
     setSelectedExample(e.target.value)
-    if (examples[name]) {
-      setCode(examples[name])
+    if (example) {
+      setCode(example.source)
     }
-  }, [])
+  }, [exampleList])
 
   return (
     <ToastProvider position="bottom-right">
@@ -99,12 +102,9 @@ function App() {
                   className="w-52"
                 >
                   <option value="">-- Choose an example --</option>
-                  <option value="hello">Hello World</option>
-                  <option value="fibonacci">Fibonacci</option>
-                  <option value="classe">Pizzaiolo Class</option>
-                  <option value="async">Async/Await</option>
-                  <option value="operatori">Logical Operators</option>
-                  <option value="array">Arrays and Objects</option>
+                  {exampleList.map(example => (
+                    <option key={example.name} value={example.name} title={example.description}>{example.title}</option>
+                  ))}
                 </NativeSelect>
               </div>
               <SyntaxHelp />
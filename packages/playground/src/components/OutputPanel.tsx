@@ -131,6 +131,11 @@ export function OutputPanel({ jsOutput, consoleLogs, error }: OutputPanelProps)
                 )}
               </div>
             </div>
+            {error.frame && (
+              <pre className="px-4 pb-3 overflow-x-auto font-mono text-muted-foreground text-xs">
+                {error.frame}
+              </pre>
+            )}
             {error.suggestion && (
               <div className="bg-warning/5 px-4 py-3 border-destructive/20 border-t">
                 <div className="flex items-start gap-2 text-sm">
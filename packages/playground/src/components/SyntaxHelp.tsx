@@ -59,6 +59,7 @@ const syntaxReference = [
     { fratm: 'stamm a dì', js: 'console.log', desc: 'Log output' },
     { fratm: 'avvis a dì', js: 'console.warn', desc: 'Warning output' },
     { fratm: 'scrive a dì', js: 'console.error', desc: 'Error output' },
+    { fratm: 'stamm.tabella', js: 'console.table', desc: 'Other console methods' },
   ]},
   { category: 'Error Handling', items: [
     { fratm: 'pruvamm', js: 'try', desc: 'Try block' },
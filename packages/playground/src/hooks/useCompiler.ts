@@ -1,5 +1,5 @@
 import { useState, useEffect, useCallback } from 'react'
-import { loadWasm, compile, executeCode, isCompilerLoaded, type CompilationOutput, type LogEntry } from '@/lib/compiler'
+import { loadWasm, compile, run, executeCode, isCompilerLoaded, type CompilationOutput, type LogEntry } from '@/lib/compiler'
 
 interface CompilerState {
   isLoading: boolean
@@ -13,6 +13,17 @@ interface CompilerState {
   statusText: string
 }
 
+// Runs in the WASM interpreter when available, falling back to evaluating
+// the generated JS in demo mode
+function execute(source: string, code: string): { logs: LogEntry[]; error?: string } {
+  const result = run(source)
+  if (!result) return executeCode(code)
+  return {
+    logs: result.console.map(line => ({ type: line.level, args: [line.text] })),
+    error: result.error,
+  }
+}
+
 export function useCompiler() {
   const [state, setState] = useState<CompilerState>({
     isLoading: true,
@@ -50,7 +61,7 @@ export function useCompiler() {
     const result = compile(source)
 
     if (result.success && result.code) {
-      const { logs, error } = executeCode(result.code)
+      const { logs, error } = execute(source, result.code)
 
       setState(prev => ({
         ...prev,
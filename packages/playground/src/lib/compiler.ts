@@ -1,18 +1,58 @@
-// Types for WASM module
-interface WasmModule {
-  compile: (source: string, sourceMap: boolean) => CompileResult
-  version: () => string
-  default: (input?: { module_or_path?: WebAssembly.Module | URL | string }) => Promise<void>
-}
+// Types for WASM module. They come from the typings wasm-bindgen generates
+// (see `TS_TYPES` in crates/fratm-wasm), so run `pnpm build:wasm` after
+// changing the Rust side.
+import type {
+  Completion,
+  DocumentSymbol,
+  Example,
+  FoldingRange,
+  FormatOptions,
+  FormatResponse,
+  KeywordInfo,
+  LintResponse,
+  ParseResponse,
+  Quote,
+  Repl,
+  RunResponse,
+  SelectionRange,
+} from '../../public/pkg/fratm_wasm'
 
-interface CompileResult {
-  success: boolean
-  code: string
-  error?: string
-  line?: number
-  column?: number
-  suggestion?: string
-}
+export type {
+  Assertions,
+  CodegenStyle,
+  CompileOptions,
+  CompileResponse,
+  Completion,
+  ConsoleLine,
+  Diagnostic,
+  DiagnosticLabel,
+  DialectPack,
+  DocumentSymbol,
+  Example,
+  FoldingKind,
+  FoldingRange,
+  FormatOptions,
+  FormatResponse,
+  KeywordInfo,
+  LintResponse,
+  LintWarning,
+  Locale,
+  ModuleFormat,
+  ParseResponse,
+  Repl,
+  ReplResponse,
+  RunResponse,
+  SelectionRange,
+  Severity,
+  Span,
+  SymbolKind,
+  Target,
+  TextPosition,
+  TextRange,
+  Token,
+} from '../../public/pkg/fratm_wasm'
+
+type WasmModule = typeof import('../../public/pkg/fratm_wasm')
 
 let wasmModule: WasmModule | null = null
 let isWasmLoaded = false
@@ -67,6 +107,8 @@ export interface CompilationOutput {
   line?: number
   column?: number
   suggestion?: string
+  /** Code frame under the error, as the CLI prints it */
+  frame?: string
   compileTime: number
   isDemo: boolean
 }
@@ -76,7 +118,7 @@ export function compile(source: string): CompilationOutput {
 
   if (isWasmLoaded && wasmModule) {
     try {
-      const result = wasmModule.compile(source, false)
+      const result = wasmModule.compile(source, { filename: 'playground.fratm' })
       const compileTime = performance.now() - startTime
 
       if (result.success) {
@@ -93,6 +135,7 @@ export function compile(source: string): CompilationOutput {
           line: result.line,
           column: result.column,
           suggestion: result.suggestion,
+          frame: result.frame,
           compileTime,
           isDemo: false,
         }
@@ -126,11 +169,79 @@ export function compile(source: string): CompilationOutput {
   }
 }
 
+export function parse(source: string): ParseResponse | null {
+  if (!isWasmLoaded || !wasmModule) return null
+  return wasmModule.parse(source)
+}
+
+export function format(source: string, options?: FormatOptions): FormatResponse | null {
+  if (!isWasmLoaded || !wasmModule) return null
+  return wasmModule.format(source, options)
+}
+
+export function lint(source: string): LintResponse | null {
+  if (!isWasmLoaded || !wasmModule) return null
+  return wasmModule.lint(source)
+}
+
+export function keywordInfo(word: string): KeywordInfo | null {
+  if (!isWasmLoaded || !wasmModule) return null
+  return wasmModule.keyword_info(word)
+}
+
+export function allKeywords(): KeywordInfo[] {
+  if (!isWasmLoaded || !wasmModule) return []
+  return wasmModule.all_keywords()
+}
+
+export function examples(): Example[] {
+  if (!isWasmLoaded || !wasmModule) return []
+  return wasmModule.examples()
+}
+
+// `offset` is a string index into `source` (UTF-16, like CodeMirror positions)
+export function complete(source: string, offset: number): Completion[] {
+  if (!isWasmLoaded || !wasmModule) return []
+  return wasmModule.complete(source, offset)
+}
+
+export function documentSymbols(source: string): DocumentSymbol[] {
+  if (!isWasmLoaded || !wasmModule) return []
+  return wasmModule.document_symbols(source)
+}
+
+export function foldingRanges(source: string): FoldingRange[] {
+  if (!isWasmLoaded || !wasmModule) return []
+  return wasmModule.folding_ranges(source)
+}
+
+// `offset` is a string index into `source`, like in `complete`
+export function selectionRanges(source: string, offset: number): SelectionRange[] {
+  if (!isWasmLoaded || !wasmModule) return []
+  return wasmModule.selection_ranges(source, offset)
+}
+
 export interface LogEntry {
   type: 'log' | 'warn' | 'error'
   args: unknown[]
 }
 
+// Runs FratmScript with the interpreter inside the WASM module, so nothing
+// is evaluated in the page. Returns null in demo mode.
+export function run(source: string): RunResponse | null {
+  if (!isWasmLoaded || !wasmModule) return null
+  return wasmModule.run(source)
+}
+
+// A REPL session on the WASM interpreter: `repl.eval(line)` keeps the
+// bindings of earlier lines. Call `free()` when done with it. Returns null
+// in demo mode.
+export function createRepl(): Repl | null {
+  if (!isWasmLoaded || !wasmModule) return null
+  return new wasmModule.Repl()
+}
+
+// Demo mode fallback: evaluates the transpiled JS in the page
 export function executeCode(code: string): { logs: LogEntry[]; error?: string } {
   const logs: LogEntry[] = []
   const sandbox = {
@@ -6,7 +6,7 @@
  */
 
 import * as path from 'path';
-import { workspace, ExtensionContext } from 'vscode';
+import { debug, workspace, DebugAdapterExecutable, ExtensionContext } from 'vscode';
 import {
   LanguageClient,
   LanguageClientOptions,
@@ -58,6 +58,16 @@ export function activate(context: ExtensionContext): void {
 
   // Avvia il client (che a sua volta avvia il server)
   client.start();
+
+  // Il debugger è `fratm dap`: VSCode gli parla direttamente in DAP
+  context.subscriptions.push(
+    debug.registerDebugAdapterDescriptorFactory('fratmscript', {
+      createDebugAdapterDescriptor: () => {
+        const cli = workspace.getConfiguration('fratmscript').get<string>('cliPath', 'fratm');
+        return new DebugAdapterExecutable(cli, ['dap']);
+      }
+    })
+  );
 }
 
 export function deactivate(): Thenable<void> | undefined {